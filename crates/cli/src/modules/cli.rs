@@ -507,6 +507,44 @@ pub enum QueueCommands {
         // Cancel one or multiple message ids
         ids: Vec<String>,
     },
+
+    /// Shows the delivery history of messages
+    Track {
+        /// Filter by Message-ID header
+        #[clap(short, long)]
+        message_id: Option<String>,
+        /// Filter by sender address
+        #[clap(short, long)]
+        sender: Option<String>,
+        /// Filter by recipient
+        #[clap(short, long)]
+        rcpt: Option<String>,
+        /// Filter messages received before a certain datetime
+        #[clap(short, long)]
+        #[arg(value_parser = parse_datetime)]
+        before: Option<DateTime>,
+        /// Filter messages received after a certain datetime
+        #[clap(short, long)]
+        #[arg(value_parser = parse_datetime)]
+        after: Option<DateTime>,
+        // Show the history of one or multiple message ids
+        ids: Vec<String>,
+    },
+
+    /// Shows the configured delivery queues
+    Queues,
+
+    /// Pause delivery on a queue, or on all queues if none is specified
+    Pause {
+        /// Queue name
+        queue: Option<String>,
+    },
+
+    /// Resume delivery on a queue, or on all queues if none is specified
+    Resume {
+        /// Queue name
+        queue: Option<String>,
+    },
 }
 
 #[derive(Subcommand)]
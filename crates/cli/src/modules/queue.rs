@@ -25,6 +25,45 @@ pub struct Message {
     #[serde(default)]
     pub priority: i16,
     pub env_id: Option<String>,
+    pub queue: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackedMessage {
+    pub id: u64,
+    pub message_id: Option<String>,
+    pub return_path: String,
+    pub size: usize,
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub created: DateTime,
+    pub recipients: Vec<TrackedRecipient>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct TrackedRecipient {
+    pub address: String,
+    pub events: Vec<TrackingEvent>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrackingEvent {
+    #[serde(deserialize_with = "deserialize_datetime")]
+    pub timestamp: DateTime,
+    #[serde(rename = "type")]
+    pub typ: String,
+    pub mx: Option<String>,
+    pub remote_ip: Option<String>,
+    pub tls: Option<String>,
+    pub response: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq, Eq)]
+pub struct QueueInfo {
+    pub name: String,
+    pub threads: usize,
+    pub active: bool,
 }
 
 #[derive(Debug, Deserialize, PartialEq, Eq)]
@@ -197,6 +236,12 @@ impl QueueCommands {
                                 Cell::new(env_id),
                             ]));
                         }
+                        if let Some(queue) = &message.queue {
+                            table.add_row(Row::new(vec![
+                                Cell::new("Queue").with_style(Attr::Bold),
+                                Cell::new(queue),
+                            ]));
+                        }
                         if message.priority != 0 {
                             table.add_row(Row::new(vec![
                                 Cell::new("Priority").with_style(Attr::Bold),
@@ -385,11 +430,176 @@ impl QueueCommands {
                 }
                 eprintln!();
             }
+            QueueCommands::Track {
+                message_id,
+                sender,
+                rcpt,
+                before,
+                after,
+                ids,
+            } => {
+                let messages = if !ids.is_empty() {
+                    let mut messages = Vec::with_capacity(ids.len());
+                    for (uid, id) in parse_ids(&ids).into_iter().zip(ids) {
+                        if let Some(message) = client
+                            .try_http_request::<TrackedMessage, String>(
+                                Method::GET,
+                                &format!("/api/queue/tracking/{uid}"),
+                                None,
+                            )
+                            .await
+                        {
+                            messages.push(message);
+                        } else {
+                            eprintln!("No delivery history found for id {id}.");
+                        }
+                    }
+                    messages
+                } else {
+                    let mut query =
+                        form_urlencoded::Serializer::new("/api/queue/tracking".to_string());
+
+                    if let Some(message_id) = &message_id {
+                        query.append_pair("message-id", message_id);
+                    }
+                    if let Some(sender) = &sender {
+                        query.append_pair("from", sender);
+                    }
+                    if let Some(rcpt) = &rcpt {
+                        query.append_pair("to", rcpt);
+                    }
+                    if let Some(before) = before {
+                        query.append_pair("before", &before.to_rfc3339());
+                    }
+                    if let Some(after) = after {
+                        query.append_pair("after", &after.to_rfc3339());
+                    }
+
+                    client
+                        .http_request::<List<TrackedMessage>, String>(
+                            Method::GET,
+                            &query.finish(),
+                            None,
+                        )
+                        .await
+                        .items
+                };
+
+                for message in &messages {
+                    let mut table = Table::new();
+                    table.add_row(Row::new(vec![
+                        Cell::new("ID").with_style(Attr::Bold),
+                        Cell::new(&format!("{:X}", message.id)),
+                    ]));
+                    if let Some(message_id) = &message.message_id {
+                        table.add_row(Row::new(vec![
+                            Cell::new("Message-ID").with_style(Attr::Bold),
+                            Cell::new(message_id),
+                        ]));
+                    }
+                    table.add_row(Row::new(vec![
+                        Cell::new("Sender").with_style(Attr::Bold),
+                        Cell::new(if !message.return_path.is_empty() {
+                            &message.return_path
+                        } else {
+                            "<>"
+                        }),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Created").with_style(Attr::Bold),
+                        Cell::new(&message.created.to_rfc822()),
+                    ]));
+                    table.add_row(Row::new(vec![
+                        Cell::new("Size").with_style(Attr::Bold),
+                        Cell::new(
+                            &SpecificSize::new(message.size as u32, Byte)
+                                .unwrap()
+                                .to_string(),
+                        ),
+                    ]));
+
+                    for rcpt in &message.recipients {
+                        let mut events = Table::new();
+                        events.add_row(Row::new(
+                            ["Date", "Event", "Host", "TLS", "Response"]
+                                .iter()
+                                .map(|p| Cell::new(p).with_style(Attr::Bold))
+                                .collect(),
+                        ));
+                        for event in &rcpt.events {
+                            let host = match (&event.mx, &event.remote_ip) {
+                                (Some(mx), Some(ip)) => format!("{mx} ({ip})"),
+                                (Some(mx), None) => mx.clone(),
+                                _ => String::new(),
+                            };
+                            events.add_row(Row::new(vec![
+                                Cell::new(&event.timestamp.to_rfc822()),
+                                Cell::new(&event.typ),
+                                Cell::new(&host),
+                                Cell::new(event.tls.as_deref().unwrap_or_default()),
+                                Cell::new(event.response.as_deref().unwrap_or_default()),
+                            ]));
+                        }
+                        table.add_row(Row::new(vec![
+                            Cell::new(&rcpt.address).with_style(Attr::Bold),
+                            Cell::from(&events),
+                        ]));
+                    }
+
+                    eprintln!();
+                    table.printstd();
+                    eprintln!();
+                }
+                eprintln!("\n{} message(s) found.", messages.len());
+            }
+            QueueCommands::Queues => {
+                let queues = client
+                    .http_request::<Vec<QueueInfo>, String>(Method::GET, "/api/queue/queues", None)
+                    .await;
+
+                let mut table = Table::new();
+                table.add_row(Row::new(
+                    ["Name", "Threads", "Status"]
+                        .iter()
+                        .map(|p| Cell::new(p).with_style(Attr::Bold))
+                        .collect(),
+                ));
+                for queue in &queues {
+                    table.add_row(Row::new(vec![
+                        Cell::new(&queue.name),
+                        Cell::new(&queue.threads.to_string()),
+                        Cell::new(if queue.active { "Active" } else { "Paused" }),
+                    ]));
+                }
+
+                eprintln!();
+                table.printstd();
+                eprintln!();
+            }
+            QueueCommands::Pause { queue } => {
+                client.set_queue_status(queue, "stop").await;
+                eprintln!("Successfully paused delivery.");
+            }
+            QueueCommands::Resume { queue } => {
+                client.set_queue_status(queue, "start").await;
+                eprintln!("Successfully resumed delivery.");
+            }
         }
     }
 }
 
 impl Client {
+    async fn set_queue_status(&self, queue: Option<String>, action: &str) {
+        let url = if let Some(queue) = queue {
+            format!("/api/queue/queues/{queue}/{action}")
+        } else {
+            format!("/api/queue/status/{action}")
+        };
+
+        self.http_request::<bool, String>(Method::PATCH, &url, None)
+            .await;
+    }
+
     async fn query_messages(
         &self,
         from: &Option<String>,
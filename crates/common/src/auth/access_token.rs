@@ -170,6 +170,16 @@ impl Server {
                     {
                         collections.insert(Collection::Email);
                     }
+                    if collection == Collection::AddressBook
+                        && (acl.contains(Acl::ReadItems) || acl.contains(Acl::Administer))
+                    {
+                        collections.insert(Collection::ContactCard);
+                    }
+                    if collection == Collection::Calendar
+                        && (acl.contains(Acl::ReadItems) || acl.contains(Acl::Administer))
+                    {
+                        collections.insert(Collection::CalendarEvent);
+                    }
 
                     if !collections.is_empty() {
                         access_token
@@ -556,6 +566,16 @@ impl AccessToken {
                 }
                 jmap_proto::method::get::RequestArguments::Quota => Permission::JmapQuotaGet,
                 jmap_proto::method::get::RequestArguments::Blob(_) => Permission::JmapBlobGet,
+                jmap_proto::method::get::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookGet
+                }
+                jmap_proto::method::get::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardGet
+                }
+                jmap_proto::method::get::RequestArguments::Calendar => Permission::JmapCalendarGet,
+                jmap_proto::method::get::RequestArguments::CalendarEvent => {
+                    Permission::JmapCalendarEventGet
+                }
             },
             RequestMethod::Set(m) => match &m.arguments {
                 jmap_proto::method::set::RequestArguments::Email => Permission::JmapEmailSet,
@@ -573,6 +593,16 @@ impl AccessToken {
                 jmap_proto::method::set::RequestArguments::VacationResponse => {
                     Permission::JmapVacationResponseSet
                 }
+                jmap_proto::method::set::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookSet
+                }
+                jmap_proto::method::set::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardSet
+                }
+                jmap_proto::method::set::RequestArguments::Calendar => Permission::JmapCalendarSet,
+                jmap_proto::method::set::RequestArguments::CalendarEvent => {
+                    Permission::JmapCalendarEventSet
+                }
             },
             RequestMethod::Changes(m) => match m.arguments {
                 jmap_proto::method::changes::RequestArguments::Email => {
@@ -593,6 +623,18 @@ impl AccessToken {
                 jmap_proto::method::changes::RequestArguments::Quota => {
                     Permission::JmapQuotaChanges
                 }
+                jmap_proto::method::changes::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookChanges
+                }
+                jmap_proto::method::changes::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardChanges
+                }
+                jmap_proto::method::changes::RequestArguments::Calendar => {
+                    Permission::JmapCalendarChanges
+                }
+                jmap_proto::method::changes::RequestArguments::CalendarEvent => {
+                    Permission::JmapCalendarEventChanges
+                }
             },
             RequestMethod::Copy(m) => match m.arguments {
                 jmap_proto::method::copy::RequestArguments::Email => Permission::JmapEmailCopy,
@@ -619,6 +661,18 @@ impl AccessToken {
                 jmap_proto::method::query::RequestArguments::Quota => {
                     Permission::JmapQuotaQueryChanges
                 }
+                jmap_proto::method::query::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookQueryChanges
+                }
+                jmap_proto::method::query::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardQueryChanges
+                }
+                jmap_proto::method::query::RequestArguments::Calendar => {
+                    Permission::JmapCalendarQueryChanges
+                }
+                jmap_proto::method::query::RequestArguments::CalendarEvent => {
+                    Permission::JmapCalendarEventQueryChanges
+                }
             },
             RequestMethod::Query(m) => match m.arguments {
                 jmap_proto::method::query::RequestArguments::Email(_) => Permission::JmapEmailQuery,
@@ -635,6 +689,18 @@ impl AccessToken {
                     Permission::JmapPrincipalQuery
                 }
                 jmap_proto::method::query::RequestArguments::Quota => Permission::JmapQuotaQuery,
+                jmap_proto::method::query::RequestArguments::AddressBook => {
+                    Permission::JmapAddressBookQuery
+                }
+                jmap_proto::method::query::RequestArguments::ContactCard => {
+                    Permission::JmapContactCardQuery
+                }
+                jmap_proto::method::query::RequestArguments::Calendar => {
+                    Permission::JmapCalendarQuery
+                }
+                jmap_proto::method::query::RequestArguments::CalendarEvent => {
+                    Permission::JmapCalendarEventQuery
+                }
             },
             RequestMethod::SearchSnippet(_) => Permission::JmapSearchSnippet,
             RequestMethod::ValidateScript(_) => Permission::JmapSieveScriptValidate,
@@ -6,6 +6,7 @@
 
 use std::time::Duration;
 
+use ahash::AHashSet;
 use utils::config::{Config, Rate};
 
 #[derive(Default, Clone)]
@@ -20,10 +21,33 @@ pub struct ImapConfig {
 
     pub rate_requests: Option<Rate>,
     pub rate_concurrent: Option<u64>,
+
+    pub metadata_max_size: usize,
+    pub metadata_max_entries: usize,
+    pub metadata_quota: u64,
+    pub metadata_server: Vec<(String, String)>,
+
+    pub compress_listeners: AHashSet<String>,
 }
 
 impl ImapConfig {
     pub fn parse(config: &mut Config) -> Self {
+        // COMPRESS=DEFLATE can be enabled or disabled on each listener
+        let compress_default = config
+            .property_or_default("imap.compress.enable", "true")
+            .unwrap_or(true);
+        let compress_listeners = config
+            .sub_keys("server.listener", ".protocol")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter(|id| {
+                config
+                    .property::<bool>(("server.listener", id.as_str(), "imap.compress"))
+                    .unwrap_or(compress_default)
+            })
+            .collect();
+
         ImapConfig {
             max_request_size: config
                 .property_or_default("imap.request.max-size", "52428800")
@@ -49,6 +73,24 @@ impl ImapConfig {
             allow_plain_auth: config
                 .property_or_default("imap.auth.allow-plain-text", "false")
                 .unwrap_or(false),
+            metadata_max_size: config
+                .property_or_default("imap.metadata.max-size", "1024")
+                .unwrap_or(1024),
+            metadata_max_entries: config
+                .property_or_default("imap.metadata.max-entries", "100")
+                .unwrap_or(100),
+            metadata_quota: config
+                .property_or_default("imap.metadata.quota", "1048576")
+                .unwrap_or(1048576),
+            metadata_server: ["comment", "admin"]
+                .into_iter()
+                .filter_map(|entry| {
+                    config
+                        .value(("imap.metadata.server", entry))
+                        .map(|value| (format!("/shared/{entry}"), value.to_string()))
+                })
+                .collect(),
+            compress_listeners,
         }
     }
 }
@@ -65,6 +65,7 @@ impl Data {
             queue_id_gen: id_generator.clone(),
             span_id_gen: id_generator,
             queue_status: true.into(),
+            queue_paused: Default::default(),
             webadmin: config
                 .value("webadmin.path")
                 .map(|path| WebAdminManager::new(path.into()))
@@ -72,6 +73,7 @@ impl Data {
             config_version: 0.into(),
             logos: Default::default(),
             smtp_connectors: TlsConnectors::default(),
+            smtp_connections: Default::default(),
             asn_geo_data: Default::default(),
         }
     }
@@ -218,10 +220,12 @@ impl Default for Data {
             queue_id_gen: Default::default(),
             span_id_gen: Default::default(),
             queue_status: true.into(),
+            queue_paused: Default::default(),
             webadmin: Default::default(),
             config_version: Default::default(),
             logos: Default::default(),
             smtp_connectors: Default::default(),
+            smtp_connections: Default::default(),
             asn_geo_data: Default::default(),
         }
     }
@@ -7,9 +7,9 @@
 use ahash::AHashSet;
 use jmap_proto::{
     request::capability::{
-        BlobCapabilities, Capabilities, Capability, CoreCapabilities, EmptyCapabilities,
-        MailCapabilities, SieveAccountCapabilities, SieveSessionCapabilities,
-        SubmissionCapabilities,
+        BlobCapabilities, CalendarsCapabilities, Capabilities, Capability, ContactsCapabilities,
+        CoreCapabilities, EmptyCapabilities, MailCapabilities, SieveAccountCapabilities,
+        SieveSessionCapabilities, SubmissionCapabilities,
     },
     types::type_state::DataType,
 };
@@ -176,5 +176,31 @@ impl JmapConfig {
             Capability::Quota,
             Capabilities::Empty(EmptyCapabilities::default()),
         );
+
+        // Add Contacts capabilities
+        self.capabilities.session.append(
+            Capability::Contacts,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Contacts,
+            Capabilities::Contacts(ContactsCapabilities {
+                max_address_books_per_card: None,
+                may_create_address_book: true,
+            }),
+        );
+
+        // Add Calendars capabilities
+        self.capabilities.session.append(
+            Capability::Calendars,
+            Capabilities::Empty(EmptyCapabilities::default()),
+        );
+        self.capabilities.account.append(
+            Capability::Calendars,
+            Capabilities::Calendars(CalendarsCapabilities {
+                max_calendars_per_event: None,
+                may_create_calendar: true,
+            }),
+        );
     }
 }
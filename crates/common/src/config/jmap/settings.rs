@@ -71,6 +71,14 @@ pub struct JmapConfig {
     pub encrypt: bool,
     pub encrypt_append: bool,
 
+    pub itip_auto_reply: bool,
+
+    pub list_confirm_expiry: u64,
+    pub list_hold_expiry: u64,
+    pub list_bounce_max: i64,
+    pub list_bounce_period: u64,
+    pub list_unsubscribe_url: Option<String>,
+
     pub capabilities: BaseCapabilities,
     pub account_purge_frequency: SimpleCron,
 }
@@ -323,6 +331,27 @@ impl JmapConfig {
             encrypt_append: config
                 .property_or_default("storage.encryption.append", "false")
                 .unwrap_or(false),
+            itip_auto_reply: config
+                .property_or_default("calendar.itip.auto-reply", "true")
+                .unwrap_or(true),
+            list_confirm_expiry: config
+                .property_or_default::<Duration>("mailing-list.confirm.expiry", "3d")
+                .unwrap_or_else(|| Duration::from_secs(3 * 86400))
+                .as_secs(),
+            list_hold_expiry: config
+                .property_or_default::<Duration>("mailing-list.moderation.expiry", "7d")
+                .unwrap_or_else(|| Duration::from_secs(7 * 86400))
+                .as_secs(),
+            list_bounce_max: config
+                .property_or_default("mailing-list.bounce.max-count", "5")
+                .unwrap_or(5),
+            list_bounce_period: config
+                .property_or_default::<Duration>("mailing-list.bounce.period", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .as_secs(),
+            list_unsubscribe_url: config
+                .value("mailing-list.unsubscribe-url")
+                .map(|url| url.trim_end_matches('/').to_string()),
             http_use_forwarded: config
                 .property("server.http.use-x-forwarded")
                 .unwrap_or(false),
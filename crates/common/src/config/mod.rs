@@ -20,8 +20,8 @@ use telemetry::Metrics;
 use utils::config::{utils::AsKey, Config};
 
 use crate::{
-    auth::oauth::config::OAuthConfig, expr::*, listener::tls::AcmeProviders,
-    manager::config::ConfigManager, Core, Network, Security,
+    auth::oauth::config::OAuthConfig, dns_sync::DnsSyncProviders, expr::*,
+    listener::tls::AcmeProviders, manager::config::ConfigManager, Core, Network, Security,
 };
 
 use self::{
@@ -184,6 +184,7 @@ impl Core {
             imap: ImapConfig::parse(config),
             oauth: OAuthConfig::parse(config),
             acme: AcmeProviders::parse(config),
+            dns_sync: DnsSyncProviders::parse(config),
             metrics: Metrics::parse(config),
             spam: SpamFilterConfig::parse(config).await,
             storage: Storage {
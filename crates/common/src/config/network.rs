@@ -42,6 +42,8 @@ pub struct ClusterRoles {
     pub purge_stores: bool,
     pub purge_accounts: bool,
     pub renew_acme: bool,
+    pub rotate_dkim: bool,
+    pub sync_dns: bool,
     pub calculate_metrics: bool,
     pub push_metrics: bool,
 }
@@ -107,6 +109,8 @@ impl Default for Network {
                 purge_stores: true,
                 purge_accounts: true,
                 renew_acme: true,
+                rotate_dkim: true,
+                sync_dns: true,
                 calculate_metrics: true,
                 push_metrics: true,
             },
@@ -219,6 +223,8 @@ impl Network {
                 "cluster.roles.purge.accounts",
             ),
             (&mut network.roles.renew_acme, "cluster.roles.acme.renew"),
+            (&mut network.roles.rotate_dkim, "cluster.roles.dkim.rotate"),
+            (&mut network.roles.sync_dns, "cluster.roles.dns.sync"),
             (
                 &mut network.roles.calculate_metrics,
                 "cluster.roles.metrics.calculate",
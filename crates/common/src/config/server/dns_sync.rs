@@ -0,0 +1,131 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use ahash::AHashMap;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dns_update::providers::rfc2136::DnsAddress;
+use hickory_client::{
+    client::Signer,
+    proto::rr::dnssec::{rdata::tsig::TsigAlgorithm, tsig::TSigner},
+    rr::Name,
+};
+use utils::config::{cron::SimpleCron, Config};
+
+use crate::dns_sync::{DnsSyncProvider, DnsSyncProviders};
+
+impl DnsSyncProviders {
+    pub fn parse(config: &mut Config) -> Self {
+        let mut providers = AHashMap::new();
+
+        for sync_id in config
+            .sub_keys("dns-sync", ".provider")
+            .map(|s| s.to_string())
+            .collect::<Vec<_>>()
+        {
+            if let Some(provider) = parse_provider(config, &sync_id) {
+                providers.insert(sync_id, Arc::new(provider));
+            }
+        }
+
+        DnsSyncProviders {
+            schedule: if !providers.is_empty() {
+                config.property_or_default::<SimpleCron>("dns-sync.schedule", "30 * *")
+            } else {
+                None
+            },
+            providers,
+        }
+    }
+}
+
+fn parse_provider(config: &mut Config, sync_id: &str) -> Option<DnsSyncProvider> {
+    if config.value_require(("dns-sync", sync_id, "provider"))? != "rfc2136-tsig" {
+        config.new_parse_error(("dns-sync", sync_id, "provider"), "Unsupported provider");
+        return None;
+    }
+
+    // Only algorithms backed by ring can be used for signing
+    let algorithm = match config.value_require(("dns-sync", sync_id, "tsig-algorithm"))? {
+        "hmac-sha256" => TsigAlgorithm::HmacSha256,
+        "hmac-sha384" => TsigAlgorithm::HmacSha384,
+        "hmac-sha512" => TsigAlgorithm::HmacSha512,
+        _ => {
+            config.new_parse_error(
+                ("dns-sync", sync_id, "tsig-algorithm"),
+                "Unsupported algorithm",
+            );
+            return None;
+        }
+    };
+    let key = STANDARD
+        .decode(
+            config
+                .value_require(("dns-sync", sync_id, "secret"))?
+                .trim(),
+        )
+        .map_err(|_| {
+            config.new_parse_error(
+                ("dns-sync", sync_id, "secret"),
+                "Failed to base64 decode secret",
+            )
+        })
+        .ok()?;
+    let signer = Name::from_ascii(config.value_require(("dns-sync", sync_id, "key"))?.trim())
+        .map_err(|err| err.to_string())
+        .and_then(|key_name| {
+            TSigner::new(key, algorithm, key_name, 300).map_err(|err| err.to_string())
+        })
+        .map_err(|err| {
+            config.new_build_error(
+                ("dns-sync", sync_id, "key"),
+                format!("Failed to create TSIG signer: {err}"),
+            )
+        })
+        .ok()?;
+
+    let host = config.property_require::<IpAddr>(("dns-sync", sync_id, "host"))?;
+    let port = config
+        .property_or_default::<u16>(("dns-sync", sync_id, "port"), "53")
+        .unwrap_or(53);
+    let addr = if config.value(("dns-sync", sync_id, "protocol")) == Some("tcp") {
+        DnsAddress::Tcp(SocketAddr::new(host, port))
+    } else {
+        DnsAddress::Udp(SocketAddr::new(host, port))
+    };
+
+    // Domains whose records are published through this server
+    let domains = config
+        .values(("dns-sync", sync_id, "domains"))
+        .map(|(_, s)| s.trim().trim_end_matches('.').to_lowercase())
+        .collect::<Vec<_>>();
+    if domains.is_empty() {
+        config.new_parse_error(("dns-sync", sync_id, "domains"), "Missing property");
+        return None;
+    }
+
+    Some(DnsSyncProvider {
+        id: sync_id.to_string(),
+        addr,
+        signer: Arc::new(Signer::from(signer)),
+        origin: config
+            .value(("dns-sync", sync_id, "origin"))
+            .map(|s| s.trim().trim_end_matches('.').to_lowercase()),
+        domains,
+        ttl: config
+            .property_or_default(("dns-sync", sync_id, "ttl"), "1h")
+            .unwrap_or_else(|| Duration::from_secs(3600))
+            .as_secs() as u32,
+        timeout: config
+            .property_or_default(("dns-sync", sync_id, "timeout"), "10s")
+            .unwrap_or_else(|| Duration::from_secs(10)),
+    })
+}
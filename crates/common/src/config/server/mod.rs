@@ -13,6 +13,7 @@ use utils::{config::ipmask::IpAddrMask, snowflake::SnowflakeIdGenerator};
 
 use crate::listener::TcpAcceptor;
 
+pub mod dns_sync;
 pub mod listener;
 pub mod tls;
 
@@ -14,6 +14,7 @@ use mail_auth::{
 use mail_parser::decoders::base64::base64_decode;
 use utils::config::{
     Config,
+    cron::SimpleCron,
     utils::{AsKey, ParseValue},
 };
 
@@ -31,6 +32,7 @@ pub struct MailAuthConfig {
     pub spf: SpfAuthConfig,
     pub dmarc: DmarcAuthConfig,
     pub iprev: IpRevAuthConfig,
+    pub srs: SrsConfig,
     pub signatures: AHashMap<String, Arc<ArcSwap<LazySignature>>>,
 }
 
@@ -52,6 +54,17 @@ pub struct DkimAuthConfig {
     pub verify: IfBlock,
     pub sign: IfBlock,
     pub strict: bool,
+    pub rotation: DkimRotationConfig,
+}
+
+#[derive(Clone)]
+pub struct DkimRotationConfig {
+    pub schedule: SimpleCron,
+    // Seconds before a rotation is due when the next key is generated
+    pub lead_time: u64,
+    // Seconds the previous key stays published after a rotation
+    pub grace_period: u64,
+    pub notify: Vec<String>,
 }
 
 #[derive(Clone)]
@@ -76,6 +89,15 @@ pub struct IpRevAuthConfig {
     pub verify: IfBlock,
 }
 
+#[derive(Clone, Default)]
+pub struct SrsConfig {
+    pub enable: bool,
+    pub domain: Option<String>,
+    // The first secret signs new addresses, all of them are accepted on reversal
+    pub secrets: Vec<String>,
+    pub max_age: u64,
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 pub enum VerifyStrategy {
     #[default]
@@ -114,6 +136,12 @@ impl Default for MailAuthConfig {
                     "false",
                 ),
                 strict: true,
+                rotation: DkimRotationConfig {
+                    schedule: SimpleCron::Hour { minute: 0 },
+                    lead_time: 7 * 86400,
+                    grace_period: 7 * 86400,
+                    notify: vec![],
+                },
             },
             arc: ArcAuthConfig {
                 verify: IfBlock::new::<VerifyStrategy>("auth.arc.verify", [], "relaxed"),
@@ -161,6 +189,7 @@ impl Default for MailAuthConfig {
                     "relaxed",
                 ),
             },
+            srs: SrsConfig::default(),
             signatures: Default::default(),
         }
     }
@@ -202,6 +231,51 @@ impl MailAuthConfig {
             .property_or_default("auth.dkim.strict", "true")
             .unwrap_or(true);
 
+        // Parse DKIM key rotation
+        mail_auth.dkim.rotation = DkimRotationConfig {
+            schedule: config
+                .property_or_default::<SimpleCron>("auth.dkim.rotation.schedule", "0 * *")
+                .unwrap_or(SimpleCron::Hour { minute: 0 }),
+            lead_time: config
+                .property_or_default::<Duration>("auth.dkim.rotation.lead-time", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+            grace_period: config
+                .property_or_default::<Duration>("auth.dkim.rotation.grace-period", "7d")
+                .unwrap_or(Duration::from_secs(7 * 86400))
+                .as_secs(),
+            notify: config
+                .values("auth.dkim.rotation.notify")
+                .map(|(_, addr)| addr.trim().to_lowercase())
+                .filter(|addr| addr.contains('@'))
+                .collect(),
+        };
+
+        // Parse SRS
+        let secrets = config
+            .values("auth.srs.secret")
+            .map(|(_, secret)| secret.to_string())
+            .filter(|secret| !secret.is_empty())
+            .collect::<Vec<_>>();
+        mail_auth.srs = SrsConfig {
+            enable: config
+                .property_or_default("auth.srs.enable", "false")
+                .unwrap_or(false),
+            domain: config.value("auth.srs.domain").map(|v| v.to_lowercase()),
+            secrets,
+            max_age: config
+                .property_or_default::<Duration>("auth.srs.max-age", "21d")
+                .map_or(21, |d| d.as_secs() / 86400)
+                .clamp(1, 1023),
+        };
+        if mail_auth.srs.enable && mail_auth.srs.secrets.is_empty() {
+            config.new_build_error(
+                "auth.srs.secret",
+                "At least one secret is required to enable SRS.",
+            );
+            mail_auth.srs.enable = false;
+        }
+
         // Parse signatures
         let mut signatures: AHashMap<&str, Config> = AHashMap::new();
         let mut current_id = None;
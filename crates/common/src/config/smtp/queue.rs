@@ -4,9 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+use std::time::Duration;
+
 use ahash::AHashMap;
 use mail_auth::IpLookupStrategy;
 use mail_send::Credentials;
+use regex::Regex;
+use smtp_proto::Response;
 use throttle::parse_queue_rate_limiter_key;
 use utils::config::{utils::ParseValue, Config};
 
@@ -19,6 +23,8 @@ use self::throttle::parse_queue_rate_limiter;
 
 use super::*;
 
+pub const DEFAULT_QUEUE_NAME: &str = "default";
+
 #[derive(Clone)]
 pub struct QueueConfig {
     // Schedule
@@ -26,6 +32,10 @@ pub struct QueueConfig {
     pub notify: IfBlock,
     pub expire: IfBlock,
 
+    // Named queues
+    pub select: IfBlock,
+    pub named: AHashMap<String, NamedQueue>,
+
     // Outbound
     pub hostname: IfBlock,
     pub next_hop: IfBlock,
@@ -34,6 +44,7 @@ pub struct QueueConfig {
     pub ip_strategy: IfBlock,
     pub source_ip: QueueOutboundSourceIp,
     pub tls: QueueOutboundTls,
+    pub connection: QueueOutboundConnection,
     pub dsn: Dsn,
 
     // Timeouts
@@ -47,6 +58,48 @@ pub struct QueueConfig {
 
     // Relay hosts
     pub relay_hosts: AHashMap<String, RelayHost>,
+
+    // Outbound proxies
+    pub proxy: IfBlock,
+    pub proxies: AHashMap<String, OutboundProxy>,
+
+    // Adaptive throttling
+    pub adaptive: AdaptiveThrottle,
+
+    // Delivery tracking
+    pub tracking: Option<Duration>,
+}
+
+#[derive(Clone)]
+pub struct AdaptiveThrottle {
+    pub enable: bool,
+    pub scope: AdaptiveScope,
+    pub status_codes: Vec<[u8; 3]>,
+    pub signatures: Vec<(String, Regex)>,
+    pub min_concurrency: usize,
+    pub max_concurrency: usize,
+    pub min_rate: u64,
+    pub max_rate: u64,
+    pub min_backoff: u64,
+    pub max_backoff: u64,
+    pub recovery: u64,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum AdaptiveScope {
+    #[default]
+    Domain,
+    Mx,
+}
+
+#[derive(Clone)]
+pub struct NamedQueue {
+    pub retry: Option<IfBlock>,
+    pub notify: Option<IfBlock>,
+    pub expire: Option<IfBlock>,
+    pub source_ipv4: Option<IfBlock>,
+    pub source_ipv6: Option<IfBlock>,
+    pub threads: usize,
 }
 
 #[derive(Clone)]
@@ -70,6 +123,14 @@ pub struct QueueOutboundTls {
     pub invalid_certs: IfBlock,
 }
 
+#[derive(Clone)]
+pub struct QueueOutboundConnection {
+    pub reuse: IfBlock,
+    pub idle_timeout: IfBlock,
+    pub max_messages: IfBlock,
+    pub max_per_host: IfBlock,
+}
+
 #[derive(Clone)]
 pub struct QueueOutboundTimeout {
     pub connect: IfBlock,
@@ -115,7 +176,22 @@ pub struct RelayHost {
     pub tls_allow_invalid_certs: bool,
 }
 
-#[derive(Debug, Clone, Copy, Default)]
+#[derive(Clone)]
+pub struct OutboundProxy {
+    pub id: String,
+    pub protocol: ProxyProtocol,
+    pub address: String,
+    pub port: u16,
+    pub auth: Option<Credentials<String>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ProxyProtocol {
+    Socks5,
+    Http,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
 pub enum RequireOptional {
     #[default]
     Optional,
@@ -133,6 +209,8 @@ impl Default for QueueConfig {
             ),
             notify: IfBlock::new::<()>("queue.schedule.notify", [], "[1d, 3d]"),
             expire: IfBlock::new::<()>("queue.schedule.expire", [], "5d"),
+            select: IfBlock::new::<()>("queue.select", [], "'default'"),
+            named: Default::default(),
             hostname: IfBlock::new::<()>("queue.outbound.hostname", [], "config_get('server.hostname')"),
             next_hop: IfBlock::new::<()>(
                 "queue.outbound.next-hop",
@@ -177,6 +255,24 @@ impl Default for QueueConfig {
                     "false",
                 ),
             },
+            connection: QueueOutboundConnection {
+                reuse: IfBlock::new::<()>("queue.outbound.connection.reuse", [], "false"),
+                idle_timeout: IfBlock::new::<()>(
+                    "queue.outbound.connection.idle-timeout",
+                    [],
+                    "30s",
+                ),
+                max_messages: IfBlock::new::<()>(
+                    "queue.outbound.connection.max-messages",
+                    [],
+                    "100",
+                ),
+                max_per_host: IfBlock::new::<()>(
+                    "queue.outbound.connection.max-per-host",
+                    [],
+                    "0",
+                ),
+            },
             dsn: Dsn {
                 name: IfBlock::new::<()>("report.dsn.from-name", [], "'Mail Delivery Subsystem'"),
                 address: IfBlock::new::<()>(
@@ -205,6 +301,39 @@ impl Default for QueueConfig {
             outbound_limiters: QueueRateLimiters::default(),
             quota: QueueQuotas::default(),
             relay_hosts: Default::default(),
+            proxy: IfBlock::new::<()>("queue.outbound.proxy", [], "false"),
+            proxies: Default::default(),
+            adaptive: AdaptiveThrottle::default(),
+            tracking: None,
+        }
+    }
+}
+
+impl Default for AdaptiveThrottle {
+    fn default() -> Self {
+        Self {
+            enable: false,
+            scope: AdaptiveScope::Domain,
+            status_codes: vec![[4, 7, 28]],
+            signatures: [
+                ("rate-limit", r"(?i)rate[ -]?limit"),
+                (
+                    "too-many",
+                    r"(?i)too many (connections|messages|recipients)",
+                ),
+                ("deferred", r"\[TS0[1-4]\]"),
+                ("throttled", r"(?i)\bthrottl"),
+            ]
+            .into_iter()
+            .map(|(id, pattern)| (id.to_string(), Regex::new(pattern).unwrap()))
+            .collect(),
+            min_concurrency: 1,
+            max_concurrency: 10,
+            min_rate: 1,
+            max_rate: 120,
+            min_backoff: 60,
+            max_backoff: 3600,
+            recovery: 300,
         }
     }
 }
@@ -216,6 +345,7 @@ impl QueueConfig {
         let sender_vars = TokenMap::default().with_variables(SMTP_QUEUE_SENDER_VARS);
         let mx_vars = TokenMap::default().with_variables(SMTP_QUEUE_MX_VARS);
         let host_vars = TokenMap::default().with_variables(SMTP_QUEUE_HOST_VARS);
+        let select_vars = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
         let ip_strategy_vars = sender_vars.clone().with_constants::<IpLookupStrategy>();
         let dane_vars = mx_vars.clone().with_constants::<RequireOptional>();
         let mta_sts_vars = rcpt_vars.clone().with_constants::<RequireOptional>();
@@ -224,6 +354,7 @@ impl QueueConfig {
             (&mut queue.retry, "queue.schedule.retry", &host_vars),
             (&mut queue.notify, "queue.schedule.notify", &rcpt_vars),
             (&mut queue.expire, "queue.schedule.expire", &rcpt_vars),
+            (&mut queue.select, "queue.select", &select_vars),
             (&mut queue.hostname, "queue.outbound.hostname", &sender_vars),
             (&mut queue.max_mx, "queue.outbound.limits.mx", &rcpt_vars),
             (
@@ -247,6 +378,7 @@ impl QueueConfig {
                 &mx_vars,
             ),
             (&mut queue.next_hop, "queue.outbound.next-hop", &rcpt_vars),
+            (&mut queue.proxy, "queue.outbound.proxy", &rcpt_vars),
             (&mut queue.tls.dane, "queue.outbound.tls.dane", &dane_vars),
             (
                 &mut queue.tls.mta_sts,
@@ -263,6 +395,26 @@ impl QueueConfig {
                 "queue.outbound.tls.allow-invalid-certs",
                 &mx_vars,
             ),
+            (
+                &mut queue.connection.reuse,
+                "queue.outbound.connection.reuse",
+                &mx_vars,
+            ),
+            (
+                &mut queue.connection.idle_timeout,
+                "queue.outbound.connection.idle-timeout",
+                &mx_vars,
+            ),
+            (
+                &mut queue.connection.max_messages,
+                "queue.outbound.connection.max-messages",
+                &mx_vars,
+            ),
+            (
+                &mut queue.connection.max_per_host,
+                "queue.outbound.connection.max-per-host",
+                &mx_vars,
+            ),
             (
                 &mut queue.timeout.connect,
                 "queue.outbound.timeouts.connect",
@@ -316,11 +468,56 @@ impl QueueConfig {
             }
         }
 
-        // Parse rate limiters
+        // Parse worker limits
         queue.max_threads = config
             .property_or_default::<usize>("queue.threads.remote", "25")
             .unwrap_or(25)
             .max(1);
+
+        // Parse named queues
+        queue.named = config
+            .sub_keys("queue.named", "")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter(|id| id != DEFAULT_QUEUE_NAME)
+            .map(|id| {
+                let queue = NamedQueue {
+                    retry: IfBlock::try_parse(
+                        config,
+                        ("queue.named", id.as_str(), "schedule.retry"),
+                        &host_vars,
+                    ),
+                    notify: IfBlock::try_parse(
+                        config,
+                        ("queue.named", id.as_str(), "schedule.notify"),
+                        &rcpt_vars,
+                    ),
+                    expire: IfBlock::try_parse(
+                        config,
+                        ("queue.named", id.as_str(), "schedule.expire"),
+                        &rcpt_vars,
+                    ),
+                    source_ipv4: IfBlock::try_parse(
+                        config,
+                        ("queue.named", id.as_str(), "source-ip.v4"),
+                        &mx_vars,
+                    ),
+                    source_ipv6: IfBlock::try_parse(
+                        config,
+                        ("queue.named", id.as_str(), "source-ip.v6"),
+                        &mx_vars,
+                    ),
+                    threads: config
+                        .property::<usize>(("queue.named", id.as_str(), "threads"))
+                        .unwrap_or(queue.max_threads)
+                        .max(1),
+                };
+                (id, queue)
+            })
+            .collect();
+
+        // Parse rate limiters
         queue.inbound_limiters = parse_inbound_rate_limters(config);
         queue.outbound_limiters = parse_outbound_rate_limiters(config);
         queue.quota = parse_queue_quota(config);
@@ -334,6 +531,29 @@ impl QueueConfig {
             .filter_map(|id| parse_relay_host(config, &id).map(|host| (id, host)))
             .collect();
 
+        // Parse outbound proxies
+        queue.proxies = config
+            .sub_keys("proxy", ".address")
+            .map(|id| id.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter_map(|id| parse_proxy(config, &id).map(|proxy| (id, proxy)))
+            .collect();
+
+        // Parse adaptive throttling
+        queue.adaptive = parse_adaptive_throttle(config);
+
+        // Parse delivery tracking
+        if config
+            .property_or_default("queue.tracking.enable", "false")
+            .unwrap_or(false)
+        {
+            queue.tracking = config
+                .property_or_default::<Duration>("queue.tracking.retention", "30d")
+                .unwrap_or_else(|| Duration::from_secs(30 * 86400))
+                .into();
+        }
+
         // Add local delivery host
         queue.relay_hosts.insert(
             "local".to_string(),
@@ -351,6 +571,48 @@ impl QueueConfig {
     }
 }
 
+impl QueueConfig {
+    pub fn retry_for(&self, queue: Option<&str>) -> &IfBlock {
+        self.named_queue(queue)
+            .and_then(|q| q.retry.as_ref())
+            .unwrap_or(&self.retry)
+    }
+
+    pub fn notify_for(&self, queue: Option<&str>) -> &IfBlock {
+        self.named_queue(queue)
+            .and_then(|q| q.notify.as_ref())
+            .unwrap_or(&self.notify)
+    }
+
+    pub fn expire_for(&self, queue: Option<&str>) -> &IfBlock {
+        self.named_queue(queue)
+            .and_then(|q| q.expire.as_ref())
+            .unwrap_or(&self.expire)
+    }
+
+    pub fn source_ipv4_for(&self, queue: Option<&str>) -> &IfBlock {
+        self.named_queue(queue)
+            .and_then(|q| q.source_ipv4.as_ref())
+            .unwrap_or(&self.source_ip.ipv4)
+    }
+
+    pub fn source_ipv6_for(&self, queue: Option<&str>) -> &IfBlock {
+        self.named_queue(queue)
+            .and_then(|q| q.source_ipv6.as_ref())
+            .unwrap_or(&self.source_ip.ipv6)
+    }
+
+    pub fn threads_for(&self, queue: Option<&str>) -> usize {
+        self.named_queue(queue)
+            .map(|q| q.threads)
+            .unwrap_or(self.max_threads)
+    }
+
+    fn named_queue(&self, queue: Option<&str>) -> Option<&NamedQueue> {
+        queue.and_then(|queue| self.named.get(queue))
+    }
+}
+
 fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
     Some(RelayHost {
         address: config.property_require(("remote", id, "address"))?,
@@ -377,6 +639,99 @@ fn parse_relay_host(config: &mut Config, id: &str) -> Option<RelayHost> {
     })
 }
 
+fn parse_proxy(config: &mut Config, id: &str) -> Option<OutboundProxy> {
+    Some(OutboundProxy {
+        id: id.to_string(),
+        protocol: config.property_require(("proxy", id, "protocol"))?,
+        address: config.property_require(("proxy", id, "address"))?,
+        port: config.property_require(("proxy", id, "port"))?,
+        auth: if let (Some(username), Some(secret)) = (
+            config.value(("proxy", id, "auth.username")),
+            config.value(("proxy", id, "auth.secret")),
+        ) {
+            Credentials::new(username.to_string(), secret.to_string()).into()
+        } else {
+            None
+        },
+    })
+}
+
+fn parse_adaptive_throttle(config: &mut Config) -> AdaptiveThrottle {
+    let mut adaptive = AdaptiveThrottle::default();
+    adaptive.enable = config
+        .property_or_default("queue.adaptive.enable", "false")
+        .unwrap_or(false);
+    if !adaptive.enable {
+        return adaptive;
+    }
+    adaptive.scope = config
+        .property_or_default("queue.adaptive.scope", "domain")
+        .unwrap_or_default();
+
+    // Enhanced status codes and response texts signaling a rate limit
+    let status_codes = config.properties::<EnhancedStatusCode>("queue.adaptive.status-codes");
+    if !status_codes.is_empty() {
+        adaptive.status_codes = status_codes.into_iter().map(|(_, code)| code.0).collect();
+    }
+    if config.has_prefix("queue.adaptive.match") {
+        let mut signatures = Vec::new();
+        for (key, pattern) in config
+            .iterate_prefix("queue.adaptive.match")
+            .map(|(key, pattern)| (key.to_string(), pattern.to_string()))
+            .collect::<Vec<_>>()
+        {
+            match Regex::new(&pattern) {
+                Ok(regex) => {
+                    let id = key.split_once('.').map_or(key.as_str(), |(id, _)| id);
+                    signatures.push((id.to_string(), regex));
+                }
+                Err(err) => {
+                    config.new_parse_error(
+                        ("queue.adaptive.match", key.as_str()),
+                        format!("Invalid regular expression: {err}"),
+                    );
+                }
+            }
+        }
+        adaptive.signatures = signatures;
+    }
+
+    // Limits
+    adaptive.max_concurrency = config
+        .property_or_default::<usize>("queue.adaptive.concurrency.max", "10")
+        .unwrap_or(10)
+        .max(1);
+    adaptive.min_concurrency = config
+        .property_or_default::<usize>("queue.adaptive.concurrency.min", "1")
+        .unwrap_or(1)
+        .clamp(1, adaptive.max_concurrency);
+    adaptive.max_rate = config
+        .property_or_default::<u64>("queue.adaptive.rate.max", "120")
+        .unwrap_or(120)
+        .max(1);
+    adaptive.min_rate = config
+        .property_or_default::<u64>("queue.adaptive.rate.min", "1")
+        .unwrap_or(1)
+        .clamp(1, adaptive.max_rate);
+    adaptive.max_backoff = config
+        .property_or_default::<Duration>("queue.adaptive.backoff.max", "1h")
+        .unwrap_or_else(|| Duration::from_secs(3600))
+        .as_secs()
+        .max(1);
+    adaptive.min_backoff = config
+        .property_or_default::<Duration>("queue.adaptive.backoff.min", "1m")
+        .unwrap_or_else(|| Duration::from_secs(60))
+        .as_secs()
+        .clamp(1, adaptive.max_backoff);
+    adaptive.recovery = config
+        .property_or_default::<Duration>("queue.adaptive.recovery", "5m")
+        .unwrap_or_else(|| Duration::from_secs(300))
+        .as_secs()
+        .max(1);
+
+    adaptive
+}
+
 fn parse_inbound_rate_limters(config: &mut Config) -> QueueRateLimiters {
     let mut throttle = QueueRateLimiters::default();
     let all_throttles = parse_queue_rate_limiter(
@@ -659,6 +1014,79 @@ impl ConstantValue for IpLookupStrategy {
     }
 }
 
+impl AdaptiveThrottle {
+    /// Returns the signature matched by a temporary failure that
+    /// indicates the remote host is rate limiting deliveries.
+    pub fn is_rate_limited(&self, response: &Response<String>) -> Option<String> {
+        if !(400..500).contains(&response.code) {
+            return None;
+        }
+
+        if self.status_codes.contains(&response.esc) {
+            Some(format!(
+                "{}.{}.{}",
+                response.esc[0], response.esc[1], response.esc[2]
+            ))
+        } else {
+            self.signatures
+                .iter()
+                .find(|(_, regex)| regex.is_match(&response.message))
+                .map(|(id, _)| id.clone())
+        }
+    }
+}
+
+struct EnhancedStatusCode([u8; 3]);
+
+impl ParseValue for EnhancedStatusCode {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        let mut code = [0u8; 3];
+        let mut parts = value.trim().split('.');
+        for part in code.iter_mut() {
+            *part = parts
+                .next()
+                .and_then(|part| part.parse().ok())
+                .ok_or_else(|| format!("Invalid enhanced status code {:?}.", value))?;
+        }
+        if parts.next().is_none() && matches!(code[0], 4 | 5) {
+            Ok(EnhancedStatusCode(code))
+        } else {
+            Err(format!("Invalid enhanced status code {:?}.", value))
+        }
+    }
+}
+
+impl ParseValue for AdaptiveScope {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "domain" => Ok(AdaptiveScope::Domain),
+            "mx" => Ok(AdaptiveScope::Mx),
+            _ => Err(format!("Invalid adaptive throttle scope {:?}.", value)),
+        }
+    }
+}
+
+impl ParseValue for ProxyProtocol {
+    fn parse_value(value: &str) -> Result<Self, String> {
+        match value {
+            "socks5" => Ok(ProxyProtocol::Socks5),
+            "http" => Ok(ProxyProtocol::Http),
+            _ => Err(format!("Invalid proxy protocol value {:?}.", value)),
+        }
+    }
+}
+
+impl std::fmt::Debug for OutboundProxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutboundProxy")
+            .field("id", &self.id)
+            .field("protocol", &self.protocol)
+            .field("address", &self.address)
+            .field("port", &self.port)
+            .finish()
+    }
+}
+
 impl std::fmt::Debug for RelayHost {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("RelayHost")
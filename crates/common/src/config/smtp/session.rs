@@ -17,10 +17,10 @@ use hyper::{
     HeaderMap,
 };
 use smtp_proto::*;
-use utils::config::{utils::ParseValue, Config};
+use utils::config::{cron::SimpleCron, utils::ParseValue, Config};
 
 use crate::{
-    config::CONNECTION_VARS,
+    config::{spamfilter::AntivirusAction, CONNECTION_VARS},
     expr::{if_block::IfBlock, tokenizer::TokenMap, *},
 };
 
@@ -123,6 +123,11 @@ pub enum AddressMapping {
 pub struct Data {
     pub script: IfBlock,
     pub spam_filter: IfBlock,
+    pub antivirus_action: IfBlock,
+
+    // Quarantine
+    pub quarantine_expire: IfBlock,
+    pub quarantine_digest: Option<SimpleCron>,
 
     // Limits
     pub max_messages: IfBlock,
@@ -196,6 +201,7 @@ impl SessionConfig {
         let has_rcpt_vars = TokenMap::default().with_variables(SMTP_RCPT_TO_VARS);
         let mt_priority_vars = has_sender_vars.clone().with_constants::<MtPriority>();
         let mechanisms_vars = has_ehlo_hars.clone().with_constants::<Mechanism>();
+        let antivirus_vars = has_rcpt_vars.clone().with_constants::<AntivirusAction>();
 
         let mut session = SessionConfig::default();
         session.rcpt.catch_all = AddressMapping::parse(config, "session.rcpt.catch-all");
@@ -409,6 +415,16 @@ impl SessionConfig {
                 "session.data.spam-filter",
                 &has_rcpt_vars,
             ),
+            (
+                &mut session.data.antivirus_action,
+                "session.data.antivirus.action",
+                &antivirus_vars,
+            ),
+            (
+                &mut session.data.quarantine_expire,
+                "session.data.quarantine.expire",
+                &has_rcpt_vars,
+            ),
             (
                 &mut session.data.add_received,
                 "session.data.add-headers.received",
@@ -444,6 +460,9 @@ impl SessionConfig {
                 *value = if_block;
             }
         }
+        session.data.quarantine_digest = config
+            .property::<Option<SimpleCron>>("session.data.quarantine.digest")
+            .unwrap_or_default();
         session.data.add_delivered_to = config
             .property_or_default("session.data.add-headers.delivered-to", "true")
             .unwrap_or(true);
@@ -717,6 +736,17 @@ impl Default for SessionConfig {
             data: Data {
                 script: IfBlock::empty("session.data.script"),
                 spam_filter: IfBlock::new::<()>("session.data.spam-filter", [], "true"),
+                antivirus_action: IfBlock::new::<AntivirusAction>(
+                    "session.data.antivirus.action",
+                    [],
+                    "reject",
+                ),
+                quarantine_expire: IfBlock::new::<()>(
+                    "session.data.quarantine.expire",
+                    [],
+                    "30d",
+                ),
+                quarantine_digest: None,
                 max_messages: IfBlock::new::<()>("session.data.limits.messages", [], "10"),
                 max_message_size: IfBlock::new::<()>("session.data.limits.size", [], "104857600"),
                 max_received_headers: IfBlock::new::<()>(
@@ -6,12 +6,13 @@
 
 use std::{
     net::{IpAddr, SocketAddr},
+    path::PathBuf,
     time::Duration,
 };
 
 use ahash::AHashSet;
 use mail_auth::common::resolver::ToReverseName;
-use nlp::bayes::BayesClassifier;
+use nlp::{bayes::BayesClassifier, ftrl::FtrlParameters};
 use tokio::net::lookup_host;
 use utils::{
     cache::CacheItemWeight,
@@ -19,7 +20,10 @@ use utils::{
     glob::GlobMap,
 };
 
-use super::{functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Variable};
+use super::{
+    functions::ResolveVariable, if_block::IfBlock, tokenizer::TokenMap, Constant, ConstantValue,
+    Variable,
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct SpamFilterConfig {
@@ -28,6 +32,9 @@ pub struct SpamFilterConfig {
     pub rules: SpamFilterRules,
     pub lists: SpamFilterLists,
     pub pyzor: Option<PyzorConfig>,
+    pub fuzzy: Option<FuzzyConfig>,
+    pub antivirus: Option<AntivirusConfig>,
+    pub inspect: Option<InspectConfig>,
     pub reputation: Option<ReputationConfig>,
     pub bayes: Option<BayesConfig>,
     pub scores: SpamFilterScoreConfig,
@@ -41,12 +48,14 @@ pub struct SpamFilterHeaderConfig {
     pub result: Option<String>,
     pub bayes_result: Option<String>,
     pub llm: Option<String>,
+    pub antivirus: Option<String>,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct SpamFilterScoreConfig {
     pub reject_threshold: f64,
     pub discard_threshold: f64,
+    pub quarantine_threshold: f64,
     pub spam_threshold: f64,
 }
 
@@ -76,11 +85,13 @@ pub enum SpamFilterAction<T> {
     Allow(T),
     Discard,
     Reject,
+    Quarantine,
 }
 
 #[derive(Debug, Clone, Default)]
 pub struct BayesConfig {
     pub classifier: BayesClassifier,
+    pub ftrl: Option<FtrlConfig>,
     pub auto_learn: bool,
     pub auto_learn_reply_ham: bool,
     pub auto_learn_spam_threshold: f64,
@@ -92,6 +103,19 @@ pub struct BayesConfig {
     pub account_classify: bool,
 }
 
+#[derive(Debug, Clone)]
+pub struct FtrlConfig {
+    pub parameters: FtrlParameters,
+    pub tags: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+enum ClassifierModel {
+    #[default]
+    Bayes,
+    Ftrl,
+}
+
 #[derive(Debug, Clone, Default)]
 pub struct ReputationConfig {
     pub expiry: u64,
@@ -112,6 +136,53 @@ pub struct PyzorConfig {
     pub ratio: f64,
 }
 
+#[derive(Debug, Clone)]
+pub struct FuzzyConfig {
+    pub min_length: usize,
+    pub max_size: usize,
+    pub attachments: bool,
+    pub min_score: u32,
+    pub bulk_count: u64,
+    pub spam_count: u64,
+    pub spam_ratio: f64,
+    pub expiry: u64,
+}
+
+#[derive(Debug, Clone)]
+pub struct AntivirusConfig {
+    pub address: ClamdAddress,
+    pub timeout: Duration,
+    pub max_size: usize,
+    pub scan: AntivirusScope,
+    pub cache_ttl: Option<u64>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InspectConfig {
+    pub max_size: usize,
+    pub max_members: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ClamdAddress {
+    Tcp(SocketAddr),
+    Unix(PathBuf),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntivirusScope {
+    Message,
+    Attachments,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AntivirusAction {
+    Reject,
+    Quarantine,
+    Tag,
+    Disable,
+}
+
 #[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct SpamFilterRules {
     pub url: Vec<IfBlock>,
@@ -182,6 +253,9 @@ impl SpamFilterConfig {
             rules: SpamFilterRules::parse(config),
             lists: SpamFilterLists::parse(config),
             pyzor: PyzorConfig::parse(config).await,
+            fuzzy: FuzzyConfig::parse(config),
+            antivirus: AntivirusConfig::parse(config).await,
+            inspect: InspectConfig::parse(config),
             reputation: ReputationConfig::parse(config),
             bayes: BayesConfig::parse(config),
             scores: SpamFilterScoreConfig::parse(config),
@@ -330,6 +404,7 @@ impl SpamFilterHeaderConfig {
             ("result", &mut header.result),
             ("llm", &mut header.llm),
             ("bayes", &mut header.bayes_result),
+            ("antivirus", &mut header.antivirus),
         ] {
             if config
                 .property_or_default(("spam-filter.header", typ, "enable"), "true")
@@ -348,6 +423,22 @@ impl SpamFilterHeaderConfig {
     }
 }
 
+const INSPECT_SCORES: &[(&str, f64)] = &[
+    ("ARCHIVE_ENCRYPTED", 3.0),
+    ("ARCHIVE_BAD_EXTENSION", 5.0),
+    ("ARCHIVE_IN_ARCHIVE", 2.0),
+    ("OFFICE_MACRO", 4.0),
+    ("OFFICE_OLE_OBJECT", 3.0),
+    ("OFFICE_ENCRYPTED", 2.0),
+    ("PDF_JAVASCRIPT", 4.0),
+    ("PDF_LAUNCH", 6.0),
+    ("PDF_AUTO_ACTION", 1.0),
+    ("PDF_EMBEDDED_FILE", 2.0),
+    ("PDF_ENCRYPTED", 0.5),
+];
+
+const FUZZY_SCORES: &[(&str, f64)] = &[("FUZZY_BULK", 2.0), ("FUZZY_SPAM", 5.0)];
+
 impl SpamFilterLists {
     pub fn parse(config: &mut Config) -> Self {
         let mut lists = SpamFilterLists {
@@ -355,6 +446,11 @@ impl SpamFilterLists {
             scores: GlobMap::default(),
         };
 
+        // Default scores for attachment inspection and fuzzy hash tags
+        for (tag, score) in INSPECT_SCORES.iter().chain(FUZZY_SCORES) {
+            lists.scores.insert(tag, SpamFilterAction::Allow(*score));
+        }
+
         // Parse local lists
         let mut errors = vec![];
         for (key, value) in config.iterate_prefix("spam-filter.list") {
@@ -367,6 +463,7 @@ impl SpamFilterLists {
                         let action = match value.to_lowercase().as_str() {
                             "reject" => SpamFilterAction::Reject,
                             "discard" => SpamFilterAction::Discard,
+                            "quarantine" => SpamFilterAction::Quarantine,
                             score => match score.parse() {
                                 Ok(score) => SpamFilterAction::Allow(score),
                                 Err(err) => {
@@ -478,6 +575,126 @@ impl PyzorConfig {
     }
 }
 
+impl FuzzyConfig {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.fuzzy.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        FuzzyConfig {
+            min_length: config
+                .property_or_default("spam-filter.fuzzy.min-length", "256")
+                .unwrap_or(256),
+            max_size: config
+                .property_or_default("spam-filter.fuzzy.max-size", "10485760")
+                .unwrap_or(10485760),
+            attachments: config
+                .property_or_default("spam-filter.fuzzy.attachments", "true")
+                .unwrap_or(true),
+            min_score: config
+                .property_or_default::<u32>("spam-filter.fuzzy.score", "70")
+                .unwrap_or(70)
+                .min(100),
+            bulk_count: config
+                .property_or_default("spam-filter.fuzzy.count.bulk", "20")
+                .unwrap_or(20),
+            spam_count: config
+                .property_or_default("spam-filter.fuzzy.count.spam", "3")
+                .unwrap_or(3),
+            spam_ratio: config
+                .property_or_default("spam-filter.fuzzy.ratio", "0.6")
+                .unwrap_or(0.6),
+            expiry: config
+                .property_or_default::<Duration>("spam-filter.fuzzy.expiry", "30d")
+                .map(|d| d.as_secs())
+                .unwrap_or(2592000),
+        }
+        .into()
+    }
+}
+
+impl AntivirusConfig {
+    pub async fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.antivirus.enable", "false")
+            .unwrap_or(false)
+        {
+            return None;
+        }
+
+        let address = config
+            .value("spam-filter.antivirus.address")
+            .unwrap_or("127.0.0.1:3310")
+            .to_string();
+        let address = if let Some(path) = address
+            .strip_prefix("unix:")
+            .or_else(|| address.starts_with('/').then_some(address.as_str()))
+        {
+            ClamdAddress::Unix(PathBuf::from(path))
+        } else {
+            match lookup_host(&address).await.map(|mut a| a.next()) {
+                Ok(Some(address)) => ClamdAddress::Tcp(address),
+                Ok(None) => {
+                    config.new_build_error(
+                        "spam-filter.antivirus.address",
+                        "Invalid address: No addresses found.",
+                    );
+                    return None;
+                }
+                Err(err) => {
+                    config.new_build_error(
+                        "spam-filter.antivirus.address",
+                        format!("Invalid address: {}", err),
+                    );
+                    return None;
+                }
+            }
+        };
+
+        AntivirusConfig {
+            address,
+            timeout: config
+                .property_or_default::<Duration>("spam-filter.antivirus.timeout", "30s")
+                .unwrap_or(Duration::from_secs(30)),
+            max_size: config
+                .property_or_default("spam-filter.antivirus.max-size", "26214400")
+                .unwrap_or(26214400),
+            scan: config
+                .property_or_default("spam-filter.antivirus.scan", "message")
+                .unwrap_or(AntivirusScope::Message),
+            cache_ttl: config
+                .property_or_default::<Option<Duration>>("spam-filter.antivirus.cache", "1h")
+                .unwrap_or_default()
+                .map(|d| d.as_secs()),
+        }
+        .into()
+    }
+}
+
+impl InspectConfig {
+    pub fn parse(config: &mut Config) -> Option<Self> {
+        if !config
+            .property_or_default("spam-filter.inspect.enable", "true")
+            .unwrap_or(true)
+        {
+            return None;
+        }
+
+        InspectConfig {
+            max_size: config
+                .property_or_default("spam-filter.inspect.max-size", "10485760")
+                .unwrap_or(10485760),
+            max_members: config
+                .property_or_default("spam-filter.inspect.max-members", "1000")
+                .unwrap_or(1000),
+        }
+        .into()
+    }
+}
+
 impl ReputationConfig {
     pub fn parse(config: &mut Config) -> Option<Self> {
         if !config
@@ -524,7 +741,16 @@ impl BayesConfig {
             return None;
         }
 
+        let ftrl = match config
+            .property_or_default::<ClassifierModel>("spam-filter.bayes.model", "bayes")
+            .unwrap_or_default()
+        {
+            ClassifierModel::Bayes => None,
+            ClassifierModel::Ftrl => FtrlConfig::parse(config).into(),
+        };
+
         BayesConfig {
+            ftrl,
             classifier: BayesClassifier {
                 min_token_hits: config
                     .property_or_default("spam-filter.bayes.classify.tokens.hits", "2")
@@ -574,6 +800,34 @@ impl BayesConfig {
     }
 }
 
+impl FtrlConfig {
+    pub fn parse(config: &mut Config) -> Self {
+        FtrlConfig {
+            parameters: FtrlParameters {
+                alpha: config
+                    .property_or_default("spam-filter.bayes.ftrl.alpha", "0.1")
+                    .unwrap_or(0.1),
+                beta: config
+                    .property_or_default("spam-filter.bayes.ftrl.beta", "1.0")
+                    .unwrap_or(1.0),
+                l1: config
+                    .property_or_default("spam-filter.bayes.ftrl.l1", "1.0")
+                    .unwrap_or(1.0),
+                l2: config
+                    .property_or_default("spam-filter.bayes.ftrl.l2", "1.0")
+                    .unwrap_or(1.0),
+                feature_bits: config
+                    .property_or_default::<u32>("spam-filter.bayes.ftrl.feature-bits", "22")
+                    .unwrap_or(22)
+                    .clamp(10, 30),
+            },
+            tags: config
+                .property_or_default("spam-filter.bayes.ftrl.tags", "true")
+                .unwrap_or(true),
+        }
+    }
+}
+
 impl SpamFilterScoreConfig {
     pub fn parse(config: &mut Config) -> Self {
         SpamFilterScoreConfig {
@@ -583,6 +837,9 @@ impl SpamFilterScoreConfig {
             discard_threshold: config
                 .property("spam-filter.score.discard")
                 .unwrap_or_default(),
+            quarantine_threshold: config
+                .property("spam-filter.score.quarantine")
+                .unwrap_or_default(),
             spam_threshold: config
                 .property_or_default("spam-filter.score.spam", "5.0")
                 .unwrap_or(5.0),
@@ -623,6 +880,86 @@ impl ParseValue for Element {
     }
 }
 
+impl std::fmt::Display for ClamdAddress {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ClamdAddress::Tcp(addr) => addr.fmt(f),
+            ClamdAddress::Unix(path) => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+impl ParseValue for ClassifierModel {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "bayes" | "naive-bayes" => Ok(ClassifierModel::Bayes),
+            "ftrl" | "logistic-regression" => Ok(ClassifierModel::Ftrl),
+            other => Err(format!("Invalid classifier model {other:?}.",)),
+        }
+    }
+}
+
+impl ParseValue for AntivirusScope {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "message" => Ok(AntivirusScope::Message),
+            "attachments" => Ok(AntivirusScope::Attachments),
+            other => Err(format!("Invalid antivirus scan scope {other:?}.",)),
+        }
+    }
+}
+
+impl ParseValue for AntivirusAction {
+    fn parse_value(value: &str) -> utils::config::Result<Self> {
+        match value {
+            "reject" => Ok(AntivirusAction::Reject),
+            "quarantine" => Ok(AntivirusAction::Quarantine),
+            "tag" => Ok(AntivirusAction::Tag),
+            "disable" | "disabled" | "false" => Ok(AntivirusAction::Disable),
+            other => Err(format!("Invalid antivirus action {other:?}.",)),
+        }
+    }
+}
+
+impl<'x> TryFrom<Variable<'x>> for AntivirusAction {
+    type Error = ();
+
+    fn try_from(value: Variable<'x>) -> Result<Self, Self::Error> {
+        match value {
+            Variable::Integer(value) => match value {
+                0 => Ok(AntivirusAction::Disable),
+                1 => Ok(AntivirusAction::Reject),
+                2 => Ok(AntivirusAction::Quarantine),
+                3 => Ok(AntivirusAction::Tag),
+                _ => Err(()),
+            },
+            Variable::String(value) => AntivirusAction::parse_value(&value).map_err(|_| ()),
+            _ => Err(()),
+        }
+    }
+}
+
+impl From<AntivirusAction> for Constant {
+    fn from(value: AntivirusAction) -> Self {
+        Constant::Integer(match value {
+            AntivirusAction::Disable => 0,
+            AntivirusAction::Reject => 1,
+            AntivirusAction::Quarantine => 2,
+            AntivirusAction::Tag => 3,
+        })
+    }
+}
+
+impl ConstantValue for AntivirusAction {
+    fn add_constants(token_map: &mut TokenMap) {
+        token_map
+            .add_constant("reject", AntivirusAction::Reject)
+            .add_constant("quarantine", AntivirusAction::Quarantine)
+            .add_constant("tag", AntivirusAction::Tag)
+            .add_constant("disable", AntivirusAction::Disable);
+    }
+}
+
 impl Location {
     pub fn as_str(&self) -> &'static str {
         match self {
@@ -654,6 +991,7 @@ impl Default for SpamFilterHeaderConfig {
             result: "X-Spam-Result".to_string().into(),
             bayes_result: "X-Spam-Bayes".to_string().into(),
             llm: "X-Spam-LLM".to_string().into(),
+            antivirus: "X-Virus-Status".to_string().into(),
         }
     }
 }
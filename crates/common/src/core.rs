@@ -29,7 +29,7 @@ use crate::{
     auth::{AccessToken, ResourceToken, TenantInfo},
     config::smtp::{
         auth::{ArcSealer, DkimSigner, LazySignature, ResolvedSignature, build_signature},
-        queue::RelayHost,
+        queue::{OutboundProxy, RelayHost},
     },
     ipc::StateEvent,
 };
@@ -193,6 +193,18 @@ impl Server {
         })
     }
 
+    pub fn get_outbound_proxy(&self, name: &str, session_id: u64) -> Option<&OutboundProxy> {
+        self.core.smtp.queue.proxies.get(name).or_else(|| {
+            trc::event!(
+                Smtp(trc::SmtpEvent::RemoteIdNotFound),
+                Id = name.to_string(),
+                SpanId = session_id,
+            );
+
+            None
+        })
+    }
+
     pub async fn get_used_quota(&self, account_id: u32) -> trc::Result<i64> {
         self.core
             .storage
@@ -557,6 +569,10 @@ impl Server {
             Collection::Thread,
             Collection::Identity,
             Collection::EmailSubmission,
+            Collection::AddressBook,
+            Collection::ContactCard,
+            Collection::Calendar,
+            Collection::CalendarEvent,
         ] {
             self.core
                 .storage
@@ -0,0 +1,297 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Duration};
+
+use ahash::AHashMap;
+use dns_update::providers::rfc2136::DnsAddress;
+use hickory_client::{
+    client::{AsyncClient, ClientHandle, Signer},
+    op::ResponseCode,
+    proto::iocompat::AsyncIoTokioAsStd,
+    rr::{
+        rdata::{
+            tlsa::{CertUsage, Matching, Selector},
+            CNAME, MX, SRV, TLSA, TXT,
+        },
+        DNSClass, Name, RData, Record, RecordType,
+    },
+    tcp::TcpClientStream,
+    udp::UdpClientStream,
+};
+use tokio::net::{TcpStream, UdpSocket};
+use trc::DnsSyncEvent;
+use utils::config::cron::SimpleCron;
+
+#[derive(Default, Clone)]
+pub struct DnsSyncProviders {
+    pub providers: AHashMap<String, Arc<DnsSyncProvider>>,
+    pub schedule: Option<SimpleCron>,
+}
+
+pub struct DnsSyncProvider {
+    pub id: String,
+    pub addr: DnsAddress,
+    pub signer: Arc<Signer>,
+    pub origin: Option<String>,
+    pub domains: Vec<String>,
+    pub ttl: u32,
+    pub timeout: Duration,
+}
+
+pub struct DnsSyncClient {
+    client: AsyncClient,
+    ttl: u32,
+}
+
+impl DnsSyncProviders {
+    pub fn get(&self, domain: &str) -> Option<&Arc<DnsSyncProvider>> {
+        self.providers
+            .values()
+            .find(|provider| provider.domains.iter().any(|d| d == domain))
+    }
+}
+
+impl DnsSyncProvider {
+    pub fn origin<'x>(&'x self, domain: &'x str) -> &'x str {
+        self.origin
+            .as_deref()
+            .or_else(|| psl::domain_str(domain))
+            .unwrap_or(domain)
+    }
+
+    pub async fn connect(&self) -> trc::Result<DnsSyncClient> {
+        let client = match &self.addr {
+            DnsAddress::Udp(addr) => {
+                let stream = UdpClientStream::<UdpSocket, Signer>::with_timeout_and_signer(
+                    *addr,
+                    self.timeout,
+                    Some(self.signer.clone()),
+                );
+                let (client, background) = AsyncClient::connect(stream)
+                    .await
+                    .map_err(|err| self.error(err))?;
+                tokio::spawn(background);
+                client
+            }
+            DnsAddress::Tcp(addr) => {
+                let (stream, sender) =
+                    TcpClientStream::<AsyncIoTokioAsStd<TcpStream>>::with_timeout(
+                        *addr,
+                        self.timeout,
+                    );
+                let (client, background) = AsyncClient::with_timeout(
+                    stream,
+                    sender,
+                    self.timeout,
+                    Some(self.signer.clone()),
+                )
+                .await
+                .map_err(|err| self.error(err))?;
+                tokio::spawn(background);
+                client
+            }
+        };
+
+        Ok(DnsSyncClient {
+            client,
+            ttl: self.ttl,
+        })
+    }
+
+    fn error(&self, err: impl std::fmt::Display) -> trc::Error {
+        DnsSyncEvent::Error
+            .into_err()
+            .ctx(trc::Key::Id, self.id.clone())
+            .reason(err)
+    }
+}
+
+impl DnsSyncClient {
+    // Returns the records of the given type at a name along with their
+    // presentation format, as produced by the DNS management API.
+    pub async fn lookup(&mut self, name: &str, typ: &str) -> trc::Result<Vec<(String, Record)>> {
+        let record_type = match typ {
+            "TXT" => RecordType::TXT,
+            "MX" => RecordType::MX,
+            "CNAME" => RecordType::CNAME,
+            "SRV" => RecordType::SRV,
+            "TLSA" => RecordType::TLSA,
+            _ => {
+                return Err(DnsSyncEvent::Error
+                    .into_err()
+                    .ctx(trc::Key::Type, typ.to_string())
+                    .details("Unsupported record type"));
+            }
+        };
+
+        let response = self
+            .client
+            .query(parse_name(name)?, DNSClass::IN, record_type)
+            .await
+            .map_err(|err| {
+                DnsSyncEvent::Error
+                    .into_err()
+                    .ctx(trc::Key::Hostname, name.to_string())
+                    .reason(err)
+            })?;
+        match response.response_code() {
+            ResponseCode::NoError | ResponseCode::NXDomain => Ok(response
+                .answers()
+                .iter()
+                .filter(|record| record.record_type() == record_type)
+                .filter_map(|record| {
+                    record
+                        .data()
+                        .and_then(record_content)
+                        .map(|content| (content, record.clone()))
+                })
+                .collect()),
+            code => Err(DnsSyncEvent::Error
+                .into_err()
+                .ctx(trc::Key::Hostname, name.to_string())
+                .ctx(trc::Key::Code, code.to_string())
+                .details("Lookup failed")),
+        }
+    }
+
+    pub async fn create(
+        &mut self,
+        origin: &str,
+        typ: &str,
+        name: &str,
+        content: &str,
+    ) -> trc::Result<()> {
+        let rdata = build_rdata(typ, content).ok_or_else(|| {
+            DnsSyncEvent::Error
+                .into_err()
+                .ctx(trc::Key::Hostname, name.to_string())
+                .ctx(trc::Key::Contents, content.to_string())
+                .details("Invalid record")
+        })?;
+        let record = Record::from_rdata(parse_name(name)?, self.ttl, rdata);
+        let response = self
+            .client
+            .append(record, parse_name(origin)?, false)
+            .await
+            .map_err(|err| {
+                DnsSyncEvent::Error
+                    .into_err()
+                    .ctx(trc::Key::Hostname, name.to_string())
+                    .reason(err)
+            })?;
+
+        update_result(response.response_code(), name)
+    }
+
+    pub async fn delete(&mut self, origin: &str, record: Record) -> trc::Result<()> {
+        let name = record.name().to_string();
+        let response = self
+            .client
+            .delete_by_rdata(record, parse_name(origin)?)
+            .await
+            .map_err(|err| {
+                DnsSyncEvent::Error
+                    .into_err()
+                    .ctx(trc::Key::Hostname, name.clone())
+                    .reason(err)
+            })?;
+
+        update_result(response.response_code(), &name)
+    }
+}
+
+fn update_result(code: ResponseCode, name: &str) -> trc::Result<()> {
+    if code == ResponseCode::NoError {
+        Ok(())
+    } else {
+        Err(DnsSyncEvent::Error
+            .into_err()
+            .ctx(trc::Key::Hostname, name.to_string())
+            .ctx(trc::Key::Code, code.to_string())
+            .details("Update rejected"))
+    }
+}
+
+fn parse_name(name: &str) -> trc::Result<Name> {
+    Name::from_ascii(name)
+        .map(|mut result| {
+            result.set_fqdn(true);
+            result
+        })
+        .map_err(|err| {
+            DnsSyncEvent::Error
+                .into_err()
+                .ctx(trc::Key::Hostname, name.to_string())
+                .reason(err)
+        })
+}
+
+fn build_rdata(typ: &str, content: &str) -> Option<RData> {
+    let mut parts = content.split(' ');
+    match typ {
+        "TXT" => {
+            // Character strings are limited to 255 octets, multibyte
+            // characters may span two of them
+            Some(RData::TXT(TXT::from_bytes(
+                content.as_bytes().chunks(255).collect(),
+            )))
+        }
+        "MX" => Some(RData::MX(MX::new(
+            parts.next()?.parse().ok()?,
+            Name::from_ascii(parts.next()?).ok()?,
+        ))),
+        "CNAME" => Some(RData::CNAME(CNAME(Name::from_ascii(content).ok()?))),
+        "SRV" => Some(RData::SRV(SRV::new(
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+            parts.next()?.parse().ok()?,
+            Name::from_ascii(parts.next()?).ok()?,
+        ))),
+        "TLSA" => {
+            let usage = CertUsage::from(parts.next()?.parse::<u8>().ok()?);
+            let selector = Selector::from(parts.next()?.parse::<u8>().ok()?);
+            let matching = Matching::from(parts.next()?.parse::<u8>().ok()?);
+            let hex = parts.next()?.as_bytes();
+            if hex.len() % 2 != 0 {
+                return None;
+            }
+            let data = hex
+                .chunks(2)
+                .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).ok()?, 16).ok())
+                .collect::<Option<Vec<_>>>()?;
+
+            Some(RData::TLSA(TLSA::new(usage, selector, matching, data)))
+        }
+        _ => None,
+    }
+}
+
+fn record_content(rdata: &RData) -> Option<String> {
+    match rdata {
+        RData::TXT(txt) => Some(String::from_utf8_lossy(&txt.txt_data().concat()).into_owned()),
+        RData::MX(mx) => Some(format!("{} {}", mx.preference(), mx.exchange())),
+        RData::CNAME(cname) => Some(cname.0.to_string()),
+        RData::SRV(srv) => Some(format!(
+            "{} {} {} {}",
+            srv.priority(),
+            srv.weight(),
+            srv.port(),
+            srv.target()
+        )),
+        RData::TLSA(tlsa) => Some(format!(
+            "{} {} {} {}",
+            u8::from(tlsa.cert_usage()),
+            u8::from(tlsa.selector()),
+            u8::from(tlsa.matching()),
+            tlsa.cert_data()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>()
+        )),
+        _ => None,
+    }
+}
@@ -99,6 +99,10 @@ pub enum QueueEvent {
         status: QueueEventStatus,
     },
     Paused(bool),
+    PausedQueue {
+        queue: String,
+        paused: bool,
+    },
     Stop,
 }
 
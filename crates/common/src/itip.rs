@@ -0,0 +1,122 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::{Message, MimeHeaders};
+use utils::codec::content_line::ContentLine;
+
+// iTIP (RFC 5546) scheduling messages are transported by iMIP (RFC 6047)
+// as text/calendar parts, only the first VEVENT is inspected.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Itip {
+    pub method: String,
+    pub uid: String,
+    pub sequence: u32,
+    pub summary: Option<String>,
+    pub organizer: Option<String>,
+    pub attendees: Vec<ItipAttendee>,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ItipAttendee {
+    pub address: String,
+    pub name: Option<String>,
+    pub status: Option<String>,
+}
+
+impl Itip {
+    pub fn from_message(message: &Message<'_>) -> Option<Itip> {
+        message.parts.iter().find_map(|part| {
+            part.content_type()
+                .filter(|ct| {
+                    ct.ctype().eq_ignore_ascii_case("text")
+                        && ct
+                            .subtype()
+                            .is_some_and(|st| st.eq_ignore_ascii_case("calendar"))
+                })
+                .and_then(|_| std::str::from_utf8(part.contents()).ok())
+                .and_then(Itip::parse)
+        })
+    }
+
+    pub fn parse(text: &str) -> Option<Itip> {
+        let mut itip = Itip {
+            data: text.to_string(),
+            ..Default::default()
+        };
+        let mut depth = 0;
+        let mut in_event = false;
+        let mut has_event = false;
+
+        for line in ContentLine::parse_all(text) {
+            match line.name.as_str() {
+                "BEGIN" => {
+                    if depth == 1 && !has_event && line.value.eq_ignore_ascii_case("VEVENT") {
+                        in_event = true;
+                    }
+                    depth += 1;
+                }
+                "END" => {
+                    depth -= 1;
+                    if depth == 1 && in_event {
+                        in_event = false;
+                        has_event = true;
+                    } else if depth <= 0 {
+                        break;
+                    }
+                }
+                "METHOD" if depth == 1 => {
+                    itip.method = line.value.trim().to_ascii_uppercase();
+                }
+                _ if !in_event || depth != 2 => (),
+                "UID" => {
+                    itip.uid = line.text();
+                }
+                "SEQUENCE" => {
+                    itip.sequence = line.value.trim().parse().unwrap_or_default();
+                }
+                "SUMMARY" => {
+                    itip.summary = Some(line.text());
+                }
+                "ORGANIZER" => {
+                    itip.organizer = Some(calendar_address(&line.value));
+                }
+                "ATTENDEE" => {
+                    itip.attendees.push(ItipAttendee {
+                        address: calendar_address(&line.value),
+                        name: line
+                            .param("CN")
+                            .map(|name| name.trim_matches('"').to_string()),
+                        status: line
+                            .param("PARTSTAT")
+                            .map(|status| status.to_ascii_uppercase()),
+                    });
+                }
+                _ => (),
+            }
+        }
+
+        (has_event && !itip.method.is_empty() && !itip.uid.is_empty()).then_some(itip)
+    }
+
+    pub fn attendee(&self, addresses: &[String]) -> Option<&ItipAttendee> {
+        self.attendees.iter().find(|attendee| {
+            addresses
+                .iter()
+                .any(|address| address.eq_ignore_ascii_case(&attendee.address))
+        })
+    }
+}
+
+// Calendar user addresses are mailto: URIs, the scheme is removed
+pub fn calendar_address(value: &str) -> String {
+    let value = value.trim();
+    value
+        .get(..7)
+        .filter(|scheme| scheme.eq_ignore_ascii_case("mailto:"))
+        .map_or(value, |_| &value[7..])
+        .to_lowercase()
+}
@@ -9,9 +9,10 @@ use std::{
     hash::{BuildHasher, Hasher},
     net::{IpAddr, Ipv4Addr, Ipv6Addr},
     sync::{
-        atomic::{AtomicBool, AtomicU8},
+        atomic::{AtomicBool, AtomicU8, AtomicUsize, Ordering},
         Arc,
     },
+    time::Instant,
 };
 
 use ahash::{AHashMap, AHashSet};
@@ -23,6 +24,7 @@ use config::{
     network::Network,
     scripts::Scripting,
     smtp::{
+        queue::RequireOptional,
         resolver::{Policy, Tlsa},
         SmtpConfig,
     },
@@ -31,6 +33,7 @@ use config::{
     telemetry::Metrics,
 };
 
+use dns_sync::DnsSyncProviders;
 use imap_proto::protocol::list::Attribute;
 use ipc::{HousekeeperEvent, QueueEvent, ReportingEvent, StateEvent};
 use listener::{asn::AsnGeoLookupData, blocked::Security, tls::AcmeProviders};
@@ -40,8 +43,12 @@ use manager::webadmin::{Resource, WebAdminManager};
 use nlp::bayes::{TokenHash, Weights};
 use parking_lot::{Mutex, RwLock};
 use rustls::sign::CertifiedKey;
-use tokio::sync::{mpsc, Notify, Semaphore};
-use tokio_rustls::TlsConnector;
+use smtp_proto::EhloResponse;
+use tokio::{
+    net::TcpStream,
+    sync::{mpsc, Notify, Semaphore},
+};
+use tokio_rustls::{client::TlsStream, TlsConnector};
 use utils::{
     cache::{Cache, CacheItemWeight, CacheWithTtl},
     snowflake::SnowflakeIdGenerator,
@@ -52,10 +59,12 @@ pub mod auth;
 pub mod config;
 pub mod core;
 pub mod dns;
+pub mod dns_sync;
 #[cfg(feature = "enterprise")]
 pub mod enterprise;
 pub mod expr;
 pub mod ipc;
+pub mod itip;
 pub mod listener;
 pub mod manager;
 pub mod scripts;
@@ -93,6 +102,13 @@ pub const KV_LOCK_QUEUE_MESSAGE: u8 = 21;
 pub const KV_LOCK_QUEUE_REPORT: u8 = 22;
 pub const KV_LOCK_EMAIL_TASK: u8 = 23;
 pub const KV_LOCK_HOUSEKEEPER: u8 = 24;
+pub const KV_LIST_TOKEN: u8 = 25;
+pub const KV_LIST_BOUNCE: u8 = 26;
+pub const KV_ANTIVIRUS: u8 = 27;
+pub const KV_FUZZY_INDEX: u8 = 28;
+pub const KV_FUZZY_COUNT: u8 = 29;
+pub const KV_FTRL_MODEL_GLOBAL: u8 = 30;
+pub const KV_FTRL_MODEL_USER: u8 = 31;
 
 #[derive(Clone)]
 pub struct Server {
@@ -120,12 +136,14 @@ pub struct Data {
     pub queue_id_gen: SnowflakeIdGenerator,
     pub span_id_gen: SnowflakeIdGenerator,
     pub queue_status: AtomicBool,
+    pub queue_paused: RwLock<AHashSet<String>>,
 
     pub webadmin: WebAdminManager,
     pub logos: Mutex<AHashMap<String, Option<Resource<Vec<u8>>>>>,
     pub config_version: AtomicU8,
 
     pub smtp_connectors: TlsConnectors,
+    pub smtp_connections: SmtpConnectionPool,
 }
 
 pub struct Caches {
@@ -169,6 +187,64 @@ pub struct TlsConnectors {
     pub dummy_verify: TlsConnector,
 }
 
+#[derive(Default)]
+pub struct SmtpConnectionPool {
+    pub idle: Mutex<AHashMap<SmtpConnectionKey, Vec<SmtpIdleConnection>>>,
+    pub hosts: Mutex<AHashMap<String, Arc<AtomicUsize>>>,
+    pub throttled: Mutex<AHashMap<String, SmtpThrottleState>>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SmtpThrottleState {
+    pub concurrency: usize,
+    pub rate: u64,
+    pub backoff: u64,
+    pub retry_at: u64,
+    pub hits: u64,
+    pub last_hit: u64,
+    pub last_change: u64,
+    pub last_response: String,
+    #[serde(skip)]
+    pub window_start: u64,
+    #[serde(skip)]
+    pub window_count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SmtpConnectionKey {
+    pub hostname: String,
+    pub port: u16,
+    pub local_ip: Option<IpAddr>,
+    pub implicit_tls: bool,
+    pub dane: RequireOptional,
+    pub mta_sts: RequireOptional,
+    pub tls: RequireOptional,
+    pub proxy: Option<String>,
+}
+
+pub struct SmtpIdleConnection {
+    pub stream: SmtpStream,
+    pub capabilities: EhloResponse<String>,
+    pub messages: usize,
+    pub expires: Instant,
+    pub slot: SmtpConnectionSlot,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum SmtpStream {
+    Plain(TcpStream),
+    Tls(TlsStream<TcpStream>),
+}
+
+pub struct SmtpConnectionSlot(pub Arc<AtomicUsize>);
+
+impl Drop for SmtpConnectionSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 pub struct AccountId {
     pub account_id: u32,
@@ -243,6 +319,7 @@ pub struct Core {
     pub sieve: Scripting,
     pub network: Network,
     pub acme: AcmeProviders,
+    pub dns_sync: DnsSyncProviders,
     pub oauth: OAuthConfig,
     pub smtp: SmtpConfig,
     pub jmap: JmapConfig,
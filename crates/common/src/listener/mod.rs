@@ -85,6 +85,7 @@ pub enum SessionResult {
     Continue,
     Close,
     UpgradeTls,
+    UpgradeCompress,
 }
 
 pub trait SessionManager: Sync + Send + 'static + Clone {
@@ -177,6 +177,35 @@ impl Core {
                             .send(Op::AccountId(account_id))
                             .failed("Failed to send account id");
                         last_account_id = account_id;
+                        last_collection = u8::MAX;
+                        last_document_id = u32::MAX;
+
+                        // Obtain annotation size counter
+                        let value = store
+                            .get_counter(ValueKey {
+                                account_id,
+                                collection: Collection::Principal.into(),
+                                document_id: 0,
+                                class: ValueClass::Property(Property::AnnotationsSize.into()),
+                            })
+                            .await
+                            .failed("Failed to get counter");
+                        if value != 0 {
+                            writer
+                                .send(Op::Collection(Collection::Principal.into()))
+                                .failed("Failed to send collection");
+                            writer
+                                .send(Op::DocumentId(0))
+                                .failed("Failed to send document id");
+                            writer
+                                .send(Op::KeyValue((
+                                    vec![u8::from(Property::AnnotationsSize)],
+                                    value.serialize(),
+                                )))
+                                .failed("Failed to send key value");
+                            last_collection = Collection::Principal.into();
+                            last_document_id = 0;
+                        }
                     }
 
                     if collection != last_collection {
@@ -97,8 +97,10 @@ async fn restore_file(store: Store, blob_store: BlobStore, path: &Path) {
                             .as_slice()
                             .deserialize_u8(0)
                             .expect("Failed to deserialize field");
-                        if collection == u8::from(Collection::Mailbox)
-                            && u8::from(Property::EmailIds) == field
+                        if (collection == u8::from(Collection::Mailbox)
+                            && u8::from(Property::EmailIds) == field)
+                            || (collection == u8::from(Collection::Principal)
+                                && u8::from(Property::AnnotationsSize) == field)
                         {
                             batch.add(
                                 ValueClass::Property(field),
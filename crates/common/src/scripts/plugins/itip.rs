@@ -0,0 +1,36 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use sieve::{runtime::Variable, FunctionMap};
+
+use crate::itip::Itip;
+
+use super::PluginContext;
+
+pub fn register(plugin_id: u32, fnc_map: &mut FunctionMap) {
+    fnc_map.set_external_function("itip", plugin_id, 1);
+}
+
+pub fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
+    let Some(itip) = Itip::from_message(ctx.message) else {
+        return Ok(Variable::default());
+    };
+
+    Ok(match ctx.arguments[0].to_string().as_ref() {
+        "method" => itip.method.into(),
+        "uid" => itip.uid.into(),
+        "sequence" => Variable::Integer(itip.sequence as i64),
+        "summary" => itip.summary.unwrap_or_default().into(),
+        "organizer" => itip.organizer.unwrap_or_default().into(),
+        "attendees" => itip
+            .attendees
+            .into_iter()
+            .map(|attendee| Variable::from(attendee.address))
+            .collect::<Vec<_>>()
+            .into(),
+        _ => Variable::default(),
+    })
+}
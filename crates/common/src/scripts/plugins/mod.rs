@@ -8,8 +8,10 @@ pub mod dns;
 pub mod exec;
 pub mod headers;
 pub mod http;
+pub mod itip;
 pub mod llm_prompt;
 pub mod lookup;
+pub mod quarantine;
 pub mod query;
 pub mod text;
 
@@ -31,7 +33,7 @@ pub struct PluginContext<'x> {
     pub arguments: Vec<Variable>,
 }
 
-const PLUGINS_REGISTER: [RegisterPluginFnc; 13] = [
+const PLUGINS_REGISTER: [RegisterPluginFnc; 15] = [
     query::register,
     exec::register,
     lookup::register,
@@ -45,6 +47,8 @@ const PLUGINS_REGISTER: [RegisterPluginFnc; 13] = [
     text::register_tokenize,
     text::register_domain_part,
     llm_prompt::register,
+    itip::register,
+    quarantine::register,
 ];
 
 pub trait RegisterSievePlugins {
@@ -67,6 +71,7 @@ impl RegisterSievePlugins for FunctionMap {
 
     fn register_plugins_untrusted(mut self) -> Self {
         llm_prompt::register(12, &mut self);
+        itip::register(13, &mut self);
         self
     }
 }
@@ -93,6 +98,8 @@ impl Core {
             10 => text::exec_tokenize(ctx),
             11 => text::exec_domain_part(ctx),
             12 => llm_prompt::exec(ctx).await,
+            13 => itip::exec(ctx),
+            14 => quarantine::exec(ctx),
             _ => unreachable!(),
         };
 
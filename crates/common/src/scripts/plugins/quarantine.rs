@@ -0,0 +1,28 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use sieve::{runtime::Variable, FunctionMap};
+
+use crate::scripts::ScriptModification;
+
+use super::PluginContext;
+
+pub fn register(plugin_id: u32, fnc_map: &mut FunctionMap) {
+    fnc_map.set_external_function("quarantine", plugin_id, 1);
+}
+
+pub fn exec(ctx: PluginContext<'_>) -> trc::Result<Variable> {
+    let reason = ctx.arguments[0].to_string();
+    ctx.modifications.push(ScriptModification::Quarantine {
+        reason: if !reason.is_empty() {
+            reason.into_owned()
+        } else {
+            "Quarantined by Sieve script".to_string()
+        },
+    });
+
+    Ok(true.into())
+}
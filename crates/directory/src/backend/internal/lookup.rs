@@ -11,7 +11,7 @@ use store::{
 };
 use trc::AddContext;
 
-use crate::{backend::RcptType, Principal, QueryBy, Type};
+use crate::{backend::RcptType, ListOption, Principal, QueryBy, Type};
 
 use super::{manage::ManageDirectory, PrincipalField, PrincipalInfo};
 
@@ -103,6 +103,13 @@ impl DirectoryStore for Store {
         {
             if pinfo.typ != Type::List {
                 Ok(RcptType::Mailbox)
+            } else if self
+                .get_principal(pinfo.id)
+                .await?
+                .is_some_and(|p| p.has_list_option(ListOption::Managed))
+            {
+                // Managed lists are distributed by the list manager on delivery
+                Ok(RcptType::Mailbox)
             } else {
                 self.expn_by_id(pinfo.id).await.map(RcptType::List)
             }
@@ -158,6 +165,13 @@ impl DirectoryStore for Store {
     }
 
     async fn expn_by_id(&self, list_id: u32) -> trc::Result<Vec<String>> {
+        let Some(mut list) = self.get_principal(list_id).await? else {
+            return Ok(vec![]);
+        };
+        let disabled = list
+            .take_str_array(PrincipalField::DisabledMembers)
+            .unwrap_or_default();
+
         let mut results = Vec::new();
         for account_id in self.get_members(list_id).await? {
             if let Some(email) = self
@@ -168,15 +182,15 @@ impl DirectoryStore for Store {
                 results.push(email);
             }
         }
-
-        if let Some(emails) = self
-            .get_principal(list_id)
-            .await?
-            .and_then(|mut p| p.take_str_array(PrincipalField::ExternalMembers))
-        {
+        if let Some(emails) = list.take_str_array(PrincipalField::ExternalMembers) {
             results.extend(emails);
         }
 
+        // Skip members that unsubscribed or were disabled after bouncing
+        if !disabled.is_empty() {
+            results.retain(|email| !disabled.iter().any(|d| d.eq_ignore_ascii_case(email)));
+        }
+
         Ok(results)
     }
 }
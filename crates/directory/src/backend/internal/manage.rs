@@ -17,8 +17,8 @@ use trc::AddContext;
 use utils::sanitize_email;
 
 use crate::{
-    MAX_TYPE_ID, Permission, Permissions, Principal, QueryBy, ROLE_ADMIN, ROLE_TENANT_ADMIN,
-    ROLE_USER, Type, backend::RcptType,
+    ListOption, MAX_TYPE_ID, Permission, Permissions, Principal, QueryBy, ROLE_ADMIN,
+    ROLE_TENANT_ADMIN, ROLE_USER, Type, backend::RcptType,
 };
 
 use super::{
@@ -421,6 +421,11 @@ impl ManageDirectory for Store {
             }
         }
 
+        // Validate list options
+        for option in principal.iter_str(PrincipalField::ListOptions) {
+            validate_list_option(option)?;
+        }
+
         // Make sure the e-mail is not taken and validate domain
         if principal.typ != Type::OauthClient {
             for email in principal.iter_mut_str(PrincipalField::Emails) {
@@ -1651,10 +1656,18 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::Set,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::Moderators
+                    | PrincipalField::DisabledMembers
+                    | PrincipalField::ListOptions,
                     PrincipalValue::StringList(mut items),
                 ) => {
-                    if matches!(change.field, PrincipalField::ExternalMembers) {
+                    if change.field == PrincipalField::ListOptions {
+                        for item in &items {
+                            validate_list_option(item)?;
+                        }
+                    } else if change.field != PrincipalField::Urls {
                         items = items
                             .into_iter()
                             .map(|item| {
@@ -1681,10 +1694,16 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::AddItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::Moderators
+                    | PrincipalField::DisabledMembers
+                    | PrincipalField::ListOptions,
                     PrincipalValue::String(mut item),
                 ) => {
-                    if matches!(change.field, PrincipalField::ExternalMembers) {
+                    if change.field == PrincipalField::ListOptions {
+                        validate_list_option(&item)?;
+                    } else if change.field != PrincipalField::Urls {
                         item = sanitize_email(&item).ok_or_else(|| {
                             error(
                                 "Invalid email address",
@@ -1700,7 +1719,11 @@ impl ManageDirectory for Store {
                 }
                 (
                     PrincipalAction::RemoveItem,
-                    PrincipalField::Urls | PrincipalField::ExternalMembers,
+                    PrincipalField::Urls
+                    | PrincipalField::ExternalMembers
+                    | PrincipalField::Moderators
+                    | PrincipalField::DisabledMembers
+                    | PrincipalField::ListOptions,
                     PrincipalValue::String(item),
                 ) => {
                     if principal.inner.has_str_value(change.field, &item) {
@@ -2199,6 +2222,17 @@ impl<'x> UpdatePrincipal<'x> {
     }
 }
 
+fn validate_list_option(option: &str) -> trc::Result<()> {
+    if ListOption::parse(option).is_some() {
+        Ok(())
+    } else {
+        Err(error(
+            "Invalid listOptions value",
+            format!("List option {option:?} is invalid").into(),
+        ))
+    }
+}
+
 fn validate_member_of(
     field: PrincipalField,
     typ: Type,
@@ -414,6 +414,9 @@ pub enum PrincipalField {
     Picture,
     Urls,
     ExternalMembers,
+    Moderators,
+    DisabledMembers,
+    ListOptions,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
@@ -494,6 +497,9 @@ impl PrincipalField {
             PrincipalField::Picture => 14,
             PrincipalField::Urls => 15,
             PrincipalField::ExternalMembers => 16,
+            PrincipalField::Moderators => 17,
+            PrincipalField::DisabledMembers => 18,
+            PrincipalField::ListOptions => 19,
         }
     }
 
@@ -516,6 +522,9 @@ impl PrincipalField {
             14 => Some(PrincipalField::Picture),
             15 => Some(PrincipalField::Urls),
             16 => Some(PrincipalField::ExternalMembers),
+            17 => Some(PrincipalField::Moderators),
+            18 => Some(PrincipalField::DisabledMembers),
+            19 => Some(PrincipalField::ListOptions),
             _ => None,
         }
     }
@@ -539,6 +548,9 @@ impl PrincipalField {
             PrincipalField::Picture => "picture",
             PrincipalField::Urls => "urls",
             PrincipalField::ExternalMembers => "externalMembers",
+            PrincipalField::Moderators => "moderators",
+            PrincipalField::DisabledMembers => "disabledMembers",
+            PrincipalField::ListOptions => "listOptions",
         }
     }
 
@@ -561,6 +573,9 @@ impl PrincipalField {
             "picture" => Some(PrincipalField::Picture),
             "urls" => Some(PrincipalField::Urls),
             "externalMembers" => Some(PrincipalField::ExternalMembers),
+            "moderators" => Some(PrincipalField::Moderators),
+            "disabledMembers" => Some(PrincipalField::DisabledMembers),
+            "listOptions" => Some(PrincipalField::ListOptions),
             _ => None,
         }
     }
@@ -171,6 +171,48 @@ impl Permission {
             Permission::ImapStore => "Modify message flags via IMAP",
             Permission::ImapSubscribe => "Subscribe to mailboxes via IMAP",
             Permission::ImapThread => "Thread messages via IMAP",
+            Permission::ImapNotify => "Receive mailbox notifications via IMAP NOTIFY",
+            Permission::ImapGetMetadata => "Retrieve mailbox or server annotations via IMAP",
+            Permission::ImapSetMetadata => "Modify mailbox or server annotations via IMAP",
+            Permission::ImapCompress => "Enable compression via IMAP",
+            Permission::ImapReplace => "Replace messages via IMAP",
+            Permission::ImapResetKey => "Reset mailbox access keys via IMAP",
+            Permission::ImapGenUrlAuth => "Generate authorized message URLs via IMAP",
+            Permission::ImapUrlFetch => "Fetch message parts by URL via IMAP",
+            Permission::JmapAddressBookGet => "Retrieve address books via JMAP",
+            Permission::JmapAddressBookSet => "Modify address books via JMAP",
+            Permission::JmapAddressBookChanges => "Track address book changes via JMAP",
+            Permission::JmapAddressBookQuery => "Search address books via JMAP",
+            Permission::JmapAddressBookQueryChanges => "Track address book query changes via JMAP",
+            Permission::JmapContactCardGet => "Retrieve contact cards via JMAP",
+            Permission::JmapContactCardSet => "Modify contact cards via JMAP",
+            Permission::JmapContactCardChanges => "Track contact card changes via JMAP",
+            Permission::JmapContactCardQuery => "Search contact cards via JMAP",
+            Permission::JmapContactCardQueryChanges => "Track contact card query changes via JMAP",
+            Permission::JmapCalendarGet => "Retrieve calendars via JMAP",
+            Permission::JmapCalendarSet => "Modify calendars via JMAP",
+            Permission::JmapCalendarChanges => "Track calendar changes via JMAP",
+            Permission::JmapCalendarQuery => "Search calendars via JMAP",
+            Permission::JmapCalendarQueryChanges => "Track calendar query changes via JMAP",
+            Permission::JmapCalendarEventGet => "Retrieve calendar events via JMAP",
+            Permission::JmapCalendarEventSet => "Modify calendar events via JMAP",
+            Permission::JmapCalendarEventChanges => "Track calendar event changes via JMAP",
+            Permission::JmapCalendarEventQuery => "Search calendar events via JMAP",
+            Permission::JmapCalendarEventQueryChanges => {
+                "Track calendar event query changes via JMAP"
+            }
+            Permission::DavPropFind => "Retrieve properties via WebDAV",
+            Permission::DavPropPatch => "Modify properties via WebDAV",
+            Permission::DavReport => "Run reports via WebDAV",
+            Permission::DavGet => "Retrieve resources via WebDAV",
+            Permission::DavPut => "Store resources via WebDAV",
+            Permission::DavDelete => "Delete resources via WebDAV",
+            Permission::DavMkCol => "Create collections via WebDAV",
+            Permission::QuarantineList => "View quarantined messages",
+            Permission::QuarantineGet => "Retrieve quarantined messages",
+            Permission::QuarantineRelease => "Release quarantined messages",
+            Permission::QuarantineDelete => "Remove quarantined messages",
+            Permission::ManageQuarantine => "Manage own quarantined messages",
             Permission::Pop3Authenticate => "Authenticate via POP3",
             Permission::Pop3List => "List messages via POP3",
             Permission::Pop3Uidl => "Retrieve unique IDs via POP3",
@@ -7,15 +7,15 @@
 use std::{collections::hash_map::Entry, fmt, str::FromStr};
 
 use serde::{
-    Deserializer, Serializer,
     de::{self, IgnoredAny, Visitor},
     ser::SerializeMap,
+    Deserializer, Serializer,
 };
 use store::U64_LEN;
 
 use crate::{
-    Permission, Principal, ROLE_ADMIN, Type,
     backend::internal::{PrincipalField, PrincipalUpdate, PrincipalValue},
+    ListOption, Permission, Principal, Type, ROLE_ADMIN,
 };
 
 impl Principal {
@@ -290,6 +290,11 @@ impl Principal {
         self.fields.contains_key(&key)
     }
 
+    pub fn has_list_option(&self, option: ListOption) -> bool {
+        self.iter_str(PrincipalField::ListOptions)
+            .any(|v| ListOption::parse(v) == Some(option))
+    }
+
     pub fn has_str_value(&self, key: PrincipalField, value: &str) -> bool {
         self.fields.get(&key).is_some_and(|v| match v {
             PrincipalValue::String(v) => v == value,
@@ -651,6 +656,33 @@ impl Type {
     }
 }
 
+impl ListOption {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListOption::Managed => "managed",
+            ListOption::Subscribe => "subscribe",
+            ListOption::Moderated => "moderated",
+            ListOption::OpenPosting => "openPosting",
+            ListOption::ReplyToList => "replyToList",
+            ListOption::RewriteFrom => "rewriteFrom",
+            ListOption::Archive => "archive",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "managed" => Some(ListOption::Managed),
+            "subscribe" => Some(ListOption::Subscribe),
+            "moderated" => Some(ListOption::Moderated),
+            "openPosting" => Some(ListOption::OpenPosting),
+            "replyToList" => Some(ListOption::ReplyToList),
+            "rewriteFrom" => Some(ListOption::RewriteFrom),
+            "archive" => Some(ListOption::Archive),
+            _ => None,
+        }
+    }
+}
+
 impl FromStr for Type {
     type Err = ();
 
@@ -848,7 +880,10 @@ impl<'de> serde::Deserialize<'de> for Principal {
                         | PrincipalField::EnabledPermissions
                         | PrincipalField::DisabledPermissions
                         | PrincipalField::Urls
-                        | PrincipalField::ExternalMembers => {
+                        | PrincipalField::ExternalMembers
+                        | PrincipalField::Moderators
+                        | PrincipalField::DisabledMembers
+                        | PrincipalField::ListOptions => {
                             match map.next_value::<StringOrMany>()? {
                                 StringOrMany::One(v) => PrincipalValue::StringList(vec![v]),
                                 StringOrMany::Many(v) => {
@@ -1076,6 +1111,42 @@ impl Permission {
                 | Permission::ImapStore
                 | Permission::ImapSubscribe
                 | Permission::ImapThread
+                | Permission::ImapNotify
+                | Permission::ImapGetMetadata
+                | Permission::ImapSetMetadata
+                | Permission::ImapCompress
+                | Permission::ImapReplace
+                | Permission::ImapResetKey
+                | Permission::ImapGenUrlAuth
+                | Permission::ImapUrlFetch
+                | Permission::JmapAddressBookGet
+                | Permission::JmapAddressBookSet
+                | Permission::JmapAddressBookChanges
+                | Permission::JmapAddressBookQuery
+                | Permission::JmapAddressBookQueryChanges
+                | Permission::JmapContactCardGet
+                | Permission::JmapContactCardSet
+                | Permission::JmapContactCardChanges
+                | Permission::JmapContactCardQuery
+                | Permission::JmapContactCardQueryChanges
+                | Permission::JmapCalendarGet
+                | Permission::JmapCalendarSet
+                | Permission::JmapCalendarChanges
+                | Permission::JmapCalendarQuery
+                | Permission::JmapCalendarQueryChanges
+                | Permission::JmapCalendarEventGet
+                | Permission::JmapCalendarEventSet
+                | Permission::JmapCalendarEventChanges
+                | Permission::JmapCalendarEventQuery
+                | Permission::JmapCalendarEventQueryChanges
+                | Permission::DavPropFind
+                | Permission::DavPropPatch
+                | Permission::DavReport
+                | Permission::DavGet
+                | Permission::DavPut
+                | Permission::DavDelete
+                | Permission::DavMkCol
+                | Permission::ManageQuarantine
                 | Permission::Pop3Authenticate
                 | Permission::Pop3List
                 | Permission::Pop3Uidl
@@ -1113,6 +1184,10 @@ impl Permission {
                 | Permission::IncomingReportList
                 | Permission::IncomingReportGet
                 | Permission::IncomingReportDelete
+                | Permission::QuarantineList
+                | Permission::QuarantineGet
+                | Permission::QuarantineRelease
+                | Permission::QuarantineDelete
                 | Permission::IndividualList
                 | Permission::IndividualGet
                 | Permission::IndividualUpdate
@@ -58,6 +58,17 @@ pub enum Type {
 
 pub const MAX_TYPE_ID: usize = 11;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ListOption {
+    Managed,
+    Subscribe,
+    Moderated,
+    OpenPosting,
+    ReplyToList,
+    RewriteFrom,
+    Archive,
+}
+
 #[derive(
     Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize, EnumMethods,
 )]
@@ -266,6 +277,46 @@ pub enum Permission {
     AiModelInteract,
     Troubleshoot,
     SpamFilterClassify,
+    ImapNotify,
+    ImapGetMetadata,
+    ImapSetMetadata,
+    ImapCompress,
+    ImapReplace,
+    ImapResetKey,
+    ImapGenUrlAuth,
+    ImapUrlFetch,
+    JmapAddressBookGet,
+    JmapAddressBookSet,
+    JmapAddressBookChanges,
+    JmapAddressBookQuery,
+    JmapAddressBookQueryChanges,
+    JmapContactCardGet,
+    JmapContactCardSet,
+    JmapContactCardChanges,
+    JmapContactCardQuery,
+    JmapContactCardQueryChanges,
+    JmapCalendarGet,
+    JmapCalendarSet,
+    JmapCalendarChanges,
+    JmapCalendarQuery,
+    JmapCalendarQueryChanges,
+    JmapCalendarEventGet,
+    JmapCalendarEventSet,
+    JmapCalendarEventChanges,
+    JmapCalendarEventQuery,
+    JmapCalendarEventQueryChanges,
+    DavPropFind,
+    DavPropPatch,
+    DavReport,
+    DavGet,
+    DavPut,
+    DavDelete,
+    DavMkCol,
+    QuarantineList,
+    QuarantineGet,
+    QuarantineRelease,
+    QuarantineDelete,
+    ManageQuarantine,
     // WARNING: add new ids at the end (TODO: use static ids)
 }
 
@@ -13,7 +13,9 @@ use store::ahash::AHashMap;
 use utils::BlobHash;
 
 use crate::{
+    groupware::itip::ItipScheduling,
     ingest::{EmailIngest, IngestEmail, IngestSource},
+    list::MailingListManager,
     mailbox::INBOX_ID,
     sieve::SieveScriptIngest,
 };
@@ -21,12 +23,39 @@ use crate::{
 #[derive(Debug)]
 pub struct IngestMessage {
     pub sender_address: String,
+    pub sender_authenticated: bool,
+    pub spf_pass: bool,
+    pub dmarc_pass: bool,
     pub recipients: Vec<String>,
     pub message_blob: BlobHash,
     pub message_size: usize,
     pub session_id: u64,
 }
 
+impl IngestMessage {
+    // Envelope senders can be trusted when the session was authenticated
+    // or the sender's domain passed SPF
+    pub fn is_sender_verified(&self) -> bool {
+        self.sender_authenticated || self.spf_pass
+    }
+
+    // Returns whether the address belongs to a verified envelope sender
+    // or to a From: header that passed DMARC
+    pub fn is_verified_address(&self, address: &str, raw_message: &[u8]) -> bool {
+        (self.is_sender_verified() && self.sender_address.eq_ignore_ascii_case(address))
+            || (self.dmarc_pass
+                && MessageParser::new()
+                    .parse_headers(raw_message)
+                    .is_some_and(|message| {
+                        message
+                            .from()
+                            .and_then(|from| from.first())
+                            .and_then(|from| from.address())
+                            .is_some_and(|from| from.eq_ignore_ascii_case(address))
+                    }))
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum LocalDeliveryStatus {
     Success,
@@ -176,9 +205,9 @@ impl MailDelivery for Server {
             autogenerated: Vec::new(),
         };
 
-        for rcpt in message.recipients {
+        for rcpt in &message.recipients {
             let uid = match self
-                .email_to_id(&self.core.storage.directory, &rcpt, message.session_id)
+                .email_to_id(&self.core.storage.directory, rcpt, message.session_id)
                 .await
             {
                 Ok(Some(uid)) => uid,
@@ -193,7 +222,7 @@ impl MailDelivery for Server {
                 Err(err) => {
                     trc::error!(err
                         .details("Failed to lookup recipient.")
-                        .ctx(trc::Key::To, rcpt)
+                        .ctx(trc::Key::To, rcpt.to_string())
                         .span_id(message.session_id)
                         .caused_by(trc::location!()));
                     result.status.push(LocalDeliveryStatus::TemporaryFailure {
@@ -202,6 +231,30 @@ impl MailDelivery for Server {
                     continue;
                 }
             };
+
+            // Managed mailing lists handle posts and commands themselves
+            match self
+                .mailing_list_deliver(uid, rcpt, &message, &raw_message, &mut result.autogenerated)
+                .await
+            {
+                Ok(Some(status)) => {
+                    result.status.push(status);
+                    continue;
+                }
+                Ok(None) => (),
+                Err(err) => {
+                    trc::error!(err
+                        .details("Failed to process mailing list message.")
+                        .ctx(trc::Key::To, rcpt.to_string())
+                        .span_id(message.session_id)
+                        .caused_by(trc::location!()));
+                    result.status.push(LocalDeliveryStatus::TemporaryFailure {
+                        reason: "Transient server failure.".into(),
+                    });
+                    continue;
+                }
+            }
+
             if let Some(status) = uids.get(&uid).and_then(|pos| result.status.get(*pos)) {
                 result.status.push(status.clone());
                 continue;
@@ -225,11 +278,12 @@ impl MailDelivery for Server {
                                 mailbox_ids: vec![INBOX_ID],
                                 keywords: vec![],
                                 received_at: None,
-                                source: IngestSource::Smtp { deliver_to: &rcpt },
+                                source: IngestSource::Smtp { deliver_to: rcpt },
                                 spam_classify: access_token
                                     .has_permission(Permission::SpamFilterClassify),
                                 spam_train: self.email_bayes_can_train(&access_token),
                                 session_id: message.session_id,
+                                replace: None,
                             })
                             .await
                         }
@@ -238,7 +292,7 @@ impl MailDelivery for Server {
                                 &access_token,
                                 &raw_message,
                                 &message.sender_address,
-                                &rcpt,
+                                rcpt,
                                 message.session_id,
                                 active_script,
                                 &mut result.autogenerated,
@@ -266,6 +320,23 @@ impl MailDelivery for Server {
                         .await;
                     }
 
+                    // Resources reply to scheduling requests
+                    if let Some(itip) = &ingested_message.itip {
+                        match self
+                            .itip_resource_reply(uid, rcpt, itip, &message, &raw_message)
+                            .await
+                        {
+                            Ok(Some(reply)) => result.autogenerated.push(reply),
+                            Ok(None) => (),
+                            Err(err) => {
+                                trc::error!(err
+                                    .details("Failed to process iTIP message.")
+                                    .span_id(message.session_id)
+                                    .caused_by(trc::location!()));
+                            }
+                        }
+                    }
+
                     LocalDeliveryStatus::Success
                 }
                 Err(err) => {
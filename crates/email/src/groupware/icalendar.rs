@@ -0,0 +1,313 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use chrono::{NaiveDate, NaiveDateTime, TimeDelta};
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+use utils::codec::content_line::{escape_text, write_line, ContentLine};
+
+use super::{get_text, get_value, parse_duration, vcard::IdMap};
+
+pub fn parse_icalendar(text: &str) -> Result<Object<Value>, &'static str> {
+    let mut lines = ContentLine::parse_all(text).into_iter();
+    if !lines
+        .next()
+        .is_some_and(|line| line.is("BEGIN", "VCALENDAR"))
+    {
+        return Err("Expected an iCalendar object");
+    }
+
+    // Only the first VEVENT is mapped, recurrence overrides and other
+    // components are preserved in the original iCalendar data.
+    let mut has_event = false;
+    let mut depth = 0;
+    let mut in_event = false;
+    let mut is_complete = false;
+    let mut start = None;
+    let mut end = None;
+    let mut duration = None;
+    let mut locations = IdMap::new("l", "name");
+    let mut participants = IdMap::new("p", "name");
+    let mut item = Object::with_capacity(8).with_property(Property::parse("@type"), "Event");
+
+    for line in lines {
+        match line.name.as_str() {
+            "BEGIN" => {
+                if depth == 0 && !has_event && line.value.eq_ignore_ascii_case("VEVENT") {
+                    in_event = true;
+                }
+                depth += 1;
+            }
+            "END" if depth == 0 => {
+                is_complete = line.value.eq_ignore_ascii_case("VCALENDAR");
+                break;
+            }
+            "END" => {
+                depth -= 1;
+                if depth == 0 && in_event {
+                    in_event = false;
+                    has_event = true;
+                }
+            }
+            _ if !in_event || depth != 1 => (),
+            "UID" => {
+                item.set(Property::Uid, line.text());
+            }
+            "SUMMARY" => {
+                item.set(Property::Title, line.text());
+            }
+            "DESCRIPTION" => {
+                item.set(Property::Description, line.text());
+            }
+            "LOCATION" => locations.push(line.text()),
+            "ORGANIZER" | "ATTENDEE" => participants.push_object(participant(&line)),
+            "SEQUENCE" => {
+                if let Ok(sequence) = line.value.trim().parse::<u64>() {
+                    item.set(Property::parse("sequence"), Value::UnsignedInt(sequence));
+                }
+            }
+            "STATUS" => {
+                item.set(Property::parse("status"), line.text().to_ascii_lowercase());
+            }
+            "DTSTART" => start = Some(line),
+            "DTEND" => end = Some(line),
+            "DURATION" => duration = Some(line.text()),
+            _ => (),
+        }
+    }
+
+    if !is_complete {
+        return Err("Unterminated iCalendar object");
+    } else if !has_event {
+        return Err("Only VEVENT components are supported");
+    }
+
+    if let Some(start) = start {
+        let (start_dt, is_utc, is_date) = parse_date_time(&start.value).ok_or("Invalid DTSTART")?;
+        item.set(
+            Property::parse("start"),
+            start_dt.format("%Y-%m-%dT%H:%M:%S").to_string(),
+        );
+        if let Some(tz) = start.param("TZID") {
+            item.set(
+                Property::parse("timeZone"),
+                tz.trim_matches('"').to_string(),
+            );
+        } else if is_utc {
+            item.set(Property::parse("timeZone"), "Etc/UTC");
+        }
+        if is_date {
+            item.set(Property::parse("showWithoutTime"), true);
+        }
+
+        let duration = duration
+            .filter(|duration| parse_duration(duration).is_some())
+            .or_else(|| {
+                end.and_then(|end| parse_date_time(&end.value))
+                    .map(|(end_dt, _, _)| format_duration(end_dt - start_dt))
+            })
+            .or_else(|| is_date.then(|| "P1D".to_string()));
+        if let Some(duration) = duration {
+            item.set(Property::parse("duration"), duration);
+        }
+    }
+    if let Some(locations) = locations.build() {
+        item.set(Property::parse("locations"), locations);
+    }
+    if let Some(participants) = participants.build() {
+        item.set(Property::parse("participants"), participants);
+    }
+
+    Ok(item)
+}
+
+pub fn build_icalendar(event: &Object<Value>) -> String {
+    let mut buf = String::with_capacity(256);
+    write_line(&mut buf, "BEGIN", &[], "VCALENDAR");
+    write_line(&mut buf, "VERSION", &[], "2.0");
+    write_line(
+        &mut buf,
+        "PRODID",
+        &[],
+        "-//Stalwart Labs Ltd.//Stalwart Server//EN",
+    );
+    write_line(&mut buf, "BEGIN", &[], "VEVENT");
+    if let Some(uid) = get_text(event, "uid") {
+        write_line(&mut buf, "UID", &[], &escape_text(uid));
+    }
+    write_line(
+        &mut buf,
+        "DTSTAMP",
+        &[],
+        &chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+    );
+    if let Some(start) = get_text(event, "start")
+        .and_then(|start| NaiveDateTime::parse_from_str(start, "%Y-%m-%dT%H:%M:%S").ok())
+    {
+        let time_zone = get_text(event, "timeZone");
+        if get_value(event, "showWithoutTime").and_then(|v| v.as_bool()) == Some(true) {
+            write_line(
+                &mut buf,
+                "DTSTART",
+                &[("VALUE", "DATE")],
+                &start.format("%Y%m%d").to_string(),
+            );
+        } else if matches!(time_zone, Some("Etc/UTC" | "UTC")) {
+            write_line(
+                &mut buf,
+                "DTSTART",
+                &[],
+                &start.format("%Y%m%dT%H%M%SZ").to_string(),
+            );
+        } else if let Some(time_zone) = time_zone {
+            write_line(
+                &mut buf,
+                "DTSTART",
+                &[("TZID", time_zone)],
+                &start.format("%Y%m%dT%H%M%S").to_string(),
+            );
+        } else {
+            write_line(
+                &mut buf,
+                "DTSTART",
+                &[],
+                &start.format("%Y%m%dT%H%M%S").to_string(),
+            );
+        }
+    }
+    if let Some(duration) = get_text(event, "duration") {
+        write_line(&mut buf, "DURATION", &[], duration);
+    }
+    for (property, name) in [("SUMMARY", "title"), ("DESCRIPTION", "description")] {
+        if let Some(value) = get_text(event, name) {
+            write_line(&mut buf, property, &[], &escape_text(value));
+        }
+    }
+    if let Some(locations) = get_value(event, "locations").and_then(|value| value.as_obj()) {
+        for location in locations.properties.values() {
+            if let Some(name) = location.as_obj().and_then(|entry| get_text(entry, "name")) {
+                write_line(&mut buf, "LOCATION", &[], &escape_text(name));
+            }
+        }
+    }
+    if let Some(status) = get_text(event, "status") {
+        write_line(&mut buf, "STATUS", &[], &status.to_ascii_uppercase());
+    }
+    if let Some(sequence) = get_value(event, "sequence").and_then(|value| value.as_uint()) {
+        write_line(&mut buf, "SEQUENCE", &[], &sequence.to_string());
+    }
+    if let Some(participants) = get_value(event, "participants").and_then(|value| value.as_obj()) {
+        for participant in participants
+            .properties
+            .values()
+            .filter_map(|participant| participant.as_obj())
+        {
+            let Some(address) = get_value(participant, "sendTo")
+                .and_then(|value| value.as_obj())
+                .and_then(|send_to| get_text(send_to, "imip"))
+            else {
+                continue;
+            };
+            let mut params = Vec::with_capacity(2);
+            if let Some(name) = get_text(participant, "name") {
+                params.push(("CN", name));
+            }
+            if get_value(participant, "roles")
+                .and_then(|value| value.as_obj())
+                .and_then(|roles| get_value(roles, "owner"))
+                .and_then(|value| value.as_bool())
+                == Some(true)
+            {
+                write_line(&mut buf, "ORGANIZER", &params, address);
+            } else {
+                let status = get_text(participant, "participationStatus")
+                    .map(|status| status.to_ascii_uppercase());
+                if let Some(status) = &status {
+                    params.push(("PARTSTAT", status));
+                }
+                write_line(&mut buf, "ATTENDEE", &params, address);
+            }
+        }
+    }
+    write_line(&mut buf, "END", &[], "VEVENT");
+    write_line(&mut buf, "END", &[], "VCALENDAR");
+    buf
+}
+
+// Organizers are mapped to participants with the owner role
+fn participant(line: &ContentLine) -> Object<Value> {
+    let is_organizer = line.name == "ORGANIZER";
+    let mut participant = Object::with_capacity(5)
+        .with_property(Property::parse("@type"), "Participant")
+        .with_property(
+            Property::parse("sendTo"),
+            Object::with_capacity(1)
+                .with_property(Property::parse("imip"), line.value.trim().to_string()),
+        )
+        .with_property(
+            Property::parse("roles"),
+            Object::with_capacity(1).with_property(
+                Property::parse(if is_organizer { "owner" } else { "attendee" }),
+                true,
+            ),
+        );
+    if let Some(name) = line.param("CN") {
+        participant.set(Property::parse("name"), name.trim_matches('"').to_string());
+    }
+    if let Some(status) = line.param("PARTSTAT").filter(|_| !is_organizer) {
+        participant.set(
+            Property::parse("participationStatus"),
+            status.to_ascii_lowercase(),
+        );
+    }
+    participant
+}
+
+// Returns the parsed date, whether it is in UTC and whether it is a DATE value
+pub fn parse_date_time(value: &str) -> Option<(NaiveDateTime, bool, bool)> {
+    let value = value.trim();
+    let (value, is_utc) = value
+        .strip_suffix(['Z', 'z'])
+        .map_or((value, false), |value| (value, true));
+    if value.len() == 8 {
+        NaiveDate::parse_from_str(value, "%Y%m%d")
+            .ok()?
+            .and_hms_opt(0, 0, 0)
+            .map(|date| (date, is_utc, true))
+    } else {
+        NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+            .ok()
+            .map(|date| (date, is_utc, false))
+    }
+}
+
+pub fn format_duration(duration: TimeDelta) -> String {
+    let seconds = duration.num_seconds().max(0);
+    let (days, seconds) = (seconds / 86400, seconds % 86400);
+    let (hours, minutes, seconds) = (seconds / 3600, (seconds % 3600) / 60, seconds % 60);
+    let mut result = String::from("P");
+
+    if days > 0 {
+        result.push_str(&format!("{days}D"));
+    }
+    if hours > 0 || minutes > 0 || seconds > 0 || days == 0 {
+        result.push('T');
+        if hours > 0 {
+            result.push_str(&format!("{hours}H"));
+        }
+        if minutes > 0 {
+            result.push_str(&format!("{minutes}M"));
+        }
+        if seconds > 0 || (hours == 0 && minutes == 0) {
+            result.push_str(&format!("{seconds}S"));
+        }
+    }
+
+    result
+}
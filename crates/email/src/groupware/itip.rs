@@ -0,0 +1,576 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    itip::{calendar_address, Itip, ItipAttendee},
+    Server,
+};
+use directory::{backend::internal::PrincipalField, QueryBy, Type};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{collection::Collection, property::Property, state::StateChange, value::Value},
+};
+use mail_builder::{
+    headers::{content_type::ContentType, HeaderType},
+    mime::{BodyPart, MimePart},
+    MessageBuilder,
+};
+use store::{
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder},
+};
+use trc::{AddContext, ItipEvent};
+use utils::codec::content_line::{escape_text, write_line};
+
+use crate::delivery::{AutogeneratedMessage, IngestMessage};
+
+use super::{
+    get_text, get_value, icalendar::parse_icalendar, item_tombstones, object_changes, CALENDARS,
+};
+use std::future::Future;
+
+pub trait ItipScheduling: Sync + Send {
+    fn itip_resource_reply(
+        &self,
+        account_id: u32,
+        deliver_to: &str,
+        itip: &Itip,
+        message: &IngestMessage,
+        raw_message: &[u8],
+    ) -> impl Future<Output = trc::Result<Option<AutogeneratedMessage>>> + Send;
+}
+
+struct ScheduledEvent {
+    document_id: u32,
+    object: HashedValue<Object<Value>>,
+}
+
+impl ItipScheduling for Server {
+    // Resource and location principals accept requests when their calendars
+    // are free during the requested time range and decline them otherwise.
+    async fn itip_resource_reply(
+        &self,
+        account_id: u32,
+        deliver_to: &str,
+        itip: &Itip,
+        message: &IngestMessage,
+        raw_message: &[u8],
+    ) -> trc::Result<Option<AutogeneratedMessage>> {
+        let session_id = message.session_id;
+        let Some(mut principal) = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|principal| matches!(principal.typ(), Type::Resource | Type::Location))
+        else {
+            return Ok(None);
+        };
+        let name = principal
+            .description()
+            .unwrap_or_else(|| principal.name())
+            .to_string();
+        let mut addresses = principal
+            .take_str_array(PrincipalField::Emails)
+            .unwrap_or_default();
+        if !addresses
+            .iter()
+            .any(|address| address.eq_ignore_ascii_case(deliver_to))
+        {
+            addresses.push(deliver_to.to_lowercase());
+        }
+
+        // Ignore messages sent by the resource or not addressed to it
+        let (Some(organizer), Some(attendee)) = (
+            itip.organizer.as_deref().filter(|organizer| {
+                !addresses
+                    .iter()
+                    .any(|address| address.eq_ignore_ascii_case(organizer))
+            }),
+            itip.attendee(&addresses),
+        ) else {
+            return Ok(None);
+        };
+
+        // Requests and cancellations must come from the organizer
+        if matches!(itip.method.as_str(), "REQUEST" | "CANCEL")
+            && !message.is_verified_address(organizer, raw_message)
+        {
+            trc::event!(
+                Itip(ItipEvent::Error),
+                SpanId = session_id,
+                AccountId = account_id,
+                Id = itip.uid.clone(),
+                From = organizer.to_string(),
+                Reason = "Organizer is not an authenticated sender",
+            );
+            return Ok(None);
+        }
+
+        // Obtain previously scheduled instances of this event
+        let mut scheduled = Vec::new();
+        for document_id in self
+            .core
+            .storage
+            .data
+            .filter(
+                account_id,
+                Collection::CalendarEvent,
+                vec![Filter::eq(Property::Uid, itip.uid.as_str())],
+            )
+            .await
+            .caused_by(trc::location!())?
+            .results
+        {
+            if let Some(object) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                scheduled.push(ScheduledEvent {
+                    document_id,
+                    object,
+                });
+            }
+        }
+
+        // Only the organizer of an event is allowed to modify it
+        let is_organizer = scheduled
+            .iter()
+            .all(|event| event_organizer(&event.object.inner).as_deref() == Some(organizer));
+        let mut changes = ChangeLogBuilder::new();
+
+        match itip.method.as_str() {
+            "REQUEST" => {
+                let mut event = match parse_icalendar(&itip.data) {
+                    Ok(event) => event,
+                    Err(reason) => {
+                        trc::event!(
+                            Itip(ItipEvent::Error),
+                            SpanId = session_id,
+                            AccountId = account_id,
+                            Id = itip.uid.clone(),
+                            Reason = reason,
+                        );
+                        return Ok(None);
+                    }
+                };
+                CALENDARS.derive_properties(&mut event);
+                let (Some(start), Some(end)) = (
+                    event.get(&Property::UtcStart).as_uint(),
+                    event.get(&Property::UtcEnd).as_uint(),
+                ) else {
+                    trc::event!(
+                        Itip(ItipEvent::Error),
+                        SpanId = session_id,
+                        AccountId = account_id,
+                        Id = itip.uid.clone(),
+                        Reason = "Event has no start time",
+                    );
+                    return Ok(None);
+                };
+
+                // Look for overlapping events in the resource's calendars
+                let is_available = is_organizer
+                    && self
+                        .core
+                        .storage
+                        .data
+                        .filter(
+                            account_id,
+                            Collection::CalendarEvent,
+                            vec![
+                                Filter::gt(Property::UtcEnd, start),
+                                Filter::lt(Property::UtcStart, end),
+                            ],
+                        )
+                        .await
+                        .caused_by(trc::location!())?
+                        .results
+                        .iter()
+                        .all(|document_id| {
+                            scheduled
+                                .iter()
+                                .any(|event| event.document_id == document_id)
+                        });
+
+                if is_available {
+                    set_participation_status(&mut event, &addresses, "accepted");
+                    CALENDARS.derive_properties(&mut event);
+                    self.itip_store_event(account_id, event, scheduled, &mut changes)
+                        .await?;
+                } else if is_organizer {
+                    // Rescheduled events that no longer fit are removed
+                    self.itip_delete_events(account_id, scheduled, &mut changes)
+                        .await?;
+                }
+                self.itip_commit_changes(account_id, changes).await?;
+
+                trc::event!(
+                    Itip(if is_available {
+                        ItipEvent::Accepted
+                    } else {
+                        ItipEvent::Declined
+                    }),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    Id = itip.uid.clone(),
+                    From = organizer.to_string(),
+                );
+
+                Ok(Some(build_reply(
+                    &name,
+                    organizer,
+                    attendee,
+                    itip,
+                    is_available,
+                )))
+            }
+            "CANCEL" if is_organizer && !scheduled.is_empty() => {
+                self.itip_delete_events(account_id, scheduled, &mut changes)
+                    .await?;
+                self.itip_commit_changes(account_id, changes).await?;
+
+                trc::event!(
+                    Itip(ItipEvent::Cancelled),
+                    SpanId = session_id,
+                    AccountId = account_id,
+                    Id = itip.uid.clone(),
+                    From = organizer.to_string(),
+                );
+
+                Ok(None)
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+trait ItipStore: Sync + Send {
+    fn itip_store_event(
+        &self,
+        account_id: u32,
+        event: Object<Value>,
+        scheduled: Vec<ScheduledEvent>,
+        changes: &mut ChangeLogBuilder,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn itip_delete_events(
+        &self,
+        account_id: u32,
+        scheduled: Vec<ScheduledEvent>,
+        changes: &mut ChangeLogBuilder,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn itip_default_calendar(
+        &self,
+        account_id: u32,
+        changes: &mut ChangeLogBuilder,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn itip_commit_changes(
+        &self,
+        account_id: u32,
+        changes: ChangeLogBuilder,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl ItipStore for Server {
+    async fn itip_store_event(
+        &self,
+        account_id: u32,
+        mut event: Object<Value>,
+        scheduled: Vec<ScheduledEvent>,
+        changes: &mut ChangeLogBuilder,
+    ) -> trc::Result<()> {
+        let mut scheduled = scheduled.into_iter();
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::CalendarEvent);
+
+        if let Some(current) = scheduled.next() {
+            // Updates keep the calendars the event was filed into
+            event.set(
+                Property::CalendarIds,
+                current.object.inner.get(&Property::CalendarIds).clone(),
+            );
+            let event_changes = object_changes(&current.object.inner, event);
+            if !event_changes.properties.is_empty() {
+                batch
+                    .update_document(current.document_id)
+                    .custom(validate_event(
+                        ObjectIndexBuilder::new(CALENDARS.item_schema)
+                            .with_changes(event_changes)
+                            .with_current(current.object),
+                    )?);
+                self.core
+                    .storage
+                    .data
+                    .write(batch.build())
+                    .await
+                    .caused_by(trc::location!())?;
+                changes.log_update(Collection::CalendarEvent, current.document_id);
+            }
+        } else {
+            let calendar_id = self.itip_default_calendar(account_id, changes).await?;
+            event.set(
+                Property::CalendarIds,
+                Value::List(vec![Value::Id(calendar_id.into())]),
+            );
+            batch.create_document().custom(validate_event(
+                ObjectIndexBuilder::new(CALENDARS.item_schema).with_changes(event),
+            )?);
+            let document_id = self
+                .core
+                .storage
+                .data
+                .write(batch.build())
+                .await
+                .and_then(|ids| ids.last_document_id())
+                .caused_by(trc::location!())?;
+            changes.log_insert(Collection::CalendarEvent, document_id);
+        }
+
+        // Duplicate instances of the same event are removed
+        self.itip_delete_events(account_id, scheduled.collect(), changes)
+            .await
+    }
+
+    async fn itip_delete_events(
+        &self,
+        account_id: u32,
+        scheduled: Vec<ScheduledEvent>,
+        changes: &mut ChangeLogBuilder,
+    ) -> trc::Result<()> {
+        for event in scheduled {
+            let tombstones = item_tombstones(
+                &CALENDARS,
+                &event.object,
+                self.get_property::<Value>(
+                    account_id,
+                    Collection::CalendarEvent,
+                    event.document_id,
+                    Property::DavTombstones,
+                )
+                .await?,
+            );
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::CalendarEvent)
+                .delete_document(event.document_id)
+                .custom(ObjectIndexBuilder::new(CALENDARS.item_schema).with_current(event.object))
+                .value(Property::DavTombstones, tombstones, 0);
+            self.core
+                .storage
+                .data
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+            changes.log_delete(Collection::CalendarEvent, event.document_id);
+        }
+
+        Ok(())
+    }
+
+    async fn itip_default_calendar(
+        &self,
+        account_id: u32,
+        changes: &mut ChangeLogBuilder,
+    ) -> trc::Result<u32> {
+        if let Some(calendar_id) = self
+            .get_document_ids(account_id, Collection::Calendar)
+            .await?
+            .and_then(|ids| ids.min())
+        {
+            return Ok(calendar_id);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Calendar)
+            .create_document()
+            .custom(
+                ObjectIndexBuilder::new(CALENDARS.container_schema)
+                    .with_changes(CALENDARS.default_container()),
+            );
+        let calendar_id = self
+            .core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .and_then(|ids| ids.last_document_id())
+            .caused_by(trc::location!())?;
+        changes.log_insert(Collection::Calendar, calendar_id);
+
+        Ok(calendar_id)
+    }
+
+    async fn itip_commit_changes(
+        &self,
+        account_id: u32,
+        changes: ChangeLogBuilder,
+    ) -> trc::Result<()> {
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            self.broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(CALENDARS.container_type, change_id)
+                    .with_change(CALENDARS.item_type, change_id),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+}
+
+fn validate_event(builder: ObjectIndexBuilder) -> trc::Result<ObjectIndexBuilder> {
+    builder.validate().map_err(|err| {
+        ItipEvent::Error
+            .into_err()
+            .details("Invalid calendar event")
+            .reason(err.description.unwrap_or_default())
+            .caused_by(trc::location!())
+    })
+}
+
+fn event_organizer(event: &Object<Value>) -> Option<String> {
+    get_value(event, "participants")?
+        .as_obj()?
+        .properties
+        .values()
+        .filter_map(|participant| participant.as_obj())
+        .find(|participant| {
+            get_value(participant, "roles")
+                .and_then(|roles| roles.as_obj())
+                .and_then(|roles| get_value(roles, "owner"))
+                .and_then(|owner| owner.as_bool())
+                == Some(true)
+        })
+        .and_then(participant_address)
+}
+
+fn participant_address(participant: &Object<Value>) -> Option<String> {
+    get_value(participant, "sendTo")
+        .and_then(|send_to| send_to.as_obj())
+        .and_then(|send_to| get_text(send_to, "imip"))
+        .map(calendar_address)
+}
+
+fn set_participation_status(event: &mut Object<Value>, addresses: &[String], status: &str) {
+    let Some(participants) = event
+        .properties
+        .iter_mut()
+        .find(|(property, _)| property.to_string() == "participants")
+        .and_then(|(_, value)| value.as_obj_mut())
+    else {
+        return;
+    };
+
+    for participant in participants
+        .properties
+        .values_mut()
+        .filter_map(|participant| participant.as_obj_mut())
+    {
+        if participant_address(participant).is_some_and(|address| {
+            addresses
+                .iter()
+                .any(|resource| resource.eq_ignore_ascii_case(&address))
+        }) {
+            participant.set(Property::parse("participationStatus"), status);
+        }
+    }
+}
+
+fn build_reply(
+    name: &str,
+    organizer: &str,
+    attendee: &ItipAttendee,
+    itip: &Itip,
+    is_accepted: bool,
+) -> AutogeneratedMessage {
+    let (status, verb) = if is_accepted {
+        ("ACCEPTED", "Accepted")
+    } else {
+        ("DECLINED", "Declined")
+    };
+    let summary = itip.summary.as_deref().unwrap_or("Event");
+
+    let mut ical = String::with_capacity(512);
+    write_line(&mut ical, "BEGIN", &[], "VCALENDAR");
+    write_line(&mut ical, "VERSION", &[], "2.0");
+    write_line(
+        &mut ical,
+        "PRODID",
+        &[],
+        "-//Stalwart Labs Ltd.//Stalwart Server//EN",
+    );
+    write_line(&mut ical, "METHOD", &[], "REPLY");
+    write_line(&mut ical, "BEGIN", &[], "VEVENT");
+    write_line(&mut ical, "UID", &[], &escape_text(&itip.uid));
+    write_line(&mut ical, "SEQUENCE", &[], &itip.sequence.to_string());
+    write_line(
+        &mut ical,
+        "DTSTAMP",
+        &[],
+        &chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(),
+    );
+    write_line(&mut ical, "ORGANIZER", &[], &format!("mailto:{organizer}"));
+    write_line(
+        &mut ical,
+        "ATTENDEE",
+        &[("CN", name), ("PARTSTAT", status)],
+        &format!("mailto:{}", attendee.address),
+    );
+    write_line(&mut ical, "SUMMARY", &[], &escape_text(summary));
+    write_line(&mut ical, "END", &[], "VEVENT");
+    write_line(&mut ical, "END", &[], "VCALENDAR");
+
+    let message = MessageBuilder::new()
+        .from((name, attendee.address.as_str()))
+        .header("To", HeaderType::Text(organizer.into()))
+        .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+        .subject(format!("{verb}: {summary}"))
+        .body(MimePart::new(
+            ContentType::new("multipart/alternative"),
+            BodyPart::Multipart(vec![
+                MimePart::new(
+                    ContentType::new("text/plain").attribute("charset", "utf-8"),
+                    BodyPart::Text(
+                        format!(
+                            "{name} has {} your invitation to \"{summary}\".\r\n",
+                            verb.to_lowercase()
+                        )
+                        .into(),
+                    ),
+                ),
+                MimePart::new(
+                    ContentType::new("text/calendar")
+                        .attribute("method", "REPLY")
+                        .attribute("charset", "utf-8"),
+                    BodyPart::Text(ical.into()),
+                ),
+            ]),
+        ))
+        .write_to_vec()
+        .unwrap_or_default();
+
+    AutogeneratedMessage {
+        sender_address: attendee.address.clone(),
+        recipients: vec![organizer.to_string()],
+        message,
+    }
+}
@@ -0,0 +1,338 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use chrono::{NaiveDateTime, TimeDelta};
+use jmap_proto::{
+    error::set::SetErrorType,
+    object::{
+        index::{IndexAs, IndexProperty},
+        Object,
+    },
+    types::{collection::Collection, property::Property, type_state::DataType, value::Value},
+};
+
+pub mod icalendar;
+pub mod itip;
+pub mod vcard;
+
+// Address books and calendars share the same storage model: a container
+// object with ACLs and free-form JSContact/JSCalendar items linked to one
+// or more containers.
+pub struct GroupwareType {
+    pub container: Collection,
+    pub item: Collection,
+    pub container_type: DataType,
+    pub item_type: DataType,
+    pub container_ids: Property,
+    pub container_schema: &'static [IndexProperty],
+    pub item_schema: &'static [IndexProperty],
+    pub has_contents: SetErrorType,
+    pub container_name: &'static str,
+    pub item_name: &'static str,
+    pub text_properties: &'static [&'static str],
+    pub dav_path: &'static str,
+    pub dav_extension: &'static str,
+    pub dav_content_type: &'static str,
+}
+
+pub static CONTACTS: GroupwareType = GroupwareType {
+    container: Collection::AddressBook,
+    item: Collection::ContactCard,
+    container_type: DataType::AddressBook,
+    item_type: DataType::ContactCard,
+    container_ids: Property::AddressBookIds,
+    container_schema: CONTAINER_SCHEMA,
+    item_schema: CONTACT_CARD_SCHEMA,
+    has_contents: SetErrorType::AddressBookHasContents,
+    container_name: "address book",
+    item_name: "contact card",
+    text_properties: &[
+        "name",
+        "nicknames",
+        "emails",
+        "phones",
+        "organizations",
+        "titles",
+        "notes",
+    ],
+    dav_path: "card",
+    dav_extension: "vcf",
+    dav_content_type: "text/vcard; charset=utf-8",
+};
+
+pub static CALENDARS: GroupwareType = GroupwareType {
+    container: Collection::Calendar,
+    item: Collection::CalendarEvent,
+    container_type: DataType::Calendar,
+    item_type: DataType::CalendarEvent,
+    container_ids: Property::CalendarIds,
+    container_schema: CONTAINER_SCHEMA,
+    item_schema: CALENDAR_EVENT_SCHEMA,
+    has_contents: SetErrorType::CalendarHasEvent,
+    container_name: "calendar",
+    item_name: "calendar event",
+    text_properties: &["title", "description", "locations", "participants"],
+    dav_path: "cal",
+    dav_extension: "ics",
+    dav_content_type: "text/calendar; charset=utf-8",
+};
+
+pub static CONTAINER_SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::Name)
+        .index_as(IndexAs::Text {
+            tokenize: true,
+            index: true,
+        })
+        .max_size(255)
+        .required(),
+    IndexProperty::new(Property::SortOrder).index_as(IndexAs::Integer),
+    IndexProperty::new(Property::IsDefault).index_as(IndexAs::Integer),
+    IndexProperty::new(Property::IsSubscribed).index_as(IndexAs::IntegerList),
+    IndexProperty::new(Property::Acl).index_as(IndexAs::Acl),
+    IndexProperty::new(Property::DavName)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255),
+];
+
+pub static CONTACT_CARD_SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::AddressBookIds).index_as(IndexAs::IntegerList),
+    IndexProperty::new(Property::Uid)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255)
+        .required(),
+    IndexProperty::new(Property::Kind).index_as(IndexAs::Text {
+        tokenize: false,
+        index: true,
+    }),
+    IndexProperty::new(Property::Text).index_as(IndexAs::Text {
+        tokenize: true,
+        index: false,
+    }),
+    IndexProperty::new(Property::DavName)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255),
+];
+
+pub static CALENDAR_EVENT_SCHEMA: &[IndexProperty] = &[
+    IndexProperty::new(Property::CalendarIds).index_as(IndexAs::IntegerList),
+    IndexProperty::new(Property::Uid)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255)
+        .required(),
+    IndexProperty::new(Property::Title).index_as(IndexAs::Text {
+        tokenize: true,
+        index: true,
+    }),
+    IndexProperty::new(Property::UtcStart).index_as(IndexAs::LongInteger),
+    IndexProperty::new(Property::UtcEnd).index_as(IndexAs::LongInteger),
+    IndexProperty::new(Property::Text).index_as(IndexAs::Text {
+        tokenize: true,
+        index: false,
+    }),
+    IndexProperty::new(Property::DavName)
+        .index_as(IndexAs::Text {
+            tokenize: false,
+            index: true,
+        })
+        .max_size(255),
+];
+
+impl GroupwareType {
+    // Clients expect at least one container, it is created on first use
+    pub fn default_container(&self) -> Object<Value> {
+        Object::with_capacity(3)
+            .with_property(Property::Name, "Default")
+            .with_property(Property::DavName, "default")
+            .with_property(Property::IsDefault, true)
+    }
+
+    // Derived properties are stored alongside the item so they can be indexed
+    pub fn derive_properties(&self, item: &mut Object<Value>) {
+        let mut text = String::new();
+        for (property, value) in &item.properties {
+            if self
+                .text_properties
+                .contains(&property.to_string().as_str())
+            {
+                collect_text(value, &mut text);
+            }
+        }
+        if !text.is_empty() {
+            item.set(Property::Text, Value::Text(text));
+        } else {
+            item.properties.remove(&Property::Text);
+        }
+
+        if self.item == Collection::CalendarEvent {
+            match event_time_range(item) {
+                Some((start, end)) => {
+                    item.set(Property::UtcStart, Value::UnsignedInt(start));
+                    item.set(Property::UtcEnd, Value::UnsignedInt(end));
+                }
+                None => {
+                    item.properties.remove(&Property::UtcStart);
+                    item.properties.remove(&Property::UtcEnd);
+                }
+            }
+        }
+    }
+}
+
+fn collect_text(value: &Value, text: &mut String) {
+    match value {
+        Value::Text(value) => {
+            if !text.is_empty() {
+                text.push(' ');
+            }
+            text.push_str(value);
+        }
+        Value::List(values) => {
+            for value in values {
+                collect_text(value, text);
+            }
+        }
+        Value::Object(object) => {
+            for (property, value) in &object.properties {
+                if !matches!(property, Property::_T(name) if name.starts_with('@')) {
+                    collect_text(value, text);
+                }
+            }
+        }
+        _ => (),
+    }
+}
+
+pub fn container_ids(item: &Object<Value>, kind: &GroupwareType) -> Vec<u32> {
+    item.get(&kind.container_ids)
+        .as_list()
+        .map(|ids| {
+            ids.iter()
+                .filter_map(|id| id.as_id().map(|id| id.document_id()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+// Document ids are reused, so the deletions of earlier items are kept too
+const MAX_TOMBSTONES: usize = 16;
+
+// Deleted items keep their containers and resource name, so that WebDAV
+// sync reports can still list them as removed members.
+pub fn item_tombstones(
+    kind: &GroupwareType,
+    item: &Object<Value>,
+    tombstones: Option<Value>,
+) -> Value {
+    let mut tombstones = match tombstones {
+        Some(Value::List(tombstones)) => tombstones,
+        _ => Vec::new(),
+    };
+    if tombstones.len() >= MAX_TOMBSTONES {
+        tombstones.remove(0);
+    }
+    let mut tombstone = Object::with_capacity(2).with_property(
+        kind.container_ids.clone(),
+        item.get(&kind.container_ids).clone(),
+    );
+    if let Some(name) = item.get(&Property::DavName).as_string() {
+        tombstone.append(Property::DavName, name.to_string());
+    }
+    tombstones.push(Value::Object(tombstone));
+    Value::List(tombstones)
+}
+
+// Returns the properties that differ from the current object, removed
+// properties are set to null.
+pub fn object_changes(current: &Object<Value>, item: Object<Value>) -> Object<Value> {
+    let mut changes = Object::with_capacity(item.properties.len());
+    for property in current.properties.keys() {
+        if !item.properties.contains_key(property) {
+            changes.append(property.clone(), Value::Null);
+        }
+    }
+    for (property, value) in item.properties {
+        if current.get(&property) != &value {
+            changes.append(property, value);
+        }
+    }
+    changes
+}
+
+pub fn get_value<'x>(item: &'x Object<Value>, name: &str) -> Option<&'x Value> {
+    item.properties
+        .iter()
+        .find(|(property, _)| property.to_string() == name)
+        .map(|(_, value)| value)
+}
+
+pub fn get_text<'x>(item: &'x Object<Value>, name: &str) -> Option<&'x str> {
+    get_value(item, name).and_then(|value| value.as_string())
+}
+
+// Time zones are resolved as UTC, floating and zoned start times are
+// therefore indexed using their wall clock time.
+fn event_time_range(item: &Object<Value>) -> Option<(u64, u64)> {
+    let start = NaiveDateTime::parse_from_str(get_text(item, "start")?, "%Y-%m-%dT%H:%M:%S")
+        .ok()?
+        .and_utc();
+    let duration = get_text(item, "duration")
+        .and_then(parse_duration)
+        .unwrap_or_default();
+    let start = start.timestamp().max(0) as u64;
+
+    Some((start, start + duration.num_seconds().max(0) as u64))
+}
+
+pub fn parse_duration(value: &str) -> Option<TimeDelta> {
+    let mut duration = TimeDelta::zero();
+    let mut num = 0i64;
+    let mut is_time = false;
+    let mut has_digits = false;
+
+    let value = value.strip_prefix('P')?;
+    for ch in value.chars() {
+        match ch {
+            '0'..='9' => {
+                num = num.checked_mul(10)?.checked_add(ch as i64 - '0' as i64)?;
+                has_digits = true;
+                continue;
+            }
+            'T' if !is_time => {
+                is_time = true;
+                continue;
+            }
+            'W' if !is_time => duration = duration.checked_add(&TimeDelta::try_weeks(num)?)?,
+            'D' if !is_time => duration = duration.checked_add(&TimeDelta::try_days(num)?)?,
+            'H' if is_time => duration = duration.checked_add(&TimeDelta::try_hours(num)?)?,
+            'M' if is_time => duration = duration.checked_add(&TimeDelta::try_minutes(num)?)?,
+            'S' if is_time => duration = duration.checked_add(&TimeDelta::try_seconds(num)?)?,
+            _ => return None,
+        }
+        if !has_digits {
+            return None;
+        }
+        num = 0;
+        has_digits = false;
+    }
+
+    if !has_digits {
+        Some(duration)
+    } else {
+        None
+    }
+}
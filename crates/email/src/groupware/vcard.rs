@@ -0,0 +1,190 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::{
+    object::Object,
+    types::{property::Property, value::Value},
+};
+
+use super::{get_text, get_value};
+
+use utils::codec::content_line::{escape_text, write_line, ContentLine};
+
+pub fn parse_vcard(text: &str) -> Result<Object<Value>, &'static str> {
+    let mut lines = ContentLine::parse_all(text).into_iter();
+    if !lines.next().is_some_and(|line| line.is("BEGIN", "VCARD")) {
+        return Err("Expected a vCard");
+    }
+
+    let mut card = Object::with_capacity(8)
+        .with_property(Property::parse("@type"), "Card")
+        .with_property(Property::parse("version"), "1.0");
+    let mut full_name = None;
+    let mut structured_name = None;
+    let mut nicknames = IdMap::new("k", "name");
+    let mut emails = IdMap::new("e", "address");
+    let mut phones = IdMap::new("p", "number");
+    let mut organizations = IdMap::new("o", "name");
+    let mut titles = IdMap::new("t", "name");
+    let mut notes = IdMap::new("n", "note");
+    let mut is_complete = false;
+
+    for line in lines {
+        match line.name.as_str() {
+            "END" if line.value.eq_ignore_ascii_case("VCARD") => {
+                is_complete = true;
+                break;
+            }
+            "BEGIN" => return Err("Nested components are not supported"),
+            "UID" => {
+                card.set(Property::Uid, line.text());
+            }
+            "KIND" => {
+                card.set(Property::Kind, line.text().to_ascii_lowercase());
+            }
+            "FN" => full_name = Some(line.text()),
+            "N" => structured_name = Some(line.components()),
+            "NICKNAME" => nicknames.push(line.text()),
+            "EMAIL" => emails.push(line.text()),
+            "TEL" => {
+                let number = line.text();
+                phones.push(
+                    number
+                        .strip_prefix("tel:")
+                        .map(|number| number.to_string())
+                        .unwrap_or(number),
+                );
+            }
+            "ORG" => {
+                if let Some(name) = line.components().into_iter().next() {
+                    organizations.push(name);
+                }
+            }
+            "TITLE" => titles.push(line.text()),
+            "NOTE" => notes.push(line.text()),
+            _ => (),
+        }
+    }
+
+    if !is_complete {
+        return Err("Unterminated vCard");
+    }
+
+    // The full name is derived from the structured name if missing
+    let full_name = full_name.filter(|name| !name.is_empty()).or_else(|| {
+        structured_name.and_then(|components| {
+            let name = [components.get(1), components.first()]
+                .into_iter()
+                .flatten()
+                .filter(|component| !component.is_empty())
+                .cloned()
+                .collect::<Vec<_>>()
+                .join(" ");
+            (!name.is_empty()).then_some(name)
+        })
+    });
+    if let Some(full_name) = full_name {
+        card.set(
+            Property::Name,
+            Object::with_capacity(1).with_property(Property::parse("full"), full_name),
+        );
+    }
+    if card.get(&Property::Kind) == &Value::Null {
+        card.set(Property::Kind, "individual");
+    }
+
+    for (name, map) in [
+        ("nicknames", nicknames),
+        ("emails", emails),
+        ("phones", phones),
+        ("organizations", organizations),
+        ("titles", titles),
+        ("notes", notes),
+    ] {
+        if let Some(value) = map.build() {
+            card.set(Property::parse(name), value);
+        }
+    }
+
+    Ok(card)
+}
+
+pub fn build_vcard(card: &Object<Value>) -> String {
+    let mut buf = String::with_capacity(256);
+    write_line(&mut buf, "BEGIN", &[], "VCARD");
+    write_line(&mut buf, "VERSION", &[], "4.0");
+    if let Some(uid) = get_text(card, "uid") {
+        write_line(&mut buf, "UID", &[], &escape_text(uid));
+    }
+    if let Some(kind) = get_text(card, "kind").filter(|kind| *kind != "individual") {
+        write_line(&mut buf, "KIND", &[], &escape_text(kind));
+    }
+    write_line(
+        &mut buf,
+        "FN",
+        &[],
+        &escape_text(
+            get_value(card, "name")
+                .and_then(|name| name.as_obj())
+                .and_then(|name| get_text(name, "full"))
+                .unwrap_or_default(),
+        ),
+    );
+    for (property, name, field) in [
+        ("NICKNAME", "nicknames", "name"),
+        ("EMAIL", "emails", "address"),
+        ("TEL", "phones", "number"),
+        ("ORG", "organizations", "name"),
+        ("TITLE", "titles", "name"),
+        ("NOTE", "notes", "note"),
+    ] {
+        if let Some(entries) = get_value(card, name).and_then(|value| value.as_obj()) {
+            for entry in entries.properties.values() {
+                if let Some(value) = entry.as_obj().and_then(|entry| get_text(entry, field)) {
+                    write_line(&mut buf, property, &[], &escape_text(value));
+                }
+            }
+        }
+    }
+    write_line(&mut buf, "END", &[], "VCARD");
+    buf
+}
+
+// JSContact uses maps keyed by arbitrary identifiers for multi-valued properties
+pub(super) struct IdMap {
+    prefix: &'static str,
+    field: &'static str,
+    entries: Object<Value>,
+}
+
+impl IdMap {
+    pub fn new(prefix: &'static str, field: &'static str) -> Self {
+        IdMap {
+            prefix,
+            field,
+            entries: Object::with_capacity(0),
+        }
+    }
+
+    pub fn push(&mut self, value: String) {
+        if !value.is_empty() {
+            let id = format!("{}{}", self.prefix, self.entries.properties.len() + 1);
+            self.entries.append(
+                Property::_T(id),
+                Object::with_capacity(1).with_property(Property::parse(self.field), value),
+            );
+        }
+    }
+
+    pub fn push_object(&mut self, value: Object<Value>) {
+        let id = format!("{}{}", self.prefix, self.entries.properties.len() + 1);
+        self.entries.append(Property::_T(id), value);
+    }
+
+    pub fn build(self) -> Option<Value> {
+        (!self.entries.properties.is_empty()).then_some(Value::Object(self.entries))
+    }
+}
@@ -12,6 +12,7 @@ use std::{
 
 use common::{
     auth::{AccessToken, ResourceToken},
+    itip::Itip,
     Server,
 };
 use directory::Permission;
@@ -36,6 +37,7 @@ use store::{
     ahash::AHashSet,
     query::Filter,
     write::{
+        assert::HashedValue,
         log::{ChangeLogBuilder, Changes, LogInsert},
         now, AssignedIds, BatchBuilder, BitmapClass, MaybeDynamicId, MaybeDynamicValue,
         SerializeWithId, TagValue, TaskQueueClass, ValueClass, F_BITMAP, F_CLEAR, F_VALUE,
@@ -47,7 +49,7 @@ use utils::map::vec_map::VecMap;
 
 use crate::{
     index::{IndexMessage, VisitValues, MAX_ID_LENGTH},
-    mailbox::{UidMailbox, INBOX_ID, JUNK_ID},
+    mailbox::{UidMailbox, INBOX_ID, JUNK_ID, TOMBSTONE_ID},
 };
 
 use super::{
@@ -63,6 +65,7 @@ pub struct IngestedEmail {
     pub blob_id: BlobId,
     pub size: usize,
     pub imap_uids: Vec<u32>,
+    pub itip: Option<Itip>,
 }
 
 pub struct IngestEmail<'x> {
@@ -76,6 +79,14 @@ pub struct IngestEmail<'x> {
     pub spam_classify: bool,
     pub spam_train: bool,
     pub session_id: u64,
+    pub replace: Option<ReplaceEmail>,
+}
+
+pub struct ReplaceEmail {
+    pub document_id: u32,
+    pub thread_id: u32,
+    pub mailbox_id: u32,
+    pub mailbox_ids: HashedValue<Vec<UidMailbox>>,
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Debug)]
@@ -240,6 +251,13 @@ impl EmailIngest for Server {
             _ => (),
         }
 
+        // Extract iTIP scheduling messages before the message is encrypted
+        let itip = if params.source.is_smtp() && self.core.jmap.itip_auto_reply {
+            Itip::from_message(&message)
+        } else {
+            None
+        };
+
         // Obtain message references and thread name
         let mut message_id = String::new();
         let thread_id = {
@@ -314,6 +332,7 @@ impl EmailIngest for Server {
                     blob_id: BlobId::default(),
                     imap_uids: Vec::new(),
                     size: 0,
+                    itip: None,
                 });
             }
 
@@ -458,6 +477,49 @@ impl EmailIngest for Server {
             imap_uids.push(uid);
         }
 
+        // Obtain the changes caused by replacing an existing message
+        let mut thread_changes = Changes::default();
+        let mut email_changes = Changes::default();
+        let mut changed_mailbox_ids = params.mailbox_ids.clone();
+        let mut deleted_thread_id = None;
+        if let Some(replace) = &params.replace {
+            let replaced_id = Id::from_parts(replace.thread_id, replace.document_id);
+            if replace
+                .mailbox_ids
+                .inner
+                .iter()
+                .any(|m| m.mailbox_id != replace.mailbox_id)
+            {
+                email_changes.updates.insert(replaced_id.into());
+            } else {
+                email_changes.deletes.insert(replaced_id.into());
+                if thread_id != Some(replace.thread_id) {
+                    // Delete the thread if the replaced message was its only member
+                    if self
+                        .get_tag(
+                            account_id,
+                            Collection::Email,
+                            Property::ThreadId,
+                            TagValue::Id(replace.thread_id),
+                        )
+                        .await
+                        .caused_by(trc::location!())?
+                        .is_some_and(|ids| ids.len() > 1)
+                    {
+                        thread_changes
+                            .child_updates
+                            .insert(replace.thread_id.into());
+                    } else {
+                        thread_changes.deletes.insert(replace.thread_id.into());
+                        deleted_thread_id = Some(replace.thread_id);
+                    }
+                }
+            }
+            if !changed_mailbox_ids.contains(&replace.mailbox_id) {
+                changed_mailbox_ids.push(replace.mailbox_id);
+            }
+        }
+
         // Prepare batch
         let mut batch = BatchBuilder::new();
         batch
@@ -465,7 +527,10 @@ impl EmailIngest for Server {
             .with_account_id(account_id)
             .with_collection(Collection::Thread);
         if let Some(thread_id) = thread_id {
-            batch.log(Changes::update([thread_id]));
+            thread_changes.updates.insert(thread_id.into());
+            batch.log(thread_changes);
+        } else if !thread_changes.child_updates.is_empty() || !thread_changes.deletes.is_empty() {
+            batch.create_document().log(LogThreadInsert(thread_changes));
         } else {
             batch.create_document().log(LogInsert());
         }
@@ -480,10 +545,10 @@ impl EmailIngest for Server {
             .unwrap_or(MaybeDynamicId::Dynamic(0));
         batch
             .with_collection(Collection::Mailbox)
-            .log(Changes::child_update(params.mailbox_ids.iter().copied()))
+            .log(Changes::child_update(changed_mailbox_ids))
             .with_collection(Collection::Email)
             .create_document()
-            .log(LogEmailInsert(thread_id))
+            .log(LogEmailInsert::new(thread_id).with_changes(email_changes))
             .index_message(
                 account_id,
                 tenant_id,
@@ -516,6 +581,52 @@ impl EmailIngest for Server {
             );
         }
 
+        // Expunge the replaced message from its mailbox
+        if let Some(replace) = params.replace {
+            batch.update_document(replace.document_id).assert_value(
+                ValueClass::Property(Property::MailboxIds.into()),
+                &replace.mailbox_ids,
+            );
+            let mailbox_ids = replace
+                .mailbox_ids
+                .inner
+                .into_iter()
+                .filter(|m| m.mailbox_id != replace.mailbox_id)
+                .collect::<Vec<_>>();
+            if !mailbox_ids.is_empty() {
+                batch
+                    .value(Property::MailboxIds, mailbox_ids, F_VALUE)
+                    .value(
+                        Property::MailboxIds,
+                        UidMailbox::new_unassigned(replace.mailbox_id),
+                        F_BITMAP | F_CLEAR,
+                    )
+                    .value(Property::Cid, change_id, F_VALUE);
+            } else {
+                batch
+                    .value(
+                        Property::MailboxIds,
+                        vec![UidMailbox::new_unassigned(replace.mailbox_id)],
+                        F_VALUE | F_BITMAP | F_CLEAR,
+                    )
+                    .value(
+                        Property::ThreadId,
+                        replace.thread_id,
+                        F_VALUE | F_BITMAP | F_CLEAR,
+                    )
+                    .tag(
+                        Property::MailboxIds,
+                        TagValue::Id(MaybeDynamicId::Static(TOMBSTONE_ID)),
+                        0,
+                    );
+            }
+            if let Some(thread_id) = deleted_thread_id {
+                batch
+                    .with_collection(Collection::Thread)
+                    .delete_document(thread_id);
+            }
+        }
+
         // Insert and obtain ids
         let ids = self
             .core
@@ -570,6 +681,7 @@ impl EmailIngest for Server {
             },
             size: raw_message_len as usize,
             imap_uids,
+            itip,
         })
     }
 
@@ -729,17 +841,35 @@ impl EmailIngest for Server {
     }
 
     fn email_bayes_can_train(&self, access_token: &AccessToken) -> bool {
-        self.core.spam.bayes.as_ref().is_some_and(|bayes| {
-            bayes.account_classify && access_token.has_permission(Permission::SpamFilterTrain)
-        })
+        (self.core.spam.fuzzy.is_some()
+            || self
+                .core
+                .spam
+                .bayes
+                .as_ref()
+                .is_some_and(|bayes| bayes.account_classify))
+            && access_token.has_permission(Permission::SpamFilterTrain)
     }
 }
 
-pub struct LogEmailInsert(Option<u32>);
+pub struct LogEmailInsert {
+    thread_id: Option<u32>,
+    changes: Changes,
+}
+
+struct LogThreadInsert(Changes);
 
 impl LogEmailInsert {
     pub fn new(thread_id: Option<u32>) -> Self {
-        Self(thread_id)
+        Self {
+            thread_id,
+            changes: Changes::default(),
+        }
+    }
+
+    pub fn with_changes(mut self, changes: Changes) -> Self {
+        self.changes = changes;
+        self
     }
 }
 
@@ -751,13 +881,26 @@ impl IngestSource<'_> {
 
 impl SerializeWithId for LogEmailInsert {
     fn serialize_with_id(&self, ids: &AssignedIds) -> trc::Result<Vec<u8>> {
-        let thread_id = match self.0 {
+        let thread_id = match self.thread_id {
             Some(thread_id) => thread_id,
             None => ids.first_document_id()?,
         };
         let document_id = ids.last_document_id()?;
+        let mut changes = self.changes.clone();
+        changes
+            .inserts
+            .insert(Id::from_parts(thread_id, document_id).into());
 
-        Ok(Changes::insert([Id::from_parts(thread_id, document_id)]).serialize())
+        Ok(changes.serialize())
+    }
+}
+
+impl SerializeWithId for LogThreadInsert {
+    fn serialize_with_id(&self, ids: &AssignedIds) -> trc::Result<Vec<u8>> {
+        let mut changes = self.0.clone();
+        changes.inserts.insert(ids.last_document_id()?.into());
+
+        Ok(changes.serialize())
     }
 }
 
@@ -767,6 +910,12 @@ impl From<LogEmailInsert> for MaybeDynamicValue {
     }
 }
 
+impl From<LogThreadInsert> for MaybeDynamicValue {
+    fn from(log: LogThreadInsert) -> Self {
+        MaybeDynamicValue::Dynamic(Box::new(log))
+    }
+}
+
 impl From<IngestedEmail> for Object<Value> {
     fn from(email: IngestedEmail) -> Self {
         Object::with_capacity(3)
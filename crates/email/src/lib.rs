@@ -7,8 +7,11 @@
 pub mod cache;
 pub mod crypto;
 pub mod delivery;
+pub mod groupware;
 pub mod index;
 pub mod ingest;
+pub mod list;
 pub mod mailbox;
 pub mod metadata;
 pub mod sieve;
+pub mod urlauth;
@@ -0,0 +1,1255 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Write as _, future::Future, io::Write};
+
+use common::{Server, KV_LIST_BOUNCE, KV_LIST_TOKEN};
+use directory::{
+    backend::internal::{
+        lookup::DirectoryStore,
+        manage::{ChangedPrincipals, ManageDirectory, UpdatePrincipal},
+        PrincipalField, PrincipalUpdate, PrincipalValue,
+    },
+    ListOption, Principal, Type,
+};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{
+        acl::Acl,
+        collection::Collection,
+        property::Property,
+        state::StateChange,
+        type_state::DataType,
+        value::{AclGrant, Value},
+    },
+};
+use mail_builder::{
+    headers::{address::Address, Header, HeaderType},
+    MessageBuilder,
+};
+use mail_parser::{Message, MessageParser, MimeHeaders};
+use serde::{Deserialize, Serialize};
+use store::{
+    dispatch::lookup::KeyValue,
+    write::{assert::HashedValue, log::ChangeLogBuilder, now, BatchBuilder, Bincode, BlobOp},
+    Serialize as _,
+};
+use trc::{AddContext, MailingListEvent};
+use utils::{map::bitmap::Bitmap, BlobHash};
+
+use crate::{
+    delivery::{AutogeneratedMessage, IngestMessage, LocalDeliveryStatus},
+    ingest::{EmailIngest, IngestEmail, IngestSource},
+    mailbox::{MailboxFnc, INBOX_ID, SCHEMA},
+};
+
+const SIGNATURE_LEN: usize = 10;
+const UNSUBSCRIBE_CONTEXT: &str = "mailing list unsubscribe";
+const BOUNCE_CONTEXT: &str = "mailing list bounce";
+const LIST_HEADERS: [&str; 9] = [
+    "List-Id",
+    "List-Help",
+    "List-Owner",
+    "List-Post",
+    "List-Archive",
+    "List-Subscribe",
+    "List-Unsubscribe",
+    "List-Unsubscribe-Post",
+    "Precedence",
+];
+
+pub trait MailingListManager: Sync + Send {
+    fn mailing_list_deliver(
+        &self,
+        list_id: u32,
+        deliver_to: &str,
+        message: &IngestMessage,
+        raw_message: &[u8],
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> impl Future<Output = trc::Result<Option<LocalDeliveryStatus>>> + Send;
+
+    fn mailing_list_unsubscribe(
+        &self,
+        token: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+enum ListRequest {
+    Subscribe {
+        list_id: u32,
+        address: String,
+    },
+    Unsubscribe {
+        list_id: u32,
+        address: String,
+    },
+    Held {
+        list_id: u32,
+        blob_hash: BlobHash,
+        sender: String,
+        subject: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ListCommand<'x> {
+    Post,
+    Subscribe,
+    Unsubscribe,
+    Bounces,
+    Bounce(&'x str),
+    Confirm(&'x str),
+    Approve(&'x str),
+    Reject(&'x str),
+}
+
+struct MailingList {
+    id: u32,
+    name: String,
+    address: String,
+    principal: Principal,
+}
+
+impl MailingListManager for Server {
+    async fn mailing_list_deliver(
+        &self,
+        list_id: u32,
+        deliver_to: &str,
+        message: &IngestMessage,
+        raw_message: &[u8],
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> trc::Result<Option<LocalDeliveryStatus>> {
+        let Some(list) = self.mailing_list(list_id).await? else {
+            return Ok(None);
+        };
+        let session_id = message.session_id;
+        let sender = message.sender_address.to_lowercase();
+
+        let command = ListCommand::parse(deliver_to);
+        let status = match command {
+            ListCommand::Post => {
+                self.list_post(&list, message, raw_message, autogenerated)
+                    .await?
+            }
+            // Bounces of list notifications are not tracked
+            ListCommand::Bounces => LocalDeliveryStatus::Success,
+            ListCommand::Bounce(token) => {
+                self.list_bounce(&list, token, raw_message, session_id)
+                    .await?;
+                LocalDeliveryStatus::Success
+            }
+            // Commands from the null sender are ignored to avoid loops
+            _ if sender.is_empty() => LocalDeliveryStatus::Success,
+            ListCommand::Subscribe => {
+                if list.has_option(ListOption::Subscribe) {
+                    let token = self
+                        .list_issue_token(
+                            ListRequest::Subscribe {
+                                list_id,
+                                address: sender.clone(),
+                            },
+                            self.core.jmap.list_confirm_expiry,
+                        )
+                        .await?;
+                    autogenerated.push(list.confirmation_request(&sender, &token, true));
+
+                    trc::event!(
+                        MailingList(MailingListEvent::ConfirmationSent),
+                        SpanId = session_id,
+                        AccountId = list_id,
+                        To = sender,
+                        Type = "subscribe",
+                    );
+
+                    LocalDeliveryStatus::Success
+                } else {
+                    LocalDeliveryStatus::PermanentFailure {
+                        code: [5, 7, 1],
+                        reason: "This list does not accept subscription requests.".into(),
+                    }
+                }
+            }
+            ListCommand::Unsubscribe => {
+                // Only subscribed addresses receive a confirmation request
+                if self.list_is_subscribed(&list, &sender).await? {
+                    let token = self
+                        .list_issue_token(
+                            ListRequest::Unsubscribe {
+                                list_id,
+                                address: sender.clone(),
+                            },
+                            self.core.jmap.list_confirm_expiry,
+                        )
+                        .await?;
+                    autogenerated.push(list.confirmation_request(&sender, &token, false));
+
+                    trc::event!(
+                        MailingList(MailingListEvent::ConfirmationSent),
+                        SpanId = session_id,
+                        AccountId = list_id,
+                        To = sender,
+                        Type = "unsubscribe",
+                    );
+                }
+
+                LocalDeliveryStatus::Success
+            }
+            ListCommand::Confirm(token) => match self.list_take_token(token).await? {
+                Some(ListRequest::Subscribe { list_id, address }) if list_id == list.id => {
+                    self.list_subscribe(&list, &address, session_id).await?;
+                    LocalDeliveryStatus::Success
+                }
+                Some(ListRequest::Unsubscribe { list_id, address }) if list_id == list.id => {
+                    self.list_unsubscribe(&list, &address, session_id).await?;
+                    LocalDeliveryStatus::Success
+                }
+                _ => invalid_token(&list, token, session_id),
+            },
+            ListCommand::Approve(token) | ListCommand::Reject(token) => {
+                if !list.is_moderator(&sender) {
+                    trc::event!(
+                        MailingList(MailingListEvent::Error),
+                        SpanId = session_id,
+                        AccountId = list_id,
+                        From = sender,
+                        Reason = "Sender is not a list moderator",
+                    );
+
+                    return Ok(Some(LocalDeliveryStatus::PermanentFailure {
+                        code: [5, 7, 1],
+                        reason: "Only list moderators can approve or reject messages.".into(),
+                    }));
+                }
+
+                match self.list_take_token(token).await? {
+                    Some(ListRequest::Held {
+                        list_id,
+                        blob_hash,
+                        sender: poster,
+                        subject,
+                    }) if list_id == list.id => {
+                        if matches!(command, ListCommand::Approve(_)) {
+                            self.list_approve(&list, &blob_hash, session_id, autogenerated)
+                                .await?;
+
+                            trc::event!(
+                                MailingList(MailingListEvent::Approved),
+                                SpanId = session_id,
+                                AccountId = list_id,
+                                From = poster,
+                                Details = sender,
+                            );
+                        } else {
+                            autogenerated.push(list.rejection_notice(&poster, &subject));
+
+                            trc::event!(
+                                MailingList(MailingListEvent::Rejected),
+                                SpanId = session_id,
+                                AccountId = list_id,
+                                From = poster,
+                                Details = sender,
+                            );
+                        }
+
+                        LocalDeliveryStatus::Success
+                    }
+                    _ => invalid_token(&list, token, session_id),
+                }
+            }
+        };
+
+        Ok(Some(status))
+    }
+
+    async fn mailing_list_unsubscribe(&self, token: &str, session_id: u64) -> trc::Result<bool> {
+        let Some((list_id, address)) =
+            decode_list_token(&self.core.oauth.oauth_key, UNSUBSCRIBE_CONTEXT, token)
+        else {
+            return Ok(false);
+        };
+
+        if let Some(list) = self.mailing_list(list_id).await? {
+            self.list_unsubscribe(&list, &address, session_id)
+                .await
+                .map(|_| true)
+        } else {
+            Ok(false)
+        }
+    }
+}
+
+trait MailingListStore: Sync + Send {
+    fn mailing_list(
+        &self,
+        list_id: u32,
+    ) -> impl Future<Output = trc::Result<Option<MailingList>>> + Send;
+
+    fn list_post(
+        &self,
+        list: &MailingList,
+        message: &IngestMessage,
+        raw_message: &[u8],
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> impl Future<Output = trc::Result<LocalDeliveryStatus>> + Send;
+
+    fn list_approve(
+        &self,
+        list: &MailingList,
+        blob_hash: &BlobHash,
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_distribute(
+        &self,
+        list: &MailingList,
+        message: &Message<'_>,
+        raw_message: &[u8],
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_archive(
+        &self,
+        list: &MailingList,
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_share_archive(
+        &self,
+        list: &MailingList,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_bounce(
+        &self,
+        list: &MailingList,
+        token: &str,
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_is_subscribed(
+        &self,
+        list: &MailingList,
+        address: &str,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn list_subscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_unsubscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_update(
+        &self,
+        list: &MailingList,
+        update: PrincipalUpdate,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn list_issue_token(
+        &self,
+        request: ListRequest,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+
+    fn list_take_token(
+        &self,
+        token: &str,
+    ) -> impl Future<Output = trc::Result<Option<ListRequest>>> + Send;
+}
+
+impl MailingListStore for Server {
+    async fn mailing_list(&self, list_id: u32) -> trc::Result<Option<MailingList>> {
+        let Some(principal) = self
+            .store()
+            .get_principal(list_id)
+            .await
+            .caused_by(trc::location!())?
+            .filter(|p| p.typ() == Type::List && p.has_list_option(ListOption::Managed))
+        else {
+            return Ok(None);
+        };
+        let Some(address) = principal
+            .iter_str(PrincipalField::Emails)
+            .next()
+            .map(|address| address.to_lowercase())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(MailingList {
+            id: list_id,
+            name: principal
+                .description()
+                .unwrap_or_else(|| principal.name())
+                .to_string(),
+            address,
+            principal,
+        }))
+    }
+
+    async fn list_post(
+        &self,
+        list: &MailingList,
+        message: &IngestMessage,
+        raw_message: &[u8],
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> trc::Result<LocalDeliveryStatus> {
+        let session_id = message.session_id;
+        let Some(parsed) = MessageParser::new().parse(raw_message) else {
+            return Ok(LocalDeliveryStatus::PermanentFailure {
+                code: [5, 6, 0],
+                reason: "Failed to parse message.".into(),
+            });
+        };
+
+        // Drop bounces and messages already distributed by this list
+        if message.sender_address.is_empty() || list.is_loop(&parsed) {
+            trc::event!(
+                MailingList(MailingListEvent::LoopDetected),
+                SpanId = session_id,
+                AccountId = list.id,
+                From = message.sender_address.clone(),
+            );
+            return Ok(LocalDeliveryStatus::Success);
+        }
+
+        // Non-members and moderated lists require approval, posting rights are
+        // only granted to envelope senders that are SPF or DMARC aligned
+        let poster = message.sender_address.to_lowercase();
+        let is_verified = message.is_verified_address(&poster, raw_message);
+        let needs_approval = !(is_verified && list.is_moderator(&poster))
+            && (list.has_option(ListOption::Moderated)
+                || !(list.has_option(ListOption::OpenPosting)
+                    || (is_verified && self.list_is_subscribed(list, &poster).await?)));
+        if !needs_approval {
+            self.list_distribute(list, &parsed, raw_message, session_id, autogenerated)
+                .await
+                .map(|_| LocalDeliveryStatus::Success)
+        } else if list.principal.has_field(PrincipalField::Moderators) {
+            // Hold the message blob until the moderators decide
+            let expires = self.core.jmap.list_hold_expiry;
+            let mut batch = BatchBuilder::new();
+            batch.set(
+                BlobOp::Reserve {
+                    hash: message.message_blob.clone(),
+                    until: now() + expires,
+                },
+                0u32.serialize(),
+            );
+            self.store()
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+
+            let subject = parsed.subject().unwrap_or_default().to_string();
+            let token = self
+                .list_issue_token(
+                    ListRequest::Held {
+                        list_id: list.id,
+                        blob_hash: message.message_blob.clone(),
+                        sender: poster.clone(),
+                        subject: subject.clone(),
+                    },
+                    expires,
+                )
+                .await?;
+            autogenerated.push(list.moderation_request(&poster, &subject, &token, raw_message));
+
+            trc::event!(
+                MailingList(MailingListEvent::Held),
+                SpanId = session_id,
+                AccountId = list.id,
+                From = poster,
+                Expires = trc::Value::Timestamp(now() + expires),
+            );
+
+            Ok(LocalDeliveryStatus::Success)
+        } else {
+            trc::event!(
+                MailingList(MailingListEvent::Rejected),
+                SpanId = session_id,
+                AccountId = list.id,
+                From = poster,
+                Reason = "Sender is not a list member",
+            );
+
+            Ok(LocalDeliveryStatus::PermanentFailure {
+                code: [5, 7, 1],
+                reason: "Posting to this list is restricted to its members.".into(),
+            })
+        }
+    }
+
+    async fn list_approve(
+        &self,
+        list: &MailingList,
+        blob_hash: &BlobHash,
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> trc::Result<()> {
+        let raw_message = self
+            .blob_store()
+            .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .details("Held message blob not found.")
+                    .ctx(trc::Key::BlobId, blob_hash.to_hex())
+            })?;
+
+        if let Some(message) = MessageParser::new().parse(&raw_message) {
+            self.list_distribute(list, &message, &raw_message, session_id, autogenerated)
+                .await
+        } else {
+            Ok(())
+        }
+    }
+
+    async fn list_distribute(
+        &self,
+        list: &MailingList,
+        message: &Message<'_>,
+        raw_message: &[u8],
+        session_id: u64,
+        autogenerated: &mut Vec<AutogeneratedMessage>,
+    ) -> trc::Result<()> {
+        let subscribers = self
+            .store()
+            .expn_by_id(list.id)
+            .await
+            .caused_by(trc::location!())?;
+        let list_message = list.rewrite_message(message, raw_message);
+        let unsubscribe_url = self
+            .core
+            .jmap
+            .list_unsubscribe_url
+            .clone()
+            .unwrap_or_else(|| {
+                format!("https://{}/list/unsubscribe", self.core.network.server_name)
+            });
+        let unsubscribe_address = list.command_address("unsubscribe");
+        let key = &self.core.oauth.oauth_key;
+
+        // Each subscriber gets its own copy with a one-click unsubscribe link
+        // and a VERP bounce address
+        for subscriber in &subscribers {
+            let mut copy = Vec::with_capacity(list_message.len() + 256);
+            let _ = write!(
+                copy,
+                "List-Unsubscribe: <{}/{}>,\r\n\t<mailto:{}>\r\n",
+                unsubscribe_url,
+                list_token(key, UNSUBSCRIBE_CONTEXT, list.id, subscriber),
+                unsubscribe_address
+            );
+            copy.extend_from_slice(b"List-Unsubscribe-Post: List-Unsubscribe=One-Click\r\n");
+            copy.extend_from_slice(&list_message);
+
+            autogenerated.push(AutogeneratedMessage {
+                sender_address: list.command_address(&format!(
+                    "bounces-{}",
+                    list_token(key, BOUNCE_CONTEXT, list.id, subscriber)
+                )),
+                recipients: vec![subscriber.clone()],
+                message: copy,
+            });
+        }
+
+        trc::event!(
+            MailingList(MailingListEvent::Posted),
+            SpanId = session_id,
+            AccountId = list.id,
+            From = message
+                .from()
+                .and_then(|from| from.first())
+                .and_then(|addr| addr.address())
+                .unwrap_or_default()
+                .to_string(),
+            Total = subscribers.len(),
+        );
+
+        if list.has_option(ListOption::Archive) {
+            self.list_archive(list, &list_message, session_id).await?;
+        }
+
+        Ok(())
+    }
+
+    async fn list_archive(
+        &self,
+        list: &MailingList,
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<()> {
+        let access_token = self
+            .get_access_token(list.id)
+            .await
+            .caused_by(trc::location!())?;
+        self.mailbox_get_or_create(list.id)
+            .await
+            .caused_by(trc::location!())?;
+        self.list_share_archive(list).await?;
+
+        let ingested_message = self
+            .email_ingest(IngestEmail {
+                raw_message,
+                message: MessageParser::new().parse(raw_message),
+                resource: access_token.as_resource_token(),
+                mailbox_ids: vec![INBOX_ID],
+                keywords: vec![],
+                received_at: None,
+                source: IngestSource::Smtp {
+                    deliver_to: &list.address,
+                },
+                spam_classify: false,
+                spam_train: false,
+                session_id,
+                replace: None,
+            })
+            .await?;
+
+        if ingested_message.change_id != u64::MAX {
+            self.broadcast_state_change(
+                StateChange::new(list.id)
+                    .with_change(DataType::Email, ingested_message.change_id)
+                    .with_change(DataType::Mailbox, ingested_message.change_id)
+                    .with_change(DataType::Thread, ingested_message.change_id),
+            )
+            .await;
+        }
+
+        Ok(())
+    }
+
+    // Local list members are granted read access to the archive mailbox,
+    // the grants are kept in sync with the membership on every post.
+    async fn list_share_archive(&self, list: &MailingList) -> trc::Result<()> {
+        let members = self
+            .store()
+            .get_members(list.id)
+            .await
+            .caused_by(trc::location!())?;
+        let Some(mailbox) = self
+            .get_property::<HashedValue<Object<Value>>>(
+                list.id,
+                Collection::Mailbox,
+                INBOX_ID,
+                Property::Value,
+            )
+            .await?
+        else {
+            return Ok(());
+        };
+        let current = match mailbox.inner.properties.get(&Property::Acl) {
+            Some(Value::Acl(acl)) => acl.clone(),
+            _ => vec![],
+        };
+        if current.len() == members.len()
+            && current
+                .iter()
+                .all(|grant| members.contains(&grant.account_id))
+        {
+            return Ok(());
+        }
+
+        let grants = Bitmap::from_iter([Acl::Read, Acl::ReadItems]);
+        let mut changed_principals = ChangedPrincipals::default();
+        for account_id in current
+            .iter()
+            .map(|grant| grant.account_id)
+            .chain(members.iter().copied())
+        {
+            changed_principals.add_change(
+                account_id,
+                Type::Individual,
+                PrincipalField::EnabledPermissions,
+            );
+        }
+        let acl = members
+            .into_iter()
+            .map(|account_id| AclGrant { account_id, grants })
+            .collect::<Vec<_>>();
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(list.id)
+            .with_collection(Collection::Mailbox)
+            .update_document(INBOX_ID)
+            .custom(
+                ObjectIndexBuilder::new(SCHEMA)
+                    .with_changes(
+                        Object::with_capacity(1).with_property(Property::Acl, Value::Acl(acl)),
+                    )
+                    .with_current(mailbox),
+            );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        let mut changes = ChangeLogBuilder::new();
+        changes.log_update(Collection::Mailbox, INBOX_ID);
+        let change_id = self.commit_changes(list.id, changes).await?;
+        self.broadcast_state_change(
+            StateChange::new(list.id).with_change(DataType::Mailbox, change_id),
+        )
+        .await;
+        self.increment_token_revision(changed_principals).await;
+
+        Ok(())
+    }
+
+    async fn list_bounce(
+        &self,
+        list: &MailingList,
+        token: &str,
+        raw_message: &[u8],
+        session_id: u64,
+    ) -> trc::Result<()> {
+        // Bounces are attributed to the subscriber encoded in the VERP address
+        let Some(address) = decode_list_token(&self.core.oauth.oauth_key, BOUNCE_CONTEXT, token)
+            .filter(|(list_id, _)| *list_id == list.id)
+            .map(|(_, address)| address)
+        else {
+            trc::event!(
+                MailingList(MailingListEvent::Error),
+                SpanId = session_id,
+                AccountId = list.id,
+                Id = token.to_string(),
+                Reason = "Invalid bounce token",
+            );
+            return Ok(());
+        };
+
+        // Only delivery failure reports are counted
+        if MessageParser::new()
+            .parse(raw_message)
+            .is_none_or(|message| failed_recipients(&message).is_empty())
+            || !self.list_is_subscribed(list, &address).await?
+        {
+            return Ok(());
+        }
+
+        let mut key = Vec::with_capacity(address.len() + std::mem::size_of::<u32>());
+        key.extend_from_slice(&list.id.to_be_bytes());
+        key.extend_from_slice(address.as_bytes());
+        let bounces = self
+            .in_memory_store()
+            .counter_incr(
+                KeyValue::with_prefix(KV_LIST_BOUNCE, &key, 1)
+                    .expires(self.core.jmap.list_bounce_period),
+                true,
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            MailingList(MailingListEvent::Bounce),
+            SpanId = session_id,
+            AccountId = list.id,
+            To = address.clone(),
+            Total = bounces,
+        );
+
+        if bounces >= self.core.jmap.list_bounce_max {
+            self.list_update(
+                list,
+                PrincipalUpdate::add_item(
+                    PrincipalField::DisabledMembers,
+                    PrincipalValue::String(address.clone()),
+                ),
+            )
+            .await?;
+            self.in_memory_store()
+                .counter_delete(KeyValue::<()>::build_key(KV_LIST_BOUNCE, &key))
+                .await
+                .caused_by(trc::location!())?;
+
+            trc::event!(
+                MailingList(MailingListEvent::SubscriberDisabled),
+                SpanId = session_id,
+                AccountId = list.id,
+                To = address,
+                Total = bounces,
+            );
+        }
+
+        Ok(())
+    }
+
+    async fn list_is_subscribed(&self, list: &MailingList, address: &str) -> trc::Result<bool> {
+        self.store()
+            .expn_by_id(list.id)
+            .await
+            .caused_by(trc::location!())
+            .map(|subscribers| {
+                subscribers
+                    .iter()
+                    .any(|subscriber| subscriber.eq_ignore_ascii_case(address))
+            })
+    }
+
+    async fn list_subscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        // Disabled members are re-enabled, new addresses become external members
+        let update = if list.has_address(PrincipalField::DisabledMembers, address) {
+            PrincipalUpdate::remove_item(
+                PrincipalField::DisabledMembers,
+                PrincipalValue::String(address.to_string()),
+            )
+        } else if !self.list_is_subscribed(list, address).await? {
+            PrincipalUpdate::add_item(
+                PrincipalField::ExternalMembers,
+                PrincipalValue::String(address.to_string()),
+            )
+        } else {
+            return Ok(());
+        };
+        self.list_update(list, update).await?;
+
+        trc::event!(
+            MailingList(MailingListEvent::Subscribed),
+            SpanId = session_id,
+            AccountId = list.id,
+            To = address.to_string(),
+        );
+
+        Ok(())
+    }
+
+    async fn list_unsubscribe(
+        &self,
+        list: &MailingList,
+        address: &str,
+        session_id: u64,
+    ) -> trc::Result<()> {
+        // Local members can only be disabled, external members are removed
+        let update = if list.has_address(PrincipalField::ExternalMembers, address) {
+            PrincipalUpdate::remove_item(
+                PrincipalField::ExternalMembers,
+                PrincipalValue::String(address.to_string()),
+            )
+        } else if self.list_is_subscribed(list, address).await? {
+            PrincipalUpdate::add_item(
+                PrincipalField::DisabledMembers,
+                PrincipalValue::String(address.to_string()),
+            )
+        } else {
+            return Ok(());
+        };
+        self.list_update(list, update).await?;
+
+        trc::event!(
+            MailingList(MailingListEvent::Unsubscribed),
+            SpanId = session_id,
+            AccountId = list.id,
+            To = address.to_string(),
+        );
+
+        Ok(())
+    }
+
+    async fn list_update(&self, list: &MailingList, update: PrincipalUpdate) -> trc::Result<()> {
+        let changed_principals = self
+            .store()
+            .update_principal(UpdatePrincipal::by_id(list.id).with_updates(vec![update]))
+            .await
+            .caused_by(trc::location!())?;
+        self.increment_token_revision(changed_principals).await;
+
+        Ok(())
+    }
+
+    async fn list_issue_token(&self, request: ListRequest, expires: u64) -> trc::Result<String> {
+        let token = format!(
+            "{:016x}{:016x}",
+            rand::random::<u64>(),
+            rand::random::<u64>()
+        );
+        self.in_memory_store()
+            .key_set(
+                KeyValue::with_prefix(
+                    KV_LIST_TOKEN,
+                    token.as_bytes(),
+                    Bincode::new(request).serialize(),
+                )
+                .expires(expires),
+            )
+            .await
+            .caused_by(trc::location!())
+            .map(|_| token)
+    }
+
+    async fn list_take_token(&self, token: &str) -> trc::Result<Option<ListRequest>> {
+        let key = KeyValue::<()>::build_key(KV_LIST_TOKEN, token.as_bytes());
+        if let Some(request) = self
+            .in_memory_store()
+            .key_get::<Bincode<ListRequest>>(key.clone())
+            .await
+            .caused_by(trc::location!())?
+        {
+            self.in_memory_store()
+                .key_delete(key)
+                .await
+                .caused_by(trc::location!())?;
+            Ok(Some(request.inner))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<'x> ListCommand<'x> {
+    fn parse(address: &'x str) -> Self {
+        let Some(detail) = address
+            .rsplit_once('@')
+            .and_then(|(local_part, _)| local_part.split_once('+'))
+            .map(|(_, detail)| detail)
+        else {
+            return ListCommand::Post;
+        };
+
+        match detail {
+            "subscribe" => ListCommand::Subscribe,
+            "unsubscribe" => ListCommand::Unsubscribe,
+            "bounces" => ListCommand::Bounces,
+            _ => {
+                if let Some(token) = detail.strip_prefix("bounces-") {
+                    ListCommand::Bounce(token)
+                } else if let Some(token) = detail.strip_prefix("confirm-") {
+                    ListCommand::Confirm(token)
+                } else if let Some(token) = detail.strip_prefix("approve-") {
+                    ListCommand::Approve(token)
+                } else if let Some(token) = detail.strip_prefix("reject-") {
+                    ListCommand::Reject(token)
+                } else {
+                    ListCommand::Post
+                }
+            }
+        }
+    }
+}
+
+impl MailingList {
+    fn has_option(&self, option: ListOption) -> bool {
+        self.principal.has_list_option(option)
+    }
+
+    fn has_address(&self, field: PrincipalField, address: &str) -> bool {
+        self.principal
+            .iter_str(field)
+            .any(|item| item.eq_ignore_ascii_case(address))
+    }
+
+    fn is_moderator(&self, address: &str) -> bool {
+        self.has_address(PrincipalField::Moderators, address)
+    }
+
+    fn command_address(&self, command: &str) -> String {
+        let (local_part, domain) = self
+            .address
+            .rsplit_once('@')
+            .unwrap_or((self.address.as_str(), ""));
+        format!("{local_part}+{command}@{domain}")
+    }
+
+    fn list_id(&self) -> String {
+        self.address.replace('@', ".")
+    }
+
+    fn is_loop(&self, message: &Message<'_>) -> bool {
+        let list_id = format!("<{}>", self.list_id());
+        message.headers().iter().any(|header| {
+            let name = header.name.as_str();
+            let value = message
+                .raw_message()
+                .get(header.offset_start..header.offset_end)
+                .map(String::from_utf8_lossy)
+                .unwrap_or_default();
+
+            (name.eq_ignore_ascii_case("X-Loop")
+                && value.trim().eq_ignore_ascii_case(&self.address))
+                || (name.eq_ignore_ascii_case("List-Id") && value.to_lowercase().contains(&list_id))
+        })
+    }
+
+    // Adds the RFC 2369 and RFC 2919 headers and applies the reply-to and
+    // From rewriting options. List-Unsubscribe is added for each subscriber.
+    fn rewrite_message(&self, message: &Message<'_>, raw_message: &[u8]) -> Vec<u8> {
+        let rewrite_from = self.has_option(ListOption::RewriteFrom);
+        let reply_to_list = self.has_option(ListOption::ReplyToList);
+        let mut output = Vec::with_capacity(raw_message.len() + 512);
+
+        let _ = write!(
+            output,
+            "List-Id: \"{}\" <{}>\r\nList-Post: <mailto:{}>\r\n",
+            self.name.replace('"', ""),
+            self.list_id(),
+            self.address
+        );
+        if self.has_option(ListOption::Subscribe) {
+            let _ = write!(
+                output,
+                "List-Subscribe: <mailto:{}>\r\n",
+                self.command_address("subscribe")
+            );
+        }
+        if self.has_option(ListOption::Archive) {
+            let _ = write!(output, "List-Archive: <mailto:{}>\r\n", self.address);
+        }
+        let _ = write!(output, "Precedence: list\r\nX-Loop: {}\r\n", self.address);
+
+        // DMARC-safe From rewriting
+        let from = message.from().and_then(|from| from.first());
+        if rewrite_from {
+            let name = from
+                .and_then(|addr| addr.name().or_else(|| addr.address()))
+                .unwrap_or("Unknown sender");
+            output.extend_from_slice(b"From: ");
+            let _ = Address::new_address(
+                Some(format!("{name} via {}", self.name)),
+                self.address.as_str(),
+            )
+            .write_header(&mut output, 6);
+        }
+        if reply_to_list {
+            let _ = write!(output, "Reply-To: <{}>\r\n", self.address);
+        } else if let (true, None, Some(address)) = (
+            rewrite_from,
+            message.reply_to(),
+            from.and_then(|addr| addr.address()),
+        ) {
+            output.extend_from_slice(b"Reply-To: ");
+            let _ = Address::new_address(from.and_then(|addr| addr.name()), address)
+                .write_header(&mut output, 10);
+        }
+
+        // Copy the original message without the replaced headers
+        let mut offset = 0;
+        for header in message.headers() {
+            let name = header.name.as_str();
+            if LIST_HEADERS
+                .iter()
+                .any(|list_header| list_header.eq_ignore_ascii_case(name))
+                || (rewrite_from && name.eq_ignore_ascii_case("From"))
+                || (reply_to_list && name.eq_ignore_ascii_case("Reply-To"))
+            {
+                if let Some(bytes) = raw_message.get(offset..header.offset_field) {
+                    output.extend_from_slice(bytes);
+                }
+                offset = header.offset_end;
+            }
+        }
+        if let Some(bytes) = raw_message.get(offset..) {
+            output.extend_from_slice(bytes);
+        }
+
+        output
+    }
+
+    fn confirmation_request(
+        &self,
+        address: &str,
+        token: &str,
+        is_subscribe: bool,
+    ) -> AutogeneratedMessage {
+        let confirm_address = self.command_address(&format!("confirm-{token}"));
+        let action = if is_subscribe {
+            "subscribe to"
+        } else {
+            "unsubscribe from"
+        };
+
+        AutogeneratedMessage {
+            sender_address: self.command_address("bounces"),
+            recipients: vec![address.to_string()],
+            message: MessageBuilder::new()
+                .from((self.name.as_str(), self.address.as_str()))
+                .to(address)
+                .reply_to(confirm_address.as_str())
+                .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+                .subject(format!("Confirm your request to {action} {}", self.name))
+                .text_body(format!(
+                    "We received a request to {action} the {} mailing list <{}>.\r\n\r\n\
+                     To confirm, reply to this message or send an empty message to\r\n\
+                     <{confirm_address}>.\r\n\r\n\
+                     If you did not make this request, please ignore this message.\r\n",
+                    self.name, self.address
+                ))
+                .write_to_vec()
+                .unwrap_or_default(),
+        }
+    }
+
+    fn moderation_request(
+        &self,
+        poster: &str,
+        subject: &str,
+        token: &str,
+        raw_message: &[u8],
+    ) -> AutogeneratedMessage {
+        let moderators = self
+            .principal
+            .iter_str(PrincipalField::Moderators)
+            .cloned()
+            .collect::<Vec<_>>();
+        let approve_address = self.command_address(&format!("approve-{token}"));
+        let reject_address = self.command_address(&format!("reject-{token}"));
+
+        AutogeneratedMessage {
+            sender_address: self.command_address("bounces"),
+            message: MessageBuilder::new()
+                .from((self.name.as_str(), self.address.as_str()))
+                .to(moderators.clone())
+                .reply_to(approve_address.as_str())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .subject(format!("{} approval required: {subject}", self.name))
+                .text_body(format!(
+                    "A message from <{poster}> to the {} mailing list <{}>\r\n\
+                     requires approval.\r\n\r\n\
+                     To approve it, reply to this message or send an empty message to\r\n\
+                     <{approve_address}>.\r\n\r\n\
+                     To reject it, send an empty message to\r\n\
+                     <{reject_address}>.\r\n",
+                    self.name, self.address
+                ))
+                .attachment("message/rfc822", "message.eml", raw_message)
+                .write_to_vec()
+                .unwrap_or_default(),
+            recipients: moderators,
+        }
+    }
+
+    fn rejection_notice(&self, poster: &str, subject: &str) -> AutogeneratedMessage {
+        AutogeneratedMessage {
+            sender_address: self.command_address("bounces"),
+            recipients: vec![poster.to_string()],
+            message: MessageBuilder::new()
+                .from((self.name.as_str(), self.address.as_str()))
+                .to(poster)
+                .header("Auto-Submitted", HeaderType::Text("auto-replied".into()))
+                .subject(format!("Rejected: {subject}"))
+                .text_body(format!(
+                    "Your message \"{subject}\" to the {} mailing list <{}>\r\n\
+                     was rejected by the list moderators.\r\n",
+                    self.name, self.address
+                ))
+                .write_to_vec()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn invalid_token(list: &MailingList, token: &str, session_id: u64) -> LocalDeliveryStatus {
+    trc::event!(
+        MailingList(MailingListEvent::Error),
+        SpanId = session_id,
+        AccountId = list.id,
+        Id = token.to_string(),
+        Reason = "Invalid or expired token",
+    );
+
+    LocalDeliveryStatus::PermanentFailure {
+        code: [5, 1, 1],
+        reason: "Invalid or expired confirmation token.".into(),
+    }
+}
+
+// Extracts the recipients with a failed action from RFC 3464 delivery reports
+fn failed_recipients(message: &Message<'_>) -> Vec<String> {
+    let mut addresses = Vec::new();
+
+    for part in &message.parts {
+        if !part.is_content_type("message", "delivery-status") {
+            continue;
+        }
+
+        let report = String::from_utf8_lossy(part.contents());
+        let mut recipient = None;
+        let mut is_failed = false;
+        for line in report.lines().chain([""]) {
+            let line = line.trim_end();
+            if line.is_empty() {
+                if let (Some(address), true) = (recipient.take(), is_failed) {
+                    addresses.push(address);
+                }
+                is_failed = false;
+            } else if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("Final-Recipient") {
+                    recipient = value
+                        .split_once(';')
+                        .map(|(_, address)| address.trim().to_lowercase());
+                } else if name.eq_ignore_ascii_case("Action") {
+                    is_failed = value.eq_ignore_ascii_case("failed");
+                }
+            }
+        }
+    }
+
+    addresses
+}
+
+// One-click unsubscribe (RFC 8058) and VERP bounce tokens are stateless, they contain
+// the list id, a signature and the subscriber's address encoded in hexadecimal.
+fn list_token(key: &str, context: &str, list_id: u32, address: &str) -> String {
+    let mut token = String::with_capacity((4 + SIGNATURE_LEN + address.len()) * 2);
+    for byte in list_id
+        .to_be_bytes()
+        .iter()
+        .chain(token_signature(key, context, list_id, address).iter())
+        .chain(address.as_bytes())
+    {
+        let _ = write!(token, "{byte:02x}");
+    }
+    token
+}
+
+fn decode_list_token(key: &str, context: &str, token: &str) -> Option<(u32, String)> {
+    let bytes = (0..token.len())
+        .step_by(2)
+        .map(|pos| {
+            token
+                .get(pos..pos + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+        })
+        .collect::<Option<Vec<_>>>()?;
+    let list_id = u32::from_be_bytes(bytes.get(..4)?.try_into().ok()?);
+    let signature = bytes.get(4..4 + SIGNATURE_LEN)?;
+    let address = std::str::from_utf8(bytes.get(4 + SIGNATURE_LEN..)?).ok()?;
+
+    if !address.is_empty() && signature == token_signature(key, context, list_id, address) {
+        Some((list_id, address.to_string()))
+    } else {
+        None
+    }
+}
+
+fn token_signature(key: &str, context: &str, list_id: u32, address: &str) -> [u8; SIGNATURE_LEN] {
+    let mut hasher = blake3::Hasher::new_derive_key(context);
+    hasher.update(key.as_bytes());
+    hasher.update(&list_id.to_be_bytes());
+    hasher.update(address.as_bytes());
+    let mut signature = [0u8; SIGNATURE_LEN];
+    signature.copy_from_slice(&hasher.finalize().as_bytes()[..SIGNATURE_LEN]);
+    signature
+}
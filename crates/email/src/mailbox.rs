@@ -4,9 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{future::Future, slice::Iter};
+use std::{collections::BTreeMap, future::Future, slice::Iter, sync::Arc};
 
-use common::{config::jmap::settings::SpecialUse, Server};
+use common::{config::jmap::settings::SpecialUse, ImapId, MailboxId, MailboxState, Server};
 use jmap_proto::{
     object::{
         index::{IndexAs, IndexProperty, ObjectIndexBuilder},
@@ -15,15 +15,15 @@ use jmap_proto::{
     types::{collection::Collection, id::Id, keyword::Keyword, property::Property, value::Value},
 };
 use store::{
-    ahash::AHashSet,
+    ahash::{AHashMap, AHashSet},
     query::Filter,
     rand,
     roaring::RoaringBitmap,
     write::{
-        BatchBuilder, BitmapClass, DeserializeFrom, MaybeDynamicId, Operation, SerializeInto,
-        TagValue, ToBitmaps,
+        assert::HashedValue, now, BatchBuilder, BitmapClass, DeserializeFrom, MaybeDynamicId,
+        Operation, SerializeInto, TagValue, ToBitmaps, ValueClass, F_CLEAR, F_VALUE,
     },
-    Serialize, U32_LEN,
+    Deserialize, Serialize, ValueKey, U32_LEN, U64_LEN,
 };
 use trc::AddContext;
 use utils::codec::leb128::{Leb128Iterator, Leb128Vec};
@@ -62,12 +62,29 @@ pub static SCHEMA: &[IndexProperty] = &[
     IndexProperty::new(Property::Acl).index_as(IndexAs::Acl),
 ];
 
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Annotations {
+    pub entries: Vec<Annotation>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct Annotation {
+    pub name: String,
+    pub owner_id: Option<u32>,
+    pub value: Vec<u8>,
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct UidMailbox {
     pub mailbox_id: u32,
     pub uid: u32,
+    pub save_date: u64,
 }
 
+// Set on the encoded UID when a save date follows, entries written
+// before save dates were tracked are decoded with a save date of zero
+const HAS_SAVE_DATE: u64 = 1 << 32;
+
 pub trait MailboxFnc: Sync + Send {
     fn mailbox_get_or_create(
         &self,
@@ -111,6 +128,45 @@ pub trait MailboxFnc: Sync + Send {
         account_id: u32,
         role: &str,
     ) -> impl Future<Output = trc::Result<Option<u32>>> + Send;
+
+    fn mailbox_get_annotations(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<Option<HashedValue<Annotations>>>> + Send;
+
+    fn mailbox_set_annotations(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+        current: Option<HashedValue<Annotations>>,
+        annotations: Annotations,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn mailbox_annotations_size(
+        &self,
+        account_id: u32,
+    ) -> impl Future<Output = trc::Result<u64>> + Send;
+
+    fn mailbox_state(
+        &self,
+        mailbox: &MailboxId,
+    ) -> impl Future<Output = trc::Result<MailboxState>> + Send;
+
+    fn mailbox_state_cached(
+        &self,
+        mailbox: &MailboxId,
+    ) -> impl Future<Output = trc::Result<Arc<MailboxState>>> + Send;
+
+    fn mailbox_uid_validity(
+        &self,
+        mailbox: &MailboxId,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
+
+    fn mailbox_uid_next(
+        &self,
+        mailbox: &MailboxId,
+    ) -> impl Future<Output = trc::Result<u32>> + Send;
 }
 
 impl MailboxFnc for Server {
@@ -438,6 +494,322 @@ impl MailboxFnc for Server {
             .caused_by(trc::location!())
             .map(|r| r.results.min())
     }
+
+    async fn mailbox_get_annotations(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+    ) -> trc::Result<Option<HashedValue<Annotations>>> {
+        // Server annotations are stored next to the account settings
+        let (collection, document_id) = match document_id {
+            Some(document_id) => (Collection::Mailbox, document_id),
+            None => (Collection::Principal, 0),
+        };
+
+        self.get_property::<HashedValue<Annotations>>(
+            account_id,
+            collection,
+            document_id,
+            Property::Annotations,
+        )
+        .await
+        .caused_by(trc::location!())
+    }
+
+    async fn mailbox_set_annotations(
+        &self,
+        account_id: u32,
+        document_id: Option<u32>,
+        current: Option<HashedValue<Annotations>>,
+        annotations: Annotations,
+    ) -> trc::Result<()> {
+        let (collection, document_id) = match document_id {
+            Some(document_id) => (Collection::Mailbox, document_id),
+            None => (Collection::Principal, 0),
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(collection)
+            .update_document(document_id);
+        let old_size = if let Some(current) = &current {
+            batch.assert_value(Property::Annotations, current);
+            current.inner.size() as i64
+        } else {
+            batch.assert_value(Property::Annotations, ());
+            0
+        };
+        let new_size = annotations.size() as i64;
+        if !annotations.entries.is_empty() {
+            batch.value(Property::Annotations, &annotations, F_VALUE);
+        } else {
+            batch.value(Property::Annotations, (), F_VALUE | F_CLEAR);
+        }
+
+        // Keep track of the total annotation size of the account
+        if new_size != old_size {
+            batch
+                .with_collection(Collection::Principal)
+                .update_document(0)
+                .add(Property::AnnotationsSize, new_size - old_size);
+        }
+
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn mailbox_annotations_size(&self, account_id: u32) -> trc::Result<u64> {
+        self.store()
+            .get_counter(ValueKey {
+                account_id,
+                collection: Collection::Principal.into(),
+                document_id: 0,
+                class: ValueClass::Property(Property::AnnotationsSize.into()),
+            })
+            .await
+            .map(|size| size.max(0) as u64)
+            .caused_by(trc::location!())
+    }
+
+    async fn mailbox_state(&self, mailbox: &MailboxId) -> trc::Result<MailboxState> {
+        // Obtain message ids
+        let message_ids = self
+            .get_tag(
+                mailbox.account_id,
+                Collection::Email,
+                Property::MailboxIds,
+                mailbox.mailbox_id,
+            )
+            .await?
+            .unwrap_or_default();
+
+        // Obtain UID validity and UID next
+        let uid_validity = self.mailbox_uid_validity(mailbox).await?;
+        let uid_next = self.mailbox_uid_next(mailbox).await?;
+
+        // Obtain current state
+        let modseq = self
+            .store()
+            .get_last_change_id(mailbox.account_id, Collection::Email)
+            .await
+            .add_context(|e| e.caused_by(trc::location!()).account_id(mailbox.account_id))?;
+
+        // Obtain all message ids
+        let mut uid_map = BTreeMap::new();
+        for (message_id, uid_mailbox) in self
+            .get_properties::<HashedValue<Vec<UidMailbox>>, _, _>(
+                mailbox.account_id,
+                Collection::Email,
+                &message_ids,
+                Property::MailboxIds,
+            )
+            .await?
+            .into_iter()
+        {
+            // Make sure the message is still in this mailbox
+            if let Some(item) = uid_mailbox
+                .inner
+                .iter()
+                .find(|item| item.mailbox_id == mailbox.mailbox_id)
+            {
+                debug_assert!(item.uid != 0, "UID is zero for message {item:?}");
+                if uid_map.insert(item.uid, message_id).is_some() {
+                    trc::event!(
+                        Store(trc::StoreEvent::UnexpectedError),
+                        AccountId = mailbox.account_id,
+                        Collection = Collection::Mailbox,
+                        MailboxId = mailbox.mailbox_id,
+                        MessageId = message_id,
+                        Details = "Duplicate IMAP UID"
+                    );
+                }
+            }
+        }
+
+        // Obtain UID next and assign UIDs
+        let mut uid_max = 0;
+        let mut id_to_imap = AHashMap::with_capacity(uid_map.len());
+        let mut uid_to_id = AHashMap::with_capacity(uid_map.len());
+
+        for (seqnum, (uid, message_id)) in uid_map.into_iter().enumerate() {
+            if uid > uid_max {
+                uid_max = uid;
+            }
+            id_to_imap.insert(
+                message_id,
+                ImapId {
+                    uid,
+                    seqnum: seqnum as u32 + 1,
+                },
+            );
+            uid_to_id.insert(uid, message_id);
+        }
+
+        let mut state = MailboxState {
+            uid_next,
+            uid_validity,
+            total_messages: id_to_imap.len(),
+            id_to_imap,
+            uid_to_id,
+            uid_max,
+            modseq,
+            next_state: None,
+            obj_size: 0,
+        };
+        state.obj_size = state.calculate_weight();
+
+        Ok(state)
+    }
+
+    async fn mailbox_state_cached(&self, mailbox: &MailboxId) -> trc::Result<Arc<MailboxState>> {
+        let modseq = self
+            .store()
+            .get_last_change_id(mailbox.account_id, Collection::Email)
+            .await
+            .add_context(|e| {
+                e.caused_by(trc::location!())
+                    .account_id(mailbox.account_id)
+                    .collection(Collection::Email)
+            })?;
+
+        if let Some(cached_state) = self
+            .inner
+            .cache
+            .mailbox
+            .get(mailbox)
+            .filter(|cached_state| cached_state.modseq.unwrap_or(0) >= modseq.unwrap_or(0))
+        {
+            Ok(cached_state)
+        } else {
+            let new_state = Arc::new(self.mailbox_state(mailbox).await?);
+            self.inner.cache.mailbox.insert(*mailbox, new_state.clone());
+            Ok(new_state)
+        }
+    }
+
+    async fn mailbox_uid_validity(&self, mailbox: &MailboxId) -> trc::Result<u32> {
+        self.get_property::<Object<Value>>(
+            mailbox.account_id,
+            Collection::Mailbox,
+            mailbox.mailbox_id,
+            &Property::Value,
+        )
+        .await?
+        .and_then(|obj| obj.get(&Property::Cid).as_uint())
+        .ok_or_else(|| {
+            trc::ImapEvent::Error
+                .caused_by(trc::location!())
+                .details("Mailbox unavailable")
+                .account_id(mailbox.account_id)
+                .collection(Collection::Mailbox)
+                .document_id(mailbox.mailbox_id)
+        })
+        .map(|v| v as u32)
+    }
+
+    async fn mailbox_uid_next(&self, mailbox: &MailboxId) -> trc::Result<u32> {
+        self.store()
+            .get_counter(ValueKey {
+                account_id: mailbox.account_id,
+                collection: Collection::Mailbox.into(),
+                document_id: mailbox.mailbox_id,
+                class: ValueClass::Property(Property::EmailIds.into()),
+            })
+            .await
+            .map(|v| (v + 1) as u32)
+    }
+}
+
+impl Annotations {
+    pub fn get(&self, account_id: u32, name: &str) -> Option<&Annotation> {
+        self.entries
+            .iter()
+            .find(|entry| entry.is_visible(account_id) && entry.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn set(&mut self, account_id: u32, name: String, value: Option<Vec<u8>>) {
+        let owner_id = Annotation::owner_id(account_id, &name);
+        self.entries
+            .retain(|entry| entry.owner_id != owner_id || !entry.name.eq_ignore_ascii_case(&name));
+        if let Some(value) = value {
+            self.entries.push(Annotation {
+                name,
+                owner_id,
+                value,
+            });
+        }
+    }
+
+    pub fn visible(&self, account_id: u32) -> impl Iterator<Item = &Annotation> {
+        self.entries
+            .iter()
+            .filter(move |entry| entry.is_visible(account_id))
+    }
+
+    pub fn size(&self) -> u64 {
+        self.entries
+            .iter()
+            .map(|entry| (entry.name.len() + entry.value.len()) as u64)
+            .sum()
+    }
+}
+
+impl Annotation {
+    // Private entries are kept separately for each account with access to the mailbox
+    fn owner_id(account_id: u32, name: &str) -> Option<u32> {
+        if name
+            .get(..9)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("/private/"))
+        {
+            Some(account_id)
+        } else {
+            None
+        }
+    }
+
+    pub fn is_visible(&self, account_id: u32) -> bool {
+        self.owner_id.is_none_or(|owner_id| owner_id == account_id)
+    }
+}
+
+impl Serialize for &Annotations {
+    fn serialize(self) -> Vec<u8> {
+        let len = bincode::serialized_size(&self).unwrap_or_default();
+        let mut buf = Vec::with_capacity(len as usize + 1);
+        buf.push(1);
+        let _ = bincode::serialize_into(&mut buf, &self);
+        buf
+    }
+}
+
+impl Deserialize for Annotations {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        let version = *bytes
+            .first()
+            .ok_or_else(|| trc::StoreEvent::DataCorruption.caused_by(trc::location!()))?;
+        match version {
+            1 if bytes.len() > 1 => bincode::deserialize(&bytes[1..]).map_err(|err| {
+                trc::EventType::Store(trc::StoreEvent::DeserializeError)
+                    .from_bincode_error(err)
+                    .caused_by(trc::location!())
+            }),
+
+            _ => Err(trc::StoreEvent::DeserializeError
+                .into_err()
+                .caused_by(trc::location!())
+                .ctx(trc::Key::Value, version as u64)),
+        }
+    }
+}
+
+impl ToBitmaps for &Annotations {
+    fn to_bitmaps(&self, _: &mut Vec<Operation>, _: u8, _: bool) {
+        unreachable!()
+    }
 }
 
 impl PartialEq for UidMailbox {
@@ -463,22 +835,36 @@ impl ToBitmaps for UidMailbox {
 impl SerializeInto for UidMailbox {
     fn serialize_into(&self, buf: &mut Vec<u8>) {
         buf.push_leb128(self.mailbox_id);
-        buf.push_leb128(self.uid);
+        if self.save_date != 0 {
+            buf.push_leb128(self.uid as u64 | HAS_SAVE_DATE);
+            buf.push_leb128(self.save_date);
+        } else {
+            buf.push_leb128(self.uid);
+        }
     }
 }
 
 impl DeserializeFrom for UidMailbox {
     fn deserialize_from(bytes: &mut Iter<'_, u8>) -> Option<Self> {
+        let mailbox_id = bytes.next_leb128()?;
+        let uid: u64 = bytes.next_leb128()?;
+        let save_date = if uid & HAS_SAVE_DATE != 0 {
+            bytes.next_leb128()?
+        } else {
+            0
+        };
+
         Some(UidMailbox {
-            mailbox_id: bytes.next_leb128()?,
-            uid: bytes.next_leb128()?,
+            mailbox_id,
+            uid: uid as u32,
+            save_date,
         })
     }
 }
 
 impl Serialize for UidMailbox {
     fn serialize(self) -> Vec<u8> {
-        let mut buf = Vec::with_capacity(U32_LEN * 2);
+        let mut buf = Vec::with_capacity(U32_LEN * 2 + U64_LEN);
         self.serialize_into(&mut buf);
         buf
     }
@@ -486,10 +872,27 @@ impl Serialize for UidMailbox {
 
 impl UidMailbox {
     pub fn new(mailbox_id: u32, uid: u32) -> Self {
-        UidMailbox { mailbox_id, uid }
+        UidMailbox {
+            mailbox_id,
+            uid,
+            save_date: now(),
+        }
     }
 
     pub fn new_unassigned(mailbox_id: u32) -> Self {
-        UidMailbox { mailbox_id, uid: 0 }
+        UidMailbox {
+            mailbox_id,
+            uid: 0,
+            save_date: 0,
+        }
+    }
+
+    pub fn assign_uid(&mut self, uid: u32) {
+        self.uid = uid;
+        self.save_date = now();
+    }
+
+    pub fn save_date(&self) -> Option<u64> {
+        (self.save_date != 0).then_some(self.save_date)
     }
 }
@@ -163,6 +163,7 @@ impl SieveScriptIngest for Server {
             blob_id: Default::default(),
             size: raw_message.len(),
             imap_uids: Vec::new(),
+            itip: None,
         };
 
         while let Some(event) = instance.run(input) {
@@ -526,6 +527,7 @@ impl SieveScriptIngest for Server {
                         spam_classify: access_token.has_permission(Permission::SpamFilterClassify),
                         spam_train: can_spam_train,
                         session_id,
+                        replace: None,
                     })
                     .await
                 {
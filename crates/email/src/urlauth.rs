@@ -0,0 +1,621 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{MailboxId, Server, auth::AccessToken};
+use directory::QueryBy;
+use jmap_proto::{
+    object::Object,
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use mail_parser::{Message, MessageParser, PartType};
+use store::{
+    BlobClass, blake3, rand,
+    write::{BatchBuilder, Bincode, F_CLEAR, F_VALUE, now},
+};
+use trc::AddContext;
+
+use crate::{mailbox::MailboxFnc, metadata::MessageMetadata};
+
+// RFC 5092 IMAP URL, optionally authorized as described in RFC 4467
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImapUrl {
+    pub user: Option<String>,
+    pub host: Option<String>,
+    pub mailbox: String,
+    pub uid_validity: Option<u32>,
+    pub uid: u32,
+    pub section: Option<String>,
+    pub partial: Option<(u32, Option<u32>)>,
+    pub expire: Option<i64>,
+    pub access: Option<UrlAccess>,
+    pub mechanism: Option<String>,
+    pub token: Option<String>,
+    rump: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UrlAccess {
+    AuthUser,
+    Anonymous,
+    User(String),
+    Submit(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UrlRequester<'x> {
+    User(&'x str),
+    Submit(&'x str),
+}
+
+pub const URLAUTH_INTERNAL: &str = "INTERNAL";
+
+pub trait UrlAuth: Sync + Send {
+    fn urlauth_key(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        create: bool,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+
+    fn urlauth_reset(
+        &self,
+        account_id: u32,
+        mailbox_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn urlauth_generate(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        url: &ImapUrl,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+
+    fn urlauth_fetch(
+        &self,
+        url: &ImapUrl,
+        requester: UrlRequester<'_>,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+
+    fn url_fetch(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        url: &ImapUrl,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+
+    fn url_fetch_as(
+        &self,
+        access_token: &AccessToken,
+        url: &ImapUrl,
+    ) -> impl Future<Output = trc::Result<Option<Vec<u8>>>> + Send;
+}
+
+impl UrlAuth for Server {
+    async fn urlauth_key(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        create: bool,
+    ) -> trc::Result<Option<String>> {
+        for _ in 0..3 {
+            if let Some(key) = self
+                .get_property::<String>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    Property::AccessKey,
+                )
+                .await
+                .caused_by(trc::location!())?
+            {
+                return Ok(Some(key));
+            } else if !create {
+                return Ok(None);
+            }
+
+            // Generate a new mailbox access key
+            let key = rand::random::<[u8; 32]>()
+                .iter()
+                .map(|byte| format!("{byte:02x}"))
+                .collect::<String>();
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account_id)
+                .with_collection(Collection::Mailbox)
+                .update_document(mailbox_id)
+                .assert_value(Property::AccessKey, ())
+                .value(Property::AccessKey, key.as_str(), F_VALUE);
+            match self.store().write(batch.build()).await {
+                Ok(_) => return Ok(Some(key)),
+                Err(err) if err.is_assertion_failure() => continue,
+                Err(err) => return Err(err.caused_by(trc::location!())),
+            }
+        }
+
+        Err(trc::StoreEvent::AssertValueFailed
+            .into_err()
+            .details("Failed to generate mailbox access key.")
+            .caused_by(trc::location!()))
+    }
+
+    async fn urlauth_reset(&self, account_id: u32, mailbox_id: Option<u32>) -> trc::Result<()> {
+        let mailbox_ids = if let Some(mailbox_id) = mailbox_id {
+            vec![mailbox_id]
+        } else {
+            self.get_document_ids(account_id, Collection::Mailbox)
+                .await
+                .caused_by(trc::location!())?
+                .unwrap_or_default()
+                .into_iter()
+                .collect()
+        };
+
+        // New keys are generated on demand by GENURLAUTH
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(Collection::Mailbox);
+        for mailbox_id in mailbox_ids {
+            batch
+                .update_document(mailbox_id)
+                .value(Property::AccessKey, (), F_VALUE | F_CLEAR);
+        }
+        if !batch.is_empty() {
+            self.store()
+                .write(batch.build())
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+
+    async fn urlauth_generate(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        url: &ImapUrl,
+    ) -> trc::Result<String> {
+        let key = self
+            .urlauth_key(account_id, mailbox_id, true)
+            .await?
+            .unwrap_or_default();
+
+        Ok(format!(
+            "{}:{}:{}",
+            url.rump,
+            URLAUTH_INTERNAL.to_ascii_lowercase(),
+            url.token(&key)
+        ))
+    }
+
+    async fn urlauth_fetch(
+        &self,
+        url: &ImapUrl,
+        requester: UrlRequester<'_>,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        // Validate the access identifier and expiration
+        let (Some(access), Some(user), Some(mechanism), Some(token)) =
+            (&url.access, &url.user, &url.mechanism, &url.token)
+        else {
+            return Ok(None);
+        };
+        if !mechanism.eq_ignore_ascii_case(URLAUTH_INTERNAL)
+            || !access.is_allowed(requester)
+            || url.expire.is_some_and(|expire| expire <= now() as i64)
+        {
+            return Ok(None);
+        }
+
+        // Obtain the mailbox of the authorizing user
+        let Some(account_id) = self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Name(user), false)
+            .await
+            .caused_by(trc::location!())?
+            .map(|principal| principal.id())
+        else {
+            return Ok(None);
+        };
+        let Some(mailbox_id) = self
+            .mailbox_get_by_name(account_id, &url.mailbox)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+
+        // Verify the token against the current mailbox access key
+        match self.urlauth_key(account_id, mailbox_id, false).await? {
+            Some(key) if url.verify_token(&key, token) => {
+                self.url_fetch(account_id, mailbox_id, url).await
+            }
+            _ => Ok(None),
+        }
+    }
+
+    async fn url_fetch(
+        &self,
+        account_id: u32,
+        mailbox_id: u32,
+        url: &ImapUrl,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        // Find the message using the mailbox UID index
+        let state = self
+            .mailbox_state_cached(&MailboxId {
+                account_id,
+                mailbox_id,
+            })
+            .await
+            .caused_by(trc::location!())?;
+        if url
+            .uid_validity
+            .is_some_and(|uid_validity| uid_validity != state.uid_validity)
+        {
+            return Ok(None);
+        }
+        let Some(document_id) = state.uid_to_id.get(&url.uid).copied() else {
+            return Ok(None);
+        };
+
+        // Obtain the message blob, making sure it is linked to the message
+        let Some(metadata) = self
+            .get_property::<Bincode<MessageMetadata>>(
+                account_id,
+                Collection::Email,
+                document_id,
+                &Property::BodyStructure,
+            )
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+        let blob_hash = &metadata.inner.blob_hash;
+        if !self
+            .store()
+            .blob_has_access(
+                blob_hash,
+                BlobClass::Linked {
+                    account_id,
+                    collection: Collection::Email.into(),
+                    document_id,
+                },
+            )
+            .await
+            .caused_by(trc::location!())?
+        {
+            return Ok(None);
+        }
+        let Some(raw_message) = self
+            .blob_store()
+            .get_blob(blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+
+        Ok(
+            message_section(&raw_message, url.section.as_deref()).map(|bytes| match url.partial {
+                Some((start, length)) => {
+                    let start = std::cmp::min(start as usize, bytes.len());
+                    let end = length.map_or(bytes.len(), |length| {
+                        std::cmp::min(start + length as usize, bytes.len())
+                    });
+                    bytes[start..end].to_vec()
+                }
+                None => bytes,
+            }),
+        )
+    }
+
+    async fn url_fetch_as(
+        &self,
+        access_token: &AccessToken,
+        url: &ImapUrl,
+    ) -> trc::Result<Option<Vec<u8>>> {
+        // Unauthorized URLs can only be resolved by the user they refer to
+        if url
+            .user
+            .as_ref()
+            .is_some_and(|user| !user.eq_ignore_ascii_case(&access_token.name))
+        {
+            return Ok(None);
+        }
+
+        // Shared mailboxes are referenced as "Shared Folders/<owner>/<mailbox>"
+        let (account_id, mailbox_name) = if let Some((owner, mailbox_name)) = url
+            .mailbox
+            .strip_prefix(self.core.jmap.shared_folder.as_str())
+            .and_then(|name| name.strip_prefix('/'))
+            .and_then(|name| name.split_once('/'))
+        {
+            match self
+                .core
+                .storage
+                .directory
+                .query(QueryBy::Name(owner), false)
+                .await
+                .caused_by(trc::location!())?
+            {
+                Some(principal) => (principal.id(), mailbox_name),
+                None => return Ok(None),
+            }
+        } else {
+            (access_token.primary_id(), url.mailbox.as_str())
+        };
+        let Some(mailbox_id) = self
+            .mailbox_get_by_name(account_id, mailbox_name)
+            .await
+            .caused_by(trc::location!())?
+        else {
+            return Ok(None);
+        };
+
+        // Validate the mailbox ACL
+        if !access_token.is_member(account_id)
+            && !self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    Collection::Mailbox,
+                    mailbox_id,
+                    &Property::Value,
+                )
+                .await
+                .caused_by(trc::location!())?
+                .is_some_and(|mailbox| match mailbox.properties.get(&Property::Acl) {
+                    Some(Value::Acl(acl)) => acl.iter().any(|item| {
+                        access_token.is_member(item.account_id)
+                            && item.grants.contains(Acl::ReadItems)
+                    }),
+                    _ => false,
+                })
+        {
+            return Ok(None);
+        }
+
+        self.url_fetch(account_id, mailbox_id, url).await
+    }
+}
+
+impl ImapUrl {
+    pub fn parse(url: &str) -> Option<Self> {
+        // Split the URLAUTH component from the rump
+        let (rump, mechanism, token) = match find_ignore_case(url, ";urlauth=") {
+            Some(pos) => {
+                let mut parts = url[pos..].splitn(3, ':');
+                let access = parts.next()?;
+                match (parts.next(), parts.next()) {
+                    (Some(mechanism), Some(token))
+                        if !mechanism.is_empty() && !token.is_empty() =>
+                    {
+                        (
+                            &url[..pos + access.len()],
+                            Some(mechanism.to_string()),
+                            Some(token.to_string()),
+                        )
+                    }
+                    (None, None) => (url, None, None),
+                    _ => return None,
+                }
+            }
+            None => (url, None, None),
+        };
+        let (path, access) = match find_ignore_case(rump, ";urlauth=") {
+            Some(pos) => (&rump[..pos], Some(UrlAccess::parse(&rump[pos + 9..])?)),
+            None => (rump, None),
+        };
+
+        // Parse server
+        let (user, host, path) = if let Some(server) = path
+            .get(..7)
+            .filter(|scheme| scheme.eq_ignore_ascii_case("imap://"))
+            .map(|_| &path[7..])
+        {
+            let (authority, path) = server.split_at(server.find('/')?);
+            let (user, host) = match authority.rsplit_once('@') {
+                Some((user, host)) => (
+                    Some(url_decode(user.split(';').next().unwrap_or_default())?),
+                    host,
+                ),
+                None => (None, authority),
+            };
+            (user, Some(host.to_string()), path)
+        } else {
+            (None, None, path)
+        };
+
+        // Parse expiration
+        let (path, expire) = match find_ignore_case(path, ";expire=") {
+            Some(pos) => (
+                &path[..pos],
+                Some(mail_parser::DateTime::parse_rfc3339(&path[pos + 8..])?.to_timestamp()),
+            ),
+            None => (path, None),
+        };
+
+        // Parse mailbox and message part
+        let mut segments = path.strip_prefix('/')?.split("/;");
+        let mailbox = segments.next()?;
+        let (mailbox, uid_validity) = match find_ignore_case(mailbox, ";uidvalidity=") {
+            Some(pos) => (
+                &mailbox[..pos],
+                Some(mailbox[pos + 13..].parse::<u32>().ok()?),
+            ),
+            None => (mailbox, None),
+        };
+        let mut result = ImapUrl {
+            user,
+            host,
+            mailbox: url_decode(mailbox.trim_end_matches('/'))?,
+            uid_validity,
+            uid: 0,
+            section: None,
+            partial: None,
+            expire,
+            access,
+            mechanism,
+            token,
+            rump: rump.to_string(),
+        };
+        for segment in segments {
+            let (name, value) = segment.split_once('=')?;
+            if name.eq_ignore_ascii_case("uid") {
+                result.uid = value.parse().ok()?;
+            } else if name.eq_ignore_ascii_case("section") {
+                result.section = Some(url_decode(value)?);
+            } else if name.eq_ignore_ascii_case("partial") {
+                result.partial = Some(match value.split_once('.') {
+                    Some((start, length)) => (start.parse().ok()?, Some(length.parse().ok()?)),
+                    None => (value.parse().ok()?, None),
+                });
+            } else {
+                return None;
+            }
+        }
+
+        if result.uid != 0 && !result.mailbox.is_empty() {
+            Some(result)
+        } else {
+            None
+        }
+    }
+
+    pub fn rump(&self) -> &str {
+        &self.rump
+    }
+
+    fn token(&self, key: &str) -> String {
+        self.token_bytes(key)
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect()
+    }
+
+    fn token_bytes(&self, key: &str) -> [u8; 16] {
+        let mut hasher = blake3::Hasher::new_keyed(blake3::hash(key.as_bytes()).as_bytes());
+        hasher.update(self.rump.as_bytes());
+        let mut token = [0u8; 16];
+        token.copy_from_slice(&hasher.finalize().as_bytes()[..16]);
+        token
+    }
+
+    fn verify_token(&self, key: &str, token: &str) -> bool {
+        hex_decode(token).is_some_and(|token| {
+            ring::constant_time::verify_slices_are_equal(&token, &self.token_bytes(key)).is_ok()
+        })
+    }
+}
+
+impl UrlAccess {
+    fn parse(value: &str) -> Option<Self> {
+        if value.eq_ignore_ascii_case("authuser") {
+            Some(UrlAccess::AuthUser)
+        } else if value.eq_ignore_ascii_case("anonymous") {
+            Some(UrlAccess::Anonymous)
+        } else {
+            let (application, user) = value.split_once('+')?;
+            let user = url_decode(user).filter(|user| !user.is_empty())?;
+            if application.eq_ignore_ascii_case("user") {
+                Some(UrlAccess::User(user))
+            } else if application.eq_ignore_ascii_case("submit") {
+                Some(UrlAccess::Submit(user))
+            } else {
+                None
+            }
+        }
+    }
+
+    pub fn is_allowed(&self, requester: UrlRequester<'_>) -> bool {
+        match (self, requester) {
+            (UrlAccess::AuthUser | UrlAccess::Anonymous, _) => true,
+            (UrlAccess::User(user), UrlRequester::User(requester))
+            | (UrlAccess::Submit(user), UrlRequester::Submit(requester)) => {
+                user.eq_ignore_ascii_case(requester)
+            }
+            _ => false,
+        }
+    }
+}
+
+fn message_section(raw_message: &[u8], section: Option<&str>) -> Option<Vec<u8>> {
+    let Some(section) = section.filter(|section| !section.is_empty()) else {
+        return Some(raw_message.to_vec());
+    };
+    let message = MessageParser::new().parse(raw_message)?;
+    let mut parts = section.split('.').peekable();
+    let mut message: &Message<'_> = &message;
+    let mut part = message.root_part();
+    let mut is_root = true;
+
+    while let Some(num) = parts.peek().and_then(|num| num.parse::<usize>().ok()) {
+        parts.next();
+        part = match part.sub_parts() {
+            Some(sub_part_ids) => message.parts.get(*sub_part_ids.get(num.checked_sub(1)?)?)?,
+            None if num == 1 => part,
+            None => return None,
+        };
+        is_root = false;
+
+        if let (PartType::Message(nested_message), Some(_)) = (&part.body, parts.peek()) {
+            message = nested_message;
+            part = message.root_part();
+            is_root = true;
+        }
+    }
+
+    let text = parts.collect::<Vec<_>>().join(".");
+    let range = if text.eq_ignore_ascii_case("header") && is_root {
+        part.offset_header..part.offset_body
+    } else if text.eq_ignore_ascii_case("text") && is_root {
+        part.offset_body..part.offset_end
+    } else if text.eq_ignore_ascii_case("mime") && !is_root {
+        part.offset_header..part.offset_body
+    } else if text.is_empty() {
+        part.offset_body..part.offset_end
+    } else {
+        return None;
+    };
+
+    message.raw_message.get(range).map(|bytes| bytes.to_vec())
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack
+        .as_bytes()
+        .windows(needle.len())
+        .position(|window| window.eq_ignore_ascii_case(needle.as_bytes()))
+}
+
+fn url_decode(value: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(value.len());
+    let mut iter = value.bytes();
+    while let Some(ch) = iter.next() {
+        if ch == b'%' {
+            let hex = [iter.next()?, iter.next()?];
+            bytes.push(u8::from_str_radix(std::str::from_utf8(&hex).ok()?, 16).ok()?);
+        } else {
+            bytes.push(ch);
+        }
+    }
+    String::from_utf8(bytes).ok()
+}
+
+fn hex_decode(value: &str) -> Option<Vec<u8>> {
+    if value.len() % 2 != 0 {
+        return None;
+    }
+    value
+        .as_bytes()
+        .chunks(2)
+        .map(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok())
+        .collect()
+}
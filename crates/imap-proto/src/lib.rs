@@ -76,6 +76,27 @@ pub enum Command {
     // RFC 9208
     GetQuota,
     GetQuotaRoot,
+
+    // RFC 5465
+    Notify,
+
+    // RFC 5464
+    GetMetadata,
+    SetMetadata,
+
+    // RFC 4978
+    Compress,
+
+    // RFC 8508
+    Replace(bool),
+
+    // RFC 7377
+    Esearch,
+
+    // RFC 4467
+    ResetKey,
+    GenUrlAuth,
+    UrlFetch,
 }
 
 impl Command {
@@ -90,6 +111,7 @@ impl Command {
                 | Command::Expunge(true)
                 | Command::Sort(true)
                 | Command::Thread(true)
+                | Command::Replace(true)
         )
     }
 }
@@ -154,6 +176,29 @@ pub enum ResponseCode {
 
     // USEATTR
     UseAttr,
+
+    // NOTIFY
+    NotificationOverflow,
+    BadEvent,
+
+    // METADATA
+    MetadataLongEntries {
+        size: usize,
+    },
+    MetadataMaxSize {
+        size: usize,
+    },
+    MetadataTooMany,
+    MetadataNoPrivate,
+
+    // COMPRESS
+    CompressionActive,
+
+    // CATENATE
+    BadUrl {
+        url: String,
+    },
+    TooBig,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
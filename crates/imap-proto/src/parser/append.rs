@@ -6,7 +6,7 @@
 
 use crate::{
     protocol::{
-        append::{self, Message},
+        append::{self, CatenatePart, Message},
         Flag, ProtocolVersion,
     },
     receiver::{bad, Request, Token},
@@ -21,6 +21,10 @@ enum State {
     Flags,
     UTF8,
     UTF8Data,
+    Catenate,
+    CatenateParts,
+    CatenateUrl,
+    CatenateText,
 }
 
 impl Request<Command> {
@@ -46,6 +50,7 @@ impl Request<Command> {
                         message: vec![],
                         flags: vec![],
                         received_at: None,
+                        catenate: vec![],
                     };
                     let mut state = State::None;
                     let mut seen_flags = false;
@@ -59,6 +64,7 @@ impl Request<Command> {
                                         State::Flags
                                     }
                                     State::UTF8 => State::UTF8Data,
+                                    State::Catenate => State::CatenateParts,
                                     _ => {
                                         return Err(bad(
                                             self.tag.to_string(),
@@ -68,7 +74,15 @@ impl Request<Command> {
                                 };
                             }
                             Token::ParenthesisClose => match state {
-                                State::None | State::UTF8 => {
+                                State::CatenateParts if !message.catenate.is_empty() => {
+                                    break;
+                                }
+                                State::None
+                                | State::UTF8
+                                | State::Catenate
+                                | State::CatenateParts
+                                | State::CatenateUrl
+                                | State::CatenateText => {
                                     return Err(bad(
                                         self.tag.to_string(),
                                         "Invalid closing parenthesis found.",
@@ -85,6 +99,10 @@ impl Request<Command> {
                                 State::None => {
                                     if value.eq_ignore_ascii_case(b"utf8") {
                                         state = State::UTF8;
+                                    } else if value.eq_ignore_ascii_case(b"catenate")
+                                        && matches!(tokens.peek(), Some(Token::ParenthesisOpen))
+                                    {
+                                        state = State::Catenate;
                                     } else if matches!(tokens.peek(), Some(Token::Argument(_)))
                                         && value.len() <= 28
                                         && !value.contains(&b'\n')
@@ -114,6 +132,36 @@ impl Request<Command> {
                                         "Expected parenthesis after UTF8.",
                                     ));
                                 }
+                                State::Catenate => {
+                                    return Err(bad(
+                                        self.tag.to_string(),
+                                        "Expected parenthesis after CATENATE.",
+                                    ));
+                                }
+                                State::CatenateParts => {
+                                    if value.eq_ignore_ascii_case(b"url") {
+                                        state = State::CatenateUrl;
+                                    } else if value.eq_ignore_ascii_case(b"text") {
+                                        state = State::CatenateText;
+                                    } else {
+                                        return Err(bad(
+                                            self.tag.to_string(),
+                                            "Expected URL or TEXT catenate part.",
+                                        ));
+                                    }
+                                }
+                                State::CatenateUrl => {
+                                    message.catenate.push(CatenatePart::Url(
+                                        String::from_utf8(value).map_err(|_| {
+                                            bad(self.tag.to_string(), "Invalid UTF-8 in URL.")
+                                        })?,
+                                    ));
+                                    state = State::CatenateParts;
+                                }
+                                State::CatenateText => {
+                                    message.catenate.push(CatenatePart::Text(value));
+                                    state = State::CatenateParts;
+                                }
                                 State::UTF8Data => {
                                     if message.message.is_empty() {
                                         message.message = value;
@@ -147,7 +195,7 @@ mod tests {
 
     use crate::{
         protocol::{
-            append::{self, Message},
+            append::{self, CatenatePart, Message},
             Flag, ProtocolVersion,
         },
         receiver::{Error, Receiver},
@@ -167,6 +215,7 @@ mod tests {
                         message: vec![b'a'],
                         flags: vec![Flag::Seen],
                         received_at: None,
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -179,6 +228,7 @@ mod tests {
                         message: vec![b'a'],
                         flags: vec![Flag::Seen, Flag::Draft, Flag::MDNSent],
                         received_at: None,
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -191,6 +241,7 @@ mod tests {
                         message: vec![b'a'],
                         flags: vec![Flag::Junk],
                         received_at: Some(760689784),
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -203,6 +254,7 @@ mod tests {
                         message: vec![b'a'],
                         flags: vec![],
                         received_at: Some(1668977999),
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -215,6 +267,7 @@ mod tests {
                         message: vec![b'a'],
                         flags: vec![],
                         received_at: Some(1668977999),
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -227,6 +280,7 @@ mod tests {
                         message: vec![b'h', b'e', b'l', b'l', b'o'],
                         flags: vec![Flag::Draft],
                         received_at: None,
+                        catenate: vec![],
                     }],
                 },
             ),
@@ -239,6 +293,27 @@ mod tests {
                         message: vec![b'h', b'e', b'l', b'l', b'o'],
                         flags: vec![Flag::Draft],
                         received_at: Some(1668977999),
+                        catenate: vec![],
+                    }],
+                },
+            ),
+            (
+                concat!(
+                    "A003 APPEND Drafts (\\Draft) CATENATE (TEXT {7+}\r\nHello, ",
+                    "URL \"/INBOX;UIDVALIDITY=385759045/;UID=20\" TEXT {2+}\r\n\r\n)\r\n"
+                ),
+                append::Arguments {
+                    tag: "A003".to_string(),
+                    mailbox_name: "Drafts".to_string(),
+                    messages: vec![Message {
+                        message: vec![],
+                        flags: vec![Flag::Draft],
+                        received_at: None,
+                        catenate: vec![
+                            CatenatePart::Text(b"Hello, ".to_vec()),
+                            CatenatePart::Url("/INBOX;UIDVALIDITY=385759045/;UID=20".to_string()),
+                            CatenatePart::Text(b"\r\n".to_vec()),
+                        ],
                     }],
                 },
             ),
@@ -301,6 +376,7 @@ mod tests {
                                     .to_vec(),
                                     flags: vec![Flag::Seen],
                                     received_at: None,
+                                    catenate: vec![],
                                 },
                                 Message {
                                     message: concat!(
@@ -317,6 +393,7 @@ mod tests {
                                     .to_vec(),
                                     flags: vec![Flag::Seen],
                                     received_at: Some(760689784),
+                                    catenate: vec![],
                                 }
                             ],
                         },
@@ -328,5 +405,21 @@ mod tests {
                 },
             }
         }
+
+        // Invalid catenate parts
+        for command in [
+            "A003 APPEND Drafts CATENATE ()\r\n",
+            "A003 APPEND Drafts CATENATE (FILE \"/INBOX/;UID=20\")\r\n",
+            "A003 APPEND Drafts CATENATE (URL)\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .expect(command)
+                    .parse_append(ProtocolVersion::Rev1)
+                    .is_err(),
+                "{command}"
+            );
+        }
     }
 }
@@ -0,0 +1,76 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    Command,
+    protocol::compress::{self, Algorithm},
+    receiver::{Request, bad},
+};
+
+impl Request<Command> {
+    pub fn parse_compress(self) -> trc::Result<compress::Arguments> {
+        if self.tokens.len() != 1 {
+            return Err(bad(self.tag, "Expected compression algorithm."));
+        }
+
+        let algorithm = self.tokens.into_iter().next().unwrap().unwrap_bytes();
+        if algorithm.eq_ignore_ascii_case(b"DEFLATE") {
+            Ok(compress::Arguments {
+                tag: self.tag,
+                algorithm: Algorithm::Deflate,
+            })
+        } else {
+            Err(bad(
+                self.tag,
+                format!(
+                    "Unsupported compression algorithm '{}'.",
+                    String::from_utf8_lossy(&algorithm)
+                ),
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::compress::{self, Algorithm},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_compress() {
+        let mut receiver = Receiver::new();
+
+        assert_eq!(
+            receiver
+                .parse(&mut "A1 COMPRESS DEFLATE\r\n".as_bytes().iter())
+                .unwrap()
+                .parse_compress()
+                .unwrap(),
+            compress::Arguments {
+                tag: "A1".to_string(),
+                algorithm: Algorithm::Deflate,
+            }
+        );
+
+        for command in [
+            "A2 COMPRESS\r\n",
+            "A3 COMPRESS GZIP\r\n",
+            "A4 COMPRESS DEFLATE DEFLATE\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_compress()
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+}
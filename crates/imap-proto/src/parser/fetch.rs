@@ -14,7 +14,7 @@ use crate::{
     receiver::{Request, Token, bad},
 };
 
-use super::{PushUnique, parse_number, parse_sequence_set};
+use super::{PushUnique, parse_number, parse_partial_range, parse_sequence_set};
 
 impl Request<Command> {
     #[allow(clippy::while_let_on_iterator)]
@@ -334,6 +334,9 @@ impl Request<Command> {
                         "THREADID" => {
                             attributes.push_unique(Attribute::ThreadId);
                         },
+                        "SAVEDATE" => {
+                            attributes.push_unique(Attribute::SaveDate);
+                        },
                         _ => {
                             return Err(bad(
                                 self.tag,
@@ -369,9 +372,10 @@ impl Request<Command> {
             }
         }
 
-        // CONDSTORE parameters
+        // CONDSTORE and PARTIAL parameters
         let mut changed_since = None;
         let mut include_vanished = false;
+        let mut partial = None;
         if let Some(Token::ParenthesisOpen) = tokens.peek() {
             tokens.next();
             while let Some(token) = tokens.next() {
@@ -391,6 +395,18 @@ impl Request<Command> {
                     Token::Argument(param) if param.eq_ignore_ascii_case(b"VANISHED") => {
                         include_vanished = true;
                     }
+                    Token::Argument(param) if param.eq_ignore_ascii_case(b"PARTIAL") => {
+                        partial = parse_partial_range(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| {
+                                    bad(self.tag.to_string(), "Missing PARTIAL parameter.")
+                                })?
+                                .unwrap_bytes(),
+                        )
+                        .map_err(|v| bad(self.tag.to_string(), v))?
+                        .into();
+                    }
                     Token::ParenthesisClose => {
                         break;
                     }
@@ -411,6 +427,7 @@ impl Request<Command> {
                 attributes,
                 changed_since,
                 include_vanished,
+                partial,
             })
         } else {
             Err(bad(self.tag, "No data items to fetch specified."))
@@ -499,7 +516,7 @@ pub fn parse_partial(tokens: &mut Peekable<IntoIter<Token>>) -> super::Result<Op
 mod tests {
     use crate::{
         protocol::{
-            Sequence,
+            PartialRange, Sequence,
             fetch::{self, Attribute, Section},
         },
         receiver::Receiver,
@@ -528,6 +545,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -542,6 +560,7 @@ mod tests {
                     }],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -556,6 +575,7 @@ mod tests {
                     }],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -576,6 +596,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -597,6 +618,7 @@ mod tests {
                     }],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -619,6 +641,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -635,6 +658,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -650,6 +674,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -723,6 +748,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -738,6 +764,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -754,6 +781,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -768,6 +796,7 @@ mod tests {
                     ],
                     changed_since: None,
                     include_vanished: false,
+                    partial: None,
                 },
             ),
             (
@@ -778,6 +807,7 @@ mod tests {
                     attributes: vec![Attribute::Flags, Attribute::ModSeq],
                     changed_since: 12345.into(),
                     include_vanished: true,
+                    partial: None,
                 },
             ),
             (
@@ -788,6 +818,22 @@ mod tests {
                     attributes: vec![Attribute::Uid],
                     changed_since: 1.into(),
                     include_vanished: true,
+                    partial: None,
+                },
+            ),
+            (
+                "A3 UID FETCH 1:* (FLAGS SAVEDATE) (PARTIAL -1:-30)\r\n",
+                fetch::Arguments {
+                    tag: "A3".to_string(),
+                    sequence_set: Sequence::range(1.into(), None),
+                    attributes: vec![Attribute::Flags, Attribute::SaveDate],
+                    changed_since: None,
+                    include_vanished: false,
+                    partial: Some(PartialRange {
+                        start: 1,
+                        end: 30,
+                        from_end: true,
+                    }),
                 },
             ),
         ] {
@@ -0,0 +1,349 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    Command,
+    protocol::{
+        ProtocolVersion,
+        metadata::{self, Depth, is_valid_entry},
+    },
+    receiver::{Request, Token, bad},
+    utf7::utf7_maybe_decode,
+};
+
+use super::parse_number;
+
+/*
+
+   getmetadata     = "GETMETADATA" [SP getmetadata-options]
+                     SP mailbox SP getmetadata-entries
+
+   getmetadata-options = "(" getmetadata-option
+                         *(SP getmetadata-option) ")"
+
+   getmetadata-option  = maxsize-opt / scope-opt
+
+   maxsize-opt     = "MAXSIZE" SP number
+
+   scope-opt       = "DEPTH" SP ("0" / "1" / "infinity")
+
+   getmetadata-entries = "(" entry *(SP entry) ")" / entry
+
+   setmetadata     = "SETMETADATA" SP mailbox
+                     SP entry-values
+
+   entry-values    = "(" entry-value *(SP entry-value) ")"
+
+   entry-value     = entry SP value
+
+   value           = nstring / literal8
+
+*/
+
+impl Request<Command> {
+    pub fn parse_get_metadata(
+        self,
+        version: ProtocolVersion,
+    ) -> trc::Result<metadata::GetArguments> {
+        let mut tokens = self.tokens.into_iter().peekable();
+        let mut max_size = None;
+        let mut depth = Depth::Zero;
+
+        // Parse options
+        if tokens
+            .peek()
+            .is_some_and(|token| token.is_parenthesis_open())
+        {
+            tokens.next();
+            while let Some(token) = tokens.next() {
+                match token {
+                    Token::ParenthesisClose => break,
+                    Token::Argument(value) if value.eq_ignore_ascii_case(b"MAXSIZE") => {
+                        max_size = parse_number::<usize>(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| bad(self.tag.to_string(), "Missing MAXSIZE value."))?
+                                .unwrap_bytes(),
+                        )
+                        .map_err(|v| bad(self.tag.to_string(), v))?
+                        .into();
+                    }
+                    Token::Argument(value) if value.eq_ignore_ascii_case(b"DEPTH") => {
+                        let value = tokens
+                            .next()
+                            .ok_or_else(|| bad(self.tag.to_string(), "Missing DEPTH value."))?
+                            .unwrap_bytes();
+                        depth = hashify::tiny_map_ignore_case!(value.as_slice(),
+                            "0" => Depth::Zero,
+                            "1" => Depth::One,
+                            "infinity" => Depth::Infinity,
+                        )
+                        .ok_or_else(|| {
+                            bad(
+                                self.tag.to_string(),
+                                format!(
+                                    "Invalid DEPTH value '{}'.",
+                                    String::from_utf8_lossy(&value)
+                                ),
+                            )
+                        })?;
+                    }
+                    token => {
+                        return Err(bad(
+                            self.tag.to_string(),
+                            format!("Unsupported option '{}'.", token),
+                        ));
+                    }
+                }
+            }
+        }
+
+        // Parse mailbox name
+        let mailbox_name = utf7_maybe_decode(
+            tokens
+                .next()
+                .ok_or_else(|| bad(self.tag.to_string(), "Missing mailbox name."))?
+                .unwrap_string()
+                .map_err(|v| bad(self.tag.to_string(), v))?,
+            version,
+        );
+
+        // Parse entries
+        let mut entries = Vec::new();
+        match tokens.next() {
+            Some(Token::ParenthesisOpen) => {
+                for token in tokens.by_ref() {
+                    match token {
+                        Token::ParenthesisClose => break,
+                        token => {
+                            entries.push(
+                                token
+                                    .unwrap_string()
+                                    .map_err(|v| bad(self.tag.to_string(), v))?,
+                            );
+                        }
+                    }
+                }
+            }
+            Some(token) => {
+                entries.push(
+                    token
+                        .unwrap_string()
+                        .map_err(|v| bad(self.tag.to_string(), v))?,
+                );
+            }
+            None => (),
+        }
+
+        if entries.is_empty() {
+            Err(bad(self.tag, "Missing entry names."))
+        } else if let Some(entry) = entries.iter().find(|entry| !is_valid_entry(entry)) {
+            Err(bad(
+                self.tag.to_string(),
+                format!("Invalid entry name '{entry}'."),
+            ))
+        } else {
+            Ok(metadata::GetArguments {
+                tag: self.tag,
+                mailbox_name,
+                entries,
+                max_size,
+                depth,
+            })
+        }
+    }
+
+    pub fn parse_set_metadata(
+        self,
+        version: ProtocolVersion,
+    ) -> trc::Result<metadata::SetArguments> {
+        let mut tokens = self.tokens.into_iter();
+
+        // Parse mailbox name
+        let mailbox_name = utf7_maybe_decode(
+            tokens
+                .next()
+                .ok_or_else(|| bad(self.tag.to_string(), "Missing mailbox name."))?
+                .unwrap_string()
+                .map_err(|v| bad(self.tag.to_string(), v))?,
+            version,
+        );
+
+        // Parse entry values
+        if tokens
+            .next()
+            .is_none_or(|token| !token.is_parenthesis_open())
+        {
+            return Err(bad(self.tag, "Expected '(' before entry values."));
+        }
+        let mut entries = Vec::new();
+        while let Some(token) = tokens.next() {
+            let name = match token {
+                Token::ParenthesisClose => break,
+                token => token
+                    .unwrap_string()
+                    .map_err(|v| bad(self.tag.to_string(), v))?,
+            };
+            if !is_valid_entry(&name) {
+                return Err(bad(self.tag, format!("Invalid entry name '{name}'.")));
+            }
+            let value = match tokens.next() {
+                Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NIL") => None,
+                Some(Token::Argument(value)) => Some(value),
+                Some(Token::Nil) => Some(vec![]),
+                _ => {
+                    return Err(bad(self.tag, format!("Missing value for entry '{name}'.")));
+                }
+            };
+            entries.push((name, value));
+        }
+
+        if !entries.is_empty() {
+            Ok(metadata::SetArguments {
+                tag: self.tag,
+                mailbox_name,
+                entries,
+            })
+        } else {
+            Err(bad(self.tag, "Missing entry values."))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            ProtocolVersion,
+            metadata::{self, Depth},
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_get_metadata() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 GETMETADATA INBOX /private/comment\r\n",
+                metadata::GetArguments {
+                    tag: "A1".to_string(),
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec!["/private/comment".to_string()],
+                    max_size: None,
+                    depth: Depth::Zero,
+                },
+            ),
+            (
+                "A2 GETMETADATA (MAXSIZE 1024 DEPTH infinity) \"\" (/shared/comment /private/vendor)\r\n",
+                metadata::GetArguments {
+                    tag: "A2".to_string(),
+                    mailbox_name: "".to_string(),
+                    entries: vec!["/shared/comment".to_string(), "/private/vendor".to_string()],
+                    max_size: Some(1024),
+                    depth: Depth::Infinity,
+                },
+            ),
+            (
+                "A3 GETMETADATA (DEPTH 1) \"Other Folder\" /shared/vendor\r\n",
+                metadata::GetArguments {
+                    tag: "A3".to_string(),
+                    mailbox_name: "Other Folder".to_string(),
+                    entries: vec!["/shared/vendor".to_string()],
+                    max_size: None,
+                    depth: Depth::One,
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(ProtocolVersion::Rev2)
+                    .expect(command),
+                arguments,
+                "{}",
+                command
+            );
+        }
+
+        for command in [
+            "A4 GETMETADATA INBOX\r\n",
+            "A5 GETMETADATA INBOX /comment\r\n",
+            "A6 GETMETADATA (DEPTH 2) INBOX /private/comment\r\n",
+            "A7 GETMETADATA INBOX /private/*\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_get_metadata(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+
+    #[test]
+    fn parse_set_metadata() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 SETMETADATA INBOX (/private/comment \"My new comment\")\r\n",
+                metadata::SetArguments {
+                    tag: "A1".to_string(),
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec![(
+                        "/private/comment".to_string(),
+                        Some(b"My new comment".to_vec()),
+                    )],
+                },
+            ),
+            (
+                "A2 SETMETADATA \"\" (/shared/vendor/a NIL /private/vendor/b {5+}\r\nhello)\r\n",
+                metadata::SetArguments {
+                    tag: "A2".to_string(),
+                    mailbox_name: "".to_string(),
+                    entries: vec![
+                        ("/shared/vendor/a".to_string(), None),
+                        ("/private/vendor/b".to_string(), Some(b"hello".to_vec())),
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_set_metadata(ProtocolVersion::Rev2)
+                    .expect(command),
+                arguments,
+                "{}",
+                command
+            );
+        }
+
+        for command in [
+            "A3 SETMETADATA INBOX\r\n",
+            "A4 SETMETADATA INBOX ()\r\n",
+            "A5 SETMETADATA INBOX (/private/comment)\r\n",
+            "A6 SETMETADATA INBOX (/comment \"value\")\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_set_metadata(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+}
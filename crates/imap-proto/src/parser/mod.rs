@@ -7,6 +7,7 @@
 pub mod acl;
 pub mod append;
 pub mod authenticate;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -15,8 +16,11 @@ pub mod fetch;
 pub mod list;
 pub mod login;
 pub mod lsub;
+pub mod metadata;
+pub mod notify;
 pub mod quota;
 pub mod rename;
+pub mod replace;
 pub mod search;
 pub mod select;
 pub mod sort;
@@ -24,6 +28,7 @@ pub mod status;
 pub mod store;
 pub mod subscribe;
 pub mod thread;
+pub mod urlauth;
 
 use std::{borrow::Cow, str::FromStr};
 
@@ -31,7 +36,7 @@ use chrono::{DateTime, NaiveDate};
 
 use crate::{
     Command,
-    protocol::{Flag, Sequence},
+    protocol::{Flag, PartialRange, Sequence},
     receiver::CommandParser,
 };
 
@@ -80,6 +85,15 @@ impl CommandParser for Command {
             "ID" => Command::Id,
             "GETQUOTA" => Command::GetQuota,
             "GETQUOTAROOT" => Command::GetQuotaRoot,
+            "NOTIFY" => Command::Notify,
+            "GETMETADATA" => Command::GetMetadata,
+            "SETMETADATA" => Command::SetMetadata,
+            "COMPRESS" => Command::Compress,
+            "REPLACE" => Command::Replace(uid),
+            "ESEARCH" => Command::Esearch,
+            "RESETKEY" => Command::ResetKey,
+            "GENURLAUTH" => Command::GenUrlAuth,
+            "URLFETCH" => Command::UrlFetch,
         )
     }
 
@@ -365,6 +379,32 @@ pub fn parse_sequence_set(value: &[u8]) -> Result<Sequence> {
     }
 }
 
+pub fn parse_partial_range(value: &[u8]) -> Result<PartialRange> {
+    let invalid = || {
+        Cow::from(format!(
+            "Invalid partial range {:?}.",
+            String::from_utf8_lossy(value)
+        ))
+    };
+    let (start, end) = std::str::from_utf8(value)
+        .ok()
+        .and_then(|range| range.split_once(':'))
+        .ok_or_else(invalid)?;
+    let (start, end, from_end) = match (start.strip_prefix('-'), end.strip_prefix('-')) {
+        (Some(start), Some(end)) => (start, end, true),
+        (None, None) => (start, end, false),
+        _ => return Err(invalid()),
+    };
+    match (start.parse::<u32>(), end.parse::<u32>()) {
+        (Ok(start), Ok(end)) if start > 0 && end > 0 => Ok(PartialRange {
+            start: start.min(end),
+            end: start.max(end),
+            from_end,
+        }),
+        _ => Err(invalid()),
+    }
+}
+
 pub trait PushUnique<T> {
     fn push_unique(&mut self, value: T);
 }
@@ -379,7 +419,7 @@ impl<T: PartialEq> PushUnique<T> for Vec<T> {
 
 #[cfg(test)]
 mod tests {
-    use crate::protocol::Sequence;
+    use crate::protocol::{PartialRange, Sequence};
 
     #[test]
     fn parse_sequence_set() {
@@ -453,4 +493,46 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn parse_partial_range() {
+        for (range, expected_result) in [
+            ("1:100", Some((1, 100, false))),
+            ("100:1", Some((1, 100, false))),
+            ("-1:-100", Some((1, 100, true))),
+            ("-50:-10", Some((10, 50, true))),
+            ("0:10", None),
+            ("-1:10", None),
+            ("1:*", None),
+            ("10", None),
+        ] {
+            assert_eq!(
+                super::parse_partial_range(range.as_bytes()).ok(),
+                expected_result.map(|(start, end, from_end)| PartialRange {
+                    start,
+                    end,
+                    from_end
+                }),
+                "{range}"
+            );
+        }
+
+        let items = (1..=10).collect::<Vec<u32>>();
+        for (range, expected_result) in [
+            ("1:3", vec![1, 2, 3]),
+            ("8:20", vec![8, 9, 10]),
+            ("11:20", vec![]),
+            ("-1:-3", vec![8, 9, 10]),
+            ("-9:-20", vec![1, 2]),
+            ("-11:-20", vec![]),
+        ] {
+            assert_eq!(
+                super::parse_partial_range(range.as_bytes())
+                    .unwrap()
+                    .slice(&items),
+                expected_result.as_slice(),
+                "{range}"
+            );
+        }
+    }
 }
@@ -0,0 +1,398 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{iter::Peekable, vec::IntoIter};
+
+use crate::{
+    Command,
+    protocol::{
+        ProtocolVersion, fetch,
+        notify::{self, Event, EventGroup, Filter},
+    },
+    receiver::{Receiver, Request, Token, bad},
+    utf7::utf7_maybe_decode,
+};
+
+impl Request<Command> {
+    pub fn parse_notify(self, version: ProtocolVersion) -> trc::Result<notify::Arguments> {
+        if self.tokens.is_empty() {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        let mut tokens = self.tokens.into_iter().peekable();
+
+        match tokens.next() {
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => {
+                if tokens.next().is_none() {
+                    Ok(notify::Arguments::None { tag: self.tag })
+                } else {
+                    Err(bad(self.tag, "Unexpected arguments after NONE."))
+                }
+            }
+            Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"SET") => {
+                let mut status = false;
+                let mut groups = Vec::new();
+
+                if tokens
+                    .peek()
+                    .is_some_and(|token| token.eq_ignore_ascii_case(b"STATUS"))
+                {
+                    tokens.next();
+                    status = true;
+                }
+
+                while let Some(token) = tokens.next() {
+                    if !token.is_parenthesis_open() {
+                        return Err(bad(self.tag, "Expected '(' before event group."));
+                    }
+                    let filter = parse_filter(&mut tokens, version)
+                        .map_err(|v| bad(self.tag.to_string(), v))?;
+                    let events = parse_events(&mut tokens, &self.tag)?;
+                    if tokens
+                        .next()
+                        .is_none_or(|token| !token.is_parenthesis_close())
+                    {
+                        return Err(bad(self.tag, "Expected ')' after event group."));
+                    }
+
+                    // Validate event combinations
+                    let group = EventGroup { filter, events };
+                    let has_new = group.has_event(&Event::MessageNew { attributes: vec![] });
+                    let has_expunge = group.has_event(&Event::MessageExpunge);
+                    if has_new != has_expunge {
+                        return Err(bad(
+                            self.tag,
+                            "MessageNew and MessageExpunge must be specified together.",
+                        ));
+                    } else if !has_new
+                        && (group.has_event(&Event::FlagChange)
+                            || group.has_event(&Event::AnnotationChange))
+                    {
+                        return Err(bad(
+                            self.tag,
+                            "FlagChange and AnnotationChange require MessageNew and MessageExpunge.",
+                        ));
+                    } else if group.filter.is_selected()
+                        && (group.has_event(&Event::MailboxName)
+                            || group.has_event(&Event::SubscriptionChange)
+                            || group.has_event(&Event::MailboxMetadataChange)
+                            || group.has_event(&Event::ServerMetadataChange))
+                    {
+                        return Err(bad(
+                            self.tag,
+                            "Mailbox events are not allowed for the selected mailbox.",
+                        ));
+                    } else if !group.filter.is_selected()
+                        && group
+                            .new_message_attributes()
+                            .is_some_and(|attributes| !attributes.is_empty())
+                    {
+                        return Err(bad(
+                            self.tag,
+                            "Fetch attributes are only allowed for the selected mailbox.",
+                        ));
+                    } else if groups
+                        .iter()
+                        .any(|g: &EventGroup| g.filter.is_selected() && group.filter.is_selected())
+                    {
+                        return Err(bad(
+                            self.tag,
+                            "SELECTED and SELECTED-DELAYED can only be specified once.",
+                        ));
+                    }
+
+                    groups.push(group);
+                }
+
+                if !groups.is_empty() {
+                    Ok(notify::Arguments::Set {
+                        tag: self.tag,
+                        status,
+                        groups,
+                    })
+                } else {
+                    Err(bad(self.tag, "Missing event groups."))
+                }
+            }
+            _ => Err(bad(self.tag, "Expected SET or NONE.")),
+        }
+    }
+}
+
+pub(crate) fn parse_filter(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<Filter> {
+    let filter = tokens
+        .next()
+        .ok_or("Missing filter.")?
+        .unwrap_bytes();
+
+    if filter.eq_ignore_ascii_case(b"SUBTREE") {
+        parse_mailboxes(tokens, version).map(Filter::Subtree)
+    } else if filter.eq_ignore_ascii_case(b"SUBTREE-ONE") {
+        parse_mailboxes(tokens, version).map(Filter::SubtreeOne)
+    } else if filter.eq_ignore_ascii_case(b"MAILBOXES") {
+        parse_mailboxes(tokens, version).map(Filter::Mailboxes)
+    } else {
+        hashify::tiny_map_ignore_case!(filter.as_slice(),
+            "SELECTED" => Filter::Selected,
+            "SELECTED-DELAYED" => Filter::SelectedDelayed,
+            "INBOXES" => Filter::Inboxes,
+            "PERSONAL" => Filter::Personal,
+            "SUBSCRIBED" => Filter::Subscribed,
+        )
+        .ok_or_else(|| format!("Invalid filter '{}'.", String::from_utf8_lossy(&filter)).into())
+    }
+}
+
+fn parse_mailboxes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    version: ProtocolVersion,
+) -> super::Result<Vec<String>> {
+    let mut mailboxes = Vec::new();
+
+    match tokens.next() {
+        Some(Token::ParenthesisOpen) => {
+            for token in tokens.by_ref() {
+                match token {
+                    Token::ParenthesisClose => break,
+                    token => {
+                        mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+                    }
+                }
+            }
+        }
+        Some(token @ Token::Argument(_)) => {
+            mailboxes.push(utf7_maybe_decode(token.unwrap_string()?, version));
+        }
+        _ => return Err("Expected mailbox name or list.".into()),
+    }
+
+    if !mailboxes.is_empty() {
+        Ok(mailboxes)
+    } else {
+        Err("Empty mailbox list.".into())
+    }
+}
+
+fn parse_events(tokens: &mut Peekable<IntoIter<Token>>, tag: &str) -> trc::Result<Vec<Event>> {
+    let mut events = Vec::new();
+
+    match tokens.next() {
+        Some(Token::Argument(value)) if value.eq_ignore_ascii_case(b"NONE") => {
+            return Ok(events);
+        }
+        Some(Token::ParenthesisOpen) => {}
+        _ => return Err(bad(tag.to_string(), "Expected event list or NONE.")),
+    }
+
+    while let Some(token) = tokens.next() {
+        let value = match token {
+            Token::ParenthesisClose => break,
+            Token::Argument(value) => value,
+            _ => return Err(bad(tag.to_string(), "Invalid event.")),
+        };
+
+        let event = hashify::tiny_map_ignore_case!(value.as_slice(),
+            "MessageNew" => Event::MessageNew { attributes: vec![] },
+            "MessageExpunge" => Event::MessageExpunge,
+            "FlagChange" => Event::FlagChange,
+            "AnnotationChange" => Event::AnnotationChange,
+            "MailboxName" => Event::MailboxName,
+            "SubscriptionChange" => Event::SubscriptionChange,
+            "MailboxMetadataChange" => Event::MailboxMetadataChange,
+            "ServerMetadataChange" => Event::ServerMetadataChange,
+        )
+        .ok_or_else(|| {
+            bad(
+                tag.to_string(),
+                format!("Invalid event '{}'.", String::from_utf8_lossy(&value)),
+            )
+        })?;
+
+        if matches!(event, Event::MessageNew { .. })
+            && tokens.peek().is_some_and(|token| token.is_parenthesis_open())
+        {
+            events.push(Event::MessageNew {
+                attributes: parse_fetch_attributes(tokens, tag)?,
+            });
+        } else {
+            events.push(event);
+        }
+    }
+
+    Ok(events)
+}
+
+fn parse_fetch_attributes(
+    tokens: &mut Peekable<IntoIter<Token>>,
+    tag: &str,
+) -> trc::Result<Vec<fetch::Attribute>> {
+    // NOTIFY is not tokenized with brackets, so the fetch attributes
+    // are rebuilt as a FETCH request and parsed with the FETCH parser.
+    let mut request = b"N FETCH 1 ".to_vec();
+    let mut depth = 0;
+
+    for token in tokens.by_ref() {
+        match token {
+            Token::ParenthesisOpen => {
+                depth += 1;
+                request.push(b'(');
+            }
+            Token::ParenthesisClose => {
+                depth -= 1;
+                request.push(b')');
+                if depth == 0 {
+                    break;
+                }
+            }
+            Token::Argument(value) => {
+                request.extend_from_slice(&value);
+            }
+            Token::Nil => {
+                request.extend_from_slice(b"NIL");
+            }
+            _ => {}
+        }
+        request.push(b' ');
+    }
+    request.extend_from_slice(b"\r\n");
+
+    Receiver::<Command>::new()
+        .parse(&mut request.iter())
+        .ok()
+        .and_then(|request| request.parse_fetch().ok())
+        .map(|arguments| arguments.attributes)
+        .ok_or_else(|| bad(tag.to_string(), "Invalid MessageNew fetch attributes."))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{
+            ProtocolVersion,
+            fetch::{Attribute, Section},
+            notify::{self, Event, EventGroup, Filter},
+        },
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_notify() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 NOTIFY NONE\r\n",
+                notify::Arguments::None {
+                    tag: "A1".to_string(),
+                },
+            ),
+            (
+                concat!(
+                    "A2 NOTIFY SET STATUS (selected (MessageNew (UID ",
+                    "BODY.PEEK[HEADER.FIELDS (From Subject)]) MessageExpunge)) ",
+                    "(subtree (Lists \"Other Users\") (MessageNew MessageExpunge FlagChange)) ",
+                    "(personal (MailboxName SubscriptionChange))\r\n"
+                ),
+                notify::Arguments::Set {
+                    tag: "A2".to_string(),
+                    status: true,
+                    groups: vec![
+                        EventGroup {
+                            filter: Filter::Selected,
+                            events: vec![
+                                Event::MessageNew {
+                                    attributes: vec![
+                                        Attribute::Uid,
+                                        Attribute::BodySection {
+                                            peek: true,
+                                            sections: vec![Section::HeaderFields {
+                                                not: false,
+                                                fields: vec![
+                                                    "From".to_string(),
+                                                    "Subject".to_string(),
+                                                ],
+                                            }],
+                                            partial: None,
+                                        },
+                                    ],
+                                },
+                                Event::MessageExpunge,
+                            ],
+                        },
+                        EventGroup {
+                            filter: Filter::Subtree(vec![
+                                "Lists".to_string(),
+                                "Other Users".to_string(),
+                            ]),
+                            events: vec![
+                                Event::MessageNew { attributes: vec![] },
+                                Event::MessageExpunge,
+                                Event::FlagChange,
+                            ],
+                        },
+                        EventGroup {
+                            filter: Filter::Personal,
+                            events: vec![Event::MailboxName, Event::SubscriptionChange],
+                        },
+                    ],
+                },
+            ),
+            (
+                "A3 NOTIFY SET (mailboxes INBOX NONE) (inboxes (MessageExpunge MessageNew))\r\n",
+                notify::Arguments::Set {
+                    tag: "A3".to_string(),
+                    status: false,
+                    groups: vec![
+                        EventGroup {
+                            filter: Filter::Mailboxes(vec!["INBOX".to_string()]),
+                            events: vec![],
+                        },
+                        EventGroup {
+                            filter: Filter::Inboxes,
+                            events: vec![
+                                Event::MessageExpunge,
+                                Event::MessageNew { attributes: vec![] },
+                            ],
+                        },
+                    ],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .expect(command),
+                arguments,
+                "{}",
+                command
+            );
+        }
+
+        for command in [
+            "A4 NOTIFY SET (selected (MessageNew))\r\n",
+            "A5 NOTIFY SET (personal (FlagChange))\r\n",
+            "A6 NOTIFY SET (selected (MailboxName))\r\n",
+            "A7 NOTIFY SET (personal (MessageNew (UID) MessageExpunge))\r\n",
+            "A8 NOTIFY SET (unknown (MailboxName))\r\n",
+            "A9 NOTIFY SET STATUS\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_notify(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+}
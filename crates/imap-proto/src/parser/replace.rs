@@ -0,0 +1,130 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    Command,
+    protocol::{ProtocolVersion, Sequence, replace},
+    receiver::{Request, bad},
+};
+
+use super::parse_number;
+
+/*
+
+   replace         = "REPLACE" SP seq-number SP mailbox append-message
+
+   uid-replace     = "UID" SP "REPLACE" SP uniqueid SP mailbox append-message
+
+*/
+
+impl Request<Command> {
+    pub fn parse_replace(self, version: ProtocolVersion) -> trc::Result<replace::Arguments> {
+        if self.tokens.len() < 3 {
+            return Err(self.into_error("Missing arguments."));
+        }
+
+        // Parse message number
+        let mut tokens = self.tokens.into_iter();
+        let number = parse_number::<u32>(&tokens.next().unwrap().unwrap_bytes())
+            .map_err(|v| bad(self.tag.to_string(), v))?;
+        if number == 0 {
+            return Err(bad(self.tag, "Invalid message number."));
+        }
+
+        // Parse mailbox and message
+        let mut arguments = Request {
+            tag: self.tag,
+            command: self.command,
+            tokens: tokens.collect(),
+        }
+        .parse_append(version)?;
+        if arguments.messages.len() != 1
+            || (arguments.messages[0].message.is_empty()
+                && arguments.messages[0].catenate.is_empty())
+        {
+            return Err(bad(arguments.tag, "Expected exactly one message."));
+        }
+
+        Ok(replace::Arguments {
+            sequence: Sequence::number(number),
+            mailbox_name: arguments.mailbox_name,
+            message: arguments.messages.pop().unwrap(),
+            tag: arguments.tag,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{Flag, ProtocolVersion, Sequence, append::Message, replace},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_replace() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 REPLACE 4 Drafts {11+}\r\nHello world\r\n",
+                replace::Arguments {
+                    tag: "A1".to_string(),
+                    sequence: Sequence::number(4),
+                    mailbox_name: "Drafts".to_string(),
+                    message: Message {
+                        message: b"Hello world".to_vec(),
+                        flags: vec![],
+                        received_at: None,
+                        catenate: vec![],
+                    },
+                },
+            ),
+            (
+                "A2 UID REPLACE 2000 \"Other Folder\" (\\Seen \\Draft) \"7-Feb-1994 21:52:25 -0800\" {5+}\r\nhello\r\n",
+                replace::Arguments {
+                    tag: "A2".to_string(),
+                    sequence: Sequence::number(2000),
+                    mailbox_name: "Other Folder".to_string(),
+                    message: Message {
+                        message: b"hello".to_vec(),
+                        flags: vec![Flag::Seen, Flag::Draft],
+                        received_at: Some(760686745),
+                        catenate: vec![],
+                    },
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_replace(ProtocolVersion::Rev2)
+                    .expect(command),
+                arguments,
+                "{}",
+                command
+            );
+        }
+
+        for command in [
+            "A3 REPLACE 1 Drafts\r\n",
+            "A4 REPLACE 0 Drafts {5+}\r\nhello\r\n",
+            "A5 REPLACE 1:2 Drafts {5+}\r\nhello\r\n",
+            "A6 REPLACE 1 Drafts {5+}\r\nhello {5+}\r\nworld\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_replace(ProtocolVersion::Rev2)
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
+}
@@ -12,12 +12,14 @@ use mail_parser::decoders::charsets::DecoderFnc;
 use mail_parser::decoders::charsets::map::charset_decoder;
 
 use crate::Command;
+use crate::protocol::notify;
 use crate::protocol::search::{self, Filter};
 use crate::protocol::search::{ModSeqEntry, ResultOption};
 use crate::protocol::{Flag, ProtocolVersion};
 use crate::receiver::{Request, Token, bad};
 
-use super::{parse_date, parse_number, parse_sequence_set};
+use super::notify::parse_filter;
+use super::{parse_date, parse_number, parse_partial_range, parse_sequence_set};
 
 impl Request<Command> {
     #[allow(clippy::while_let_on_iterator)]
@@ -66,6 +68,49 @@ impl Request<Command> {
             }),
         }
     }
+
+    pub fn parse_esearch(self, version: ProtocolVersion) -> trc::Result<search::EsearchArguments> {
+        let mut tokens = self.tokens.into_iter().peekable();
+        let mut source = Vec::new();
+
+        if tokens
+            .peek()
+            .is_some_and(|token| token.eq_ignore_ascii_case(b"IN"))
+        {
+            tokens.next();
+            if tokens
+                .next()
+                .is_none_or(|token| !token.is_parenthesis_open())
+            {
+                return Err(bad(self.tag, "Expected '(' after IN."));
+            }
+            while tokens
+                .peek()
+                .is_some_and(|token| !token.is_parenthesis_close())
+            {
+                source.push(
+                    parse_filter(&mut tokens, version).map_err(|v| bad(self.tag.to_string(), v))?,
+                );
+            }
+            if tokens.next().is_none() {
+                return Err(bad(self.tag, "Expected ')' after source mailboxes."));
+            } else if source.is_empty() {
+                return Err(bad(self.tag, "Missing source mailboxes."));
+            }
+        } else {
+            source.push(notify::Filter::Selected);
+        }
+
+        let mut arguments = Request {
+            tag: self.tag,
+            command: self.command,
+            tokens: tokens.collect(),
+        }
+        .parse_search(version)?;
+        arguments.is_esearch = true;
+
+        Ok(search::EsearchArguments { source, arguments })
+    }
 }
 
 pub fn parse_result_options(
@@ -79,9 +124,17 @@ pub fn parse_result_options(
         return Err(Cow::from("Invalid result option, expected parenthesis."));
     }
 
-    for token in tokens {
+    while let Some(token) = tokens.next() {
         match token {
             Token::ParenthesisClose => break,
+            Token::Argument(value) if value.eq_ignore_ascii_case(b"partial") => {
+                result_options.push(ResultOption::Partial(parse_partial_range(
+                    &tokens
+                        .next()
+                        .ok_or_else(|| Cow::from("Missing partial range."))?
+                        .unwrap_bytes(),
+                )?));
+            }
             Token::Argument(value) => {
                 result_options.push(ResultOption::parse(&value)?);
             }
@@ -89,6 +142,16 @@ pub fn parse_result_options(
         }
     }
 
+    if result_options.contains(&ResultOption::All)
+        && result_options
+            .iter()
+            .any(|option| matches!(option, ResultOption::Partial(_)))
+    {
+        return Err(Cow::from(
+            "The ALL and PARTIAL result options are mutually exclusive.",
+        ));
+    }
+
     Ok(result_options)
 }
 
@@ -416,6 +479,37 @@ pub fn parse_filters(
                                 .unwrap_string()?,
                         ));
 
+                    },
+                    "SAVEDBEFORE" => {
+                        filters.push(Filter::SavedBefore(parse_date(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| Cow::from("Expected date"))?
+                                .unwrap_bytes(),
+                        )?));
+
+                    },
+                    "SAVEDON" => {
+                        filters.push(Filter::SavedOn(parse_date(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| Cow::from("Expected date"))?
+                                .unwrap_bytes(),
+                        )?));
+
+                    },
+                    "SAVEDSINCE" => {
+                        filters.push(Filter::SavedSince(parse_date(
+                            &tokens
+                                .next()
+                                .ok_or_else(|| Cow::from("Expected date"))?
+                                .unwrap_bytes(),
+                        )?));
+
+                    },
+                    "SAVEDATESUPPORTED" => {
+                        filters.push(Filter::SaveDateSupported);
+
                     },
                     "OR" => {
                         if filters_stack.len() > 10 {
@@ -538,7 +632,7 @@ impl ResultOption {
 mod tests {
     use crate::{
         protocol::{
-            Flag, ProtocolVersion, Sequence,
+            Flag, PartialRange, ProtocolVersion, Sequence, notify,
             search::{self, Filter, ModSeqEntry, ResultOption},
         },
         receiver::Receiver,
@@ -850,6 +944,44 @@ mod tests {
                     sort: None,
                 },
             ),
+            (
+                b"A1 UID SEARCH RETURN (COUNT PARTIAL -1:-100) SAVEDATESUPPORTED SAVEDSINCE 1-Feb-1994\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "A1".to_string(),
+                    result_options: vec![
+                        ResultOption::Count,
+                        ResultOption::Partial(PartialRange {
+                            start: 1,
+                            end: 100,
+                            from_end: true,
+                        }),
+                    ],
+                    filter: vec![Filter::SaveDateSupported, Filter::SavedSince(760060800)],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
+            (
+                b"A2 SEARCH RETURN (PARTIAL 1:10) SAVEDON 1-Feb-1994 NOT SAVEDBEFORE 1-Dec-2023\r\n"
+                    .to_vec(),
+                search::Arguments {
+                    tag: "A2".to_string(),
+                    result_options: vec![ResultOption::Partial(PartialRange {
+                        start: 1,
+                        end: 10,
+                        from_end: false,
+                    })],
+                    filter: vec![
+                        Filter::SavedOn(760060800),
+                        Filter::Not,
+                        Filter::SavedBefore(1701388800),
+                        Filter::End,
+                    ],
+                    is_esearch: true,
+                    sort: None,
+                },
+            ),
         ] {
             let command_str = String::from_utf8_lossy(&command).into_owned();
             assert_eq!(
@@ -864,4 +996,85 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn parse_esearch() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 ESEARCH IN (mailboxes \"folder1\" subtree-one \"folder2\") unseen\r\n",
+                search::EsearchArguments {
+                    source: vec![
+                        notify::Filter::Mailboxes(vec!["folder1".to_string()]),
+                        notify::Filter::SubtreeOne(vec!["folder2".to_string()]),
+                    ],
+                    arguments: search::Arguments {
+                        tag: "A1".to_string(),
+                        result_options: vec![],
+                        filter: vec![Filter::Unseen],
+                        is_esearch: true,
+                        sort: None,
+                    },
+                },
+            ),
+            (
+                "A2 ESEARCH IN (personal subtree (a b)) RETURN (MIN MAX) FLAGGED\r\n",
+                search::EsearchArguments {
+                    source: vec![
+                        notify::Filter::Personal,
+                        notify::Filter::Subtree(vec!["a".to_string(), "b".to_string()]),
+                    ],
+                    arguments: search::Arguments {
+                        tag: "A2".to_string(),
+                        result_options: vec![ResultOption::Min, ResultOption::Max],
+                        filter: vec![Filter::Flagged],
+                        is_esearch: true,
+                        sort: None,
+                    },
+                },
+            ),
+            (
+                "A3 ESEARCH RETURN (COUNT) DELETED\r\n",
+                search::EsearchArguments {
+                    source: vec![notify::Filter::Selected],
+                    arguments: search::Arguments {
+                        tag: "A3".to_string(),
+                        result_options: vec![ResultOption::Count],
+                        filter: vec![Filter::Deleted],
+                        is_esearch: true,
+                        sort: None,
+                    },
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_esearch(ProtocolVersion::Rev1)
+                    .expect(command),
+                arguments,
+                "{}",
+                command
+            );
+        }
+
+        for command in [
+            "A4 ESEARCH IN () ALL\r\n",
+            "A5 ESEARCH IN (everything) ALL\r\n",
+            "A6 ESEARCH IN (personal) RETURN (ALL PARTIAL 1:10) ALL\r\n",
+            "A7 ESEARCH RETURN (PARTIAL 0:10) ALL\r\n",
+        ] {
+            assert!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .unwrap()
+                    .parse_esearch(ProtocolVersion::Rev1)
+                    .is_err(),
+                "{}",
+                command
+            );
+        }
+    }
 }
@@ -0,0 +1,205 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::{
+    Command,
+    protocol::{ProtocolVersion, urlauth},
+    receiver::{Request, bad},
+    utf7::utf7_maybe_decode,
+};
+
+/*
+
+   resetkey        = "RESETKEY" [SP mailbox *(SP mechanism)]
+
+   genurlauth      = "GENURLAUTH" 1*(SP url-rump SP mechanism)
+
+   urlfetch        = "URLFETCH" 1*(SP url-full)
+
+   mechanism       = "INTERNAL" / 1*(ALPHA / DIGIT / "-" / ".")
+
+*/
+
+impl Request<Command> {
+    pub fn parse_reset_key(
+        self,
+        version: ProtocolVersion,
+    ) -> trc::Result<urlauth::ResetKeyArguments> {
+        let mut tokens = self.tokens.into_iter();
+        let mailbox_name = tokens
+            .next()
+            .map(|token| {
+                token
+                    .unwrap_string()
+                    .map(|name| utf7_maybe_decode(name, version))
+                    .map_err(|v| bad(self.tag.to_string(), v))
+            })
+            .transpose()?;
+        let mechanisms = tokens
+            .map(|token| {
+                token
+                    .unwrap_string()
+                    .map(|mechanism| mechanism.to_ascii_uppercase())
+                    .map_err(|v| bad(self.tag.to_string(), v))
+            })
+            .collect::<trc::Result<Vec<_>>>()?;
+
+        Ok(urlauth::ResetKeyArguments {
+            tag: self.tag,
+            mailbox_name,
+            mechanisms,
+        })
+    }
+
+    pub fn parse_genurlauth(self) -> trc::Result<urlauth::GenUrlAuthArguments> {
+        if self.tokens.is_empty() || !self.tokens.len().is_multiple_of(2) {
+            return Err(self.into_error("Expected URL and mechanism pairs."));
+        }
+
+        let mut urls = Vec::with_capacity(self.tokens.len() / 2);
+        let mut tokens = self.tokens.into_iter();
+        while let (Some(url), Some(mechanism)) = (tokens.next(), tokens.next()) {
+            urls.push((
+                url.unwrap_string()
+                    .map_err(|v| bad(self.tag.to_string(), v))?,
+                mechanism
+                    .unwrap_string()
+                    .map_err(|v| bad(self.tag.to_string(), v))?
+                    .to_ascii_uppercase(),
+            ));
+        }
+
+        Ok(urlauth::GenUrlAuthArguments {
+            tag: self.tag,
+            urls,
+        })
+    }
+
+    pub fn parse_urlfetch(self) -> trc::Result<urlauth::UrlFetchArguments> {
+        if self.tokens.is_empty() {
+            return Err(self.into_error("Missing URLs."));
+        }
+
+        Ok(urlauth::UrlFetchArguments {
+            urls: self
+                .tokens
+                .into_iter()
+                .map(|token| {
+                    token
+                        .unwrap_string()
+                        .map_err(|v| bad(self.tag.to_string(), v))
+                })
+                .collect::<trc::Result<Vec<_>>>()?,
+            tag: self.tag,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        protocol::{ProtocolVersion, urlauth},
+        receiver::Receiver,
+    };
+
+    #[test]
+    fn parse_urlauth() {
+        let mut receiver = Receiver::new();
+
+        for (command, arguments) in [
+            (
+                "A1 RESETKEY\r\n",
+                urlauth::ResetKeyArguments {
+                    tag: "A1".to_string(),
+                    mailbox_name: None,
+                    mechanisms: vec![],
+                },
+            ),
+            (
+                "A2 RESETKEY INBOX internal\r\n",
+                urlauth::ResetKeyArguments {
+                    tag: "A2".to_string(),
+                    mailbox_name: Some("INBOX".to_string()),
+                    mechanisms: vec!["INTERNAL".to_string()],
+                },
+            ),
+        ] {
+            assert_eq!(
+                receiver
+                    .parse(&mut command.as_bytes().iter())
+                    .expect(command)
+                    .parse_reset_key(ProtocolVersion::Rev2)
+                    .expect(command),
+                arguments,
+                "{command}"
+            );
+        }
+
+        assert_eq!(
+            receiver
+                .parse(
+                    &mut concat!(
+                        "A3 GENURLAUTH \"imap://joe@example.com/INBOX/;uid=20/;section=1.2;",
+                        "urlauth=submit+fred\" INTERNAL \"imap://joe@example.com/INBOX/;",
+                        "uid=21;urlauth=anonymous\" internal\r\n"
+                    )
+                    .as_bytes()
+                    .iter()
+                )
+                .unwrap()
+                .parse_genurlauth()
+                .unwrap(),
+            urlauth::GenUrlAuthArguments {
+                tag: "A3".to_string(),
+                urls: vec![
+                    (
+                        "imap://joe@example.com/INBOX/;uid=20/;section=1.2;urlauth=submit+fred"
+                            .to_string(),
+                        "INTERNAL".to_string()
+                    ),
+                    (
+                        "imap://joe@example.com/INBOX/;uid=21;urlauth=anonymous".to_string(),
+                        "INTERNAL".to_string()
+                    ),
+                ],
+            }
+        );
+
+        assert_eq!(
+            receiver
+                .parse(
+                    &mut "A4 URLFETCH \"/INBOX/;uid=20\" \"/INBOX/;uid=21/;section=1\"\r\n"
+                        .as_bytes()
+                        .iter()
+                )
+                .unwrap()
+                .parse_urlfetch()
+                .unwrap(),
+            urlauth::UrlFetchArguments {
+                tag: "A4".to_string(),
+                urls: vec![
+                    "/INBOX/;uid=20".to_string(),
+                    "/INBOX/;uid=21/;section=1".to_string()
+                ],
+            }
+        );
+
+        for command in [
+            "A5 GENURLAUTH \"/INBOX/;uid=20;urlauth=anonymous\"\r\n",
+            "A6 URLFETCH\r\n",
+        ] {
+            let request = receiver.parse(&mut command.as_bytes().iter()).unwrap();
+            assert!(
+                if command.contains("GENURLAUTH") {
+                    request.parse_genurlauth().is_err()
+                } else {
+                    request.parse_urlfetch().is_err()
+                },
+                "{command}"
+            );
+        }
+    }
+}
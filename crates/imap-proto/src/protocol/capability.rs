@@ -52,6 +52,15 @@ pub enum Capability {
     Quota,
     QuotaResource(QuotaResourceName),
     QuotaSet,
+    Notify,
+    Metadata,
+    Compress, //COMPRESS=DEFLATE
+    Replace,
+    SaveDate,
+    MultiSearch,
+    Partial,
+    Catenate,
+    UrlAuth,
 }
 
 /*
@@ -126,10 +135,23 @@ impl Capability {
                 return;
             }
             Capability::QuotaSet => b"QUOTA=SET",
+            Capability::Notify => b"NOTIFY",
+            Capability::Metadata => b"METADATA",
+            Capability::Compress => b"COMPRESS=DEFLATE",
+            Capability::Replace => b"REPLACE",
+            Capability::SaveDate => b"SAVEDATE",
+            Capability::MultiSearch => b"MULTISEARCH",
+            Capability::Partial => b"PARTIAL",
+            Capability::Catenate => b"CATENATE",
+            Capability::UrlAuth => b"URLAUTH",
         });
     }
 
-    pub fn all_capabilities(is_authenticated: bool, offer_tls: bool) -> Vec<Capability> {
+    pub fn all_capabilities(
+        is_authenticated: bool,
+        offer_tls: bool,
+        offer_compress: bool,
+    ) -> Vec<Capability> {
         let mut capabilities = vec![
             Capability::IMAP4rev2,
             Capability::IMAP4rev1,
@@ -170,6 +192,15 @@ impl Capability {
                 Capability::Preview,
                 Capability::Quota,
                 Capability::QuotaResource(QuotaResourceName::Storage),
+                Capability::QuotaResource(QuotaResourceName::AnnotationStorage),
+                Capability::Notify,
+                Capability::Metadata,
+                Capability::Replace,
+                Capability::SaveDate,
+                Capability::MultiSearch,
+                Capability::Partial,
+                Capability::Catenate,
+                Capability::UrlAuth,
             ]);
         } else {
             capabilities.extend([
@@ -180,6 +211,9 @@ impl Capability {
         if offer_tls {
             capabilities.push(Capability::StartTLS);
         }
+        if offer_compress {
+            capabilities.push(Capability::Compress);
+        }
 
         capabilities
     }
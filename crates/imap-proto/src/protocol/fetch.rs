@@ -10,7 +10,7 @@ use mail_parser::DateTime;
 
 use super::{
     literal_string, quoted_or_literal_string, quoted_or_literal_string_or_nil,
-    quoted_rfc2822_or_nil, quoted_timestamp, Flag, ImapResponse, Sequence,
+    quoted_rfc2822_or_nil, quoted_timestamp, Flag, ImapResponse, PartialRange, Sequence,
 };
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -20,6 +20,7 @@ pub struct Arguments {
     pub attributes: Vec<Attribute>,
     pub changed_since: Option<u64>,
     pub include_vanished: bool,
+    pub partial: Option<PartialRange>,
 }
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Response<'x> {
@@ -64,6 +65,7 @@ pub enum Attribute {
     ModSeq,
     EmailId,
     ThreadId,
+    SaveDate,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -133,6 +135,9 @@ pub enum DataItem<'x> {
     ThreadId {
         thread_id: String,
     },
+    SaveDate {
+        date: Option<i64>,
+    },
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -844,6 +849,14 @@ impl DataItem<'_> {
                 buf.extend_from_slice(thread_id.as_bytes());
                 buf.push(b')');
             }
+            DataItem::SaveDate { date } => {
+                buf.extend_from_slice(b"SAVEDATE ");
+                if let Some(date) = date {
+                    quoted_timestamp(buf, *date);
+                } else {
+                    buf.extend_from_slice(b"NIL");
+                }
+            }
         }
     }
 }
@@ -1358,6 +1371,13 @@ mod tests {
                 super::DataItem::InternalDate { date: 482374938 },
                 "INTERNALDATE \"15-Apr-1985 01:02:18 +0000\"",
             ),
+            (
+                super::DataItem::SaveDate {
+                    date: Some(482374938),
+                },
+                "SAVEDATE \"15-Apr-1985 01:02:18 +0000\"",
+            ),
+            (super::DataItem::SaveDate { date: None }, "SAVEDATE NIL"),
         ] {
             let mut buf = Vec::with_capacity(100);
 
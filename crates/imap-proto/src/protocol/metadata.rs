@@ -0,0 +1,187 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use crate::utf7::utf7_encode;
+
+use super::{ImapResponse, literal_string, quoted_string};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<String>,
+    pub max_size: Option<usize>,
+    pub depth: Depth,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetArguments {
+    pub tag: String,
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<Vec<u8>>)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Depth {
+    #[default]
+    Zero,
+    One,
+    Infinity,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Response {
+    pub mailbox_name: String,
+    pub entries: Vec<(String, Option<Vec<u8>>)>,
+    pub is_rev2: bool,
+}
+
+impl Depth {
+    pub fn matches(&self, requested: &str, entry: &str) -> bool {
+        if entry.eq_ignore_ascii_case(requested) {
+            true
+        } else if let Some(suffix) = entry
+            .get(..requested.len())
+            .filter(|prefix| prefix.eq_ignore_ascii_case(requested))
+            .and_then(|_| entry[requested.len()..].strip_prefix('/'))
+        {
+            match self {
+                Depth::Zero => false,
+                Depth::One => !suffix.contains('/'),
+                Depth::Infinity => true,
+            }
+        } else {
+            false
+        }
+    }
+}
+
+pub fn is_valid_entry(name: &str) -> bool {
+    let name_lc = name.to_ascii_lowercase();
+    (name_lc.starts_with("/private/") || name_lc.starts_with("/shared/"))
+        && !name.ends_with('/')
+        && !name.contains("//")
+        && name
+            .bytes()
+            .all(|ch| ch > 0x19 && ch < 0x7f && ch != b'*' && ch != b'%')
+}
+
+impl ImapResponse for Response {
+    fn serialize(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(b"* METADATA ");
+        if self.is_rev2 {
+            quoted_string(&mut buf, &self.mailbox_name);
+        } else {
+            quoted_string(&mut buf, &utf7_encode(&self.mailbox_name));
+        }
+        buf.extend_from_slice(b" (");
+        for (pos, (name, value)) in self.entries.iter().enumerate() {
+            if pos > 0 {
+                buf.push(b' ');
+            }
+            buf.extend_from_slice(name.as_bytes());
+            buf.push(b' ');
+            match value {
+                Some(value)
+                    if value
+                        .iter()
+                        .all(|ch| *ch >= 0x20 && *ch < 0x7f && !matches!(ch, b'\\' | b'"')) =>
+                {
+                    buf.push(b'"');
+                    buf.extend_from_slice(value);
+                    buf.push(b'"');
+                }
+                Some(value) => literal_string(&mut buf, value),
+                None => buf.extend_from_slice(b"NIL"),
+            }
+        }
+        buf.extend_from_slice(b")\r\n");
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::ImapResponse;
+
+    use super::{Depth, is_valid_entry};
+
+    #[test]
+    fn serialize_metadata() {
+        for (response, expected) in [
+            (
+                super::Response {
+                    mailbox_name: "INBOX".to_string(),
+                    entries: vec![("/private/comment".to_string(), Some(b"My comment".to_vec()))],
+                    is_rev2: true,
+                },
+                "* METADATA \"INBOX\" (/private/comment \"My comment\")\r\n",
+            ),
+            (
+                super::Response {
+                    mailbox_name: "".to_string(),
+                    entries: vec![
+                        ("/shared/comment".to_string(), None),
+                        (
+                            "/shared/vendor/example".to_string(),
+                            Some(b"line 1\r\nline 2".to_vec()),
+                        ),
+                    ],
+                    is_rev2: true,
+                },
+                concat!(
+                    "* METADATA \"\" (/shared/comment NIL ",
+                    "/shared/vendor/example {14}\r\nline 1\r\nline 2)\r\n"
+                ),
+            ),
+        ] {
+            assert_eq!(String::from_utf8(response.serialize()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn metadata_entries() {
+        for (depth, requested, entry, expected) in [
+            (Depth::Zero, "/shared/comment", "/shared/comment", true),
+            (Depth::Zero, "/shared/comment", "/Shared/Comment", true),
+            (Depth::Zero, "/shared/vendor", "/shared/vendor/a", false),
+            (Depth::One, "/shared/vendor", "/shared/vendor/a", true),
+            (Depth::One, "/shared/vendor", "/shared/vendor/a/b", false),
+            (Depth::One, "/shared/vendor", "/shared/vendorx", false),
+            (
+                Depth::Infinity,
+                "/shared/vendor",
+                "/shared/vendor/a/b",
+                true,
+            ),
+            (
+                Depth::Infinity,
+                "/shared/vendor",
+                "/private/vendor/a",
+                false,
+            ),
+        ] {
+            assert_eq!(
+                depth.matches(requested, entry),
+                expected,
+                "{depth:?} {requested} {entry}"
+            );
+        }
+
+        for (entry, expected) in [
+            ("/private/comment", true),
+            ("/shared/vendor/example/setting", true),
+            ("/comment", false),
+            ("/private/", false),
+            ("/shared/vendor//example", false),
+            ("/shared/vendor/*", false),
+            ("/private/comment/", false),
+        ] {
+            assert_eq!(is_valid_entry(entry), expected, "{entry}");
+        }
+    }
+}
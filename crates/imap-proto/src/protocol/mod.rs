@@ -16,6 +16,7 @@ pub mod acl;
 pub mod append;
 pub mod authenticate;
 pub mod capability;
+pub mod compress;
 pub mod copy_move;
 pub mod create;
 pub mod delete;
@@ -24,15 +25,19 @@ pub mod expunge;
 pub mod fetch;
 pub mod list;
 pub mod login;
+pub mod metadata;
 pub mod namespace;
+pub mod notify;
 pub mod quota;
 pub mod rename;
+pub mod replace;
 pub mod search;
 pub mod select;
 pub mod status;
 pub mod store;
 pub mod subscribe;
 pub mod thread;
+pub mod urlauth;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProtocolVersion {
@@ -148,6 +153,39 @@ impl Sequence {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PartialRange {
+    pub start: u32,
+    pub end: u32,
+    pub from_end: bool,
+}
+
+impl PartialRange {
+    pub fn slice<'x, T>(&self, items: &'x [T]) -> &'x [T] {
+        let (from, to) = if !self.from_end {
+            (self.start as usize - 1, self.end as usize)
+        } else {
+            (
+                items.len().saturating_sub(self.end as usize),
+                items.len().saturating_sub(self.start as usize - 1),
+            )
+        };
+        items.get(from..to.min(items.len())).unwrap_or_default()
+    }
+
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        if self.from_end {
+            buf.push(b'-');
+        }
+        buf.extend_from_slice(self.start.to_string().as_bytes());
+        buf.push(b':');
+        if self.from_end {
+            buf.push(b'-');
+        }
+        buf.extend_from_slice(self.end.to_string().as_bytes());
+    }
+}
+
 pub trait ImapResponse {
     fn serialize(self) -> Vec<u8>;
 }
@@ -380,6 +418,37 @@ impl ResponseCode {
                 return;
             }
             ResponseCode::UseAttr => b"USEATTR",
+            ResponseCode::NotificationOverflow => b"NOTIFICATIONOVERFLOW",
+            ResponseCode::BadEvent => {
+                buf.extend_from_slice(b"BADEVENT (");
+                for (pos, event) in notify::Event::supported().iter().enumerate() {
+                    if pos > 0 {
+                        buf.push(b' ');
+                    }
+                    event.serialize(buf);
+                }
+                buf.push(b')');
+                return;
+            }
+            ResponseCode::MetadataLongEntries { size } => {
+                buf.extend_from_slice(b"METADATA LONGENTRIES ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+                return;
+            }
+            ResponseCode::MetadataMaxSize { size } => {
+                buf.extend_from_slice(b"METADATA MAXSIZE ");
+                buf.extend_from_slice(size.to_string().as_bytes());
+                return;
+            }
+            ResponseCode::MetadataTooMany => b"METADATA TOOMANY",
+            ResponseCode::MetadataNoPrivate => b"METADATA NOPRIVATE",
+            ResponseCode::CompressionActive => b"COMPRESSIONACTIVE",
+            ResponseCode::BadUrl { url } => {
+                buf.extend_from_slice(b"BADURL ");
+                buf.extend_from_slice(url.as_bytes());
+                return;
+            }
+            ResponseCode::TooBig => b"TOOBIG",
         });
     }
 
@@ -423,6 +492,16 @@ impl ResponseCode {
             ResponseCode::MailboxId { .. } => "MAILBOXID",
             ResponseCode::HighestModseq { .. } => "HIGHESTMODSEQ",
             ResponseCode::UseAttr => "USEATTR",
+            ResponseCode::NotificationOverflow => "NOTIFICATIONOVERFLOW",
+            ResponseCode::BadEvent => "BADEVENT",
+            ResponseCode::MetadataLongEntries { .. } | ResponseCode::MetadataMaxSize { .. } => {
+                "METADATA"
+            }
+            ResponseCode::MetadataTooMany => "METADATA TOOMANY",
+            ResponseCode::MetadataNoPrivate => "METADATA NOPRIVATE",
+            ResponseCode::CompressionActive => "COMPRESSIONACTIVE",
+            ResponseCode::BadUrl { .. } => "BADURL",
+            ResponseCode::TooBig => "TOOBIG",
         }
     }
 }
@@ -614,6 +693,16 @@ impl Display for Command {
             Command::Id => write!(f, "ID"),
             Command::GetQuota => write!(f, "GETQUOTA"),
             Command::GetQuotaRoot => write!(f, "GETQUOTAROOT"),
+            Command::Notify => write!(f, "NOTIFY"),
+            Command::GetMetadata => write!(f, "GETMETADATA"),
+            Command::SetMetadata => write!(f, "SETMETADATA"),
+            Command::Compress => write!(f, "COMPRESS"),
+            Command::Replace(false) => write!(f, "REPLACE"),
+            Command::Replace(true) => write!(f, "UID REPLACE"),
+            Command::Esearch => write!(f, "ESEARCH"),
+            Command::ResetKey => write!(f, "RESETKEY"),
+            Command::GenUrlAuth => write!(f, "GENURLAUTH"),
+            Command::UrlFetch => write!(f, "URLFETCH"),
         }
     }
 }
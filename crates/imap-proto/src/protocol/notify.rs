@@ -0,0 +1,124 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::fetch;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Arguments {
+    Set {
+        tag: String,
+        status: bool,
+        groups: Vec<EventGroup>,
+    },
+    None {
+        tag: String,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EventGroup {
+    pub filter: Filter,
+    pub events: Vec<Event>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    Selected,
+    SelectedDelayed,
+    Inboxes,
+    Personal,
+    Subscribed,
+    Subtree(Vec<String>),
+    SubtreeOne(Vec<String>),
+    Mailboxes(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Event {
+    MessageNew { attributes: Vec<fetch::Attribute> },
+    MessageExpunge,
+    FlagChange,
+    AnnotationChange,
+    MailboxName,
+    SubscriptionChange,
+    MailboxMetadataChange,
+    ServerMetadataChange,
+}
+
+impl Arguments {
+    pub fn unwrap_tag(self) -> String {
+        match self {
+            Arguments::Set { tag, .. } => tag,
+            Arguments::None { tag } => tag,
+        }
+    }
+}
+
+impl Filter {
+    pub fn is_selected(&self) -> bool {
+        matches!(self, Filter::Selected | Filter::SelectedDelayed)
+    }
+}
+
+impl EventGroup {
+    pub fn has_message_events(&self) -> bool {
+        self.events.iter().any(|event| {
+            matches!(
+                event,
+                Event::MessageNew { .. } | Event::MessageExpunge | Event::FlagChange
+            )
+        })
+    }
+
+    pub fn has_event(&self, event: &Event) -> bool {
+        self.events
+            .iter()
+            .any(|e| std::mem::discriminant(e) == std::mem::discriminant(event))
+    }
+
+    pub fn new_message_attributes(&self) -> Option<&[fetch::Attribute]> {
+        self.events.iter().find_map(|event| match event {
+            Event::MessageNew { attributes } => Some(attributes.as_slice()),
+            _ => None,
+        })
+    }
+}
+
+impl Event {
+    pub fn is_supported(&self) -> bool {
+        matches!(
+            self,
+            Event::MessageNew { .. }
+                | Event::MessageExpunge
+                | Event::FlagChange
+                | Event::MailboxName
+                | Event::SubscriptionChange
+        )
+    }
+
+    pub fn serialize(&self, buf: &mut Vec<u8>) {
+        buf.extend_from_slice(match self {
+            Event::MessageNew { .. } => b"MessageNew",
+            Event::MessageExpunge => b"MessageExpunge",
+            Event::FlagChange => b"FlagChange",
+            Event::AnnotationChange => b"AnnotationChange",
+            Event::MailboxName => b"MailboxName",
+            Event::SubscriptionChange => b"SubscriptionChange",
+            Event::MailboxMetadataChange => b"MailboxMetadataChange",
+            Event::ServerMetadataChange => b"ServerMetadataChange",
+        });
+    }
+
+    pub fn supported() -> [Event; 5] {
+        [
+            Event::MessageNew { attributes: vec![] },
+            Event::MessageExpunge,
+            Event::FlagChange,
+            Event::MailboxName,
+            Event::SubscriptionChange,
+        ]
+    }
+}
@@ -63,6 +63,9 @@ impl ImapResponse for Response {
                         QuotaResourceName::Message => buf.extend_from_slice(b"MESSAGE "),
                         QuotaResourceName::Mailbox => buf.extend_from_slice(b"MAILBOX "),
                         QuotaResourceName::AnnotationStorage => {
+                            total /= 1024;
+                            used /= 1024;
+
                             buf.extend_from_slice(b"ANNOTATION-STORAGE ")
                         }
                     }
@@ -123,12 +126,17 @@ mod tests {
                                 total: 100,
                                 used: 2,
                             },
+                            QuotaResource {
+                                resource: QuotaResourceName::AnnotationStorage,
+                                total: 1048576,
+                                used: 2048,
+                            },
                         ],
                     }],
                 },
                 concat!(
                     "* QUOTAROOT \"my mailbox\" \"\"\r\n",
-                    "* QUOTA \"INBOX\" (STORAGE 1024 1048576 MESSAGE 2 100)\r\n"
+                    "* QUOTA \"INBOX\" (STORAGE 1024 1048576 MESSAGE 2 100 ANNOTATION-STORAGE 2 1024)\r\n"
                 ),
             ),
         ] {
@@ -6,7 +6,7 @@
 
 use store::fts::{FilterItem, FilterType};
 
-use super::{quoted_string, serialize_sequence, Flag, Sequence};
+use super::{notify, quoted_string, serialize_sequence, Flag, PartialRange, Sequence};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Arguments {
@@ -17,6 +17,12 @@ pub struct Arguments {
     pub filter: Vec<Filter>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EsearchArguments {
+    pub source: Vec<notify::Filter>,
+    pub arguments: Arguments,
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Sort {
     Arrival,
@@ -45,7 +51,15 @@ pub struct Response {
     pub min: Option<u32>,
     pub max: Option<u32>,
     pub count: Option<u32>,
+    pub partial: Option<(PartialRange, Vec<u32>)>,
     pub highest_modseq: Option<u64>,
+    pub mailbox: Option<MailboxCorrelator>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MailboxCorrelator {
+    pub mailbox_name: String,
+    pub uid_validity: u32,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -56,6 +70,7 @@ pub enum ResultOption {
     Count,
     Save,
     Context,
+    Partial(PartialRange),
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -112,6 +127,12 @@ pub enum Filter {
     // RFC 8474 - ObjectID
     EmailId(String),
     ThreadId(String),
+
+    // RFC 8514 - SAVEDATE
+    SavedBefore(i64),
+    SavedOn(i64),
+    SavedSince(i64),
+    SaveDateSupported,
 }
 
 impl FilterItem for Filter {
@@ -170,6 +191,12 @@ impl Response {
         if self.is_esearch {
             buf.extend_from_slice(b"* ESEARCH (TAG ");
             quoted_string(&mut buf, tag);
+            if let Some(mailbox) = &self.mailbox {
+                buf.extend_from_slice(b" MAILBOX ");
+                quoted_string(&mut buf, &mailbox.mailbox_name);
+                buf.extend_from_slice(b" UIDVALIDITY ");
+                buf.extend_from_slice(mailbox.uid_validity.to_string().as_bytes());
+            }
             buf.extend_from_slice(b")");
             if self.is_uid {
                 buf.extend_from_slice(b" UID");
@@ -190,6 +217,17 @@ impl Response {
                 buf.extend_from_slice(b" ALL ");
                 serialize_sequence(&mut buf, &self.ids);
             }
+            if let Some((range, ids)) = &self.partial {
+                buf.extend_from_slice(b" PARTIAL (");
+                range.serialize(&mut buf);
+                if !ids.is_empty() {
+                    buf.push(b' ');
+                    serialize_sequence(&mut buf, ids);
+                } else {
+                    buf.extend_from_slice(b" NIL");
+                }
+                buf.push(b')');
+            }
             if let Some(highest_modseq) = self.highest_modseq {
                 buf.extend_from_slice(b" MODSEQ ");
                 buf.extend_from_slice(highest_modseq.to_string().as_bytes());
@@ -219,6 +257,8 @@ impl Response {
 
 #[cfg(test)]
 mod tests {
+    use super::MailboxCorrelator;
+    use crate::protocol::PartialRange;
 
     #[test]
     fn serialize_search() {
@@ -232,7 +272,9 @@ mod tests {
                     min: 2.into(),
                     max: 11.into(),
                     count: 3.into(),
+                    partial: None,
                     highest_modseq: None,
+                    mailbox: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") COUNT 3 MIN 2 MAX 11 ALL 2,10:11\r\n",),
@@ -249,7 +291,9 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
                     highest_modseq: None,
+                    mailbox: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") ALL 1:3,5,10:13,90,92:99\r\n",),
@@ -264,7 +308,9 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
                     highest_modseq: None,
+                    mailbox: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\")\r\n",),
@@ -279,12 +325,68 @@ mod tests {
                     min: None,
                     max: None,
                     count: None,
+                    partial: None,
                     highest_modseq: 12345.into(),
+                    mailbox: None,
                 },
                 "A283",
                 concat!("* ESEARCH (TAG \"A283\") ALL 10:13,21 MODSEQ 12345\r\n",),
                 concat!("* SEARCH 10 11 12 13 21 (MODSEQ 12345)\r\n",),
             ),
+            (
+                super::Response {
+                    is_uid: true,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![],
+                    min: None,
+                    max: None,
+                    count: 10.into(),
+                    partial: Some((
+                        PartialRange {
+                            start: 1,
+                            end: 5,
+                            from_end: false,
+                        },
+                        vec![3, 4, 5, 8, 9],
+                    )),
+                    highest_modseq: None,
+                    mailbox: None,
+                },
+                "A01",
+                "* ESEARCH (TAG \"A01\") UID COUNT 10 PARTIAL (1:5 3:5,8:9)\r\n",
+                "* SEARCH\r\n",
+            ),
+            (
+                super::Response {
+                    is_uid: true,
+                    is_esearch: true,
+                    is_sort: false,
+                    ids: vec![],
+                    min: None,
+                    max: None,
+                    count: None,
+                    partial: Some((
+                        PartialRange {
+                            start: 1,
+                            end: 100,
+                            from_end: true,
+                        },
+                        vec![],
+                    )),
+                    highest_modseq: None,
+                    mailbox: Some(MailboxCorrelator {
+                        mailbox_name: "folder1".to_string(),
+                        uid_validity: 1,
+                    }),
+                },
+                "A02",
+                concat!(
+                    "* ESEARCH (TAG \"A02\" MAILBOX \"folder1\" UIDVALIDITY 1) ",
+                    "UID PARTIAL (-1:-100 NIL)\r\n"
+                ),
+                "* SEARCH\r\n",
+            ),
         ] {
             let response_v2 = String::from_utf8(response.clone().serialize(tag)).unwrap();
             response.is_esearch = false;
@@ -0,0 +1,118 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use super::{ImapResponse, literal_string, quoted_string};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResetKeyArguments {
+    pub tag: String,
+    pub mailbox_name: Option<String>,
+    pub mechanisms: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenUrlAuthArguments {
+    pub tag: String,
+    pub urls: Vec<(String, String)>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlFetchArguments {
+    pub tag: String,
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GenUrlAuthResponse {
+    pub urls: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UrlFetchResponse {
+    pub items: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl ImapResponse for GenUrlAuthResponse {
+    fn serialize(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(64);
+        buf.extend_from_slice(b"* GENURLAUTH");
+        for url in &self.urls {
+            buf.push(b' ');
+            quoted_string(&mut buf, url);
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
+
+impl ImapResponse for UrlFetchResponse {
+    fn serialize(self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(
+            64 + self
+                .items
+                .iter()
+                .map(|(url, data)| url.len() + data.as_ref().map_or(0, |data| data.len()))
+                .sum::<usize>(),
+        );
+        buf.extend_from_slice(b"* URLFETCH");
+        for (url, data) in &self.items {
+            buf.push(b' ');
+            quoted_string(&mut buf, url);
+            buf.push(b' ');
+            if let Some(data) = data {
+                literal_string(&mut buf, data);
+            } else {
+                buf.extend_from_slice(b"NIL");
+            }
+        }
+        buf.extend_from_slice(b"\r\n");
+        buf
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::protocol::ImapResponse;
+
+    #[test]
+    fn serialize_urlauth() {
+        assert_eq!(
+            String::from_utf8(
+                super::GenUrlAuthResponse {
+                    urls: vec![
+                        "imap://joe@example.com/INBOX/;uid=20/;section=1.2;urlauth=submit+fred:internal:91354a473744909de610943775f92038".to_string()
+                    ],
+                }
+                .serialize()
+            )
+            .unwrap(),
+            concat!(
+                "* GENURLAUTH \"imap://joe@example.com/INBOX/;uid=20/;section=1.2;",
+                "urlauth=submit+fred:internal:91354a473744909de610943775f92038\"\r\n"
+            )
+        );
+
+        assert_eq!(
+            String::from_utf8(
+                super::UrlFetchResponse {
+                    items: vec![
+                        (
+                            "/INBOX/;uid=20/;section=1.2".to_string(),
+                            Some(b"Hello world".to_vec())
+                        ),
+                        ("/INBOX/;uid=21".to_string(), None),
+                    ],
+                }
+                .serialize()
+            )
+            .unwrap(),
+            concat!(
+                "* URLFETCH \"/INBOX/;uid=20/;section=1.2\" {11}\r\nHello world ",
+                "\"/INBOX/;uid=21\" NIL\r\n"
+            )
+        );
+    }
+}
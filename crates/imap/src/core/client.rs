@@ -246,6 +246,42 @@ impl<T: SessionStream> Session<T> {
                     .handle_id(request)
                     .await
                     .map(|_| SessionResult::Continue),
+                Command::Notify => self
+                    .handle_notify(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::GetMetadata => self
+                    .handle_get_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::SetMetadata => self
+                    .handle_set_metadata(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::Compress => self
+                    .handle_compress(request)
+                    .await
+                    .map(|_| SessionResult::UpgradeCompress),
+                Command::Replace(is_uid) => self
+                    .handle_replace(request, is_uid)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::Esearch => self
+                    .handle_esearch(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::ResetKey => self
+                    .handle_reset_key(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::GenUrlAuth => self
+                    .handle_genurlauth(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
+                Command::UrlFetch => self
+                    .handle_urlfetch(request)
+                    .await
+                    .map(|_| SessionResult::Continue),
             };
 
             match result {
@@ -318,7 +354,7 @@ impl<T: SessionStream> Session<T> {
             Command::Capability | Command::Noop | Command::Logout | Command::Id => Ok(request),
             Command::StartTls => {
                 if !self.is_tls {
-                    if self.instance.acceptor.is_tls() {
+                    if self.instance.acceptor.is_tls() && !self.is_compress {
                         Ok(request)
                     } else {
                         Err(trc::ImapEvent::Error
@@ -381,7 +417,15 @@ impl<T: SessionStream> Session<T> {
             | Command::MyRights
             | Command::Unauthenticate
             | Command::GetQuota
-            | Command::GetQuotaRoot => {
+            | Command::GetQuotaRoot
+            | Command::Notify
+            | Command::GetMetadata
+            | Command::SetMetadata
+            | Command::Compress
+            | Command::Esearch
+            | Command::ResetKey
+            | Command::GenUrlAuth
+            | Command::UrlFetch => {
                 if let State::Authenticated { .. } | State::Selected { .. } = state {
                     Ok(request)
                 } else {
@@ -401,12 +445,16 @@ impl<T: SessionStream> Session<T> {
             | Command::Move(_)
             | Command::Check
             | Command::Sort(_)
-            | Command::Thread(_) => match state {
+            | Command::Thread(_)
+            | Command::Replace(_) => match state {
                 State::Selected { mailbox, .. } => {
                     if mailbox.is_select
                         || !matches!(
                             request.command,
-                            Command::Store(_) | Command::Expunge(_) | Command::Move(_),
+                            Command::Store(_)
+                                | Command::Expunge(_)
+                                | Command::Move(_)
+                                | Command::Replace(_),
                         )
                     {
                         Ok(request)
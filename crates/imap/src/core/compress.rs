@@ -0,0 +1,174 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    borrow::Cow,
+    io,
+    pin::Pin,
+    task::{Context, Poll, ready},
+};
+
+use common::listener::SessionStream;
+use flate2::{Compress, Compression, Decompress, FlushCompress, FlushDecompress, Status};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+const READ_BUFFER_SIZE: usize = 8192;
+
+// Raw DEFLATE stream as described in RFC 4978, each flush
+// produces a sync point so the peer can decode all sent data.
+pub struct DeflateStream<T: SessionStream> {
+    inner: T,
+    inflate: Decompress,
+    deflate: Compress,
+    read_buf: Box<[u8]>,
+    read_pos: usize,
+    read_len: usize,
+    write_buf: Vec<u8>,
+    write_pos: usize,
+    needs_flush: bool,
+}
+
+impl<T: SessionStream> DeflateStream<T> {
+    pub fn new(inner: T) -> Self {
+        DeflateStream {
+            inner,
+            inflate: Decompress::new(false),
+            deflate: Compress::new(Compression::default(), false),
+            read_buf: vec![0; READ_BUFFER_SIZE].into_boxed_slice(),
+            read_pos: 0,
+            read_len: 0,
+            write_buf: Vec::with_capacity(READ_BUFFER_SIZE),
+            write_pos: 0,
+            needs_flush: false,
+        }
+    }
+
+    fn compress(&mut self, mut bytes: &[u8], flush: FlushCompress) -> io::Result<()> {
+        // Output is complete once the compressor leaves spare capacity
+        loop {
+            self.write_buf.reserve(bytes.len() / 2 + 64);
+            let total_in = self.deflate.total_in();
+            self.deflate
+                .compress_vec(bytes, &mut self.write_buf, flush)
+                .map_err(io::Error::other)?;
+            bytes = &bytes[(self.deflate.total_in() - total_in) as usize..];
+
+            if bytes.is_empty() && self.write_buf.len() < self.write_buf.capacity() {
+                return Ok(());
+            }
+        }
+    }
+
+    fn poll_write_buf(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.write_pos < self.write_buf.len() {
+            let bytes_written = ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.write_buf[self.write_pos..])
+            )?;
+            if bytes_written == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.write_pos += bytes_written;
+        }
+        self.write_buf.clear();
+        self.write_pos = 0;
+
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T: SessionStream> AsyncRead for DeflateStream<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if buf.remaining() == 0 {
+            return Poll::Ready(Ok(()));
+        }
+
+        loop {
+            // Inflate any buffered input
+            let total_in = this.inflate.total_in();
+            let total_out = this.inflate.total_out();
+            let status = this
+                .inflate
+                .decompress(
+                    &this.read_buf[this.read_pos..this.read_len],
+                    buf.initialize_unfilled(),
+                    FlushDecompress::None,
+                )
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+            let bytes_in = (this.inflate.total_in() - total_in) as usize;
+            let bytes_out = (this.inflate.total_out() - total_out) as usize;
+            this.read_pos += bytes_in;
+            buf.advance(bytes_out);
+
+            if bytes_out > 0 || status == Status::StreamEnd {
+                return Poll::Ready(Ok(()));
+            } else if this.read_pos < this.read_len {
+                if bytes_in == 0 {
+                    return Poll::Ready(Err(io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        "Failed to decompress stream",
+                    )));
+                }
+                continue;
+            }
+
+            // Read more compressed data
+            let mut read_buf = ReadBuf::new(&mut this.read_buf);
+            ready!(Pin::new(&mut this.inner).poll_read(cx, &mut read_buf))?;
+            this.read_pos = 0;
+            this.read_len = read_buf.filled().len();
+            if this.read_len == 0 {
+                return Poll::Ready(Ok(()));
+            }
+        }
+    }
+}
+
+impl<T: SessionStream> AsyncWrite for DeflateStream<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buf(cx))?;
+        this.compress(buf, FlushCompress::None)?;
+        this.needs_flush |= !buf.is_empty();
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        if this.needs_flush {
+            this.compress(&[], FlushCompress::Sync)?;
+            this.needs_flush = false;
+        }
+        ready!(this.poll_write_buf(cx))?;
+
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        ready!(self.as_mut().poll_flush(cx))?;
+
+        Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+    }
+}
+
+impl<T: SessionStream> SessionStream for DeflateStream<T> {
+    fn is_tls(&self) -> bool {
+        self.inner.is_tls()
+    }
+
+    fn tls_version_and_cipher(&self) -> (Cow<'static, str>, Cow<'static, str>) {
+        self.inner.tls_version_and_cipher()
+    }
+}
@@ -545,10 +545,23 @@ impl<T: SessionStream> SessionData<T> {
                     if let Some(changes) = &mut changes {
                         let old_account = &mailboxes[pos];
                         let new_account = &changed_account;
+                        let old_names = old_account
+                            .mailbox_names
+                            .iter()
+                            .map(|(mailbox_name, mailbox_id)| (*mailbox_id, mailbox_name))
+                            .collect::<AHashMap<_, _>>();
 
                         // Add new mailboxes
                         for (mailbox_name, mailbox_id) in new_account.mailbox_names.iter() {
                             if let Some(old_mailbox) = old_account.mailbox_state.get(mailbox_id) {
+                                if let Some(old_name) = old_names
+                                    .get(mailbox_id)
+                                    .filter(|old_name| **old_name != mailbox_name)
+                                {
+                                    changes
+                                        .renamed
+                                        .push((old_name.to_string(), mailbox_name.to_string()));
+                                }
                                 if let Some(mailbox) = new_account.mailbox_state.get(mailbox_id) {
                                     if mailbox.total_messages.unwrap_or(0)
                                         != old_mailbox.total_messages.unwrap_or(0)
@@ -557,6 +570,13 @@ impl<T: SessionStream> SessionData<T> {
                                     {
                                         changes.changed.push(mailbox_name.to_string());
                                     }
+                                    if mailbox.is_subscribed != old_mailbox.is_subscribed {
+                                        if mailbox.is_subscribed {
+                                            changes.subscribed.push(mailbox_name.to_string());
+                                        } else {
+                                            changes.unsubscribed.push(mailbox_name.to_string());
+                                        }
+                                    }
                                 }
                             } else {
                                 changes.added.push(mailbox_name.to_string());
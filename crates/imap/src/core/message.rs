@@ -4,20 +4,13 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
-use std::{collections::BTreeMap, sync::Arc};
+use std::sync::Arc;
 
 use ahash::AHashMap;
 use common::{NextMailboxState, listener::SessionStream};
-use email::mailbox::UidMailbox;
+use email::mailbox::MailboxFnc;
 use imap_proto::protocol::{Sequence, expunge, select::Exists};
-use jmap_proto::{
-    object::Object,
-    types::{collection::Collection, property::Property, value::Value},
-};
-use store::{
-    ValueKey,
-    write::{ValueClass, assert::HashedValue},
-};
+use jmap_proto::types::collection::Collection;
 use trc::AddContext;
 
 use crate::core::ImapId;
@@ -28,99 +21,14 @@ pub(crate) const MAX_RETRIES: usize = 10;
 
 impl<T: SessionStream> SessionData<T> {
     pub async fn fetch_messages(&self, mailbox: &MailboxId) -> trc::Result<MailboxState> {
-        // Obtain message ids
-        let message_ids = self
-            .server
-            .get_tag(
-                mailbox.account_id,
-                Collection::Email,
-                Property::MailboxIds,
-                mailbox.mailbox_id,
-            )
-            .await?
-            .unwrap_or_default();
-
-        // Obtain UID validity and UID next
-        let uid_validity = self.get_uid_validity(mailbox).await?;
-        let uid_next = self.get_uid_next(mailbox).await?;
-
-        // Obtain current state
-        let modseq = self
-            .server
-            .core
-            .storage
-            .data
-            .get_last_change_id(mailbox.account_id, Collection::Email)
-            .await
-            .add_context(|e| e.caused_by(trc::location!()).account_id(mailbox.account_id))?;
-
-        // Obtain all message ids
-        let mut uid_map = BTreeMap::new();
-        for (message_id, uid_mailbox) in self
-            .server
-            .get_properties::<HashedValue<Vec<UidMailbox>>, _, _>(
-                mailbox.account_id,
-                Collection::Email,
-                &message_ids,
-                Property::MailboxIds,
-            )
-            .await?
-            .into_iter()
-        {
-            // Make sure the message is still in this mailbox
-            if let Some(item) = uid_mailbox
-                .inner
-                .iter()
-                .find(|item| item.mailbox_id == mailbox.mailbox_id)
-            {
-                debug_assert!(item.uid != 0, "UID is zero for message {item:?}");
-                if uid_map.insert(item.uid, message_id).is_some() {
-                    trc::event!(
-                        Store(trc::StoreEvent::UnexpectedError),
-                        AccountId = mailbox.account_id,
-                        Collection = Collection::Mailbox,
-                        MailboxId = mailbox.mailbox_id,
-                        MessageId = message_id,
-                        SpanId = self.session_id,
-                        Details = "Duplicate IMAP UID"
-                    );
-                }
-            }
-        }
-
-        // Obtain UID next and assign UIDs
-        let mut uid_max = 0;
-        let mut id_to_imap = AHashMap::with_capacity(uid_map.len());
-        let mut uid_to_id = AHashMap::with_capacity(uid_map.len());
-
-        for (seqnum, (uid, message_id)) in uid_map.into_iter().enumerate() {
-            if uid > uid_max {
-                uid_max = uid;
-            }
-            id_to_imap.insert(
-                message_id,
-                ImapId {
-                    uid,
-                    seqnum: seqnum as u32 + 1,
-                },
-            );
-            uid_to_id.insert(uid, message_id);
-        }
-
-        let mut state = MailboxState {
-            uid_next,
-            uid_validity,
-            total_messages: id_to_imap.len(),
-            id_to_imap,
-            uid_to_id,
-            uid_max,
-            modseq,
-            next_state: None,
-            obj_size: 0,
-        };
-        state.obj_size = state.calculate_weight();
+        self.server.mailbox_state(mailbox).await
+    }
 
-        Ok(state)
+    pub async fn fetch_messages_cached(
+        &self,
+        mailbox: &MailboxId,
+    ) -> trc::Result<Arc<MailboxState>> {
+        self.server.mailbox_state_cached(mailbox).await
     }
 
     pub async fn synchronize_messages(
@@ -230,39 +138,11 @@ impl<T: SessionStream> SessionData<T> {
     }
 
     pub async fn get_uid_validity(&self, mailbox: &MailboxId) -> trc::Result<u32> {
-        self.server
-            .get_property::<Object<Value>>(
-                mailbox.account_id,
-                Collection::Mailbox,
-                mailbox.mailbox_id,
-                &Property::Value,
-            )
-            .await?
-            .and_then(|obj| obj.get(&Property::Cid).as_uint())
-            .ok_or_else(|| {
-                trc::ImapEvent::Error
-                    .caused_by(trc::location!())
-                    .details("Mailbox unavailable")
-                    .account_id(mailbox.account_id)
-                    .collection(Collection::Mailbox)
-                    .document_id(mailbox.mailbox_id)
-            })
-            .map(|v| v as u32)
+        self.server.mailbox_uid_validity(mailbox).await
     }
 
     pub async fn get_uid_next(&self, mailbox: &MailboxId) -> trc::Result<u32> {
-        self.server
-            .core
-            .storage
-            .data
-            .get_counter(ValueKey {
-                account_id: mailbox.account_id,
-                collection: Collection::Mailbox.into(),
-                document_id: mailbox.mailbox_id,
-                class: ValueClass::Property(Property::EmailIds.into()),
-            })
-            .await
-            .map(|v| (v + 1) as u32)
+        self.server.mailbox_uid_next(mailbox).await
     }
 }
 
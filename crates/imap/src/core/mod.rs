@@ -14,14 +14,20 @@ use common::{
     listener::{limiter::InFlight, ServerInstance, SessionStream},
     Account, ImapId, Inner, MailboxId, MailboxState, Server,
 };
-use imap_proto::{protocol::ProtocolVersion, receiver::Receiver, Command};
+use imap_proto::{
+    protocol::{notify::EventGroup, ProtocolVersion},
+    receiver::Receiver,
+    Command,
+};
+use jmap_proto::types::state::StateChange;
 use tokio::{
     io::{ReadHalf, WriteHalf},
-    sync::watch,
+    sync::{mpsc, watch},
 };
 use trc::AddContext;
 
 pub mod client;
+pub mod compress;
 pub mod mailbox;
 pub mod message;
 pub mod session;
@@ -46,6 +52,8 @@ pub struct Session<T: SessionStream> {
     pub is_tls: bool,
     pub is_condstore: bool,
     pub is_qresync: bool,
+    pub is_compress: bool,
+    pub notify: Option<Notifier>,
     pub stream_rx: ReadHalf<T>,
     pub stream_tx: Arc<tokio::sync::Mutex<WriteHalf<T>>>,
     pub in_flight: InFlight,
@@ -72,11 +80,19 @@ pub struct SelectedMailbox {
     pub is_condstore: bool,
 }
 
+pub struct Notifier {
+    pub groups: Vec<EventGroup>,
+    pub change_rx: mpsc::Receiver<StateChange>,
+}
+
 #[derive(Debug, Default)]
 pub struct MailboxSync {
     pub added: Vec<String>,
     pub changed: Vec<String>,
     pub deleted: Vec<String>,
+    pub renamed: Vec<(String, String)>,
+    pub subscribed: Vec<String>,
+    pub unsubscribed: Vec<String>,
 }
 
 pub enum SavedSearch {
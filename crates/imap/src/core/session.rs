@@ -19,7 +19,7 @@ use tokio_rustls::server::TlsStream;
 
 use crate::{GREETING_WITHOUT_TLS, GREETING_WITH_TLS};
 
-use super::{ImapSessionManager, Session, State};
+use super::{ImapSessionManager, Notifier, Session, State, compress::DeflateStream};
 
 impl SessionManager for ImapSessionManager {
     #[allow(clippy::manual_async_fn)]
@@ -29,10 +29,17 @@ impl SessionManager for ImapSessionManager {
     ) -> impl std::future::Future<Output = ()> + Send {
         async move {
             if let Ok(mut session) = Session::new(session, self).await {
-                if session.handle_conn().await && session.instance.acceptor.is_tls() {
-                    if let Ok(mut session) = session.into_tls().await {
-                        session.handle_conn().await;
+                match session.handle_conn().await {
+                    SessionResult::UpgradeTls if session.instance.acceptor.is_tls() => {
+                        if let Ok(mut session) = session.into_tls().await {
+                            let result = session.handle_conn().await;
+                            if result == SessionResult::UpgradeCompress {
+                                session.handle_compressed_conn().await;
+                            }
+                        }
                     }
+                    SessionResult::UpgradeCompress => session.handle_compressed_conn().await,
+                    _ => (),
                 }
             }
         }
@@ -45,7 +52,7 @@ impl SessionManager for ImapSessionManager {
 }
 
 impl<T: SessionStream> Session<T> {
-    pub async fn handle_conn(&mut self) -> bool {
+    pub async fn handle_conn(&mut self) -> SessionResult {
         let mut buf = vec![0; 8192];
         let mut shutdown_rx = self.instance.shutdown_rx.clone();
 
@@ -63,12 +70,12 @@ impl<T: SessionStream> Session<T> {
                             if bytes_read > 0 {
                                 match self.ingest(&buf[..bytes_read]).await {
                                     SessionResult::Continue => (),
-                                    SessionResult::UpgradeTls => {
-                                        return true;
-                                    }
                                     SessionResult::Close => {
                                         break;
                                     }
+                                    result => {
+                                        return result;
+                                    }
                                 }
                             } else {
                                 trc::event!(
@@ -99,6 +106,13 @@ impl<T: SessionStream> Session<T> {
                         }
                     }
                 },
+                state_change = Notifier::recv(&mut self.notify) => {
+                    if let Err(err) = self.write_notifications(state_change, false).await {
+                        if !self.write_error(err).await {
+                            break;
+                        }
+                    }
+                },
                 _ = shutdown_rx.changed() => {
                     trc::event!(
                         Network(trc::NetworkEvent::Closed),
@@ -112,7 +126,13 @@ impl<T: SessionStream> Session<T> {
             };
         }
 
-        false
+        SessionResult::Close
+    }
+
+    async fn handle_compressed_conn(self) {
+        if let Ok(mut session) = self.into_compressed() {
+            session.handle_conn().await;
+        }
     }
 
     pub async fn new(
@@ -149,6 +169,8 @@ impl<T: SessionStream> Session<T> {
             is_tls,
             is_condstore: false,
             is_qresync: false,
+            is_compress: false,
+            notify: None,
             server,
             instance: session.instance,
             session_id: session.session_id,
@@ -160,12 +182,30 @@ impl<T: SessionStream> Session<T> {
     }
 
     pub async fn into_tls(self) -> Result<Session<TlsStream<T>>, ()> {
+        let (stream, session) = self.into_stream()?;
+
+        // Upgrade to TLS
+        let stream = session
+            .instance
+            .tls_accept(stream, session.session_id)
+            .await?;
+
+        Ok(session.with_stream(stream))
+    }
+
+    fn into_compressed(self) -> Result<Session<DeflateStream<T>>, ()> {
+        let (stream, session) = self.into_stream()?;
+
+        let mut session = session.with_stream(DeflateStream::new(stream));
+        session.is_compress = true;
+        Ok(session)
+    }
+
+    fn into_stream(self) -> Result<(T, Session<NullIo>), ()> {
         // Drop references to write half from state
-        let state = if let Some(state) =
-            self.state
-                .try_replace_stream_tx(Arc::new(tokio::sync::Mutex::new(
-                    tokio::io::split(NullIo::default()).1,
-                ))) {
+        let (stream_rx, stream_tx) = tokio::io::split(NullIo::default());
+        let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
+        let state = if let Some(state) = self.state.try_replace_stream_tx(stream_tx.clone()) {
             state
         } else {
             trc::event!(
@@ -191,26 +231,52 @@ impl<T: SessionStream> Session<T> {
             return Err(());
         };
 
-        // Upgrade to TLS
-        let (stream_rx, stream_tx) =
-            tokio::io::split(self.instance.tls_accept(stream, self.session_id).await?);
+        Ok((
+            stream,
+            Session {
+                server: self.server,
+                instance: self.instance,
+                receiver: self.receiver,
+                version: self.version,
+                state,
+                is_tls: self.is_tls,
+                is_condstore: self.is_condstore,
+                is_qresync: self.is_qresync,
+                is_compress: self.is_compress,
+                notify: self.notify,
+                session_id: self.session_id,
+                in_flight: self.in_flight,
+                remote_addr: self.remote_addr,
+                stream_rx,
+                stream_tx,
+            },
+        ))
+    }
+}
+
+impl Session<NullIo> {
+    fn with_stream<T: SessionStream>(self, stream: T) -> Session<T> {
+        let is_tls = stream.is_tls();
+        let (stream_rx, stream_tx) = tokio::io::split(stream);
         let stream_tx = Arc::new(tokio::sync::Mutex::new(stream_tx));
 
-        Ok(Session {
+        Session {
             server: self.server,
             instance: self.instance,
             receiver: self.receiver,
             version: self.version,
-            state: state.try_replace_stream_tx(stream_tx.clone()).unwrap(),
-            is_tls: true,
+            state: self.state.try_replace_stream_tx(stream_tx.clone()).unwrap(),
+            is_tls,
             is_condstore: self.is_condstore,
             is_qresync: self.is_qresync,
+            is_compress: self.is_compress,
+            notify: self.notify,
             session_id: self.session_id,
             in_flight: self.in_flight,
             remote_addr: self.remote_addr,
             stream_rx,
             stream_tx,
-        })
+        }
     }
 }
 
@@ -7,20 +7,30 @@
 use std::{sync::Arc, time::Instant};
 
 use directory::Permission;
-use email::ingest::{EmailIngest, IngestEmail, IngestSource};
+use email::{
+    ingest::{EmailIngest, IngestEmail, IngestSource, ReplaceEmail},
+    mailbox::UidMailbox,
+};
 use imap_proto::{
-    protocol::{append::Arguments, select::HighestModSeq},
+    protocol::{append::Arguments, replace, select::HighestModSeq},
     receiver::Request,
     Command, ResponseCode, StatusResponse,
 };
 
 use crate::{
-    core::{ImapUidToId, SelectedMailbox, Session, SessionData},
+    core::{ImapUidToId, SavedSearch, SelectedMailbox, Session, SessionData},
     spawn_op,
 };
 use common::{listener::SessionStream, MailboxId};
-use jmap_proto::types::{acl::Acl, keyword::Keyword, state::StateChange, type_state::DataType};
+use jmap_proto::types::{
+    acl::Acl, collection::Collection, keyword::Keyword, property::Property, state::StateChange,
+    type_state::DataType,
+};
 use mail_parser::MessageParser;
+use store::{
+    roaring::RoaringBitmap,
+    write::{assert::HashedValue, log::ChangeLogBuilder},
+};
 
 use super::{ImapContext, ToModSeq};
 
@@ -59,12 +69,60 @@ impl<T: SessionStream> Session<T> {
             data.write_bytes(response).await
         })
     }
+
+    pub async fn handle_replace(
+        &mut self,
+        request: Request<Command>,
+        is_uid: bool,
+    ) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapReplace)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_replace(self.version)?;
+        let (data, selected_mailbox) = self.state.select_data();
+
+        // Refresh mailboxes
+        data.synchronize_mailboxes(false)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+
+        // Obtain mailbox
+        let mailbox = if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
+            mailbox
+        } else {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Mailbox does not exist.")
+                .code(ResponseCode::TryCreate)
+                .id(arguments.tag));
+        };
+        let is_qresync = self.is_qresync;
+        let is_condstore = self.is_condstore;
+
+        spawn_op!(data, {
+            let response = data
+                .replace_message(
+                    arguments,
+                    selected_mailbox,
+                    mailbox,
+                    is_uid,
+                    is_qresync || is_condstore,
+                    is_qresync,
+                    op_start,
+                )
+                .await?
+                .into_bytes();
+
+            data.write_bytes(response).await
+        })
+    }
 }
 
 impl<T: SessionStream> SessionData<T> {
     async fn append_messages(
         &self,
-        arguments: Arguments,
+        mut arguments: Arguments,
         selected_mailbox: Option<Arc<SelectedMailbox>>,
         mailbox: MailboxId,
         is_qresync: bool,
@@ -87,6 +145,20 @@ impl<T: SessionStream> SessionData<T> {
                 .id(arguments.tag));
         }
 
+        // Build catenated messages
+        for message in &mut arguments.messages {
+            if !message.catenate.is_empty() {
+                match self
+                    .catenate_message(std::mem::take(&mut message.catenate))
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?
+                {
+                    Ok(raw_message) => message.message = raw_message,
+                    Err(response) => return Ok(response.with_tag(arguments.tag)),
+                }
+            }
+        }
+
         // Obtain quota
         let access_token = self
             .server
@@ -114,6 +186,7 @@ impl<T: SessionStream> SessionData<T> {
                     spam_classify: false,
                     spam_train,
                     session_id: self.session_id,
+                    replace: None,
                 })
                 .await
             {
@@ -125,18 +198,7 @@ impl<T: SessionStream> SessionData<T> {
                     last_change_id = Some(email.change_id);
                 }
                 Err(err) => {
-                    return Err(
-                        if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) {
-                            err.details("Disk quota exceeded.")
-                                .code(ResponseCode::OverQuota)
-                        } else if err.matches(trc::EventType::Limit(trc::LimitEvent::TenantQuota)) {
-                            err.details("Organization disk quota exceeded.")
-                                .code(ResponseCode::OverQuota)
-                        } else {
-                            err
-                        }
-                        .id(arguments.tag),
-                    );
+                    return Err(quota_error(err).id(arguments.tag));
                 }
             }
         }
@@ -191,4 +253,219 @@ impl<T: SessionStream> SessionData<T> {
 
         Ok(response.with_tag(arguments.tag))
     }
+    #[allow(clippy::too_many_arguments)]
+    async fn replace_message(
+        &self,
+        mut arguments: replace::Arguments,
+        selected_mailbox: Arc<SelectedMailbox>,
+        mailbox: MailboxId,
+        is_uid: bool,
+        is_condstore: bool,
+        is_qresync: bool,
+        op_start: Instant,
+    ) -> trc::Result<StatusResponse> {
+        // Verify ACLs
+        let source_account_id = selected_mailbox.id.account_id;
+        let source_mailbox_id = selected_mailbox.id.mailbox_id;
+        let account_id = mailbox.account_id;
+        let mailbox_id = mailbox.mailbox_id;
+        if !self
+            .check_mailbox_acl(source_account_id, source_mailbox_id, Acl::RemoveItems)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details(concat!(
+                    "You do not have the required permissions ",
+                    "to remove messages from this mailbox."
+                ))
+                .code(ResponseCode::NoPerm)
+                .id(arguments.tag));
+        } else if !self
+            .check_mailbox_acl(account_id, mailbox_id, Acl::AddItems)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details(
+                    "You do not have the required permissions to append messages to this mailbox.",
+                )
+                .code(ResponseCode::NoPerm)
+                .id(arguments.tag));
+        }
+
+        // Obtain message to replace
+        let document_id = selected_mailbox
+            .sequence_to_ids(&arguments.sequence, is_uid)
+            .await
+            .map_err(|err| err.id(arguments.tag.clone()))?
+            .into_keys()
+            .next()
+            .ok_or_else(|| {
+                trc::ImapEvent::Error
+                    .into_err()
+                    .details("Message does not exist.")
+                    .id(arguments.tag.clone())
+            })?;
+        let (mailbox_ids, thread_id) = if let (Some(mailbox_ids), Some(thread_id)) = (
+            self.server
+                .get_property::<HashedValue<Vec<UidMailbox>>>(
+                    source_account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::MailboxIds,
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?,
+            self.server
+                .get_property::<u32>(
+                    source_account_id,
+                    Collection::Email,
+                    document_id,
+                    Property::ThreadId,
+                )
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?,
+        ) {
+            (mailbox_ids, thread_id)
+        } else {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Message does not exist.")
+                .id(arguments.tag));
+        };
+
+        // Build catenated message
+        if !arguments.message.catenate.is_empty() {
+            match self
+                .catenate_message(std::mem::take(&mut arguments.message.catenate))
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+            {
+                Ok(raw_message) => arguments.message.message = raw_message,
+                Err(response) => return Ok(response.with_tag(arguments.tag)),
+            }
+        }
+
+        // Obtain quota
+        let access_token = self
+            .server
+            .get_access_token(account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let resource_token = access_token.as_resource_token();
+        let spam_train = self.server.email_bayes_can_train(&access_token);
+
+        // Append the new message and expunge the old one in the same batch,
+        // unless the new message belongs to a different account.
+        let is_same_account = account_id == source_account_id;
+        let message = arguments.message;
+        let email = self
+            .server
+            .email_ingest(IngestEmail {
+                raw_message: &message.message,
+                message: MessageParser::new().parse(&message.message),
+                resource: resource_token,
+                mailbox_ids: vec![mailbox_id],
+                keywords: message.flags.into_iter().map(Keyword::from).collect(),
+                received_at: message.received_at.map(|d| d as u64),
+                source: IngestSource::Imap,
+                spam_classify: false,
+                spam_train,
+                session_id: self.session_id,
+                replace: is_same_account.then_some(ReplaceEmail {
+                    document_id,
+                    thread_id,
+                    mailbox_id: source_mailbox_id,
+                    mailbox_ids,
+                }),
+            })
+            .await
+            .map_err(|err| quota_error(err).id(arguments.tag.clone()))?;
+        self.server
+            .broadcast_state_change(
+                StateChange::new(account_id)
+                    .with_change(DataType::Email, email.change_id)
+                    .with_change(DataType::Mailbox, email.change_id)
+                    .with_change(DataType::Thread, email.change_id),
+            )
+            .await;
+
+        if !is_same_account {
+            let mut changelog = ChangeLogBuilder::new();
+            self.email_untag_or_delete(
+                source_account_id,
+                source_mailbox_id,
+                &RoaringBitmap::from_iter([document_id]),
+                &mut changelog,
+            )
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+            if !changelog.is_empty() {
+                let change_id = self
+                    .server
+                    .commit_changes(source_account_id, changelog)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?;
+                self.server
+                    .broadcast_state_change(
+                        StateChange::new(source_account_id)
+                            .with_change(DataType::Email, change_id)
+                            .with_change(DataType::Mailbox, change_id)
+                            .with_change(DataType::Thread, change_id),
+                    )
+                    .await;
+            }
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Replace),
+            SpanId = self.session_id,
+            MailboxName = arguments.mailbox_name,
+            AccountId = account_id,
+            MailboxId = mailbox_id,
+            DocumentId = email.id.document_id(),
+            Id = document_id,
+            Elapsed = op_start.elapsed()
+        );
+
+        // Clear saved searches
+        *selected_mailbox.saved_search.lock() = SavedSearch::None;
+
+        // Synchronize messages
+        let modseq = self
+            .write_mailbox_changes(&selected_mailbox, is_qresync)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        if is_condstore {
+            self.write_bytes(HighestModSeq::new(modseq.to_modseq()).into_bytes())
+                .await?;
+        }
+
+        let uid_validity = self
+            .get_uid_validity(&mailbox)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+
+        Ok(StatusResponse::completed(Command::Replace(is_uid))
+            .with_tag(arguments.tag)
+            .with_code(ResponseCode::AppendUid {
+                uid_validity,
+                uids: email.imap_uids,
+            }))
+    }
+}
+
+fn quota_error(err: trc::Error) -> trc::Error {
+    if err.matches(trc::EventType::Limit(trc::LimitEvent::Quota)) {
+        err.details("Disk quota exceeded.")
+            .code(ResponseCode::OverQuota)
+    } else if err.matches(trc::EventType::Limit(trc::LimitEvent::TenantQuota)) {
+        err.details("Organization disk quota exceeded.")
+            .code(ResponseCode::OverQuota)
+    } else {
+        err
+    }
 }
@@ -129,6 +129,7 @@ impl<T: SessionStream> Session<T> {
                     capabilities: Capability::all_capabilities(
                         true,
                         !self.is_tls && self.instance.acceptor.is_tls(),
+                        self.is_compress_available(),
                     ),
                 })
                 .with_tag(tag)
@@ -139,6 +140,7 @@ impl<T: SessionStream> Session<T> {
 
     pub async fn handle_unauthenticate(&mut self, request: Request<Command>) -> trc::Result<()> {
         self.state = State::NotAuthenticated { auth_failures: 0 };
+        self.notify = None;
 
         self.write_bytes(
             StatusResponse::completed(Command::Unauthenticate)
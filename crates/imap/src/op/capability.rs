@@ -40,6 +40,7 @@ impl<T: SessionStream> Session<T> {
                         capabilities: Capability::all_capabilities(
                             self.state.is_authenticated(),
                             !self.is_tls && self.instance.acceptor.is_tls(),
+                            self.state.is_authenticated() && self.is_compress_available(),
                         ),
                     }
                     .serialize(),
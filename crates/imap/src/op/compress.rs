@@ -0,0 +1,72 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{sync::Arc, time::Instant};
+
+use crate::core::{Session, State};
+use common::listener::SessionStream;
+use directory::Permission;
+use imap_proto::{Command, ResponseCode, StatusResponse, receiver::Request};
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_compress(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapCompress)?;
+
+        let op_start = Instant::now();
+        let arguments = request.parse_compress()?;
+
+        if self.is_compress {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Compression is already active.")
+                .code(ResponseCode::CompressionActive)
+                .id(arguments.tag));
+        } else if !self.is_compress_available() {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Compression is not available on this listener.")
+                .code(ResponseCode::Cannot)
+                .id(arguments.tag));
+        }
+
+        // Commands still in flight hold a reference to the session data and
+        // would write uncompressed responses once the stream is switched.
+        if let State::Authenticated { data } | State::Selected { data, .. } = &self.state {
+            if Arc::strong_count(data) > 1 {
+                return Err(trc::ImapEvent::Error
+                    .into_err()
+                    .details("Other commands are still in progress.")
+                    .code(ResponseCode::InUse)
+                    .id(arguments.tag));
+            }
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Compress),
+            SpanId = self.session_id,
+            Details = arguments.algorithm.as_str(),
+            Elapsed = op_start.elapsed()
+        );
+
+        self.write_bytes(
+            StatusResponse::ok("DEFLATE active")
+                .with_tag(arguments.tag)
+                .into_bytes(),
+        )
+        .await
+    }
+
+    pub fn is_compress_available(&self) -> bool {
+        !self.is_compress
+            && self
+                .server
+                .core
+                .imap
+                .compress_listeners
+                .contains(&self.instance.id)
+    }
+}
@@ -219,7 +219,7 @@ impl<T: SessionStream> SessionData<T> {
                             .imap_ctx(&arguments.tag, trc::location!())?;
                         debug_assert!(assigned_uid > 0);
                         copied_ids.push((imap_id.uid, assigned_uid));
-                        uid_mailbox.uid = assigned_uid;
+                        uid_mailbox.assign_uid(assigned_uid);
                     }
                 }
 
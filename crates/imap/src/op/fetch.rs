@@ -13,7 +13,7 @@ use crate::{
 use ahash::AHashMap;
 use common::listener::SessionStream;
 use directory::Permission;
-use email::metadata::MessageMetadata;
+use email::{mailbox::UidMailbox, metadata::MessageMetadata};
 use imap_proto::{
     parser::PushUnique,
     protocol::{
@@ -292,6 +292,9 @@ impl<T: SessionStream> SessionData<T> {
             .map(|(id, imap_id)| (imap_id.seqnum, imap_id.uid, id))
             .collect::<Vec<_>>();
         ids.sort_unstable_by_key(|(seqnum, _, _)| *seqnum);
+        if let Some(partial) = &arguments.partial {
+            ids = partial.slice(&ids).to_vec();
+        }
         let fetched_ids = ids
             .iter()
             .map(|id| trc::Value::from(id.2))
@@ -516,6 +519,27 @@ impl<T: SessionStream> SessionData<T> {
                             thread_id: Id::from_parts(account_id, thread_id).to_string(),
                         });
                     }
+                    Attribute::SaveDate => {
+                        items.push(DataItem::SaveDate {
+                            date: self
+                                .server
+                                .get_property::<Vec<UidMailbox>>(
+                                    account_id,
+                                    Collection::Email,
+                                    id,
+                                    Property::MailboxIds,
+                                )
+                                .await
+                                .imap_ctx(&arguments.tag, trc::location!())?
+                                .and_then(|mailboxes| {
+                                    mailboxes
+                                        .into_iter()
+                                        .find(|m| m.mailbox_id == mailbox.id.mailbox_id)
+                                })
+                                .and_then(|m| m.save_date())
+                                .map(|date| date as i64),
+                        });
+                    }
                 }
             }
 
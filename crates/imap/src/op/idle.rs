@@ -39,9 +39,14 @@ impl<T: SessionStream> Session<T> {
 
         let op_start = Instant::now();
         let (data, mailbox, types) = match &self.state {
-            State::Authenticated { data, .. } => {
+            State::Authenticated { data, .. } if self.notify.is_none() => {
                 (data.clone(), None, Bitmap::from_iter([DataType::Mailbox]))
             }
+            State::Authenticated { data, .. } => (
+                data.clone(),
+                None,
+                Bitmap::from_iter([DataType::Email, DataType::Mailbox, DataType::EmailDelivery]),
+            ),
             State::Selected { data, mailbox, .. } => (
                 data.clone(),
                 mailbox.clone().into(),
@@ -97,7 +102,10 @@ impl<T: SessionStream> Session<T> {
                     }
                 }
                 state_change = change_rx.recv() => {
-                    if let Some(state_change) = state_change {
+                    if self.notify.is_some() && state_change.is_some() {
+                        // Changes are reported as requested by NOTIFY
+                        self.write_notifications(state_change, true).await?;
+                    } else if let Some(state_change) = state_change {
                         let mut has_mailbox_changes = false;
                         let mut has_email_changes = false;
 
@@ -240,6 +248,7 @@ impl<T: SessionStream> SessionData<T> {
                                 attributes: vec![fetch::Attribute::Flags, fetch::Attribute::Uid],
                                 changed_since: None,
                                 include_vanished: false,
+                                partial: None,
                             },
                             mailbox.clone(),
                             true,
@@ -0,0 +1,300 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use crate::{
+    core::{Session, SessionData},
+    op::ImapContext,
+    spawn_op,
+};
+use common::listener::SessionStream;
+use directory::Permission;
+use email::mailbox::MailboxFnc;
+use imap_proto::{
+    Command, ResponseCode, StatusResponse,
+    protocol::{
+        ImapResponse,
+        metadata::{GetArguments, Response, SetArguments},
+    },
+    receiver::Request,
+};
+use jmap_proto::types::acl::Acl;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_get_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapGetMetadata)?;
+
+        let data = self.state.session_data();
+        let version = self.version;
+
+        spawn_op!(data, {
+            let response = data
+                .get_metadata(request.parse_get_metadata(version)?, version.is_rev2())
+                .await?;
+            data.write_bytes(response).await
+        })
+    }
+
+    pub async fn handle_set_metadata(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapSetMetadata)?;
+
+        let data = self.state.session_data();
+        let version = self.version;
+
+        spawn_op!(data, {
+            let response = data
+                .set_metadata(request.parse_set_metadata(version)?)
+                .await?;
+            data.write_bytes(response.into_bytes()).await
+        })
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    pub async fn get_metadata(
+        &self,
+        arguments: GetArguments,
+        is_rev2: bool,
+    ) -> trc::Result<Vec<u8>> {
+        let op_start = Instant::now();
+
+        // Validate mailbox
+        let (account_id, mailbox_id) = self
+            .metadata_mailbox(&arguments.tag, &arguments.mailbox_name, Acl::ReadItems)
+            .await?;
+
+        // Obtain annotations
+        let annotations = self
+            .server
+            .mailbox_get_annotations(account_id, mailbox_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?
+            .map(|annotations| annotations.inner)
+            .unwrap_or_default();
+        let server_entries = if mailbox_id.is_none() {
+            self.server.core.imap.metadata_server.as_slice()
+        } else {
+            &[]
+        };
+        let available = annotations
+            .visible(self.account_id)
+            .map(|entry| (entry.name.as_str(), entry.value.as_slice()))
+            .chain(
+                server_entries
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_bytes())),
+            )
+            .collect::<Vec<_>>();
+
+        // Build response
+        let mut entries: Vec<(String, Option<Vec<u8>>)> = Vec::new();
+        let mut long_entries = 0;
+        for requested in &arguments.entries {
+            let mut found = false;
+            for (name, value) in &available {
+                if arguments.depth.matches(requested, name)
+                    && !entries
+                        .iter()
+                        .any(|(entry, _)| entry.eq_ignore_ascii_case(name))
+                {
+                    found = true;
+                    if arguments
+                        .max_size
+                        .is_some_and(|max_size| value.len() > max_size)
+                    {
+                        long_entries = long_entries.max(value.len());
+                    } else {
+                        entries.push((name.to_string(), Some(value.to_vec())));
+                    }
+                }
+            }
+            if !found
+                && !entries
+                    .iter()
+                    .any(|(entry, _)| entry.eq_ignore_ascii_case(requested))
+            {
+                entries.push((requested.to_string(), None));
+            }
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::GetMetadata),
+            SpanId = self.session_id,
+            AccountId = account_id,
+            MailboxId = mailbox_id,
+            MailboxName = arguments.mailbox_name.clone(),
+            Details = arguments
+                .entries
+                .iter()
+                .map(|entry| trc::Value::from(entry.to_string()))
+                .collect::<Vec<_>>(),
+            Elapsed = op_start.elapsed()
+        );
+
+        let mut response = StatusResponse::completed(Command::GetMetadata).with_tag(arguments.tag);
+        if long_entries > 0 {
+            response = response.with_code(ResponseCode::MetadataLongEntries { size: long_entries });
+        }
+
+        Ok(response.serialize(
+            Response {
+                mailbox_name: arguments.mailbox_name,
+                entries,
+                is_rev2,
+            }
+            .serialize(),
+        ))
+    }
+
+    pub async fn set_metadata(&self, arguments: SetArguments) -> trc::Result<StatusResponse> {
+        let op_start = Instant::now();
+
+        // Private entries only require read access, shared entries require write access
+        let is_shared = arguments
+            .entries
+            .iter()
+            .any(|(name, _)| !name.to_ascii_lowercase().starts_with("/private/"));
+        let (account_id, mailbox_id) = self
+            .metadata_mailbox(
+                &arguments.tag,
+                &arguments.mailbox_name,
+                if is_shared {
+                    Acl::ModifyItems
+                } else {
+                    Acl::ReadItems
+                },
+            )
+            .await?;
+        if is_shared && mailbox_id.is_none() {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Shared server annotations cannot be modified.")
+                .code(ResponseCode::NoPerm)
+                .id(arguments.tag));
+        }
+
+        // Validate entry sizes
+        let max_size = self.server.core.imap.metadata_max_size;
+        if arguments
+            .entries
+            .iter()
+            .any(|(_, value)| value.as_ref().is_some_and(|value| value.len() > max_size))
+        {
+            return Ok(StatusResponse::no("Annotation value is too large.")
+                .with_tag(arguments.tag)
+                .with_code(ResponseCode::MetadataMaxSize { size: max_size }));
+        }
+
+        // Apply changes
+        let current = self
+            .server
+            .mailbox_get_annotations(account_id, mailbox_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+        let mut annotations = current
+            .as_ref()
+            .map(|annotations| annotations.inner.clone())
+            .unwrap_or_default();
+        let old_size = annotations.size();
+        let num_entries = arguments.entries.len();
+        for (name, value) in arguments.entries {
+            annotations.set(self.account_id, name, value);
+        }
+
+        // Validate limits
+        if annotations.visible(self.account_id).count() > self.server.core.imap.metadata_max_entries
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Too many annotations.")
+                .code(ResponseCode::MetadataTooMany)
+                .id(arguments.tag));
+        }
+        let new_size = annotations.size();
+        let quota = self.server.core.imap.metadata_quota;
+        if quota > 0
+            && new_size > old_size
+            && self
+                .server
+                .mailbox_annotations_size(account_id)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                + new_size
+                - old_size
+                > quota
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Annotation storage quota exceeded.")
+                .code(ResponseCode::OverQuota)
+                .id(arguments.tag));
+        }
+
+        // Write changes
+        self.server
+            .mailbox_set_annotations(account_id, mailbox_id, current, annotations)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+
+        trc::event!(
+            Imap(trc::ImapEvent::SetMetadata),
+            SpanId = self.session_id,
+            AccountId = account_id,
+            MailboxId = mailbox_id,
+            MailboxName = arguments.mailbox_name,
+            Total = num_entries,
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::completed(Command::SetMetadata).with_tag(arguments.tag))
+    }
+
+    async fn metadata_mailbox(
+        &self,
+        tag: &str,
+        mailbox_name: &str,
+        acl: Acl,
+    ) -> trc::Result<(u32, Option<u32>)> {
+        // An empty mailbox name refers to the server annotations
+        if mailbox_name.is_empty() {
+            return Ok((self.account_id, None));
+        }
+
+        // Refresh mailboxes
+        self.synchronize_mailboxes(false)
+            .await
+            .imap_ctx(tag, trc::location!())?;
+
+        // Validate mailbox
+        let mailbox = self.get_mailbox_by_name(mailbox_name).ok_or_else(|| {
+            trc::ImapEvent::Error
+                .into_err()
+                .details("Mailbox does not exist.")
+                .code(ResponseCode::NonExistent)
+                .id(tag.to_string())
+        })?;
+
+        // Validate ACLs
+        if !self
+            .check_mailbox_acl(mailbox.account_id, mailbox.mailbox_id, acl)
+            .await
+            .imap_ctx(tag, trc::location!())?
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details(
+                    "You do not have enough permissions to access annotations on this mailbox.",
+                )
+                .code(ResponseCode::NoPerm)
+                .id(tag.to_string()));
+        }
+
+        Ok((mailbox.account_id, Some(mailbox.mailbox_id)))
+    }
+}
@@ -0,0 +1,430 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use common::{listener::SessionStream, MailboxId};
+use directory::Permission;
+use email::mailbox::INBOX_ID;
+use imap_proto::{
+    protocol::{
+        fetch,
+        list::{Attribute, ListItem, Tag},
+        notify::{self, Event, EventGroup, Filter},
+        status::Status,
+        Sequence,
+    },
+    receiver::Request,
+    Command, ResponseCode, StatusResponse,
+};
+use jmap::services::state::StateManager;
+use jmap_proto::types::{state::StateChange, type_state::DataType};
+use trc::AddContext;
+use utils::map::bitmap::Bitmap;
+
+use crate::core::{Notifier, SelectedMailbox, Session, SessionData};
+
+use super::ImapContext;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_notify(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapNotify)?;
+
+        let op_start = Instant::now();
+        let (tag, status, groups) = match request.parse_notify(self.version)? {
+            notify::Arguments::Set {
+                tag,
+                status,
+                groups,
+            } => (tag, status, groups),
+            notify::Arguments::None { tag } => {
+                self.notify = None;
+
+                trc::event!(
+                    Imap(trc::ImapEvent::Notify),
+                    SpanId = self.session_id,
+                    Details = "NONE",
+                    Elapsed = op_start.elapsed()
+                );
+
+                return self
+                    .write_bytes(
+                        StatusResponse::completed(Command::Notify)
+                            .with_tag(tag)
+                            .into_bytes(),
+                    )
+                    .await;
+            }
+        };
+
+        // Make sure all requested events are supported
+        if groups
+            .iter()
+            .flat_map(|group| group.events.iter())
+            .any(|event| !event.is_supported())
+        {
+            return self
+                .write_bytes(
+                    StatusResponse::no("Unsupported event requested.")
+                        .with_tag(tag)
+                        .with_code(ResponseCode::BadEvent)
+                        .into_bytes(),
+                )
+                .await;
+        }
+
+        // Register with state manager
+        let (data, mailbox) = self.state.session_mailbox_state();
+        let change_rx = self
+            .server
+            .subscribe_state_manager(
+                data.account_id,
+                Bitmap::from_iter([DataType::Email, DataType::Mailbox, DataType::EmailDelivery]),
+            )
+            .await
+            .imap_ctx(&tag, trc::location!())?;
+
+        // Only changes made after this point are reported
+        data.synchronize_mailboxes(false)
+            .await
+            .imap_ctx(&tag, trc::location!())?;
+
+        // Send the status of all monitored mailboxes
+        let mut buf = Vec::with_capacity(64);
+        if status {
+            let selected_id = mailbox.as_ref().map(|mailbox| mailbox.id);
+            let mailbox_names = data
+                .mailboxes
+                .lock()
+                .iter()
+                .flat_map(|account| account.mailbox_names.keys().cloned())
+                .collect::<Vec<_>>();
+            let items = self.notify_status_items();
+
+            for mailbox_name in mailbox_names {
+                if !data
+                    .notify_group(&groups, &mailbox_name, selected_id.as_ref())
+                    .is_some_and(|group| !group.filter.is_selected() && group.has_message_events())
+                {
+                    continue;
+                }
+                if let Ok(status) = data.status(mailbox_name, &items).await {
+                    status.serialize(&mut buf, self.version.is_rev2());
+                }
+            }
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::Notify),
+            SpanId = self.session_id,
+            Details = groups
+                .iter()
+                .map(|group| trc::Value::from(format!("{:?}", group.filter)))
+                .collect::<Vec<_>>(),
+            Elapsed = op_start.elapsed()
+        );
+
+        self.notify = Some(Notifier { groups, change_rx });
+
+        self.write_bytes(
+            StatusResponse::completed(Command::Notify)
+                .with_tag(tag)
+                .serialize(buf),
+        )
+        .await
+    }
+
+    pub async fn write_notifications(
+        &mut self,
+        state_change: Option<StateChange>,
+        allow_expunge: bool,
+    ) -> trc::Result<()> {
+        let state_change = if let Some(state_change) = state_change {
+            state_change
+        } else {
+            // The state manager dropped the subscription, notifications are no longer sent
+            self.notify = None;
+            return self
+                .write_bytes(
+                    StatusResponse::ok("Notifications disabled.")
+                        .with_code(ResponseCode::NotificationOverflow)
+                        .into_bytes(),
+                )
+                .await;
+        };
+        let groups = if let Some(notify) = &self.notify {
+            &notify.groups
+        } else {
+            return Ok(());
+        };
+        let (data, mailbox) = self.state.session_mailbox_state();
+        let selected_id = mailbox.as_ref().map(|mailbox| mailbox.id);
+        let is_rev2 = self.version.is_rev2();
+        let has_email_changes = state_change
+            .types
+            .iter()
+            .any(|(type_state, _)| matches!(type_state, DataType::Email | DataType::EmailDelivery));
+
+        // Report changes to mailboxes other than the selected one
+        let changes = data
+            .synchronize_mailboxes(true)
+            .await
+            .caused_by(trc::location!())?
+            .unwrap();
+        let mut buf = Vec::with_capacity(64);
+        for (mailbox_names, event, attributes) in [
+            (changes.deleted, Event::MailboxName, vec![Attribute::NonExistent]),
+            (changes.added, Event::MailboxName, vec![]),
+            (
+                changes.subscribed,
+                Event::SubscriptionChange,
+                vec![Attribute::Subscribed],
+            ),
+            (changes.unsubscribed, Event::SubscriptionChange, vec![]),
+        ] {
+            for mailbox_name in mailbox_names {
+                if data
+                    .notify_group(groups, &mailbox_name, selected_id.as_ref())
+                    .is_some_and(|group| group.has_event(&event))
+                {
+                    ListItem {
+                        mailbox_name,
+                        attributes: attributes.clone(),
+                        tags: vec![],
+                    }
+                    .serialize(&mut buf, is_rev2, false);
+                }
+            }
+        }
+        for (old_name, mailbox_name) in changes.renamed {
+            if data
+                .notify_group(groups, &mailbox_name, selected_id.as_ref())
+                .is_some_and(|group| group.has_event(&Event::MailboxName))
+            {
+                ListItem {
+                    mailbox_name,
+                    attributes: vec![],
+                    tags: vec![Tag::OldName(old_name)],
+                }
+                .serialize(&mut buf, is_rev2, false);
+            }
+        }
+        let items = self.notify_status_items();
+        for mailbox_name in changes.changed {
+            if !data
+                .notify_group(groups, &mailbox_name, selected_id.as_ref())
+                .is_some_and(|group| !group.filter.is_selected() && group.has_message_events())
+            {
+                continue;
+            }
+            if let Ok(status) = data.status(mailbox_name, &items).await {
+                status.serialize(&mut buf, is_rev2);
+            }
+        }
+        if !buf.is_empty() {
+            data.write_bytes(buf).await?;
+        }
+
+        // Report changes to the selected mailbox
+        if let (Some(mailbox), true) = (mailbox, has_email_changes) {
+            let group = if let Some(group) = groups
+                .iter()
+                .find(|group| group.filter.is_selected() && group.has_message_events())
+            {
+                group
+            } else {
+                return Ok(());
+            };
+
+            // SELECTED-DELAYED defers expunges until a command that allows them
+            if group.filter == Filter::SelectedDelayed
+                && !allow_expunge
+                && data
+                    .has_pending_expunges(&mailbox)
+                    .await
+                    .caused_by(trc::location!())?
+            {
+                return Ok(());
+            }
+            let uid_max = mailbox.state.lock().uid_max;
+
+            data.write_changes(
+                &Some(mailbox.clone()),
+                false,
+                true,
+                self.is_qresync,
+                is_rev2,
+            )
+            .await?;
+
+            // Fetch the requested attributes of new messages
+            if let Some(attributes) = group
+                .new_message_attributes()
+                .filter(|attributes| !attributes.is_empty())
+            {
+                let new_uids = mailbox
+                    .state
+                    .lock()
+                    .id_to_imap
+                    .values()
+                    .filter(|id| id.uid > uid_max)
+                    .map(|id| Sequence::Number { value: id.uid })
+                    .collect::<Vec<_>>();
+
+                if !new_uids.is_empty() {
+                    let mut attributes = attributes.to_vec();
+                    if !attributes.contains(&fetch::Attribute::Uid) {
+                        attributes.push(fetch::Attribute::Uid);
+                    }
+
+                    data.fetch(
+                        fetch::Arguments {
+                            tag: String::new(),
+                            sequence_set: Sequence::List { items: new_uids },
+                            attributes,
+                            changed_since: None,
+                            include_vanished: false,
+                            partial: None,
+                        },
+                        mailbox,
+                        true,
+                        self.is_qresync,
+                        is_rev2,
+                        false,
+                        Instant::now(),
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn notify_status_items(&self) -> Vec<Status> {
+        let mut items = vec![
+            Status::Messages,
+            Status::UidNext,
+            Status::UidValidity,
+            Status::Unseen,
+        ];
+        if self.is_condstore {
+            items.push(Status::HighestModSeq);
+        }
+        items
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    async fn has_pending_expunges(&self, mailbox: &SelectedMailbox) -> trc::Result<bool> {
+        if mailbox
+            .state
+            .lock()
+            .next_state
+            .as_ref()
+            .is_some_and(|next_state| !next_state.deletions.is_empty())
+        {
+            return Ok(true);
+        }
+
+        let new_state = self.fetch_messages_cached(&mailbox.id).await?;
+        Ok(mailbox
+            .state
+            .lock()
+            .id_to_imap
+            .values()
+            .any(|id| !new_state.uid_to_id.contains_key(&id.uid)))
+    }
+
+    pub fn notify_group<'x>(
+        &self,
+        groups: &'x [EventGroup],
+        mailbox_name: &str,
+        selected_id: Option<&MailboxId>,
+    ) -> Option<&'x EventGroup> {
+        // Locate the mailbox, deleted mailboxes are no longer in the cache
+        let mut mailbox_id = None;
+        let mut is_personal = !mailbox_name.starts_with(&self.server.core.jmap.shared_folder);
+        let mut is_subscribed = false;
+        for account in self.mailboxes.lock().iter() {
+            if let Some(document_id) = account.mailbox_names.get(mailbox_name) {
+                mailbox_id = Some(MailboxId {
+                    account_id: account.account_id,
+                    mailbox_id: *document_id,
+                });
+                is_personal = account.prefix.is_none();
+                is_subscribed = account
+                    .mailbox_state
+                    .get(document_id)
+                    .is_some_and(|mailbox| mailbox.is_subscribed);
+                break;
+            }
+        }
+        let is_selected = mailbox_id.is_some() && mailbox_id.as_ref() == selected_id;
+        let is_inbox = is_personal
+            && mailbox_id.is_none_or(|id| id.mailbox_id == INBOX_ID)
+            && mailbox_name.eq_ignore_ascii_case("INBOX");
+
+        // The first matching filter determines which events are reported
+        groups.iter().find(|group| {
+            (group.filter.is_selected() || !is_selected)
+                && filter_matches(
+                    &group.filter,
+                    mailbox_name,
+                    is_selected,
+                    is_inbox,
+                    is_personal,
+                    is_subscribed,
+                )
+        })
+    }
+}
+
+pub(crate) fn filter_matches(
+    filter: &Filter,
+    mailbox_name: &str,
+    is_selected: bool,
+    is_inbox: bool,
+    is_personal: bool,
+    is_subscribed: bool,
+) -> bool {
+    match filter {
+        Filter::Selected | Filter::SelectedDelayed => is_selected,
+        Filter::Inboxes => is_inbox,
+        Filter::Personal => is_personal,
+        Filter::Subscribed => is_subscribed,
+        Filter::Subtree(names) => names.iter().any(|name| {
+            mailbox_name == name
+                || mailbox_name
+                    .strip_prefix(name.as_str())
+                    .is_some_and(|suffix| suffix.starts_with('/'))
+        }),
+        Filter::SubtreeOne(names) => names.iter().any(|name| {
+            mailbox_name == name
+                || mailbox_name
+                    .strip_prefix(name.as_str())
+                    .and_then(|suffix| suffix.strip_prefix('/'))
+                    .is_some_and(|child| !child.is_empty() && !child.contains('/'))
+        }),
+        Filter::Mailboxes(names) => names.iter().any(|name| {
+            mailbox_name == name
+                || (name.eq_ignore_ascii_case("INBOX")
+                    && mailbox_name.eq_ignore_ascii_case("INBOX"))
+        }),
+    }
+}
+
+impl Notifier {
+    pub async fn recv(notify: &mut Option<Notifier>) -> Option<StateChange> {
+        if let Some(notify) = notify {
+            notify.change_rx.recv().await
+        } else {
+            std::future::pending().await
+        }
+    }
+}
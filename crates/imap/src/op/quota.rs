@@ -19,6 +19,7 @@ use crate::{
 };
 use common::listener::SessionStream;
 use directory::Permission;
+use email::mailbox::MailboxFnc;
 use imap_proto::{
     protocol::{
         capability::QuotaResourceName,
@@ -111,6 +112,11 @@ impl<T: SessionStream> SessionData<T> {
             .get_used_quota(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
+        let used_annotations = self
+            .server
+            .mailbox_annotations_size(account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
 
         trc::event!(
             Imap(trc::ImapEvent::GetQuota),
@@ -128,11 +134,18 @@ impl<T: SessionStream> SessionData<T> {
             quota_root_items: vec![],
             quota_items: vec![QuotaItem {
                 name: arguments.name,
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
-                }],
+                resources: vec![
+                    QuotaResource {
+                        resource: QuotaResourceName::Storage,
+                        total: access_token.quota,
+                        used: used_quota as u64,
+                    },
+                    QuotaResource {
+                        resource: QuotaResourceName::AnnotationStorage,
+                        total: self.server.core.imap.metadata_quota,
+                        used: used_annotations,
+                    },
+                ],
             }],
         };
 
@@ -171,6 +184,11 @@ impl<T: SessionStream> SessionData<T> {
             .get_used_quota(account_id)
             .await
             .imap_ctx(&arguments.tag, trc::location!())?;
+        let used_annotations = self
+            .server
+            .mailbox_annotations_size(account_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
 
         trc::event!(
             Imap(trc::ImapEvent::GetQuota),
@@ -188,11 +206,18 @@ impl<T: SessionStream> SessionData<T> {
             quota_root_items: vec![arguments.name, format!("#{account_id}")],
             quota_items: vec![QuotaItem {
                 name: format!("#{account_id}"),
-                resources: vec![QuotaResource {
-                    resource: QuotaResourceName::Storage,
-                    total: access_token.quota,
-                    used: used_quota as u64,
-                }],
+                resources: vec![
+                    QuotaResource {
+                        resource: QuotaResourceName::Storage,
+                        total: access_token.quota,
+                        used: used_quota as u64,
+                    },
+                    QuotaResource {
+                        resource: QuotaResourceName::AnnotationStorage,
+                        total: self.server.core.imap.metadata_quota,
+                        used: used_annotations,
+                    },
+                ],
             }],
         };
 
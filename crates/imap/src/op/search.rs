@@ -6,15 +6,18 @@
 
 use std::{sync::Arc, time::Instant};
 
-use common::{listener::SessionStream, ImapId};
+use common::{listener::SessionStream, ImapId, MailboxId};
 use directory::Permission;
+use email::mailbox::{UidMailbox, INBOX_ID};
 use imap_proto::{
     protocol::{
-        search::{self, Arguments, Filter, Response, ResultOption},
+        notify,
+        search::{self, Arguments, Filter, MailboxCorrelator, Response, ResultOption},
         Sequence,
     },
     receiver::Request,
-    Command, StatusResponse,
+    utf7::utf7_encode,
+    Command, ResponseType, StatusResponse,
 };
 use jmap::{changes::get::ChangesLookup, JmapMethods};
 use jmap_proto::types::{collection::Collection, id::Id, keyword::Keyword, property::Property};
@@ -34,7 +37,7 @@ use crate::{
     spawn_op,
 };
 
-use super::{FromModSeq, ToModSeq};
+use super::{notify::filter_matches, FromModSeq, ImapContext, ToModSeq};
 
 impl<T: SessionStream> Session<T> {
     pub async fn handle_search(
@@ -103,9 +106,151 @@ impl<T: SessionStream> Session<T> {
             data.write_bytes(bytes).await
         })
     }
+
+    pub async fn handle_esearch(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapSearch)?;
+
+        let op_start = Instant::now();
+        let mut arguments = request.parse_esearch(self.version)?;
+        let (data, selected_mailbox) = self.state.session_mailbox_state();
+        let is_rev2 = self.version.is_rev2();
+
+        // Sequence numbers and saved searches are not meaningful across mailboxes
+        if arguments
+            .arguments
+            .result_options
+            .contains(&ResultOption::Save)
+            || arguments.arguments.filter.iter().any(|filter| {
+                matches!(
+                    filter,
+                    Filter::Sequence(sequence, is_uid) if !is_uid || sequence.is_saved_search()
+                )
+            })
+        {
+            return Err(trc::ImapEvent::Error
+                .into_err()
+                .details("Sequence numbers and SAVE are not supported by ESEARCH.")
+                .ctx(trc::Key::Type, ResponseType::Bad)
+                .id(arguments.arguments.tag));
+        }
+
+        spawn_op!(data, {
+            let tag = std::mem::take(&mut arguments.arguments.tag);
+
+            // Refresh mailboxes
+            data.synchronize_mailboxes(false)
+                .await
+                .imap_ctx(&tag, trc::location!())?;
+
+            let mut buf = Vec::new();
+            for (mailbox_id, mailbox_name) in data.esearch_mailboxes(
+                &arguments.source,
+                selected_mailbox.as_ref().map(|mailbox| &mailbox.id),
+            ) {
+                // Use the session's view of the selected mailbox
+                let mailbox = match &selected_mailbox {
+                    Some(selected_mailbox) if selected_mailbox.id == mailbox_id => {
+                        selected_mailbox.clone()
+                    }
+                    _ => Arc::new(SelectedMailbox {
+                        id: mailbox_id,
+                        state: parking_lot::Mutex::new(
+                            data.fetch_messages_cached(&mailbox_id)
+                                .await
+                                .imap_ctx(&tag, trc::location!())?
+                                .as_ref()
+                                .clone(),
+                        ),
+                        saved_search: parking_lot::Mutex::new(SavedSearch::None),
+                        is_select: false,
+                        is_condstore: false,
+                    }),
+                };
+
+                let mut response = data
+                    .search(
+                        arguments.arguments.clone(),
+                        mailbox.clone(),
+                        None,
+                        None,
+                        true,
+                        op_start,
+                    )
+                    .await
+                    .map_err(|err| err.id(tag.clone()))?;
+
+                // Only mailboxes with matching messages are reported
+                if response.ids.is_empty()
+                    && response.min.is_none()
+                    && response.count.is_none_or(|count| count == 0)
+                    && response
+                        .partial
+                        .as_ref()
+                        .is_none_or(|(_, ids)| ids.is_empty())
+                {
+                    continue;
+                }
+
+                response.mailbox = Some(MailboxCorrelator {
+                    mailbox_name: if is_rev2 {
+                        mailbox_name
+                    } else {
+                        utf7_encode(&mailbox_name)
+                    },
+                    uid_validity: mailbox.state.lock().uid_validity,
+                });
+                buf.extend(response.serialize(&tag));
+            }
+
+            data.write_bytes(
+                StatusResponse::completed(Command::Esearch)
+                    .with_tag(tag)
+                    .serialize(buf),
+            )
+            .await
+        })
+    }
 }
 
 impl<T: SessionStream> SessionData<T> {
+    fn esearch_mailboxes(
+        &self,
+        source: &[notify::Filter],
+        selected_id: Option<&MailboxId>,
+    ) -> Vec<(MailboxId, String)> {
+        let mut mailboxes = Vec::new();
+        for account in self.mailboxes.lock().iter() {
+            let is_personal = account.prefix.is_none();
+            for (mailbox_name, mailbox_id) in &account.mailbox_names {
+                let id = MailboxId {
+                    account_id: account.account_id,
+                    mailbox_id: *mailbox_id,
+                };
+                let is_selected = selected_id == Some(&id);
+                let is_inbox = is_personal && *mailbox_id == INBOX_ID;
+                let is_subscribed = account
+                    .mailbox_state
+                    .get(mailbox_id)
+                    .is_some_and(|mailbox| mailbox.is_subscribed);
+
+                if source.iter().any(|filter| {
+                    filter_matches(
+                        filter,
+                        mailbox_name,
+                        is_selected,
+                        is_inbox,
+                        is_personal,
+                        is_subscribed,
+                    )
+                }) {
+                    mailboxes.push((id, mailbox_name.clone()));
+                }
+            }
+        }
+        mailboxes
+    }
+
     pub async fn search(
         &self,
         arguments: Arguments,
@@ -135,7 +280,16 @@ impl<T: SessionStream> SessionData<T> {
         let mut max: Option<(u32, ImapId)> = None;
         let mut total = 0;
         let results_len = result_set.results.len() as usize;
-        let mut saved_results = if results_tx.is_some() {
+        let partial = arguments.result_options.iter().find_map(|option| {
+            if let ResultOption::Partial(range) = option {
+                Some(*range)
+            } else {
+                None
+            }
+        });
+        let find_min = partial.is_none() && arguments.result_options.contains(&ResultOption::Min);
+        let find_max = partial.is_none() && arguments.result_options.contains(&ResultOption::Max);
+        let mut saved_results = if results_tx.is_some() || partial.is_some() {
             Some(Vec::with_capacity(results_len))
         } else {
             None
@@ -182,8 +336,8 @@ impl<T: SessionStream> SessionData<T> {
                     .into_iter()
                     .map(|id| id as u32),
                 is_uid,
-                arguments.result_options.contains(&ResultOption::Min),
-                arguments.result_options.contains(&ResultOption::Max),
+                find_min,
+                find_max,
                 &mut min,
                 &mut max,
                 &mut total,
@@ -195,8 +349,8 @@ impl<T: SessionStream> SessionData<T> {
             mailbox.map_search_results(
                 result_set.results.into_iter(),
                 is_uid,
-                arguments.result_options.contains(&ResultOption::Min),
-                arguments.result_options.contains(&ResultOption::Max),
+                find_min,
+                find_max,
                 &mut min,
                 &mut max,
                 &mut total,
@@ -207,6 +361,31 @@ impl<T: SessionStream> SessionData<T> {
             false
         };
 
+        // Apply partial range
+        let partial = if let Some(range) = partial {
+            let mut results = saved_results.take().unwrap_or_default();
+            if !is_sort {
+                results.sort_unstable_by_key(|imap_id| imap_id.uid);
+            }
+            let results = imap_ids.into_iter().zip(results).collect::<Vec<_>>();
+            imap_ids = Vec::new();
+            if arguments.result_options.contains(&ResultOption::Min) {
+                min = results.iter().min_by_key(|(id, _)| *id).copied();
+            }
+            if arguments.result_options.contains(&ResultOption::Max) {
+                max = results.iter().max_by_key(|(id, _)| *id).copied();
+            }
+            let results = range.slice(&results);
+            if results_tx.is_some() {
+                saved_results = Some(results.iter().map(|(_, imap_id)| *imap_id).collect());
+            }
+            let mut ids = results.iter().map(|(id, _)| *id).collect::<Vec<_>>();
+            ids.sort_unstable();
+            Some((range, ids))
+        } else {
+            None
+        };
+
         // Save results
         if let (Some(results_tx), Some(saved_results)) = (results_tx, saved_results) {
             let saved_results = Arc::new(saved_results);
@@ -246,9 +425,11 @@ impl<T: SessionStream> SessionData<T> {
             } else {
                 vec![]
             },
+            partial,
             is_sort,
             is_esearch: arguments.is_esearch,
             highest_modseq,
+            mailbox: None,
         })
     }
 
@@ -610,6 +791,32 @@ impl<T: SessionStream> SessionData<T> {
                             now().saturating_sub(secs as u64),
                         ));
                     }
+                    search::Filter::SavedBefore(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_between(mailbox, &message_ids, 0, date as u64)
+                                .await?,
+                        ));
+                    }
+                    search::Filter::SavedOn(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_between(
+                                mailbox,
+                                &message_ids,
+                                date as u64,
+                                (date + 86400) as u64,
+                            )
+                            .await?,
+                        ));
+                    }
+                    search::Filter::SavedSince(date) => {
+                        filters.push(query::Filter::is_in_set(
+                            self.saved_between(mailbox, &message_ids, date as u64, u64::MAX)
+                                .await?,
+                        ));
+                    }
+                    search::Filter::SaveDateSupported => {
+                        filters.push(query::Filter::is_in_set(message_ids.clone()));
+                    }
                     search::Filter::ModSeq((modseq, _)) => {
                         let mut set = RoaringBitmap::new();
                         for change in self
@@ -665,6 +872,39 @@ impl<T: SessionStream> SessionData<T> {
             .map(|res| (res, include_highest_modseq))
             .caused_by(trc::location!())
     }
+
+    async fn saved_between(
+        &self,
+        mailbox: &SelectedMailbox,
+        message_ids: &RoaringBitmap,
+        from: u64,
+        to: u64,
+    ) -> trc::Result<RoaringBitmap> {
+        // Save dates are kept for each mailbox the message belongs to
+        let mut set = RoaringBitmap::new();
+        for (id, mailboxes) in self
+            .server
+            .get_properties::<Vec<UidMailbox>, _, _>(
+                mailbox.id.account_id,
+                Collection::Email,
+                message_ids,
+                Property::MailboxIds,
+            )
+            .await
+            .caused_by(trc::location!())?
+        {
+            if mailboxes
+                .iter()
+                .find(|m| m.mailbox_id == mailbox.id.mailbox_id)
+                .and_then(|m| m.save_date())
+                .is_some_and(|date| date >= from && date < to)
+            {
+                set.insert(id);
+            }
+        }
+
+        Ok(set)
+    }
 }
 
 impl SelectedMailbox {
@@ -46,41 +46,12 @@ impl<T: SessionStream> Session<T> {
 
         if let Some(mailbox) = data.get_mailbox_by_name(&arguments.mailbox_name) {
             // Try obtaining the mailbox from the cache
-            let state = {
-                let modseq = data
-                    .get_modseq(mailbox.account_id)
-                    .await
-                    .imap_ctx(&arguments.tag, trc::location!())?;
-
-                if let Some(cached_state) =
-                    self.server
-                        .inner
-                        .cache
-                        .mailbox
-                        .get(&mailbox)
-                        .and_then(|cached_state| {
-                            if cached_state.modseq.unwrap_or(0) >= modseq.unwrap_or(0) {
-                                Some(cached_state)
-                            } else {
-                                None
-                            }
-                        })
-                {
-                    cached_state.as_ref().clone()
-                } else {
-                    let new_state = Arc::new(
-                        data.fetch_messages(&mailbox)
-                            .await
-                            .imap_ctx(&arguments.tag, trc::location!())?,
-                    );
-                    self.server
-                        .inner
-                        .cache
-                        .mailbox
-                        .insert(mailbox, new_state.clone());
-                    new_state.as_ref().clone()
-                }
-            };
+            let state = data
+                .fetch_messages_cached(&mailbox)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+                .as_ref()
+                .clone();
 
             // Synchronize messages
             let closed_previous = self.state.close_mailbox();
@@ -127,6 +98,7 @@ impl<T: SessionStream> Session<T> {
                             attributes: vec![fetch::Attribute::Flags],
                             changed_since: qresync.modseq.into(),
                             include_vanished: true,
+                            partial: None,
                         },
                         mailbox.clone(),
                         true,
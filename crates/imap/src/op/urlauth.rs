@@ -0,0 +1,277 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use crate::{
+    core::{Session, SessionData},
+    op::ImapContext,
+    spawn_op,
+};
+use common::listener::SessionStream;
+use directory::Permission;
+use email::{
+    mailbox::MailboxFnc,
+    urlauth::{ImapUrl, URLAUTH_INTERNAL, UrlAuth, UrlRequester},
+};
+use imap_proto::{
+    Command, ResponseCode, StatusResponse,
+    protocol::{
+        ImapResponse,
+        append::CatenatePart,
+        urlauth::{
+            GenUrlAuthArguments, GenUrlAuthResponse, ResetKeyArguments, UrlFetchArguments,
+            UrlFetchResponse,
+        },
+    },
+    receiver::Request,
+};
+use jmap_proto::types::acl::Acl;
+
+impl<T: SessionStream> Session<T> {
+    pub async fn handle_reset_key(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapResetKey)?;
+
+        let data = self.state.session_data();
+        let version = self.version;
+
+        spawn_op!(data, {
+            let response = data.reset_key(request.parse_reset_key(version)?).await?;
+            data.write_bytes(response.into_bytes()).await
+        })
+    }
+
+    pub async fn handle_genurlauth(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapGenUrlAuth)?;
+
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            let response = data.genurlauth(request.parse_genurlauth()?).await?;
+            data.write_bytes(response).await
+        })
+    }
+
+    pub async fn handle_urlfetch(&mut self, request: Request<Command>) -> trc::Result<()> {
+        // Validate access
+        self.assert_has_permission(Permission::ImapUrlFetch)?;
+
+        let data = self.state.session_data();
+
+        spawn_op!(data, {
+            let response = data.urlfetch(request.parse_urlfetch()?).await?;
+            data.write_bytes(response).await
+        })
+    }
+}
+
+impl<T: SessionStream> SessionData<T> {
+    async fn reset_key(&self, arguments: ResetKeyArguments) -> trc::Result<StatusResponse> {
+        let op_start = Instant::now();
+
+        if arguments
+            .mechanisms
+            .iter()
+            .any(|mechanism| mechanism != URLAUTH_INTERNAL)
+        {
+            return Ok(StatusResponse::no("Unsupported URLAUTH mechanism.")
+                .with_tag(arguments.tag)
+                .with_code(ResponseCode::Cannot));
+        }
+
+        // Only personal mailboxes can be referenced by authorized URLs
+        let mailbox_id = if let Some(mailbox_name) = &arguments.mailbox_name {
+            match self
+                .server
+                .mailbox_get_by_name(self.account_id, mailbox_name)
+                .await
+                .imap_ctx(&arguments.tag, trc::location!())?
+            {
+                Some(mailbox_id) => Some(mailbox_id),
+                None => {
+                    return Ok(StatusResponse::no("Mailbox does not exist.")
+                        .with_tag(arguments.tag)
+                        .with_code(ResponseCode::NonExistent));
+                }
+            }
+        } else {
+            None
+        };
+
+        self.server
+            .urlauth_reset(self.account_id, mailbox_id)
+            .await
+            .imap_ctx(&arguments.tag, trc::location!())?;
+
+        trc::event!(
+            Imap(trc::ImapEvent::ResetKey),
+            SpanId = self.session_id,
+            AccountId = self.account_id,
+            MailboxName = arguments.mailbox_name,
+            MailboxId = mailbox_id,
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::completed(Command::ResetKey).with_tag(arguments.tag))
+    }
+
+    async fn genurlauth(&self, arguments: GenUrlAuthArguments) -> trc::Result<Vec<u8>> {
+        let op_start = Instant::now();
+        let mut response = GenUrlAuthResponse {
+            urls: Vec::with_capacity(arguments.urls.len()),
+        };
+
+        for (url_str, mechanism) in arguments.urls {
+            if mechanism != URLAUTH_INTERNAL {
+                return Ok(StatusResponse::no("Unsupported URLAUTH mechanism.")
+                    .with_tag(arguments.tag)
+                    .with_code(ResponseCode::Cannot)
+                    .into_bytes());
+            }
+
+            // URLs can only be authorized by the owner of the mailbox
+            let mailbox_id = if let Some(url) = ImapUrl::parse(&url_str).filter(|url| {
+                url.access.is_some()
+                    && url.token.is_none()
+                    && url
+                        .user
+                        .as_ref()
+                        .is_some_and(|user| user.eq_ignore_ascii_case(&self.access_token.name))
+            }) {
+                self.server
+                    .mailbox_get_by_name(self.account_id, &url.mailbox)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?
+                    .map(|mailbox_id| (url, mailbox_id))
+            } else {
+                None
+            };
+            let Some((url, mailbox_id)) = mailbox_id else {
+                return Ok(StatusResponse::no("Invalid URL.")
+                    .with_tag(arguments.tag)
+                    .with_code(ResponseCode::BadUrl { url: url_str })
+                    .into_bytes());
+            };
+
+            response.urls.push(
+                self.server
+                    .urlauth_generate(self.account_id, mailbox_id, &url)
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?,
+            );
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::GenUrlAuth),
+            SpanId = self.session_id,
+            AccountId = self.account_id,
+            Total = response.urls.len(),
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::completed(Command::GenUrlAuth)
+            .with_tag(arguments.tag)
+            .serialize(response.serialize()))
+    }
+
+    async fn urlfetch(&self, arguments: UrlFetchArguments) -> trc::Result<Vec<u8>> {
+        let op_start = Instant::now();
+        let mut response = UrlFetchResponse {
+            items: Vec::with_capacity(arguments.urls.len()),
+        };
+
+        for url_str in arguments.urls {
+            let data = match ImapUrl::parse(&url_str) {
+                Some(url) => self
+                    .server
+                    .urlauth_fetch(&url, UrlRequester::User(&self.access_token.name))
+                    .await
+                    .imap_ctx(&arguments.tag, trc::location!())?,
+                None => None,
+            };
+            response.items.push((url_str, data));
+        }
+
+        trc::event!(
+            Imap(trc::ImapEvent::UrlFetch),
+            SpanId = self.session_id,
+            AccountId = self.account_id,
+            Total = response.items.len(),
+            Elapsed = op_start.elapsed()
+        );
+
+        Ok(StatusResponse::completed(Command::UrlFetch)
+            .with_tag(arguments.tag)
+            .serialize(response.serialize()))
+    }
+
+    pub(crate) async fn catenate_message(
+        &self,
+        parts: Vec<CatenatePart>,
+    ) -> trc::Result<Result<Vec<u8>, StatusResponse>> {
+        let max_size = self.server.core.imap.max_request_size;
+        let mut message = Vec::new();
+
+        for part in parts {
+            match part {
+                CatenatePart::Text(text) => {
+                    message.extend_from_slice(&text);
+                }
+                CatenatePart::Url(url_str) => {
+                    let data = match ImapUrl::parse(&url_str) {
+                        Some(url) if url.access.is_some() => {
+                            self.server
+                                .urlauth_fetch(&url, UrlRequester::User(&self.access_token.name))
+                                .await?
+                        }
+                        Some(url)
+                            if url.user.as_ref().is_none_or(|user| {
+                                user.eq_ignore_ascii_case(&self.access_token.name)
+                            }) =>
+                        {
+                            match self.get_mailbox_by_name(&url.mailbox) {
+                                Some(mailbox)
+                                    if self
+                                        .check_mailbox_acl(
+                                            mailbox.account_id,
+                                            mailbox.mailbox_id,
+                                            Acl::ReadItems,
+                                        )
+                                        .await? =>
+                                {
+                                    self.server
+                                        .url_fetch(mailbox.account_id, mailbox.mailbox_id, &url)
+                                        .await?
+                                }
+                                _ => None,
+                            }
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(data) = data {
+                        message.extend_from_slice(&data);
+                    } else {
+                        return Ok(Err(StatusResponse::no(
+                            "Invalid URL or message part not found.",
+                        )
+                        .with_code(ResponseCode::BadUrl { url: url_str })));
+                    }
+                }
+            }
+
+            if message.len() > max_size {
+                return Ok(Err(
+                    StatusResponse::no("Message is too large.").with_code(ResponseCode::TooBig)
+                ));
+            }
+        }
+
+        Ok(Ok(message))
+    }
+}
@@ -36,6 +36,10 @@ pub enum RequestArguments {
     Principal,
     Quota,
     Blob(blob::GetArguments),
+    AddressBook,
+    ContactCard,
+    Calendar,
+    CalendarEvent,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
@@ -71,6 +75,10 @@ impl JsonObjectParser for GetRequest<RequestArguments> {
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Blob => RequestArguments::Blob(Default::default()),
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::AddressBook => RequestArguments::AddressBook,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
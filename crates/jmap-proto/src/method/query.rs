@@ -99,6 +99,11 @@ pub enum Filter {
     IsActive(bool),
     Scope(String),
     ResourceType(String),
+    InAddressBook(Id),
+    InCalendar(Id),
+    Uid(String),
+    Kind(String),
+    Title(String),
     _T(String),
 
     And,
@@ -136,6 +141,9 @@ pub enum SortProperty {
     AllInThreadHaveKeyword,
     SomeInThreadHaveKeyword,
     Used,
+    Start,
+    Title,
+    Uid,
     _T(String),
 }
 
@@ -147,6 +155,10 @@ pub enum RequestArguments {
     SieveScript,
     Principal,
     Quota,
+    AddressBook,
+    ContactCard,
+    Calendar,
+    CalendarEvent,
 }
 
 impl JsonObjectParser for QueryRequest<RequestArguments> {
@@ -162,6 +174,10 @@ impl JsonObjectParser for QueryRequest<RequestArguments> {
                 MethodObject::SieveScript => RequestArguments::SieveScript,
                 MethodObject::Principal => RequestArguments::Principal,
                 MethodObject::Quota => RequestArguments::Quota,
+                MethodObject::AddressBook => RequestArguments::AddressBook,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
@@ -424,6 +440,21 @@ pub fn parse_filter(parser: &mut Parser) -> trc::Result<Vec<Filter>> {
                                 .next_token::<String>()?
                                 .unwrap_string("resourceType")?,
                         ),
+                        (0x006b_6f6f_4273_7365_7264_6441_6e69, _) => Filter::InAddressBook(
+                            parser.next_token::<Id>()?.unwrap_string("inAddressBook")?,
+                        ),
+                        (0x7261_646e_656c_6143_6e69, _) => Filter::InCalendar(
+                            parser.next_token::<Id>()?.unwrap_string("inCalendar")?,
+                        ),
+                        (0x0064_6975, _) => {
+                            Filter::Uid(parser.next_token::<String>()?.unwrap_string("uid")?)
+                        }
+                        (0x646e_696b, _) => {
+                            Filter::Kind(parser.next_token::<String>()?.unwrap_string("kind")?)
+                        }
+                        (0x0065_6c74_6974, _) => {
+                            Filter::Title(parser.next_token::<String>()?.unwrap_string("title")?)
+                        }
                         _ => {
                             if parser.is_eof || parser.skip_string() {
                                 let filter = Filter::_T(
@@ -566,6 +597,9 @@ impl JsonObjectParser for SortProperty {
             0x4b65_7661_4864_6165_7268_546e_496c_6c61 => Ok(SortProperty::AllInThreadHaveKeyword),
             0x6576_6148_6461_6572_6854_6e49_656d_6f73 => Ok(SortProperty::SomeInThreadHaveKeyword),
             0x6465_7375 => Ok(SortProperty::Used),
+            0x0074_7261_7473 => Ok(SortProperty::Start),
+            0x0065_6c74_6974 => Ok(SortProperty::Title),
+            0x0064_6975 => Ok(SortProperty::Uid),
             _ => {
                 if parser.is_eof || parser.skip_string() {
                     Ok(SortProperty::_T(
@@ -628,6 +662,11 @@ impl Display for Filter {
             Filter::IsActive(_) => "isActive",
             Filter::ResourceType(_) => "resourceType",
             Filter::Scope(_) => "scope",
+            Filter::InAddressBook(_) => "inAddressBook",
+            Filter::InCalendar(_) => "inCalendar",
+            Filter::Uid(_) => "uid",
+            Filter::Kind(_) => "kind",
+            Filter::Title(_) => "title",
             Filter::_T(v) => v.as_str(),
             Filter::And => "and",
             Filter::Or => "or",
@@ -659,6 +698,9 @@ impl Display for SortProperty {
             SortProperty::AllInThreadHaveKeyword => "allInThreadHaveKeyword",
             SortProperty::SomeInThreadHaveKeyword => "someInThreadHaveKeyword",
             SortProperty::Used => "used",
+            SortProperty::Start => "start",
+            SortProperty::Title => "title",
+            SortProperty::Uid => "uid",
             SortProperty::_T(s) => s,
         })
     }
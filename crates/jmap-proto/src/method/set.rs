@@ -51,6 +51,10 @@ pub enum RequestArguments {
     PushSubscription,
     SieveScript(sieve::SetArguments),
     VacationResponse,
+    AddressBook,
+    ContactCard,
+    Calendar,
+    CalendarEvent,
 }
 
 #[derive(Debug, Clone, Default, serde::Serialize)]
@@ -111,6 +115,10 @@ impl JsonObjectParser for SetRequest<RequestArguments> {
                 MethodObject::PushSubscription => RequestArguments::PushSubscription,
                 MethodObject::VacationResponse => RequestArguments::VacationResponse,
                 MethodObject::SieveScript => RequestArguments::SieveScript(Default::default()),
+                MethodObject::AddressBook => RequestArguments::AddressBook,
+                MethodObject::ContactCard => RequestArguments::ContactCard,
+                MethodObject::Calendar => RequestArguments::Calendar,
+                MethodObject::CalendarEvent => RequestArguments::CalendarEvent,
                 _ => {
                     return Err(trc::JmapEvent::UnknownMethod
                         .into_err()
@@ -179,6 +187,21 @@ impl JsonObjectParser for Object<SetValue> {
         while let Some(mut key) = parser.next_dict_key::<SetProperty>()? {
             let value = if !key.is_ref {
                 match &key.property {
+                    // JSContact and JSCalendar objects are stored as free-form JSON
+                    property
+                        if matches!(
+                            parser.ctx,
+                            MethodObject::ContactCard | MethodObject::CalendarEvent
+                        ) && !matches!(
+                            property,
+                            Property::Id | Property::AddressBookIds | Property::CalendarIds
+                        ) =>
+                    {
+                        SetValue::Value(Value::parse::<ObjectProperty, String>(
+                            parser.next_token()?,
+                            parser,
+                        )?)
+                    }
                     Property::Id | Property::ThreadId => parser
                         .next_token::<Id>()?
                         .unwrap_string_or_null("")?
@@ -217,6 +240,7 @@ impl JsonObjectParser for Object<SetValue> {
                     | Property::Location
                     | Property::Cid
                     | Property::Role
+                    | Property::Color
                     | Property::PartId => parser
                         .next_token::<String>()?
                         .unwrap_string_or_null("")?
@@ -239,7 +263,9 @@ impl JsonObjectParser for Object<SetValue> {
                     Property::HasAttachment
                     | Property::IsSubscribed
                     | Property::IsEnabled
-                    | Property::IsActive => parser
+                    | Property::IsActive
+                    | Property::IsDefault
+                    | Property::IsVisible => parser
                         .next_token::<String>()?
                         .unwrap_bool_or_null("")?
                         .map(|bool| SetValue::Value(Value::Bool(bool)))
@@ -254,7 +280,7 @@ impl JsonObjectParser for Object<SetValue> {
                         .unwrap_string_or_null("")?
                         .map(SetValue::from)
                         .unwrap_or(SetValue::Value(Value::Null)),
-                    Property::MailboxIds => {
+                    Property::MailboxIds | Property::AddressBookIds | Property::CalendarIds => {
                         if key.patch.is_empty() {
                             SetValue::from(
                                 <SetValueMap<MaybeReference<Id, String>>>::parse(parser)?.values,
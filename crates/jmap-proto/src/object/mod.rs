@@ -64,6 +64,53 @@ impl Object<Value> {
     pub fn get(&self, property: &Property) -> &Value {
         self.properties.get(property).unwrap_or(&Value::Null)
     }
+
+    pub fn patch(&mut self, path: &str, value: Value) -> bool {
+        let mut object = self;
+        let mut path = path
+            .split('/')
+            .map(|key| key.replace("~1", "/").replace("~0", "~"));
+        let mut key = path.next().unwrap_or_default();
+
+        for next_key in path {
+            // Properties are matched by name as free-form objects use untyped keys
+            match object
+                .properties
+                .iter_mut()
+                .find(|(property, _)| property.to_string() == key)
+            {
+                Some((_, Value::Object(child))) => {
+                    object = child;
+                    key = next_key;
+                }
+                _ => return false,
+            }
+        }
+
+        if key.is_empty() {
+            return false;
+        }
+
+        let property = object
+            .properties
+            .iter()
+            .find(|(property, _)| property.to_string() == key)
+            .map(|(property, _)| property.clone());
+        match (property, value) {
+            (Some(property), Value::Null) => {
+                object.properties.remove(&property);
+            }
+            (Some(property), value) => {
+                object.properties.set(property, value);
+            }
+            (None, Value::Null) => (),
+            (None, value) => {
+                object.append(Property::_T(key), value);
+            }
+        }
+
+        true
+    }
 }
 
 impl ToBitmaps for Value {
@@ -132,7 +132,11 @@ impl Request {
                                 | MethodObject::SieveScript
                                 | MethodObject::Principal
                                 | MethodObject::Quota
-                                | MethodObject::Blob,
+                                | MethodObject::Blob
+                                | MethodObject::AddressBook
+                                | MethodObject::ContactCard
+                                | MethodObject::Calendar
+                                | MethodObject::CalendarEvent,
                             ) => GetRequest::parse(parser).map(RequestMethod::Get),
                             (MethodFunction::Get, MethodObject::SearchSnippet) => {
                                 GetSearchSnippetRequest::parse(parser)
@@ -24,7 +24,11 @@ pub enum Collection {
     SieveScript = 5,
     PushSubscription = 6,
     Principal = 7,
-    None = 8,
+    AddressBook = 8,
+    ContactCard = 9,
+    Calendar = 10,
+    CalendarEvent = 11,
+    None = 12,
 }
 
 impl From<u8> for Collection {
@@ -38,6 +42,10 @@ impl From<u8> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::AddressBook,
+            9 => Collection::ContactCard,
+            10 => Collection::Calendar,
+            11 => Collection::CalendarEvent,
             _ => Collection::None,
         }
     }
@@ -54,6 +62,10 @@ impl From<u64> for Collection {
             5 => Collection::SieveScript,
             6 => Collection::PushSubscription,
             7 => Collection::Principal,
+            8 => Collection::AddressBook,
+            9 => Collection::ContactCard,
+            10 => Collection::Calendar,
+            11 => Collection::CalendarEvent,
             _ => Collection::None,
         }
     }
@@ -83,6 +95,10 @@ impl TryFrom<Collection> for DataType {
             Collection::EmailSubmission => Ok(DataType::EmailSubmission),
             Collection::SieveScript => Ok(DataType::SieveScript),
             Collection::PushSubscription => Ok(DataType::PushSubscription),
+            Collection::AddressBook => Ok(DataType::AddressBook),
+            Collection::ContactCard => Ok(DataType::ContactCard),
+            Collection::Calendar => Ok(DataType::Calendar),
+            Collection::CalendarEvent => Ok(DataType::CalendarEvent),
             _ => Err(()),
         }
     }
@@ -105,6 +121,10 @@ impl Collection {
             Collection::EmailSubmission => "emailSubmission",
             Collection::SieveScript => "sieveScript",
             Collection::Principal => "principal",
+            Collection::AddressBook => "addressBook",
+            Collection::ContactCard => "contactCard",
+            Collection::Calendar => "calendar",
+            Collection::CalendarEvent => "calendarEvent",
             Collection::None => "",
         }
     }
@@ -123,6 +143,10 @@ impl FromStr for Collection {
             "emailSubmission" => Ok(Collection::EmailSubmission),
             "sieveScript" => Ok(Collection::SieveScript),
             "principal" => Ok(Collection::Principal),
+            "addressBook" => Ok(Collection::AddressBook),
+            "contactCard" => Ok(Collection::ContactCard),
+            "calendar" => Ok(Collection::Calendar),
+            "calendarEvent" => Ok(Collection::CalendarEvent),
             _ => Err(()),
         }
     }
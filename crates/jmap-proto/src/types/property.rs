@@ -10,7 +10,10 @@ use mail_parser::HeaderName;
 use serde::Serialize;
 use store::write::{DeserializeFrom, SerializeInto};
 
-use crate::parser::{json::Parser, JsonObjectParser};
+use crate::{
+    parser::{json::Parser, JsonObjectParser},
+    request::method::MethodObject,
+};
 
 use super::{acl::Acl, id::Id, keyword::Keyword, value::Value};
 
@@ -119,6 +122,31 @@ pub enum Property {
     WarnLimit,
     SoftLimit,
     Scope,
+    Annotations,
+    AnnotationsSize,
+    AccessKey,
+    AddressBookIds,
+    CalendarIds,
+    IsDefault,
+    Uid,
+    Color,
+    IsVisible,
+    MayRead,
+    MayWrite,
+    MayAdmin,
+    MayReadFreeBusy,
+    MayWriteAll,
+    MayWriteOwn,
+    MayUpdatePrivate,
+    MayRsvp,
+    Kind,
+    Title,
+    UtcStart,
+    UtcEnd,
+    Text,
+    DavName,
+    DavData,
+    DavTombstones,
     Digest(DigestProperty),
     Data(DataProperty),
     _T(String),
@@ -247,7 +275,10 @@ impl JsonObjectParser for SetProperty {
 
         if is_patch {
             match &property {
-                Property::MailboxIds | Property::Members => match Id::parse(parser) {
+                Property::MailboxIds
+                | Property::Members
+                | Property::AddressBookIds
+                | Property::CalendarIds => match Id::parse(parser) {
                     Ok(id) => {
                         patch.push(Value::Id(id));
                     }
@@ -258,6 +289,14 @@ impl JsonObjectParser for SetProperty {
                         return Err(err);
                     }
                 },
+                _ if matches!(
+                    parser.ctx,
+                    MethodObject::ContactCard | MethodObject::CalendarEvent
+                ) =>
+                {
+                    // Patches to JSContact and JSCalendar objects are resolved later
+                    property = parser.invalid_property()?;
+                }
                 Property::Keywords => match Keyword::parse(parser) {
                     Ok(keyword) => {
                         patch.push(Value::Keyword(keyword));
@@ -335,6 +374,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x6c63 => Property::Acl,
             0x7365_7361_696c => Property::Aliases,
             0x7374_6e65_6d68_6361_7474 => Property::Attachments,
+            0x0073_6449_6b6f_6f42_7373_6572_6464 => Property::AddressBookIds,
             _ => return None,
         },
         b'b' => match hash {
@@ -349,6 +389,8 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x63 => Property::Cc,
             0x7465_7372_6168 => Property::Charset,
             0x6469 => Property::Cid,
+            0x7364_4972_6164_6e65_6c61 => Property::CalendarIds,
+            0x726f_6c6f => Property::Color,
             _ => return None,
         },
         b'd' => match hash {
@@ -387,11 +429,14 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0065_7669_7463_4173 => Property::IsActive,
             0x6465_6c62_616e_4573 => Property::IsEnabled,
             0x0064_6562_6972_6373_6275_5373 => Property::IsSubscribed,
+            0x746c_7561_6665_4473 => Property::IsDefault,
+            0x656c_6269_7369_5673 => Property::IsVisible,
             _ => return None,
         },
         b'k' => match hash {
             0x0073_7965 => Property::Keys,
             0x0073_6472_6f77_7965 => Property::Keywords,
+            0x0064_6e69 => Property::Kind,
             _ => return None,
         },
         b'l' => match hash {
@@ -452,6 +497,7 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6461_6572_6854_6c61_746f => Property::TotalThreads,
             0x0065_7079 => Property::Type,
             0x7365_7079 => Property::Types,
+            0x656c_7469 => Property::Title,
             _ => return None,
         },
         b'u' => match hash {
@@ -459,6 +505,9 @@ fn parse_property(first_char: u8, hash: u128) -> Option<Property> {
             0x0073_6c69_616d_4564_6165_726e => Property::UnreadEmails,
             0x7364_6165_7268_5464_6165_726e => Property::UnreadThreads,
             0x6c72 => Property::Url,
+            0x6469 => Property::Uid,
+            0x0074_7261_7453_6374 => Property::UtcStart,
+            0x0064_6e45_6374 => Property::UtcEnd,
             _ => return None,
         },
         b'v' => match hash {
@@ -883,6 +932,31 @@ impl Display for Property {
             Property::Scope => write!(f, "scope"),
             Property::WarnLimit => write!(f, "warnLimit"),
             Property::SoftLimit => write!(f, "softLimit"),
+            Property::Annotations => write!(f, "annotations"),
+            Property::AnnotationsSize => write!(f, "annotationsSize"),
+            Property::AccessKey => write!(f, "accessKey"),
+            Property::AddressBookIds => write!(f, "addressBookIds"),
+            Property::CalendarIds => write!(f, "calendarIds"),
+            Property::IsDefault => write!(f, "isDefault"),
+            Property::Uid => write!(f, "uid"),
+            Property::Color => write!(f, "color"),
+            Property::IsVisible => write!(f, "isVisible"),
+            Property::MayRead => write!(f, "mayRead"),
+            Property::MayWrite => write!(f, "mayWrite"),
+            Property::MayAdmin => write!(f, "mayAdmin"),
+            Property::MayReadFreeBusy => write!(f, "mayReadFreeBusy"),
+            Property::MayWriteAll => write!(f, "mayWriteAll"),
+            Property::MayWriteOwn => write!(f, "mayWriteOwn"),
+            Property::MayUpdatePrivate => write!(f, "mayUpdatePrivate"),
+            Property::MayRsvp => write!(f, "mayRSVP"),
+            Property::Kind => write!(f, "kind"),
+            Property::Title => write!(f, "title"),
+            Property::UtcStart => write!(f, "utcStart"),
+            Property::UtcEnd => write!(f, "utcEnd"),
+            Property::Text => write!(f, "text"),
+            Property::DavName => write!(f, "davName"),
+            Property::DavData => write!(f, "davData"),
+            Property::DavTombstones => write!(f, "davTombstones"),
             Property::_T(s) => write!(f, "{s}"),
         }
     }
@@ -1063,6 +1137,31 @@ impl From<&Property> for u8 {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Annotations => 104,
+            Property::AnnotationsSize => 105,
+            Property::AccessKey => 106,
+            Property::AddressBookIds => 107,
+            Property::CalendarIds => 108,
+            Property::IsDefault => 109,
+            Property::Uid => 110,
+            Property::Color => 111,
+            Property::IsVisible => 112,
+            Property::MayRead => 113,
+            Property::MayWrite => 114,
+            Property::MayAdmin => 115,
+            Property::MayReadFreeBusy => 116,
+            Property::MayWriteAll => 117,
+            Property::MayWriteOwn => 118,
+            Property::MayUpdatePrivate => 119,
+            Property::MayRsvp => 120,
+            Property::Kind => 121,
+            Property::Title => 122,
+            Property::UtcStart => 123,
+            Property::UtcEnd => 124,
+            Property::Text => 125,
+            Property::DavName => 126,
+            Property::DavData => 127,
+            Property::DavTombstones => 128,
             Property::Digest(_) | Property::Data(_) => unreachable!("invalid property"),
         }
     }
@@ -1205,6 +1304,31 @@ impl SerializeInto for Property {
             Property::WarnLimit => 101,
             Property::SoftLimit => 102,
             Property::Scope => 103,
+            Property::Annotations => 104,
+            Property::AnnotationsSize => 105,
+            Property::AccessKey => 106,
+            Property::AddressBookIds => 107,
+            Property::CalendarIds => 108,
+            Property::IsDefault => 109,
+            Property::Uid => 110,
+            Property::Color => 111,
+            Property::IsVisible => 112,
+            Property::MayRead => 113,
+            Property::MayWrite => 114,
+            Property::MayAdmin => 115,
+            Property::MayReadFreeBusy => 116,
+            Property::MayWriteAll => 117,
+            Property::MayWriteOwn => 118,
+            Property::MayUpdatePrivate => 119,
+            Property::MayRsvp => 120,
+            Property::Kind => 121,
+            Property::Title => 122,
+            Property::UtcStart => 123,
+            Property::UtcEnd => 124,
+            Property::Text => 125,
+            Property::DavName => 126,
+            Property::DavData => 127,
+            Property::DavTombstones => 128,
             Property::Digest(_) | Property::Data(_) => {
                 unreachable!("Property::Digest and Property::Data are not serializable")
             }
@@ -1323,6 +1447,31 @@ impl DeserializeFrom for Property {
             101 => Some(Property::WarnLimit),
             102 => Some(Property::SoftLimit),
             103 => Some(Property::Scope),
+            104 => Some(Property::Annotations),
+            105 => Some(Property::AnnotationsSize),
+            106 => Some(Property::AccessKey),
+            107 => Some(Property::AddressBookIds),
+            108 => Some(Property::CalendarIds),
+            109 => Some(Property::IsDefault),
+            110 => Some(Property::Uid),
+            111 => Some(Property::Color),
+            112 => Some(Property::IsVisible),
+            113 => Some(Property::MayRead),
+            114 => Some(Property::MayWrite),
+            115 => Some(Property::MayAdmin),
+            116 => Some(Property::MayReadFreeBusy),
+            117 => Some(Property::MayWriteAll),
+            118 => Some(Property::MayWriteOwn),
+            119 => Some(Property::MayUpdatePrivate),
+            120 => Some(Property::MayRsvp),
+            121 => Some(Property::Kind),
+            122 => Some(Property::Title),
+            123 => Some(Property::UtcStart),
+            124 => Some(Property::UtcEnd),
+            125 => Some(Property::Text),
+            126 => Some(Property::DavName),
+            127 => Some(Property::DavData),
+            128 => Some(Property::DavTombstones),
             _ => None,
         }
     }
@@ -203,6 +203,9 @@ impl FormHandler for Server {
             for result in self
                 .deliver_message(IngestMessage {
                     sender_address: from_email,
+                    sender_authenticated: false,
+                    spf_pass: false,
+                    dmarc_pass: false,
                     recipients: form.rcpt_to.clone(),
                     message_blob,
                     message_size,
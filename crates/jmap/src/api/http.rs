@@ -16,6 +16,7 @@ use common::{
     Inner, Server, KV_ACME,
 };
 use directory::Permission;
+use email::list::MailingListManager;
 use http_body_util::{BodyExt, Full};
 use hyper::{
     body::{self, Bytes},
@@ -49,6 +50,7 @@ use crate::{
         rate_limit::RateLimiter,
     },
     blob::{download::BlobDownload, upload::BlobUpload, DownloadResponse, UploadResponse},
+    dav::{dav_options_response, DavRequestHandler},
     websocket::upgrade::WebSocketUpgrade,
 };
 
@@ -219,7 +221,43 @@ impl ParseHttp for Server {
                     _ => (),
                 }
             }
+            "dav" => {
+                // Allow clients to discover the supported features
+                if req.method() == Method::OPTIONS {
+                    return Ok(dav_options_response());
+                }
+
+                // Authenticate user
+                return match self.authenticate_headers(&req, &session, false).await {
+                    Ok((_in_flight, access_token)) => {
+                        self.handle_dav_request(&mut req, access_token, &session)
+                            .await
+                    }
+                    Err(err) if err.matches(trc::EventType::Auth(trc::AuthEvent::Failed)) => {
+                        // WebDAV clients only send credentials when challenged
+                        let response = err.into_http_response().with_header(
+                            header::WWW_AUTHENTICATE,
+                            "Basic realm=\"Stalwart Server\"",
+                        );
+                        trc::error!(err.span_id(session.session_id));
+                        Ok(response)
+                    }
+                    Err(err) => Err(err),
+                };
+            }
             ".well-known" => match (path.next().unwrap_or_default(), req.method()) {
+                (name @ ("carddav" | "caldav"), _) => {
+                    return Ok(
+                        HttpResponse::new_empty(StatusCode::MOVED_PERMANENTLY).with_header(
+                            header::LOCATION,
+                            if name == "carddav" {
+                                "/dav/card/"
+                            } else {
+                                "/dav/cal/"
+                            },
+                        ),
+                    );
+                }
                 ("jmap", &Method::GET) => {
                     // Authenticate request
                     let (_in_flight, access_token) =
@@ -539,6 +577,48 @@ impl ParseHttp for Server {
 
                 // SPDX-SnippetEnd
             }
+            "list" => {
+                if let ("unsubscribe", Some(token)) = (path.next().unwrap_or_default(), path.next())
+                {
+                    // Limit anonymous requests
+                    self.is_http_anonymous_request_allowed(&session.remote_ip)
+                        .await?;
+
+                    match *req.method() {
+                        // RFC 8058 one-click unsubscribe
+                        Method::POST => {
+                            return if self
+                                .mailing_list_unsubscribe(token, session.session_id)
+                                .await?
+                            {
+                                Ok(HtmlResponse::new(
+                                    "<html><body><p>You have been unsubscribed.</p></body></html>"
+                                        .to_string(),
+                                )
+                                .into_http_response())
+                            } else {
+                                Err(trc::ResourceEvent::NotFound.into_err())
+                            };
+                        }
+                        // Unsubscribing requires a POST, so mail scanners following
+                        // links do not unsubscribe recipients
+                        Method::GET => {
+                            return Ok(HtmlResponse::new(format!(
+                                concat!(
+                                    "<html><body><form method=\"post\" action=\"{}\">",
+                                    "<input type=\"hidden\" name=\"List-Unsubscribe\" ",
+                                    "value=\"One-Click\"/>",
+                                    "<button type=\"submit\">Unsubscribe</button>",
+                                    "</form></body></html>"
+                                ),
+                                token
+                            ))
+                            .into_http_response());
+                        }
+                        _ => {}
+                    }
+                }
+            }
             "form" => {
                 if let Some(form) = &self.core.network.contact_form {
                     match *req.method() {
@@ -900,6 +980,7 @@ impl HttpResponse {
             content_type: "".into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            headers: vec![],
             body: HttpResponseBody::Empty,
         }
     }
@@ -914,6 +995,7 @@ impl HttpResponse {
             content_type: content_type.into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            headers: vec![],
             body: HttpResponseBody::Text(body.into()),
         }
     }
@@ -928,10 +1010,16 @@ impl HttpResponse {
             content_type: content_type.into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            headers: vec![],
             body: HttpResponseBody::Binary(body.into()),
         }
     }
 
+    pub fn with_header(mut self, name: header::HeaderName, value: impl Into<String>) -> Self {
+        self.headers.push((name, value.into()));
+        self
+    }
+
     pub fn size(&self) -> usize {
         match &self.body {
             HttpResponseBody::Text(value) => value.len(),
@@ -944,7 +1032,10 @@ impl HttpResponse {
         self,
     ) -> hyper::Response<http_body_util::combinators::BoxBody<hyper::body::Bytes, hyper::Error>>
     {
-        let builder = hyper::Response::builder().status(self.status);
+        let mut builder = hyper::Response::builder().status(self.status);
+        for (name, value) in self.headers {
+            builder = builder.header(name, value);
+        }
 
         match self.body {
             HttpResponseBody::Text(body) => builder
@@ -1010,6 +1101,7 @@ impl<T: serde::Serialize> ToHttpResponse for JsonResponse<T> {
                 "no-store, no-cache, must-revalidate"
             }
             .into(),
+            headers: vec![],
             body: HttpResponseBody::Text(serde_json::to_string(&self.inner).unwrap_or_default()),
         }
     }
@@ -1189,6 +1281,7 @@ impl ToHttpResponse for DownloadResponse {
             )
             .into(),
             cache_control: "private, immutable, max-age=31536000".into(),
+            headers: vec![],
             body: HttpResponseBody::Binary(self.blob),
         }
     }
@@ -149,11 +149,7 @@ impl DkimManagement for Server {
                 "{:04}{:02}{}",
                 dt.year,
                 dt.month,
-                if Algorithm::Rsa == request.algorithm {
-                    "r"
-                } else {
-                    "e"
-                }
+                request.algorithm.selector_suffix()
             )
         });
 
@@ -189,66 +185,85 @@ impl DkimManagement for Server {
         selector: impl Into<String>,
     ) -> trc::Result<()> {
         let id = id.as_ref();
-        let (algorithm, pk_type) = match algo {
-            Algorithm::Rsa => ("rsa-sha256", "RSA PRIVATE KEY"),
-            Algorithm::Ed25519 => ("ed25519-sha256", "PRIVATE KEY"),
+        let algorithm = match algo {
+            Algorithm::Rsa => "rsa-sha256",
+            Algorithm::Ed25519 => "ed25519-sha256",
         };
-        let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
-        let mut lf_count = 65;
-        for ch in base64_encode(
-            match algo {
-                Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
-                Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
-            }
-            .map_err(|err| {
-                manage::error("Failed to generate key", err.to_string().into())
-                    .caused_by(trc::location!())
-            })?
-            .private_key(),
-        )
-        .unwrap_or_default()
-        {
-            pk.push(ch);
-            lf_count -= 1;
-            if lf_count == 0 {
-                pk.push(b'\n');
-                lf_count = 65;
-            }
-        }
-        if lf_count != 65 {
-            pk.push(b'\n');
-        }
-        pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
+        let pk = generate_dkim_private_key(algo)?;
 
         self.core
             .storage
             .config
-            .set([
-                (
-                    format!("signature.{id}.private-key"),
-                    String::from_utf8(pk).unwrap(),
-                ),
-                (format!("signature.{id}.domain"), domain.into()),
-                (format!("signature.{id}.selector"), selector.into()),
-                (format!("signature.{id}.algorithm"), algorithm.to_string()),
-                (
-                    format!("signature.{id}.canonicalization"),
-                    "relaxed/relaxed".to_string(),
-                ),
-                (format!("signature.{id}.headers.0"), "From".to_string()),
-                (format!("signature.{id}.headers.1"), "To".to_string()),
-                (format!("signature.{id}.headers.2"), "Date".to_string()),
-                (format!("signature.{id}.headers.3"), "Subject".to_string()),
-                (
-                    format!("signature.{id}.headers.4"),
-                    "Message-ID".to_string(),
-                ),
-                (format!("signature.{id}.report"), "false".to_string()),
-            ], true)
+            .set(
+                [
+                    (format!("signature.{id}.private-key"), pk),
+                    (format!("signature.{id}.domain"), domain.into()),
+                    (format!("signature.{id}.selector"), selector.into()),
+                    (format!("signature.{id}.algorithm"), algorithm.to_string()),
+                    (
+                        format!("signature.{id}.canonicalization"),
+                        "relaxed/relaxed".to_string(),
+                    ),
+                    (format!("signature.{id}.headers.0"), "From".to_string()),
+                    (format!("signature.{id}.headers.1"), "To".to_string()),
+                    (format!("signature.{id}.headers.2"), "Date".to_string()),
+                    (format!("signature.{id}.headers.3"), "Subject".to_string()),
+                    (
+                        format!("signature.{id}.headers.4"),
+                        "Message-ID".to_string(),
+                    ),
+                    (format!("signature.{id}.report"), "false".to_string()),
+                ],
+                true,
+            )
             .await
     }
 }
 
+pub fn generate_dkim_private_key(algo: Algorithm) -> trc::Result<String> {
+    let pk_type = match algo {
+        Algorithm::Rsa => "RSA PRIVATE KEY",
+        Algorithm::Ed25519 => "PRIVATE KEY",
+    };
+    let mut pk = format!("-----BEGIN {pk_type}-----\n").into_bytes();
+    let mut lf_count = 65;
+    for ch in base64_encode(
+        match algo {
+            Algorithm::Rsa => DkimKeyPair::generate_rsa(2048),
+            Algorithm::Ed25519 => DkimKeyPair::generate_ed25519(),
+        }
+        .map_err(|err| {
+            manage::error("Failed to generate key", err.to_string().into())
+                .caused_by(trc::location!())
+        })?
+        .private_key(),
+    )
+    .unwrap_or_default()
+    {
+        pk.push(ch);
+        lf_count -= 1;
+        if lf_count == 0 {
+            pk.push(b'\n');
+            lf_count = 65;
+        }
+    }
+    if lf_count != 65 {
+        pk.push(b'\n');
+    }
+    pk.extend_from_slice(format!("-----END {pk_type}-----\n").as_bytes());
+
+    String::from_utf8(pk).map_err(|err| {
+        manage::error("Failed to encode key", err.to_string().into()).caused_by(trc::location!())
+    })
+}
+
+pub fn dkim_txt_record(algo: Algorithm, public_key: &str) -> String {
+    match algo {
+        Algorithm::Rsa => format!("v=DKIM1; k=rsa; h=sha256; p={public_key}"),
+        Algorithm::Ed25519 => format!("v=DKIM1; k=ed25519; h=sha256; p={public_key}"),
+    }
+}
+
 pub fn obtain_dkim_public_key(algo: Algorithm, pk: &str) -> trc::Result<String> {
     match simple_pem_parse(pk) {
         Some(der) => match algo {
@@ -281,6 +296,15 @@ pub fn obtain_dkim_public_key(algo: Algorithm, pk: &str) -> trc::Result<String>
     }
 }
 
+impl Algorithm {
+    pub fn selector_suffix(&self) -> &'static str {
+        match self {
+            Algorithm::Rsa => "r",
+            Algorithm::Ed25519 => "e",
+        }
+    }
+}
+
 impl FromStr for Algorithm {
     type Err = ();
 
@@ -17,10 +17,13 @@ use sha1::Digest;
 use utils::config::Config;
 use x509_parser::parse_x509_certificate;
 
-use crate::api::{
-    http::ToHttpResponse,
-    management::dkim::{obtain_dkim_public_key, Algorithm},
-    HttpRequest, HttpResponse, JsonResponse,
+use crate::{
+    api::{
+        http::ToHttpResponse,
+        management::dkim::{dkim_txt_record, obtain_dkim_public_key, Algorithm},
+        HttpRequest, HttpResponse, JsonResponse,
+    },
+    services::dns_sync::DnsSync,
 };
 
 use super::decode_path_element;
@@ -29,9 +32,9 @@ use std::future::Future;
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DnsRecord {
     #[serde(rename = "type")]
-    typ: String,
-    name: String,
-    content: String,
+    pub typ: String,
+    pub name: String,
+    pub content: String,
 }
 
 pub trait DnsManagement: Sync + Send {
@@ -71,6 +74,25 @@ impl DnsManagement for Server {
                 }))
                 .into_http_response())
             }
+            ("sync", Some(domain), method @ (&Method::GET | &Method::POST)) => {
+                // Validate the access token
+                let dry_run = *method == Method::GET;
+                access_token.assert_has_permission(if dry_run {
+                    Permission::DomainGet
+                } else {
+                    Permission::DomainUpdate
+                })?;
+
+                // Reconcile the zone, changes are only applied on POST
+                let domain = decode_path_element(domain);
+                match self.dns_sync_domain(domain.as_ref(), dry_run).await? {
+                    Some(changes) => Ok(JsonResponse::new(json!({
+                        "data": changes,
+                    }))
+                    .into_http_response()),
+                    None => Err(manage::not_found(domain.into_owned())),
+                }
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -117,27 +139,31 @@ impl DnsManagement for Server {
             keys.log_errors();
         }
         for signature_id in signature_ids {
-            if let (Some(algo), Some(pk), Some(selector)) = (
-                keys.value(format!("{signature_id}.algorithm"))
-                    .and_then(|algo| algo.parse::<Algorithm>().ok()),
-                keys.value(format!("{signature_id}.private-key")),
-                keys.value(format!("{signature_id}.selector")),
-            ) {
-                match obtain_dkim_public_key(algo, pk) {
-                    Ok(public) => {
-                        records.push(DnsRecord {
-                            typ: "TXT".to_string(),
-                            name: format!("{selector}._domainkey.{domain_name}.",),
-                            content: match algo {
-                                Algorithm::Rsa => format!("v=DKIM1; k=rsa; h=sha256; p={public}"),
-                                Algorithm::Ed25519 => {
-                                    format!("v=DKIM1; k=ed25519; h=sha256; p={public}")
-                                }
-                            },
-                        });
-                    }
-                    Err(err) => {
-                        trc::error!(err);
+            let Some(algo) = keys
+                .value(format!("{signature_id}.algorithm"))
+                .and_then(|algo| algo.parse::<Algorithm>().ok())
+            else {
+                continue;
+            };
+
+            // Keys staged for rotation and retired keys in their grace period
+            // are published next to the active key
+            for prefix in ["", "rotation.pending.", "rotation.retired."] {
+                if let (Some(pk), Some(selector)) = (
+                    keys.value(format!("{signature_id}.{prefix}private-key")),
+                    keys.value(format!("{signature_id}.{prefix}selector")),
+                ) {
+                    match obtain_dkim_public_key(algo, pk) {
+                        Ok(public) => {
+                            records.push(DnsRecord {
+                                typ: "TXT".to_string(),
+                                name: format!("{selector}._domainkey.{domain_name}.",),
+                                content: dkim_txt_record(algo, &public),
+                            });
+                        }
+                        Err(err) => {
+                            trc::error!(err);
+                        }
                     }
                 }
             }
@@ -215,6 +215,7 @@ impl UndeleteApi for Server {
                                             spam_classify: false,
                                             spam_train: false,
                                             session_id: session.session_id,
+                                            replace: None,
                                         })
                                         .await
                                     {
@@ -10,6 +10,7 @@ pub mod dns;
 pub mod enterprise;
 pub mod log;
 pub mod principal;
+pub mod quarantine;
 pub mod queue;
 pub mod reload;
 pub mod report;
@@ -30,6 +31,7 @@ use hyper::Method;
 use log::LogManagement;
 use mail_parser::DateTime;
 use principal::PrincipalManager;
+use quarantine::ManageQuarantine;
 use queue::QueueManagement;
 use reload::ManageReload;
 use report::ManageReports;
@@ -94,6 +96,10 @@ impl ManagementApi for Server {
 
         match path.first().copied().unwrap_or_default() {
             "queue" => self.handle_manage_queue(req, path, &access_token).await,
+            "quarantine" => {
+                self.handle_manage_quarantine(req, path.get(1).copied(), &access_token, false)
+                    .await
+            }
             "settings" => {
                 self.handle_manage_settings(req, path, body, &access_token)
                     .await
@@ -158,6 +164,13 @@ impl ManagementApi for Server {
 
                     self.handle_account_auth_post(req, access_token, body).await
                 }
+                ("quarantine", _) => {
+                    // Validate the access token
+                    access_token.assert_has_permission(Permission::ManageQuarantine)?;
+
+                    self.handle_manage_quarantine(req, path.get(2).copied(), &access_token, true)
+                        .await
+                }
                 _ => Err(trc::ResourceEvent::NotFound.into_err()),
             },
             "troubleshoot" => {
@@ -18,6 +18,7 @@ use directory::{
 
 use hyper::{header, Method};
 use serde_json::json;
+use spam_filter::modules::ftrl::FtrlClassifier;
 use trc::AddContext;
 use utils::url_params::UrlParams;
 
@@ -402,6 +403,12 @@ impl PrincipalManager for Server {
                                             err.details("Failed to delete user bayes model")
                                         );
                                     }
+
+                                    if let Err(err) =
+                                        server.ftrl_delete_account(principal.id()).await
+                                    {
+                                        trc::error!(err.details("Failed to delete user FTRL model"));
+                                    }
                                 }
                             }
                         }
@@ -518,6 +525,10 @@ impl PrincipalManager for Server {
                                 {
                                     trc::error!(err.details("Failed to delete user bayes model"));
                                 }
+
+                                if let Err(err) = self.ftrl_delete_account(account_id).await {
+                                    trc::error!(err.details("Failed to delete user FTRL model"));
+                                }
                             }
                         }
 
@@ -571,7 +582,10 @@ impl PrincipalManager for Server {
                                 | PrincipalField::Members
                                 | PrincipalField::Lists
                                 | PrincipalField::Urls
-                                | PrincipalField::ExternalMembers => (),
+                                | PrincipalField::ExternalMembers
+                                | PrincipalField::Moderators
+                                | PrincipalField::DisabledMembers
+                                | PrincipalField::ListOptions => (),
                                 PrincipalField::Tenant => {
                                     // Tenants are not allowed to change their tenantId
                                     if access_token.tenant.is_some() {
@@ -0,0 +1,384 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{auth::AccessToken, Server};
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField},
+    Permission, Type,
+};
+use hyper::Method;
+use mail_parser::{DateTime, MessageParser};
+use serde_json::{json, Value};
+use smtp::queue::{
+    quarantine::{QuarantineSource, QuarantinedMessage, SmtpQuarantine},
+    DomainPart,
+};
+use spam_filter::{
+    analysis::init::SpamFilterInit,
+    modules::{bayes::BayesClassifier, fuzzy::FuzzyDatabase},
+    SpamFilterInput,
+};
+use store::{
+    write::{key::DeserializeBigEndian, Bincode, ReportClass, ValueClass},
+    Deserialize, IterateParams, ValueKey, U64_LEN,
+};
+use trc::AddContext;
+use utils::url_params::UrlParams;
+
+use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
+
+use super::decode_path_element;
+
+const PREVIEW_LEN: usize = 1024;
+
+pub trait ManageQuarantine: Sync + Send {
+    fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        id: Option<&str>,
+        access_token: &AccessToken,
+        is_owner: bool,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+}
+
+impl ManageQuarantine for Server {
+    async fn handle_manage_quarantine(
+        &self,
+        req: &HttpRequest,
+        id: Option<&str>,
+        access_token: &AccessToken,
+        is_owner: bool,
+    ) -> trc::Result<HttpResponse> {
+        let params = UrlParams::new(req.uri().query());
+
+        // Users can only access messages addressed to them
+        let owner_addresses = is_owner.then_some(access_token.emails.as_slice());
+
+        // SPDX-SnippetBegin
+        // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+        // SPDX-License-Identifier: LicenseRef-SEL
+
+        // Limit to tenant domains
+        let mut tenant_domains: Option<Vec<String>> = None;
+        #[cfg(feature = "enterprise")]
+        if self.core.is_enterprise_edition() && !is_owner {
+            if let Some(tenant) = access_token.tenant {
+                tenant_domains = self
+                    .core
+                    .storage
+                    .data
+                    .list_principals(
+                        None,
+                        tenant.id.into(),
+                        &[Type::Domain],
+                        &[PrincipalField::Name],
+                        0,
+                        0,
+                    )
+                    .await
+                    .map(|principals| {
+                        principals
+                            .items
+                            .into_iter()
+                            .filter_map(|mut p| p.take_str(PrincipalField::Name))
+                            .collect::<Vec<_>>()
+                    })
+                    .caused_by(trc::location!())?
+                    .into();
+            }
+        }
+
+        // SPDX-SnippetEnd
+
+        let filter = QuarantineFilter {
+            tenant_domains: tenant_domains.as_deref(),
+            owner_addresses,
+        };
+
+        match (id.map(decode_path_element), req.method()) {
+            (None, &Method::GET) => {
+                // Validate the access token
+                if !is_owner {
+                    access_token.assert_has_permission(Permission::QuarantineList)?;
+                }
+
+                let QuarantinedMessages { items, total } =
+                    fetch_quarantined_messages(self, &params, &filter).await?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": items
+                                .iter()
+                                .map(|(id, expires, message)| {
+                                    quarantine_to_json(*id, *expires, message, &filter)
+                                })
+                                .collect::<Vec<_>>(),
+                            "total": total,
+                        },
+                }))
+                .into_http_response())
+            }
+            (Some(id), &Method::GET) => {
+                // Validate the access token
+                if !is_owner {
+                    access_token.assert_has_permission(Permission::QuarantineGet)?;
+                }
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let message = self
+                    .read_quarantined_message(id, expires)
+                    .await?
+                    .filter(|message| filter.matches(message))
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let raw_message = self
+                    .blob_store()
+                    .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+                    .await
+                    .caused_by(trc::location!())?
+                    .unwrap_or_default();
+
+                let mut result = quarantine_to_json(id, expires, &message, &filter);
+                if let Some(parsed) = MessageParser::new().parse(&raw_message) {
+                    let headers = parsed
+                        .root_part()
+                        .raw_header_offset()
+                        .min(raw_message.len());
+                    let headers = String::from_utf8_lossy(
+                        &raw_message
+                            [headers..parsed.root_part().raw_body_offset().min(raw_message.len())],
+                    );
+                    let preview = parsed
+                        .body_text(0)
+                        .or_else(|| parsed.body_html(0))
+                        .map(|text| text.chars().take(PREVIEW_LEN).collect::<String>())
+                        .unwrap_or_default();
+                    result["headers"] = Value::String(headers.into_owned());
+                    result["preview"] = Value::String(preview);
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": result,
+                }))
+                .into_http_response())
+            }
+            (Some(id), &Method::PATCH) => {
+                // Validate the access token
+                if !is_owner {
+                    access_token.assert_has_permission(Permission::QuarantineRelease)?;
+                }
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                let message = self
+                    .read_quarantined_message(id, expires)
+                    .await?
+                    .filter(|message| filter.matches(message))
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+
+                // Train the spam filter before releasing the message
+                if params.get("train-ham").is_some_and(|v| v == "true") {
+                    if let Some(raw_message) = self
+                        .blob_store()
+                        .get_blob(message.blob_hash.as_slice(), 0..usize::MAX)
+                        .await
+                        .caused_by(trc::location!())?
+                    {
+                        if let Some(parsed) = MessageParser::new().parse(&raw_message) {
+                            let ctx =
+                                self.spam_filter_init(SpamFilterInput::from_message(&parsed, 0));
+                            self.bayes_train(&ctx, false, true).await?;
+                            if self.core.spam.fuzzy.is_some() {
+                                if let Err(err) = self.fuzzy_train(&ctx, false).await {
+                                    trc::error!(err.caused_by(trc::location!()));
+                                }
+                            }
+                        }
+                    }
+                }
+
+                let recipients = filter.visible_recipients(&message);
+                let released = self
+                    .release_quarantined_message(
+                        id,
+                        expires,
+                        filter.is_scoped().then_some(recipients.as_slice()),
+                    )
+                    .await?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": released,
+                }))
+                .into_http_response())
+            }
+            (Some(id), &Method::DELETE) => {
+                // Validate the access token
+                if !is_owner {
+                    access_token.assert_has_permission(Permission::QuarantineDelete)?;
+                }
+
+                let (id, expires) = parse_quarantine_id(id.as_ref())
+                    .ok_or_else(|| trc::ResourceEvent::NotFound.into_err())?;
+                if self
+                    .read_quarantined_message(id, expires)
+                    .await?
+                    .filter(|message| filter.matches(message))
+                    .is_none()
+                {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                Ok(JsonResponse::new(json!({
+                        "data": self.delete_quarantined_message(id, expires).await?,
+                }))
+                .into_http_response())
+            }
+            _ => Err(trc::ResourceEvent::NotFound.into_err()),
+        }
+    }
+}
+
+struct QuarantineFilter<'x> {
+    tenant_domains: Option<&'x [String]>,
+    owner_addresses: Option<&'x [String]>,
+}
+
+impl QuarantineFilter<'_> {
+    fn matches(&self, message: &QuarantinedMessage) -> bool {
+        self.tenant_domains
+            .is_none_or(|domains| message.has_domain(domains))
+            && self.owner_addresses.is_none_or(|addresses| {
+                message
+                    .recipients
+                    .iter()
+                    .any(|rcpt| addresses.iter().any(|a| a.eq_ignore_ascii_case(rcpt)))
+            })
+    }
+
+    fn visible_recipients(&self, message: &QuarantinedMessage) -> Vec<String> {
+        message
+            .recipients
+            .iter()
+            .filter(|rcpt| {
+                self.tenant_domains.is_none_or(|domains| {
+                    domains
+                        .iter()
+                        .any(|d| rcpt.domain_part().eq_ignore_ascii_case(d))
+                }) && self
+                    .owner_addresses
+                    .is_none_or(|addresses| addresses.iter().any(|a| a.eq_ignore_ascii_case(rcpt)))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn is_scoped(&self) -> bool {
+        self.tenant_domains.is_some() || self.owner_addresses.is_some()
+    }
+}
+
+struct QuarantinedMessages {
+    items: Vec<(u64, u64, QuarantinedMessage)>,
+    total: usize,
+}
+
+async fn fetch_quarantined_messages(
+    server: &Server,
+    params: &UrlParams<'_>,
+    filter: &QuarantineFilter<'_>,
+) -> trc::Result<QuarantinedMessages> {
+    let text = params.get("text").map(|text| text.to_lowercase());
+    let page: usize = params.parse::<usize>("page").unwrap_or_default();
+    let limit: usize = params.parse::<usize>("limit").unwrap_or_default();
+
+    let mut results = QuarantinedMessages {
+        items: Vec::new(),
+        total: 0,
+    };
+    let mut offset = page.saturating_sub(1) * limit;
+
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                    id: 0,
+                    expires: 0,
+                })),
+                ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                    id: u64::MAX,
+                    expires: u64::MAX,
+                })),
+            )
+            .descending(),
+            |key, value| {
+                let message = Bincode::<QuarantinedMessage>::deserialize(value)
+                    .caused_by(trc::location!())?
+                    .inner;
+
+                if filter.matches(&message)
+                    && text.as_ref().is_none_or(|text| message.contains(text))
+                {
+                    if offset == 0 {
+                        if limit == 0 || results.items.len() < limit {
+                            results.items.push((
+                                key.deserialize_be_u64(U64_LEN + 1)?,
+                                key.deserialize_be_u64(1)?,
+                                message,
+                            ));
+                        }
+                    } else {
+                        offset -= 1;
+                    }
+
+                    results.total += 1;
+                }
+
+                Ok(true)
+            },
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|_| results)
+}
+
+fn quarantine_to_json(
+    id: u64,
+    expires: u64,
+    message: &QuarantinedMessage,
+    filter: &QuarantineFilter<'_>,
+) -> Value {
+    json!({
+        "id": format!("{id}_{expires}"),
+        "created": DateTime::from_timestamp(message.created as i64).to_rfc3339(),
+        "expires": DateTime::from_timestamp(expires as i64).to_rfc3339(),
+        "size": message.size,
+        "returnPath": message.return_path,
+        "recipients": filter.visible_recipients(message),
+        "source": match message.source {
+            QuarantineSource::Milter => "milter",
+            QuarantineSource::MtaHook => "mtaHook",
+            QuarantineSource::Sieve => "sieve",
+            QuarantineSource::SpamFilter => "spamFilter",
+            QuarantineSource::Antivirus => "antivirus",
+        },
+        "reason": message.reason,
+        "score": message.score,
+        "remoteIp": message.remote_ip.to_string(),
+        "heloDomain": message.helo_domain,
+        "from": message.from,
+        "subject": message.subject,
+    })
+}
+
+fn parse_quarantine_id(id: &str) -> Option<(u64, u64)> {
+    let (id, expires) = id.split_once('_')?;
+    Some((id.parse().ok()?, expires.parse().ok()?))
+}
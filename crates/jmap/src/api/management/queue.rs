@@ -7,7 +7,9 @@
 use std::{future::Future, sync::atomic::Ordering};
 
 use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
-use common::{auth::AccessToken, ipc::QueueEvent, Server};
+use common::{
+    auth::AccessToken, config::smtp::queue::DEFAULT_QUEUE_NAME, ipc::QueueEvent, Server,
+};
 use directory::{
     backend::internal::{manage::ManageDirectory, PrincipalField},
     Permission, Type,
@@ -22,11 +24,19 @@ use mail_parser::DateTime;
 use serde::{Deserializer, Serializer};
 use serde_json::json;
 use smtp::{
-    queue::{self, spool::SmtpSpool, ErrorDetails, HostResponse, QueueId, Status},
+    queue::{
+        self,
+        spool::SmtpSpool,
+        tracking::{TrackedMessage, TrackingEventType},
+        ErrorDetails, HostResponse, QueueId, Status,
+    },
     reporting::{dmarc::DmarcReporting, tls::TlsReporting},
 };
 use store::{
-    write::{key::DeserializeBigEndian, now, Bincode, QueueClass, ReportEvent, ValueClass},
+    ahash::AHashMap,
+    write::{
+        key::DeserializeBigEndian, now, Bincode, QueueClass, ReportClass, ReportEvent, ValueClass,
+    },
     Deserialize, IterateParams, ValueKey,
 };
 use trc::AddContext;
@@ -34,7 +44,7 @@ use utils::url_params::UrlParams;
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
-use super::{decode_path_element, FutureTimestamp};
+use super::{decode_path_element, FutureTimestamp, Timestamp};
 
 #[derive(Debug, serde::Serialize, serde::Deserialize, PartialEq, Eq)]
 pub struct Message {
@@ -50,6 +60,9 @@ pub struct Message {
     pub priority: i16,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub env_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub queue: Option<String>,
     pub blob_hash: String,
 }
 
@@ -599,6 +612,181 @@ impl QueueManagement for Server {
                 }))
                 .into_http_response())
             }
+            ("queues", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let queue_config = &self.core.smtp.queue;
+                let mut named = queue_config
+                    .named
+                    .iter()
+                    .map(|(name, queue)| (name.as_str(), queue.threads))
+                    .collect::<Vec<_>>();
+                named.sort_unstable();
+                let queue_paused = self.inner.data.queue_paused.read();
+                let queues = [(DEFAULT_QUEUE_NAME, queue_config.max_threads)]
+                    .into_iter()
+                    .chain(named)
+                    .map(|(name, threads)| {
+                        json!({
+                            "name": name,
+                            "threads": threads,
+                            "active": !queue_paused.contains(name),
+                        })
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                        "data": queues,
+                }))
+                .into_http_response())
+            }
+            ("queues", Some(name), &Method::PATCH) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let paused = match path.get(3).copied() {
+                    Some("stop") => true,
+                    Some("start") => false,
+                    _ => return Err(trc::ResourceEvent::NotFound.into_err()),
+                };
+                if name != DEFAULT_QUEUE_NAME
+                    && !self.core.smtp.queue.named.contains_key(name.as_ref())
+                {
+                    return Err(trc::ResourceEvent::NotFound.into_err());
+                }
+
+                let prev_status = !self
+                    .inner
+                    .data
+                    .queue_paused
+                    .read()
+                    .contains(name.as_ref());
+
+                let _ = self
+                    .inner
+                    .ipc
+                    .queue_tx
+                    .send(QueueEvent::PausedQueue {
+                        queue: name.into_owned(),
+                        paused,
+                    })
+                    .await;
+
+                Ok(JsonResponse::new(json!({
+                        "data": prev_status,
+                }))
+                .into_http_response())
+            }
+            ("throttle", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let mut throttled = self
+                    .inner
+                    .data
+                    .smtp_connections
+                    .throttled
+                    .lock()
+                    .iter()
+                    .map(|(key, state)| (key.clone(), state.clone()))
+                    .collect::<Vec<_>>();
+                throttled.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+                let throttled = throttled
+                    .into_iter()
+                    .map(|(key, state)| {
+                        let mut value = serde_json::to_value(state).unwrap_or_default();
+                        value["key"] = key.into();
+                        value
+                    })
+                    .collect::<Vec<_>>();
+
+                Ok(JsonResponse::new(json!({
+                        "data": throttled,
+                }))
+                .into_http_response())
+            }
+            ("throttle", Some(key), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let state = self
+                    .inner
+                    .data
+                    .smtp_connections
+                    .throttled
+                    .lock()
+                    .get(key.as_ref())
+                    .cloned();
+                if let Some(state) = state {
+                    Ok(JsonResponse::new(json!({
+                            "data": state,
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
+            ("throttle", Some(key), &Method::DELETE) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueUpdate)?;
+
+                let found = self
+                    .inner
+                    .data
+                    .smtp_connections
+                    .throttled
+                    .lock()
+                    .remove(key.as_ref())
+                    .is_some();
+
+                Ok(JsonResponse::new(json!({
+                        "data": found,
+                }))
+                .into_http_response())
+            }
+            ("tracking", None, &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueList)?;
+
+                let result = fetch_tracked_messages(self, &params, None, &tenant_domains).await?;
+
+                Ok(JsonResponse::new(json!({
+                        "data": {
+                            "items": result
+                                .items
+                                .iter()
+                                .map(|(expires, message)| tracking_to_json(*expires, message))
+                                .collect::<Vec<_>>(),
+                            "total": result.total,
+                        },
+                }))
+                .into_http_response())
+            }
+            ("tracking", Some(queue_id), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::MessageQueueGet)?;
+
+                let queue_id = queue_id
+                    .parse::<QueueId>()
+                    .map_err(|_| trc::ResourceEvent::NotFound.into_err())?;
+                let result = fetch_tracked_messages(
+                    self,
+                    &UrlParams::new(None),
+                    queue_id.into(),
+                    &tenant_domains,
+                )
+                .await?;
+
+                if let Some((expires, message)) = result.items.first() {
+                    Ok(JsonResponse::new(json!({
+                            "data": tracking_to_json(*expires, message),
+                    }))
+                    .into_http_response())
+                } else {
+                    Err(trc::ResourceEvent::NotFound.into_err())
+                }
+            }
             _ => Err(trc::ResourceEvent::NotFound.into_err()),
         }
     }
@@ -615,6 +803,7 @@ impl From<&queue::Message> for Message {
             size: message.size,
             priority: message.priority,
             env_id: message.env_id.clone(),
+            queue: message.queue.clone(),
             domains: message
                 .domains
                 .iter()
@@ -681,6 +870,7 @@ async fn fetch_queued_messages(
     let text = params.get("text");
     let from = params.get("from");
     let to = params.get("to");
+    let queue = params.get("queue");
     let before = params
         .parse::<FutureTimestamp>("before")
         .map(|t| t.into_inner());
@@ -707,6 +897,13 @@ async fn fetch_queued_messages(
     let mut offset = page.saturating_sub(1) * limit;
     let mut total_returned = 0;
 
+    // Queue names are stored in the message events
+    let mut queues = if queue.is_some() || values {
+        server.read_message_queues().await?
+    } else {
+        AHashMap::new()
+    };
+
     server
         .core
         .storage
@@ -714,12 +911,16 @@ async fn fetch_queued_messages(
         .iterate(
             IterateParams::new(from_key, to_key).ascending(),
             |key, value| {
-                let message = Bincode::<queue::Message>::deserialize(value)
+                let mut message = Bincode::<queue::Message>::deserialize(value)
                     .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?
                     .inner;
+                message.queue = queues.remove(&message.queue_id);
                 let matches = tenant_domains
                     .as_ref()
                     .is_none_or( |domains| message.has_domain(domains))
+                    && queue.is_none_or(|queue| {
+                        message.queue.as_deref().unwrap_or(DEFAULT_QUEUE_NAME) == queue
+                    })
                     && (!has_filters
                         || (text
                             .as_ref()
@@ -900,6 +1101,133 @@ impl GenerateQueueId for QueueClass {
     }
 }
 
+struct TrackedMessages {
+    items: Vec<(u64, TrackedMessage)>,
+    total: usize,
+}
+
+async fn fetch_tracked_messages(
+    server: &Server,
+    params: &UrlParams<'_>,
+    queue_id: Option<QueueId>,
+    tenant_domains: &Option<Vec<String>>,
+) -> trc::Result<TrackedMessages> {
+    let message_id = params
+        .get("message-id")
+        .map(|id| id.trim().trim_start_matches('<').trim_end_matches('>'));
+    let from = params.get("from").map(|from| from.to_lowercase());
+    let to = params.get("to").map(|to| to.to_lowercase());
+    let before = params.parse::<Timestamp>("before").map(|t| t.into_inner());
+    let after = params.parse::<Timestamp>("after").map(|t| t.into_inner());
+    let page = params.parse::<usize>("page").unwrap_or_default();
+    let limit = params.parse::<usize>("limit").unwrap_or_default();
+
+    let mut result = TrackedMessages {
+        items: Vec::new(),
+        total: 0,
+    };
+    let mut offset = page.saturating_sub(1) * limit;
+
+    server
+        .core
+        .storage
+        .data
+        .iterate(
+            IterateParams::new(
+                ValueKey::from(ValueClass::Report(ReportClass::Tracking {
+                    id: 0,
+                    expires: now(),
+                })),
+                ValueKey::from(ValueClass::Report(ReportClass::Tracking {
+                    id: u64::MAX,
+                    expires: u64::MAX,
+                })),
+            )
+            .descending(),
+            |key, value| {
+                let message = Bincode::<TrackedMessage>::deserialize(value)
+                    .add_context(|ctx| ctx.ctx(trc::Key::Key, key))?
+                    .inner;
+                let matches = queue_id.is_none_or(|id| message.queue_id == id)
+                    && tenant_domains
+                        .as_ref()
+                        .is_none_or(|domains| message.has_domain(domains))
+                    && message_id.is_none_or(|id| message.message_id.as_deref() == Some(id))
+                    && from
+                        .as_ref()
+                        .is_none_or(|from| message.return_path.to_lowercase().contains(from))
+                    && to.as_ref().is_none_or(|to| {
+                        message
+                            .recipients
+                            .iter()
+                            .any(|rcpt| rcpt.address.contains(to))
+                    })
+                    && before.is_none_or(|before| message.created < before)
+                    && after.is_none_or(|after| message.created > after);
+
+                if matches {
+                    if offset == 0 {
+                        if limit == 0 || result.items.len() < limit {
+                            result.items.push((key.deserialize_be_u64(1)?, message));
+                        }
+                    } else {
+                        offset -= 1;
+                    }
+
+                    result.total += 1;
+                }
+
+                Ok(queue_id.is_none() || result.total == 0)
+            },
+        )
+        .await
+        .caused_by(trc::location!())
+        .map(|_| result)
+}
+
+fn tracking_to_json(expires: u64, message: &TrackedMessage) -> serde_json::Value {
+    json!({
+        "id": message.queue_id,
+        "messageId": message.message_id,
+        "returnPath": message.return_path,
+        "size": message.size,
+        "created": DateTime::from_timestamp(message.created as i64).to_rfc3339(),
+        "expires": DateTime::from_timestamp(expires as i64).to_rfc3339(),
+        "recipients": message
+            .recipients
+            .iter()
+            .map(|rcpt| {
+                json!({
+                    "address": rcpt.address,
+                    "events": rcpt
+                        .events
+                        .iter()
+                        .map(|event| {
+                            json!({
+                                "timestamp": DateTime::from_timestamp(event.timestamp as i64)
+                                    .to_rfc3339(),
+                                "type": match event.typ {
+                                    TrackingEventType::Queued => "queued",
+                                    TrackingEventType::Delivered => "delivered",
+                                    TrackingEventType::Deferred => "deferred",
+                                    TrackingEventType::Failed => "failed",
+                                    TrackingEventType::DsnSuccess => "dsnSuccess",
+                                    TrackingEventType::DsnDelay => "dsnDelay",
+                                    TrackingEventType::DsnFailure => "dsnFailure",
+                                },
+                                "mx": event.mx,
+                                "remoteIp": event.remote_ip.map(|ip| ip.to_string()),
+                                "tls": event.tls,
+                                "response": event.response,
+                            })
+                        })
+                        .collect::<Vec<_>>(),
+                })
+            })
+            .collect::<Vec<_>>(),
+    })
+}
+
 fn parse_queued_report_id(id: &str) -> Option<QueueClass> {
     let mut parts = id.split('!');
     let type_ = parts.next()?;
@@ -17,7 +17,10 @@ use mail_auth::report::{
     Feedback,
 };
 use serde_json::json;
-use smtp::reporting::analysis::IncomingReport;
+use smtp::reporting::{
+    analysis::IncomingReport,
+    analytics::{AnalyticsQuery, ReportAnalytics},
+};
 use store::{
     write::{key::DeserializeBigEndian, BatchBuilder, Bincode, ReportClass, ValueClass},
     Deserialize, IterateParams, ValueKey, U64_LEN,
@@ -27,7 +30,7 @@ use utils::url_params::UrlParams;
 
 use crate::api::{http::ToHttpResponse, HttpRequest, HttpResponse, JsonResponse};
 
-use super::decode_path_element;
+use super::{decode_path_element, Timestamp};
 
 enum ReportType {
     Dmarc,
@@ -92,6 +95,46 @@ impl ManageReports for Server {
             path.get(2).copied().map(decode_path_element),
             req.method(),
         ) {
+            ("analytics", Some(class), &Method::GET) => {
+                // Validate the access token
+                access_token.assert_has_permission(Permission::IncomingReportList)?;
+
+                let params = UrlParams::new(req.uri().query());
+                let query = AnalyticsQuery {
+                    domain: params.get("domain").map(|domain| domain.to_lowercase()),
+                    from: params
+                        .parse::<Timestamp>("after")
+                        .map(|t| t.into_inner())
+                        .unwrap_or_default(),
+                    to: params
+                        .parse::<Timestamp>("before")
+                        .map(|t| t.into_inner())
+                        .unwrap_or(u64::MAX),
+                    interval: match params.get("interval").unwrap_or("day") {
+                        "hour" => 3600,
+                        "day" => 86400,
+                        "week" => 7 * 86400,
+                        _ => {
+                            return Err(trc::EventType::Resource(trc::ResourceEvent::BadParameters)
+                                .into_err()
+                                .details("Invalid interval"))
+                        }
+                    },
+                    limit: params.parse::<usize>("limit").unwrap_or(100),
+                };
+
+                match class.as_ref() {
+                    "dmarc" => Ok(JsonResponse::new(json!({
+                            "data": self.dmarc_analytics(&query, &tenant_domains).await?,
+                    }))
+                    .into_http_response()),
+                    "tls" => Ok(JsonResponse::new(json!({
+                            "data": self.tls_analytics(&query, &tenant_domains).await?,
+                    }))
+                    .into_http_response()),
+                    _ => Err(trc::ResourceEvent::NotFound.into_err()),
+                }
+            }
             (class @ ("dmarc" | "tls" | "arf"), None, &Method::GET) => {
                 // Validate the access token
                 access_token.assert_has_permission(Permission::IncomingReportList)?;
@@ -180,6 +223,9 @@ impl ManageReports for Server {
                             }
                             _ => Err(trc::ResourceEvent::NotFound.into_err()),
                         },
+                        ReportClass::Quarantine { .. } | ReportClass::Tracking { .. } => {
+                            Err(trc::ResourceEvent::NotFound.into_err())
+                        }
                     }
                 } else {
                     Err(trc::ResourceEvent::NotFound.into_err())
@@ -211,6 +257,9 @@ impl ManageReports for Server {
                                 ReportClass::Dmarc { .. } => ReportClass::Dmarc { id, expires },
                                 ReportClass::Tls { .. } => ReportClass::Tls { id, expires },
                                 ReportClass::Arf { .. } => ReportClass::Arf { id, expires },
+                                ReportClass::Quarantine { .. } | ReportClass::Tracking { .. } => {
+                                    unreachable!()
+                                }
                             };
 
                             batch.clear(ValueClass::Report(report_id));
@@ -273,6 +322,7 @@ impl ManageReports for Server {
                                 ))
                                 .await?
                                 .is_none_or( |report| report.inner.has_domain(domains)),
+                            ReportClass::Quarantine { .. } | ReportClass::Tracking { .. } => false,
                         };
 
                         if !is_tenant_report {
@@ -20,7 +20,7 @@ use serde::{Deserialize, Serialize};
 use serde_json::json;
 use spam_filter::{
     analysis::{init::SpamFilterInit, score::SpamFilterAnalyzeScore},
-    modules::bayes::BayesClassifier,
+    modules::{bayes::BayesClassifier, fuzzy::FuzzyDatabase},
     SpamFilterInput,
 };
 use std::future::Future;
@@ -81,6 +81,7 @@ pub enum SpamFilterDisposition<T> {
     Allow { value: T },
     Discard,
     Reject,
+    Quarantine,
 }
 
 impl ManageSpamHandler for Server {
@@ -108,8 +109,13 @@ impl ManageSpamHandler for Server {
                 } else {
                     SpamFilterInput::from_message(&message, session.session_id)
                 };
-                self.bayes_train(&self.spam_filter_init(input), class == "spam", true)
-                    .await?;
+                let ctx = self.spam_filter_init(input);
+                self.bayes_train(&ctx, class == "spam", true).await?;
+                if self.core.spam.fuzzy.is_some() {
+                    if let Err(err) = self.fuzzy_train(&ctx, class == "spam").await {
+                        trc::error!(err.span_id(session.session_id));
+                    }
+                }
 
                 Ok(JsonResponse::new(json!({
                     "data": (),
@@ -247,6 +253,7 @@ impl ManageSpamHandler for Server {
                     env_rcpt_to: request.env_rcpt_to.iter().map(String::as_str).collect(),
                     account_id: None,
                     is_test: true,
+                    antivirus: None,
                 };
 
                 // Classify
@@ -261,6 +268,7 @@ impl ManageSpamHandler for Server {
                         SpamFilterAction::Allow(value) => SpamFilterDisposition::Allow { value },
                         SpamFilterAction::Discard => SpamFilterDisposition::Discard,
                         SpamFilterAction::Reject => SpamFilterDisposition::Reject,
+                        SpamFilterAction::Quarantine => SpamFilterDisposition::Quarantine,
                     },
                 };
                 for tag in ctx.result.tags {
@@ -270,6 +278,7 @@ impl ManageSpamHandler for Server {
                         }
                         Some(SpamFilterAction::Discard) => SpamFilterDisposition::Discard,
                         Some(SpamFilterAction::Reject) => SpamFilterDisposition::Reject,
+                        Some(SpamFilterAction::Quarantine) => SpamFilterDisposition::Quarantine,
                         None => SpamFilterDisposition::Allow { value: 0.0 },
                     };
                     response.tags.insert(tag, disposition);
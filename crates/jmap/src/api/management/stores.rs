@@ -395,10 +395,12 @@ pub async fn reset_imap_uids(server: &Server, account_id: u32) -> trc::Result<(u
         };
 
         for uid_mailbox in &mut uids.inner {
-            uid_mailbox.uid = server
-                .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
-                .await
-                .caused_by(trc::location!())?;
+            uid_mailbox.assign_uid(
+                server
+                    .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
+                    .await
+                    .caused_by(trc::location!())?,
+            );
         }
 
         // Prepare write batch
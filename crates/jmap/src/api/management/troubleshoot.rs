@@ -96,6 +96,7 @@ impl TroubleshootApi for Server {
                     content_type: "text/event-stream".into(),
                     content_disposition: "".into(),
                     cache_control: "no-store".into(),
+                    headers: vec![],
                     body: HttpResponseBody::Stream(BoxBody::new(StreamBody::new(
                         async_stream::stream! {
                             while let Some(stage) = rx.recv().await {
@@ -375,7 +376,7 @@ async fn delivery_troubleshoot(
     // Fetch MTA-STS policy
     let now = Instant::now();
     tx.send(DeliveryStage::MtaStsFetchStart).await?;
-    let mta_sts_policy = match server.lookup_mta_sts_policy(&domain, timeout).await {
+    let mta_sts_policy = match server.lookup_mta_sts_policy(&domain, timeout, None).await {
         Ok(policy) => {
             tx.send(DeliveryStage::MtaStsFetchSuccess {
                 policy: policy.as_ref().clone(),
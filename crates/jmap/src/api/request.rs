@@ -7,6 +7,7 @@
 use std::{sync::Arc, time::Instant};
 
 use common::{auth::AccessToken, Server};
+use email::groupware::{CALENDARS, CONTACTS};
 use jmap_proto::{
     method::{
         get, query,
@@ -25,6 +26,7 @@ use crate::{
         copy::EmailCopy, get::EmailGet, import::EmailImport, parse::EmailParse, query::EmailQuery,
         set::EmailSet, snippet::EmailSearchSnippet,
     },
+    groupware::{get::GroupwareGet, query::GroupwareQuery, set::GroupwareSet},
     identity::{get::IdentityGet, set::IdentitySet},
     mailbox::{get::MailboxGet, query::MailboxQuery, set::MailboxSet},
     principal::{get::PrincipalGet, query::PrincipalQuery},
@@ -234,6 +236,30 @@ impl RequestHandler for Server {
                         .await?
                         .into()
                 }
+                get::RequestArguments::AddressBook => {
+                    access_token.assert_has_access(req.account_id, Collection::AddressBook)?;
+
+                    self.container_get(req, access_token, &CONTACTS)
+                        .await?
+                        .into()
+                }
+                get::RequestArguments::ContactCard => {
+                    access_token.assert_has_access(req.account_id, Collection::ContactCard)?;
+
+                    self.item_get(req, access_token, &CONTACTS).await?.into()
+                }
+                get::RequestArguments::Calendar => {
+                    access_token.assert_has_access(req.account_id, Collection::Calendar)?;
+
+                    self.container_get(req, access_token, &CALENDARS)
+                        .await?
+                        .into()
+                }
+                get::RequestArguments::CalendarEvent => {
+                    access_token.assert_has_access(req.account_id, Collection::CalendarEvent)?;
+
+                    self.item_get(req, access_token, &CALENDARS).await?.into()
+                }
             },
             RequestMethod::Query(mut req) => match req.take_arguments() {
                 query::RequestArguments::Email(arguments) => {
@@ -268,6 +294,30 @@ impl RequestHandler for Server {
 
                     self.quota_query(req, access_token).await?.into()
                 }
+                query::RequestArguments::AddressBook => {
+                    access_token.assert_has_access(req.account_id, Collection::AddressBook)?;
+
+                    self.container_query(req, access_token, &CONTACTS)
+                        .await?
+                        .into()
+                }
+                query::RequestArguments::ContactCard => {
+                    access_token.assert_has_access(req.account_id, Collection::ContactCard)?;
+
+                    self.item_query(req, access_token, &CONTACTS).await?.into()
+                }
+                query::RequestArguments::Calendar => {
+                    access_token.assert_has_access(req.account_id, Collection::Calendar)?;
+
+                    self.container_query(req, access_token, &CALENDARS)
+                        .await?
+                        .into()
+                }
+                query::RequestArguments::CalendarEvent => {
+                    access_token.assert_has_access(req.account_id, Collection::CalendarEvent)?;
+
+                    self.item_query(req, access_token, &CALENDARS).await?.into()
+                }
             },
             RequestMethod::Set(mut req) => match req.take_arguments() {
                 set::RequestArguments::Email => {
@@ -313,6 +363,30 @@ impl RequestHandler for Server {
 
                     self.vacation_response_set(req, access_token).await?.into()
                 }
+                set::RequestArguments::AddressBook => {
+                    access_token.assert_has_access(req.account_id, Collection::AddressBook)?;
+
+                    self.container_set(req, access_token, &CONTACTS)
+                        .await?
+                        .into()
+                }
+                set::RequestArguments::ContactCard => {
+                    access_token.assert_has_access(req.account_id, Collection::ContactCard)?;
+
+                    self.item_set(req, access_token, &CONTACTS).await?.into()
+                }
+                set::RequestArguments::Calendar => {
+                    access_token.assert_has_access(req.account_id, Collection::Calendar)?;
+
+                    self.container_set(req, access_token, &CALENDARS)
+                        .await?
+                        .into()
+                }
+                set::RequestArguments::CalendarEvent => {
+                    access_token.assert_has_access(req.account_id, Collection::CalendarEvent)?;
+
+                    self.item_set(req, access_token, &CALENDARS).await?.into()
+                }
             },
             RequestMethod::Changes(req) => self.changes(req, access_token).await?.into(),
             RequestMethod::Copy(req) => {
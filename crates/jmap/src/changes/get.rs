@@ -65,6 +65,26 @@ impl ChangesLookup for Server {
 
                 return Err(trc::JmapEvent::CannotCalculateChanges.into_err());
             }
+            RequestArguments::AddressBook => {
+                access_token.assert_has_access(request.account_id, Collection::AddressBook)?;
+
+                Collection::AddressBook
+            }
+            RequestArguments::ContactCard => {
+                access_token.assert_has_access(request.account_id, Collection::ContactCard)?;
+
+                Collection::ContactCard
+            }
+            RequestArguments::Calendar => {
+                access_token.assert_has_access(request.account_id, Collection::Calendar)?;
+
+                Collection::Calendar
+            }
+            RequestArguments::CalendarEvent => {
+                access_token.assert_has_access(request.account_id, Collection::CalendarEvent)?;
+
+                Collection::CalendarEvent
+            }
         };
 
         let max_changes = if self.core.jmap.changes_max_results > 0
@@ -5,6 +5,7 @@
  */
 
 use common::{auth::AccessToken, Server};
+use email::groupware::{CALENDARS, CONTACTS};
 use jmap_proto::method::{
     changes::{self, ChangesRequest},
     query::{self, QueryRequest},
@@ -13,8 +14,8 @@ use jmap_proto::method::{
 use std::future::Future;
 
 use crate::{
-    email::query::EmailQuery, mailbox::query::MailboxQuery, quota::query::QuotaQuery,
-    submission::query::EmailSubmissionQuery,
+    email::query::EmailQuery, groupware::query::GroupwareQuery, mailbox::query::MailboxQuery,
+    quota::query::QuotaQuery, submission::query::EmailSubmissionQuery,
 };
 
 use super::get::ChangesLookup;
@@ -47,6 +48,16 @@ impl QueryChanges for Server {
                             changes::RequestArguments::EmailSubmission
                         }
                         query::RequestArguments::Quota => changes::RequestArguments::Quota,
+                        query::RequestArguments::AddressBook => {
+                            changes::RequestArguments::AddressBook
+                        }
+                        query::RequestArguments::ContactCard => {
+                            changes::RequestArguments::ContactCard
+                        }
+                        query::RequestArguments::Calendar => changes::RequestArguments::Calendar,
+                        query::RequestArguments::CalendarEvent => {
+                            changes::RequestArguments::CalendarEvent
+                        }
                         _ => {
                             return Err(trc::JmapEvent::UnknownMethod
                                 .into_err()
@@ -98,6 +109,19 @@ impl QueryChanges for Server {
                     self.email_submission_query(query).await?
                 }
                 query::RequestArguments::Quota => self.quota_query(query, access_token).await?,
+                query::RequestArguments::AddressBook => {
+                    self.container_query(query, access_token, &CONTACTS).await?
+                }
+                query::RequestArguments::ContactCard => {
+                    self.item_query(query, access_token, &CONTACTS).await?
+                }
+                query::RequestArguments::Calendar => {
+                    self.container_query(query, access_token, &CALENDARS)
+                        .await?
+                }
+                query::RequestArguments::CalendarEvent => {
+                    self.item_query(query, access_token, &CALENDARS).await?
+                }
                 _ => unreachable!(),
             };
 
@@ -0,0 +1,398 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::{GroupwareType, CALENDARS};
+use hyper::StatusCode;
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use store::{
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder},
+};
+use trc::AddContext;
+
+use crate::{api::HttpResponse, JmapMethods};
+
+use super::{
+    container_acl,
+    item::DavItemDelete,
+    xml::{MultiStatus, Namespace, XmlElement},
+    DavAccount, DavCommit, DavError, DavObject, DavResource, DavResult, Preconditions,
+};
+use std::future::Future;
+
+pub trait DavCollection: Sync + Send {
+    fn dav_mkcol(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        is_mkcalendar: bool,
+        body: &[u8],
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_proppatch(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        body: &[u8],
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_delete_collection(
+        &self,
+        access_token: &AccessToken,
+        account: DavAccount,
+        kind: &'static GroupwareType,
+        container: DavObject,
+        preconditions: &Preconditions,
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_default_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl DavCollection for Server {
+    async fn dav_mkcol(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        is_mkcalendar: bool,
+        body: &[u8],
+    ) -> DavResult<HttpResponse> {
+        let (account, kind, name) = match resource {
+            DavResource::Collection {
+                account,
+                kind,
+                name,
+                container: None,
+            } if !is_mkcalendar || std::ptr::eq(kind, &CALENDARS) => (account, kind, name),
+            DavResource::Collection {
+                container: Some(_), ..
+            }
+            | DavResource::Item { .. } => {
+                return Err(DavError::Status(StatusCode::METHOD_NOT_ALLOWED));
+            }
+            _ => return Err(DavError::Status(StatusCode::FORBIDDEN)),
+        };
+
+        // Containers can only be created by their owners
+        if !access_token.is_member(account.id) {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+
+        // Parse extended MKCOL properties
+        let mut container = Object::with_capacity(4)
+            .with_property(Property::Name, name.clone())
+            .with_property(Property::DavName, name);
+        if let Some(request) = XmlElement::parse(body).map_err(|_| bad_request())? {
+            if !request.is(&Namespace::Dav, "mkcol")
+                && !request.is(&Namespace::CalDav, "mkcalendar")
+            {
+                return Err(bad_request());
+            }
+
+            for prop in request
+                .children(&Namespace::Dav, "set")
+                .flat_map(|set| set.children(&Namespace::Dav, "prop"))
+                .flat_map(|prop| prop.children.iter())
+            {
+                if let Some((property, value)) = container_property(kind, prop, true) {
+                    container.set(property, value);
+                } else if !prop.name.as_ref().is_some_and(|name| {
+                    name.is(&Namespace::Dav, "resourcetype")
+                        || name.is(&Namespace::CalDav, "supported-calendar-component-set")
+                        || name.is(&Namespace::CalDav, "calendar-timezone")
+                }) {
+                    return Err(DavError::Status(StatusCode::FORBIDDEN));
+                }
+            }
+        }
+
+        let mut changes = ChangeLogBuilder::new();
+        self.dav_create_container(account.id, kind, container, &mut changes)
+            .await?;
+        self.dav_commit_changes(account.id, changes, &[kind.container_type])
+            .await?;
+
+        Ok(HttpResponse::new_empty(StatusCode::CREATED))
+    }
+
+    async fn dav_proppatch(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        body: &[u8],
+    ) -> DavResult<HttpResponse> {
+        let (account, kind, container) = match resource {
+            DavResource::Collection {
+                account,
+                kind,
+                container: Some(container),
+                ..
+            } => (account, kind, container),
+            DavResource::Collection {
+                container: None, ..
+            } => return Err(DavError::Status(StatusCode::NOT_FOUND)),
+            _ => return Err(DavError::Status(StatusCode::FORBIDDEN)),
+        };
+        if !container_acl(access_token, account.id, &container.object.inner).contains(Acl::Modify) {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+
+        let request = XmlElement::parse(body)
+            .map_err(|_| bad_request())?
+            .filter(|request| request.is(&Namespace::Dav, "propertyupdate"))
+            .ok_or_else(bad_request)?;
+
+        // Property updates are atomic, all of them fail if one is rejected
+        let mut changes = Object::with_capacity(2);
+        let mut updated = String::new();
+        let mut failed = String::new();
+        for instruction in &request.children {
+            let is_set = instruction.is(&Namespace::Dav, "set");
+            if !is_set && !instruction.is(&Namespace::Dav, "remove") {
+                continue;
+            }
+            for prop in instruction
+                .children(&Namespace::Dav, "prop")
+                .flat_map(|prop| prop.children.iter())
+            {
+                let Some(name) = &prop.name else {
+                    continue;
+                };
+                if let Some((property, value)) = container_property(kind, prop, is_set) {
+                    changes.set(property, value);
+                    name.write_empty(&mut updated);
+                } else {
+                    name.write_empty(&mut failed);
+                }
+            }
+        }
+
+        let href = account.container_href(kind, container.document_id, &container.object.inner);
+        let mut response = MultiStatus::new();
+        if !failed.is_empty() {
+            response.add_response(
+                &href,
+                &[
+                    (StatusCode::FORBIDDEN, &failed),
+                    (StatusCode::FAILED_DEPENDENCY, &updated),
+                ],
+            );
+            return Ok(response.into_http_response());
+        }
+
+        if !changes.properties.is_empty() {
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(account.id)
+                .with_collection(kind.container)
+                .update_document(container.document_id)
+                .custom(
+                    ObjectIndexBuilder::new(kind.container_schema)
+                        .with_changes(changes)
+                        .with_current(container.object)
+                        .validate()
+                        .map_err(|_| DavError::Status(StatusCode::FORBIDDEN))?,
+                );
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => {}
+                Err(err) if err.is_assertion_failure() => {
+                    return Err(DavError::Status(StatusCode::CONFLICT));
+                }
+                Err(err) => {
+                    return Err(err.caused_by(trc::location!()).into());
+                }
+            }
+
+            let mut changes = ChangeLogBuilder::new();
+            changes.log_update(kind.container, container.document_id);
+            self.dav_commit_changes(account.id, changes, &[kind.container_type])
+                .await?;
+        }
+
+        response.add_response(&href, &[(StatusCode::OK, &updated)]);
+        Ok(response.into_http_response())
+    }
+
+    async fn dav_delete_collection(
+        &self,
+        access_token: &AccessToken,
+        account: DavAccount,
+        kind: &'static GroupwareType,
+        container: DavObject,
+        preconditions: &Preconditions,
+    ) -> DavResult<HttpResponse> {
+        if !container_acl(access_token, account.id, &container.object.inner)
+            .contains_any([Acl::Delete, Acl::Administer].into_iter())
+        {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+        preconditions.validate(Some(&container.etag()))?;
+
+        // Remove the items before deleting the container
+        let mut changes = ChangeLogBuilder::new();
+        for document_id in self
+            .filter(
+                account.id,
+                kind.item,
+                vec![Filter::eq(
+                    kind.container_ids.clone(),
+                    container.document_id,
+                )],
+            )
+            .await?
+            .results
+        {
+            if let Some(object) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account.id,
+                    kind.item,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                self.dav_delete_item(
+                    account.id,
+                    kind,
+                    container.document_id,
+                    DavObject {
+                        document_id,
+                        object,
+                    },
+                    &mut changes,
+                )
+                .await?;
+            }
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account.id)
+            .with_collection(kind.container)
+            .delete_document(container.document_id)
+            .custom(ObjectIndexBuilder::new(kind.container_schema).with_current(container.object));
+        let result = match self.core.storage.data.write(batch.build()).await {
+            Ok(_) => {
+                changes.log_delete(kind.container, container.document_id);
+                Ok(HttpResponse::new_empty(StatusCode::NO_CONTENT))
+            }
+            Err(err) if err.is_assertion_failure() => Err(DavError::Status(StatusCode::CONFLICT)),
+            Err(err) => Err(err.caused_by(trc::location!()).into()),
+        };
+        self.dav_commit_changes(account.id, changes, &[kind.container_type, kind.item_type])
+            .await?;
+
+        result
+    }
+
+    async fn dav_default_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<()> {
+        if self
+            .get_document_ids(account_id, kind.container)
+            .await?
+            .is_none_or(|ids| ids.is_empty())
+        {
+            let mut changes = ChangeLogBuilder::new();
+            match self
+                .dav_create_container(account_id, kind, kind.default_container(), &mut changes)
+                .await
+            {
+                Ok(_) => {}
+                Err(DavError::Internal(err)) => return Err(err),
+                Err(_) => return Ok(()),
+            }
+            self.dav_commit_changes(account_id, changes, &[kind.container_type])
+                .await?;
+        }
+
+        Ok(())
+    }
+}
+
+trait DavCreateContainer: Sync + Send {
+    fn dav_create_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container: Object<Value>,
+        changes: &mut ChangeLogBuilder,
+    ) -> impl Future<Output = DavResult<u32>> + Send;
+}
+
+impl DavCreateContainer for Server {
+    async fn dav_create_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container: Object<Value>,
+        changes: &mut ChangeLogBuilder,
+    ) -> DavResult<u32> {
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(account_id)
+            .with_collection(kind.container)
+            .create_document()
+            .custom(
+                ObjectIndexBuilder::new(kind.container_schema)
+                    .with_changes(container)
+                    .validate()
+                    .map_err(|_| DavError::Status(StatusCode::FORBIDDEN))?,
+            );
+        let document_id = self
+            .core
+            .storage
+            .data
+            .write(batch.build())
+            .await
+            .and_then(|ids| ids.last_document_id())
+            .caused_by(trc::location!())?;
+        changes.log_insert(kind.container, document_id);
+
+        Ok(document_id)
+    }
+}
+
+// Maps a WebDAV collection property to its JMAP counterpart, removals are
+// returned as null values.
+fn container_property(
+    kind: &GroupwareType,
+    prop: &XmlElement,
+    is_set: bool,
+) -> Option<(Property, Value)> {
+    let name = prop.name.as_ref()?;
+    let is_calendar = kind.container == Collection::Calendar;
+    let value = if is_set {
+        Value::Text(prop.text.trim().to_string())
+    } else {
+        Value::Null
+    };
+
+    if name.is(&Namespace::Dav, "displayname") {
+        (is_set && !prop.text.trim().is_empty()).then_some((Property::Name, value))
+    } else if (is_calendar && name.is(&Namespace::CalDav, "calendar-description"))
+        || (!is_calendar && name.is(&Namespace::CardDav, "addressbook-description"))
+    {
+        Some((Property::Description, value))
+    } else if is_calendar && name.is(&Namespace::AppleIcal, "calendar-color") {
+        Some((Property::Color, value))
+    } else {
+        None
+    }
+}
+
+fn bad_request() -> DavError {
+    DavError::Status(StatusCode::BAD_REQUEST)
+}
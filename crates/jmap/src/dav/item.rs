@@ -0,0 +1,406 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::{
+    container_ids, icalendar::parse_icalendar, item_tombstones, object_changes, vcard::parse_vcard,
+    GroupwareType,
+};
+use hyper::{header, StatusCode};
+use jmap_proto::{
+    object::{index::ObjectIndexBuilder, Object},
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use store::{
+    query::Filter,
+    write::{log::ChangeLogBuilder, BatchBuilder},
+};
+use trc::AddContext;
+
+use crate::{api::HttpResponse, JmapMethods};
+
+use super::{
+    collection::DavCollection,
+    container_acl, etag, item_data,
+    xml::{Namespace, XmlName},
+    DavCommit, DavError, DavObject, DavResource, DavResult, Preconditions,
+};
+use std::future::Future;
+
+pub trait DavItemHandler: Sync + Send {
+    fn dav_get(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        is_head: bool,
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_put(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        preconditions: &Preconditions,
+        body: Vec<u8>,
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_delete(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        preconditions: &Preconditions,
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+}
+
+impl DavItemHandler for Server {
+    async fn dav_get(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        is_head: bool,
+    ) -> DavResult<HttpResponse> {
+        match resource {
+            DavResource::Item {
+                account,
+                kind,
+                container,
+                item: Some(item),
+                ..
+            } => {
+                if !container_acl(access_token, account.id, &container.object.inner)
+                    .contains(Acl::ReadItems)
+                {
+                    return Err(DavError::Status(StatusCode::FORBIDDEN));
+                }
+
+                let data = item_data(kind, &item.object.inner);
+                let response = if is_head {
+                    HttpResponse::new_empty(StatusCode::OK)
+                        .with_header(header::CONTENT_TYPE, kind.dav_content_type)
+                        .with_header(header::CONTENT_LENGTH, data.len().to_string())
+                } else {
+                    HttpResponse::new_text(StatusCode::OK, kind.dav_content_type, data.into_owned())
+                };
+
+                Ok(response.with_header(header::ETAG, item.etag()))
+            }
+            DavResource::Item { item: None, .. }
+            | DavResource::Collection {
+                container: None, ..
+            } => Err(DavError::Status(StatusCode::NOT_FOUND)),
+            _ => Err(DavError::Status(StatusCode::METHOD_NOT_ALLOWED)),
+        }
+    }
+
+    async fn dav_put(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        preconditions: &Preconditions,
+        body: Vec<u8>,
+    ) -> DavResult<HttpResponse> {
+        let (account, kind, container, name, current) = match resource {
+            DavResource::Item {
+                account,
+                kind,
+                container,
+                name,
+                item,
+            } => (account, kind, container, name, item),
+            DavResource::Collection {
+                container: None, ..
+            } => {
+                return Err(DavError::Status(StatusCode::CONFLICT));
+            }
+            _ => return Err(DavError::Status(StatusCode::METHOD_NOT_ALLOWED)),
+        };
+
+        // Validate ACLs and preconditions
+        let acl = container_acl(access_token, account.id, &container.object.inner);
+        if !acl.contains(if current.is_some() {
+            Acl::ModifyItems
+        } else {
+            Acl::AddItems
+        }) {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+        preconditions.validate(current.as_ref().map(|item| item.etag()).as_deref())?;
+
+        // Parse vCard or iCalendar data
+        let text = String::from_utf8(body).map_err(|_| invalid_data(kind))?;
+        let mut item = if kind.item == Collection::ContactCard {
+            parse_vcard(&text)
+        } else {
+            parse_icalendar(&text)
+        }
+        .map_err(|_| invalid_data(kind))?;
+
+        // Items shared across containers keep their memberships on update
+        let container_ids = current
+            .as_ref()
+            .map(|current| container_ids(&current.object.inner, kind))
+            .unwrap_or_else(|| vec![container.document_id]);
+        item.set(
+            kind.container_ids.clone(),
+            Value::List(
+                container_ids
+                    .into_iter()
+                    .map(|id| Value::Id(id.into()))
+                    .collect(),
+            ),
+        );
+        if item.get(&Property::Uid) == &Value::Null {
+            item.set(
+                Property::Uid,
+                Value::Text(format!("{:032x}", rand::random::<u128>())),
+            );
+        }
+        item.set(Property::DavName, name);
+        item.set(Property::DavData, text);
+        kind.derive_properties(&mut item);
+
+        // Verify that the uid is unique
+        if let Value::Text(uid) = item.get(&Property::Uid) {
+            if self
+                .filter(
+                    account.id,
+                    kind.item,
+                    vec![Filter::eq(Property::Uid, uid.as_str())],
+                )
+                .await?
+                .results
+                .iter()
+                .any(|document_id| {
+                    current
+                        .as_ref()
+                        .is_none_or(|current| current.document_id != document_id)
+                })
+            {
+                return Err(DavError::Condition(
+                    StatusCode::FORBIDDEN,
+                    XmlName::new(kind_namespace(kind), "no-uid-conflict"),
+                ));
+            }
+        }
+
+        // Write item
+        let mut changes = ChangeLogBuilder::new();
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account.id).with_collection(kind.item);
+        let (status, stored) = if let Some(current) = current {
+            let document_id = current.document_id;
+            let item_changes = object_changes(&current.object.inner, item);
+
+            // Mirror the merge performed by the index builder to obtain the new ETag
+            let mut stored = current.object.inner.clone();
+            for (property, value) in &item_changes.properties {
+                if value != &Value::Null {
+                    stored.set(property.clone(), value.clone());
+                } else {
+                    stored.remove(property);
+                }
+            }
+
+            if !item_changes.properties.is_empty() {
+                batch.update_document(document_id).custom(
+                    ObjectIndexBuilder::new(kind.item_schema)
+                        .with_changes(item_changes)
+                        .with_current(current.object)
+                        .validate()
+                        .map_err(|_| DavError::Status(StatusCode::BAD_REQUEST))?,
+                );
+                match self.core.storage.data.write(batch.build()).await {
+                    Ok(_) => {
+                        changes.log_update(kind.item, document_id);
+                    }
+                    Err(err) if err.is_assertion_failure() => {
+                        return Err(DavError::Status(StatusCode::PRECONDITION_FAILED));
+                    }
+                    Err(err) => {
+                        return Err(err.caused_by(trc::location!()).into());
+                    }
+                }
+            }
+
+            (StatusCode::NO_CONTENT, stored)
+        } else {
+            batch.create_document().custom(
+                ObjectIndexBuilder::new(kind.item_schema)
+                    .with_changes(item.clone())
+                    .validate()
+                    .map_err(|_| DavError::Status(StatusCode::BAD_REQUEST))?,
+            );
+            let document_id = self
+                .core
+                .storage
+                .data
+                .write(batch.build())
+                .await
+                .and_then(|ids| ids.last_document_id())
+                .caused_by(trc::location!())?;
+            changes.log_insert(kind.item, document_id);
+
+            (StatusCode::CREATED, item)
+        };
+
+        self.dav_commit_changes(account.id, changes, &[kind.item_type])
+            .await?;
+
+        Ok(HttpResponse::new_empty(status).with_header(header::ETAG, etag(&stored)))
+    }
+
+    async fn dav_delete(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        preconditions: &Preconditions,
+    ) -> DavResult<HttpResponse> {
+        let (account, kind, container, item) = match resource {
+            DavResource::Item {
+                account,
+                kind,
+                container,
+                item: Some(item),
+                ..
+            } => (account, kind, container, item),
+            DavResource::Collection {
+                account,
+                kind,
+                container: Some(container),
+                ..
+            } => {
+                return self
+                    .dav_delete_collection(access_token, account, kind, container, preconditions)
+                    .await;
+            }
+            DavResource::Item { item: None, .. }
+            | DavResource::Collection {
+                container: None, ..
+            } => {
+                return Err(DavError::Status(StatusCode::NOT_FOUND));
+            }
+            _ => return Err(DavError::Status(StatusCode::METHOD_NOT_ALLOWED)),
+        };
+
+        // Validate ACLs and preconditions
+        if !container_acl(access_token, account.id, &container.object.inner)
+            .contains(Acl::RemoveItems)
+        {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+        preconditions.validate(Some(&item.etag()))?;
+
+        let mut changes = ChangeLogBuilder::new();
+        self.dav_delete_item(account.id, kind, container.document_id, item, &mut changes)
+            .await?;
+        self.dav_commit_changes(account.id, changes, &[kind.item_type])
+            .await?;
+
+        Ok(HttpResponse::new_empty(StatusCode::NO_CONTENT))
+    }
+}
+
+pub(super) trait DavItemDelete: Sync + Send {
+    fn dav_delete_item(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+        item: DavObject,
+        changes: &mut ChangeLogBuilder,
+    ) -> impl Future<Output = DavResult<()>> + Send;
+}
+
+impl DavItemDelete for Server {
+    // Items belonging to several containers are only removed from this one
+    async fn dav_delete_item(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+        item: DavObject,
+        changes: &mut ChangeLogBuilder,
+    ) -> DavResult<()> {
+        let document_id = item.document_id;
+        let container_ids = container_ids(&item.object.inner, kind)
+            .into_iter()
+            .filter(|id| *id != container_id)
+            .collect::<Vec<_>>();
+        let mut batch = BatchBuilder::new();
+        batch.with_account_id(account_id).with_collection(kind.item);
+
+        let is_delete = container_ids.is_empty();
+        if is_delete {
+            let tombstones = item_tombstones(
+                kind,
+                &item.object.inner,
+                self.get_property::<Value>(
+                    account_id,
+                    kind.item,
+                    document_id,
+                    Property::DavTombstones,
+                )
+                .await?,
+            );
+            batch
+                .delete_document(document_id)
+                .custom(ObjectIndexBuilder::new(kind.item_schema).with_current(item.object))
+                .value(Property::DavTombstones, tombstones, 0);
+        } else {
+            batch.update_document(document_id).custom(
+                ObjectIndexBuilder::new(kind.item_schema)
+                    .with_changes(
+                        Object::with_capacity(1).with_property(
+                            kind.container_ids.clone(),
+                            Value::List(
+                                container_ids
+                                    .into_iter()
+                                    .map(|id| Value::Id(id.into()))
+                                    .collect(),
+                            ),
+                        ),
+                    )
+                    .with_current(item.object),
+            );
+        }
+
+        match self.core.storage.data.write(batch.build()).await {
+            Ok(_) => {
+                if is_delete {
+                    changes.log_delete(kind.item, document_id);
+                } else {
+                    changes.log_update(kind.item, document_id);
+                }
+                Ok(())
+            }
+            Err(err) if err.is_assertion_failure() => {
+                Err(DavError::Status(StatusCode::PRECONDITION_FAILED))
+            }
+            Err(err) => Err(err.caused_by(trc::location!()).into()),
+        }
+    }
+}
+
+fn kind_namespace(kind: &GroupwareType) -> Namespace {
+    if kind.item == Collection::ContactCard {
+        Namespace::CardDav
+    } else {
+        Namespace::CalDav
+    }
+}
+
+fn invalid_data(kind: &GroupwareType) -> DavError {
+    DavError::Condition(
+        StatusCode::FORBIDDEN,
+        XmlName::new(
+            kind_namespace(kind),
+            if kind.item == Collection::ContactCard {
+                "valid-address-data"
+            } else {
+                "valid-calendar-data"
+            },
+        ),
+    )
+}
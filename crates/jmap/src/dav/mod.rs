@@ -0,0 +1,663 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{borrow::Cow, sync::Arc};
+
+use common::{auth::AccessToken, Server};
+use directory::{Permission, QueryBy};
+use email::groupware::{
+    icalendar::build_icalendar, vcard::build_vcard, GroupwareType, CALENDARS, CONTACTS,
+};
+use hyper::{
+    header::{self, HeaderName},
+    Method, StatusCode,
+};
+use jmap_proto::{
+    object::Object,
+    types::{
+        acl::Acl, collection::Collection, id::Id, property::Property, state::StateChange,
+        type_state::DataType, value::Value,
+    },
+};
+use store::{
+    query::Filter,
+    write::{assert::HashedValue, log::ChangeLogBuilder},
+    xxhash_rust::xxh3::xxh3_64,
+    Serialize,
+};
+use trc::DavEvent;
+use utils::map::bitmap::Bitmap;
+
+use crate::{
+    api::{
+        http::{fetch_body, HttpSessionData, ToHttpResponse},
+        HttpRequest, HttpResponse,
+    },
+    auth::acl::EffectiveAcl,
+    JmapMethods,
+};
+
+use self::{
+    collection::DavCollection,
+    item::DavItemHandler,
+    propfind::DavPropFind,
+    report::DavReport,
+    xml::{error_response, XmlName},
+};
+use std::future::Future;
+
+pub mod collection;
+pub mod item;
+pub mod propfind;
+pub mod report;
+pub mod xml;
+
+pub const DAV_CAPABILITIES: &str =
+    "1, 3, access-control, addressbook, calendar-access, extended-mkcol";
+pub const DAV_METHODS: &str =
+    "OPTIONS, GET, HEAD, PUT, DELETE, PROPFIND, PROPPATCH, REPORT, MKCOL, MKCALENDAR";
+
+pub enum DavResource {
+    Root(Option<&'static GroupwareType>),
+    Principals,
+    Principal(DavAccount),
+    Home {
+        account: DavAccount,
+        kind: &'static GroupwareType,
+    },
+    Collection {
+        account: DavAccount,
+        kind: &'static GroupwareType,
+        name: String,
+        container: Option<DavObject>,
+    },
+    Item {
+        account: DavAccount,
+        kind: &'static GroupwareType,
+        container: DavObject,
+        name: String,
+        item: Option<DavObject>,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub struct DavAccount {
+    pub id: u32,
+    pub name: String,
+}
+
+pub struct DavObject {
+    pub document_id: u32,
+    pub object: HashedValue<Object<Value>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Depth {
+    Zero,
+    One,
+    Infinity,
+}
+
+#[derive(Debug, Default)]
+pub struct Preconditions {
+    pub if_match: Option<String>,
+    pub if_none_match: Option<String>,
+}
+
+pub enum DavError {
+    Status(StatusCode),
+    Condition(StatusCode, XmlName),
+    Internal(trc::Error),
+}
+
+pub type DavResult<T> = Result<T, DavError>;
+
+pub trait DavRequestHandler: Sync + Send {
+    fn handle_dav_request(
+        &self,
+        req: &mut HttpRequest,
+        access_token: Arc<AccessToken>,
+        session: &HttpSessionData,
+    ) -> impl Future<Output = trc::Result<HttpResponse>> + Send;
+
+    fn dav_resource(
+        &self,
+        access_token: &AccessToken,
+        path: &str,
+    ) -> impl Future<Output = DavResult<DavResource>> + Send;
+
+    fn dav_account(
+        &self,
+        access_token: &AccessToken,
+        name: &str,
+    ) -> impl Future<Output = DavResult<DavAccount>> + Send;
+
+    fn dav_account_name(&self, account_id: u32)
+        -> impl Future<Output = trc::Result<String>> + Send;
+
+    fn dav_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        name: &str,
+    ) -> impl Future<Output = trc::Result<Option<DavObject>>> + Send;
+
+    fn dav_item(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+        name: &str,
+    ) -> impl Future<Output = trc::Result<Option<DavObject>>> + Send;
+}
+
+impl DavRequestHandler for Server {
+    async fn handle_dav_request(
+        &self,
+        req: &mut HttpRequest,
+        access_token: Arc<AccessToken>,
+        session: &HttpSessionData,
+    ) -> trc::Result<HttpResponse> {
+        // Validate method and permissions
+        let method = req.method().clone();
+        let (event, permission) = match method.as_str() {
+            "PROPFIND" => (DavEvent::Propfind, Permission::DavPropFind),
+            "PROPPATCH" => (DavEvent::Proppatch, Permission::DavPropPatch),
+            "REPORT" => (DavEvent::Report, Permission::DavReport),
+            "GET" | "HEAD" => (DavEvent::Get, Permission::DavGet),
+            "PUT" => (DavEvent::Put, Permission::DavPut),
+            "DELETE" => (DavEvent::Delete, Permission::DavDelete),
+            "MKCOL" | "MKCALENDAR" => (DavEvent::Mkcol, Permission::DavMkCol),
+            _ => {
+                return Ok(HttpResponse::new_empty(StatusCode::METHOD_NOT_ALLOWED)
+                    .with_header(header::ALLOW, DAV_METHODS));
+            }
+        };
+        access_token.assert_has_permission(permission)?;
+
+        // Parse headers
+        let path = req.uri().path().to_string();
+        let header = |name: HeaderName| {
+            req.headers()
+                .get(name)
+                .and_then(|h| h.to_str().ok())
+                .map(|h| h.trim().to_string())
+        };
+        let depth = match header(HeaderName::from_static("depth")).as_deref() {
+            Some("0") => Depth::Zero,
+            Some("1") => Depth::One,
+            Some(_) => Depth::Infinity,
+            None if method.as_str() == "PROPFIND" => Depth::Infinity,
+            None => Depth::Zero,
+        };
+        let preconditions = Preconditions {
+            if_match: header(header::IF_MATCH),
+            if_none_match: header(header::IF_NONE_MATCH),
+        };
+
+        // Fetch request body
+        let body = if !matches!(method, Method::GET | Method::HEAD | Method::DELETE) {
+            fetch_body(
+                req,
+                if !access_token.has_permission(Permission::UnlimitedUploads) {
+                    self.core.jmap.upload_max_size
+                } else {
+                    0
+                },
+                session.session_id,
+            )
+            .await
+            .ok_or_else(|| trc::LimitEvent::SizeRequest.into_err())?
+        } else {
+            vec![]
+        };
+
+        let result = match self.dav_resource(&access_token, &path).await {
+            Ok(resource) => match method.as_str() {
+                "PROPFIND" => {
+                    self.dav_propfind(&access_token, resource, depth, &body)
+                        .await
+                }
+                "PROPPATCH" => self.dav_proppatch(&access_token, resource, &body).await,
+                "REPORT" => self.dav_report(&access_token, resource, depth, &body).await,
+                "GET" | "HEAD" => {
+                    self.dav_get(&access_token, resource, method == Method::HEAD)
+                        .await
+                }
+                "PUT" => {
+                    self.dav_put(&access_token, resource, &preconditions, body)
+                        .await
+                }
+                "DELETE" => {
+                    self.dav_delete(&access_token, resource, &preconditions)
+                        .await
+                }
+                _ => {
+                    self.dav_mkcol(
+                        &access_token,
+                        resource,
+                        method.as_str() == "MKCALENDAR",
+                        &body,
+                    )
+                    .await
+                }
+            },
+            Err(err) => Err(err),
+        };
+
+        match result {
+            Ok(response) => {
+                trc::event!(
+                    Dav(event),
+                    SpanId = session.session_id,
+                    AccountId = access_token.primary_id(),
+                    Url = path,
+                    Code = response.status.as_u16(),
+                );
+
+                Ok(response)
+            }
+            Err(DavError::Internal(err)) => Err(err),
+            Err(err) => {
+                let response = err.into_http_response();
+
+                trc::event!(
+                    Dav(DavEvent::Error),
+                    SpanId = session.session_id,
+                    AccountId = access_token.primary_id(),
+                    Url = path,
+                    Code = response.status.as_u16(),
+                );
+
+                Ok(response)
+            }
+        }
+    }
+
+    async fn dav_resource(&self, access_token: &AccessToken, path: &str) -> DavResult<DavResource> {
+        let mut path = path
+            .strip_prefix("/dav")
+            .unwrap_or(path)
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(decode_path_segment);
+
+        let kind = match path.next().as_deref() {
+            None => return Ok(DavResource::Root(None)),
+            Some("card") => &CONTACTS,
+            Some("cal") => &CALENDARS,
+            Some("principal") => {
+                return match (path.next(), path.next()) {
+                    (None, _) => Ok(DavResource::Principals),
+                    (Some(name), None) => {
+                        let account = self.dav_account(access_token, &name).await?;
+                        if access_token.is_member(account.id) || access_token.is_shared(account.id)
+                        {
+                            Ok(DavResource::Principal(account))
+                        } else {
+                            Err(DavError::Status(StatusCode::FORBIDDEN))
+                        }
+                    }
+                    _ => Err(DavError::Status(StatusCode::NOT_FOUND)),
+                };
+            }
+            Some(_) => return Err(DavError::Status(StatusCode::NOT_FOUND)),
+        };
+
+        // Obtain account
+        let account = if let Some(name) = path.next() {
+            self.dav_account(access_token, &name).await?
+        } else {
+            return Ok(DavResource::Root(Some(kind)));
+        };
+        if !access_token.has_access(account.id, kind.container) {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+
+        // Obtain container
+        let name = if let Some(name) = path.next() {
+            name
+        } else {
+            return Ok(DavResource::Home { account, kind });
+        };
+        let container = self.dav_container(account.id, kind, &name).await?;
+
+        // Obtain item
+        match (path.next(), container) {
+            (None, container) => Ok(DavResource::Collection {
+                account,
+                kind,
+                name,
+                container,
+            }),
+            (Some(name), Some(container)) if path.next().is_none() => {
+                let item = self
+                    .dav_item(account.id, kind, container.document_id, &name)
+                    .await?;
+                Ok(DavResource::Item {
+                    account,
+                    kind,
+                    container,
+                    name,
+                    item,
+                })
+            }
+            _ => Err(DavError::Status(StatusCode::NOT_FOUND)),
+        }
+    }
+
+    async fn dav_account(&self, access_token: &AccessToken, name: &str) -> DavResult<DavAccount> {
+        if name == access_token.name {
+            Ok(DavAccount {
+                id: access_token.primary_id(),
+                name: name.to_string(),
+            })
+        } else {
+            self.core
+                .storage
+                .directory
+                .query(QueryBy::Name(name), false)
+                .await?
+                .map(|principal| DavAccount {
+                    id: principal.id(),
+                    name: name.to_string(),
+                })
+                .ok_or(DavError::Status(StatusCode::NOT_FOUND))
+        }
+    }
+
+    async fn dav_account_name(&self, account_id: u32) -> trc::Result<String> {
+        Ok(self
+            .core
+            .storage
+            .directory
+            .query(QueryBy::Id(account_id), false)
+            .await?
+            .map(|principal| principal.name().to_string())
+            .unwrap_or_else(|| Id::from(account_id).to_string()))
+    }
+
+    async fn dav_container(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        name: &str,
+    ) -> trc::Result<Option<DavObject>> {
+        // Containers created through JMAP are addressed by their id
+        let document_id = if let Some(document_id) = self
+            .filter(
+                account_id,
+                kind.container,
+                vec![Filter::eq(Property::DavName, name)],
+            )
+            .await?
+            .results
+            .min()
+        {
+            document_id
+        } else if let Some(id) = Id::from_bytes(name.as_bytes()) {
+            id.document_id()
+        } else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get_property::<HashedValue<Object<Value>>>(
+                account_id,
+                kind.container,
+                document_id,
+                Property::Value,
+            )
+            .await?
+            .map(|object| DavObject {
+                document_id,
+                object,
+            }))
+    }
+
+    async fn dav_item(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+        name: &str,
+    ) -> trc::Result<Option<DavObject>> {
+        // Items created through JMAP are addressed by their id and extension
+        let document_id = if let Some(document_id) = self
+            .filter(
+                account_id,
+                kind.item,
+                vec![
+                    Filter::eq(Property::DavName, name),
+                    Filter::eq(kind.container_ids.clone(), container_id),
+                ],
+            )
+            .await?
+            .results
+            .min()
+        {
+            document_id
+        } else if let Some(id) = name
+            .strip_suffix(kind.dav_extension)
+            .and_then(|name| name.strip_suffix('.'))
+            .and_then(|name| Id::from_bytes(name.as_bytes()))
+        {
+            id.document_id()
+        } else {
+            return Ok(None);
+        };
+
+        Ok(self
+            .get_property::<HashedValue<Object<Value>>>(
+                account_id,
+                kind.item,
+                document_id,
+                Property::Value,
+            )
+            .await?
+            .filter(|object| {
+                object
+                    .inner
+                    .get(&kind.container_ids)
+                    .as_list()
+                    .is_some_and(|ids| ids.contains(&Value::Id(container_id.into())))
+            })
+            .map(|object| DavObject {
+                document_id,
+                object,
+            }))
+    }
+}
+
+pub(super) trait DavCommit: Sync + Send {
+    fn dav_commit_changes(
+        &self,
+        account_id: u32,
+        changes: ChangeLogBuilder,
+        data_types: &[DataType],
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl DavCommit for Server {
+    async fn dav_commit_changes(
+        &self,
+        account_id: u32,
+        changes: ChangeLogBuilder,
+        data_types: &[DataType],
+    ) -> trc::Result<()> {
+        if !changes.is_empty() {
+            let change_id = self.commit_changes(account_id, changes).await?;
+            let mut state_change = StateChange::new(account_id);
+            for data_type in data_types {
+                state_change = state_change.with_change(*data_type, change_id);
+            }
+            self.broadcast_state_change(state_change).await;
+        }
+
+        Ok(())
+    }
+}
+
+impl DavObject {
+    pub fn etag(&self) -> String {
+        etag(&self.object.inner)
+    }
+}
+
+impl DavAccount {
+    pub fn principal_href(&self) -> String {
+        principal_href(&self.name)
+    }
+
+    pub fn home_href(&self, kind: &GroupwareType) -> String {
+        format!(
+            "/dav/{}/{}/",
+            kind.dav_path,
+            encode_path_segment(&self.name)
+        )
+    }
+
+    pub fn container_href(
+        &self,
+        kind: &GroupwareType,
+        document_id: u32,
+        container: &Object<Value>,
+    ) -> String {
+        let mut href = self.home_href(kind);
+        if let Some(name) = container.get(&Property::DavName).as_string() {
+            href.push_str(&encode_path_segment(name));
+        } else {
+            href.push_str(&Id::from(document_id).to_string());
+        }
+        href.push('/');
+        href
+    }
+}
+
+impl Preconditions {
+    pub fn validate(&self, current_etag: Option<&str>) -> DavResult<()> {
+        let matches = |header: &str| {
+            header.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" && current_etag.is_some()
+                    || current_etag
+                        .is_some_and(|etag| tag.strip_prefix("W/").unwrap_or(tag) == etag)
+            })
+        };
+
+        if self.if_match.as_deref().is_some_and(|h| !matches(h))
+            || self.if_none_match.as_deref().is_some_and(matches)
+        {
+            Err(DavError::Status(StatusCode::PRECONDITION_FAILED))
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl From<trc::Error> for DavError {
+    fn from(err: trc::Error) -> Self {
+        DavError::Internal(err)
+    }
+}
+
+impl ToHttpResponse for DavError {
+    fn into_http_response(self) -> HttpResponse {
+        match self {
+            DavError::Status(status) => HttpResponse::new_empty(status),
+            DavError::Condition(status, condition) => error_response(status, &condition),
+            DavError::Internal(err) => (&err).into_http_response(),
+        }
+    }
+}
+
+pub fn dav_options_response() -> HttpResponse {
+    HttpResponse::new_empty(StatusCode::OK)
+        .with_header(HeaderName::from_static("dav"), DAV_CAPABILITIES)
+        .with_header(header::ALLOW, DAV_METHODS)
+}
+
+pub fn principal_href(name: &str) -> String {
+    format!("/dav/principal/{}/", encode_path_segment(name))
+}
+
+pub fn item_href(
+    container_href: &str,
+    kind: &GroupwareType,
+    document_id: u32,
+    item: &Object<Value>,
+) -> String {
+    if let Some(name) = item.get(&Property::DavName).as_string() {
+        format!("{container_href}{}", encode_path_segment(name))
+    } else {
+        format!(
+            "{container_href}{}.{}",
+            Id::from(document_id),
+            kind.dav_extension
+        )
+    }
+}
+
+pub fn etag(object: &Object<Value>) -> String {
+    format!("\"{:x}\"", xxh3_64(&object.serialize()))
+}
+
+// The original vCard or iCalendar data is returned unless modified through JMAP
+pub fn item_data<'x>(kind: &GroupwareType, item: &'x Object<Value>) -> Cow<'x, str> {
+    if let Some(data) = item.get(&Property::DavData).as_string() {
+        Cow::Borrowed(data)
+    } else if kind.item == Collection::ContactCard {
+        Cow::Owned(build_vcard(item))
+    } else {
+        Cow::Owned(build_icalendar(item))
+    }
+}
+
+pub fn container_acl(
+    access_token: &AccessToken,
+    account_id: u32,
+    container: &Object<Value>,
+) -> Bitmap<Acl> {
+    if access_token.is_member(account_id) {
+        Bitmap::all()
+    } else {
+        container.effective_acl(access_token)
+    }
+}
+
+pub fn decode_path_segment(segment: &str) -> String {
+    let mut bytes = Vec::with_capacity(segment.len());
+    let mut iter = segment.as_bytes().iter();
+    while let Some(&ch) = iter.next() {
+        if ch == b'%' {
+            let hex = iter.as_slice().get(..2).and_then(|hex| {
+                std::str::from_utf8(hex)
+                    .ok()
+                    .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+            });
+            if let Some(value) = hex {
+                bytes.push(value);
+                iter.nth(1);
+                continue;
+            }
+        }
+        bytes.push(ch);
+    }
+    String::from_utf8(bytes)
+        .unwrap_or_else(|err| String::from_utf8_lossy(err.as_bytes()).into_owned())
+}
+
+pub fn encode_path_segment(segment: &str) -> String {
+    let mut result = String::with_capacity(segment.len());
+    for &ch in segment.as_bytes() {
+        if ch.is_ascii_alphanumeric() || b"-._~@+".contains(&ch) {
+            result.push(ch as char);
+        } else {
+            result.push_str(&format!("%{ch:02X}"));
+        }
+    }
+    result
+}
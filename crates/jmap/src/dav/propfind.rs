@@ -0,0 +1,723 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::Write;
+
+use common::{auth::AccessToken, Server};
+use email::groupware::{GroupwareType, CALENDARS, CONTACTS};
+use hyper::StatusCode;
+use jmap_proto::{
+    object::Object,
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use quick_xml::escape::escape;
+use store::{query::Filter, write::assert::HashedValue};
+use utils::map::bitmap::Bitmap;
+
+use crate::{api::HttpResponse, auth::acl::AclMethods, JmapMethods};
+
+use super::{
+    collection::DavCollection,
+    container_acl, item_data, item_href, principal_href,
+    xml::{MultiStatus, Namespace, XmlElement, XmlName},
+    DavAccount, DavError, DavObject, DavRequestHandler, DavResource, DavResult, Depth,
+};
+use std::future::Future;
+
+pub enum PropRequest {
+    AllProp,
+    PropName,
+    Prop(Vec<XmlName>),
+}
+
+pub enum DavEntry<'x> {
+    Root(Option<&'static GroupwareType>),
+    Principals,
+    Principal(&'x DavAccount),
+    Home(&'x DavAccount, &'static GroupwareType),
+    Container {
+        account: &'x DavAccount,
+        kind: &'static GroupwareType,
+        container: &'x DavObject,
+        acl: Bitmap<Acl>,
+        sync_token: &'x str,
+    },
+    Item {
+        account: &'x DavAccount,
+        kind: &'static GroupwareType,
+        href: String,
+        item: &'x DavObject,
+        acl: Bitmap<Acl>,
+    },
+}
+
+// Properties returned by allprop and propname requests, the address and
+// calendar data are only returned when explicitly requested.
+static ALL_PROPERTIES: &[(Namespace, &str)] = &[
+    (Namespace::Dav, "resourcetype"),
+    (Namespace::Dav, "displayname"),
+    (Namespace::Dav, "getetag"),
+    (Namespace::Dav, "getcontenttype"),
+    (Namespace::Dav, "getcontentlength"),
+    (Namespace::Dav, "current-user-principal"),
+    (Namespace::Dav, "principal-URL"),
+    (Namespace::Dav, "principal-collection-set"),
+    (Namespace::Dav, "owner"),
+    (Namespace::Dav, "sync-token"),
+    (Namespace::Dav, "supported-report-set"),
+    (Namespace::Dav, "current-user-privilege-set"),
+    (Namespace::CalendarServer, "getctag"),
+    (Namespace::CardDav, "addressbook-home-set"),
+    (Namespace::CardDav, "addressbook-description"),
+    (Namespace::CardDav, "supported-address-data"),
+    (Namespace::CalDav, "calendar-home-set"),
+    (Namespace::CalDav, "calendar-user-address-set"),
+    (Namespace::CalDav, "calendar-description"),
+    (Namespace::CalDav, "supported-calendar-component-set"),
+    (Namespace::CalDav, "supported-calendar-data"),
+    (Namespace::AppleIcal, "calendar-color"),
+];
+
+pub trait DavPropFind: Sync + Send {
+    fn dav_propfind(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        depth: Depth,
+        body: &[u8],
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_sync_token(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<String>> + Send;
+
+    fn dav_container_items(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+    ) -> impl Future<Output = trc::Result<Vec<DavObject>>> + Send;
+}
+
+impl DavPropFind for Server {
+    async fn dav_propfind(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        depth: Depth,
+        body: &[u8],
+    ) -> DavResult<HttpResponse> {
+        let request = PropRequest::parse(body)?;
+        let mut response = MultiStatus::new();
+        let with_children = depth != Depth::Zero;
+
+        match resource {
+            DavResource::Root(kind) => {
+                DavEntry::Root(kind).write(access_token, &request, &mut response);
+                if with_children {
+                    if let Some(kind) = kind {
+                        // List the homes of all accessible accounts
+                        let mut account_ids = vec![access_token.primary_id()];
+                        for account_id in access_token.shared_accounts(kind.container) {
+                            if !account_ids.contains(account_id) {
+                                account_ids.push(*account_id);
+                            }
+                        }
+                        for account_id in account_ids {
+                            let account = DavAccount {
+                                id: account_id,
+                                name: self.dav_account_name(account_id).await?,
+                            };
+                            DavEntry::Home(&account, kind).write(
+                                access_token,
+                                &request,
+                                &mut response,
+                            );
+                        }
+                    } else {
+                        for kind in [&CONTACTS, &CALENDARS] {
+                            DavEntry::Root(Some(kind)).write(access_token, &request, &mut response);
+                        }
+                        DavEntry::Principals.write(access_token, &request, &mut response);
+                    }
+                }
+            }
+            DavResource::Principals => {
+                DavEntry::Principals.write(access_token, &request, &mut response);
+                if with_children {
+                    let account = DavAccount {
+                        id: access_token.primary_id(),
+                        name: access_token.name.clone(),
+                    };
+                    DavEntry::Principal(&account).write(access_token, &request, &mut response);
+                }
+            }
+            DavResource::Principal(account) => {
+                DavEntry::Principal(&account).write(access_token, &request, &mut response);
+            }
+            DavResource::Home { account, kind } => {
+                DavEntry::Home(&account, kind).write(access_token, &request, &mut response);
+                if with_children {
+                    if access_token.is_member(account.id) {
+                        self.dav_default_container(account.id, kind).await?;
+                    }
+                    let sync_token = self.dav_sync_token(account.id, kind).await?;
+                    for document_id in self
+                        .owned_or_shared_documents(
+                            access_token,
+                            account.id,
+                            kind.container,
+                            Acl::Read,
+                        )
+                        .await?
+                    {
+                        if let Some(object) = self
+                            .get_property::<HashedValue<Object<Value>>>(
+                                account.id,
+                                kind.container,
+                                document_id,
+                                Property::Value,
+                            )
+                            .await?
+                        {
+                            let container = DavObject {
+                                document_id,
+                                object,
+                            };
+                            DavEntry::Container {
+                                account: &account,
+                                kind,
+                                acl: container_acl(
+                                    access_token,
+                                    account.id,
+                                    &container.object.inner,
+                                ),
+                                container: &container,
+                                sync_token: &sync_token,
+                            }
+                            .write(
+                                access_token,
+                                &request,
+                                &mut response,
+                            );
+                        }
+                    }
+                }
+            }
+            DavResource::Collection {
+                account,
+                kind,
+                container: Some(container),
+                ..
+            } => {
+                let acl = container_acl(access_token, account.id, &container.object.inner);
+                if !acl.contains_any([Acl::Read, Acl::ReadItems].into_iter()) {
+                    return Err(DavError::Status(StatusCode::FORBIDDEN));
+                }
+                let sync_token = self.dav_sync_token(account.id, kind).await?;
+                DavEntry::Container {
+                    account: &account,
+                    kind,
+                    container: &container,
+                    acl,
+                    sync_token: &sync_token,
+                }
+                .write(access_token, &request, &mut response);
+
+                if with_children && acl.contains(Acl::ReadItems) {
+                    let container_href = account.container_href(
+                        kind,
+                        container.document_id,
+                        &container.object.inner,
+                    );
+                    for item in self
+                        .dav_container_items(account.id, kind, container.document_id)
+                        .await?
+                    {
+                        DavEntry::Item {
+                            account: &account,
+                            kind,
+                            href: item_href(
+                                &container_href,
+                                kind,
+                                item.document_id,
+                                &item.object.inner,
+                            ),
+                            item: &item,
+                            acl,
+                        }
+                        .write(access_token, &request, &mut response);
+                    }
+                }
+            }
+            DavResource::Item {
+                account,
+                kind,
+                container,
+                item: Some(item),
+                ..
+            } => {
+                let acl = container_acl(access_token, account.id, &container.object.inner);
+                if !acl.contains(Acl::ReadItems) {
+                    return Err(DavError::Status(StatusCode::FORBIDDEN));
+                }
+                let container_href =
+                    account.container_href(kind, container.document_id, &container.object.inner);
+                DavEntry::Item {
+                    account: &account,
+                    kind,
+                    href: item_href(&container_href, kind, item.document_id, &item.object.inner),
+                    item: &item,
+                    acl,
+                }
+                .write(access_token, &request, &mut response);
+            }
+            DavResource::Collection {
+                container: None, ..
+            }
+            | DavResource::Item { item: None, .. } => {
+                return Err(DavError::Status(StatusCode::NOT_FOUND));
+            }
+        }
+
+        Ok(response.into_http_response())
+    }
+
+    // Change ids are globally ordered, the token covers both containers and items
+    async fn dav_sync_token(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<String> {
+        let mut change_id = 0;
+        for collection in [kind.container, kind.item] {
+            if let Some(last_change_id) = self
+                .core
+                .storage
+                .data
+                .get_last_change_id(account_id, collection)
+                .await?
+            {
+                change_id = change_id.max(last_change_id);
+            }
+        }
+
+        Ok(format!("urn:stalwart:sync:{change_id}"))
+    }
+
+    async fn dav_container_items(
+        &self,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        container_id: u32,
+    ) -> trc::Result<Vec<DavObject>> {
+        let mut items = Vec::new();
+        for document_id in self
+            .filter(
+                account_id,
+                kind.item,
+                vec![Filter::eq(kind.container_ids.clone(), container_id)],
+            )
+            .await?
+            .results
+        {
+            if let Some(object) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    kind.item,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                items.push(DavObject {
+                    document_id,
+                    object,
+                });
+            }
+        }
+
+        Ok(items)
+    }
+}
+
+impl PropRequest {
+    pub fn parse(body: &[u8]) -> DavResult<Self> {
+        let Some(request) =
+            XmlElement::parse(body).map_err(|_| DavError::Status(StatusCode::BAD_REQUEST))?
+        else {
+            return Ok(PropRequest::AllProp);
+        };
+        if !request.is(&Namespace::Dav, "propfind") {
+            return Err(DavError::Status(StatusCode::BAD_REQUEST));
+        }
+
+        Ok(PropRequest::from_element(&request).unwrap_or(PropRequest::AllProp))
+    }
+
+    pub fn from_element(element: &XmlElement) -> Option<Self> {
+        if element.child(&Namespace::Dav, "propname").is_some() {
+            Some(PropRequest::PropName)
+        } else if element.child(&Namespace::Dav, "allprop").is_some() {
+            Some(PropRequest::AllProp)
+        } else {
+            element.child(&Namespace::Dav, "prop").map(|prop| {
+                PropRequest::Prop(
+                    prop.children
+                        .iter()
+                        .filter_map(|child| child.name.clone())
+                        .collect(),
+                )
+            })
+        }
+    }
+}
+
+impl DavEntry<'_> {
+    pub fn href(&self) -> String {
+        match self {
+            DavEntry::Root(None) => "/dav/".to_string(),
+            DavEntry::Root(Some(kind)) => format!("/dav/{}/", kind.dav_path),
+            DavEntry::Principals => "/dav/principal/".to_string(),
+            DavEntry::Principal(account) => account.principal_href(),
+            DavEntry::Home(account, kind) => account.home_href(kind),
+            DavEntry::Container {
+                account,
+                kind,
+                container,
+                ..
+            } => account.container_href(kind, container.document_id, &container.object.inner),
+            DavEntry::Item { href, .. } => href.clone(),
+        }
+    }
+
+    pub fn write(
+        &self,
+        access_token: &AccessToken,
+        request: &PropRequest,
+        response: &mut MultiStatus,
+    ) {
+        let mut found = String::new();
+        let mut not_found = String::new();
+
+        match request {
+            PropRequest::AllProp => {
+                for (ns, name) in ALL_PROPERTIES {
+                    if let Some(value) = self.property(access_token, ns, name) {
+                        found.push_str(&value);
+                    }
+                }
+            }
+            PropRequest::PropName => {
+                for (ns, name) in ALL_PROPERTIES {
+                    if self.property(access_token, ns, name).is_some() {
+                        XmlName::new(ns.clone(), *name).write_empty(&mut found);
+                    }
+                }
+            }
+            PropRequest::Prop(names) => {
+                for name in names {
+                    if let Some(value) = self.property(access_token, &name.ns, &name.name) {
+                        found.push_str(&value);
+                    } else {
+                        name.write_empty(&mut not_found);
+                    }
+                }
+            }
+        }
+
+        response.add_response(
+            &self.href(),
+            &[
+                (StatusCode::OK, &found),
+                (StatusCode::NOT_FOUND, &not_found),
+            ],
+        );
+    }
+
+    fn property(&self, access_token: &AccessToken, ns: &Namespace, name: &str) -> Option<String> {
+        let mut buf = String::new();
+        match (ns, name) {
+            (Namespace::Dav, "resourcetype") => {
+                buf.push_str("<D:resourcetype>");
+                match self {
+                    DavEntry::Principal(_) => buf.push_str("<D:principal/>"),
+                    DavEntry::Container { kind, .. } => {
+                        buf.push_str("<D:collection/>");
+                        buf.push_str(if kind.container == Collection::AddressBook {
+                            "<CR:addressbook/>"
+                        } else {
+                            "<C:calendar/>"
+                        });
+                    }
+                    DavEntry::Item { .. } => (),
+                    _ => buf.push_str("<D:collection/>"),
+                }
+                buf.push_str("</D:resourcetype>");
+            }
+            (Namespace::Dav, "displayname") => {
+                let display_name = match self {
+                    DavEntry::Principal(account) | DavEntry::Home(account, _) => {
+                        account.name.as_str()
+                    }
+                    DavEntry::Container { container, .. } => {
+                        container.object.inner.get(&Property::Name).as_string()?
+                    }
+                    _ => return None,
+                };
+                text_element(&mut buf, "D:displayname", display_name);
+            }
+            (Namespace::Dav, "getetag") => match self {
+                DavEntry::Container { container, .. } => {
+                    text_element(&mut buf, "D:getetag", &container.etag());
+                }
+                DavEntry::Item { item, .. } => {
+                    text_element(&mut buf, "D:getetag", &item.etag());
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "getcontenttype") => match self {
+                DavEntry::Item { kind, .. } => {
+                    text_element(&mut buf, "D:getcontenttype", kind.dav_content_type);
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "getcontentlength") => match self {
+                DavEntry::Item { kind, item, .. } => {
+                    text_element(
+                        &mut buf,
+                        "D:getcontentlength",
+                        &item_data(kind, &item.object.inner).len().to_string(),
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "current-user-principal") => {
+                href_element(
+                    &mut buf,
+                    "D:current-user-principal",
+                    [principal_href(&access_token.name)],
+                );
+            }
+            (Namespace::Dav, "principal-URL") => match self {
+                DavEntry::Principal(account) => {
+                    href_element(&mut buf, "D:principal-URL", [account.principal_href()]);
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "principal-collection-set") => {
+                href_element(
+                    &mut buf,
+                    "D:principal-collection-set",
+                    ["/dav/principal/".to_string()],
+                );
+            }
+            (Namespace::Dav, "owner") => match self {
+                DavEntry::Home(account, _)
+                | DavEntry::Container { account, .. }
+                | DavEntry::Item { account, .. } => {
+                    href_element(&mut buf, "D:owner", [account.principal_href()]);
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "sync-token") | (Namespace::CalendarServer, "getctag") => match self {
+                DavEntry::Container { sync_token, .. } => {
+                    text_element(
+                        &mut buf,
+                        if matches!(ns, Namespace::Dav) {
+                            "D:sync-token"
+                        } else {
+                            "CS:getctag"
+                        },
+                        sync_token,
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::Dav, "supported-report-set") => {
+                let kind = match self {
+                    DavEntry::Home(_, kind) | DavEntry::Container { kind, .. } => kind,
+                    _ => return None,
+                };
+                let reports: &[&str] = if kind.container == Collection::AddressBook {
+                    &[
+                        "CR:addressbook-multiget",
+                        "CR:addressbook-query",
+                        "D:sync-collection",
+                    ]
+                } else {
+                    &[
+                        "C:calendar-multiget",
+                        "C:calendar-query",
+                        "D:sync-collection",
+                    ]
+                };
+                buf.push_str("<D:supported-report-set>");
+                for report in reports {
+                    let _ = write!(
+                        buf,
+                        "<D:supported-report><D:report><{report}/></D:report></D:supported-report>"
+                    );
+                }
+                buf.push_str("</D:supported-report-set>");
+            }
+            (Namespace::Dav, "current-user-privilege-set") => {
+                let acl = match self {
+                    DavEntry::Container { acl, .. } | DavEntry::Item { acl, .. } => *acl,
+                    DavEntry::Home(account, _) if access_token.is_member(account.id) => {
+                        Bitmap::all()
+                    }
+                    _ => Bitmap::from(Acl::Read),
+                };
+                buf.push_str("<D:current-user-privilege-set>");
+                for (privilege, rights) in [
+                    ("D:read", [Acl::Read, Acl::ReadItems].as_slice()),
+                    ("D:write-properties", [Acl::Modify].as_slice()),
+                    ("D:write-content", [Acl::ModifyItems].as_slice()),
+                    ("D:bind", [Acl::AddItems, Acl::CreateChild].as_slice()),
+                    ("D:unbind", [Acl::RemoveItems, Acl::Delete].as_slice()),
+                    ("D:read-acl", [Acl::Administer].as_slice()),
+                    ("D:write-acl", [Acl::Administer].as_slice()),
+                ] {
+                    if acl.contains_any(rights.iter().copied()) {
+                        let _ = write!(buf, "<D:privilege><{privilege}/></D:privilege>");
+                    }
+                }
+                buf.push_str("</D:current-user-privilege-set>");
+            }
+            (Namespace::CardDav, "addressbook-home-set") => match self {
+                DavEntry::Principal(account) => {
+                    href_element(
+                        &mut buf,
+                        "CR:addressbook-home-set",
+                        [account.home_href(&CONTACTS)],
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::CalDav, "calendar-home-set") => match self {
+                DavEntry::Principal(account) => {
+                    href_element(
+                        &mut buf,
+                        "C:calendar-home-set",
+                        [account.home_href(&CALENDARS)],
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::CalDav, "calendar-user-address-set") => match self {
+                DavEntry::Principal(account) if account.id == access_token.primary_id() => {
+                    href_element(
+                        &mut buf,
+                        "C:calendar-user-address-set",
+                        access_token
+                            .emails
+                            .iter()
+                            .map(|email| format!("mailto:{email}")),
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::CardDav, "addressbook-description")
+            | (Namespace::CalDav, "calendar-description") => match self {
+                DavEntry::Container {
+                    kind, container, ..
+                } if is_kind_namespace(kind, ns) => {
+                    text_element(
+                        &mut buf,
+                        &format!("{}:{name}", ns.prefix()),
+                        container
+                            .object
+                            .inner
+                            .get(&Property::Description)
+                            .as_string()?,
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::CardDav, "supported-address-data") => match self {
+                DavEntry::Container { kind, .. } if is_kind_namespace(kind, ns) => {
+                    buf.push_str(concat!(
+                        "<CR:supported-address-data>",
+                        "<CR:address-data-type content-type=\"text/vcard\" version=\"4.0\"/>",
+                        "<CR:address-data-type content-type=\"text/vcard\" version=\"3.0\"/>",
+                        "</CR:supported-address-data>"
+                    ));
+                }
+                _ => return None,
+            },
+            (Namespace::CalDav, "supported-calendar-component-set") => match self {
+                DavEntry::Container { kind, .. } if is_kind_namespace(kind, ns) => {
+                    buf.push_str(concat!(
+                        "<C:supported-calendar-component-set>",
+                        "<C:comp name=\"VEVENT\"/>",
+                        "</C:supported-calendar-component-set>"
+                    ));
+                }
+                _ => return None,
+            },
+            (Namespace::CalDav, "supported-calendar-data") => match self {
+                DavEntry::Container { kind, .. } if is_kind_namespace(kind, ns) => {
+                    buf.push_str(concat!(
+                        "<C:supported-calendar-data>",
+                        "<C:calendar-data content-type=\"text/calendar\" version=\"2.0\"/>",
+                        "</C:supported-calendar-data>"
+                    ));
+                }
+                _ => return None,
+            },
+            (Namespace::AppleIcal, "calendar-color") => match self {
+                DavEntry::Container {
+                    kind, container, ..
+                } if kind.container == Collection::Calendar => {
+                    text_element(
+                        &mut buf,
+                        "I:calendar-color",
+                        container.object.inner.get(&Property::Color).as_string()?,
+                    );
+                }
+                _ => return None,
+            },
+            (Namespace::CardDav, "address-data") | (Namespace::CalDav, "calendar-data") => {
+                match self {
+                    DavEntry::Item { kind, item, .. } if is_kind_namespace(kind, ns) => {
+                        text_element(
+                            &mut buf,
+                            &format!("{}:{name}", ns.prefix()),
+                            &item_data(kind, &item.object.inner),
+                        );
+                    }
+                    _ => return None,
+                }
+            }
+            _ => return None,
+        }
+
+        Some(buf)
+    }
+}
+
+fn is_kind_namespace(kind: &GroupwareType, ns: &Namespace) -> bool {
+    match ns {
+        Namespace::CardDav => kind.container == Collection::AddressBook,
+        Namespace::CalDav => kind.container == Collection::Calendar,
+        _ => false,
+    }
+}
+
+fn text_element(buf: &mut String, name: &str, text: &str) {
+    let _ = write!(buf, "<{name}>{}</{name}>", escape(text));
+}
+
+fn href_element(buf: &mut String, name: &str, hrefs: impl IntoIterator<Item = String>) {
+    let _ = write!(buf, "<{name}>");
+    for href in hrefs {
+        let _ = write!(buf, "<D:href>{}</D:href>", escape(href.as_str()));
+    }
+    let _ = write!(buf, "</{name}>");
+}
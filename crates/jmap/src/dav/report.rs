@@ -0,0 +1,425 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::{container_ids, icalendar::parse_date_time, GroupwareType};
+use hyper::StatusCode;
+use jmap_proto::{
+    object::Object,
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use store::{
+    query::{
+        log::{Change, Query},
+        Filter,
+    },
+    write::assert::HashedValue,
+};
+use trc::AddContext;
+
+use crate::{api::HttpResponse, JmapMethods};
+
+use super::{
+    container_acl, item_href,
+    propfind::{DavEntry, DavPropFind, PropRequest},
+    xml::{MultiStatus, Namespace, XmlElement, XmlName},
+    DavAccount, DavError, DavObject, DavRequestHandler, DavResource, DavResult, Depth,
+};
+use std::future::Future;
+
+pub trait DavReport: Sync + Send {
+    fn dav_report(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        depth: Depth,
+        body: &[u8],
+    ) -> impl Future<Output = DavResult<HttpResponse>> + Send;
+
+    fn dav_multiget(
+        &self,
+        access_token: &AccessToken,
+        request: &XmlElement,
+        props: &PropRequest,
+        response: &mut MultiStatus,
+    ) -> impl Future<Output = DavResult<()>> + Send;
+
+    fn dav_sync_collection(
+        &self,
+        access_token: &AccessToken,
+        collection: DavCollectionRef<'_>,
+        request: &XmlElement,
+        props: &PropRequest,
+        response: &mut MultiStatus,
+    ) -> impl Future<Output = DavResult<()>> + Send;
+}
+
+pub struct DavCollectionRef<'x> {
+    pub account: &'x DavAccount,
+    pub kind: &'static GroupwareType,
+    pub container: &'x DavObject,
+    pub href: String,
+}
+
+impl DavReport for Server {
+    async fn dav_report(
+        &self,
+        access_token: &AccessToken,
+        resource: DavResource,
+        _: Depth,
+        body: &[u8],
+    ) -> DavResult<HttpResponse> {
+        let request = XmlElement::parse(body)
+            .map_err(|_| DavError::Status(StatusCode::BAD_REQUEST))?
+            .ok_or(DavError::Status(StatusCode::BAD_REQUEST))?;
+        let props = PropRequest::from_element(&request).unwrap_or(PropRequest::AllProp);
+        let mut response = MultiStatus::new();
+
+        // Multiget reports can be sent to any collection
+        if request.is(&Namespace::CardDav, "addressbook-multiget")
+            || request.is(&Namespace::CalDav, "calendar-multiget")
+        {
+            self.dav_multiget(access_token, &request, &props, &mut response)
+                .await?;
+            return Ok(response.into_http_response());
+        }
+
+        let (account, kind, container) = match resource {
+            DavResource::Collection {
+                account,
+                kind,
+                container: Some(container),
+                ..
+            } => (account, kind, container),
+            DavResource::Collection {
+                container: None, ..
+            } => return Err(DavError::Status(StatusCode::NOT_FOUND)),
+            _ => return Err(unsupported_report()),
+        };
+        let acl = container_acl(access_token, account.id, &container.object.inner);
+        if !acl.contains(Acl::ReadItems) {
+            return Err(DavError::Status(StatusCode::FORBIDDEN));
+        }
+        let href = account.container_href(kind, container.document_id, &container.object.inner);
+
+        if request.is(&Namespace::Dav, "sync-collection") {
+            self.dav_sync_collection(
+                access_token,
+                DavCollectionRef {
+                    account: &account,
+                    kind,
+                    container: &container,
+                    href,
+                },
+                &request,
+                &props,
+                &mut response,
+            )
+            .await?;
+        } else if (kind.item == Collection::ContactCard
+            && request.is(&Namespace::CardDav, "addressbook-query"))
+            || (kind.item == Collection::CalendarEvent
+                && request.is(&Namespace::CalDav, "calendar-query"))
+        {
+            let mut filters = vec![Filter::eq(
+                kind.container_ids.clone(),
+                container.document_id,
+            )];
+            if let Some(filter) = request
+                .child(&Namespace::CardDav, "filter")
+                .or_else(|| request.child(&Namespace::CalDav, "filter"))
+            {
+                query_filters(filter, &mut filters)?;
+            }
+
+            for document_id in self.filter(account.id, kind.item, filters).await?.results {
+                if let Some(object) = self
+                    .get_property::<HashedValue<Object<Value>>>(
+                        account.id,
+                        kind.item,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                {
+                    let item = DavObject {
+                        document_id,
+                        object,
+                    };
+                    DavEntry::Item {
+                        account: &account,
+                        kind,
+                        href: item_href(&href, kind, document_id, &item.object.inner),
+                        item: &item,
+                        acl,
+                    }
+                    .write(access_token, &props, &mut response);
+                }
+            }
+        } else {
+            return Err(unsupported_report());
+        }
+
+        Ok(response.into_http_response())
+    }
+
+    async fn dav_multiget(
+        &self,
+        access_token: &AccessToken,
+        request: &XmlElement,
+        props: &PropRequest,
+        response: &mut MultiStatus,
+    ) -> DavResult<()> {
+        for href in request.children(&Namespace::Dav, "href") {
+            let href = href.text.trim();
+
+            // Absolute URLs are accepted as well
+            let path = href
+                .split_once("://")
+                .and_then(|(_, url)| url.find('/').map(|pos| &url[pos..]))
+                .unwrap_or(href);
+
+            match self.dav_resource(access_token, path).await {
+                Ok(DavResource::Item {
+                    account,
+                    kind,
+                    container,
+                    item: Some(item),
+                    ..
+                }) => {
+                    let acl = container_acl(access_token, account.id, &container.object.inner);
+                    if acl.contains(Acl::ReadItems) {
+                        DavEntry::Item {
+                            account: &account,
+                            kind,
+                            href: href.to_string(),
+                            item: &item,
+                            acl,
+                        }
+                        .write(access_token, props, response);
+                    } else {
+                        response.add_status(href, StatusCode::FORBIDDEN);
+                    }
+                }
+                Ok(_) => response.add_status(href, StatusCode::NOT_FOUND),
+                Err(DavError::Status(status) | DavError::Condition(status, _)) => {
+                    response.add_status(href, status)
+                }
+                Err(DavError::Internal(err)) => return Err(DavError::Internal(err)),
+            }
+        }
+
+        Ok(())
+    }
+
+    async fn dav_sync_collection(
+        &self,
+        access_token: &AccessToken,
+        collection: DavCollectionRef<'_>,
+        request: &XmlElement,
+        props: &PropRequest,
+        response: &mut MultiStatus,
+    ) -> DavResult<()> {
+        let DavCollectionRef {
+            account,
+            kind,
+            container,
+            href,
+        } = collection;
+        let acl = container_acl(access_token, account.id, &container.object.inner);
+        let sync_token = self.dav_sync_token(account.id, kind).await?;
+
+        let sync_from = request
+            .child(&Namespace::Dav, "sync-token")
+            .map(|token| token.text.trim())
+            .filter(|token| !token.is_empty());
+        if let Some(sync_from) = sync_from {
+            let change_id = sync_from
+                .strip_prefix("urn:stalwart:sync:")
+                .and_then(|id| id.parse::<u64>().ok())
+                .ok_or_else(invalid_sync_token)?;
+            let changes = self
+                .core
+                .storage
+                .data
+                .changes(account.id, kind.item, Query::Since(change_id))
+                .await
+                .caused_by(trc::location!())?;
+
+            let mut document_ids = Vec::with_capacity(changes.changes.len());
+            let mut deleted_ids = Vec::new();
+            for change in changes.changes {
+                match change {
+                    Change::Insert(id) | Change::Update(id) | Change::ChildUpdate(id) => {
+                        let document_id = id as u32;
+                        if !document_ids.contains(&document_id) {
+                            document_ids.push(document_id);
+                        }
+                    }
+                    Change::Delete(id) => deleted_ids.push(id as u32),
+                }
+            }
+
+            let mut hrefs = Vec::with_capacity(document_ids.len() + deleted_ids.len());
+
+            for document_id in document_ids {
+                if let Some(object) = self
+                    .get_property::<HashedValue<Object<Value>>>(
+                        account.id,
+                        kind.item,
+                        document_id,
+                        Property::Value,
+                    )
+                    .await?
+                {
+                    let item = DavObject {
+                        document_id,
+                        object,
+                    };
+                    let item_href = item_href(&href, kind, document_id, &item.object.inner);
+                    hrefs.push(item_href.clone());
+                    if item
+                        .object
+                        .inner
+                        .get(&kind.container_ids)
+                        .as_list()
+                        .is_some_and(|ids| ids.contains(&Value::Id(container.document_id.into())))
+                    {
+                        DavEntry::Item {
+                            account,
+                            kind,
+                            href: item_href,
+                            item: &item,
+                            acl,
+                        }
+                        .write(access_token, props, response);
+                    } else {
+                        response.add_status(&item_href, StatusCode::NOT_FOUND);
+                    }
+                }
+            }
+
+            // Deleted items are listed by the resource names they had
+            for document_id in deleted_ids {
+                let Some(Value::List(tombstones)) = self
+                    .get_property::<Value>(
+                        account.id,
+                        kind.item,
+                        document_id,
+                        Property::DavTombstones,
+                    )
+                    .await?
+                else {
+                    continue;
+                };
+                for tombstone in tombstones {
+                    let Value::Object(tombstone) = tombstone else {
+                        continue;
+                    };
+                    if !container_ids(&tombstone, kind).contains(&container.document_id) {
+                        continue;
+                    }
+                    let item_href = item_href(&href, kind, document_id, &tombstone);
+                    if hrefs.contains(&item_href) {
+                        continue;
+                    }
+
+                    // Names of earlier deletions may have been reused since
+                    if let Some(name) = tombstone.get(&Property::DavName).as_string() {
+                        if !self
+                            .filter(
+                                account.id,
+                                kind.item,
+                                vec![
+                                    Filter::eq(kind.container_ids.clone(), container.document_id),
+                                    Filter::eq(Property::DavName, name),
+                                ],
+                            )
+                            .await?
+                            .results
+                            .is_empty()
+                        {
+                            continue;
+                        }
+                    }
+
+                    response.add_status(&item_href, StatusCode::NOT_FOUND);
+                    hrefs.push(item_href);
+                }
+            }
+        } else {
+            for item in self
+                .dav_container_items(account.id, kind, container.document_id)
+                .await?
+            {
+                DavEntry::Item {
+                    account,
+                    kind,
+                    href: item_href(&href, kind, item.document_id, &item.object.inner),
+                    item: &item,
+                    acl,
+                }
+                .write(access_token, props, response);
+            }
+        }
+
+        response.add_sync_token(&sync_token);
+
+        Ok(())
+    }
+}
+
+// Text matches are mapped to a full-text search, while time ranges are
+// evaluated against the indexed UTC start and end times.
+fn query_filters(filter: &XmlElement, filters: &mut Vec<Filter>) -> DavResult<()> {
+    for child in &filter.children {
+        let Some(name) = &child.name else {
+            continue;
+        };
+        match name.name.as_str() {
+            "comp-filter" | "prop-filter" | "param-filter" => {
+                query_filters(child, filters)?;
+            }
+            "text-match" => {
+                let text = child.text.trim();
+                if !text.is_empty() {
+                    filters.push(Filter::has_text(Property::Text, text));
+                }
+            }
+            "time-range" => {
+                if let Some(start) = child.attribute("start") {
+                    filters.push(Filter::gt(Property::UtcEnd, parse_timestamp(start)?));
+                }
+                if let Some(end) = child.attribute("end") {
+                    filters.push(Filter::lt(Property::UtcStart, parse_timestamp(end)?));
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_timestamp(value: &str) -> DavResult<u64> {
+    parse_date_time(value)
+        .map(|(date, _, _)| date.and_utc().timestamp().max(0) as u64)
+        .ok_or(DavError::Status(StatusCode::BAD_REQUEST))
+}
+
+fn unsupported_report() -> DavError {
+    DavError::Condition(
+        StatusCode::FORBIDDEN,
+        XmlName::new(Namespace::Dav, "supported-report"),
+    )
+}
+
+fn invalid_sync_token() -> DavError {
+    DavError::Condition(
+        StatusCode::FORBIDDEN,
+        XmlName::new(Namespace::Dav, "valid-sync-token"),
+    )
+}
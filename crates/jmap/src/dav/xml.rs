@@ -0,0 +1,296 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::fmt::Write;
+
+use hyper::StatusCode;
+use quick_xml::{
+    escape::escape,
+    events::Event,
+    name::{Namespace as XmlNamespace, ResolveResult},
+    NsReader,
+};
+
+use crate::api::HttpResponse;
+
+pub const NS_DAV: &str = "DAV:";
+pub const NS_CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+pub const NS_CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+pub const NS_CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+pub const NS_APPLE_ICAL: &str = "http://apple.com/ns/ical/";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Namespace {
+    Dav,
+    CalDav,
+    CardDav,
+    CalendarServer,
+    AppleIcal,
+    Other(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct XmlName {
+    pub ns: Namespace,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct XmlElement {
+    pub name: Option<XmlName>,
+    pub attributes: Vec<(String, String)>,
+    pub text: String,
+    pub children: Vec<XmlElement>,
+}
+
+impl Namespace {
+    pub fn parse(uri: &[u8]) -> Self {
+        match uri {
+            b"DAV:" => Namespace::Dav,
+            b"urn:ietf:params:xml:ns:caldav" => Namespace::CalDav,
+            b"urn:ietf:params:xml:ns:carddav" => Namespace::CardDav,
+            b"http://calendarserver.org/ns/" => Namespace::CalendarServer,
+            b"http://apple.com/ns/ical/" => Namespace::AppleIcal,
+            _ => Namespace::Other(String::from_utf8_lossy(uri).into_owned()),
+        }
+    }
+
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Namespace::Dav => "D",
+            Namespace::CalDav => "C",
+            Namespace::CardDav => "CR",
+            Namespace::CalendarServer => "CS",
+            Namespace::AppleIcal => "I",
+            Namespace::Other(_) => "X",
+        }
+    }
+}
+
+impl XmlName {
+    pub fn new(ns: Namespace, name: impl Into<String>) -> Self {
+        XmlName {
+            ns,
+            name: name.into(),
+        }
+    }
+
+    pub fn is(&self, ns: &Namespace, name: &str) -> bool {
+        &self.ns == ns && self.name == name
+    }
+
+    // Empty elements from unknown namespaces carry their own declaration
+    pub fn write_empty(&self, buf: &mut String) {
+        match &self.ns {
+            Namespace::Other(uri) => {
+                let _ = write!(
+                    buf,
+                    "<X:{} xmlns:X=\"{}\"/>",
+                    self.name,
+                    escape(uri.as_str())
+                );
+            }
+            ns => {
+                let _ = write!(buf, "<{}:{}/>", ns.prefix(), self.name);
+            }
+        }
+    }
+}
+
+impl XmlElement {
+    pub fn parse(bytes: &[u8]) -> Result<Option<XmlElement>, String> {
+        if bytes.iter().all(|ch| ch.is_ascii_whitespace()) {
+            return Ok(None);
+        }
+
+        let mut reader = NsReader::from_reader(bytes);
+        reader.config_mut().trim_text(true);
+        let mut buf = Vec::with_capacity(128);
+        let mut stack: Vec<XmlElement> = vec![XmlElement::default()];
+
+        loop {
+            match reader.read_resolved_event_into(&mut buf) {
+                Ok((ns, Event::Start(e))) => {
+                    stack.push(XmlElement::new(ns, e.local_name().as_ref(), &e)?);
+                }
+                Ok((ns, Event::Empty(e))) => {
+                    let element = XmlElement::new(ns, e.local_name().as_ref(), &e)?;
+                    stack.last_mut().unwrap().children.push(element);
+                }
+                Ok((_, Event::End(_))) => {
+                    if stack.len() > 1 {
+                        let element = stack.pop().unwrap();
+                        stack.last_mut().unwrap().children.push(element);
+                    } else {
+                        return Err("Unbalanced end tag".to_string());
+                    }
+                }
+                Ok((_, Event::Text(text))) => {
+                    let text = text.unescape().map_err(|err| err.to_string())?;
+                    stack.last_mut().unwrap().text.push_str(&text);
+                }
+                Ok((_, Event::CData(text))) => {
+                    stack
+                        .last_mut()
+                        .unwrap()
+                        .text
+                        .push_str(&String::from_utf8_lossy(&text));
+                }
+                Ok((_, Event::Eof)) => break,
+                Ok(_) => (),
+                Err(err) => {
+                    return Err(format!(
+                        "Error at position {}: {}",
+                        reader.buffer_position(),
+                        err
+                    ));
+                }
+            }
+            buf.clear();
+        }
+
+        if stack.len() == 1 {
+            Ok(stack.pop().unwrap().children.into_iter().next())
+        } else {
+            Err("Unexpected end of document".to_string())
+        }
+    }
+
+    fn new(
+        ns: ResolveResult<'_>,
+        name: &[u8],
+        start: &quick_xml::events::BytesStart<'_>,
+    ) -> Result<Self, String> {
+        let ns = match ns {
+            ResolveResult::Bound(XmlNamespace(uri)) => Namespace::parse(uri),
+            _ => Namespace::Other(String::new()),
+        };
+        let mut attributes = Vec::new();
+        for attr in start.attributes() {
+            let attr = attr.map_err(|err| err.to_string())?;
+            attributes.push((
+                String::from_utf8_lossy(attr.key.local_name().as_ref()).into_owned(),
+                attr.unescape_value()
+                    .map_err(|err| err.to_string())?
+                    .into_owned(),
+            ));
+        }
+
+        Ok(XmlElement {
+            name: Some(XmlName::new(ns, String::from_utf8_lossy(name).into_owned())),
+            attributes,
+            text: String::new(),
+            children: Vec::new(),
+        })
+    }
+
+    pub fn is(&self, ns: &Namespace, name: &str) -> bool {
+        self.name.as_ref().is_some_and(|n| n.is(ns, name))
+    }
+
+    pub fn child(&self, ns: &Namespace, name: &str) -> Option<&XmlElement> {
+        self.children.iter().find(|child| child.is(ns, name))
+    }
+
+    pub fn children<'x>(
+        &'x self,
+        ns: &'x Namespace,
+        name: &'x str,
+    ) -> impl Iterator<Item = &'x XmlElement> + 'x {
+        self.children.iter().filter(move |child| child.is(ns, name))
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&str> {
+        self.attributes
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+pub struct MultiStatus {
+    buf: String,
+}
+
+impl MultiStatus {
+    pub fn new() -> Self {
+        let mut buf = String::with_capacity(1024);
+        buf.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+        let _ = write!(
+            buf,
+            concat!(
+                "<D:multistatus xmlns:D=\"{}\" xmlns:C=\"{}\" xmlns:CR=\"{}\" ",
+                "xmlns:CS=\"{}\" xmlns:I=\"{}\">"
+            ),
+            NS_DAV, NS_CALDAV, NS_CARDDAV, NS_CALENDARSERVER, NS_APPLE_ICAL
+        );
+        MultiStatus { buf }
+    }
+
+    pub fn add_response(&mut self, href: &str, propstats: &[(StatusCode, &str)]) {
+        let _ = write!(self.buf, "<D:response><D:href>{}</D:href>", escape(href));
+        for (status, props) in propstats {
+            if !props.is_empty() {
+                let _ = write!(
+                    self.buf,
+                    "<D:propstat><D:prop>{}</D:prop><D:status>{}</D:status></D:propstat>",
+                    props,
+                    status_line(*status)
+                );
+            }
+        }
+        self.buf.push_str("</D:response>");
+    }
+
+    pub fn add_status(&mut self, href: &str, status: StatusCode) {
+        let _ = write!(
+            self.buf,
+            "<D:response><D:href>{}</D:href><D:status>{}</D:status></D:response>",
+            escape(href),
+            status_line(status)
+        );
+    }
+
+    pub fn add_sync_token(&mut self, token: &str) {
+        let _ = write!(self.buf, "<D:sync-token>{}</D:sync-token>", escape(token));
+    }
+
+    pub fn into_http_response(mut self) -> HttpResponse {
+        self.buf.push_str("</D:multistatus>\n");
+        xml_response(StatusCode::MULTI_STATUS, self.buf)
+    }
+}
+
+impl Default for MultiStatus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+pub fn status_line(status: StatusCode) -> String {
+    format!(
+        "HTTP/1.1 {} {}",
+        status.as_u16(),
+        status.canonical_reason().unwrap_or_default()
+    )
+}
+
+pub fn error_response(status: StatusCode, condition: &XmlName) -> HttpResponse {
+    let mut buf = String::with_capacity(256);
+    buf.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    let _ = write!(
+        buf,
+        "<D:error xmlns:D=\"{}\" xmlns:C=\"{}\" xmlns:CR=\"{}\">",
+        NS_DAV, NS_CALDAV, NS_CARDDAV
+    );
+    condition.write_empty(&mut buf);
+    buf.push_str("</D:error>\n");
+    xml_response(status, buf)
+}
+
+pub fn xml_response(status: StatusCode, body: String) -> HttpResponse {
+    HttpResponse::new_text(status, "application/xml; charset=utf-8", body)
+}
@@ -11,7 +11,9 @@ use email::metadata::MessageMetadata;
 use jmap_proto::types::{collection::Collection, property::Property};
 use mail_parser::Message;
 use spam_filter::{
-    analysis::init::SpamFilterInit, modules::bayes::BayesClassifier, SpamFilterInput,
+    analysis::init::SpamFilterInit,
+    modules::{bayes::BayesClassifier, fuzzy::FuzzyDatabase},
+    SpamFilterInput,
 };
 use store::write::{Bincode, TaskQueueClass};
 use trc::StoreEvent;
@@ -41,13 +43,26 @@ impl EmailBayesTrain for Server {
         message: Message<'_>,
         learn_spam: bool,
     ) {
-        self.bayes_train_if_balanced(
-            &self.spam_filter_init(SpamFilterInput::from_account_message(
-                &message, account_id, span_id,
-            )),
-            learn_spam,
-        )
-        .await
+        let ctx = self.spam_filter_init(SpamFilterInput::from_account_message(
+            &message, account_id, span_id,
+        ));
+
+        if self
+            .core
+            .spam
+            .bayes
+            .as_ref()
+            .is_some_and(|config| config.account_classify)
+        {
+            self.bayes_train_if_balanced(&ctx, learn_spam).await;
+        }
+
+        // User spam reports also train the shared fuzzy hash database
+        if self.core.spam.fuzzy.is_some() {
+            if let Err(err) = self.fuzzy_train(&ctx, learn_spam).await {
+                trc::error!(err.account_id(account_id).span_id(span_id));
+            }
+        }
     }
 
     async fn email_bayes_queue_task_build(
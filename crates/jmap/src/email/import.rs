@@ -142,6 +142,7 @@ impl EmailImport for Server {
                     spam_classify: false,
                     spam_train: can_train_spam,
                     session_id: session.session_id,
+                    replace: None,
                 })
                 .await
             {
@@ -741,6 +741,7 @@ impl EmailSet for Server {
                     spam_classify: false,
                     spam_train: can_train_spam,
                     session_id: session.session_id,
+                    replace: None,
                 })
                 .await
             {
@@ -960,10 +961,11 @@ impl EmailSet for Server {
                 // Obtain IMAP UIDs for added mailboxes
                 for uid_mailbox in mailboxes.inner_tags_mut() {
                     if uid_mailbox.uid == 0 {
-                        uid_mailbox.uid = self
-                            .assign_imap_uid(account_id, uid_mailbox.mailbox_id)
-                            .await
-                            .caused_by(trc::location!())?;
+                        uid_mailbox.assign_uid(
+                            self.assign_imap_uid(account_id, uid_mailbox.mailbox_id)
+                                .await
+                                .caused_by(trc::location!())?,
+                        );
                     }
                 }
 
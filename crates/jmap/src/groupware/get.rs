@@ -0,0 +1,327 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::GroupwareType;
+use jmap_proto::{
+    method::get::{GetRequest, GetResponse, RequestArguments},
+    object::Object,
+    types::{acl::Acl, collection::Collection, property::Property, value::Value},
+};
+use store::{query::Filter, roaring::RoaringBitmap};
+
+use crate::{
+    auth::acl::{AclMethods, EffectiveAcl},
+    changes::state::StateManager,
+    JmapMethods,
+};
+
+use std::future::Future;
+
+pub trait GroupwareGet: Sync + Send {
+    fn container_get(
+        &self,
+        request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+
+    fn item_get(
+        &self,
+        request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<GetResponse>> + Send;
+
+    fn shared_items(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        check_acls: Acl,
+    ) -> impl Future<Output = trc::Result<RoaringBitmap>> + Send;
+}
+
+impl GroupwareGet for Server {
+    async fn container_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<GetResponse> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = if kind.container == Collection::Calendar {
+            request.unwrap_properties(&[
+                Property::Id,
+                Property::Name,
+                Property::Description,
+                Property::Color,
+                Property::SortOrder,
+                Property::IsDefault,
+                Property::IsSubscribed,
+                Property::IsVisible,
+                Property::MyRights,
+            ])
+        } else {
+            request.unwrap_properties(&[
+                Property::Id,
+                Property::Name,
+                Property::Description,
+                Property::SortOrder,
+                Property::IsDefault,
+                Property::IsSubscribed,
+                Property::MyRights,
+            ])
+        };
+        let account_id = request.account_id.document_id();
+        let container_ids = self
+            .owned_or_shared_documents(access_token, account_id, kind.container, Acl::Read)
+            .await?;
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            container_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self.get_state(account_id, kind.container).await?.into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the container object
+            let document_id = id.document_id();
+            if !container_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut values = if let Some(values) = self
+                .get_property::<Object<Value>>(
+                    account_id,
+                    kind.container,
+                    document_id,
+                    &Property::Value,
+                )
+                .await?
+            {
+                values
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+
+            let is_owner = !access_token.is_shared(account_id);
+            let acl = values.effective_acl(access_token);
+            let has_acl = |item| is_owner || acl.contains(item);
+            let mut container = Object::with_capacity(properties.len());
+
+            for property in &properties {
+                let value = match property {
+                    Property::Id => Value::Id(id),
+                    Property::Name | Property::Description | Property::Color => {
+                        values.remove(property)
+                    }
+                    Property::SortOrder => values
+                        .properties
+                        .remove(property)
+                        .unwrap_or(Value::UnsignedInt(0)),
+                    Property::IsDefault => values
+                        .properties
+                        .remove(property)
+                        .unwrap_or(Value::Bool(false)),
+                    Property::IsVisible => values
+                        .properties
+                        .remove(property)
+                        .unwrap_or(Value::Bool(true)),
+                    Property::IsSubscribed => values
+                        .properties
+                        .remove(property)
+                        .map(|subscribers| match subscribers {
+                            Value::List(subscribers)
+                                if subscribers
+                                    .contains(&Value::Id(access_token.primary_id().into())) =>
+                            {
+                                Value::Bool(true)
+                            }
+                            _ => Value::Bool(false),
+                        })
+                        .unwrap_or(Value::Bool(false)),
+                    Property::MyRights => {
+                        let may_read = has_acl(Acl::ReadItems);
+                        let may_write = has_acl(Acl::ModifyItems);
+                        if kind.container == Collection::Calendar {
+                            Object::with_capacity(8)
+                                .with_property(Property::MayReadFreeBusy, may_read)
+                                .with_property(Property::MayReadItems, may_read)
+                                .with_property(Property::MayWriteAll, may_write)
+                                .with_property(Property::MayWriteOwn, may_write)
+                                .with_property(Property::MayUpdatePrivate, may_write)
+                                .with_property(Property::MayRsvp, may_write)
+                                .with_property(Property::MayAdmin, has_acl(Acl::Administer))
+                                .with_property(Property::MayDelete, has_acl(Acl::Delete))
+                                .into()
+                        } else {
+                            Object::with_capacity(4)
+                                .with_property(Property::MayRead, may_read)
+                                .with_property(Property::MayWrite, may_write)
+                                .with_property(Property::MayAdmin, has_acl(Acl::Administer))
+                                .with_property(Property::MayDelete, has_acl(Acl::Delete))
+                                .into()
+                        }
+                    }
+                    Property::Acl => {
+                        self.acl_get(
+                            values
+                                .properties
+                                .get(&Property::Acl)
+                                .and_then(|v| v.as_acl())
+                                .map(|v| &v[..])
+                                .unwrap_or_else(|| &[]),
+                            access_token,
+                            account_id,
+                        )
+                        .await
+                    }
+
+                    _ => Value::Null,
+                };
+
+                container.append(property.clone(), value);
+            }
+
+            // Add result to response
+            response.list.push(container);
+        }
+        Ok(response)
+    }
+
+    async fn item_get(
+        &self,
+        mut request: GetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<GetResponse> {
+        let ids = request.unwrap_ids(self.core.jmap.get_max_objects)?;
+        let properties = request
+            .properties
+            .take()
+            .map(|properties| properties.unwrap());
+        let account_id = request.account_id.document_id();
+        let item_ids = if access_token.is_member(account_id) {
+            self.get_document_ids(account_id, kind.item)
+                .await?
+                .unwrap_or_default()
+        } else {
+            self.shared_items(access_token, account_id, kind, Acl::ReadItems)
+                .await?
+        };
+        let ids = if let Some(ids) = ids {
+            ids
+        } else {
+            item_ids
+                .iter()
+                .take(self.core.jmap.get_max_objects)
+                .map(Into::into)
+                .collect::<Vec<_>>()
+        };
+        let mut response = GetResponse {
+            account_id: request.account_id.into(),
+            state: self.get_state(account_id, kind.item).await?.into(),
+            list: Vec::with_capacity(ids.len()),
+            not_found: vec![],
+        };
+
+        for id in ids {
+            // Obtain the item
+            let document_id = id.document_id();
+            if !item_ids.contains(document_id) {
+                response.not_found.push(id.into());
+                continue;
+            }
+            let mut values = if let Some(values) = self
+                .get_property::<Object<Value>>(account_id, kind.item, document_id, &Property::Value)
+                .await?
+            {
+                values
+            } else {
+                response.not_found.push(id.into());
+                continue;
+            };
+            for property in [Property::Text, Property::DavName, Property::DavData] {
+                values.properties.remove(&property);
+            }
+
+            // Container ids are stored as a list but returned as an id set
+            if let Value::List(container_ids) = values.remove(&kind.container_ids) {
+                let mut ids = Object::with_capacity(container_ids.len());
+                for id in container_ids {
+                    if let Value::Id(id) = id {
+                        ids.append(Property::_T(id.to_string()), true);
+                    }
+                }
+                values.append(kind.container_ids.clone(), ids);
+            }
+
+            let item = if let Some(properties) = &properties {
+                let mut item = Object::with_capacity(properties.len());
+                for property in properties {
+                    let value = if property == &Property::Id {
+                        Value::Id(id)
+                    } else {
+                        let name = property.to_string();
+                        values
+                            .properties
+                            .iter()
+                            .find(|(property, _)| property.to_string() == name)
+                            .map(|(_, value)| value.clone())
+                            .unwrap_or_default()
+                    };
+                    item.append(property.clone(), value);
+                }
+                if !properties.contains(&Property::Id) {
+                    item.append(Property::Id, Value::Id(id));
+                }
+                item
+            } else {
+                values.with_property(Property::Id, Value::Id(id))
+            };
+
+            // Add result to response
+            response.list.push(item);
+        }
+        Ok(response)
+    }
+
+    async fn shared_items(
+        &self,
+        access_token: &AccessToken,
+        account_id: u32,
+        kind: &'static GroupwareType,
+        check_acls: Acl,
+    ) -> trc::Result<RoaringBitmap> {
+        let container_ids = self
+            .shared_documents(access_token, account_id, kind.container, check_acls)
+            .await?;
+        let mut item_ids = RoaringBitmap::new();
+        for container_id in container_ids {
+            item_ids |= self
+                .filter(
+                    account_id,
+                    kind.item,
+                    vec![Filter::eq(kind.container_ids.clone(), container_id)],
+                )
+                .await?
+                .results;
+        }
+
+        Ok(item_ids)
+    }
+}
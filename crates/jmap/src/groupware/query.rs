@@ -0,0 +1,210 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::GroupwareType;
+use jmap_proto::{
+    method::query::{
+        Comparator, Filter, QueryRequest, QueryResponse, RequestArguments, SortProperty,
+    },
+    types::{acl::Acl, collection::Collection, property::Property},
+};
+use store::query::{self};
+
+use crate::{auth::acl::AclMethods, JmapMethods};
+
+use super::get::GroupwareGet;
+use std::future::Future;
+
+pub trait GroupwareQuery: Sync + Send {
+    fn container_query(
+        &self,
+        request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<QueryResponse>> + Send;
+
+    fn item_query(
+        &self,
+        request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<QueryResponse>> + Send;
+}
+
+impl GroupwareQuery for Server {
+    async fn container_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<QueryResponse> {
+        let account_id = request.account_id.document_id();
+        let mut filters = Vec::with_capacity(request.filter.len());
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::Name(name) => filters.push(query::Filter::has_text(Property::Name, &name)),
+                Filter::IsSubscribed(is_subscribed) => {
+                    if !is_subscribed {
+                        filters.push(query::Filter::Not);
+                    }
+                    filters.push(query::Filter::eq(
+                        Property::IsSubscribed,
+                        access_token.primary_id,
+                    ));
+                    if !is_subscribed {
+                        filters.push(query::Filter::End);
+                    }
+                }
+                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                    filters.push(cond.into());
+                }
+
+                other => {
+                    return Err(trc::JmapEvent::UnsupportedFilter
+                        .into_err()
+                        .details(other.to_string()))
+                }
+            }
+        }
+
+        let mut result_set = self.filter(account_id, kind.container, filters).await?;
+        if access_token.is_shared(account_id) {
+            result_set.apply_mask(
+                self.shared_documents(access_token, account_id, kind.container, Acl::Read)
+                    .await?,
+            );
+        }
+        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+
+        if let Some(paginate) = paginate {
+            // Parse sort criteria
+            let mut comparators = Vec::with_capacity(request.sort.as_ref().map_or(1, |s| s.len()));
+            for comparator in request
+                .sort
+                .and_then(|s| if !s.is_empty() { s.into() } else { None })
+                .unwrap_or_else(|| vec![Comparator::ascending(SortProperty::SortOrder)])
+            {
+                comparators.push(match comparator.property {
+                    SortProperty::Name => {
+                        query::Comparator::field(Property::Name, comparator.is_ascending)
+                    }
+                    SortProperty::SortOrder => {
+                        query::Comparator::field(Property::SortOrder, comparator.is_ascending)
+                    }
+
+                    other => {
+                        return Err(trc::JmapEvent::UnsupportedSort
+                            .into_err()
+                            .details(other.to_string()))
+                    }
+                });
+            }
+
+            // Sort results
+            self.sort(result_set, comparators, paginate, response).await
+        } else {
+            Ok(response)
+        }
+    }
+
+    async fn item_query(
+        &self,
+        mut request: QueryRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<QueryResponse> {
+        let account_id = request.account_id.document_id();
+        let is_calendar = kind.item == Collection::CalendarEvent;
+        let mut filters = Vec::with_capacity(request.filter.len());
+
+        for cond in std::mem::take(&mut request.filter) {
+            match cond {
+                Filter::InAddressBook(id) if !is_calendar => filters.push(query::Filter::eq(
+                    Property::AddressBookIds,
+                    id.document_id(),
+                )),
+                Filter::InCalendar(id) if is_calendar => {
+                    filters.push(query::Filter::eq(Property::CalendarIds, id.document_id()))
+                }
+                Filter::Uid(uid) => filters.push(query::Filter::eq(Property::Uid, uid)),
+                Filter::Kind(kind) if !is_calendar => {
+                    filters.push(query::Filter::eq(Property::Kind, kind))
+                }
+                Filter::Title(title) if is_calendar => {
+                    filters.push(query::Filter::has_text(Property::Title, title))
+                }
+                Filter::Text(text) => filters.push(query::Filter::has_text(Property::Text, text)),
+                Filter::Before(before) if is_calendar => filters.push(query::Filter::lt(
+                    Property::UtcStart,
+                    before.timestamp() as u64,
+                )),
+                Filter::After(after) if is_calendar => filters.push(query::Filter::gt(
+                    Property::UtcEnd,
+                    after.timestamp() as u64,
+                )),
+                Filter::And | Filter::Or | Filter::Not | Filter::Close => {
+                    filters.push(cond.into());
+                }
+
+                other => {
+                    return Err(trc::JmapEvent::UnsupportedFilter
+                        .into_err()
+                        .details(other.to_string()))
+                }
+            }
+        }
+
+        let mut result_set = self.filter(account_id, kind.item, filters).await?;
+        if access_token.is_shared(account_id) {
+            result_set.apply_mask(
+                self.shared_items(access_token, account_id, kind, Acl::ReadItems)
+                    .await?,
+            );
+        }
+        let (response, paginate) = self.build_query_response(&result_set, &request).await?;
+
+        if let Some(paginate) = paginate {
+            // Parse sort criteria
+            let mut comparators = Vec::with_capacity(request.sort.as_ref().map_or(1, |s| s.len()));
+            for comparator in request
+                .sort
+                .and_then(|s| if !s.is_empty() { s.into() } else { None })
+                .unwrap_or_else(|| {
+                    vec![Comparator::ascending(if is_calendar {
+                        SortProperty::Start
+                    } else {
+                        SortProperty::Uid
+                    })]
+                })
+            {
+                comparators.push(match comparator.property {
+                    SortProperty::Uid => {
+                        query::Comparator::field(Property::Uid, comparator.is_ascending)
+                    }
+                    SortProperty::Start if is_calendar => {
+                        query::Comparator::field(Property::UtcStart, comparator.is_ascending)
+                    }
+                    SortProperty::Title if is_calendar => {
+                        query::Comparator::field(Property::Title, comparator.is_ascending)
+                    }
+
+                    other => {
+                        return Err(trc::JmapEvent::UnsupportedSort
+                            .into_err()
+                            .details(other.to_string()))
+                    }
+                });
+            }
+
+            // Sort results
+            self.sort(result_set, comparators, paginate, response).await
+        } else {
+            Ok(response)
+        }
+    }
+}
@@ -0,0 +1,912 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{auth::AccessToken, Server};
+use email::groupware::{container_ids, item_tombstones, object_changes, GroupwareType};
+use jmap_proto::{
+    error::set::{SetError, SetErrorType},
+    method::set::{RequestArguments, SetRequest, SetResponse},
+    object::{index::ObjectIndexBuilder, Object},
+    response::references::EvalObjectReferences,
+    types::{
+        acl::Acl,
+        collection::Collection,
+        id::Id,
+        property::Property,
+        state::StateChange,
+        value::{MaybePatchValue, SetValue, Value},
+    },
+};
+use store::{
+    query::Filter,
+    roaring::RoaringBitmap,
+    write::{assert::HashedValue, log::ChangeLogBuilder, BatchBuilder},
+};
+use trc::AddContext;
+
+use crate::{
+    auth::acl::{AclMethods, EffectiveAcl},
+    mailbox::set::MailboxSubscribe,
+    JmapMethods,
+};
+
+use std::future::Future;
+
+pub struct SetContext<'x> {
+    account_id: u32,
+    access_token: &'x AccessToken,
+    kind: &'static GroupwareType,
+    is_shared: bool,
+    response: SetResponse,
+    container_ids: RoaringBitmap,
+    will_destroy: Vec<Id>,
+}
+
+pub trait GroupwareSet: Sync + Send {
+    fn container_set(
+        &self,
+        request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<SetResponse>> + Send;
+
+    fn container_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        update: Option<(u32, HashedValue<Object<Value>>)>,
+        ctx: &SetContext,
+    ) -> impl Future<Output = trc::Result<Result<ObjectIndexBuilder, SetError>>> + Send;
+
+    fn container_destroy(
+        &self,
+        document_id: u32,
+        changes: &mut ChangeLogBuilder,
+        ctx: &SetContext,
+    ) -> impl Future<Output = trc::Result<Result<(), SetError>>> + Send;
+
+    fn item_set(
+        &self,
+        request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> impl Future<Output = trc::Result<SetResponse>> + Send;
+
+    fn item_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        update: Option<(u32, HashedValue<Object<Value>>)>,
+        ctx: &SetContext,
+    ) -> impl Future<Output = trc::Result<Result<ObjectIndexBuilder, SetError>>> + Send;
+
+    fn item_destroy(
+        &self,
+        document_id: u32,
+        changes: &mut ChangeLogBuilder,
+        ctx: &SetContext,
+    ) -> impl Future<Output = trc::Result<Result<(), SetError>>> + Send;
+}
+
+impl GroupwareSet for Server {
+    async fn container_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<SetResponse> {
+        // Prepare response
+        let account_id = request.account_id.document_id();
+        let mut ctx = SetContext {
+            account_id,
+            access_token,
+            kind,
+            is_shared: access_token.is_shared(account_id),
+            response: self.prepare_set_response(&request, kind.container).await?,
+            container_ids: self
+                .get_document_ids(account_id, kind.container)
+                .await?
+                .unwrap_or_default(),
+            will_destroy: request.unwrap_destroy(),
+        };
+
+        // Process creates
+        let mut changes = ChangeLogBuilder::new();
+        'create: for (id, object) in request.unwrap_create() {
+            if ctx.is_shared {
+                ctx.response.not_created.append(
+                    id,
+                    SetError::forbidden().with_description(format!(
+                        "You are not allowed to create {}s in this account.",
+                        kind.container_name
+                    )),
+                );
+                continue 'create;
+            }
+
+            match self.container_set_item(object, None, &ctx).await? {
+                Ok(builder) => {
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(kind.container)
+                        .create_document()
+                        .custom(builder);
+                    let document_id = self
+                        .core
+                        .storage
+                        .data
+                        .write(batch.build())
+                        .await
+                        .and_then(|ids| ids.last_document_id())
+                        .caused_by(trc::location!())?;
+                    changes.log_insert(kind.container, document_id);
+                    ctx.container_ids.insert(document_id);
+                    ctx.response.created(id, document_id);
+                }
+                Err(err) => {
+                    ctx.response.not_created.append(id, err);
+                    continue 'create;
+                }
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if ctx.will_destroy.contains(&id) {
+                ctx.response
+                    .not_updated
+                    .append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain container
+            let document_id = id.document_id();
+            let container = if let Some(container) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    kind.container,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                container
+            } else {
+                ctx.response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+
+            // Validate ACL
+            if ctx.is_shared {
+                let acl = container.inner.effective_acl(access_token);
+                if !acl.contains(Acl::Modify) {
+                    ctx.response.not_updated.append(
+                        id,
+                        SetError::forbidden().with_description(format!(
+                            "You are not allowed to modify this {}.",
+                            kind.container_name
+                        )),
+                    );
+                    continue 'update;
+                } else if object.properties.contains_key(&Property::Acl)
+                    && !acl.contains(Acl::Administer)
+                {
+                    ctx.response.not_updated.append(
+                        id,
+                        SetError::forbidden().with_description(format!(
+                            "You are not allowed to change the permissions of this {}.",
+                            kind.container_name
+                        )),
+                    );
+                    continue 'update;
+                }
+            }
+
+            match self
+                .container_set_item(object, (document_id, container).into(), &ctx)
+                .await?
+            {
+                Ok(builder) => {
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(kind.container)
+                        .update_document(document_id)
+                        .custom(builder);
+                    if !batch.is_empty() {
+                        match self.core.storage.data.write(batch.build()).await {
+                            Ok(_) => {
+                                changes.log_update(kind.container, document_id);
+                            }
+                            Err(err) if err.is_assertion_failure() => {
+                                ctx.response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(format!(
+                                        "Another process modified this {}, please try again.",
+                                        kind.container_name
+                                    )),
+                                );
+                                continue 'update;
+                            }
+                            Err(err) => {
+                                return Err(err.caused_by(trc::location!()));
+                            }
+                        }
+                    }
+                    ctx.response.updated.append(id, None);
+                }
+                Err(err) => {
+                    ctx.response.not_updated.append(id, err);
+                    continue 'update;
+                }
+            }
+        }
+
+        // Process deletions
+        for id in std::mem::take(&mut ctx.will_destroy) {
+            match self
+                .container_destroy(id.document_id(), &mut changes, &ctx)
+                .await?
+            {
+                Ok(_) => {
+                    ctx.response.destroyed.push(id);
+                }
+                Err(err) => {
+                    ctx.response.not_destroyed.append(id, err);
+                }
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            ctx.response.state_change = StateChange::new(account_id)
+                .with_change(kind.container_type, changes.change_id)
+                .into();
+            ctx.response.new_state = Some(self.commit_changes(account_id, changes).await?.into());
+        }
+
+        Ok(ctx.response)
+    }
+
+    async fn container_destroy(
+        &self,
+        document_id: u32,
+        changes: &mut ChangeLogBuilder,
+        ctx: &SetContext<'_>,
+    ) -> trc::Result<Result<(), SetError>> {
+        let kind = ctx.kind;
+
+        // Obtain container
+        let Some(container) = self
+            .get_property::<HashedValue<Object<Value>>>(
+                ctx.account_id,
+                kind.container,
+                document_id,
+                Property::Value,
+            )
+            .await?
+        else {
+            return Ok(Err(SetError::not_found()));
+        };
+
+        // Validate ACLs
+        if ctx.is_shared
+            && !container
+                .inner
+                .effective_acl(ctx.access_token)
+                .contains_any([Acl::Delete, Acl::Administer].into_iter())
+        {
+            return Ok(Err(SetError::forbidden().with_description(format!(
+                "You are not allowed to delete this {}.",
+                kind.container_name
+            ))));
+        }
+
+        // Verify that the container is empty
+        if !self
+            .filter(
+                ctx.account_id,
+                kind.item,
+                vec![Filter::eq(kind.container_ids.clone(), document_id)],
+            )
+            .await?
+            .results
+            .is_empty()
+        {
+            return Ok(Err(SetError::new(kind.has_contents.clone())
+                .with_description(format!(
+                    "The {} is not empty.",
+                    kind.container_name
+                ))));
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .with_account_id(ctx.account_id)
+            .with_collection(kind.container)
+            .delete_document(document_id)
+            .custom(ObjectIndexBuilder::new(kind.container_schema).with_current(container));
+
+        match self.core.storage.data.write(batch.build()).await {
+            Ok(_) => {
+                changes.log_delete(kind.container, document_id);
+                Ok(Ok(()))
+            }
+            Err(err) if err.is_assertion_failure() => Ok(Err(SetError::forbidden()
+                .with_description(format!(
+                    "Another process modified this {} while deleting it, please try again.",
+                    kind.container_name
+                )))),
+            Err(err) => Err(err.caused_by(trc::location!())),
+        }
+    }
+
+    async fn container_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        update: Option<(u32, HashedValue<Object<Value>>)>,
+        ctx: &SetContext<'_>,
+    ) -> trc::Result<Result<ObjectIndexBuilder, SetError>> {
+        let is_calendar = ctx.kind.container == Collection::Calendar;
+
+        // Parse properties
+        let mut changes = Object::with_capacity(changes_.properties.len());
+        for (property, value) in changes_.properties {
+            let value = match ctx.response.eval_object_references(value) {
+                Ok(value) => value,
+                Err(err) => {
+                    return Ok(Err(err));
+                }
+            };
+            let value = match (&property, value) {
+                (Property::Name, MaybePatchValue::Value(Value::Text(value))) => {
+                    Value::Text(value.trim().to_string())
+                }
+                (
+                    Property::Description,
+                    MaybePatchValue::Value(value @ (Value::Text(_) | Value::Null)),
+                ) => value,
+                (
+                    Property::Color,
+                    MaybePatchValue::Value(value @ (Value::Text(_) | Value::Null)),
+                ) if is_calendar => value,
+                (Property::IsVisible, MaybePatchValue::Value(Value::Bool(value)))
+                    if is_calendar =>
+                {
+                    Value::Bool(value)
+                }
+                (Property::SortOrder, MaybePatchValue::Value(Value::UnsignedInt(value))) => {
+                    Value::UnsignedInt(value)
+                }
+                (Property::IsDefault, MaybePatchValue::Value(Value::Bool(value))) => {
+                    Value::Bool(value)
+                }
+                (Property::IsSubscribed, MaybePatchValue::Value(Value::Bool(subscribe))) => {
+                    if let Some((_, current_fields)) = update.as_ref() {
+                        if let Some(value) = current_fields
+                            .inner
+                            .mailbox_subscribe(ctx.access_token.primary_id(), subscribe)
+                        {
+                            value
+                        } else {
+                            continue;
+                        }
+                    } else if subscribe {
+                        Value::List(vec![Value::Id(ctx.access_token.primary_id().into())])
+                    } else {
+                        continue;
+                    }
+                }
+                (Property::Acl, value) => {
+                    match self
+                        .acl_set(&mut changes, update.as_ref().map(|(_, obj)| obj), value)
+                        .await
+                    {
+                        Ok(_) => continue,
+                        Err(err) => {
+                            return Ok(Err(err));
+                        }
+                    }
+                }
+
+                _ => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Invalid property or value.".to_string())))
+                }
+            };
+
+            changes.append(property, value);
+        }
+
+        // Verify that there is only one default container
+        if changes.get(&Property::IsDefault) == &Value::Bool(true)
+            && update.as_ref().map_or(true, |(_, current)| {
+                current.inner.get(&Property::IsDefault) != &Value::Bool(true)
+            })
+            && !self
+                .filter(
+                    ctx.account_id,
+                    ctx.kind.container,
+                    vec![Filter::eq(Property::IsDefault, 1u32)],
+                )
+                .await?
+                .results
+                .is_empty()
+        {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(Property::IsDefault)
+                .with_description(format!(
+                    "Another {} is already the default.",
+                    ctx.kind.container_name
+                ))));
+        }
+
+        // Refresh ACLs
+        let current = update.map(|(_, current)| current);
+        if changes.properties.contains_key(&Property::Acl) {
+            self.refresh_acls(&changes, &current).await;
+        }
+
+        // Validate
+        Ok(ObjectIndexBuilder::new(ctx.kind.container_schema)
+            .with_changes(changes)
+            .with_current_opt(current)
+            .validate())
+    }
+
+    async fn item_set(
+        &self,
+        mut request: SetRequest<RequestArguments>,
+        access_token: &AccessToken,
+        kind: &'static GroupwareType,
+    ) -> trc::Result<SetResponse> {
+        // Prepare response
+        let account_id = request.account_id.document_id();
+        let mut ctx = SetContext {
+            account_id,
+            access_token,
+            kind,
+            is_shared: access_token.is_shared(account_id),
+            response: self.prepare_set_response(&request, kind.item).await?,
+            container_ids: self
+                .get_document_ids(account_id, kind.container)
+                .await?
+                .unwrap_or_default(),
+            will_destroy: request.unwrap_destroy(),
+        };
+
+        // Process creates
+        let mut changes = ChangeLogBuilder::new();
+        'create: for (id, object) in request.unwrap_create() {
+            match self.item_set_item(object, None, &ctx).await? {
+                Ok(builder) => {
+                    let uid = builder.get(&Property::Uid).clone();
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(kind.item)
+                        .create_document()
+                        .custom(builder);
+                    let document_id = self
+                        .core
+                        .storage
+                        .data
+                        .write(batch.build())
+                        .await
+                        .and_then(|ids| ids.last_document_id())
+                        .caused_by(trc::location!())?;
+                    changes.log_insert(kind.item, document_id);
+                    ctx.response.created(id.clone(), document_id);
+                    if let Some(created) = ctx.response.created.get_mut(&id) {
+                        created.append(Property::Uid, uid);
+                    }
+                }
+                Err(err) => {
+                    ctx.response.not_created.append(id, err);
+                    continue 'create;
+                }
+            }
+        }
+
+        // Process updates
+        'update: for (id, object) in request.unwrap_update() {
+            // Make sure id won't be destroyed
+            if ctx.will_destroy.contains(&id) {
+                ctx.response
+                    .not_updated
+                    .append(id, SetError::will_destroy());
+                continue 'update;
+            }
+
+            // Obtain item
+            let document_id = id.document_id();
+            let item = if let Some(item) = self
+                .get_property::<HashedValue<Object<Value>>>(
+                    account_id,
+                    kind.item,
+                    document_id,
+                    Property::Value,
+                )
+                .await?
+            {
+                item
+            } else {
+                ctx.response.not_updated.append(id, SetError::not_found());
+                continue 'update;
+            };
+
+            match self
+                .item_set_item(object, (document_id, item).into(), &ctx)
+                .await?
+            {
+                Ok(builder) => {
+                    let mut batch = BatchBuilder::new();
+                    batch
+                        .with_account_id(account_id)
+                        .with_collection(kind.item)
+                        .update_document(document_id)
+                        .custom(builder);
+                    if !batch.is_empty() {
+                        match self.core.storage.data.write(batch.build()).await {
+                            Ok(_) => {
+                                changes.log_update(kind.item, document_id);
+                            }
+                            Err(err) if err.is_assertion_failure() => {
+                                ctx.response.not_updated.append(
+                                    id,
+                                    SetError::forbidden().with_description(format!(
+                                        "Another process modified this {}, please try again.",
+                                        kind.item_name
+                                    )),
+                                );
+                                continue 'update;
+                            }
+                            Err(err) => {
+                                return Err(err.caused_by(trc::location!()));
+                            }
+                        }
+                    }
+                    ctx.response.updated.append(id, None);
+                }
+                Err(err) => {
+                    ctx.response.not_updated.append(id, err);
+                    continue 'update;
+                }
+            }
+        }
+
+        // Process deletions
+        for id in std::mem::take(&mut ctx.will_destroy) {
+            match self
+                .item_destroy(id.document_id(), &mut changes, &ctx)
+                .await?
+            {
+                Ok(_) => {
+                    ctx.response.destroyed.push(id);
+                }
+                Err(err) => {
+                    ctx.response.not_destroyed.append(id, err);
+                }
+            }
+        }
+
+        // Write changes
+        if !changes.is_empty() {
+            ctx.response.state_change = StateChange::new(account_id)
+                .with_change(kind.item_type, changes.change_id)
+                .into();
+            ctx.response.new_state = Some(self.commit_changes(account_id, changes).await?.into());
+        }
+
+        Ok(ctx.response)
+    }
+
+    async fn item_destroy(
+        &self,
+        document_id: u32,
+        changes: &mut ChangeLogBuilder,
+        ctx: &SetContext<'_>,
+    ) -> trc::Result<Result<(), SetError>> {
+        let kind = ctx.kind;
+
+        if let Some(item) = self
+            .get_property::<HashedValue<Object<Value>>>(
+                ctx.account_id,
+                kind.item,
+                document_id,
+                Property::Value,
+            )
+            .await?
+        {
+            // Validate ACLs
+            if ctx.is_shared
+                && !self
+                    .has_container_access(ctx, container_ids(&item.inner, kind), Acl::RemoveItems)
+                    .await?
+            {
+                return Ok(Err(SetError::forbidden().with_description(format!(
+                    "You are not allowed to delete this {}.",
+                    kind.item_name
+                ))));
+            }
+
+            let tombstones = item_tombstones(
+                kind,
+                &item.inner,
+                self.get_property::<Value>(
+                    ctx.account_id,
+                    kind.item,
+                    document_id,
+                    Property::DavTombstones,
+                )
+                .await?,
+            );
+            let mut batch = BatchBuilder::new();
+            batch
+                .with_account_id(ctx.account_id)
+                .with_collection(kind.item)
+                .delete_document(document_id)
+                .custom(ObjectIndexBuilder::new(kind.item_schema).with_current(item))
+                .value(Property::DavTombstones, tombstones, 0);
+
+            match self.core.storage.data.write(batch.build()).await {
+                Ok(_) => {
+                    changes.log_delete(kind.item, document_id);
+                    Ok(Ok(()))
+                }
+                Err(err) if err.is_assertion_failure() => Ok(Err(SetError::forbidden()
+                    .with_description(format!(
+                        "Another process modified this {} while deleting it, please try again.",
+                        kind.item_name
+                    )))),
+                Err(err) => Err(err.caused_by(trc::location!())),
+            }
+        } else {
+            Ok(Err(SetError::not_found()))
+        }
+    }
+
+    async fn item_set_item(
+        &self,
+        changes_: Object<SetValue>,
+        update: Option<(u32, HashedValue<Object<Value>>)>,
+        ctx: &SetContext<'_>,
+    ) -> trc::Result<Result<ObjectIndexBuilder, SetError>> {
+        let kind = ctx.kind;
+        let mut item = update
+            .as_ref()
+            .map(|(_, current)| current.inner.clone())
+            .unwrap_or_default();
+        let current_container_ids = container_ids(&item, kind);
+        let mut new_container_ids = current_container_ids.clone();
+
+        // Validate ACL
+        if ctx.is_shared
+            && update.is_some()
+            && !self
+                .has_container_access(ctx, current_container_ids.clone(), Acl::ModifyItems)
+                .await?
+        {
+            return Ok(Err(SetError::forbidden().with_description(format!(
+                "You are not allowed to modify this {}.",
+                kind.item_name
+            ))));
+        }
+
+        // The raw vCard/iCalendar is regenerated once modified through JMAP
+        if update.is_some() {
+            item.properties.remove(&Property::DavData);
+        }
+
+        // Parse properties
+        for (property, value) in changes_.properties {
+            let value = match ctx.response.eval_object_references(value) {
+                Ok(value) => value,
+                Err(err) => {
+                    return Ok(Err(err));
+                }
+            };
+            match (property, value) {
+                (property, MaybePatchValue::Value(Value::List(ids)))
+                    if property == kind.container_ids =>
+                {
+                    new_container_ids = ids
+                        .into_iter()
+                        .filter_map(|id| id.try_unwrap_id()?.document_id().into())
+                        .collect();
+                }
+                (property, MaybePatchValue::Patch(patch)) if property == kind.container_ids => {
+                    let mut patch = patch.into_iter();
+                    if let Some(document_id) = patch.next().unwrap().try_unwrap_id() {
+                        let document_id = document_id.document_id();
+                        if patch.next().unwrap().try_unwrap_bool().unwrap_or_default() {
+                            if !new_container_ids.contains(&document_id) {
+                                new_container_ids.push(document_id);
+                            }
+                        } else {
+                            new_container_ids.retain(|id| id != &document_id);
+                        }
+                    }
+                }
+                (
+                    property @ (Property::Id
+                    | Property::Text
+                    | Property::DavName
+                    | Property::DavData
+                    | Property::UtcStart
+                    | Property::UtcEnd
+                    | Property::AddressBookIds
+                    | Property::CalendarIds),
+                    _,
+                ) => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Invalid property or value.".to_string())));
+                }
+                (Property::_T(path), MaybePatchValue::Value(value)) if path.contains('/') => {
+                    if !item.patch(&path, value) {
+                        return Ok(Err(SetError::new(SetErrorType::InvalidPatch)
+                            .with_property(Property::_T(path))
+                            .with_description("Patch path does not exist.")));
+                    }
+                }
+                (property, MaybePatchValue::Value(Value::Null)) => {
+                    item.properties.remove(&property);
+                }
+                (property, MaybePatchValue::Value(value)) => {
+                    item.set(property, value);
+                }
+                (property, MaybePatchValue::Patch(_)) => {
+                    return Ok(Err(SetError::invalid_properties()
+                        .with_property(property)
+                        .with_description("Invalid property or value.".to_string())));
+                }
+            }
+        }
+
+        // Validate containers
+        if new_container_ids.is_empty() {
+            return Ok(Err(SetError::invalid_properties()
+                .with_property(kind.container_ids.clone())
+                .with_description(format!(
+                    "The {} has to belong to at least one {}.",
+                    kind.item_name, kind.container_name
+                ))));
+        }
+        for container_id in &new_container_ids {
+            if !ctx.container_ids.contains(*container_id) {
+                return Ok(Err(SetError::invalid_properties()
+                    .with_property(kind.container_ids.clone())
+                    .with_description(format!(
+                        "{} {} does not exist.",
+                        kind.container_name,
+                        Id::from(*container_id)
+                    ))));
+            }
+        }
+        if ctx.is_shared {
+            let added_ids = new_container_ids
+                .iter()
+                .filter(|id| !current_container_ids.contains(id))
+                .copied()
+                .collect::<Vec<_>>();
+            let removed_ids = current_container_ids
+                .iter()
+                .filter(|id| !new_container_ids.contains(id))
+                .copied()
+                .collect::<Vec<_>>();
+            for (container_ids, acl) in
+                [(added_ids, Acl::AddItems), (removed_ids, Acl::RemoveItems)]
+            {
+                for container_id in container_ids {
+                    if !self
+                        .has_container_access(ctx, vec![container_id], acl)
+                        .await?
+                    {
+                        return Ok(Err(SetError::forbidden().with_description(format!(
+                            "You are not allowed to modify {} {}.",
+                            kind.container_name,
+                            Id::from(container_id)
+                        ))));
+                    }
+                }
+            }
+        }
+        item.set(
+            kind.container_ids.clone(),
+            Value::List(
+                new_container_ids
+                    .into_iter()
+                    .map(|id| Value::Id(id.into()))
+                    .collect(),
+            ),
+        );
+
+        // Generate a unique identifier if missing
+        if update.is_none() && item.get(&Property::Uid) == &Value::Null {
+            item.set(
+                Property::Uid,
+                Value::Text(format!("{:032x}", rand::random::<u128>())),
+            );
+        }
+        kind.derive_properties(&mut item);
+
+        // Verify that the uid is unique
+        if let Value::Text(uid) = item.get(&Property::Uid) {
+            if update.as_ref().map_or(true, |(_, current)| {
+                current.inner.get(&Property::Uid).as_string() != Some(uid)
+            }) {
+                if let Some(document_id) = self
+                    .filter(
+                        ctx.account_id,
+                        kind.item,
+                        vec![Filter::eq(Property::Uid, uid.as_str())],
+                    )
+                    .await?
+                    .results
+                    .min()
+                {
+                    return Ok(Err(SetError::already_exists()
+                        .with_existing_id(document_id.into())
+                        .with_description(format!(
+                            "A {} with uid '{}' already exists.",
+                            kind.item_name, uid
+                        ))));
+                }
+            }
+        }
+
+        // Obtain changes
+        let current = update.map(|(_, current)| current);
+        let changes = if let Some(current) = &current {
+            object_changes(&current.inner, item)
+        } else {
+            item
+        };
+
+        // Validate
+        Ok(ObjectIndexBuilder::new(kind.item_schema)
+            .with_changes(changes)
+            .with_current_opt(current)
+            .validate())
+    }
+}
+
+trait ContainerAccess {
+    fn has_container_access(
+        &self,
+        ctx: &SetContext<'_>,
+        container_ids: Vec<u32>,
+        check_acls: Acl,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+}
+
+impl ContainerAccess for Server {
+    async fn has_container_access(
+        &self,
+        ctx: &SetContext<'_>,
+        container_ids: Vec<u32>,
+        check_acls: Acl,
+    ) -> trc::Result<bool> {
+        for container_id in container_ids {
+            if self
+                .has_access_to_document(
+                    ctx.access_token,
+                    ctx.account_id,
+                    ctx.kind.container,
+                    container_id,
+                    check_acls,
+                )
+                .await?
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
@@ -33,7 +33,9 @@ pub mod api;
 pub mod auth;
 pub mod blob;
 pub mod changes;
+pub mod dav;
 pub mod email;
+pub mod groupware;
 pub mod identity;
 pub mod mailbox;
 pub mod principal;
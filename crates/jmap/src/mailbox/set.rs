@@ -455,13 +455,34 @@ impl MailboxSet for Server {
                 }
             }
 
+            // Obtain annotations
+            let annotations = self
+                .mailbox_get_annotations(account_id, Some(document_id))
+                .await?;
+
             let mut batch = BatchBuilder::new();
             batch
                 .with_account_id(account_id)
                 .with_collection(Collection::Mailbox)
-                .delete_document(document_id)
+                .delete_document(document_id);
+            if let Some(annotations) = &annotations {
+                batch.assert_value(Property::Annotations, annotations);
+            }
+            batch
                 .value(Property::EmailIds, (), F_VALUE | F_CLEAR)
+                .value(Property::Annotations, (), F_VALUE | F_CLEAR)
+                .value(Property::AccessKey, (), F_VALUE | F_CLEAR)
                 .custom(ObjectIndexBuilder::new(SCHEMA).with_current(mailbox));
+            if let Some(annotations) = annotations {
+                // Release the annotation storage used by this mailbox
+                batch
+                    .with_collection(Collection::Principal)
+                    .update_document(0)
+                    .add(
+                        Property::AnnotationsSize,
+                        -(annotations.inner.size() as i64),
+                    );
+            }
 
             match self.core.storage.data.write(batch.build()).await {
                 Ok(_) => {
@@ -0,0 +1,431 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, future::Future, time::Duration};
+
+use common::{config::smtp::auth::build_signature, Server};
+use mail_auth::{common::headers::HeaderWriter, AuthenticatedMessage, DkimResult};
+use mail_builder::{headers::HeaderType, MessageBuilder};
+use mail_parser::DateTime;
+use smtp::{inbound::DkimSign, reporting::SmtpReporting};
+use store::{rand, write::now};
+use trc::{AddContext, DkimEvent};
+use utils::config::{utils::ParseValue, Config};
+
+use crate::{
+    api::management::dkim::{
+        dkim_txt_record, generate_dkim_private_key, obtain_dkim_public_key, Algorithm,
+    },
+    services::dns_sync::DnsSync,
+};
+
+pub trait DkimRotation: Sync + Send {
+    fn dkim_rotate(&self) -> impl Future<Output = ()> + Send;
+
+    fn dkim_rotate_signature(
+        &self,
+        id: &str,
+        keys: &BTreeMap<String, String>,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn dkim_rotation_notify(
+        &self,
+        subject: String,
+        body: String,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn dkim_rotation_publish(&self, domain: &str) -> impl Future<Output = ()> + Send;
+}
+
+// Rotation state is kept next to the signature settings:
+//
+// signature.<id>.rotation.frequency        how often the key is replaced
+// signature.<id>.rotation.last             when the current key was activated
+// signature.<id>.rotation.pending.*        next key, waiting for its DNS record
+// signature.<id>.rotation.retired.*        previous key, published until it expires
+impl DkimRotation for Server {
+    async fn dkim_rotate(&self) {
+        let keys = match self.core.storage.config.list("signature.", true).await {
+            Ok(keys) => keys,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to list DKIM signatures.")
+                    .caused_by(trc::location!()));
+                return;
+            }
+        };
+
+        let mut has_changes = false;
+        for id in keys
+            .keys()
+            .filter_map(|key| key.strip_suffix(".rotation.frequency"))
+        {
+            match self.dkim_rotate_signature(id, &keys).await {
+                Ok(changed) => {
+                    has_changes |= changed;
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .ctx(trc::Key::Id, id.to_string())
+                        .details("Failed to rotate DKIM key."));
+                }
+            }
+        }
+
+        // Reload signatures
+        if has_changes {
+            match self.reload().await {
+                Ok(result) => {
+                    if let Some(new_core) = result.new_core {
+                        // Update core
+                        self.inner.shared_core.store(new_core.into());
+
+                        // Increment version counter
+                        self.increment_config_version();
+                    }
+                }
+                Err(err) => {
+                    trc::error!(err.details("Failed to reload configuration."));
+                }
+            }
+        }
+    }
+
+    async fn dkim_rotate_signature(
+        &self,
+        id: &str,
+        keys: &BTreeMap<String, String>,
+    ) -> trc::Result<bool> {
+        let value = |key: &str| keys.get(&format!("{id}.{key}")).map(|v| v.as_str());
+        let (Some(frequency), Some(algo), Some(domain), Some(selector), Some(pk)) = (
+            value("rotation.frequency")
+                .and_then(|v| Duration::parse_value(v).ok())
+                .map(|v| v.as_secs()),
+            value("algorithm").and_then(|v| v.parse::<Algorithm>().ok()),
+            value("domain"),
+            value("selector"),
+            value("private-key"),
+        ) else {
+            trc::event!(
+                Dkim(DkimEvent::RotationError),
+                Id = id.to_string(),
+                Reason = "Incomplete signature settings.",
+            );
+            return Ok(false);
+        };
+        if domain.contains("%{") || pk.contains("%{") || selector.contains("%{") {
+            trc::event!(
+                Dkim(DkimEvent::RotationError),
+                Id = id.to_string(),
+                Reason = "Signatures using macros cannot be rotated.",
+            );
+            return Ok(false);
+        }
+
+        let rotation = &self.core.smtp.mail_auth.dkim.rotation;
+        let now = now();
+        // Retire the previous key once its grace period is over
+        if let Some(retired_selector) = value("rotation.retired.selector") {
+            if value("rotation.retired.until")
+                .and_then(|v| v.parse::<u64>().ok())
+                .is_none_or(|until| until <= now)
+            {
+                self.core
+                    .storage
+                    .config
+                    .clear_prefix(format!("signature.{id}.rotation.retired."))
+                    .await
+                    .caused_by(trc::location!())?;
+
+                self.dkim_rotation_publish(domain).await;
+
+                trc::event!(
+                    Dkim(DkimEvent::KeyRetired),
+                    Id = id.to_string(),
+                    Domain = domain.to_string(),
+                    Details = retired_selector.to_string(),
+                );
+
+                self.dkim_rotation_notify(
+                    format!("DKIM key {retired_selector} for {domain} retired"),
+                    format!(
+                        concat!(
+                            "The DKIM key with selector {0} for {1} is no longer in use.\r\n",
+                            "The following DNS record can now be removed:\r\n\r\n",
+                            "{0}._domainkey.{1}. TXT\r\n"
+                        ),
+                        retired_selector, domain
+                    ),
+                )
+                .await;
+            }
+        }
+
+        // Start the rotation clock for signatures that were never rotated
+        let Some(last) = value("rotation.last").and_then(|v| v.parse::<u64>().ok()) else {
+            self.core
+                .storage
+                .config
+                .set(
+                    [(format!("signature.{id}.rotation.last"), now.to_string())],
+                    true,
+                )
+                .await
+                .caused_by(trc::location!())?;
+            return Ok(false);
+        };
+        let due = last + frequency;
+
+        match (
+            value("rotation.pending.selector"),
+            value("rotation.pending.private-key"),
+        ) {
+            (Some(next_selector), Some(next_pk)) if now >= due => {
+                // Switch only after the new record resolves, which is checked by
+                // verifying a message signed with the staged key
+                let mut config = Config::default();
+                let prefix = format!("{id}.");
+                for (key, value) in keys.iter().filter(|(key, _)| {
+                    key.strip_prefix(&prefix)
+                        .is_some_and(|key| !key.starts_with("rotation."))
+                }) {
+                    config
+                        .keys
+                        .insert(format!("signature.{key}"), value.to_string());
+                }
+                config.keys.insert(
+                    format!("signature.{id}.selector"),
+                    next_selector.to_string(),
+                );
+                config
+                    .keys
+                    .insert(format!("signature.{id}.private-key"), next_pk.to_string());
+                let Some((signer, _)) = build_signature(&mut config, id) else {
+                    trc::event!(
+                        Dkim(DkimEvent::RotationError),
+                        Id = id.to_string(),
+                        Reason = "Failed to build the staged key.",
+                    );
+                    return Ok(false);
+                };
+                let message = format!(
+                    concat!(
+                        "From: <postmaster@{}>\r\n",
+                        "Subject: DKIM key verification\r\n",
+                        "\r\n",
+                        "Test.\r\n"
+                    ),
+                    domain
+                );
+                let mut signed = Vec::with_capacity(message.len() + 1024);
+                signer
+                    .sign(message.as_bytes())
+                    .map_err(|err| trc::Error::from(err).caused_by(trc::location!()))?
+                    .write_header(&mut signed);
+                signed.extend_from_slice(message.as_bytes());
+                let is_published = match AuthenticatedMessage::parse(&signed) {
+                    Some(auth_message) => self
+                        .core
+                        .smtp
+                        .resolvers
+                        .dns
+                        .verify_dkim(self.inner.cache.build_auth_parameters(&auth_message))
+                        .await
+                        .iter()
+                        .any(|d| matches!(d.result(), DkimResult::Pass)),
+                    None => false,
+                };
+
+                if !is_published {
+                    trc::event!(
+                        Dkim(DkimEvent::KeyNotPublished),
+                        Id = id.to_string(),
+                        Domain = domain.to_string(),
+                        Details = next_selector.to_string(),
+                        Reason = "DNS record does not resolve to the staged key.",
+                    );
+                    return Ok(false);
+                }
+
+                // Keep the current key published during the grace period
+                let retired_until = now + rotation.grace_period;
+                self.core
+                    .storage
+                    .config
+                    .set(
+                        [
+                            (format!("signature.{id}.private-key"), next_pk.to_string()),
+                            (
+                                format!("signature.{id}.selector"),
+                                next_selector.to_string(),
+                            ),
+                            (format!("signature.{id}.rotation.last"), now.to_string()),
+                            (
+                                format!("signature.{id}.rotation.retired.selector"),
+                                selector.to_string(),
+                            ),
+                            (
+                                format!("signature.{id}.rotation.retired.private-key"),
+                                pk.to_string(),
+                            ),
+                            (
+                                format!("signature.{id}.rotation.retired.until"),
+                                retired_until.to_string(),
+                            ),
+                        ],
+                        true,
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+                self.core
+                    .storage
+                    .config
+                    .clear_prefix(format!("signature.{id}.rotation.pending."))
+                    .await
+                    .caused_by(trc::location!())?;
+
+                trc::event!(
+                    Dkim(DkimEvent::KeyActivated),
+                    Id = id.to_string(),
+                    Domain = domain.to_string(),
+                    Details = next_selector.to_string(),
+                    Expires = trc::Value::Timestamp(retired_until),
+                );
+
+                self.dkim_rotation_notify(
+                    format!("DKIM key {next_selector} for {domain} activated"),
+                    format!(
+                        concat!(
+                            "Messages from {1} are now signed using the DKIM key with selector {0}.\r\n",
+                            "The previous key {2} will remain in use for verification until {3}.\r\n"
+                        ),
+                        next_selector,
+                        domain,
+                        selector,
+                        DateTime::from_timestamp(retired_until as i64).to_rfc822()
+                    ),
+                )
+                .await;
+
+                Ok(true)
+            }
+            (None, _) | (_, None) if now + rotation.lead_time >= due => {
+                // Stage the next key ahead of time so it can be published
+                let dt = DateTime::from_timestamp(now as i64);
+                let mut next_selector = format!(
+                    "{:04}{:02}{:02}{}",
+                    dt.year,
+                    dt.month,
+                    dt.day,
+                    algo.selector_suffix()
+                );
+                if next_selector == selector
+                    || value("rotation.retired.selector") == Some(next_selector.as_str())
+                {
+                    next_selector.push_str(&format!("{:04x}", rand::random::<u16>()));
+                }
+                let next_pk = generate_dkim_private_key(algo)?;
+                let record = dkim_txt_record(algo, &obtain_dkim_public_key(algo, &next_pk)?);
+                self.core
+                    .storage
+                    .config
+                    .set(
+                        [
+                            (
+                                format!("signature.{id}.rotation.pending.selector"),
+                                next_selector.clone(),
+                            ),
+                            (
+                                format!("signature.{id}.rotation.pending.private-key"),
+                                next_pk,
+                            ),
+                        ],
+                        true,
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+
+                self.dkim_rotation_publish(domain).await;
+
+                trc::event!(
+                    Dkim(DkimEvent::KeyStaged),
+                    Id = id.to_string(),
+                    Domain = domain.to_string(),
+                    Details = next_selector.clone(),
+                    Expires = trc::Value::Timestamp(due),
+                );
+
+                self.dkim_rotation_notify(
+                    format!("DKIM key {next_selector} for {domain} staged"),
+                    format!(
+                        concat!(
+                            "A new DKIM key for {1} was generated. Please publish the following\r\n",
+                            "DNS record, the key will be activated after {2} once the record\r\n",
+                            "can be resolved:\r\n\r\n",
+                            "{0}._domainkey.{1}. TXT \"{3}\"\r\n"
+                        ),
+                        next_selector,
+                        domain,
+                        DateTime::from_timestamp(due as i64).to_rfc822(),
+                        record
+                    ),
+                )
+                .await;
+
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    async fn dkim_rotation_publish(&self, domain: &str) {
+        // Update the zone right away when its records are managed
+        if let Err(err) = self.dns_sync_domain(domain, false).await {
+            trc::error!(err
+                .ctx(trc::Key::Domain, domain.to_string())
+                .details("Failed to publish DKIM records."));
+        }
+    }
+
+    async fn dkim_rotation_notify(&self, subject: String, body: String) {
+        let rotation = &self.core.smtp.mail_auth.dkim.rotation;
+        if rotation.notify.is_empty() {
+            return;
+        }
+
+        let from_addr = self
+            .core
+            .storage
+            .config
+            .get("report.domain")
+            .await
+            .ok()
+            .flatten()
+            .map(|domain| format!("postmaster@{domain}"))
+            .unwrap_or_else(|| format!("postmaster@{}", self.core.network.server_name));
+        let message = MessageBuilder::new()
+            .from(("DKIM Key Rotation", from_addr.as_str()))
+            .to(rotation
+                .notify
+                .iter()
+                .map(|addr| addr.as_str())
+                .collect::<Vec<_>>())
+            .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+            .subject(subject)
+            .text_body(body)
+            .write_to_vec()
+            .unwrap_or_default();
+
+        self.send_autogenerated(
+            from_addr.as_str(),
+            rotation.notify.iter().cloned(),
+            message,
+            None,
+            0,
+        )
+        .await;
+    }
+}
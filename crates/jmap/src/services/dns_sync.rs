@@ -0,0 +1,298 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, future::Future};
+
+use common::Server;
+use serde::{Deserialize, Serialize};
+use trc::{AddContext, DnsSyncEvent};
+
+use crate::api::management::dns::{DnsManagement, DnsRecord};
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DnsRecordChange {
+    pub action: DnsChangeAction,
+    #[serde(flatten)]
+    pub record: DnsRecord,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DnsChangeAction {
+    Create,
+    Delete,
+    Conflict,
+}
+
+pub trait DnsSync: Sync + Send {
+    fn dns_sync(&self) -> impl Future<Output = ()> + Send;
+
+    fn dns_sync_domain(
+        &self,
+        domain: &str,
+        dry_run: bool,
+    ) -> impl Future<Output = trc::Result<Option<Vec<DnsRecordChange>>>> + Send;
+}
+
+impl DnsSync for Server {
+    async fn dns_sync(&self) {
+        for provider in self.core.dns_sync.providers.values() {
+            for domain in &provider.domains {
+                if let Err(err) = self.dns_sync_domain(domain, false).await {
+                    trc::error!(err
+                        .ctx(trc::Key::Domain, domain.to_string())
+                        .details("Failed to synchronize DNS records."));
+                }
+            }
+        }
+    }
+
+    async fn dns_sync_domain(
+        &self,
+        domain: &str,
+        dry_run: bool,
+    ) -> trc::Result<Option<Vec<DnsRecordChange>>> {
+        let domain = domain.trim_end_matches('.').to_lowercase();
+        let Some(provider) = self.core.dns_sync.get(&domain).cloned() else {
+            return Ok(None);
+        };
+        let origin = format!("{}.", provider.origin(&domain));
+        let in_zone = |name: &str| name == origin || name.ends_with(&format!(".{origin}"));
+
+        // Records expected in the zone, grouped by type and name
+        let mut expected: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        for record in self.build_dns_records(&domain).await? {
+            let name = record.name.to_lowercase();
+            if in_zone(&name) {
+                let contents = expected.entry((record.typ, name)).or_default();
+                if !contents.contains(&record.content) {
+                    contents.push(record.content);
+                }
+            }
+        }
+
+        // Records pushed by the last sync, used to remove the ones that are
+        // no longer generated (for instance retired DKIM keys)
+        let published_key = format!("dns-sync.{}.published.{domain}", provider.id);
+        let mut published: BTreeMap<(String, String), Vec<String>> = BTreeMap::new();
+        if let Some(value) = self
+            .core
+            .storage
+            .config
+            .get(&published_key)
+            .await
+            .caused_by(trc::location!())?
+        {
+            for record in serde_json::from_str::<Vec<DnsRecord>>(&value).unwrap_or_default() {
+                published
+                    .entry((record.typ, record.name))
+                    .or_default()
+                    .push(record.content);
+            }
+        }
+
+        let mut client = provider.connect().await?;
+        let mut deletions = Vec::new();
+        let mut creations = Vec::new();
+        let mut conflicts = Vec::new();
+        for ((typ, name), contents) in &expected {
+            let current = client.lookup(name, typ).await?;
+            let was_published = published.get(&(typ.to_string(), name.to_string()));
+
+            for content in contents {
+                if !current
+                    .iter()
+                    .any(|(current, _)| is_same_record(typ, current, content))
+                {
+                    creations.push(DnsRecord {
+                        typ: typ.to_string(),
+                        name: name.to_string(),
+                        content: content.to_string(),
+                    });
+                }
+            }
+
+            for (content, record) in current {
+                if contents.iter().any(|c| is_same_record(typ, &content, c)) {
+                    continue;
+                }
+
+                // Only records published by this server are removed, the ones
+                // created elsewhere are reported when they conflict
+                let change = DnsRecord {
+                    typ: typ.to_string(),
+                    name: name.to_string(),
+                    content,
+                };
+                if was_published
+                    .is_some_and(|p| p.iter().any(|c| is_same_record(typ, &change.content, c)))
+                {
+                    deletions.push((change, record));
+                } else if is_conflict(typ, &change.content, contents) {
+                    conflicts.push(change);
+                }
+            }
+        }
+        for ((typ, name), contents) in &published {
+            if expected.contains_key(&(typ.to_string(), name.to_string())) || !in_zone(name) {
+                continue;
+            }
+
+            for (content, record) in client.lookup(name, typ).await? {
+                if contents.iter().any(|c| is_same_record(typ, &content, c)) {
+                    deletions.push((
+                        DnsRecord {
+                            typ: typ.to_string(),
+                            name: name.to_string(),
+                            content,
+                        },
+                        record,
+                    ));
+                }
+            }
+        }
+
+        let mut changes = Vec::with_capacity(deletions.len() + creations.len() + conflicts.len());
+        if dry_run {
+            changes.extend(
+                deletions
+                    .into_iter()
+                    .map(|(record, _)| DnsRecordChange {
+                        action: DnsChangeAction::Delete,
+                        record,
+                    })
+                    .chain(creations.into_iter().map(|record| DnsRecordChange {
+                        action: DnsChangeAction::Create,
+                        record,
+                    }))
+                    .chain(conflicts.into_iter().map(|record| DnsRecordChange {
+                        action: DnsChangeAction::Conflict,
+                        record,
+                    })),
+            );
+            return Ok(Some(changes));
+        }
+
+        // Conflicting records have to be removed by the zone administrator
+        for record in conflicts {
+            trc::event!(
+                DnsSync(DnsSyncEvent::RecordConflict),
+                Id = provider.id.to_string(),
+                Domain = domain.to_string(),
+                Hostname = record.name.to_string(),
+                Type = record.typ.to_string(),
+                Details = record.content.to_string(),
+            );
+
+            changes.push(DnsRecordChange {
+                action: DnsChangeAction::Conflict,
+                record,
+            });
+        }
+
+        // Remove stale records first so CNAMEs can be replaced
+        for (record, rr) in deletions {
+            client
+                .delete(&origin, rr)
+                .await
+                .add_context(|err| err.ctx(trc::Key::Domain, domain.to_string()))?;
+
+            trc::event!(
+                DnsSync(DnsSyncEvent::RecordDeleted),
+                Id = provider.id.to_string(),
+                Domain = domain.to_string(),
+                Hostname = record.name.to_string(),
+                Type = record.typ.to_string(),
+                Details = record.content.to_string(),
+            );
+
+            changes.push(DnsRecordChange {
+                action: DnsChangeAction::Delete,
+                record,
+            });
+        }
+        for record in creations {
+            client
+                .create(&origin, &record.typ, &record.name, &record.content)
+                .await
+                .add_context(|err| err.ctx(trc::Key::Domain, domain.to_string()))?;
+
+            trc::event!(
+                DnsSync(DnsSyncEvent::RecordCreated),
+                Id = provider.id.to_string(),
+                Domain = domain.to_string(),
+                Hostname = record.name.to_string(),
+                Type = record.typ.to_string(),
+                Details = record.content.to_string(),
+            );
+
+            changes.push(DnsRecordChange {
+                action: DnsChangeAction::Create,
+                record,
+            });
+        }
+
+        // Remember what was published
+        let published = expected
+            .into_iter()
+            .flat_map(|((typ, name), contents)| {
+                contents.into_iter().map(move |content| DnsRecord {
+                    typ: typ.clone(),
+                    name: name.clone(),
+                    content,
+                })
+            })
+            .collect::<Vec<_>>();
+        self.core
+            .storage
+            .config
+            .set(
+                [(
+                    published_key,
+                    serde_json::to_string(&published).unwrap_or_default(),
+                )],
+                true,
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            DnsSync(DnsSyncEvent::ZoneSynced),
+            Id = provider.id.to_string(),
+            Domain = domain,
+            Total = changes.len(),
+        );
+
+        Ok(Some(changes))
+    }
+}
+
+fn is_same_record(typ: &str, a: &str, b: &str) -> bool {
+    if typ == "TXT" {
+        a == b
+    } else {
+        a.eq_ignore_ascii_case(b)
+    }
+}
+
+// Records that cannot coexist with the expected ones at the same name
+fn is_conflict(typ: &str, content: &str, expected: &[String]) -> bool {
+    match typ {
+        "TXT" => expected.iter().any(|c| txt_tag(c) == txt_tag(content)),
+        "MX" => false,
+        _ => true,
+    }
+}
+
+// Version tag of a TXT record, such as "v=spf1" or "v=DKIM1"
+fn txt_tag(content: &str) -> String {
+    content
+        .split([';', ' '])
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_ascii_lowercase()
+}
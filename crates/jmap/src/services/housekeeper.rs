@@ -24,12 +24,16 @@ use common::telemetry::{
     tracers::store::TracingStore,
 };
 
-use smtp::reporting::SmtpReporting;
+use smtp::{queue::quarantine::SmtpQuarantine, reporting::SmtpReporting};
 use store::{write::now, PurgeStore};
 use tokio::sync::mpsc;
 use trc::{Collector, MetricType, PurgeEvent};
 
-use crate::{email::delete::EmailDeletion, JmapMethods, LONG_SLUMBER};
+use crate::{
+    email::delete::EmailDeletion,
+    services::{dkim::DkimRotation, dns_sync::DnsSync},
+    JmapMethods, LONG_SLUMBER,
+};
 
 #[derive(PartialEq, Eq)]
 struct Action {
@@ -41,6 +45,9 @@ struct Action {
 enum ActionClass {
     Account,
     Store(usize),
+    QuarantineDigest,
+    DkimRotation,
+    DnsSync,
     Acme(String),
     OtelMetrics,
     #[cfg(feature = "enterprise")]
@@ -88,6 +95,40 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                 }
             }
 
+            // Quarantine digests
+            if let Some(digest) = &server.core.smtp.session.data.quarantine_digest {
+                queue.schedule(
+                    Instant::now() + digest.time_to_next(),
+                    ActionClass::QuarantineDigest,
+                );
+            }
+
+            // DKIM key rotation
+            if server.core.network.roles.rotate_dkim {
+                queue.schedule(
+                    Instant::now()
+                        + server
+                            .core
+                            .smtp
+                            .mail_auth
+                            .dkim
+                            .rotation
+                            .schedule
+                            .time_to_next(),
+                    ActionClass::DkimRotation,
+                );
+            }
+
+            // DNS record synchronization
+            if server.core.network.roles.sync_dns {
+                if let Some(schedule) = &server.core.dns_sync.schedule {
+                    queue.schedule(
+                        Instant::now() + schedule.time_to_next(),
+                        ActionClass::DnsSync,
+                    );
+                }
+            }
+
             // OTEL Push Metrics
             if server.core.network.roles.push_metrics {
                 if let Some(otel) = &server.core.metrics.otel {
@@ -171,6 +212,20 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                             _ => {}
                         }
 
+                        // Reload DNS record synchronization
+                        match &server.core.dns_sync.schedule {
+                            Some(schedule)
+                                if server.core.network.roles.sync_dns
+                                    && !queue.has_action(&ActionClass::DnsSync) =>
+                            {
+                                queue.schedule(
+                                    Instant::now() + schedule.time_to_next(),
+                                    ActionClass::DnsSync,
+                                );
+                            }
+                            _ => {}
+                        }
+
                         // SPDX-SnippetBegin
                         // SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
                         // SPDX-License-Identifier: LicenseRef-SEL
@@ -275,6 +330,11 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                                     )
                                                 );
 
+                                                // Publish TLSA records for the new certificate
+                                                if server.core.network.roles.sync_dns {
+                                                    server.dns_sync().await;
+                                                }
+
                                                 renew_at
                                             }
                                             Err(err) => {
@@ -356,6 +416,68 @@ pub fn spawn_housekeeper(inner: Arc<Inner>, mut rx: mpsc::Receiver<HousekeeperEv
                                     });
                                 }
                             }
+                            ActionClass::QuarantineDigest => {
+                                if let Some(digest) =
+                                    &server.core.smtp.session.data.quarantine_digest
+                                {
+                                    trc::event!(
+                                        Housekeeper(trc::HousekeeperEvent::Run),
+                                        Type = "quarantine_digest"
+                                    );
+
+                                    queue.schedule(
+                                        Instant::now() + digest.time_to_next(),
+                                        ActionClass::QuarantineDigest,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.send_quarantine_digest().await;
+                                    });
+                                }
+                            }
+                            ActionClass::DkimRotation => {
+                                trc::event!(
+                                    Housekeeper(trc::HousekeeperEvent::Run),
+                                    Type = "dkim_rotation"
+                                );
+
+                                queue.schedule(
+                                    Instant::now()
+                                        + server
+                                            .core
+                                            .smtp
+                                            .mail_auth
+                                            .dkim
+                                            .rotation
+                                            .schedule
+                                            .time_to_next(),
+                                    ActionClass::DkimRotation,
+                                );
+
+                                let server = server.clone();
+                                tokio::spawn(async move {
+                                    server.dkim_rotate().await;
+                                });
+                            }
+                            ActionClass::DnsSync => {
+                                if let Some(schedule) = &server.core.dns_sync.schedule {
+                                    trc::event!(
+                                        Housekeeper(trc::HousekeeperEvent::Run),
+                                        Type = "dns_sync"
+                                    );
+
+                                    queue.schedule(
+                                        Instant::now() + schedule.time_to_next(),
+                                        ActionClass::DnsSync,
+                                    );
+
+                                    let server = server.clone();
+                                    tokio::spawn(async move {
+                                        server.dns_sync().await;
+                                    });
+                                }
+                            }
                             ActionClass::OtelMetrics => {
                                 if let Some(otel) = &server.core.metrics.otel {
                                     trc::event!(
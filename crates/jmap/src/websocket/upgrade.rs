@@ -107,6 +107,7 @@ impl WebSocketUpgrade for Server {
             content_type: "".into(),
             content_disposition: "".into(),
             cache_control: "".into(),
+            headers: vec![],
             body: HttpResponseBody::WebsocketUpgrade(derived_key),
         })
     }
@@ -156,6 +156,10 @@ impl CacheItemWeight for TokenHash {
 }
 
 impl TokenHash {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.hash[..self.len as usize]
+    }
+
     pub fn serialize(&self, prefix: u8, account_id: Option<u32>) -> Vec<u8> {
         if let Some(account_id) = account_id {
             self.serialize_account(prefix, account_id)
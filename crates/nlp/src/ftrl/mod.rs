@@ -0,0 +1,257 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use ahash::{AHashMap, AHashSet};
+use serde::{Deserialize, Serialize};
+
+use crate::bayes::TokenHash;
+
+// Online logistic regression trained with the FTRL-Proximal algorithm
+// (McMahan et al., "Ad Click Prediction: a View from the Trenches").
+// Features are hashed into a fixed number of buckets so the model size
+// is bounded regardless of the vocabulary seen during training.
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FtrlParameters {
+    pub alpha: f64,
+    pub beta: f64,
+    pub l1: f64,
+    pub l2: f64,
+    pub feature_bits: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize, Default, Copy, Clone, PartialEq)]
+pub struct FtrlWeight {
+    pub z: f64,
+    pub n: f64,
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct FtrlFeatures {
+    pub buckets: AHashSet<u32>,
+}
+
+#[derive(Debug, Default)]
+pub struct FtrlModel {
+    pub weights: AHashMap<u32, FtrlWeight>,
+}
+
+// Bucket reserved for the intercept term
+pub const FTRL_BIAS: u32 = 0;
+
+const SEED_TOKEN: u64 = 0;
+const SEED_TAG: u64 = 1;
+const MAX_MARGIN: f64 = 35.0;
+
+impl FtrlParameters {
+    pub fn new() -> Self {
+        FtrlParameters {
+            alpha: 0.1,
+            beta: 1.0,
+            l1: 1.0,
+            l2: 1.0,
+            feature_bits: 22,
+        }
+    }
+
+    pub fn weight(&self, state: &FtrlWeight) -> f64 {
+        if state.z.abs() <= self.l1 {
+            0.0
+        } else {
+            -(state.z - state.z.signum() * self.l1)
+                / ((self.beta + state.n.sqrt()) / self.alpha + self.l2)
+        }
+    }
+
+    pub fn predict<'x>(&self, weights: impl IntoIterator<Item = &'x FtrlWeight>) -> f64 {
+        let margin = weights
+            .into_iter()
+            .map(|state| self.weight(state))
+            .sum::<f64>()
+            .clamp(-MAX_MARGIN, MAX_MARGIN);
+
+        1.0 / (1.0 + (-margin).exp())
+    }
+
+    pub fn update(&self, state: &mut FtrlWeight, prediction: f64, is_spam: bool) {
+        // Gradient of the log loss for a binary feature
+        let gradient = prediction - if is_spam { 1.0 } else { 0.0 };
+        let sigma = ((state.n + gradient * gradient).sqrt() - state.n.sqrt()) / self.alpha;
+        state.z += gradient - sigma * self.weight(state);
+        state.n += gradient * gradient;
+    }
+}
+
+impl Default for FtrlParameters {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FtrlFeatures {
+    pub fn new() -> Self {
+        let mut buckets = AHashSet::new();
+        buckets.insert(FTRL_BIAS);
+        FtrlFeatures { buckets }
+    }
+
+    pub fn add_token(&mut self, token: &TokenHash, feature_bits: u32) {
+        self.buckets.insert(to_bucket(
+            xxhash_rust::xxh3::xxh3_64_with_seed(token.as_bytes(), SEED_TOKEN),
+            feature_bits,
+        ));
+    }
+
+    pub fn add_tag(&mut self, tag: &str, feature_bits: u32) {
+        self.buckets.insert(to_bucket(
+            xxhash_rust::xxh3::xxh3_64_with_seed(tag.as_bytes(), SEED_TAG),
+            feature_bits,
+        ));
+    }
+
+    pub fn len(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.buckets.len() <= 1
+    }
+}
+
+impl FtrlModel {
+    pub fn predict(&self, params: &FtrlParameters, features: &FtrlFeatures) -> f64 {
+        params.predict(
+            features
+                .buckets
+                .iter()
+                .filter_map(|bucket| self.weights.get(bucket)),
+        )
+    }
+
+    pub fn train(&mut self, params: &FtrlParameters, features: &FtrlFeatures, is_spam: bool) {
+        let prediction = self.predict(params, features);
+        for bucket in &features.buckets {
+            params.update(
+                self.weights.entry(*bucket).or_default(),
+                prediction,
+                is_spam,
+            );
+        }
+    }
+}
+
+impl FtrlWeight {
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut buf = Vec::with_capacity(std::mem::size_of::<f64>() * 2);
+        buf.extend_from_slice(&self.z.to_be_bytes());
+        buf.extend_from_slice(&self.n.to_be_bytes());
+        buf
+    }
+
+    pub fn deserialize(bytes: &[u8]) -> Option<Self> {
+        let (z, n) = bytes.split_at_checked(std::mem::size_of::<f64>())?;
+        Some(FtrlWeight {
+            z: f64::from_be_bytes(z.try_into().ok()?),
+            n: f64::from_be_bytes(n.try_into().ok()?),
+        })
+    }
+}
+
+fn to_bucket(hash: u64, feature_bits: u32) -> u32 {
+    // Bucket zero is reserved for the bias term
+    ((hash & ((1u64 << feature_bits) - 1)) as u32).saturating_add(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{bayes::TokenHash, tokenizers::osb::Gram};
+
+    use super::{FtrlFeatures, FtrlModel, FtrlParameters, FtrlWeight};
+
+    fn features(params: &FtrlParameters, text: &str, tags: &[&str]) -> FtrlFeatures {
+        let mut features = FtrlFeatures::new();
+        for word in text.split_whitespace() {
+            features.add_token(
+                &TokenHash::from(Gram::Uni {
+                    t1: word.as_bytes(),
+                }),
+                params.feature_bits,
+            );
+        }
+        for tag in tags {
+            features.add_tag(tag, params.feature_bits);
+        }
+        features
+    }
+
+    #[test]
+    fn ftrl_train_classify() {
+        let params = FtrlParameters {
+            alpha: 0.5,
+            l1: 0.1,
+            ..Default::default()
+        };
+        let mut model = FtrlModel::default();
+        let spam = [
+            (
+                "cheap pills online buy now limited offer",
+                &["DMARC_NA"][..],
+            ),
+            (
+                "win a free prize claim your reward now",
+                &["FROM_NEQ_ENVFROM"][..],
+            ),
+            ("limited offer cheap watches buy online", &["DMARC_NA"][..]),
+        ];
+        let ham = [
+            (
+                "meeting notes for the quarterly review",
+                &["DMARC_POLICY_ALLOW"][..],
+            ),
+            ("can we move lunch to thursday", &["DMARC_POLICY_ALLOW"][..]),
+            (
+                "please review the attached roadmap draft",
+                &["DKIM_ALLOW"][..],
+            ),
+        ];
+
+        for _ in 0..20 {
+            for (text, tags) in spam {
+                model.train(&params, &features(&params, text, tags), true);
+            }
+            for (text, tags) in ham {
+                model.train(&params, &features(&params, text, tags), false);
+            }
+        }
+
+        let p_spam = model.predict(
+            &params,
+            &features(&params, "buy cheap pills now", &["DMARC_NA"]),
+        );
+        let p_ham = model.predict(
+            &params,
+            &features(
+                &params,
+                "review the meeting roadmap",
+                &["DMARC_POLICY_ALLOW"],
+            ),
+        );
+        assert!(p_spam > 0.9, "spam probability {p_spam}");
+        assert!(p_ham < 0.1, "ham probability {p_ham}");
+
+        // Unknown features fall back to the learned prior
+        let p_unknown = model.predict(&params, &features(&params, "zzz yyy", &[]));
+        assert!(
+            (0.2..0.8).contains(&p_unknown),
+            "unknown probability {p_unknown}"
+        );
+
+        // Serialization round trip
+        let weight = FtrlWeight { z: -1.5, n: 42.25 };
+        assert_eq!(FtrlWeight::deserialize(&weight.serialize()), Some(weight));
+        assert_eq!(FtrlWeight::deserialize(&[0u8; 3]), None);
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bayes;
+pub mod ftrl;
 pub mod language;
 pub mod tokenizers;
 
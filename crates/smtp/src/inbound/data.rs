@@ -11,17 +11,19 @@ use std::{
 
 use common::{
     config::{
+        server::ServerProtocol,
         smtp::{auth::VerifyStrategy, session::Stage},
-        spamfilter::SpamFilterAction,
+        spamfilter::{AntivirusAction, SpamFilterAction},
     },
     listener::SessionStream,
     psl,
     scripts::ScriptModification,
 };
+use email::urlauth::{ImapUrl, UrlAuth, UrlRequester};
 use mail_auth::{
     common::{headers::HeaderWriter, verify::VerifySignature},
     dmarc::{self, verify::DmarcParameters},
-    AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf,
+    AuthenticatedMessage, AuthenticationResults, DkimResult, DmarcResult, ReceivedSpf, SpfResult,
 };
 use mail_builder::headers::{date::Date, message_id::generate_message_id_header};
 use mail_parser::MessageParser;
@@ -29,14 +31,23 @@ use sieve::runtime::Variable;
 use smtp_proto::{
     MAIL_BY_RETURN, RCPT_NOTIFY_DELAY, RCPT_NOTIFY_FAILURE, RCPT_NOTIFY_NEVER, RCPT_NOTIFY_SUCCESS,
 };
+use spam_filter::{analysis::antivirus::SpamFilterAnalyzeAntivirus, AntivirusVerdict};
 use store::write::now;
 use trc::SmtpEvent;
-use utils::config::Rate;
+use utils::{config::Rate, BlobHash};
 
 use crate::{
     core::{Session, SessionAddress, State},
     inbound::milter::Modification,
-    queue::{self, quota::HasQueueQuota, Message, MessageSource, QueueEnvelope, Schedule},
+    queue::{
+        self,
+        quarantine::{QuarantineSource, QuarantinedMessage, SmtpQuarantine},
+        quota::HasQueueQuota,
+        spool::SmtpSpool,
+        srs::SmtpSrs,
+        Message, MessageSource, QueueEnvelope, Schedule, MAIL_AUTHENTICATED, MAIL_DMARC_PASS,
+        MAIL_SPF_PASS,
+    },
     reporting::analysis::AnalyzeReport,
     scripts::ScriptResult,
 };
@@ -425,8 +436,55 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Antivirus scanning
+        let mut quarantine = None;
+        let antivirus = if self.server.core.spam.antivirus.is_some() {
+            match self
+                .server
+                .eval_if(&dc.antivirus_action, self, self.data.session_id)
+                .await
+                .unwrap_or(AntivirusAction::Reject)
+            {
+                AntivirusAction::Disable => None,
+                action => self
+                    .server
+                    .antivirus_scan_message(&parsed_message, self.data.session_id)
+                    .await
+                    .map(|verdict| (action, verdict)),
+            }
+        } else {
+            None
+        };
+        match &antivirus {
+            Some((AntivirusAction::Reject, AntivirusVerdict::Infected(_))) => {
+                self.data.messages_sent += 1;
+                return (b"550 5.7.1 Message rejected: malware detected.\r\n"[..]).into();
+            }
+            Some((AntivirusAction::Quarantine, AntivirusVerdict::Infected(virus))) => {
+                quarantine = Some((
+                    QuarantineSource::Antivirus,
+                    format!("Malware detected: {virus}"),
+                    None,
+                ));
+            }
+            Some((_, verdict)) => {
+                if let Some(header_name) = &self.server.core.spam.headers.antivirus {
+                    let status = match verdict {
+                        AntivirusVerdict::Clean => Cow::Borrowed("Clean"),
+                        AntivirusVerdict::Infected(virus) => {
+                            Cow::Owned(format!("Infected ({virus})"))
+                        }
+                        AntivirusVerdict::Failed => Cow::Borrowed("Scan failed"),
+                    };
+                    headers.extend_from_slice(format!("{header_name}: {status}\r\n").as_bytes());
+                }
+            }
+            None => (),
+        }
+
         // Run SPAM filter
-        if self.server.core.spam.enabled
+        if quarantine.is_none()
+            && self.server.core.spam.enabled
             && self
                 .server
                 .eval_if(&dc.spam_filter, self, self.data.session_id)
@@ -440,19 +498,27 @@ impl<T: SessionStream> Session<T> {
                     (&arc_output).into(),
                     dmarc_result.as_ref(),
                     dmarc_policy.as_ref(),
+                    antivirus.as_ref().map(|(_, verdict)| verdict),
                 )
                 .await
             {
-                SpamFilterAction::Allow(spam_headers) => {
+                (SpamFilterAction::Allow(spam_headers), _) => {
                     if !spam_headers.is_empty() {
                         headers.extend_from_slice(spam_headers.as_bytes());
                     }
                 }
-                SpamFilterAction::Discard => {
+                (SpamFilterAction::Quarantine, score) => {
+                    quarantine = Some((
+                        QuarantineSource::SpamFilter,
+                        "Message classified as spam".to_string(),
+                        Some(score),
+                    ));
+                }
+                (SpamFilterAction::Discard, _) => {
                     self.data.messages_sent += 1;
                     return (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into();
                 }
-                SpamFilterAction::Reject => {
+                (SpamFilterAction::Reject, _) => {
                     self.data.messages_sent += 1;
                     return (b"550 5.7.1 Message rejected due to excessive spam score.\r\n"[..])
                         .into();
@@ -465,6 +531,12 @@ impl<T: SessionStream> Session<T> {
         match self.run_milters(Stage::Data, (&auth_message).into()).await {
             Ok(modifications_) => {
                 if !modifications_.is_empty() {
+                    if let Some(Modification::Quarantine { reason }) = modifications_
+                        .iter()
+                        .find(|m| matches!(m, Modification::Quarantine { .. }))
+                    {
+                        quarantine = Some((QuarantineSource::Milter, reason.clone(), None));
+                    }
                     modifications = modifications_;
                 }
             }
@@ -480,6 +552,12 @@ impl<T: SessionStream> Session<T> {
         {
             Ok(modifications_) => {
                 if !modifications_.is_empty() {
+                    if let Some(Modification::Quarantine { reason }) = modifications_
+                        .iter()
+                        .find(|m| matches!(m, Modification::Quarantine { .. }))
+                    {
+                        quarantine = Some((QuarantineSource::MtaHook, reason.clone(), None));
+                    }
                     modifications.retain(|m| !matches!(m, Modification::ReplaceBody { .. }));
                     modifications.extend(modifications_);
                 }
@@ -588,6 +666,9 @@ impl<T: SessionStream> Session<T> {
                     ScriptModification::SetEnvelope { name, value } => {
                         self.data.apply_envelope_modification(name, value);
                     }
+                    ScriptModification::Quarantine { reason } => {
+                        quarantine = Some((QuarantineSource::Sieve, reason, None));
+                    }
                 }
             }
         }
@@ -599,6 +680,27 @@ impl<T: SessionStream> Session<T> {
             .build_message(mail_from, rcpt_to, message_id, self.data.session_id)
             .await;
 
+        // Keep track of how the sender was authenticated
+        if self.data.authenticated_as.is_some() {
+            message.flags |= MAIL_AUTHENTICATED;
+        }
+        if self
+            .data
+            .spf_mail_from
+            .as_ref()
+            .is_some_and(|spf| matches!(spf.result(), SpfResult::Pass))
+        {
+            message.flags |= MAIL_SPF_PASS;
+        }
+        if matches!(dmarc_result, Some(DmarcResult::Pass)) {
+            message.flags |= MAIL_DMARC_PASS;
+        }
+
+        // Rewrite the sender of relayed external messages
+        if self.data.authenticated_as.is_none() {
+            self.server.srs_forward(&mut message).await;
+        }
+
         // Add Return-Path
         if self
             .server
@@ -660,6 +762,13 @@ impl<T: SessionStream> Session<T> {
         // Update size
         message.size = raw_message.len() + headers.len();
 
+        // Hold message in quarantine
+        if let Some((source, reason, score)) = quarantine {
+            return self
+                .quarantine_message(message, &headers, raw_message, source, reason, score)
+                .await;
+        }
+
         // Verify queue quota
         if self.server.has_quota(&mut message).await {
             // Prepare webhook event
@@ -692,6 +801,78 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
+    async fn quarantine_message(
+        &mut self,
+        message: Message,
+        headers: &[u8],
+        raw_message: &[u8],
+        source: QuarantineSource,
+        reason: String,
+        score: Option<f64>,
+    ) -> Cow<'static, [u8]> {
+        let expires = now()
+            + self
+                .server
+                .eval_if::<Duration, _>(
+                    &self.server.core.smtp.session.data.quarantine_expire,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(Duration::from_secs(30 * 86400))
+                .as_secs();
+        let mut contents = Vec::with_capacity(headers.len() + raw_message.len());
+        contents.extend_from_slice(headers);
+        contents.extend_from_slice(raw_message);
+        let parsed = MessageParser::new().parse_headers(&contents);
+        let quarantined = QuarantinedMessage {
+            created: message.created,
+            blob_hash: BlobHash::from(contents.as_slice()),
+            size: contents.len(),
+            return_path: message.return_path,
+            recipients: message
+                .recipients
+                .into_iter()
+                .map(|rcpt| rcpt.address)
+                .collect(),
+            source,
+            reason,
+            score,
+            remote_ip: self.data.remote_ip,
+            helo_domain: self.data.helo_domain.clone(),
+            from: parsed
+                .as_ref()
+                .and_then(|m| m.from())
+                .and_then(|a| a.first())
+                .and_then(|a| a.address())
+                .unwrap_or_default()
+                .to_string(),
+            subject: parsed
+                .as_ref()
+                .and_then(|m| m.subject())
+                .unwrap_or_default()
+                .to_string(),
+            notified: false,
+        };
+
+        match self
+            .server
+            .quarantine_message(quarantined, &contents, expires, self.data.session_id)
+            .await
+        {
+            Ok(_) => {
+                self.data.messages_sent += 1;
+                (b"250 2.0.0 Message queued for delivery.\r\n"[..]).into()
+            }
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .details("Failed to quarantine message."));
+                (b"451 4.3.5 Unable to accept message at this time.\r\n"[..]).into()
+            }
+        }
+    }
+
     pub async fn build_message(
         &self,
         mail_from: SessionAddress,
@@ -718,6 +899,7 @@ impl<T: SessionStream> Session<T> {
             env_id: mail_from.dsn_info,
             blob_hash: Default::default(),
             quota_keys: Vec::new(),
+            queue: self.server.select_queue(self, self.data.session_id).await,
         };
 
         // Add recipients
@@ -727,7 +909,7 @@ impl<T: SessionStream> Session<T> {
             if message
                 .domains
                 .last()
-                .is_none_or( |d| d.domain != rcpt.domain)
+                .is_none_or(|d| d.domain != rcpt.domain)
             {
                 let rcpt_idx = message.domains.len();
                 message.domains.push(queue::Domain {
@@ -751,7 +933,11 @@ impl<T: SessionStream> Session<T> {
                 let config = &self.server.core.smtp.queue;
                 let (num_intervals, next_notify) = self
                     .server
-                    .eval_if::<Vec<Duration>, _>(&config.notify, &envelope, self.data.session_id)
+                    .eval_if::<Vec<Duration>, _>(
+                        config.notify_for(message.queue.as_deref()),
+                        &envelope,
+                        self.data.session_id,
+                    )
                     .await
                     .and_then(|v| (v.len(), v.into_iter().next()?).into())
                     .unwrap_or_else(|| (1, Duration::from_secs(86400)));
@@ -762,7 +948,11 @@ impl<T: SessionStream> Session<T> {
                             + future_release.as_secs()
                             + self
                                 .server
-                                .eval_if(&config.expire, &envelope, self.data.session_id)
+                                .eval_if(
+                                    config.expire_for(message.queue.as_deref()),
+                                    &envelope,
+                                    self.data.session_id,
+                                )
                                 .await
                                 .unwrap_or_else(|| Duration::from_secs(5 * 86400))
                                 .as_secs(),
@@ -775,7 +965,11 @@ impl<T: SessionStream> Session<T> {
                 } else {
                     let expire = self
                         .server
-                        .eval_if(&config.expire, &envelope, self.data.session_id)
+                        .eval_if(
+                            config.expire_for(message.queue.as_deref()),
+                            &envelope,
+                            self.data.session_id,
+                        )
                         .await
                         .unwrap_or_else(|| Duration::from_secs(5 * 86400));
                     let expire_secs = expire.as_secs();
@@ -865,6 +1059,102 @@ impl<T: SessionStream> Session<T> {
         }
     }
 
+    pub async fn handle_burl(&mut self, uri: String, is_last: bool) -> Result<(), ()> {
+        let Some(access_token) = self.data.authenticated_as.clone() else {
+            trc::event!(
+                Smtp(SmtpEvent::BurlFailed),
+                SpanId = self.data.session_id,
+                Url = uri,
+                Reason = "Unauthenticated session",
+            );
+
+            return self.write(b"530 5.7.0 Authentication required.\r\n").await;
+        };
+        if !self.can_send_data().await? {
+            self.data.message = Vec::with_capacity(0);
+            return Ok(());
+        }
+
+        // Fetch the referenced message from the blob store
+        let result = match ImapUrl::parse(&uri) {
+            Some(url) if url.access.is_some() => {
+                self.server
+                    .urlauth_fetch(&url, UrlRequester::Submit(&access_token.name))
+                    .await
+            }
+            Some(url) => self.server.url_fetch_as(&access_token, &url).await,
+            None => Ok(None),
+        };
+        match result {
+            Ok(Some(bytes))
+                if bytes.len() + self.data.message.len() < self.params.max_message_size =>
+            {
+                trc::event!(
+                    Smtp(SmtpEvent::Burl),
+                    SpanId = self.data.session_id,
+                    Url = uri,
+                    Size = bytes.len(),
+                );
+
+                self.data.message.extend_from_slice(&bytes);
+            }
+            Ok(Some(_)) => {
+                trc::event!(
+                    Smtp(SmtpEvent::MessageTooLarge),
+                    SpanId = self.data.session_id,
+                );
+
+                self.data.message = Vec::with_capacity(0);
+                return self
+                    .write(b"552 5.3.4 Message too big for system.\r\n")
+                    .await;
+            }
+            Ok(None) => {
+                trc::event!(
+                    Smtp(SmtpEvent::BurlFailed),
+                    SpanId = self.data.session_id,
+                    Url = uri,
+                );
+
+                self.data.message = Vec::with_capacity(0);
+                return self
+                    .write(b"554 5.6.6 IMAP URL resolution failed.\r\n")
+                    .await;
+            }
+            Err(err) => {
+                trc::error!(err
+                    .span_id(self.data.session_id)
+                    .details("Failed to resolve BURL URL"));
+
+                self.data.message = Vec::with_capacity(0);
+                return self
+                    .write(b"451 4.4.1 Temporary failure resolving URL.\r\n")
+                    .await;
+            }
+        }
+
+        if is_last {
+            let message = self.queue_message().await;
+            if !message.is_empty() {
+                let num_responses = if self.instance.protocol == ServerProtocol::Smtp {
+                    1
+                } else {
+                    self.data.rcpt_oks
+                };
+                for _ in 0..num_responses {
+                    self.write(message.as_ref()).await?;
+                }
+                self.reset();
+                Ok(())
+            } else {
+                // Disconnect requested
+                Err(())
+            }
+        } else {
+            self.write(b"250 2.5.0 URL content accepted.\r\n").await
+        }
+    }
+
     fn write_received(&self, headers: &mut Vec<u8>, id: u64) {
         headers.extend_from_slice(b"Received: from ");
         headers.extend_from_slice(self.data.helo_domain.as_bytes());
@@ -224,6 +224,9 @@ impl<T: SessionStream> Session<T> {
             }
         }
 
+        // Submission of stored messages by URL
+        let is_burl = self.is_authenticated();
+
         // Future release
         if let Some(value) = self
             .server
@@ -286,6 +289,14 @@ impl<T: SessionStream> Session<T> {
         // Generate response
         let mut buf = Vec::with_capacity(64);
         response.write(&mut buf).ok();
+        if is_burl {
+            // Advertise BURL with IMAP URL support right after the greeting line
+            let pos = buf
+                .iter()
+                .position(|&ch| ch == b'\n')
+                .map_or(buf.len(), |pos| pos + 1);
+            buf.splice(pos..pos, b"250-BURL imap\r\n".iter().copied());
+        }
         self.write(&buf).await
     }
 }
@@ -129,13 +129,16 @@ impl<T: SessionStream> Session<T> {
                         Action::Accept => continue,
                         Action::Discard => FilterResponse::accept(),
                         Action::Reject => FilterResponse::reject(),
-                        Action::Quarantine => {
-                            modifications.push(Modification::AddHeader {
-                                name: "X-Quarantine".to_string(),
-                                value: "true".to_string(),
+                        Action::Quarantine if stage == Stage::Data => {
+                            modifications.push(Modification::Quarantine {
+                                reason: response
+                                    .response
+                                    .and_then(|r| r.message)
+                                    .unwrap_or_else(|| "Quarantined by MTA hook".to_string()),
                             });
-                            FilterResponse::accept()
+                            return Ok(modifications);
                         }
+                        Action::Quarantine => FilterResponse::accept(),
                     };
 
                     if let Some(response) = response.response {
@@ -16,7 +16,7 @@ use trc::{SecurityEvent, SmtpEvent};
 
 use crate::{
     core::{Session, SessionAddress},
-    queue::DomainPart,
+    queue::{srs::SmtpSrs, DomainPart},
     scripts::ScriptResult,
 };
 
@@ -66,12 +66,47 @@ impl<T: SessionStream> Session<T> {
                 .await;
         }
 
+        // Reverse SRS addresses, only bounces are relayed to the original sender
+        let (address, is_srs_bounce) = match self.server.srs_reverse(&to.address) {
+            Ok(Some(address)) => {
+                trc::event!(
+                    Smtp(SmtpEvent::SrsReversed),
+                    SpanId = self.data.session_id,
+                    Details = to.address,
+                    To = address.clone(),
+                );
+
+                let is_bounce = self
+                    .data
+                    .mail_from
+                    .as_ref()
+                    .is_some_and(|mail_from| mail_from.address.is_empty());
+                (address, is_bounce)
+            }
+            Ok(None) => (to.address, false),
+            Err(err) => {
+                trc::event!(
+                    Smtp(SmtpEvent::SrsInvalid),
+                    SpanId = self.data.session_id,
+                    To = to.address.to_lowercase(),
+                    Reason = format!("{err:?}"),
+                );
+
+                return self
+                    .rcpt_error(
+                        b"550 5.1.1 Invalid SRS address.\r\n",
+                        to.address.to_lowercase(),
+                    )
+                    .await;
+            }
+        };
+
         // Build RCPT
-        let address_lcase = to.address.to_lowercase();
+        let address_lcase = address.to_lowercase();
         let rcpt = SessionAddress {
             domain: address_lcase.domain_part().to_string(),
             address_lcase,
-            address: to.address,
+            address,
             flags: to.flags,
             dsn_info: to.orcpt,
         };
@@ -244,15 +279,16 @@ impl<T: SessionStream> Session<T> {
                     }
                 }
                 Ok(false) => {
-                    if !self
-                        .server
-                        .eval_if(
-                            &self.server.core.smtp.session.rcpt.relay,
-                            self,
-                            self.data.session_id,
-                        )
-                        .await
-                        .unwrap_or(false)
+                    if !is_srs_bounce
+                        && !self
+                            .server
+                            .eval_if(
+                                &self.server.core.smtp.session.rcpt.relay,
+                                self,
+                                self.data.session_id,
+                            )
+                            .await
+                            .unwrap_or(false)
                     {
                         trc::event!(
                             Smtp(SmtpEvent::RelayNotAllowed),
@@ -278,15 +314,16 @@ impl<T: SessionStream> Session<T> {
                         .await;
                 }
             }
-        } else if !self
-            .server
-            .eval_if(
-                &self.server.core.smtp.session.rcpt.relay,
-                self,
-                self.data.session_id,
-            )
-            .await
-            .unwrap_or(false)
+        } else if !is_srs_bounce
+            && !self
+                .server
+                .eval_if(
+                    &self.server.core.smtp.session.rcpt.relay,
+                    self,
+                    self.data.session_id,
+                )
+                .await
+                .unwrap_or(false)
         {
             trc::event!(
                 Smtp(SmtpEvent::RelayNotAllowed),
@@ -220,9 +220,10 @@ impl<T: SessionStream> Session<T> {
                                         .await?;
                                 }
                             }
-                            cmd @ (Request::Etrn { .. }
-                            | Request::Atrn { .. }
-                            | Request::Burl { .. }) => {
+                            Request::Burl { uri, is_last } => {
+                                self.handle_burl(uri, is_last).await?;
+                            }
+                            cmd @ (Request::Etrn { .. } | Request::Atrn { .. }) => {
                                 trc::event!(
                                     Smtp(SmtpEvent::CommandNotImplemented),
                                     SpanId = self.data.session_id,
@@ -12,7 +12,7 @@ use spam_filter::{
         init::SpamFilterInit, score::SpamFilterAnalyzeScore,
         trusted_reply::SpamFilterAnalyzeTrustedReply,
     },
-    SpamFilterInput,
+    AntivirusVerdict, SpamFilterInput,
 };
 
 use crate::core::Session;
@@ -25,23 +25,22 @@ impl<T: SessionStream> Session<T> {
         arc_result: Option<&'x ArcOutput<'x>>,
         dmarc_result: Option<&'x DmarcResult>,
         dmarc_policy: Option<&'x Policy>,
-    ) -> SpamFilterAction<String> {
+        antivirus: Option<&'x AntivirusVerdict>,
+    ) -> (SpamFilterAction<String>, f64) {
         let server = &self.server;
-        let mut ctx = server.spam_filter_init(self.build_spam_input(
-            message,
-            dkim_result,
-            arc_result,
-            dmarc_result,
-            dmarc_policy,
-        ));
+        let mut input =
+            self.build_spam_input(message, dkim_result, arc_result, dmarc_result, dmarc_policy);
+        input.antivirus = antivirus;
+        let mut ctx = server.spam_filter_init(input);
 
         if !self.is_authenticated() {
             // Spam classification
-            server.spam_filter_classify(&mut ctx).await
+            let action = server.spam_filter_classify(&mut ctx).await;
+            (action, ctx.result.score)
         } else {
             // Trusted reply tracking
             server.spam_filter_analyze_reply_out(&mut ctx).await;
-            SpamFilterAction::Allow(String::new())
+            (SpamFilterAction::Allow(String::new()), 0.0)
         }
     }
 
@@ -89,6 +88,7 @@ impl<T: SessionStream> Session<T> {
                 .collect(),
             account_id: None,
             is_test: false,
+            antivirus: None,
         }
     }
 }
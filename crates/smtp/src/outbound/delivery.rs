@@ -12,14 +12,15 @@ use crate::outbound::mta_sts::verify::VerifyPolicy;
 use crate::outbound::{client::StartTlsResult, dane::verify::TlsaVerify};
 use crate::queue::dsn::SendDsn;
 use crate::queue::spool::{LOCK_EXPIRY, SmtpSpool};
-use crate::queue::throttle::IsAllowed;
+use crate::queue::throttle::{AdaptiveThrottling, IsAllowed};
+use crate::queue::tracking::{DeliveryTracker, SmtpTracking};
 use crate::reporting::SmtpReporting;
-use common::Server;
 use common::config::{
     server::ServerProtocol,
     smtp::{queue::RequireOptional, report::AggregateFrequency},
 };
 use common::ipc::{PolicyType, QueueEvent, QueueEventStatus, TlsEvent};
+use common::{Server, SmtpConnectionKey};
 use mail_auth::{
     mta_sts::TlsRpt,
     report::tlsrpt::{FailureDetails, ResultType},
@@ -39,7 +40,13 @@ use crate::{
     reporting::tls::TlsRptOptions,
 };
 
-use super::{NextHop, TlsStrategy, lookup::ToNextHop, mta_sts, session::SessionParams};
+use super::{
+    NextHop, TlsStrategy,
+    lookup::ToNextHop,
+    mta_sts,
+    pool::{CONNECTION_RETRY_INTERVAL, ConnectionReuse, ReusedClient, SmtpConnectionPool},
+    session::SessionParams,
+};
 use crate::queue::{Domain, Error, QueueEnvelope, QueuedMessage, Status};
 
 impl QueuedMessage {
@@ -196,8 +203,10 @@ impl QueuedMessage {
         }
 
         let queue_config = &server.core.smtp.queue;
+        let retry = queue_config.retry_for(message.queue.as_deref());
         let no_ip = IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0));
         let mut recipients = std::mem::take(&mut message.recipients);
+        let mut tracker = DeliveryTracker::new(&server, &recipients);
         'next_domain: for domain_idx in 0..message.domains.len() {
             // Only process domains due for delivery
             let domain = &message.domains[domain_idx];
@@ -213,6 +222,7 @@ impl QueuedMessage {
                 Domain = domain.domain.clone(),
                 Total = domain.retry.inner,
             );
+            tracker.domain(domain_idx);
 
             // Build envelope
             let mut envelope = QueueEnvelope::new(&message, domain_idx);
@@ -252,11 +262,7 @@ impl QueuedMessage {
 
                     // Update status for the current domain and continue with the next one
                     let schedule = server
-                        .eval_if::<Vec<Duration>, _>(
-                            &queue_config.retry,
-                            &envelope,
-                            message.span_id,
-                        )
+                        .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                         .await
                         .unwrap_or_else(|| vec![Duration::from_secs(60)]);
                     message.domains[domain_idx].set_status(delivery_result, &schedule);
@@ -269,6 +275,12 @@ impl QueuedMessage {
                 None => (Vec::with_capacity(0), true),
             };
 
+            // Obtain outbound proxy
+            let proxy = server
+                .eval_if::<String, _>(&queue_config.proxy, &envelope, message.span_id)
+                .await
+                .and_then(|name| server.get_outbound_proxy(&name, message.span_id));
+
             // Prepare TLS strategy
             let mut tls_strategy = TlsStrategy {
                 mta_sts: server
@@ -362,6 +374,7 @@ impl QueuedMessage {
                             .eval_if(&queue_config.timeout.mta_sts, &envelope, message.span_id)
                             .await
                             .unwrap_or_else(|| Duration::from_secs(10 * 60)),
+                        proxy,
                     )
                     .await
                 {
@@ -463,11 +476,7 @@ impl QueuedMessage {
 
                         if strict {
                             let schedule = server
-                                .eval_if::<Vec<Duration>, _>(
-                                    &queue_config.retry,
-                                    &envelope,
-                                    message.span_id,
-                                )
+                                .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                                 .await
                                 .unwrap_or_else(|| vec![Duration::from_secs(60)]);
                             message.domains[domain_idx].set_status(err, &schedule);
@@ -516,11 +525,7 @@ impl QueuedMessage {
                         );
 
                         let schedule = server
-                            .eval_if::<Vec<Duration>, _>(
-                                &queue_config.retry,
-                                &envelope,
-                                message.span_id,
-                            )
+                            .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                             .await
                             .unwrap_or_else(|| vec![Duration::from_secs(60)]);
                         message.domains[domain_idx].set_status(err, &schedule);
@@ -555,11 +560,7 @@ impl QueuedMessage {
                     );
 
                     let schedule = server
-                        .eval_if::<Vec<Duration>, _>(
-                            &queue_config.retry,
-                            &envelope,
-                            message.span_id,
-                        )
+                        .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                         .await
                         .unwrap_or_else(|| vec![Duration::from_secs(60)]);
                     message.domains[domain_idx].set_status(
@@ -581,6 +582,7 @@ impl QueuedMessage {
             'next_host: for remote_host in &remote_hosts {
                 // Validate MTA-STS
                 envelope.mx = remote_host.hostname();
+                tracker.host(envelope.mx, None);
                 if let Some(mta_sts_policy) = &mta_sts_policy {
                     let strict = mta_sts_policy.enforce();
                     if !mta_sts_policy.verify(envelope.mx) {
@@ -639,7 +641,13 @@ impl QueuedMessage {
                 // Obtain source and remote IPs
                 let time = Instant::now();
                 let resolve_result = match server
-                    .resolve_host(remote_host, &envelope, max_multihomed, message.span_id)
+                    .resolve_host(
+                        remote_host,
+                        &envelope,
+                        message.queue.as_deref(),
+                        max_multihomed,
+                        message.span_id,
+                    )
                     .await
                 {
                     Ok(result) => {
@@ -855,6 +863,7 @@ impl QueuedMessage {
 
                     // Throttle remote host
                     envelope.remote_ip = remote_ip;
+                    tracker.host(envelope.mx, Some(remote_ip));
                     for throttle in &queue_config.outbound_limiters.remote {
                         if let Err(retry_at) = server
                             .is_allowed(throttle, &envelope, message.span_id)
@@ -871,13 +880,199 @@ impl QueuedMessage {
                         }
                     }
 
+                    // Apply limits learned from the remote host responses
+                    let throttle_key = server.throttle_key(&domain.domain, envelope.mx);
+                    let mut max_adaptive = 0;
+                    if let Some(key) = &throttle_key {
+                        match server.throttle_check(key) {
+                            Ok(limit) => {
+                                max_adaptive = limit;
+                            }
+                            Err(retry_at) => {
+                                trc::event!(
+                                    Delivery(DeliveryEvent::RateLimitExceeded),
+                                    SpanId = message.span_id,
+                                    Id = "adaptive",
+                                    Hostname = key.clone(),
+                                    NextRetry = trc::Value::Timestamp(retry_at),
+                                );
+                                message.domains[domain_idx].set_rate_limiter_error(retry_at);
+                                continue 'next_domain;
+                            }
+                        }
+                    }
+
+                    // Obtain session parameters
+                    let local_hostname = server
+                        .eval_if::<String, _>(&queue_config.hostname, &envelope, message.span_id)
+                        .await
+                        .filter(|s| !s.is_empty())
+                        .unwrap_or_else(|| {
+                            trc::event!(
+                                Delivery(DeliveryEvent::MissingOutboundHostname),
+                                SpanId = message.span_id,
+                            );
+                            "local.host".to_string()
+                        });
+                    let mut params = SessionParams {
+                        session_id: message.span_id,
+                        server: &server,
+                        credentials: remote_host.credentials(),
+                        is_smtp: remote_host.is_smtp(),
+                        hostname: envelope.mx,
+                        local_hostname: &local_hostname,
+                        timeout_ehlo: server
+                            .eval_if(&queue_config.timeout.ehlo, &envelope, message.span_id)
+                            .await
+                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
+                        timeout_mail: server
+                            .eval_if(&queue_config.timeout.mail, &envelope, message.span_id)
+                            .await
+                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
+                        timeout_rcpt: server
+                            .eval_if(&queue_config.timeout.rcpt, &envelope, message.span_id)
+                            .await
+                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
+                        timeout_data: server
+                            .eval_if(&queue_config.timeout.data, &envelope, message.span_id)
+                            .await
+                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
+                        reuse: if server
+                            .eval_if(&queue_config.connection.reuse, &envelope, message.span_id)
+                            .await
+                            .unwrap_or(false)
+                        {
+                            ConnectionReuse {
+                                key: SmtpConnectionKey {
+                                    hostname: envelope.mx.to_string(),
+                                    port: remote_host.port(),
+                                    local_ip: source_ip,
+                                    implicit_tls: remote_host.implicit_tls(),
+                                    dane: tls_strategy.dane,
+                                    mta_sts: tls_strategy.mta_sts,
+                                    tls: tls_strategy.tls,
+                                    proxy: proxy.map(|proxy| proxy.id.clone()),
+                                },
+                                idle_timeout: server
+                                    .eval_if(
+                                        &queue_config.connection.idle_timeout,
+                                        &envelope,
+                                        message.span_id,
+                                    )
+                                    .await
+                                    .unwrap_or_else(|| Duration::from_secs(30)),
+                                max_messages: server
+                                    .eval_if(
+                                        &queue_config.connection.max_messages,
+                                        &envelope,
+                                        message.span_id,
+                                    )
+                                    .await
+                                    .unwrap_or(100),
+                                messages: 0,
+                            }
+                            .into()
+                        } else {
+                            None
+                        },
+                        slot: None,
+                    };
+
+                    // Reuse an idle connection to the remote host, if available
+                    if let Some(connection) = server.reuse_smtp_connection(&mut params).await {
+                        trc::event!(
+                            Delivery(DeliveryEvent::ConnectionReused),
+                            SpanId = message.span_id,
+                            Domain = domain.domain.clone(),
+                            Hostname = envelope.mx.to_string(),
+                            Total = connection.messages,
+                        );
+
+                        let rcpts = recipients.iter_mut().filter(|r| r.domain_idx == domain_idx);
+                        let delivery_result = match connection.client {
+                            ReusedClient::Plain(smtp_client) => {
+                                message
+                                    .deliver_session(
+                                        smtp_client,
+                                        connection.capabilities,
+                                        rcpts,
+                                        params,
+                                    )
+                                    .await
+                            }
+                            ReusedClient::Tls(smtp_client) => {
+                                tracker.tls(smtp_client.tls_connection());
+                                message
+                                    .deliver_session(
+                                        smtp_client,
+                                        connection.capabilities,
+                                        rcpts,
+                                        params,
+                                    )
+                                    .await
+                            }
+                        };
+                        if let Some(key) = &throttle_key {
+                            server.throttle_feedback(
+                                key,
+                                &delivery_result,
+                                recipients.iter().filter(|r| r.domain_idx == domain_idx),
+                                message.span_id,
+                            );
+                        }
+
+                        // Update status for the current domain and continue with the next one
+                        let schedule = server
+                            .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
+                            .await
+                            .unwrap_or_else(|| vec![Duration::from_secs(60)]);
+                        message.domains[domain_idx].set_status(delivery_result, &schedule);
+                        continue 'next_domain;
+                    }
+
+                    // Limit concurrent connections to the remote host
+                    let mut max_per_host = server
+                        .eval_if(
+                            &queue_config.connection.max_per_host,
+                            &envelope,
+                            message.span_id,
+                        )
+                        .await
+                        .unwrap_or(0);
+                    if max_adaptive > 0 && (max_per_host == 0 || max_adaptive < max_per_host) {
+                        max_per_host = max_adaptive;
+                    }
+                    if let Some(slot) = server.acquire_smtp_slot(envelope.mx, max_per_host) {
+                        params.slot = Some(slot);
+                    } else {
+                        trc::event!(
+                            Delivery(DeliveryEvent::ConcurrencyLimitExceeded),
+                            SpanId = message.span_id,
+                            Domain = domain.domain.clone(),
+                            Hostname = envelope.mx.to_string(),
+                            Limit = max_per_host,
+                        );
+                        message.domains[domain_idx]
+                            .set_concurrency_error(now() + CONNECTION_RETRY_INTERVAL);
+                        continue 'next_domain;
+                    }
+
                     // Connect
                     let time = Instant::now();
                     let conn_timeout = server
                         .eval_if(&queue_config.timeout.connect, &envelope, message.span_id)
                         .await
                         .unwrap_or_else(|| Duration::from_secs(5 * 60));
-                    let mut smtp_client = match if let Some(ip_addr) = source_ip {
+                    let mut smtp_client = match if let Some(proxy) = proxy {
+                        SmtpClient::connect_proxy(
+                            proxy,
+                            source_ip,
+                            SocketAddr::new(remote_ip, remote_host.port()),
+                            conn_timeout,
+                            span_id,
+                        )
+                        .await
+                    } else if let Some(ip_addr) = source_ip {
                         SmtpClient::connect_using(
                             ip_addr,
                             SocketAddr::new(remote_ip, remote_host.port()),
@@ -925,43 +1120,6 @@ impl QueuedMessage {
                         }
                     };
 
-                    // Obtain session parameters
-                    let local_hostname = server
-                        .eval_if::<String, _>(&queue_config.hostname, &envelope, message.span_id)
-                        .await
-                        .filter(|s| !s.is_empty())
-                        .unwrap_or_else(|| {
-                            trc::event!(
-                                Delivery(DeliveryEvent::MissingOutboundHostname),
-                                SpanId = message.span_id,
-                            );
-                            "local.host".to_string()
-                        });
-                    let params = SessionParams {
-                        session_id: message.span_id,
-                        server: &server,
-                        credentials: remote_host.credentials(),
-                        is_smtp: remote_host.is_smtp(),
-                        hostname: envelope.mx,
-                        local_hostname: &local_hostname,
-                        timeout_ehlo: server
-                            .eval_if(&queue_config.timeout.ehlo, &envelope, message.span_id)
-                            .await
-                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
-                        timeout_mail: server
-                            .eval_if(&queue_config.timeout.mail, &envelope, message.span_id)
-                            .await
-                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
-                        timeout_rcpt: server
-                            .eval_if(&queue_config.timeout.rcpt, &envelope, message.span_id)
-                            .await
-                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
-                        timeout_data: server
-                            .eval_if(&queue_config.timeout.data, &envelope, message.span_id)
-                            .await
-                            .unwrap_or_else(|| Duration::from_secs(5 * 60)),
-                    };
-
                     // Prepare TLS connector
                     let is_strict_tls = tls_strategy.is_tls_required()
                         || (message.flags & MAIL_REQUIRETLS) != 0
@@ -1059,6 +1217,7 @@ impl QueuedMessage {
                                         ),
                                         Elapsed = time.elapsed(),
                                     );
+                                    tracker.tls(smtp_client.tls_connection());
 
                                     // Verify DANE
                                     if let Some(dane_policy) = &dane_policy {
@@ -1240,7 +1399,10 @@ impl QueuedMessage {
                             .unwrap_or_else(|| Duration::from_secs(3 * 60));
                         let mut smtp_client =
                             match smtp_client.into_tls(tls_connector, envelope.mx).await {
-                                Ok(smtp_client) => smtp_client,
+                                Ok(smtp_client) => {
+                                    tracker.tls(smtp_client.tls_connection());
+                                    smtp_client
+                                }
                                 Err(error) => {
                                     trc::event!(
                                         Delivery(DeliveryEvent::ImplicitTlsError),
@@ -1282,14 +1444,18 @@ impl QueuedMessage {
                             )
                             .await
                     };
+                    if let Some(key) = &throttle_key {
+                        server.throttle_feedback(
+                            key,
+                            &delivery_result,
+                            recipients.iter().filter(|r| r.domain_idx == domain_idx),
+                            message.span_id,
+                        );
+                    }
 
                     // Update status for the current domain and continue with the next one
                     let schedule = server
-                        .eval_if::<Vec<Duration>, _>(
-                            &queue_config.retry,
-                            &envelope,
-                            message.span_id,
-                        )
+                        .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                         .await
                         .unwrap_or_else(|| vec![Duration::from_secs(60)]);
                     message.domains[domain_idx].set_status(delivery_result, &schedule);
@@ -1299,13 +1465,22 @@ impl QueuedMessage {
 
             // Update status
             let schedule = server
-                .eval_if::<Vec<Duration>, _>(&queue_config.retry, &envelope, message.span_id)
+                .eval_if::<Vec<Duration>, _>(retry, &envelope, message.span_id)
                 .await
                 .unwrap_or_else(|| vec![Duration::from_secs(60)]);
+            if let Some(key) = server.throttle_key(&message.domains[domain_idx].domain, envelope.mx)
+            {
+                server.throttle_feedback(&key, &last_status, std::iter::empty(), message.span_id);
+            }
             message.domains[domain_idx].set_status(last_status, &schedule);
         }
         message.recipients = recipients;
 
+        // Record delivery attempts
+        server
+            .track_events(&message, tracker.into_events(&message))
+            .await;
+
         // Send Delivery Status Notifications
         server.send_dsn(&mut message).await;
 
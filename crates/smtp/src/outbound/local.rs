@@ -11,8 +11,9 @@ use trc::SieveEvent;
 
 use crate::{
     queue::{
-        quota::HasQueueQuota, spool::SmtpSpool, DomainPart, Error, ErrorDetails, HostResponse,
-        Message, MessageSource, Recipient, Status, RCPT_STATUS_CHANGED,
+        quota::HasQueueQuota, spool::SmtpSpool, srs::SmtpSrs, DomainPart, Error, ErrorDetails,
+        HostResponse, Message, MessageSource, Recipient, Status, MAIL_AUTHENTICATED,
+        MAIL_DMARC_PASS, MAIL_SPF_PASS, RCPT_STATUS_CHANGED,
     },
     reporting::SmtpReporting,
 };
@@ -45,6 +46,9 @@ impl Message {
         let delivery_result = server
             .deliver_message(IngestMessage {
                 sender_address: self.return_path_lcase.clone(),
+                sender_authenticated: self.has_flag(MAIL_AUTHENTICATED),
+                spf_pass: self.has_flag(MAIL_SPF_PASS),
+                dmarc_pass: self.has_flag(MAIL_DMARC_PASS),
                 recipients: recipient_addresses,
                 message_blob: self.blob_hash.clone(),
                 message_size: self.size,
@@ -111,6 +115,7 @@ impl Message {
             for rcpt in autogenerated.recipients {
                 message.add_recipient(rcpt, server).await;
             }
+            server.srs_forward(&mut message).await;
 
             // Sign message
             let signature = server
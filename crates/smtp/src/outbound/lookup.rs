@@ -39,6 +39,7 @@ pub trait DnsLookup: Sync + Send {
         &self,
         remote_host: &NextHop<'_>,
         envelope: &impl ResolveVariable,
+        queue: Option<&str>,
         max_multihomed: usize,
         session_id: u64,
     ) -> impl Future<Output = Result<IpLookupResult, Status<(), Error>>> + Send;
@@ -118,6 +119,7 @@ impl DnsLookup for Server {
         &self,
         remote_host: &NextHop<'_>,
         envelope: &impl ResolveVariable,
+        queue: Option<&str>,
         max_multihomed: usize,
         session_id: u64,
     ) -> Result<IpLookupResult, Status<(), Error>> {
@@ -154,7 +156,7 @@ impl DnsLookup for Server {
             // Obtain source IPv4 address
             let source_ips = self
                 .eval_if::<Vec<Ipv4Addr>, _>(
-                    &self.core.smtp.queue.source_ip.ipv4,
+                    self.core.smtp.queue.source_ipv4_for(queue),
                     envelope,
                     session_id,
                 )
@@ -175,7 +177,7 @@ impl DnsLookup for Server {
             // Obtain source IPv6 address
             let source_ips = self
                 .eval_if::<Vec<Ipv6Addr>, _>(
-                    &self.core.smtp.queue.source_ip.ipv6,
+                    self.core.smtp.queue.source_ipv6_for(queue),
                     envelope,
                     session_id,
                 )
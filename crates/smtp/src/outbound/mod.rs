@@ -21,6 +21,8 @@ pub mod delivery;
 pub mod local;
 pub mod lookup;
 pub mod mta_sts;
+pub mod pool;
+pub mod proxy;
 pub mod session;
 
 #[derive(Debug, Clone, Copy, Default)]
@@ -9,11 +9,16 @@ use std::{fmt::Display, sync::Arc, time::Duration};
 #[cfg(feature = "test_mode")]
 pub static STS_TEST_POLICY: parking_lot::Mutex<Vec<u8>> = parking_lot::Mutex::new(Vec::new());
 
-use common::{config::smtp::resolver::Policy, Server};
+use common::{
+    config::smtp::{queue::OutboundProxy, resolver::Policy},
+    Server,
+};
 use mail_auth::{mta_sts::MtaSts, report::tlsrpt::ResultType};
 
 use super::{parse::ParsePolicy, Error};
 
+#[cfg(not(feature = "test_mode"))]
+use mail_send::Credentials;
 #[cfg(not(feature = "test_mode"))]
 use utils::HttpLimitResponse;
 
@@ -25,6 +30,7 @@ pub trait MtaStsLookup: Sync + Send {
         &self,
         domain: &str,
         timeout: Duration,
+        proxy: Option<&OutboundProxy>,
     ) -> impl std::future::Future<Output = Result<Arc<Policy>, Error>> + Send;
 }
 
@@ -34,6 +40,7 @@ impl MtaStsLookup for Server {
         &self,
         domain: &str,
         timeout: Duration,
+        proxy: Option<&OutboundProxy>,
     ) -> Result<Arc<Policy>, Error> {
         // Lookup MTA-STS TXT record
         let record = match self
@@ -67,10 +74,16 @@ impl MtaStsLookup for Server {
 
         // Fetch policy
         #[cfg(not(feature = "test_mode"))]
-        let bytes = reqwest::Client::builder()
+        let mut client = reqwest::Client::builder()
             .user_agent(common::USER_AGENT)
             .timeout(timeout)
-            .redirect(reqwest::redirect::Policy::none())
+            .redirect(reqwest::redirect::Policy::none());
+        #[cfg(not(feature = "test_mode"))]
+        if let Some(proxy) = proxy {
+            client = client.proxy(build_http_proxy(proxy)?);
+        }
+        #[cfg(not(feature = "test_mode"))]
+        let bytes = client
             .build()?
             .get(format!("https://mta-sts.{domain}/.well-known/mta-sts.txt"))
             .send()
@@ -101,6 +114,28 @@ impl MtaStsLookup for Server {
     }
 }
 
+// Policies are fetched through the same proxy used for delivery, with
+// hostnames resolved by the proxy
+#[cfg(not(feature = "test_mode"))]
+fn build_http_proxy(proxy: &OutboundProxy) -> reqwest::Result<reqwest::Proxy> {
+    use common::config::smtp::queue::ProxyProtocol;
+
+    let scheme = match proxy.protocol {
+        ProxyProtocol::Socks5 => "socks5h",
+        ProxyProtocol::Http => "http",
+    };
+    let url = format!("{scheme}://{}:{}", proxy.address, proxy.port);
+    let Ok(mut url) = reqwest::Url::parse(&url) else {
+        return reqwest::Proxy::all(url);
+    };
+    if let Some(Credentials::Plain { username, secret }) = &proxy.auth {
+        let _ = url.set_username(username);
+        let _ = url.set_password(Some(secret));
+    }
+
+    reqwest::Proxy::all(url)
+}
+
 impl From<&Error> for ResultType {
     fn from(err: &Error) -> Self {
         match &err {
@@ -0,0 +1,260 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    future::Future,
+    sync::{atomic::Ordering, Arc},
+    time::{Duration, Instant},
+};
+
+use common::{Server, SmtpConnectionKey, SmtpConnectionSlot, SmtpIdleConnection, SmtpStream};
+use smtp_proto::EhloResponse;
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+};
+use tokio_rustls::client::TlsStream;
+
+use super::{client::SmtpClient, session::SessionParams};
+
+pub const CONNECTION_RETRY_INTERVAL: u64 = 5;
+const IDLE_QUIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+pub struct ConnectionReuse {
+    pub key: SmtpConnectionKey,
+    pub idle_timeout: Duration,
+    pub max_messages: usize,
+    pub messages: usize,
+}
+
+pub struct ReusedConnection {
+    pub client: ReusedClient,
+    pub capabilities: EhloResponse<String>,
+    pub messages: usize,
+}
+
+#[allow(clippy::large_enum_variant)]
+pub enum ReusedClient {
+    Plain(SmtpClient<TcpStream>),
+    Tls(SmtpClient<TlsStream<TcpStream>>),
+}
+
+pub trait IntoSmtpStream: Send {
+    fn into_smtp_stream(self) -> SmtpStream;
+}
+
+pub trait SmtpConnectionPool: Sync + Send {
+    fn acquire_smtp_slot(&self, hostname: &str, max_per_host: usize) -> Option<SmtpConnectionSlot>;
+
+    fn reuse_smtp_connection(
+        &self,
+        params: &mut SessionParams<'_>,
+    ) -> impl Future<Output = Option<ReusedConnection>> + Send;
+
+    fn release_smtp_connection<T: AsyncRead + AsyncWrite + IntoSmtpStream + Unpin>(
+        &self,
+        smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+        params: SessionParams<'_>,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn evict_smtp_connections(&self);
+}
+
+impl SmtpConnectionPool for Server {
+    fn acquire_smtp_slot(&self, hostname: &str, max_per_host: usize) -> Option<SmtpConnectionSlot> {
+        let mut hosts = self.inner.data.smtp_connections.hosts.lock();
+        let counter = hosts.entry(hostname.to_string()).or_default();
+
+        if max_per_host == 0 || counter.load(Ordering::Relaxed) < max_per_host {
+            counter.fetch_add(1, Ordering::Relaxed);
+            Some(SmtpConnectionSlot(counter.clone()))
+        } else {
+            None
+        }
+    }
+
+    async fn reuse_smtp_connection(
+        &self,
+        params: &mut SessionParams<'_>,
+    ) -> Option<ReusedConnection> {
+        let reuse = params.reuse.as_mut()?;
+        let timeout = params.timeout_mail;
+        let session_id = params.session_id;
+
+        loop {
+            // Take the most recently used connection that has not expired
+            let connection = {
+                let mut idle = self.inner.data.smtp_connections.idle.lock();
+                let connections = idle.get_mut(&reuse.key)?;
+                let mut expired = Vec::new();
+                take_expired(connections, Instant::now(), &mut expired);
+                quit_connections(expired);
+                let connection = connections.pop();
+                if connections.is_empty() {
+                    idle.remove(&reuse.key);
+                }
+                connection?
+            };
+
+            // Reset the session, discard the connection if the server went away
+            let SmtpIdleConnection {
+                stream,
+                capabilities,
+                messages,
+                slot,
+                ..
+            } = connection;
+            let client = match stream {
+                SmtpStream::Plain(stream) => {
+                    let mut smtp_client = SmtpClient {
+                        stream,
+                        timeout,
+                        session_id,
+                    };
+                    if !smtp_client.reset().await {
+                        continue;
+                    }
+                    ReusedClient::Plain(smtp_client)
+                }
+                SmtpStream::Tls(stream) => {
+                    let mut smtp_client = SmtpClient {
+                        stream,
+                        timeout,
+                        session_id,
+                    };
+                    if !smtp_client.reset().await {
+                        continue;
+                    }
+                    ReusedClient::Tls(smtp_client)
+                }
+            };
+
+            reuse.messages = messages;
+            params.slot = Some(slot);
+
+            return Some(ReusedConnection {
+                client,
+                capabilities,
+                messages,
+            });
+        }
+    }
+
+    async fn release_smtp_connection<T: AsyncRead + AsyncWrite + IntoSmtpStream + Unpin>(
+        &self,
+        smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+        params: SessionParams<'_>,
+    ) {
+        match (params.reuse, params.slot) {
+            (Some(reuse), Some(slot)) if reuse.messages + 1 < reuse.max_messages => {
+                let now = Instant::now();
+                let mut idle = self.inner.data.smtp_connections.idle.lock();
+
+                // Close expired connections to other hosts
+                let mut expired = Vec::new();
+                idle.retain(|_, connections| {
+                    take_expired(connections, now, &mut expired);
+                    !connections.is_empty()
+                });
+                quit_connections(expired);
+                idle.entry(reuse.key).or_default().push(SmtpIdleConnection {
+                    stream: smtp_client.stream.into_smtp_stream(),
+                    capabilities,
+                    messages: reuse.messages + 1,
+                    expires: now + reuse.idle_timeout,
+                    slot,
+                });
+            }
+            _ => {
+                smtp_client.quit().await;
+            }
+        }
+    }
+
+    fn evict_smtp_connections(&self) {
+        let pool = &self.inner.data.smtp_connections;
+        let now = Instant::now();
+        let mut expired = Vec::new();
+        pool.idle.lock().retain(|_, connections| {
+            take_expired(connections, now, &mut expired);
+            !connections.is_empty()
+        });
+
+        // Remove counters of hosts without any open connections
+        pool.hosts
+            .lock()
+            .retain(|_, counter| Arc::strong_count(counter) > 1);
+
+        quit_connections(expired);
+    }
+}
+
+fn take_expired(
+    connections: &mut Vec<SmtpIdleConnection>,
+    now: Instant,
+    expired: &mut Vec<SmtpIdleConnection>,
+) {
+    let (active, stale): (Vec<_>, Vec<_>) = std::mem::take(connections)
+        .into_iter()
+        .partition(|c| c.expires > now);
+    *connections = active;
+    expired.extend(stale);
+}
+
+// Expired connections are closed in the background, their slots are
+// released once the remote server acknowledges the QUIT
+fn quit_connections(connections: Vec<SmtpIdleConnection>) {
+    if connections.is_empty() {
+        return;
+    }
+
+    tokio::spawn(async move {
+        for connection in connections {
+            match connection.stream {
+                SmtpStream::Plain(stream) => {
+                    SmtpClient {
+                        stream,
+                        timeout: IDLE_QUIT_TIMEOUT,
+                        session_id: 0,
+                    }
+                    .quit()
+                    .await
+                }
+                SmtpStream::Tls(stream) => {
+                    SmtpClient {
+                        stream,
+                        timeout: IDLE_QUIT_TIMEOUT,
+                        session_id: 0,
+                    }
+                    .quit()
+                    .await
+                }
+            }
+        }
+    });
+}
+
+impl<T: AsyncRead + AsyncWrite + Unpin> SmtpClient<T> {
+    async fn reset(&mut self) -> bool {
+        self.cmd(b"RSET\r\n")
+            .await
+            .is_ok_and(|r| r.is_positive_completion())
+    }
+}
+
+impl IntoSmtpStream for TcpStream {
+    fn into_smtp_stream(self) -> SmtpStream {
+        SmtpStream::Plain(self)
+    }
+}
+
+impl IntoSmtpStream for TlsStream<TcpStream> {
+    fn into_smtp_stream(self) -> SmtpStream {
+        SmtpStream::Tls(self)
+    }
+}
@@ -0,0 +1,214 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    io,
+    net::{IpAddr, SocketAddr},
+    time::Duration,
+};
+
+use base64::{Engine, engine::general_purpose::STANDARD};
+use common::config::smtp::queue::{OutboundProxy, ProxyProtocol};
+use mail_send::Credentials;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpSocket, TcpStream, lookup_host},
+};
+
+use super::client::SmtpClient;
+
+const MAX_HTTP_RESPONSE: usize = 8192;
+
+impl SmtpClient<TcpStream> {
+    /// Connects to a remote host address through a SOCKS5 or HTTP CONNECT proxy.
+    /// The returned stream is a plain tunnel, so STARTTLS and certificate
+    /// verification still happen end-to-end with the remote host.
+    pub async fn connect_proxy(
+        proxy: &OutboundProxy,
+        local_ip: Option<IpAddr>,
+        remote_addr: SocketAddr,
+        timeout: Duration,
+        session_id: u64,
+    ) -> mail_send::Result<Self> {
+        tokio::time::timeout(timeout, async {
+            let mut stream = connect_to_proxy(proxy, local_ip).await?;
+            match proxy.protocol {
+                ProxyProtocol::Socks5 => socks5_connect(&mut stream, proxy, remote_addr).await?,
+                ProxyProtocol::Http => http_connect(&mut stream, proxy, remote_addr).await?,
+            }
+
+            Ok(SmtpClient {
+                stream,
+                timeout,
+                session_id,
+            })
+        })
+        .await
+        .map_err(|_| mail_send::Error::Timeout)?
+    }
+}
+
+async fn connect_to_proxy(
+    proxy: &OutboundProxy,
+    local_ip: Option<IpAddr>,
+) -> io::Result<TcpStream> {
+    let mut last_err = None;
+    for addr in lookup_host((proxy.address.as_str(), proxy.port)).await? {
+        // The source address is selected for the remote host, proxies of a
+        // different address family are reached without binding to it
+        let result = match local_ip.filter(|ip| ip.is_ipv4() == addr.is_ipv4()) {
+            Some(local_ip) => {
+                let socket = if local_ip.is_ipv4() {
+                    TcpSocket::new_v4()?
+                } else {
+                    TcpSocket::new_v6()?
+                };
+                socket.bind(SocketAddr::new(local_ip, 0))?;
+                socket.connect(addr).await
+            }
+            None => TcpStream::connect(addr).await,
+        };
+
+        match result {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_err = Some(err),
+        }
+    }
+
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("Could not resolve proxy {}", proxy.address),
+        )
+    }))
+}
+
+// RFC 1928 and RFC 1929
+async fn socks5_connect(
+    stream: &mut TcpStream,
+    proxy: &OutboundProxy,
+    remote_addr: SocketAddr,
+) -> io::Result<()> {
+    // Negotiate authentication method
+    let credentials = match &proxy.auth {
+        Some(Credentials::Plain { username, secret }) => Some((username, secret)),
+        _ => None,
+    };
+    if credentials.is_some() {
+        stream.write_all(&[0x05, 0x02, 0x00, 0x02]).await?;
+    } else {
+        stream.write_all(&[0x05, 0x01, 0x00]).await?;
+    }
+    let mut reply = [0u8; 2];
+    stream.read_exact(&mut reply).await?;
+    match (reply, credentials) {
+        ([0x05, 0x00], _) => {}
+        ([0x05, 0x02], Some((username, secret))) => {
+            if username.len() > 255 || secret.len() > 255 {
+                return Err(proxy_error("SOCKS5 credentials are too long"));
+            }
+            let mut request = Vec::with_capacity(3 + username.len() + secret.len());
+            request.push(0x01);
+            request.push(username.len() as u8);
+            request.extend_from_slice(username.as_bytes());
+            request.push(secret.len() as u8);
+            request.extend_from_slice(secret.as_bytes());
+            stream.write_all(&request).await?;
+
+            stream.read_exact(&mut reply).await?;
+            if reply[1] != 0x00 {
+                return Err(proxy_error("SOCKS5 authentication failed"));
+            }
+        }
+        _ => {
+            return Err(proxy_error("SOCKS5 proxy rejected authentication methods"));
+        }
+    }
+
+    // Request a tunnel to the remote address
+    let mut request = Vec::with_capacity(22);
+    request.extend_from_slice(&[0x05, 0x01, 0x00]);
+    match remote_addr.ip() {
+        IpAddr::V4(ip) => {
+            request.push(0x01);
+            request.extend_from_slice(&ip.octets());
+        }
+        IpAddr::V6(ip) => {
+            request.push(0x04);
+            request.extend_from_slice(&ip.octets());
+        }
+    }
+    request.extend_from_slice(&remote_addr.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[0] != 0x05 {
+        return Err(proxy_error("Invalid SOCKS5 reply"));
+    }
+    if reply[1] != 0x00 {
+        return Err(proxy_error(match reply[1] {
+            0x02 => "SOCKS5 connection not allowed by ruleset",
+            0x03 => "SOCKS5 network unreachable",
+            0x04 => "SOCKS5 host unreachable",
+            0x05 => "SOCKS5 connection refused",
+            0x06 => "SOCKS5 TTL expired",
+            _ => "SOCKS5 general failure",
+        }));
+    }
+
+    // Skip the bound address
+    let addr_len = match reply[3] {
+        0x01 => 4,
+        0x04 => 16,
+        0x03 => stream.read_u8().await? as usize,
+        _ => return Err(proxy_error("Invalid SOCKS5 address type")),
+    };
+    let mut bound_addr = vec![0u8; addr_len + 2];
+    stream.read_exact(&mut bound_addr).await?;
+
+    Ok(())
+}
+
+async fn http_connect(
+    stream: &mut TcpStream,
+    proxy: &OutboundProxy,
+    remote_addr: SocketAddr,
+) -> io::Result<()> {
+    let mut request = format!("CONNECT {remote_addr} HTTP/1.1\r\nHost: {remote_addr}\r\n");
+    if let Some(Credentials::Plain { username, secret }) = &proxy.auth {
+        request.push_str(&format!(
+            "Proxy-Authorization: Basic {}\r\n",
+            STANDARD.encode(format!("{username}:{secret}"))
+        ));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // Read the response one byte at a time, as anything after the
+    // headers already belongs to the SMTP session
+    let mut response = Vec::with_capacity(128);
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE {
+            return Err(proxy_error("HTTP proxy response too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+
+    let status = std::str::from_utf8(&response)
+        .ok()
+        .and_then(|response| response.split_once("\r\n"))
+        .map(|(status, _)| status)
+        .unwrap_or_default();
+    match status.split(' ').nth(1) {
+        Some(code) if status.starts_with("HTTP/1.") && code.starts_with('2') => Ok(()),
+        _ => Err(proxy_error(format!("HTTP proxy refused tunnel: {status}"))),
+    }
+}
+
+fn proxy_error(details: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::ConnectionRefused, details.into())
+}
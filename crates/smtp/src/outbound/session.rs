@@ -5,7 +5,7 @@
  */
 
 use common::config::smtp::queue::RequireOptional;
-use common::Server;
+use common::{Server, SmtpConnectionSlot};
 use mail_send::Credentials;
 use smtp_proto::{
     EhloResponse, Severity, EXT_CHUNKING, EXT_DSN, EXT_REQUIRE_TLS, EXT_SIZE, EXT_SMTP_UTF8,
@@ -22,7 +22,11 @@ use crate::queue::{ErrorDetails, HostResponse, RCPT_STATUS_CHANGED};
 
 use crate::queue::{Error, Message, Recipient, Status};
 
-use super::{client::SmtpClient, TlsStrategy};
+use super::{
+    client::SmtpClient,
+    pool::{ConnectionReuse, IntoSmtpStream, SmtpConnectionPool},
+    TlsStrategy,
+};
 
 pub struct SessionParams<'x> {
     pub server: &'x Server,
@@ -35,10 +39,12 @@ pub struct SessionParams<'x> {
     pub timeout_rcpt: Duration,
     pub timeout_data: Duration,
     pub session_id: u64,
+    pub reuse: Option<ConnectionReuse>,
+    pub slot: Option<SmtpConnectionSlot>,
 }
 
 impl Message {
-    pub async fn deliver<T: AsyncRead + AsyncWrite + Unpin>(
+    pub async fn deliver<T: AsyncRead + AsyncWrite + IntoSmtpStream + Unpin>(
         &self,
         mut smtp_client: SmtpClient<T>,
         recipients: impl Iterator<Item = &mut Recipient>,
@@ -112,6 +118,17 @@ impl Message {
             };*/
         }
 
+        self.deliver_session(smtp_client, capabilities, recipients, params)
+            .await
+    }
+
+    pub async fn deliver_session<T: AsyncRead + AsyncWrite + IntoSmtpStream + Unpin>(
+        &self,
+        mut smtp_client: SmtpClient<T>,
+        capabilities: EhloResponse<String>,
+        recipients: impl Iterator<Item = &mut Recipient>,
+        params: SessionParams<'_>,
+    ) -> Status<(), Error> {
         // MAIL FROM
         let time = Instant::now();
         smtp_client.timeout = params.timeout_mail;
@@ -379,7 +396,11 @@ impl Message {
             }
         }
 
-        smtp_client.quit().await;
+        // Keep the connection open for the next message, if allowed
+        params
+            .server
+            .release_smtp_connection(smtp_client, capabilities, params)
+            .await;
         if total_completed == total_rcpt {
             Status::Completed(())
         } else {
@@ -22,6 +22,8 @@ use crate::outbound::client::from_error_status;
 use crate::reporting::SmtpReporting;
 
 use super::spool::SmtpSpool;
+use super::srs::SmtpSrs;
+use super::tracking::{SmtpTracking, TrackingEvent, TrackingEventType};
 use super::{
     Domain, Error, ErrorDetails, HostResponse, Message, MessageSource, QueueEnvelope, Recipient,
     Status, RCPT_DSN_SENT, RCPT_STATUS_CHANGED,
@@ -39,16 +41,36 @@ impl SendDsn for Server {
 
         if !message.return_path.is_empty() {
             // Build DSN
-            if let Some(dsn) = message.build_dsn(self).await {
+            let mut notified = Vec::new();
+            if let Some(dsn) = message.build_dsn(self, &mut notified).await {
                 let mut dsn_message = self.new_message("", "", "", message.span_id);
-                dsn_message
-                    .add_recipient_parts(
-                        &message.return_path,
-                        &message.return_path_lcase,
-                        &message.return_path_domain,
-                        self,
-                    )
-                    .await;
+                match self.srs_reverse(&message.return_path) {
+                    Ok(Some(return_path)) => {
+                        // Bounce forwarded messages directly to the original sender
+                        dsn_message.add_recipient(return_path, self).await;
+                    }
+                    Ok(None) => {
+                        dsn_message
+                            .add_recipient_parts(
+                                &message.return_path,
+                                &message.return_path_lcase,
+                                &message.return_path_domain,
+                                self,
+                            )
+                            .await;
+                    }
+                    Err(err) => {
+                        // Invalid or expired SRS addresses cannot be bounced to
+                        trc::event!(
+                            Smtp(trc::SmtpEvent::SrsInvalid),
+                            SpanId = message.span_id,
+                            To = message.return_path_lcase.clone(),
+                            Reason = format!("{err:?}"),
+                            Details = "DSN discarded.",
+                        );
+                        return;
+                    }
+                }
 
                 // Sign message
                 let signature = self
@@ -56,7 +78,7 @@ impl SendDsn for Server {
                     .await;
 
                 // Queue DSN
-                dsn_message
+                if dsn_message
                     .queue(
                         signature.as_deref(),
                         &dsn,
@@ -64,7 +86,18 @@ impl SendDsn for Server {
                         self,
                         MessageSource::Dsn,
                     )
+                    .await
+                {
+                    let timestamp = now();
+                    self.track_events(
+                        message,
+                        notified
+                            .into_iter()
+                            .map(|(rcpt, typ)| (rcpt, TrackingEvent::new(timestamp, typ)))
+                            .collect(),
+                    )
                     .await;
+                }
             }
         } else {
             // Handle double bounce
@@ -160,7 +193,11 @@ impl SendDsn for Server {
 }
 
 impl Message {
-    pub async fn build_dsn(&mut self, server: &Server) -> Option<Vec<u8>> {
+    pub async fn build_dsn(
+        &mut self,
+        server: &Server,
+        notified: &mut Vec<(String, TrackingEventType)>,
+    ) -> Option<Vec<u8>> {
         let config = &server.core.smtp.queue;
         let now = now();
 
@@ -181,6 +218,7 @@ impl Message {
                         continue;
                     }
                     rcpt.write_dsn(&mut dsn);
+                    notified.push((rcpt.address_lcase.clone(), TrackingEventType::DsnSuccess));
                     rcpt.status.write_dsn(&mut dsn);
                     response.write_dsn_text(&rcpt.address, &mut txt_success);
                 }
@@ -188,6 +226,7 @@ impl Message {
                     if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
                 {
                     rcpt.write_dsn(&mut dsn);
+                    notified.push((rcpt.address_lcase.clone(), TrackingEventType::DsnDelay));
                     rcpt.status.write_dsn(&mut dsn);
                     domain.write_dsn_will_retry_until(&mut dsn);
                     response.write_dsn_text(&rcpt.address, &mut txt_delay);
@@ -198,6 +237,7 @@ impl Message {
                         continue;
                     }
                     rcpt.write_dsn(&mut dsn);
+                    notified.push((rcpt.address_lcase.clone(), TrackingEventType::DsnFailure));
                     rcpt.status.write_dsn(&mut dsn);
                     response.write_dsn_text(&rcpt.address, &mut txt_failed);
                 }
@@ -210,6 +250,8 @@ impl Message {
                                 continue;
                             }
                             rcpt.write_dsn(&mut dsn);
+                            notified
+                                .push((rcpt.address_lcase.clone(), TrackingEventType::DsnFailure));
                             domain.status.write_dsn(&mut dsn);
                             err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_failed);
                         }
@@ -217,6 +259,8 @@ impl Message {
                             if domain.notify.due <= now && rcpt.has_flag(RCPT_NOTIFY_DELAY) =>
                         {
                             rcpt.write_dsn(&mut dsn);
+                            notified
+                                .push((rcpt.address_lcase.clone(), TrackingEventType::DsnDelay));
                             domain.status.write_dsn(&mut dsn);
                             domain.write_dsn_will_retry_until(&mut dsn);
                             err.write_dsn_text(&rcpt.address, &domain.domain, &mut txt_delay);
@@ -226,6 +270,8 @@ impl Message {
                         {
                             // This case should not happen under normal circumstances
                             rcpt.write_dsn(&mut dsn);
+                            notified
+                                .push((rcpt.address_lcase.clone(), TrackingEventType::DsnDelay));
                             domain.status.write_dsn(&mut dsn);
                             domain.write_dsn_will_retry_until(&mut dsn);
                             Error::ConcurrencyLimited.write_dsn_text(
@@ -323,7 +369,11 @@ impl Message {
                     let envelope = QueueEnvelope::new(self, domain_idx);
 
                     if let Some(next_notify) = server
-                        .eval_if::<Vec<Duration>, _>(&config.notify, &envelope, self.span_id)
+                        .eval_if::<Vec<Duration>, _>(
+                            config.notify_for(self.queue.as_deref()),
+                            &envelope,
+                            self.span_id,
+                        )
                         .await
                         .and_then(|notify| {
                             notify.into_iter().nth((domain.notify.inner + 1) as usize)
@@ -12,6 +12,7 @@ use std::{
 use ahash::{AHashMap, AHashSet};
 use common::{
     Inner,
+    config::smtp::queue::DEFAULT_QUEUE_NAME,
     core::BuildServer,
     ipc::{QueueEvent, QueueEventStatus},
     listener::limiter::ConcurrencyLimiter,
@@ -20,6 +21,8 @@ use rand::seq::SliceRandom;
 use store::write::now;
 use tokio::sync::mpsc;
 
+use crate::outbound::pool::SmtpConnectionPool;
+
 use super::{
     Message, QueueId, Status,
     spool::{QUEUE_REFRESH, SmtpSpool},
@@ -34,7 +37,9 @@ pub struct Queue {
 
 #[derive(Debug)]
 pub enum OnHold {
-    InFlight,
+    InFlight {
+        queue: Option<String>,
+    },
     ConcurrencyLimited {
         limiters: Vec<ConcurrencyLimiter>,
         next_due: Option<u64>,
@@ -54,6 +59,7 @@ impl SpawnQueue for mpsc::Receiver<QueueEvent> {
 
 const CLEANUP_INTERVAL: Duration = Duration::from_secs(10 * 60);
 const BACK_PRESSURE_WARN_INTERVAL: Duration = Duration::from_secs(60);
+const EVICTION_INTERVAL: Duration = Duration::from_secs(30);
 
 impl Queue {
     pub fn new(core: Arc<Inner>, rx: mpsc::Receiver<QueueEvent>) -> Self {
@@ -68,19 +74,28 @@ impl Queue {
     pub async fn start(&mut self) {
         let mut is_paused = false;
         let mut next_cleanup = Instant::now() + CLEANUP_INTERVAL;
+        let mut next_eviction = Instant::now() + EVICTION_INTERVAL;
         let mut last_backpressure_warning = Instant::now() - BACK_PRESSURE_WARN_INTERVAL;
         let mut in_flight_count = 0;
+        let mut in_flight_queues: AHashMap<Option<String>, usize> = AHashMap::new();
         let mut has_back_pressure = false;
 
         loop {
             let refresh_queue = match tokio::time::timeout(
-                self.next_wake_up.duration_since(Instant::now()),
+                self.next_wake_up
+                    .min(next_eviction)
+                    .duration_since(Instant::now()),
                 self.rx.recv(),
             )
             .await
             {
                 Ok(Some(QueueEvent::WorkerDone { queue_id, status })) => {
                     in_flight_count -= 1;
+                    if let Some(OnHold::InFlight { queue }) = self.on_hold.get(&queue_id) {
+                        if let Some(count) = in_flight_queues.get_mut(queue) {
+                            *count = count.saturating_sub(1);
+                        }
+                    }
 
                     match status {
                         QueueEventStatus::Completed => {
@@ -111,18 +126,39 @@ impl Queue {
                     is_paused = paused;
                     false
                 }
-                Err(_) => true,
+                Ok(Some(QueueEvent::PausedQueue { queue, paused })) => {
+                    let mut queue_paused = self.core.data.queue_paused.write();
+                    if paused {
+                        queue_paused.insert(queue);
+                        false
+                    } else {
+                        queue_paused.remove(&queue)
+                    }
+                }
+                Err(_) => false,
                 Ok(Some(QueueEvent::Stop)) | Ok(None) => {
                     break;
                 }
             };
 
+            // Close idle outbound connections that have expired
+            if next_eviction <= Instant::now() {
+                next_eviction = Instant::now() + EVICTION_INTERVAL;
+                self.core.build_server().evict_smtp_connections();
+            }
+
             if !is_paused {
                 // Deliver scheduled messages
                 if refresh_queue || self.next_wake_up <= Instant::now() {
                     // If the number of in-flight messages is greater than the maximum allowed, skip the queue
                     let server = self.core.build_server();
-                    let max_in_flight = server.core.smtp.queue.max_threads;
+                    let queue_config = &server.core.smtp.queue;
+                    let max_in_flight = queue_config.max_threads
+                        + queue_config
+                            .named
+                            .values()
+                            .map(|q| q.threads)
+                            .sum::<usize>();
                     has_back_pressure = in_flight_count >= max_in_flight;
                     if has_back_pressure {
                         self.next_wake_up = Instant::now() + Duration::from_secs(QUEUE_REFRESH);
@@ -140,7 +176,7 @@ impl Queue {
                                     .values()
                                     .fold([0, 0, 0], |mut acc, v| {
                                         match v {
-                                            OnHold::InFlight => acc[0] += 1,
+                                            OnHold::InFlight { .. } => acc[0] += 1,
                                             OnHold::ConcurrencyLimited { .. } => acc[1] += 1,
                                             OnHold::Locked { .. } => acc[2] += 1,
                                         }
@@ -164,8 +200,17 @@ impl Queue {
                         queue_events.shuffle(&mut rand::rng());
                     }
 
+                    let queue_paused = self.core.data.queue_paused.read().clone();
+
                     for queue_event in &queue_events {
                         if queue_event.due <= now {
+                            // Skip messages in paused queues
+                            if queue_paused.contains(
+                                queue_event.queue.as_deref().unwrap_or(DEFAULT_QUEUE_NAME),
+                            ) {
+                                continue;
+                            }
+
                             // Enforce global concurrency limits
                             if in_flight_count >= max_in_flight {
                                 has_back_pressure = true;
@@ -182,7 +227,7 @@ impl Queue {
                                             .values()
                                             .fold([0, 0, 0], |mut acc, v| {
                                                 match v {
-                                                    OnHold::InFlight => acc[0] += 1,
+                                                    OnHold::InFlight { .. } => acc[0] += 1,
                                                     OnHold::ConcurrencyLimited { .. } => {
                                                         acc[1] += 1
                                                     }
@@ -219,16 +264,33 @@ impl Queue {
                                             continue;
                                         }
                                     }
-                                    OnHold::InFlight => continue,
+                                    OnHold::InFlight { .. } => continue,
                                 }
 
                                 self.on_hold.remove(&queue_event.queue_id);
                             }
 
+                            // Enforce per-queue concurrency limits
+                            let queue_in_flight = in_flight_queues
+                                .entry(queue_event.queue.clone())
+                                .or_default();
+                            if *queue_in_flight
+                                >= queue_config.threads_for(queue_event.queue.as_deref())
+                            {
+                                has_back_pressure = true;
+                                continue;
+                            }
+
                             // Deliver message
                             in_flight_count += 1;
-                            self.on_hold.insert(queue_event.queue_id, OnHold::InFlight);
-                            queue_event.try_deliver(server.clone());
+                            *queue_in_flight += 1;
+                            self.on_hold.insert(
+                                queue_event.queue_id,
+                                OnHold::InFlight {
+                                    queue: queue_event.queue.clone(),
+                                },
+                            );
+                            queue_event.clone().try_deliver(server.clone());
                         } else {
                             let due_in = queue_event.due - now;
                             if due_in < next_wake_up {
@@ -249,7 +311,7 @@ impl Queue {
                                 .collect::<AHashSet<_>>();
                             let now = store::write::now();
                             self.on_hold.retain(|queue_id, status| match status {
-                                OnHold::InFlight => true,
+                                OnHold::InFlight { .. } => true,
                                 OnHold::Locked { until } => *until > now,
                                 OnHold::ConcurrencyLimited { .. } => {
                                     active_queue_ids.contains(queue_id)
@@ -18,9 +18,12 @@ use utils::BlobHash;
 
 pub mod dsn;
 pub mod manager;
+pub mod quarantine;
 pub mod quota;
 pub mod spool;
+pub mod srs;
 pub mod throttle;
+pub mod tracking;
 
 pub type QueueId = u64;
 
@@ -30,10 +33,11 @@ pub struct Schedule<T> {
     pub inner: T,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct QueuedMessage {
     pub due: u64,
     pub queue_id: u64,
+    pub queue: Option<String>,
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -64,6 +68,9 @@ pub struct Message {
     pub size: usize,
     pub quota_keys: Vec<QuotaKey>,
 
+    // Stored in the message event, see `queue_event_value`
+    #[serde(skip)]
+    pub queue: Option<String>,
     #[serde(skip)]
     pub span_id: u64,
 }
@@ -96,6 +103,10 @@ pub struct Recipient {
 pub const RCPT_DSN_SENT: u64 = 1 << 32;
 pub const RCPT_STATUS_CHANGED: u64 = 2 << 32;
 
+pub const MAIL_AUTHENTICATED: u64 = 1 << 32;
+pub const MAIL_SPF_PASS: u64 = 2 << 32;
+pub const MAIL_DMARC_PASS: u64 = 4 << 32;
+
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Status<T, E> {
     #[serde(rename = "scheduled")]
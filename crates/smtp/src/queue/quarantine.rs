@@ -0,0 +1,452 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Write, future::Future, net::IpAddr};
+
+use ahash::AHashMap;
+use common::{Server, KV_LOCK_HOUSEKEEPER};
+use directory::backend::RcptType;
+use mail_builder::{headers::HeaderType, mime::make_boundary, MessageBuilder};
+use mail_parser::DateTime;
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{
+        key::DeserializeBigEndian, now, BatchBuilder, Bincode, BlobOp, ReportClass, ValueClass,
+    },
+    Deserialize as _, IterateParams, Serialize as _, ValueKey, U64_LEN,
+};
+use trc::AddContext;
+use utils::BlobHash;
+
+use crate::reporting::SmtpReporting;
+
+use super::{spool::SmtpSpool, DomainPart, MessageSource, RecipientDomain};
+
+const DIGEST_LOCK: &[u8] = b"quarantine-digest";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuarantinedMessage {
+    pub created: u64,
+    pub blob_hash: BlobHash,
+    pub size: usize,
+    pub return_path: String,
+    pub recipients: Vec<String>,
+    pub source: QuarantineSource,
+    pub reason: String,
+    pub score: Option<f64>,
+    pub remote_ip: IpAddr,
+    pub helo_domain: String,
+    pub from: String,
+    pub subject: String,
+    pub notified: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QuarantineSource {
+    Milter,
+    MtaHook,
+    Sieve,
+    SpamFilter,
+    Antivirus,
+}
+
+pub trait SmtpQuarantine: Sync + Send {
+    fn quarantine_message(
+        &self,
+        message: QuarantinedMessage,
+        raw_message: &[u8],
+        expires: u64,
+        session_id: u64,
+    ) -> impl Future<Output = trc::Result<u64>> + Send;
+
+    fn read_quarantined_message(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<Option<QuarantinedMessage>>> + Send;
+
+    fn release_quarantined_message(
+        &self,
+        id: u64,
+        expires: u64,
+        recipients: Option<&[String]>,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn delete_quarantined_message(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<bool>> + Send;
+
+    fn send_quarantine_digest(&self) -> impl Future<Output = ()> + Send;
+}
+
+impl SmtpQuarantine for Server {
+    async fn quarantine_message(
+        &self,
+        message: QuarantinedMessage,
+        raw_message: &[u8],
+        expires: u64,
+        session_id: u64,
+    ) -> trc::Result<u64> {
+        let id = self.inner.data.queue_id_gen.generate().unwrap_or_else(now);
+
+        // Hold the blob until the quarantine entry expires
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            BlobOp::Reserve {
+                hash: message.blob_hash.clone(),
+                until: expires,
+            },
+            0u32.serialize(),
+        );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+        self.blob_store()
+            .put_blob(message.blob_hash.as_slice(), raw_message)
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(
+            Quarantine(trc::QuarantineEvent::Message),
+            SpanId = session_id,
+            Id = id,
+            From = message.return_path.clone(),
+            To = message.recipients.clone(),
+            Reason = message.reason.clone(),
+            Size = message.size,
+            Expires = trc::Value::Timestamp(expires),
+        );
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .set(
+                BlobOp::Commit {
+                    hash: message.blob_hash.clone(),
+                },
+                Vec::new(),
+            )
+            .set(
+                ValueClass::Report(ReportClass::Quarantine { id, expires }),
+                Bincode::new(message).serialize(),
+            );
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| id)
+    }
+
+    async fn read_quarantined_message(
+        &self,
+        id: u64,
+        expires: u64,
+    ) -> trc::Result<Option<QuarantinedMessage>> {
+        self.store()
+            .get_value::<Bincode<QuarantinedMessage>>(ValueKey::from(ValueClass::Report(
+                ReportClass::Quarantine { id, expires },
+            )))
+            .await
+            .caused_by(trc::location!())
+            .map(|message| message.map(|message| message.inner))
+    }
+
+    async fn release_quarantined_message(
+        &self,
+        id: u64,
+        expires: u64,
+        recipients: Option<&[String]>,
+    ) -> trc::Result<bool> {
+        let Some(mut quarantined) = self.read_quarantined_message(id, expires).await? else {
+            return Ok(false);
+        };
+        let raw_message = self
+            .blob_store()
+            .get_blob(quarantined.blob_hash.as_slice(), 0..usize::MAX)
+            .await
+            .caused_by(trc::location!())?
+            .ok_or_else(|| {
+                trc::StoreEvent::NotFound
+                    .into_err()
+                    .details("Quarantined message blob not found.")
+                    .ctx(trc::Key::BlobId, quarantined.blob_hash.to_hex())
+            })?;
+
+        // Split recipients into released and held
+        let (released, held): (Vec<_>, Vec<_>) =
+            quarantined.recipients.drain(..).partition(|rcpt| {
+                recipients.is_none_or(|recipients| {
+                    recipients
+                        .iter()
+                        .any(|r| r.eq_ignore_ascii_case(rcpt.as_str()))
+                })
+            });
+        if released.is_empty() {
+            return Ok(false);
+        }
+
+        // Queue message for the released recipients
+        let span_id = self.inner.data.span_id_gen.generate().unwrap_or_else(now);
+        let return_path_lcase = quarantined.return_path.to_lowercase();
+        let return_path_domain = return_path_lcase.domain_part().to_string();
+        let mut message = self.new_message(
+            quarantined.return_path.as_str(),
+            return_path_lcase,
+            return_path_domain,
+            span_id,
+        );
+        for rcpt in &released {
+            message.add_recipient(rcpt.as_str(), self).await;
+        }
+        if !message
+            .queue(
+                None,
+                &raw_message,
+                span_id,
+                self,
+                MessageSource::Unauthenticated,
+            )
+            .await
+        {
+            return Err(trc::StoreEvent::UnexpectedError
+                .into_err()
+                .details("Failed to queue released message.")
+                .caused_by(trc::location!()));
+        }
+
+        trc::event!(
+            Quarantine(trc::QuarantineEvent::Release),
+            SpanId = span_id,
+            Id = id,
+            To = released,
+        );
+
+        // Update or remove the quarantine entry
+        let mut batch = BatchBuilder::new();
+        if held.is_empty() {
+            batch
+                .clear(BlobOp::Reserve {
+                    hash: quarantined.blob_hash.clone(),
+                    until: expires,
+                })
+                .clear(ValueClass::Report(ReportClass::Quarantine { id, expires }));
+        } else {
+            quarantined.recipients = held;
+            batch.set(
+                ValueClass::Report(ReportClass::Quarantine { id, expires }),
+                Bincode::new(quarantined).serialize(),
+            );
+        }
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())
+            .map(|_| true)
+    }
+
+    async fn delete_quarantined_message(&self, id: u64, expires: u64) -> trc::Result<bool> {
+        let Some(quarantined) = self.read_quarantined_message(id, expires).await? else {
+            return Ok(false);
+        };
+
+        let mut batch = BatchBuilder::new();
+        batch
+            .clear(BlobOp::Reserve {
+                hash: quarantined.blob_hash,
+                until: expires,
+            })
+            .clear(ValueClass::Report(ReportClass::Quarantine { id, expires }));
+        self.store()
+            .write(batch.build())
+            .await
+            .caused_by(trc::location!())?;
+
+        trc::event!(Quarantine(trc::QuarantineEvent::Delete), Id = id);
+
+        Ok(true)
+    }
+
+    async fn send_quarantine_digest(&self) {
+        // Make sure only one node sends the digest
+        match self
+            .in_memory_store()
+            .try_lock(KV_LOCK_HOUSEKEEPER, DIGEST_LOCK, 3600)
+            .await
+        {
+            Ok(true) => (),
+            Ok(false) => return,
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to lock quarantine digest.")
+                    .caused_by(trc::location!()));
+                return;
+            }
+        }
+
+        // Obtain messages that have not been notified yet
+        let mut pending = Vec::new();
+        let result = self
+            .store()
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                        id: 0,
+                        expires: now(),
+                    })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                        id: u64::MAX,
+                        expires: u64::MAX,
+                    })),
+                )
+                .ascending(),
+                |key, value| {
+                    let message = Bincode::<QuarantinedMessage>::deserialize(value)
+                        .caused_by(trc::location!())?
+                        .inner;
+                    if !message.notified {
+                        pending.push((
+                            key.deserialize_be_u64(U64_LEN + 1)?,
+                            key.deserialize_be_u64(1)?,
+                            message,
+                        ));
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await;
+        if let Err(err) = result {
+            trc::error!(err
+                .details("Failed to read quarantined messages.")
+                .caused_by(trc::location!()));
+        }
+
+        // Group messages by local recipient
+        let mut is_local: AHashMap<String, bool> = AHashMap::new();
+        let mut digests: AHashMap<String, Vec<&QuarantinedMessage>> = AHashMap::new();
+        for (_, _, message) in &pending {
+            for rcpt in &message.recipients {
+                let rcpt = rcpt.to_lowercase();
+                let local = if let Some(local) = is_local.get(&rcpt) {
+                    *local
+                } else {
+                    let local = matches!(
+                        self.core.storage.directory.rcpt(&rcpt).await,
+                        Ok(RcptType::Mailbox)
+                    );
+                    is_local.insert(rcpt.clone(), local);
+                    local
+                };
+
+                if local {
+                    digests.entry(rcpt).or_default().push(message);
+                }
+            }
+        }
+
+        // Send digests
+        for (rcpt, messages) in digests {
+            let resolver = RecipientDomain::new(rcpt.domain_part());
+            let from_name = self
+                .eval_if(&self.core.smtp.queue.dsn.name, &resolver, 0)
+                .await
+                .unwrap_or_else(|| String::from("Mail Delivery Subsystem"));
+            let from_addr = self
+                .eval_if(&self.core.smtp.queue.dsn.address, &resolver, 0)
+                .await
+                .unwrap_or_else(|| String::from("MAILER-DAEMON@localhost"));
+            let mut txt = format!(
+                "The following {} message(s) addressed to you have been quarantined.\r\n",
+                messages.len()
+            );
+            txt.push_str("Please contact your administrator to have them released.\r\n\r\n");
+            for message in messages {
+                let _ = write!(
+                    &mut txt,
+                    "Date: {}\r\nFrom: {}\r\nSubject: {}\r\nReason: {}\r\n\r\n",
+                    DateTime::from_timestamp(message.created as i64).to_rfc822(),
+                    message.from,
+                    message.subject,
+                    message.reason,
+                );
+            }
+
+            let digest = MessageBuilder::new()
+                .from((from_name.as_str(), from_addr.as_str()))
+                .to(rcpt.as_str())
+                .header("Auto-Submitted", HeaderType::Text("auto-generated".into()))
+                .message_id(format!(
+                    "<{}@{}>",
+                    make_boundary("."),
+                    from_addr.domain_part()
+                ))
+                .subject("Quarantine digest")
+                .text_body(txt)
+                .write_to_vec()
+                .unwrap_or_default();
+
+            trc::event!(Quarantine(trc::QuarantineEvent::Digest), To = rcpt.clone());
+
+            self.send_autogenerated(from_addr.as_str(), [rcpt].into_iter(), digest, None, 0)
+                .await;
+        }
+
+        // Mark messages as notified
+        let mut batch = BatchBuilder::new();
+        for (id, expires, mut message) in pending {
+            message.notified = true;
+            batch.set(
+                ValueClass::Report(ReportClass::Quarantine { id, expires }),
+                Bincode::new(message).serialize(),
+            );
+
+            if batch.ops.len() > 1000 {
+                if let Err(err) = self.store().write(batch.build()).await {
+                    trc::error!(err.caused_by(trc::location!()));
+                }
+                batch = BatchBuilder::new();
+            }
+        }
+        if !batch.ops.is_empty() {
+            if let Err(err) = self.store().write(batch.build()).await {
+                trc::error!(err.caused_by(trc::location!()));
+            }
+        }
+
+        if let Err(err) = self
+            .in_memory_store()
+            .remove_lock(KV_LOCK_HOUSEKEEPER, DIGEST_LOCK)
+            .await
+        {
+            trc::error!(err
+                .details("Failed to remove quarantine digest lock.")
+                .caused_by(trc::location!()));
+        }
+    }
+}
+
+impl QuarantinedMessage {
+    pub fn has_domain(&self, domains: &[String]) -> bool {
+        self.recipients.iter().any(|rcpt| {
+            domains
+                .iter()
+                .any(|d| rcpt.domain_part().eq_ignore_ascii_case(d))
+        })
+    }
+
+    pub fn contains(&self, text: &str) -> bool {
+        self.return_path.to_lowercase().contains(text)
+            || self.from.to_lowercase().contains(text)
+            || self.subject.to_lowercase().contains(text)
+            || self.reason.to_lowercase().contains(text)
+            || self
+                .recipients
+                .iter()
+                .any(|rcpt| rcpt.to_lowercase().contains(text))
+    }
+}
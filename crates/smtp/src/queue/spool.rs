@@ -5,6 +5,9 @@
  */
 
 use crate::queue::DomainPart;
+use ahash::AHashMap;
+use common::config::smtp::queue::DEFAULT_QUEUE_NAME;
+use common::expr::functions::ResolveVariable;
 use common::ipc::QueueEvent;
 use common::{Server, KV_LOCK_QUEUE_MESSAGE};
 use std::borrow::Cow;
@@ -13,7 +16,7 @@ use std::time::{Duration, SystemTime};
 use store::write::key::DeserializeBigEndian;
 use store::write::{now, BatchBuilder, Bincode, BlobOp, QueueClass, ValueClass};
 use store::{IterateParams, Serialize, ValueKey, U64_LEN};
-use trc::ServerEvent;
+use trc::{AddContext, ServerEvent};
 use utils::BlobHash;
 
 use super::{
@@ -33,6 +36,12 @@ pub trait SmtpSpool: Sync + Send {
         span_id: u64,
     ) -> Message;
 
+    fn select_queue<V: ResolveVariable + Sync>(
+        &self,
+        resolver: &V,
+        session_id: u64,
+    ) -> impl Future<Output = Option<String>> + Send;
+
     fn next_event(&self) -> impl Future<Output = Vec<QueuedMessage>> + Send;
 
     fn try_lock_event(&self, queue_id: QueueId) -> impl Future<Output = bool> + Send;
@@ -40,6 +49,10 @@ pub trait SmtpSpool: Sync + Send {
     fn unlock_event(&self, queue_id: QueueId) -> impl Future<Output = ()> + Send;
 
     fn read_message(&self, id: QueueId) -> impl Future<Output = Option<Message>> + Send;
+
+    fn read_message_queues(
+        &self,
+    ) -> impl Future<Output = trc::Result<AHashMap<QueueId, String>>> + Send;
 }
 
 impl SmtpSpool for Server {
@@ -68,9 +81,23 @@ impl SmtpSpool for Server {
             size: 0,
             blob_hash: Default::default(),
             quota_keys: Vec::new(),
+            queue: None,
         }
     }
 
+    async fn select_queue<V: ResolveVariable + Sync>(
+        &self,
+        resolver: &V,
+        session_id: u64,
+    ) -> Option<String> {
+        // Unknown queue names fall back to the default queue
+        self.eval_if::<String, _>(&self.core.smtp.queue.select, resolver, session_id)
+            .await
+            .filter(|queue| {
+                queue != DEFAULT_QUEUE_NAME && self.core.smtp.queue.named.contains_key(queue)
+            })
+    }
+
     async fn next_event(&self) -> Vec<QueuedMessage> {
         let now = now();
         let from_key = ValueKey::from(ValueClass::Queue(QueueClass::MessageEvent(
@@ -91,12 +118,17 @@ impl SmtpSpool for Server {
         let result = self
             .store()
             .iterate(
-                IterateParams::new(from_key, to_key).ascending().no_values(),
-                |key, _| {
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
                     let due = key.deserialize_be_u64(0)?;
                     let queue_id = key.deserialize_be_u64(U64_LEN)?;
+                    let queue = queue_from_event_value(value);
 
-                    events.push(QueuedMessage { due, queue_id });
+                    events.push(QueuedMessage {
+                        due,
+                        queue_id,
+                        queue,
+                    });
 
                     Ok(due <= now)
                 },
@@ -153,7 +185,35 @@ impl SmtpSpool for Server {
             ))))
             .await
         {
-            Ok(Some(message)) => Some(message.inner),
+            Ok(Some(message)) => {
+                let mut message = message.inner;
+
+                // The queue name is stored in the message event
+                if let Some(due) = message.next_event() {
+                    match self
+                        .store()
+                        .get_value::<String>(ValueKey::from(ValueClass::Queue(
+                            QueueClass::MessageEvent(store::write::QueueEvent {
+                                due,
+                                queue_id: id,
+                            }),
+                        )))
+                        .await
+                    {
+                        Ok(queue) => {
+                            message.queue =
+                                queue.and_then(|queue| queue_from_event_value(queue.as_bytes()));
+                        }
+                        Err(err) => {
+                            trc::error!(err
+                                .details("Failed to read message event.")
+                                .caused_by(trc::location!()));
+                        }
+                    }
+                }
+
+                Some(message)
+            }
             Ok(None) => None,
             Err(err) => {
                 trc::error!(err
@@ -164,6 +224,37 @@ impl SmtpSpool for Server {
             }
         }
     }
+
+    async fn read_message_queues(&self) -> trc::Result<AHashMap<QueueId, String>> {
+        let from_key = ValueKey::from(ValueClass::Queue(QueueClass::MessageEvent(
+            store::write::QueueEvent {
+                due: 0,
+                queue_id: 0,
+            },
+        )));
+        let to_key = ValueKey::from(ValueClass::Queue(QueueClass::MessageEvent(
+            store::write::QueueEvent {
+                due: u64::MAX,
+                queue_id: u64::MAX,
+            },
+        )));
+        let mut queues = AHashMap::new();
+
+        self.store()
+            .iterate(
+                IterateParams::new(from_key, to_key).ascending(),
+                |key, value| {
+                    if let Some(queue) = queue_from_event_value(value) {
+                        queues.insert(key.deserialize_be_u64(U64_LEN)?, queue);
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())
+            .map(|_| queues)
+    }
 }
 
 impl Message {
@@ -271,7 +362,7 @@ impl Message {
                     due: self.next_event().unwrap_or_default(),
                     queue_id: self.queue_id,
                 })),
-                0u64.serialize(),
+                self.queue_event_value(),
             )
             .clear(BlobOp::Reserve {
                 hash: self.blob_hash.clone(),
@@ -289,12 +380,18 @@ impl Message {
                     hash: self.blob_hash.clone(),
                 },
                 vec![],
-            )
-            .set(
-                ValueClass::Queue(QueueClass::Message(self.queue_id)),
-                Bincode::new(self).serialize(),
             );
 
+        // Start tracking delivery
+        if let Some(retention) = server.core.smtp.queue.tracking {
+            self.track_queued(retention, message.as_ref(), &mut batch);
+        }
+
+        batch.set(
+            ValueClass::Queue(QueueClass::Message(self.queue_id)),
+            Bincode::new(self).serialize(),
+        );
+
         if let Err(err) = server.store().write(batch.build()).await {
             trc::error!(err
                 .details("Failed to write to store.")
@@ -346,9 +443,16 @@ impl Message {
                     status: Status::Scheduled,
                 });
 
+                // Select the queue when adding the first recipient
+                if self.recipients.is_empty() {
+                    self.queue = server
+                        .select_queue(&QueueEnvelope::new(self, idx), self.span_id)
+                        .await;
+                }
+
                 let expires = server
                     .eval_if(
-                        &server.core.smtp.queue.expire,
+                        server.core.smtp.queue.expire_for(self.queue.as_deref()),
                         &QueueEnvelope::new(self, idx),
                         self.span_id,
                     )
@@ -406,7 +510,7 @@ impl Message {
                         due: next_event,
                         queue_id: self.queue_id,
                     })),
-                    0u64.serialize(),
+                    self.queue_event_value(),
                 );
         }
 
@@ -427,6 +531,14 @@ impl Message {
         }
     }
 
+    pub fn queue_event_value(&self) -> Vec<u8> {
+        // Events for the default queue keep the legacy zero value
+        match &self.queue {
+            Some(queue) => queue.as_bytes().to_vec(),
+            None => 0u64.serialize(),
+        }
+    }
+
     pub async fn remove(self, server: &Server, prev_event: u64) -> bool {
         let mut batch = BatchBuilder::new();
 
@@ -477,3 +589,11 @@ impl Message {
                 .is_some_and(|(_, domain)| domains.contains(&domain.to_string()))
     }
 }
+
+fn queue_from_event_value(value: &[u8]) -> Option<String> {
+    if value.iter().all(|b| *b == 0) {
+        None
+    } else {
+        String::from_utf8(value.to_vec()).ok()
+    }
+}
@@ -0,0 +1,253 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use common::{config::smtp::auth::SrsConfig, Server};
+use ring::hmac;
+use store::write::now;
+use trc::SmtpEvent;
+
+use super::{DomainPart, Message};
+
+const HASH_LEN: usize = 4;
+const TIMESTAMP_PRECISION: u64 = 86400;
+const TIMESTAMP_SLOTS: u64 = 1024;
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SrsError {
+    InvalidSyntax,
+    InvalidHash,
+    Expired,
+}
+
+pub trait SmtpSrs: Sync + Send {
+    fn srs_forward(&self, message: &mut Message) -> impl Future<Output = ()> + Send;
+
+    fn srs_reverse(&self, address: &str) -> Result<Option<String>, SrsError>;
+}
+
+impl SmtpSrs for Server {
+    async fn srs_forward(&self, message: &mut Message) {
+        let config = &self.core.smtp.mail_auth.srs;
+        let srs_domain = config
+            .domain
+            .as_deref()
+            .unwrap_or(self.core.network.report_domain.as_str());
+        if !config.enable
+            || message.return_path.is_empty()
+            || message.return_path_domain == srs_domain
+        {
+            return;
+        }
+
+        // Rewrite only external senders being forwarded to at least one remote domain
+        let directory = &self.core.storage.directory;
+        match directory.is_local_domain(&message.return_path_domain).await {
+            Ok(false) => (),
+            Ok(true) => return,
+            Err(err) => {
+                trc::error!(err
+                    .span_id(message.span_id)
+                    .caused_by(trc::location!())
+                    .details("Failed to lookup local domain"));
+                return;
+            }
+        }
+        let mut has_remote = false;
+        for domain in &message.domains {
+            match directory.is_local_domain(&domain.domain).await {
+                Ok(true) => (),
+                Ok(false) => {
+                    has_remote = true;
+                    break;
+                }
+                Err(err) => {
+                    trc::error!(err
+                        .span_id(message.span_id)
+                        .caused_by(trc::location!())
+                        .details("Failed to lookup local domain"));
+                    return;
+                }
+            }
+        }
+        if !has_remote {
+            return;
+        }
+
+        if let Some(address) = srs_forward(config, srs_domain, &message.return_path, now()) {
+            trc::event!(
+                Smtp(SmtpEvent::SrsRewritten),
+                SpanId = message.span_id,
+                From = message.return_path_lcase.clone(),
+                Details = address.clone(),
+            );
+
+            message.return_path_lcase = address.to_lowercase();
+            message.return_path_domain = message.return_path_lcase.domain_part().to_string();
+            message.return_path = address;
+        }
+    }
+
+    fn srs_reverse(&self, address: &str) -> Result<Option<String>, SrsError> {
+        let config = &self.core.smtp.mail_auth.srs;
+        if config.enable {
+            srs_reverse(
+                config,
+                config
+                    .domain
+                    .as_deref()
+                    .unwrap_or(self.core.network.report_domain.as_str()),
+                address,
+                now(),
+            )
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Rewrites a sender address using SRS0, or SRS1 when the sender
+/// is already an SRS address from another forwarder.
+pub fn srs_forward(
+    config: &SrsConfig,
+    srs_domain: &str,
+    sender: &str,
+    timestamp: u64,
+) -> Option<String> {
+    let secret = config.secrets.first()?;
+    let (local, domain) = sender.rsplit_once('@')?;
+    if local.is_empty() || domain.is_empty() {
+        return None;
+    }
+
+    if strip_tag(local, "SRS0").is_some() {
+        // SRS0=HHHH=TT=domain=local@forwarder -> SRS1=HHHH=forwarder==HHHH=TT=domain=local
+        let opaque = &local[4..];
+        let hash = srs_hash(secret, &[domain, opaque]);
+        format!("SRS1={hash}={domain}={opaque}@{srs_domain}").into()
+    } else if let Some(rest) = strip_tag(local, "SRS1") {
+        // SRS1=HHHH=forwarder==opaque@other -> SRS1=HHHH=forwarder==opaque@srs_domain
+        let (_, rest) = rest.split_once('=')?;
+        let (forwarder, opaque) = rest.split_once('=')?;
+        if forwarder.is_empty() || !opaque.starts_with('=') {
+            return None;
+        }
+        let hash = srs_hash(secret, &[forwarder, opaque]);
+        format!("SRS1={hash}={forwarder}={opaque}@{srs_domain}").into()
+    } else {
+        let timestamp = srs_timestamp(timestamp);
+        let hash = srs_hash(secret, &[&timestamp, domain, local]);
+        format!("SRS0={hash}={timestamp}={domain}={local}@{srs_domain}").into()
+    }
+}
+
+/// Reverses an SRS address issued for `srs_domain`, returning `None`
+/// if the address is not an SRS address.
+pub fn srs_reverse(
+    config: &SrsConfig,
+    srs_domain: &str,
+    address: &str,
+    timestamp: u64,
+) -> Result<Option<String>, SrsError> {
+    let Some((local, _)) = address
+        .rsplit_once('@')
+        .filter(|(_, domain)| domain.eq_ignore_ascii_case(srs_domain))
+    else {
+        return Ok(None);
+    };
+
+    if let Some(rest) = strip_tag(local, "SRS0") {
+        let mut parts = rest.splitn(4, '=');
+        let (Some(hash), Some(stamp), Some(domain), Some(local)) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(SrsError::InvalidSyntax);
+        };
+        if stamp.is_empty() || domain.is_empty() || local.is_empty() {
+            return Err(SrsError::InvalidSyntax);
+        }
+        srs_verify_hash(config, hash, &[stamp, domain, local])?;
+
+        // Validate timestamp
+        let stamp = stamp.as_bytes();
+        let (Some(hi), Some(lo), 2) = (
+            base32_value(stamp[0]),
+            stamp.get(1).and_then(|ch| base32_value(*ch)),
+            stamp.len(),
+        ) else {
+            return Err(SrsError::InvalidSyntax);
+        };
+        let today = (timestamp / TIMESTAMP_PRECISION) % TIMESTAMP_SLOTS;
+        if (today + TIMESTAMP_SLOTS - ((hi << 5) | lo)) % TIMESTAMP_SLOTS > config.max_age {
+            return Err(SrsError::Expired);
+        }
+
+        Ok(Some(format!("{local}@{domain}")))
+    } else if let Some(rest) = strip_tag(local, "SRS1") {
+        let (hash, rest) = rest.split_once('=').ok_or(SrsError::InvalidSyntax)?;
+        let (forwarder, opaque) = rest.split_once('=').ok_or(SrsError::InvalidSyntax)?;
+        if forwarder.is_empty() || opaque.len() < 2 || !opaque.starts_with('=') {
+            return Err(SrsError::InvalidSyntax);
+        }
+        srs_verify_hash(config, hash, &[forwarder, opaque])?;
+
+        Ok(Some(format!("SRS0{opaque}@{forwarder}")))
+    } else {
+        Ok(None)
+    }
+}
+
+fn srs_verify_hash(config: &SrsConfig, hash: &str, data: &[&str]) -> Result<(), SrsError> {
+    if hash.len() == HASH_LEN
+        && config
+            .secrets
+            .iter()
+            .any(|secret| srs_hash(secret, data).eq_ignore_ascii_case(hash))
+    {
+        Ok(())
+    } else {
+        Err(SrsError::InvalidHash)
+    }
+}
+
+fn srs_hash(secret: &str, data: &[&str]) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA1_FOR_LEGACY_USE_ONLY, secret.as_bytes());
+    let mut ctx = hmac::Context::with_key(&key);
+    for item in data {
+        ctx.update(item.to_lowercase().as_bytes());
+    }
+    let mut hash = STANDARD.encode(ctx.sign().as_ref());
+    hash.truncate(HASH_LEN);
+    hash
+}
+
+fn srs_timestamp(timestamp: u64) -> String {
+    let timestamp = (timestamp / TIMESTAMP_PRECISION) % TIMESTAMP_SLOTS;
+    [
+        BASE32_ALPHABET[((timestamp >> 5) & 31) as usize] as char,
+        BASE32_ALPHABET[(timestamp & 31) as usize] as char,
+    ]
+    .into_iter()
+    .collect()
+}
+
+fn base32_value(ch: u8) -> Option<u64> {
+    let ch = ch.to_ascii_uppercase();
+    BASE32_ALPHABET
+        .iter()
+        .position(|&c| c == ch)
+        .map(|pos| pos as u64)
+}
+
+fn strip_tag<'x>(local: &'x str, tag: &str) -> Option<&'x str> {
+    local
+        .get(..tag.len() + 1)
+        .filter(|prefix| prefix[..tag.len()].eq_ignore_ascii_case(tag) && prefix.ends_with('='))
+        .map(|prefix| &local[prefix.len()..])
+}
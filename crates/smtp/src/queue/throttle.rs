@@ -7,13 +7,15 @@
 use std::future::Future;
 
 use common::{
-    config::smtp::QueueRateLimiter, expr::functions::ResolveVariable, Server, KV_RATE_LIMIT_SMTP,
+    config::smtp::{queue::AdaptiveScope, QueueRateLimiter},
+    expr::functions::ResolveVariable,
+    Server, SmtpThrottleState, KV_RATE_LIMIT_SMTP,
 };
 use store::write::now;
 
 use crate::core::throttle::NewKey;
 
-use super::{Domain, Status};
+use super::{Domain, Error, Recipient, Status};
 
 pub trait IsAllowed: Sync + Send {
     fn is_allowed<'x>(
@@ -70,9 +72,171 @@ impl IsAllowed for Server {
     }
 }
 
+pub trait AdaptiveThrottling: Sync + Send {
+    fn throttle_key(&self, domain: &str, mx: &str) -> Option<String>;
+
+    fn throttle_check(&self, key: &str) -> Result<usize, u64>;
+
+    fn throttle_feedback<'x>(
+        &self,
+        key: &str,
+        status: &Status<(), Error>,
+        recipients: impl Iterator<Item = &'x Recipient>,
+        session_id: u64,
+    );
+}
+
+impl AdaptiveThrottling for Server {
+    fn throttle_key(&self, domain: &str, mx: &str) -> Option<String> {
+        let adaptive = &self.core.smtp.queue.adaptive;
+        if adaptive.enable {
+            Some(match adaptive.scope {
+                AdaptiveScope::Domain => domain.to_string(),
+                AdaptiveScope::Mx => mx.to_string(),
+            })
+        } else {
+            None
+        }
+    }
+
+    // Returns the maximum number of concurrent connections allowed (zero
+    // meaning unlimited), or the time at which delivery can be retried.
+    fn throttle_check(&self, key: &str) -> Result<usize, u64> {
+        let mut throttled = self.inner.data.smtp_connections.throttled.lock();
+        let Some(state) = throttled.get_mut(key) else {
+            return Ok(0);
+        };
+
+        let now = now();
+        if state.retry_at > now {
+            return Err(state.retry_at);
+        }
+        if now >= state.window_start + 60 {
+            state.window_start = now;
+            state.window_count = 0;
+        }
+        if state.window_count >= state.rate {
+            return Err(state.window_start + 60);
+        }
+        state.window_count += 1;
+
+        Ok(state.concurrency)
+    }
+
+    fn throttle_feedback<'x>(
+        &self,
+        key: &str,
+        status: &Status<(), Error>,
+        mut recipients: impl Iterator<Item = &'x Recipient>,
+        session_id: u64,
+    ) {
+        let adaptive = &self.core.smtp.queue.adaptive;
+        let mut is_delivered = matches!(status, Status::Completed(_));
+        let mut rate_limited = match status {
+            Status::TemporaryFailure(Error::UnexpectedResponse(response)) => adaptive
+                .is_rate_limited(&response.response)
+                .map(|id| (id, &response.response)),
+            _ => None,
+        };
+        if rate_limited.is_none() {
+            rate_limited = recipients.find_map(|rcpt| match &rcpt.status {
+                Status::TemporaryFailure(response) => adaptive
+                    .is_rate_limited(&response.response)
+                    .map(|id| (id, &response.response)),
+                Status::Completed(_) => {
+                    is_delivered = true;
+                    None
+                }
+                _ => None,
+            });
+        }
+
+        let now = now();
+        let mut throttled = self.inner.data.smtp_connections.throttled.lock();
+        if let Some((id, response)) = rate_limited {
+            let state = throttled
+                .entry(key.to_string())
+                .or_insert_with(|| SmtpThrottleState {
+                    concurrency: adaptive.max_concurrency,
+                    rate: adaptive.max_rate,
+                    ..Default::default()
+                });
+            state.hits += 1;
+            state.last_hit = now;
+            state.last_response = response.to_string();
+
+            // Deliveries that were already in flight do not reduce the limits again
+            if state.retry_at > now {
+                return;
+            }
+            state.concurrency = (state.concurrency / 2).max(adaptive.min_concurrency);
+            state.rate = (state.rate / 2).max(adaptive.min_rate);
+            state.backoff = if state.backoff == 0 {
+                adaptive.min_backoff
+            } else {
+                (state.backoff * 2).min(adaptive.max_backoff)
+            };
+            state.retry_at = now + state.backoff;
+            state.last_change = now;
+
+            trc::event!(
+                Delivery(trc::DeliveryEvent::ThrottleDetected),
+                SpanId = session_id,
+                Hostname = key.to_string(),
+                Id = id,
+                Code = response.code,
+                Details = response.message.clone(),
+                Limit = vec![
+                    trc::Value::from(state.concurrency),
+                    trc::Value::from(state.rate)
+                ],
+                NextRetry = trc::Value::Timestamp(state.retry_at),
+            );
+        } else if is_delivered {
+            // Relax the limits gradually once the remote host stops complaining
+            let Some(state) = throttled.get_mut(key) else {
+                return;
+            };
+            if now < state.last_change + adaptive.recovery {
+                return;
+            }
+            state.concurrency = (state.concurrency + 1).min(adaptive.max_concurrency);
+            state.rate = (state.rate * 2).min(adaptive.max_rate);
+            state.backoff /= 2;
+            if state.backoff < adaptive.min_backoff {
+                state.backoff = 0;
+            }
+            state.last_change = now;
+
+            let limit = vec![
+                trc::Value::from(state.concurrency),
+                trc::Value::from(state.rate),
+            ];
+            if state.concurrency == adaptive.max_concurrency
+                && state.rate == adaptive.max_rate
+                && state.backoff == 0
+            {
+                throttled.remove(key);
+            }
+
+            trc::event!(
+                Delivery(trc::DeliveryEvent::ThrottleRecovered),
+                SpanId = session_id,
+                Hostname = key.to_string(),
+                Limit = limit,
+            );
+        }
+    }
+}
+
 impl Domain {
     pub fn set_rate_limiter_error(&mut self, retry_at: u64) {
         self.retry.due = retry_at;
         self.status = Status::TemporaryFailure(super::Error::RateLimited);
     }
+
+    pub fn set_concurrency_error(&mut self, retry_at: u64) {
+        self.retry.due = retry_at;
+        self.status = Status::TemporaryFailure(super::Error::ConcurrencyLimited);
+    }
 }
@@ -0,0 +1,339 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, net::IpAddr, time::Duration};
+
+use common::Server;
+use mail_parser::MessageParser;
+use rustls::ClientConnection;
+use serde::{Deserialize, Serialize};
+use store::{
+    write::{now, BatchBuilder, Bincode, ReportClass, ValueClass},
+    Serialize as _, ValueKey,
+};
+use trc::AddContext;
+
+use super::{ErrorDetails, HostResponse, Message, QueueId, Recipient, Status};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedMessage {
+    pub queue_id: QueueId,
+    pub created: u64,
+    pub message_id: Option<String>,
+    pub return_path: String,
+    pub size: usize,
+    pub recipients: Vec<TrackedRecipient>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackedRecipient {
+    pub address: String,
+    pub events: Vec<TrackingEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrackingEvent {
+    pub timestamp: u64,
+    pub typ: TrackingEventType,
+    pub mx: Option<String>,
+    pub remote_ip: Option<IpAddr>,
+    pub tls: Option<String>,
+    pub response: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TrackingEventType {
+    Queued,
+    Delivered,
+    Deferred,
+    Failed,
+    DsnSuccess,
+    DsnDelay,
+    DsnFailure,
+}
+
+pub trait SmtpTracking: Sync + Send {
+    fn track_events(
+        &self,
+        message: &Message,
+        events: Vec<(String, TrackingEvent)>,
+    ) -> impl Future<Output = ()> + Send;
+
+    fn read_tracked_message(
+        &self,
+        id: QueueId,
+        expires: u64,
+    ) -> impl Future<Output = trc::Result<Option<TrackedMessage>>> + Send;
+}
+
+impl SmtpTracking for Server {
+    async fn track_events(&self, message: &Message, events: Vec<(String, TrackingEvent)>) {
+        let Some(retention) = self.core.smtp.queue.tracking else {
+            return;
+        };
+        if events.is_empty() {
+            return;
+        }
+
+        let expires = message.tracking_expires(retention);
+        let mut tracked = match self.read_tracked_message(message.queue_id, expires).await {
+            Ok(Some(tracked)) => tracked,
+            Ok(None) => TrackedMessage::new(message, None),
+            Err(err) => {
+                trc::error!(err
+                    .details("Failed to read delivery tracking record.")
+                    .span_id(message.span_id)
+                    .caused_by(trc::location!()));
+                return;
+            }
+        };
+        for (address, event) in events {
+            tracked.add_event(address, event);
+        }
+
+        let mut batch = BatchBuilder::new();
+        batch.set(
+            ValueClass::Report(ReportClass::Tracking {
+                id: message.queue_id,
+                expires,
+            }),
+            Bincode::new(tracked).serialize(),
+        );
+        if let Err(err) = self.store().write(batch.build()).await {
+            trc::error!(err
+                .details("Failed to write delivery tracking record.")
+                .span_id(message.span_id)
+                .caused_by(trc::location!()));
+        }
+    }
+
+    async fn read_tracked_message(
+        &self,
+        id: QueueId,
+        expires: u64,
+    ) -> trc::Result<Option<TrackedMessage>> {
+        self.store()
+            .get_value::<Bincode<TrackedMessage>>(ValueKey::from(ValueClass::Report(
+                ReportClass::Tracking { id, expires },
+            )))
+            .await
+            .caused_by(trc::location!())
+            .map(|tracked| tracked.map(|tracked| tracked.inner))
+    }
+}
+
+impl Message {
+    pub fn tracking_expires(&self, retention: Duration) -> u64 {
+        self.created + retention.as_secs()
+    }
+
+    pub fn track_queued(&self, retention: Duration, raw_message: &[u8], batch: &mut BatchBuilder) {
+        let message_id = MessageParser::new()
+            .parse_headers(raw_message)
+            .and_then(|parsed| parsed.message_id().map(|id| id.to_string()));
+        let mut tracked = TrackedMessage::new(self, message_id);
+        let timestamp = now();
+        for rcpt in &self.recipients {
+            tracked.add_event(
+                rcpt.address_lcase.clone(),
+                TrackingEvent::new(timestamp, TrackingEventType::Queued),
+            );
+        }
+
+        batch.set(
+            ValueClass::Report(ReportClass::Tracking {
+                id: self.queue_id,
+                expires: self.tracking_expires(retention),
+            }),
+            Bincode::new(tracked).serialize(),
+        );
+    }
+}
+
+impl TrackedMessage {
+    fn new(message: &Message, message_id: Option<String>) -> Self {
+        TrackedMessage {
+            queue_id: message.queue_id,
+            created: message.created,
+            message_id,
+            return_path: message.return_path.clone(),
+            size: message.size,
+            recipients: Vec::new(),
+        }
+    }
+
+    fn add_event(&mut self, address: String, event: TrackingEvent) {
+        if let Some(rcpt) = self.recipients.iter_mut().find(|r| r.address == address) {
+            rcpt.events.push(event);
+        } else {
+            self.recipients.push(TrackedRecipient {
+                address,
+                events: vec![event],
+            });
+        }
+    }
+
+    pub fn has_domain(&self, domains: &[String]) -> bool {
+        self.recipients.iter().any(|rcpt| {
+            rcpt.address
+                .rsplit_once('@')
+                .is_some_and(|(_, domain)| domains.iter().any(|d| d.eq_ignore_ascii_case(domain)))
+        }) || self
+            .return_path
+            .rsplit_once('@')
+            .is_some_and(|(_, domain)| domains.iter().any(|d| d.eq_ignore_ascii_case(domain)))
+    }
+
+    pub fn last_event(&self) -> u64 {
+        self.recipients
+            .iter()
+            .flat_map(|rcpt| rcpt.events.iter())
+            .map(|event| event.timestamp)
+            .max()
+            .unwrap_or(self.created)
+    }
+}
+
+impl TrackingEvent {
+    pub fn new(timestamp: u64, typ: TrackingEventType) -> Self {
+        TrackingEvent {
+            timestamp,
+            typ,
+            mx: None,
+            remote_ip: None,
+            tls: None,
+            response: None,
+        }
+    }
+}
+
+// Collects the hosts and TLS results of a delivery attempt, which are
+// then combined with the resulting recipient statuses.
+pub struct DeliveryTracker {
+    status: Vec<Status<HostResponse<String>, HostResponse<ErrorDetails>>>,
+    attempts: Vec<DeliveryAttempt>,
+    enabled: bool,
+}
+
+struct DeliveryAttempt {
+    domain_idx: usize,
+    mx: Option<String>,
+    remote_ip: Option<IpAddr>,
+    tls: Option<String>,
+}
+
+impl DeliveryTracker {
+    pub fn new(server: &Server, recipients: &[Recipient]) -> Self {
+        let enabled = server.core.smtp.queue.tracking.is_some();
+        DeliveryTracker {
+            status: if enabled {
+                recipients.iter().map(|rcpt| rcpt.status.clone()).collect()
+            } else {
+                Vec::new()
+            },
+            attempts: Vec::new(),
+            enabled,
+        }
+    }
+
+    pub fn domain(&mut self, domain_idx: usize) {
+        if self.enabled {
+            self.attempts.push(DeliveryAttempt {
+                domain_idx,
+                mx: None,
+                remote_ip: None,
+                tls: None,
+            });
+        }
+    }
+
+    pub fn host(&mut self, mx: &str, remote_ip: Option<IpAddr>) {
+        if let Some(attempt) = self.attempts.last_mut() {
+            attempt.mx = Some(mx.to_string());
+            attempt.remote_ip = remote_ip;
+            attempt.tls = None;
+        }
+    }
+
+    pub fn tls(&mut self, tls: &ClientConnection) {
+        if let Some(attempt) = self.attempts.last_mut() {
+            let mut result = tls
+                .protocol_version()
+                .map(|version| format!("{version:?}"))
+                .unwrap_or_else(|| "TLS".to_string());
+            if let Some(cipher) = tls.negotiated_cipher_suite() {
+                result.push_str(&format!(" {:?}", cipher.suite()));
+            }
+            attempt.tls = Some(result);
+        }
+    }
+
+    pub fn into_events(self, message: &Message) -> Vec<(String, TrackingEvent)> {
+        let timestamp = now();
+        let mut events = Vec::new();
+
+        for attempt in self.attempts {
+            let domain = &message.domains[attempt.domain_idx];
+            for (rcpt, prev_status) in message.recipients.iter().zip(self.status.iter()) {
+                if rcpt.domain_idx != attempt.domain_idx
+                    || matches!(
+                        prev_status,
+                        Status::Completed(_) | Status::PermanentFailure(_)
+                    )
+                {
+                    continue;
+                }
+
+                // Use the recipient status when the remote host replied to it,
+                // otherwise the whole transaction failed.
+                let (typ, response) = if &rcpt.status != prev_status
+                    || matches!(domain.status, Status::Completed(_))
+                {
+                    match &rcpt.status {
+                        Status::Completed(response) => (
+                            TrackingEventType::Delivered,
+                            response.response.to_string().into(),
+                        ),
+                        Status::TemporaryFailure(response) => (
+                            TrackingEventType::Deferred,
+                            response.response.to_string().into(),
+                        ),
+                        Status::PermanentFailure(response) => (
+                            TrackingEventType::Failed,
+                            response.response.to_string().into(),
+                        ),
+                        Status::Scheduled => (TrackingEventType::Deferred, None),
+                    }
+                } else {
+                    match &domain.status {
+                        Status::Completed(_) => (TrackingEventType::Delivered, None),
+                        Status::TemporaryFailure(err) => {
+                            (TrackingEventType::Deferred, err.to_string().into())
+                        }
+                        Status::PermanentFailure(err) => {
+                            (TrackingEventType::Failed, err.to_string().into())
+                        }
+                        Status::Scheduled => (TrackingEventType::Deferred, None),
+                    }
+                };
+
+                events.push((
+                    rcpt.address_lcase.clone(),
+                    TrackingEvent {
+                        timestamp,
+                        typ,
+                        mx: attempt.mx.clone(),
+                        remote_ip: attempt.remote_ip,
+                        tls: attempt.tls.clone(),
+                        response,
+                    },
+                ));
+            }
+        }
+
+        events
+    }
+}
@@ -0,0 +1,625 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{collections::BTreeMap, future::Future, net::IpAddr};
+
+use ahash::{AHashMap, AHashSet};
+use common::Server;
+use mail_auth::report::{
+    tlsrpt::{ResultType, TlsReport},
+    ActionDisposition, DkimResult, DmarcResult, Record, Report, SpfResult,
+};
+use mail_parser::DateTime;
+use serde::Serialize;
+use store::{
+    write::{key::DeserializeBigEndian, Bincode, ReportClass, ValueClass},
+    Deserialize, IterateParams, ValueKey, U64_LEN,
+};
+use trc::AddContext;
+
+use super::analysis::IncomingReport;
+
+const MAX_ASN_LOOKUPS: usize = 1000;
+const MAX_SAMPLES: usize = 10;
+
+pub struct AnalyticsQuery {
+    pub domain: Option<String>,
+    pub from: u64,
+    pub to: u64,
+    pub interval: u64,
+    pub limit: usize,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmarcAnalytics {
+    pub interval: u64,
+    pub reports: u64,
+    pub series: Vec<DmarcSeries>,
+    pub source_ips: Vec<DmarcSource>,
+    pub asns: Vec<DmarcBreakdown>,
+    pub organizations: Vec<DmarcBreakdown>,
+    pub dkim_selectors: Vec<DkimSelector>,
+    pub spf_alignment: Vec<DmarcBreakdown>,
+    pub unknown_senders: Vec<DmarcSource>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmarcSeries {
+    pub timestamp: String,
+    pub reports: u64,
+    #[serde(flatten)]
+    pub counters: DmarcCounters,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmarcBreakdown {
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub counters: DmarcCounters,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmarcSource {
+    pub ip: IpAddr,
+    pub asn: Option<u32>,
+    pub asn_name: Option<String>,
+    pub country: Option<String>,
+    pub domains: Vec<String>,
+    pub organizations: Vec<String>,
+    #[serde(flatten)]
+    pub counters: DmarcCounters,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DkimSelector {
+    pub domain: String,
+    pub selector: String,
+    #[serde(flatten)]
+    pub rate: Rate,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DmarcCounters {
+    #[serde(flatten)]
+    pub rate: Rate,
+    pub dkim_aligned: u64,
+    pub spf_aligned: u64,
+    pub quarantined: u64,
+    pub rejected: u64,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Rate {
+    pub total: u64,
+    pub pass: u64,
+    pub fail: u64,
+    pub pass_rate: f64,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsAnalytics {
+    pub interval: u64,
+    pub reports: u64,
+    pub series: Vec<TlsSeries>,
+    pub domains: Vec<TlsBreakdown>,
+    pub organizations: Vec<TlsBreakdown>,
+    pub failures: Vec<TlsFailure>,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsSeries {
+    pub timestamp: String,
+    pub reports: u64,
+    #[serde(flatten)]
+    pub counters: TlsCounters,
+}
+
+#[derive(Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsBreakdown {
+    pub key: String,
+    #[serde(flatten)]
+    pub counters: TlsCounters,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsFailure {
+    pub result_type: ResultType,
+    pub sessions: u64,
+    pub receiving_mx: Vec<String>,
+    pub sending_ips: Vec<IpAddr>,
+}
+
+#[derive(Debug, Default, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TlsCounters {
+    pub success: u64,
+    pub failure: u64,
+    pub success_rate: f64,
+}
+
+pub trait ReportAnalytics: Sync + Send {
+    fn dmarc_analytics(
+        &self,
+        query: &AnalyticsQuery,
+        tenant_domains: &Option<Vec<String>>,
+    ) -> impl Future<Output = trc::Result<DmarcAnalytics>> + Send;
+
+    fn tls_analytics(
+        &self,
+        query: &AnalyticsQuery,
+        tenant_domains: &Option<Vec<String>>,
+    ) -> impl Future<Output = trc::Result<TlsAnalytics>> + Send;
+}
+
+#[derive(Default)]
+struct SourceStats {
+    counters: DmarcCounters,
+    domains: AHashSet<String>,
+    organizations: AHashSet<String>,
+}
+
+impl ReportAnalytics for Server {
+    async fn dmarc_analytics(
+        &self,
+        query: &AnalyticsQuery,
+        tenant_domains: &Option<Vec<String>>,
+    ) -> trc::Result<DmarcAnalytics> {
+        let mut reports = 0;
+        let mut series: BTreeMap<u64, (u64, DmarcCounters)> = BTreeMap::new();
+        let mut sources: AHashMap<IpAddr, SourceStats> = AHashMap::new();
+        let mut organizations: AHashMap<String, DmarcCounters> = AHashMap::new();
+        let mut selectors: AHashMap<(String, String), Rate> = AHashMap::new();
+        let mut spf_alignment: AHashMap<&'static str, DmarcCounters> = AHashMap::new();
+        let mut last_id = 0;
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Dmarc { id: 0, expires: 0 })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Dmarc {
+                        id: u64::MAX,
+                        expires: u64::MAX,
+                    })),
+                ),
+                |key, value| {
+                    // Skip chunked records
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    if id == last_id {
+                        return Ok(true);
+                    }
+                    last_id = id;
+
+                    let report = Bincode::<IncomingReport<Report>>::deserialize(value)
+                        .caused_by(trc::location!())?
+                        .inner;
+                    let begin = report.report.date_range_begin();
+                    if !query.matches(
+                        report.report.domain(),
+                        begin,
+                        report.report.date_range_end(),
+                    ) || tenant_domains
+                        .as_ref()
+                        .is_some_and(|domains| !report.has_domain(domains))
+                    {
+                        return Ok(true);
+                    }
+
+                    reports += 1;
+                    let bucket = series.entry(query.bucket(begin)).or_default();
+                    bucket.0 += 1;
+                    let org_name = report.report.org_name().to_string();
+
+                    for record in report.report.records() {
+                        bucket.1.add(record);
+                        organizations
+                            .entry(org_name.clone())
+                            .or_default()
+                            .add(record);
+                        spf_alignment
+                            .entry(record.spf_alignment())
+                            .or_default()
+                            .add(record);
+
+                        if let Some(ip) = record.source_ip() {
+                            let source = sources.entry(ip).or_default();
+                            source.counters.add(record);
+                            if !record.header_from().is_empty() {
+                                source.domains.insert(record.header_from().to_lowercase());
+                            }
+                            source.organizations.insert(org_name.clone());
+                        }
+
+                        for dkim in record.dkim_auth_result() {
+                            selectors
+                                .entry((dkim.domain().to_lowercase(), dkim.selector().to_string()))
+                                .or_default()
+                                .add(record.count() as u64, dkim.result() == DkimResult::Pass);
+                        }
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        // Resolve the ASN of the busiest sources
+        let mut sources = sources.into_iter().collect::<Vec<_>>();
+        sources.sort_unstable_by(|a, b| b.1.counters.rate.total.cmp(&a.1.counters.rate.total));
+        let mut asns: AHashMap<u32, (Option<String>, DmarcCounters)> = AHashMap::new();
+        let mut source_ips = Vec::with_capacity(sources.len());
+        for (idx, (ip, stats)) in sources.into_iter().enumerate() {
+            let mut source = DmarcSource {
+                ip,
+                asn: None,
+                asn_name: None,
+                country: None,
+                domains: stats.domains.into_iter().collect(),
+                organizations: stats.organizations.into_iter().collect(),
+                counters: stats.counters,
+            };
+            if idx < MAX_ASN_LOOKUPS {
+                let result = self.lookup_asn_country(ip).await;
+                if let Some(asn) = result.asn {
+                    let entry = asns
+                        .entry(asn.id)
+                        .or_insert_with(|| (asn.name.clone(), DmarcCounters::default()));
+                    entry.1.merge(&source.counters);
+                    source.asn = Some(asn.id);
+                    source.asn_name = asn.name.clone();
+                }
+                source.country = result.country.map(|country| country.to_string());
+            }
+            source.domains.sort_unstable();
+            source.organizations.sort_unstable();
+            source.counters.finalize();
+            source_ips.push(source);
+        }
+
+        // Sources without any DMARC pass are not authorized to send for the domain
+        let unknown_senders = source_ips
+            .iter()
+            .filter(|source| source.counters.rate.pass == 0)
+            .take(query.limit)
+            .cloned()
+            .collect();
+        source_ips.truncate(query.limit);
+
+        Ok(DmarcAnalytics {
+            interval: query.interval,
+            reports,
+            series: series
+                .into_iter()
+                .map(|(timestamp, (reports, mut counters))| {
+                    counters.finalize();
+                    DmarcSeries {
+                        timestamp: DateTime::from_timestamp(timestamp as i64).to_rfc3339(),
+                        reports,
+                        counters,
+                    }
+                })
+                .collect(),
+            source_ips,
+            asns: into_breakdown(
+                asns.into_iter()
+                    .map(|(asn, (name, counters))| (format!("AS{asn}"), name, counters)),
+                query.limit,
+            ),
+            organizations: into_breakdown(
+                organizations
+                    .into_iter()
+                    .map(|(org, counters)| (org, None, counters)),
+                query.limit,
+            ),
+            dkim_selectors: {
+                let mut selectors = selectors
+                    .into_iter()
+                    .map(|((domain, selector), mut rate)| {
+                        rate.finalize();
+                        DkimSelector {
+                            domain,
+                            selector,
+                            rate,
+                        }
+                    })
+                    .collect::<Vec<_>>();
+                selectors.sort_unstable_by(|a, b| b.rate.total.cmp(&a.rate.total));
+                selectors.truncate(query.limit);
+                selectors
+            },
+            spf_alignment: into_breakdown(
+                spf_alignment
+                    .into_iter()
+                    .map(|(key, counters)| (key.to_string(), None, counters)),
+                query.limit,
+            ),
+            unknown_senders,
+        })
+    }
+
+    async fn tls_analytics(
+        &self,
+        query: &AnalyticsQuery,
+        tenant_domains: &Option<Vec<String>>,
+    ) -> trc::Result<TlsAnalytics> {
+        let mut reports = 0;
+        let mut series: BTreeMap<u64, (u64, TlsCounters)> = BTreeMap::new();
+        let mut domains: AHashMap<String, TlsCounters> = AHashMap::new();
+        let mut organizations: AHashMap<String, TlsCounters> = AHashMap::new();
+        let mut failures: AHashMap<ResultType, TlsFailure> = AHashMap::new();
+        let mut last_id = 0;
+
+        self.core
+            .storage
+            .data
+            .iterate(
+                IterateParams::new(
+                    ValueKey::from(ValueClass::Report(ReportClass::Tls { id: 0, expires: 0 })),
+                    ValueKey::from(ValueClass::Report(ReportClass::Tls {
+                        id: u64::MAX,
+                        expires: u64::MAX,
+                    })),
+                ),
+                |key, value| {
+                    // Skip chunked records
+                    let id = key.deserialize_be_u64(U64_LEN + 1)?;
+                    if id == last_id {
+                        return Ok(true);
+                    }
+                    last_id = id;
+
+                    let report = Bincode::<IncomingReport<TlsReport>>::deserialize(value)
+                        .caused_by(trc::location!())?
+                        .inner;
+                    if tenant_domains
+                        .as_ref()
+                        .is_some_and(|domains| !report.has_domain(domains))
+                    {
+                        return Ok(true);
+                    }
+                    let begin = report.report.date_range.start_datetime.to_timestamp() as u64;
+                    let end = report.report.date_range.end_datetime.to_timestamp() as u64;
+                    let org_name = report
+                        .report
+                        .organization_name
+                        .as_deref()
+                        .unwrap_or_default()
+                        .to_string();
+
+                    let mut is_match = false;
+                    for policy in &report.report.policies {
+                        if !query.matches(&policy.policy.policy_domain, begin, end) {
+                            continue;
+                        }
+                        is_match = true;
+
+                        let success = policy.summary.total_success as u64;
+                        let failure = policy.summary.total_failure as u64;
+                        let bucket = series.entry(query.bucket(begin)).or_default();
+                        bucket.1.add(success, failure);
+                        domains
+                            .entry(policy.policy.policy_domain.to_lowercase())
+                            .or_default()
+                            .add(success, failure);
+                        organizations
+                            .entry(org_name.clone())
+                            .or_default()
+                            .add(success, failure);
+
+                        for details in &policy.failure_details {
+                            let entry =
+                                failures
+                                    .entry(details.result_type)
+                                    .or_insert_with(|| TlsFailure {
+                                        result_type: details.result_type,
+                                        sessions: 0,
+                                        receiving_mx: Vec::new(),
+                                        sending_ips: Vec::new(),
+                                    });
+                            entry.sessions += details.failed_session_count as u64;
+                            if let Some(mx) = &details.receiving_mx_hostname {
+                                if entry.receiving_mx.len() < MAX_SAMPLES
+                                    && !entry.receiving_mx.contains(mx)
+                                {
+                                    entry.receiving_mx.push(mx.clone());
+                                }
+                            }
+                            if let Some(ip) = details.sending_mta_ip {
+                                if entry.sending_ips.len() < MAX_SAMPLES
+                                    && !entry.sending_ips.contains(&ip)
+                                {
+                                    entry.sending_ips.push(ip);
+                                }
+                            }
+                        }
+                    }
+                    if is_match {
+                        reports += 1;
+                        series.entry(query.bucket(begin)).or_default().0 += 1;
+                    }
+
+                    Ok(true)
+                },
+            )
+            .await
+            .caused_by(trc::location!())?;
+
+        let mut failures = failures.into_values().collect::<Vec<_>>();
+        failures.sort_unstable_by(|a, b| b.sessions.cmp(&a.sessions));
+
+        Ok(TlsAnalytics {
+            interval: query.interval,
+            reports,
+            series: series
+                .into_iter()
+                .map(|(timestamp, (reports, mut counters))| {
+                    counters.finalize();
+                    TlsSeries {
+                        timestamp: DateTime::from_timestamp(timestamp as i64).to_rfc3339(),
+                        reports,
+                        counters,
+                    }
+                })
+                .collect(),
+            domains: into_tls_breakdown(domains, query.limit),
+            organizations: into_tls_breakdown(organizations, query.limit),
+            failures,
+        })
+    }
+}
+
+impl AnalyticsQuery {
+    fn matches(&self, domain: &str, begin: u64, end: u64) -> bool {
+        begin < self.to
+            && end.max(begin) >= self.from
+            && self
+                .domain
+                .as_ref()
+                .is_none_or(|d| d.eq_ignore_ascii_case(domain))
+    }
+
+    fn bucket(&self, timestamp: u64) -> u64 {
+        timestamp - (timestamp % self.interval.max(1))
+    }
+}
+
+impl DmarcCounters {
+    fn add(&mut self, record: &Record) {
+        let count = record.count() as u64;
+        let dkim_aligned = record.dmarc_dkim_result() == DmarcResult::Pass;
+        let spf_aligned = record.dmarc_spf_result() == DmarcResult::Pass;
+
+        self.rate.add(count, dkim_aligned || spf_aligned);
+        if dkim_aligned {
+            self.dkim_aligned += count;
+        }
+        if spf_aligned {
+            self.spf_aligned += count;
+        }
+        match record.action_disposition() {
+            ActionDisposition::Quarantine => self.quarantined += count,
+            ActionDisposition::Reject => self.rejected += count,
+            _ => {}
+        }
+    }
+
+    fn merge(&mut self, other: &DmarcCounters) {
+        self.rate.total += other.rate.total;
+        self.rate.pass += other.rate.pass;
+        self.rate.fail += other.rate.fail;
+        self.dkim_aligned += other.dkim_aligned;
+        self.spf_aligned += other.spf_aligned;
+        self.quarantined += other.quarantined;
+        self.rejected += other.rejected;
+    }
+
+    fn finalize(&mut self) {
+        self.rate.finalize();
+    }
+}
+
+impl Rate {
+    fn add(&mut self, count: u64, pass: bool) {
+        self.total += count;
+        if pass {
+            self.pass += count;
+        } else {
+            self.fail += count;
+        }
+    }
+
+    fn finalize(&mut self) {
+        self.pass_rate = ratio(self.pass, self.total);
+    }
+}
+
+impl TlsCounters {
+    fn add(&mut self, success: u64, failure: u64) {
+        self.success += success;
+        self.failure += failure;
+    }
+
+    fn finalize(&mut self) {
+        self.success_rate = ratio(self.success, self.success + self.failure);
+    }
+}
+
+trait SpfAlignment {
+    fn spf_alignment(&self) -> &'static str;
+}
+
+impl SpfAlignment for Record {
+    fn spf_alignment(&self) -> &'static str {
+        if self.dmarc_spf_result() == DmarcResult::Pass {
+            "aligned"
+        } else if self
+            .spf_auth_result()
+            .iter()
+            .any(|spf| spf.result() == SpfResult::Pass)
+        {
+            "unaligned"
+        } else {
+            "fail"
+        }
+    }
+}
+
+fn into_breakdown(
+    items: impl Iterator<Item = (String, Option<String>, DmarcCounters)>,
+    limit: usize,
+) -> Vec<DmarcBreakdown> {
+    let mut items = items
+        .map(|(key, name, mut counters)| {
+            counters.finalize();
+            DmarcBreakdown {
+                key,
+                name,
+                counters,
+            }
+        })
+        .collect::<Vec<_>>();
+    items.sort_unstable_by(|a, b| b.counters.rate.total.cmp(&a.counters.rate.total));
+    items.truncate(limit);
+    items
+}
+
+fn into_tls_breakdown(items: AHashMap<String, TlsCounters>, limit: usize) -> Vec<TlsBreakdown> {
+    let mut items = items
+        .into_iter()
+        .map(|(key, mut counters)| {
+            counters.finalize();
+            TlsBreakdown { key, counters }
+        })
+        .collect::<Vec<_>>();
+    items.sort_unstable_by(|a, b| {
+        (b.counters.success + b.counters.failure).cmp(&(a.counters.success + a.counters.failure))
+    });
+    items.truncate(limit);
+    items
+}
+
+fn ratio(value: u64, total: u64) -> f64 {
+    if total > 0 {
+        value as f64 / total as f64
+    } else {
+        0.0
+    }
+}
@@ -28,6 +28,7 @@ use crate::{
 };
 
 pub mod analysis;
+pub mod analytics;
 pub mod dkim;
 pub mod dmarc;
 pub mod scheduler;
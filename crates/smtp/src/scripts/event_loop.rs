@@ -21,7 +21,7 @@ use trc::SieveEvent;
 
 use crate::{
     inbound::DkimSign,
-    queue::{quota::HasQueueQuota, spool::SmtpSpool, DomainPart, MessageSource},
+    queue::{quota::HasQueueQuota, spool::SmtpSpool, srs::SmtpSrs, DomainPart, MessageSource},
 };
 
 use super::{ScriptModification, ScriptParameters, ScriptResult};
@@ -271,8 +271,13 @@ impl RunScript for Server {
                             Ret::Default => (),
                         }
 
-                        // Queue message
+                        // Rewrite the sender of redirected messages
                         let is_forward = message_id == 0;
+                        if is_forward {
+                            self.srs_forward(&mut message).await;
+                        }
+
+                        // Queue message
                         let raw_message = if !is_forward {
                             messages.get(message_id - 1).map(|m| m.as_slice())
                         } else {
@@ -0,0 +1,87 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Instant};
+
+use common::{config::spamfilter::AntivirusScope, Server};
+use mail_parser::Message;
+
+use crate::{modules::antivirus::antivirus_scan, AntivirusVerdict, SpamFilterContext};
+
+pub trait SpamFilterAnalyzeAntivirus: Sync + Send {
+    fn antivirus_scan_message(
+        &self,
+        message: &Message<'_>,
+        span_id: u64,
+    ) -> impl Future<Output = Option<AntivirusVerdict>> + Send;
+
+    fn spam_filter_analyze_antivirus(
+        &self,
+        ctx: &mut SpamFilterContext<'_>,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl SpamFilterAnalyzeAntivirus for Server {
+    async fn antivirus_scan_message(
+        &self,
+        message: &Message<'_>,
+        span_id: u64,
+    ) -> Option<AntivirusVerdict> {
+        let config = self.core.spam.antivirus.as_ref()?;
+        let contents = match config.scan {
+            AntivirusScope::Message => vec![message.raw_message()],
+            AntivirusScope::Attachments => message
+                .attachments
+                .iter()
+                .filter_map(|part_id| message.parts.get(*part_id as usize))
+                .map(|part| part.contents())
+                .collect(),
+        };
+
+        let mut result = AntivirusVerdict::Clean;
+        for contents in contents {
+            if contents.is_empty() || contents.len() > config.max_size {
+                continue;
+            }
+
+            let time = Instant::now();
+            match antivirus_scan(self, span_id, config, contents).await {
+                Ok(verdict) => {
+                    trc::event!(
+                        Spam(trc::SpamEvent::Antivirus),
+                        Result = verdict.is_some(),
+                        Details = verdict.clone(),
+                        Size = contents.len(),
+                        SpanId = span_id,
+                        Elapsed = time.elapsed()
+                    );
+
+                    if let Some(verdict) = verdict {
+                        return Some(AntivirusVerdict::Infected(verdict));
+                    }
+                }
+                Err(err) => {
+                    result = AntivirusVerdict::Failed;
+                    trc::error!(err.span_id(span_id).ctx(trc::Key::Elapsed, time.elapsed()));
+                }
+            }
+        }
+
+        Some(result)
+    }
+
+    async fn spam_filter_analyze_antivirus(&self, ctx: &mut SpamFilterContext<'_>) {
+        match ctx.input.antivirus {
+            Some(AntivirusVerdict::Infected(_)) => {
+                ctx.result.add_tag("VIRUS_FOUND");
+            }
+            Some(AntivirusVerdict::Failed) => {
+                ctx.result.add_tag("VIRUS_SCAN_FAIL");
+            }
+            Some(AntivirusVerdict::Clean) | None => (),
+        }
+    }
+}
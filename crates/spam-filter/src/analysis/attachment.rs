@@ -0,0 +1,95 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::Server;
+
+use crate::{
+    SpamFilterContext,
+    modules::inspect::{ContentKind, inspect_contents},
+};
+
+pub trait SpamFilterAnalyzeAttachment: Sync + Send {
+    fn spam_filter_analyze_attachment(
+        &self,
+        ctx: &mut SpamFilterContext<'_>,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl SpamFilterAnalyzeAttachment for Server {
+    async fn spam_filter_analyze_attachment(&self, ctx: &mut SpamFilterContext<'_>) {
+        let Some(config) = &self.core.spam.inspect else {
+            return;
+        };
+
+        let message = ctx.input.message;
+        for part in message
+            .attachments
+            .iter()
+            .filter_map(|part_id| message.parts.get(*part_id as usize))
+        {
+            let contents = part.contents();
+            if contents.is_empty() || contents.len() > config.max_size {
+                continue;
+            }
+            let Some(inspection) = inspect_contents(contents, config.max_members) else {
+                continue;
+            };
+
+            for member in &inspection.members {
+                let member = member.trim().to_lowercase();
+                let Some(ext) = member
+                    .rsplit_once('.')
+                    .and_then(|(_, ext)| self.core.spam.lists.file_extensions.get(ext))
+                else {
+                    continue;
+                };
+
+                if ext.is_bad {
+                    // Archive contains a file with a bad extension
+                    ctx.result.add_tag("ARCHIVE_BAD_EXTENSION");
+                }
+                if ext.is_archive {
+                    // Archive in archive
+                    ctx.result.add_tag("ARCHIVE_IN_ARCHIVE");
+                }
+            }
+
+            if inspection.encrypted {
+                match inspection.kind {
+                    ContentKind::Zip | ContentKind::SevenZip | ContentKind::Rar => {
+                        ctx.result.add_tag("ARCHIVE_ENCRYPTED");
+                    }
+                    ContentKind::Ooxml | ContentKind::Ole2 => {
+                        ctx.result.add_tag("OFFICE_ENCRYPTED");
+                    }
+                    ContentKind::Pdf => {
+                        ctx.result.add_tag("PDF_ENCRYPTED");
+                    }
+                }
+            }
+            if inspection.vba_macros {
+                ctx.result.add_tag("OFFICE_MACRO");
+            }
+            if inspection.ole_objects {
+                ctx.result.add_tag("OFFICE_OLE_OBJECT");
+            }
+            if inspection.pdf_javascript {
+                ctx.result.add_tag("PDF_JAVASCRIPT");
+            }
+            if inspection.pdf_launch {
+                ctx.result.add_tag("PDF_LAUNCH");
+            }
+            if inspection.pdf_auto_action {
+                ctx.result.add_tag("PDF_AUTO_ACTION");
+            }
+            if inspection.pdf_embedded_file {
+                ctx.result.add_tag("PDF_EMBEDDED_FILE");
+            }
+        }
+    }
+}
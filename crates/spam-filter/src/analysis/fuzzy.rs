@@ -0,0 +1,62 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{future::Future, time::Instant};
+
+use common::Server;
+
+use crate::{SpamFilterContext, modules::fuzzy::FuzzyDatabase};
+
+pub trait SpamFilterAnalyzeFuzzy: Sync + Send {
+    fn spam_filter_analyze_fuzzy(
+        &self,
+        ctx: &mut SpamFilterContext<'_>,
+    ) -> impl Future<Output = ()> + Send;
+}
+
+impl SpamFilterAnalyzeFuzzy for Server {
+    async fn spam_filter_analyze_fuzzy(&self, ctx: &mut SpamFilterContext<'_>) {
+        if let Some(config) = &self.core.spam.fuzzy {
+            let time = Instant::now();
+            match self.fuzzy_check(ctx).await {
+                Ok(matches) => {
+                    let is_bulk = matches.iter().any(|m| m.count >= config.bulk_count);
+                    let is_spam = matches.iter().any(|m| {
+                        m.spam as u64 >= config.spam_count
+                            && (m.spam as f64 / (m.spam + m.ham) as f64) >= config.spam_ratio
+                    });
+                    if is_bulk {
+                        ctx.result.add_tag("FUZZY_BULK");
+                    }
+                    if is_spam {
+                        ctx.result.add_tag("FUZZY_SPAM");
+                    }
+
+                    trc::event!(
+                        Spam(trc::SpamEvent::Fuzzy),
+                        Result = is_spam,
+                        Details = matches
+                            .iter()
+                            .map(|m| trc::Value::Array(vec![
+                                trc::Value::from(m.count),
+                                trc::Value::from(m.spam),
+                                trc::Value::from(m.ham)
+                            ]))
+                            .collect::<Vec<_>>(),
+                        SpanId = ctx.input.span_id,
+                        Elapsed = time.elapsed()
+                    );
+                }
+                Err(err) => {
+                    trc::error!(
+                        err.span_id(ctx.input.span_id)
+                            .ctx(trc::Key::Elapsed, time.elapsed())
+                    );
+                }
+            }
+        }
+    }
+}
@@ -16,12 +16,15 @@ use crate::{
     Recipient, SpamFilterContext, SpamFilterInput, SpamFilterOutput, SpamFilterResult, TextPart,
 };
 
+pub mod antivirus;
+pub mod attachment;
 pub mod bayes;
 pub mod date;
 pub mod dmarc;
 pub mod domain;
 pub mod ehlo;
 pub mod from;
+pub mod fuzzy;
 pub mod headers;
 pub mod html;
 pub mod init;
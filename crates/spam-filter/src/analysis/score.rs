@@ -9,17 +9,18 @@ use std::{fmt::Write, future::Future, vec};
 
 use crate::{
     analysis::{
+        antivirus::SpamFilterAnalyzeAntivirus, attachment::SpamFilterAnalyzeAttachment,
         bayes::SpamFilterAnalyzeBayes, date::SpamFilterAnalyzeDate, dmarc::SpamFilterAnalyzeDmarc,
         domain::SpamFilterAnalyzeDomain, ehlo::SpamFilterAnalyzeEhlo, from::SpamFilterAnalyzeFrom,
-        headers::SpamFilterAnalyzeHeaders, html::SpamFilterAnalyzeHtml, ip::SpamFilterAnalyzeIp,
-        messageid::SpamFilterAnalyzeMid, mime::SpamFilterAnalyzeMime,
-        pyzor::SpamFilterAnalyzePyzor, received::SpamFilterAnalyzeReceived,
-        recipient::SpamFilterAnalyzeRecipient, replyto::SpamFilterAnalyzeReplyTo,
-        reputation::SpamFilterAnalyzeReputation, rules::SpamFilterAnalyzeRules,
-        subject::SpamFilterAnalyzeSubject, trusted_reply::SpamFilterAnalyzeTrustedReply,
-        url::SpamFilterAnalyzeUrl,
+        fuzzy::SpamFilterAnalyzeFuzzy, headers::SpamFilterAnalyzeHeaders,
+        html::SpamFilterAnalyzeHtml, ip::SpamFilterAnalyzeIp, messageid::SpamFilterAnalyzeMid,
+        mime::SpamFilterAnalyzeMime, pyzor::SpamFilterAnalyzePyzor,
+        received::SpamFilterAnalyzeReceived, recipient::SpamFilterAnalyzeRecipient,
+        replyto::SpamFilterAnalyzeReplyTo, reputation::SpamFilterAnalyzeReputation,
+        rules::SpamFilterAnalyzeRules, subject::SpamFilterAnalyzeSubject,
+        trusted_reply::SpamFilterAnalyzeTrustedReply, url::SpamFilterAnalyzeUrl,
     },
-    modules::bayes::BayesClassifier,
+    modules::{bayes::BayesClassifier, fuzzy::FuzzyDatabase},
     SpamFilterContext,
 };
 
@@ -57,6 +58,9 @@ impl SpamFilterAnalyzeScore for Server {
                 Some(SpamFilterAction::Reject) => {
                     return SpamFilterAction::Reject;
                 }
+                Some(SpamFilterAction::Quarantine) => {
+                    return SpamFilterAction::Quarantine;
+                }
                 None => 0.0,
             };
             ctx.result.score += score;
@@ -114,6 +118,13 @@ impl SpamFilterAnalyzeScore for Server {
             }
         }
 
+        // Train fuzzy hash database with spam trap hits
+        if self.core.spam.fuzzy.is_some() && ctx.result.has_tag("SPAM_TRAP") && !ctx.input.is_test {
+            if let Err(err) = self.fuzzy_train(ctx, true).await {
+                trc::error!(err.span_id(ctx.input.span_id).caused_by(trc::location!()));
+            }
+        }
+
         if self.core.spam.scores.reject_threshold > 0.0
             && ctx.result.score >= self.core.spam.scores.reject_threshold
         {
@@ -122,6 +133,10 @@ impl SpamFilterAnalyzeScore for Server {
             && ctx.result.score >= self.core.spam.scores.discard_threshold
         {
             SpamFilterAction::Discard
+        } else if self.core.spam.scores.quarantine_threshold > 0.0
+            && ctx.result.score >= self.core.spam.scores.quarantine_threshold
+        {
+            SpamFilterAction::Quarantine
         } else {
             let mut header = std::mem::take(&mut ctx.result.header).unwrap_or_default();
             if let Some(header_name) = &self.core.spam.headers.status {
@@ -145,6 +160,9 @@ impl SpamFilterAnalyzeScore for Server {
         &self,
         ctx: &mut SpamFilterContext<'_>,
     ) -> SpamFilterAction<String> {
+        // Antivirus verdict analysis
+        self.spam_filter_analyze_antivirus(ctx).await;
+
         // IP address analysis
         self.spam_filter_analyze_ip(ctx).await;
 
@@ -187,6 +205,9 @@ impl SpamFilterAnalyzeScore for Server {
         // MIME part analysis
         self.spam_filter_analyze_mime(ctx).await;
 
+        // Attachment content inspection
+        self.spam_filter_analyze_attachment(ctx).await;
+
         // HTML content analysis
         self.spam_filter_analyze_html(ctx).await;
 
@@ -203,6 +224,9 @@ impl SpamFilterAnalyzeScore for Server {
         // Pyzor checks
         self.spam_filter_analyze_pyzor(ctx).await;
 
+        // Fuzzy hash checks
+        self.spam_filter_analyze_fuzzy(ctx).await;
+
         // Bayes classification
         self.spam_filter_analyze_bayes_classify(ctx).await;
 
@@ -214,6 +238,7 @@ impl SpamFilterAnalyzeScore for Server {
             SpamFilterAction::Allow(_) => (),
             SpamFilterAction::Discard => return SpamFilterAction::Discard,
             SpamFilterAction::Reject => return SpamFilterAction::Reject,
+            SpamFilterAction::Quarantine => return SpamFilterAction::Quarantine,
         }
 
         // Reputation tracking and adjust score
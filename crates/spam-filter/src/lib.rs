@@ -50,6 +50,9 @@ pub struct SpamFilterInput<'x> {
 
     pub account_id: Option<u32>,
     pub is_test: bool,
+
+    // Verdict of the antivirus scanning stage
+    pub antivirus: Option<&'x AntivirusVerdict>,
 }
 
 pub struct SpamFilterOutput<'x> {
@@ -109,6 +112,13 @@ pub struct SpamFilterResult {
     pub header: Option<String>,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AntivirusVerdict {
+    Clean,
+    Infected(String),
+    Failed,
+}
+
 pub struct SpamFilterContext<'x> {
     pub input: SpamFilterInput<'x>,
     pub output: SpamFilterOutput<'x>,
@@ -158,6 +168,7 @@ impl<'x> SpamFilterInput<'x> {
             env_rcpt_to: vec![],
             account_id: None,
             is_test: false,
+            antivirus: None,
         }
     }
 
@@ -183,6 +194,7 @@ impl<'x> SpamFilterInput<'x> {
             env_rcpt_to: vec![],
             account_id: Some(account_id),
             is_test: false,
+            antivirus: None,
         }
     }
 }
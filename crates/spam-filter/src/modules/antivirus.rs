@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::{
+    config::spamfilter::{AntivirusConfig, ClamdAddress},
+    Server, KV_ANTIVIRUS,
+};
+use sha2::{Digest, Sha256};
+use store::dispatch::lookup::KeyValue;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpStream,
+};
+
+use super::{key_get, key_set};
+
+const CHUNK_SIZE: usize = 64 * 1024;
+const MAX_RESPONSE_SIZE: usize = 1024;
+
+// Scans the contents and returns the name of the detected malware, if any.
+pub(crate) async fn antivirus_scan(
+    server: &Server,
+    span_id: u64,
+    config: &AntivirusConfig,
+    contents: &[u8],
+) -> trc::Result<Option<String>> {
+    // Check the verdict cache
+    let hash = config.cache_ttl.map(|_| Sha256::digest(contents));
+    if let Some(hash) = &hash {
+        if let Ok(Some(verdict)) = key_get::<String>(
+            server,
+            span_id,
+            KeyValue::<()>::build_key(KV_ANTIVIRUS, hash.as_slice()),
+        )
+        .await
+        {
+            return Ok((!verdict.is_empty()).then_some(verdict));
+        }
+    }
+
+    let verdict = clamd_scan(config, contents).await?;
+
+    if let (Some(hash), Some(ttl)) = (hash, config.cache_ttl) {
+        key_set(
+            server,
+            span_id,
+            KeyValue::with_prefix(
+                KV_ANTIVIRUS,
+                hash.as_slice(),
+                verdict.as_deref().unwrap_or_default().as_bytes().to_vec(),
+            )
+            .expires(ttl),
+        )
+        .await;
+    }
+
+    Ok(verdict)
+}
+
+async fn clamd_scan(config: &AntivirusConfig, contents: &[u8]) -> trc::Result<Option<String>> {
+    let result = match &config.address {
+        ClamdAddress::Tcp(addr) => {
+            tokio::time::timeout(config.timeout, async {
+                clamd_instream(TcpStream::connect(addr).await?, contents).await
+            })
+            .await
+        }
+        #[cfg(unix)]
+        ClamdAddress::Unix(path) => {
+            tokio::time::timeout(config.timeout, async {
+                clamd_instream(tokio::net::UnixStream::connect(path).await?, contents).await
+            })
+            .await
+        }
+        #[cfg(not(unix))]
+        ClamdAddress::Unix(_) => Ok(Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "Unix sockets are not supported on this platform",
+        ))),
+    };
+
+    result
+        .unwrap_or_else(|_| {
+            Err(std::io::Error::new(
+                std::io::ErrorKind::TimedOut,
+                "Request timed out",
+            ))
+        })
+        .and_then(|response| parse_clamd_response(&response))
+        .map_err(|err| {
+            trc::SpamEvent::AntivirusError
+                .into_err()
+                .ctx(trc::Key::Url, config.address.to_string())
+                .reason(err)
+                .details("Antivirus scan failed")
+        })
+}
+
+async fn clamd_instream<T: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: T,
+    contents: &[u8],
+) -> std::io::Result<String> {
+    stream.write_all(b"zINSTREAM\0").await?;
+    for chunk in contents.chunks(CHUNK_SIZE) {
+        stream
+            .write_all(&(chunk.len() as u32).to_be_bytes())
+            .await?;
+        stream.write_all(chunk).await?;
+    }
+    stream.write_all(&[0, 0, 0, 0]).await?;
+    stream.flush().await?;
+
+    // Responses are terminated by a NUL byte when using the 'z' prefix
+    let mut response = Vec::with_capacity(128);
+    let mut buf = [0u8; 256];
+    loop {
+        let size = stream.read(&mut buf).await?;
+        if size == 0 {
+            break;
+        }
+        response.extend_from_slice(&buf[..size]);
+        if response.contains(&0) || response.len() > MAX_RESPONSE_SIZE {
+            break;
+        }
+    }
+
+    String::from_utf8(response)
+        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+}
+
+fn parse_clamd_response(response: &str) -> std::io::Result<Option<String>> {
+    let response = response.trim_end_matches(['\0', '\r', '\n', ' ']);
+    let result = response
+        .strip_prefix("stream:")
+        .map(|result| result.trim())
+        .unwrap_or(response);
+
+    if result == "OK" {
+        Ok(None)
+    } else if let Some(name) = result
+        .strip_suffix("FOUND")
+        .map(|name| name.trim())
+        .filter(|name| !name.is_empty())
+    {
+        Ok(Some(name.to_string()))
+    } else {
+        Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("Invalid response: {response}"),
+        ))
+    }
+}
@@ -22,7 +22,10 @@ use store::dispatch::lookup::KeyValue;
 use trc::AddContext;
 use utils::cache::TtlEntry;
 
-use crate::{Email, IpParts, SpamFilterContext, TextPart, analysis::url::UrlParts};
+use crate::{
+    Email, IpParts, SpamFilterContext, TextPart, analysis::url::UrlParts,
+    modules::ftrl::FtrlClassifier,
+};
 
 pub trait BayesClassifier {
     fn bayes_train(
@@ -63,6 +66,15 @@ impl BayesClassifier for Server {
         is_spam: bool,
         is_train: bool,
     ) -> trc::Result<()> {
+        // Train the logistic regression model instead when selected
+        if let Some(config) = self.core.spam.bayes.as_ref().and_then(|c| c.ftrl.as_ref()) {
+            return if is_train {
+                self.ftrl_train(ctx, config, is_spam).await
+            } else {
+                Ok(())
+            };
+        }
+
         // Train the model
         let mut model = BayesModel::default();
 
@@ -181,6 +193,11 @@ impl BayesClassifier for Server {
 
     async fn bayes_classify(&self, ctx: &SpamFilterContext<'_>) -> trc::Result<Option<f64>> {
         let classifier = if let Some(config) = &self.core.spam.bayes {
+            if let Some(ftrl) = &config.ftrl {
+                return self
+                    .ftrl_classify(ctx, ftrl, config.classifier.min_learns)
+                    .await;
+            }
             &config.classifier
         } else {
             return Ok(None);
@@ -318,10 +335,19 @@ impl BayesClassifier for Server {
         }
 
         // Obtain training counts
-        let (spam_learns, ham_learns) = self
-            .bayes_weights_for_token(ctx.input.account_id, TokenHash::default())
-            .await
-            .map(|w| (w.spam as f64, w.ham as f64))?;
+        let (spam_learns, ham_learns) = if self
+            .core
+            .spam
+            .bayes
+            .as_ref()
+            .is_some_and(|c| c.ftrl.is_some())
+        {
+            self.ftrl_learns(ctx.input.account_id).await
+        } else {
+            self.bayes_weights_for_token(ctx.input.account_id, TokenHash::default())
+                .await
+        }
+        .map(|w| (w.spam as f64, w.ham as f64))?;
 
         let result = if spam_learns > 0.0 || ham_learns > 0.0 {
             if learn_spam {
@@ -464,7 +490,7 @@ fn add_prefix(prefix: u8, key: &[u8]) -> Vec<u8> {
     buf
 }
 
-fn to_bayes_token(
+pub(crate) fn to_bayes_token(
     token: &TokenType<Cow<'_, str>, Email, UrlParts<'_>, IpParts<'_>>,
 ) -> Option<BayesInputToken> {
     match token {
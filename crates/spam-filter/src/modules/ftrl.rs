@@ -0,0 +1,279 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::future::Future;
+
+use common::{KV_FTRL_MODEL_GLOBAL, KV_FTRL_MODEL_USER, Server, config::spamfilter::FtrlConfig};
+use nlp::{
+    bayes::{TokenHash, Weights, tokenize::BayesTokenizer},
+    ftrl::{FtrlFeatures, FtrlModel, FtrlWeight},
+    tokenizers::osb::{Gram, OsbTokenizer},
+};
+use store::{Deserialize, InMemoryStore, dispatch::lookup::KeyValue};
+use trc::AddContext;
+
+use crate::{SpamFilterContext, TextPart, modules::bayes::to_bayes_token};
+
+pub trait FtrlClassifier {
+    fn ftrl_train(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+        config: &FtrlConfig,
+        is_spam: bool,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+
+    fn ftrl_classify(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+        config: &FtrlConfig,
+        min_learns: u32,
+    ) -> impl Future<Output = trc::Result<Option<f64>>> + Send;
+
+    fn ftrl_learns(
+        &self,
+        account_id: Option<u32>,
+    ) -> impl Future<Output = trc::Result<Weights>> + Send;
+
+    fn ftrl_delete_account(&self, account_id: u32) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+#[derive(Debug)]
+struct FtrlState(FtrlWeight);
+
+impl FtrlClassifier for Server {
+    async fn ftrl_train(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+        config: &FtrlConfig,
+        is_spam: bool,
+    ) -> trc::Result<()> {
+        let features = ctx.ftrl_features(config);
+        if features.is_empty() {
+            trc::bail!(
+                trc::SpamEvent::TrainError
+                    .into_err()
+                    .reason("No features found")
+            );
+        }
+
+        // Obtain the current state of the model for these features
+        let store = ftrl_store(self);
+        let mut model = FtrlModel::default();
+        for bucket in &features.buckets {
+            if let Some(state) = store
+                .key_get::<FtrlState>(ftrl_key(ctx.input.account_id, Some(*bucket)))
+                .await
+                .caused_by(trc::location!())?
+            {
+                model.weights.insert(*bucket, state.0);
+            }
+        }
+
+        // Train the model
+        model.train(&config.parameters, &features, is_spam);
+
+        trc::event!(
+            Spam(trc::SpamEvent::Train),
+            SpanId = ctx.input.span_id,
+            Details = is_spam,
+            Total = features.len(),
+        );
+
+        // Concurrent updates to the same bucket are last-writer-wins, which FTRL tolerates
+        for (bucket, state) in model.weights {
+            store
+                .key_set(KeyValue::new(
+                    ftrl_key(ctx.input.account_id, Some(bucket)),
+                    state.serialize(),
+                ))
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        // Update training counts
+        let weights = if is_spam {
+            Weights { spam: 1, ham: 0 }
+        } else {
+            Weights { spam: 0, ham: 1 }
+        };
+        store
+            .counter_incr(
+                KeyValue::new(ftrl_key(ctx.input.account_id, None), i64::from(weights)),
+                false,
+            )
+            .await
+            .caused_by(trc::location!())
+            .map(|_| ())
+    }
+
+    async fn ftrl_classify(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+        config: &FtrlConfig,
+        min_learns: u32,
+    ) -> trc::Result<Option<f64>> {
+        // Make sure we have enough training data
+        let learns = self.ftrl_learns(ctx.input.account_id).await?;
+        if learns.spam < min_learns || learns.ham < min_learns {
+            trc::event!(
+                Spam(trc::SpamEvent::ClassifyError),
+                SpanId = ctx.input.span_id,
+                AccountId = ctx.input.account_id,
+                Reason = "Not enough training data",
+                Details = vec![
+                    trc::Value::from(learns.spam),
+                    trc::Value::from(learns.ham),
+                    trc::Value::from(min_learns)
+                ],
+            );
+            return Ok(None);
+        }
+
+        // Classify the message
+        let store = ftrl_store(self);
+        let features = ctx.ftrl_features(config);
+        let mut weights = Vec::with_capacity(features.len());
+        for bucket in &features.buckets {
+            if let Some(state) = store
+                .key_get::<FtrlState>(ftrl_key(ctx.input.account_id, Some(*bucket)))
+                .await
+                .caused_by(trc::location!())?
+            {
+                weights.push(state.0);
+            }
+        }
+        let result = config.parameters.predict(weights.iter());
+
+        trc::event!(
+            Spam(trc::SpamEvent::Classify),
+            SpanId = ctx.input.span_id,
+            AccountId = ctx.input.account_id,
+            Details = vec![
+                trc::Value::from(learns.spam),
+                trc::Value::from(learns.ham),
+                trc::Value::from(min_learns)
+            ],
+            Result = result
+        );
+
+        Ok(Some(result))
+    }
+
+    async fn ftrl_learns(&self, account_id: Option<u32>) -> trc::Result<Weights> {
+        ftrl_store(self)
+            .counter_get(ftrl_key(account_id, None))
+            .await
+            .map(Weights::from)
+    }
+
+    async fn ftrl_delete_account(&self, account_id: u32) -> trc::Result<()> {
+        ftrl_store(self)
+            .key_delete_prefix(&ftrl_key(account_id.into(), None))
+            .await
+    }
+}
+
+impl SpamFilterContext<'_> {
+    pub fn ftrl_features(&self, config: &FtrlConfig) -> FtrlFeatures {
+        let feature_bits = config.parameters.feature_bits;
+        let mut features = FtrlFeatures::new();
+
+        // Add metadata tokens
+        for token in self.spam_tokens() {
+            features.add_token(&TokenHash::from(Gram::Uni { t1: &token }), feature_bits);
+        }
+
+        // Add subject tokens
+        for token in OsbTokenizer::<_, TokenHash>::new(
+            BayesTokenizer::new(
+                &self.output.subject_thread,
+                self.output.subject_tokens.iter().filter_map(to_bayes_token),
+            ),
+            5,
+        ) {
+            features.add_token(&token.inner, feature_bits);
+        }
+
+        // Add body tokens
+        match self
+            .input
+            .message
+            .html_body
+            .first()
+            .or_else(|| self.input.message.text_body.first())
+            .and_then(|idx| self.output.text_parts.get(*idx))
+        {
+            Some(TextPart::Html {
+                text_body, tokens, ..
+            }) => {
+                for token in OsbTokenizer::<_, TokenHash>::new(
+                    BayesTokenizer::new(text_body, tokens.iter().filter_map(to_bayes_token)),
+                    5,
+                ) {
+                    features.add_token(&token.inner, feature_bits);
+                }
+            }
+            Some(TextPart::Plain { text_body, tokens }) => {
+                for token in OsbTokenizer::<_, TokenHash>::new(
+                    BayesTokenizer::new(text_body, tokens.iter().filter_map(to_bayes_token)),
+                    5,
+                ) {
+                    features.add_token(&token.inner, feature_bits);
+                }
+            }
+            _ => {}
+        }
+
+        // Add the tags set by the spam filter, excluding the classifier's own output
+        if config.tags {
+            for tag in &self.result.tags {
+                if !tag.starts_with("BAYES_") {
+                    features.add_tag(tag, feature_bits);
+                }
+            }
+        }
+
+        features
+    }
+}
+
+fn ftrl_store(server: &Server) -> InMemoryStore {
+    // Weights are kept in the data store so the model survives
+    // restarts even when the in-memory store is volatile
+    InMemoryStore::Store(server.store().clone())
+}
+
+fn ftrl_key(account_id: Option<u32>, bucket: Option<u32>) -> Vec<u8> {
+    let mut key = Vec::with_capacity(1 + (std::mem::size_of::<u32>() * 2));
+    if let Some(account_id) = account_id {
+        key.push(KV_FTRL_MODEL_USER);
+        key.extend_from_slice(&account_id.to_be_bytes());
+    } else {
+        key.push(KV_FTRL_MODEL_GLOBAL);
+    }
+    if let Some(bucket) = bucket {
+        key.extend_from_slice(&bucket.to_be_bytes());
+    }
+    key
+}
+
+impl Deserialize for FtrlState {
+    fn deserialize(bytes: &[u8]) -> trc::Result<Self> {
+        FtrlWeight::deserialize(bytes)
+            .map(FtrlState)
+            .ok_or_else(|| {
+                trc::StoreEvent::DataCorruption
+                    .caused_by(trc::location!())
+                    .ctx(trc::Key::Value, bytes)
+            })
+    }
+}
+
+impl From<store::Value<'_>> for FtrlState {
+    fn from(_: store::Value<'_>) -> Self {
+        unimplemented!()
+    }
+}
@@ -0,0 +1,487 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{fmt::Display, future::Future, str::FromStr};
+
+use common::{KV_FUZZY_COUNT, KV_FUZZY_INDEX, Server, config::spamfilter::FuzzyConfig};
+use nlp::{bayes::Weights, tokenizers::types::TokenType};
+use store::dispatch::lookup::KeyValue;
+use trc::AddContext;
+
+use crate::{SpamFilterContext, TextPart};
+
+const ROLLING_WINDOW: usize = 7;
+const MIN_BLOCK_SIZE: u32 = 3;
+const SPAMSUM_LENGTH: usize = 64;
+const HASH_PRIME: u32 = 0x01000193;
+const HASH_INIT: u32 = 0x28021967;
+const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+const MAX_INDEX_GRAMS: usize = 16;
+const COUNTER_SEEN: u8 = 0;
+const COUNTER_VOTES: u8 = 1;
+
+// Context triggered piecewise hash, compatible with ssdeep
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzyHash {
+    pub block_size: u32,
+    pub sig1: String,
+    pub sig2: String,
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzyMatch {
+    pub count: u64,
+    pub spam: u32,
+    pub ham: u32,
+}
+
+pub trait FuzzyDatabase {
+    fn fuzzy_check(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+    ) -> impl Future<Output = trc::Result<Vec<FuzzyMatch>>> + Send;
+
+    fn fuzzy_train(
+        &self,
+        ctx: &SpamFilterContext<'_>,
+        is_spam: bool,
+    ) -> impl Future<Output = trc::Result<()>> + Send;
+}
+
+impl FuzzyDatabase for Server {
+    async fn fuzzy_check(&self, ctx: &SpamFilterContext<'_>) -> trc::Result<Vec<FuzzyMatch>> {
+        let Some(config) = &self.core.spam.fuzzy else {
+            return Ok(vec![]);
+        };
+
+        let mut matches = Vec::new();
+        for hash in ctx.fuzzy_hashes(config) {
+            let Some(id) = self
+                .fuzzy_resolve(&hash, config, !ctx.input.is_test)
+                .await?
+            else {
+                continue;
+            };
+            let count = if !ctx.input.is_test {
+                self.in_memory_store()
+                    .counter_incr(
+                        KeyValue::new(counter_key(&id, COUNTER_SEEN), 1).expires(config.expiry),
+                        true,
+                    )
+                    .await
+            } else {
+                self.in_memory_store()
+                    .counter_get(counter_key(&id, COUNTER_SEEN))
+                    .await
+            }
+            .caused_by(trc::location!())?;
+            let votes = self
+                .in_memory_store()
+                .counter_get(counter_key(&id, COUNTER_VOTES))
+                .await
+                .map(Weights::from)
+                .caused_by(trc::location!())?;
+
+            matches.push(FuzzyMatch {
+                count: count.max(0) as u64,
+                spam: votes.spam,
+                ham: votes.ham,
+            });
+        }
+
+        Ok(matches)
+    }
+
+    async fn fuzzy_train(&self, ctx: &SpamFilterContext<'_>, is_spam: bool) -> trc::Result<()> {
+        let Some(config) = &self.core.spam.fuzzy else {
+            return Ok(());
+        };
+
+        let hashes = ctx.fuzzy_hashes(config);
+        if hashes.is_empty() {
+            trc::bail!(
+                trc::SpamEvent::TrainError
+                    .into_err()
+                    .reason("No fuzzy hashes found")
+            );
+        }
+
+        trc::event!(
+            Spam(trc::SpamEvent::FuzzyTrain),
+            SpanId = ctx.input.span_id,
+            Details = is_spam,
+            Total = hashes.len(),
+        );
+
+        let weights = if is_spam {
+            Weights { spam: 1, ham: 0 }
+        } else {
+            Weights { spam: 0, ham: 1 }
+        };
+        for hash in hashes {
+            let Some(id) = self.fuzzy_resolve(&hash, config, true).await? else {
+                continue;
+            };
+            self.in_memory_store()
+                .counter_incr(
+                    KeyValue::new(counter_key(&id, COUNTER_VOTES), i64::from(weights))
+                        .expires(config.expiry),
+                    false,
+                )
+                .await
+                .caused_by(trc::location!())?;
+        }
+
+        Ok(())
+    }
+}
+
+trait FuzzyResolve {
+    fn fuzzy_resolve(
+        &self,
+        hash: &FuzzyHash,
+        config: &FuzzyConfig,
+        register: bool,
+    ) -> impl Future<Output = trc::Result<Option<String>>> + Send;
+}
+
+impl FuzzyResolve for Server {
+    // Returns the id of the most similar stored hash, registering new hashes if requested
+    async fn fuzzy_resolve(
+        &self,
+        hash: &FuzzyHash,
+        config: &FuzzyConfig,
+        register: bool,
+    ) -> trc::Result<Option<String>> {
+        let store = self.in_memory_store();
+        let index = hash.index_keys();
+        let mut best_match: Option<(u32, String)> = None;
+
+        for key in &index {
+            let Some(candidate) = store
+                .key_get::<String>(KeyValue::<()>::build_key(KV_FUZZY_INDEX, key))
+                .await
+                .caused_by(trc::location!())?
+            else {
+                continue;
+            };
+            if best_match.as_ref().is_some_and(|(_, id)| id == &candidate) {
+                continue;
+            }
+            let score = candidate
+                .parse::<FuzzyHash>()
+                .map_or(0, |candidate| hash.compare(&candidate));
+            if score >= config.min_score
+                && best_match.as_ref().is_none_or(|(best, _)| score > *best)
+            {
+                best_match = Some((score, candidate));
+                if score == 100 {
+                    break;
+                }
+            }
+        }
+
+        if let Some((_, id)) = best_match {
+            Ok(Some(id))
+        } else if register {
+            let id = hash.to_string();
+            for key in index {
+                store
+                    .key_set(
+                        KeyValue::with_prefix(KV_FUZZY_INDEX, key, id.as_bytes().to_vec())
+                            .expires(config.expiry),
+                    )
+                    .await
+                    .caused_by(trc::location!())?;
+            }
+            Ok(Some(id))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl SpamFilterContext<'_> {
+    pub fn fuzzy_hashes(&self, config: &FuzzyConfig) -> Vec<FuzzyHash> {
+        let mut hashes = Vec::new();
+
+        // Hash the normalized text body, ignoring numbers, URLs and addresses
+        // that are usually personalized for each recipient
+        let tokens = match self
+            .input
+            .message
+            .html_body
+            .first()
+            .or_else(|| self.input.message.text_body.first())
+            .and_then(|idx| self.output.text_parts.get(*idx))
+        {
+            Some(TextPart::Html { tokens, .. } | TextPart::Plain { tokens, .. }) => {
+                tokens.as_slice()
+            }
+            _ => &[],
+        };
+        let mut text = String::new();
+        for token in tokens {
+            if let TokenType::Alphabetic(word) | TokenType::Alphanumeric(word) = token {
+                if !text.is_empty() {
+                    text.push(' ');
+                }
+                text.extend(word.chars().flat_map(char::to_lowercase));
+            }
+        }
+        if text.len() >= config.min_length {
+            hashes.extend(FuzzyHash::new(text.as_bytes()));
+        }
+
+        // Hash attachments
+        if config.attachments {
+            let message = self.input.message;
+            for part in message
+                .attachments
+                .iter()
+                .filter_map(|part_id| message.parts.get(*part_id as usize))
+            {
+                let contents = part.contents();
+                if contents.len() >= config.min_length && contents.len() <= config.max_size {
+                    hashes.extend(FuzzyHash::new(contents));
+                }
+            }
+        }
+
+        hashes
+    }
+}
+
+impl FuzzyHash {
+    pub fn new(data: &[u8]) -> Option<Self> {
+        if data.is_empty() {
+            return None;
+        }
+
+        let mut block_size = MIN_BLOCK_SIZE;
+        while (block_size as usize) * SPAMSUM_LENGTH < data.len() {
+            block_size *= 2;
+        }
+
+        loop {
+            let mut roll = RollingHash::default();
+            let mut h1 = HASH_INIT;
+            let mut h2 = HASH_INIT;
+            let mut sig1 = String::with_capacity(SPAMSUM_LENGTH);
+            let mut sig2 = String::with_capacity(SPAMSUM_LENGTH / 2);
+
+            for &ch in data {
+                h1 = sum_hash(ch, h1);
+                h2 = sum_hash(ch, h2);
+                let rh = roll.update(ch);
+
+                if rh % block_size == block_size - 1 && sig1.len() < SPAMSUM_LENGTH - 1 {
+                    sig1.push(BASE64[(h1 % 64) as usize] as char);
+                    h1 = HASH_INIT;
+                }
+                if rh % (block_size * 2) == block_size * 2 - 1
+                    && sig2.len() < SPAMSUM_LENGTH / 2 - 1
+                {
+                    sig2.push(BASE64[(h2 % 64) as usize] as char);
+                    h2 = HASH_INIT;
+                }
+            }
+            sig1.push(BASE64[(h1 % 64) as usize] as char);
+            sig2.push(BASE64[(h2 % 64) as usize] as char);
+
+            if block_size > MIN_BLOCK_SIZE && sig1.len() < SPAMSUM_LENGTH / 2 {
+                block_size /= 2;
+            } else {
+                return Some(FuzzyHash {
+                    block_size,
+                    sig1,
+                    sig2,
+                });
+            }
+        }
+    }
+
+    // Returns a similarity score between 0 and 100
+    pub fn compare(&self, other: &FuzzyHash) -> u32 {
+        let (bs1, bs2) = (self.block_size, other.block_size);
+
+        if bs1 == bs2 {
+            if self.sig1 == other.sig1 {
+                return 100;
+            }
+            score_strings(&self.sig1, &other.sig1, bs1).max(score_strings(
+                &self.sig2,
+                &other.sig2,
+                bs1 * 2,
+            ))
+        } else if bs1 == bs2 * 2 {
+            score_strings(&self.sig1, &other.sig2, bs1)
+        } else if bs2 == bs1 * 2 {
+            score_strings(&self.sig2, &other.sig1, bs2)
+        } else {
+            0
+        }
+    }
+
+    // Index keys are a sample of the 7-character substrings of each signature,
+    // two signatures can only be similar if they have one such substring in common.
+    fn index_keys(&self) -> Vec<Vec<u8>> {
+        let mut keys = Vec::new();
+
+        for (block_size, sig) in [
+            (self.block_size, &self.sig1),
+            (self.block_size * 2, &self.sig2),
+        ] {
+            let sig = eliminate_sequences(sig);
+            let grams = sig.windows(ROLLING_WINDOW).map(|gram| {
+                (
+                    gram.iter().fold(HASH_INIT, |hash, ch| sum_hash(*ch, hash)),
+                    gram,
+                )
+            });
+            let Some((_, min_gram)) = grams.clone().min() else {
+                continue;
+            };
+            let mut add_gram = |gram: &[u8]| {
+                let mut key = Vec::with_capacity(ROLLING_WINDOW + 4);
+                key.extend_from_slice(&block_size.to_be_bytes());
+                key.extend_from_slice(gram);
+                if !keys.contains(&key) {
+                    keys.push(key);
+                }
+            };
+            add_gram(min_gram);
+            for (_, gram) in grams
+                .filter(|(hash, _)| hash % 4 == 0)
+                .take(MAX_INDEX_GRAMS / 2)
+            {
+                add_gram(gram);
+            }
+        }
+
+        keys
+    }
+}
+
+fn score_strings(s1: &str, s2: &str, block_size: u32) -> u32 {
+    let s1 = eliminate_sequences(s1);
+    let s2 = eliminate_sequences(s2);
+    if s1.len() < ROLLING_WINDOW
+        || s2.len() < ROLLING_WINDOW
+        || !s1
+            .windows(ROLLING_WINDOW)
+            .any(|gram| s2.windows(ROLLING_WINDOW).any(|other| other == gram))
+    {
+        return 0;
+    }
+
+    // Scale the edit distance to a 0-100 similarity score
+    let distance = edit_distance(&s1, &s2) * SPAMSUM_LENGTH / (s1.len() + s2.len());
+    let distance = (100 * distance / SPAMSUM_LENGTH) as u32;
+    if distance >= 100 {
+        return 0;
+    }
+    let score = 100 - distance;
+
+    // Don't overstate matches of small block sizes
+    let max_block_size = (99 + ROLLING_WINDOW as u32) / ROLLING_WINDOW as u32 * MIN_BLOCK_SIZE;
+    if block_size >= max_block_size {
+        score
+    } else {
+        score.min(block_size / MIN_BLOCK_SIZE * s1.len().min(s2.len()) as u32)
+    }
+}
+
+// Weighted Levenshtein distance: insertions and deletions cost 1, substitutions cost 2
+fn edit_distance(s1: &[u8], s2: &[u8]) -> usize {
+    let mut prev = (0..=s2.len()).collect::<Vec<_>>();
+    let mut current = vec![0; s2.len() + 1];
+
+    for (i, ch1) in s1.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, ch2) in s2.iter().enumerate() {
+            let cost = if ch1 == ch2 { 0 } else { 2 };
+            current[j + 1] = (prev[j + 1] + 1).min(current[j] + 1).min(prev[j] + cost);
+        }
+        std::mem::swap(&mut prev, &mut current);
+    }
+
+    prev[s2.len()]
+}
+
+// Runs of more than three identical characters carry little information
+fn eliminate_sequences(sig: &str) -> Vec<u8> {
+    let mut result = Vec::with_capacity(sig.len());
+    for &ch in sig.as_bytes() {
+        if result.len() < 3 || !result[result.len() - 3..].iter().all(|c| *c == ch) {
+            result.push(ch);
+        }
+    }
+    result
+}
+
+fn counter_key(id: &str, counter: u8) -> Vec<u8> {
+    let mut key = KeyValue::<()>::build_key(KV_FUZZY_COUNT, id);
+    key.push(counter);
+    key
+}
+
+#[inline(always)]
+fn sum_hash(ch: u8, hash: u32) -> u32 {
+    hash.wrapping_mul(HASH_PRIME) ^ ch as u32
+}
+
+#[derive(Default)]
+struct RollingHash {
+    window: [u8; ROLLING_WINDOW],
+    h1: u32,
+    h2: u32,
+    h3: u32,
+    n: usize,
+}
+
+impl RollingHash {
+    fn update(&mut self, ch: u8) -> u32 {
+        let ch_ = ch as u32;
+        self.h2 = self
+            .h2
+            .wrapping_sub(self.h1)
+            .wrapping_add(ROLLING_WINDOW as u32 * ch_);
+        self.h1 = self
+            .h1
+            .wrapping_add(ch_)
+            .wrapping_sub(self.window[self.n % ROLLING_WINDOW] as u32);
+        self.window[self.n % ROLLING_WINDOW] = ch;
+        self.n += 1;
+        self.h3 = (self.h3 << 5) ^ ch_;
+
+        self.h1.wrapping_add(self.h2).wrapping_add(self.h3)
+    }
+}
+
+impl Display for FuzzyHash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}:{}", self.block_size, self.sig1, self.sig2)
+    }
+}
+
+impl FromStr for FuzzyHash {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.splitn(3, ':');
+        let block_size = parts.next().and_then(|bs| bs.parse().ok()).ok_or(())?;
+        let sig1 = parts.next().ok_or(())?;
+        let sig2 = parts.next().ok_or(())?;
+
+        Ok(FuzzyHash {
+            block_size,
+            sig1: sig1.to_string(),
+            sig2: sig2.to_string(),
+        })
+    }
+}
@@ -0,0 +1,865 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    io::{Cursor, Read, Write},
+    path::Component,
+};
+
+use flate2::read::ZlibDecoder;
+
+const SEVEN_ZIP_MAGIC: &[u8] = b"7z\xBC\xAF\x27\x1C";
+const RAR4_MAGIC: &[u8] = b"Rar!\x1A\x07\x00";
+const RAR5_MAGIC: &[u8] = b"Rar!\x1A\x07\x01\x00";
+const OLE2_MAGIC: &[u8] = b"\xD0\xCF\x11\xE0\xA1\xB1\x1A\xE1";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+
+const MAX_PDF_STREAMS: usize = 256;
+const MAX_INFLATED_SIZE: u64 = 1024 * 1024;
+const MAX_HEADER_SIZE: u64 = 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ContentKind {
+    Zip,
+    SevenZip,
+    Rar,
+    Ooxml,
+    Ole2,
+    Pdf,
+}
+
+#[derive(Debug)]
+pub(crate) struct Inspection {
+    pub kind: ContentKind,
+    pub members: Vec<String>,
+    pub encrypted: bool,
+    pub vba_macros: bool,
+    pub ole_objects: bool,
+    pub pdf_javascript: bool,
+    pub pdf_launch: bool,
+    pub pdf_auto_action: bool,
+    pub pdf_embedded_file: bool,
+}
+
+// Identifies archives, Office documents and PDFs by their magic bytes
+// and looks inside them for content commonly used to deliver malware.
+pub(crate) fn inspect_contents(bytes: &[u8], max_members: usize) -> Option<Inspection> {
+    if bytes.starts_with(ZIP_MAGIC) {
+        inspect_zip(bytes, max_members)
+    } else if bytes.starts_with(SEVEN_ZIP_MAGIC) {
+        Some(inspect_7z(bytes, max_members))
+    } else if bytes.starts_with(RAR5_MAGIC) {
+        Some(inspect_rar5(bytes, max_members))
+    } else if bytes.starts_with(RAR4_MAGIC) {
+        Some(inspect_rar4(bytes, max_members))
+    } else if bytes.starts_with(OLE2_MAGIC) {
+        inspect_ole2(bytes)
+    } else if find(&bytes[..bytes.len().min(1024)], b"%PDF-").is_some() {
+        Some(inspect_pdf(bytes))
+    } else {
+        None
+    }
+}
+
+impl Inspection {
+    fn new(kind: ContentKind) -> Self {
+        Inspection {
+            kind,
+            members: Vec::new(),
+            encrypted: false,
+            vba_macros: false,
+            ole_objects: false,
+            pdf_javascript: false,
+            pdf_launch: false,
+            pdf_auto_action: false,
+            pdf_embedded_file: false,
+        }
+    }
+
+    fn add_member(&mut self, name: String, max_members: usize) {
+        if self.members.len() < max_members && !name.is_empty() {
+            self.members.push(name);
+        }
+    }
+}
+
+fn inspect_zip(bytes: &[u8], max_members: usize) -> Option<Inspection> {
+    let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).ok()?;
+    let mut inspection = Inspection::new(ContentKind::Zip);
+    let mut members = Vec::new();
+
+    for idx in 0..archive.len().min(max_members) {
+        let Ok(file) = archive.by_index_raw(idx) else {
+            continue;
+        };
+        inspection.encrypted |= file.encrypted();
+        let name = file.name().to_lowercase();
+
+        if name == "[content_types].xml" {
+            inspection.kind = ContentKind::Ooxml;
+        } else if name.ends_with("vbaproject.bin") || name.ends_with("vbadata.xml") {
+            inspection.vba_macros = true;
+        } else if name.contains("/embeddings/oleobject")
+            || name.contains("/activex/")
+            || (name.contains("/embeddings/") && name.ends_with(".bin"))
+        {
+            inspection.ole_objects = true;
+        }
+
+        if !file.is_dir() {
+            members.push(file.name().to_string());
+        }
+    }
+
+    // Parts of an Office document are not archive members
+    if inspection.kind == ContentKind::Zip {
+        inspection.members = members;
+    }
+
+    Some(inspection)
+}
+
+fn inspect_ole2(bytes: &[u8]) -> Option<Inspection> {
+    let file = cfb::CompoundFile::open(Cursor::new(bytes)).ok()?;
+    let mut inspection = Inspection::new(ContentKind::Ole2);
+
+    for entry in file.walk() {
+        let name = entry.name().to_lowercase();
+        match name.as_str() {
+            "_vba_project" | "_vba_project_cur" | "macros" | "vba" => {
+                inspection.vba_macros = true;
+            }
+            "encryptedpackage" | "encryptioninfo" => {
+                inspection.encrypted = true;
+            }
+            "\u{1}ole10native" => {
+                inspection.ole_objects = true;
+            }
+            _ => {}
+        }
+
+        // Embedded objects are stored below 'ObjectPool' (Word) or 'MBD' (Excel) storages
+        let mut components = entry.path().components().filter_map(|c| match c {
+            Component::Normal(name) => name.to_str(),
+            _ => None,
+        });
+        let _ = components.next_back();
+        if components
+            .any(|parent| parent.eq_ignore_ascii_case("objectpool") || parent.starts_with("MBD"))
+        {
+            inspection.ole_objects = true;
+        }
+    }
+
+    Some(inspection)
+}
+
+fn inspect_pdf(bytes: &[u8]) -> Inspection {
+    let mut inspection = Inspection::new(ContentKind::Pdf);
+    scan_pdf_names(bytes, &mut inspection);
+
+    // Object streams may hide actions inside compressed streams
+    let mut pos = 0;
+    let mut num_streams = 0;
+    while num_streams < MAX_PDF_STREAMS {
+        let Some(start) = find(&bytes[pos..], b"stream").map(|idx| pos + idx) else {
+            break;
+        };
+        let mut data_start = start + 6;
+        if bytes.get(data_start) == Some(&b'\r') {
+            data_start += 1;
+        }
+        if bytes.get(data_start) != Some(&b'\n') {
+            pos = start + 6;
+            continue;
+        }
+        data_start += 1;
+        let Some(data_end) = find(&bytes[data_start..], b"endstream").map(|idx| data_start + idx)
+        else {
+            break;
+        };
+        num_streams += 1;
+
+        let dict_start = rfind(&bytes[pos..start], b"obj").map_or(pos, |idx| pos + idx);
+        if find(&bytes[dict_start..start], b"/FlateDecode").is_some() {
+            let mut inflated = Vec::new();
+            if ZlibDecoder::new(&bytes[data_start..data_end])
+                .take(MAX_INFLATED_SIZE)
+                .read_to_end(&mut inflated)
+                .is_ok()
+                || !inflated.is_empty()
+            {
+                scan_pdf_names(&inflated, &mut inspection);
+            }
+        }
+
+        pos = data_end + 9;
+    }
+
+    inspection
+}
+
+fn scan_pdf_names(bytes: &[u8], inspection: &mut Inspection) {
+    let mut iter = bytes.iter().enumerate();
+
+    while let Some((pos, ch)) = iter.next() {
+        if *ch != b'/' {
+            continue;
+        }
+
+        // Decode name, including '#xx' hex escapes used for obfuscation
+        let mut name = Vec::with_capacity(16);
+        let mut idx = pos + 1;
+        while let Some(&ch) = bytes.get(idx) {
+            match ch {
+                b'#' => {
+                    if let Some(ch) = bytes
+                        .get(idx + 1..idx + 3)
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok())
+                    {
+                        name.push(ch);
+                        idx += 3;
+                        continue;
+                    }
+                    name.push(ch);
+                }
+                b'/' | b'[' | b']' | b'<' | b'>' | b'(' | b')' | b'{' | b'}' | b'%' => break,
+                ch if ch.is_ascii_whitespace() => break,
+                ch => name.push(ch),
+            }
+            if name.len() > 32 {
+                break;
+            }
+            idx += 1;
+        }
+
+        match name.as_slice() {
+            b"JavaScript" | b"JS" => inspection.pdf_javascript = true,
+            b"Launch" => inspection.pdf_launch = true,
+            b"OpenAction" | b"AA" => inspection.pdf_auto_action = true,
+            b"EmbeddedFile" | b"EmbeddedFiles" => inspection.pdf_embedded_file = true,
+            b"Encrypt" => inspection.encrypted = true,
+            _ => {}
+        }
+
+        if idx > pos + 1 {
+            let _ = iter.nth(idx - pos - 2);
+        }
+    }
+}
+
+fn inspect_rar5(bytes: &[u8], max_members: usize) -> Inspection {
+    let mut inspection = Inspection::new(ContentKind::Rar);
+    let mut reader = Reader::new(bytes, RAR5_MAGIC.len());
+
+    while inspection.members.len() < max_members {
+        // Skip header CRC
+        if reader.skip(4).is_none() {
+            break;
+        }
+        let Some(header_size) = reader.read_vint() else {
+            break;
+        };
+        let header_end = reader.pos.saturating_add(header_size as usize);
+        let (Some(header_type), Some(flags)) = (reader.read_vint(), reader.read_vint()) else {
+            break;
+        };
+        let extra_size = if flags & 0x01 != 0 {
+            reader.read_vint().unwrap_or_default() as usize
+        } else {
+            0
+        };
+        let data_size = if flags & 0x02 != 0 {
+            reader.read_vint().unwrap_or_default() as usize
+        } else {
+            0
+        };
+
+        match header_type {
+            // File and service headers
+            2 | 3 => {
+                let Some(file_flags) = reader.read_vint() else {
+                    break;
+                };
+                reader.read_vint(); // Unpacked size
+                reader.read_vint(); // Attributes
+                if file_flags & 0x02 != 0 {
+                    reader.skip(4); // Modification time
+                }
+                if file_flags & 0x04 != 0 {
+                    reader.skip(4); // Data CRC
+                }
+                reader.read_vint(); // Compression information
+                reader.read_vint(); // Host OS
+                let name = reader
+                    .read_vint()
+                    .and_then(|len| reader.read_bytes(len as usize))
+                    .map(|name| String::from_utf8_lossy(name).into_owned());
+
+                // Look for the file encryption record in the extra area
+                let mut extra = Reader::new(
+                    bytes.get(..header_end).unwrap_or_default(),
+                    header_end.saturating_sub(extra_size),
+                );
+                while extra.pos < header_end {
+                    let (Some(size), record_start) = (extra.read_vint(), extra.pos) else {
+                        break;
+                    };
+                    if extra.read_vint() == Some(0x01) {
+                        inspection.encrypted = true;
+                    }
+                    extra.pos = record_start.saturating_add(size as usize);
+                }
+
+                if header_type == 2 && file_flags & 0x01 == 0 {
+                    if let Some(name) = name {
+                        inspection.add_member(name, max_members);
+                    }
+                }
+            }
+            // Archive encryption header, the rest of the archive can't be read
+            4 => {
+                inspection.encrypted = true;
+                break;
+            }
+            // End of archive
+            5 => break,
+            _ => {}
+        }
+
+        reader.pos = header_end.saturating_add(data_size);
+    }
+
+    inspection
+}
+
+fn inspect_rar4(bytes: &[u8], max_members: usize) -> Inspection {
+    let mut inspection = Inspection::new(ContentKind::Rar);
+    let mut pos = RAR4_MAGIC.len();
+
+    while inspection.members.len() < max_members {
+        let Some(header) = bytes.get(pos..pos + 7) else {
+            break;
+        };
+        let header_type = header[2];
+        let flags = u16::from_le_bytes([header[3], header[4]]);
+        let header_size = u16::from_le_bytes([header[5], header[6]]) as usize;
+        if header_size < 7 {
+            break;
+        }
+        let mut data_size = if flags & 0x8000 != 0 {
+            bytes.get(pos + 7..pos + 11).map_or(0, |size| {
+                u32::from_le_bytes(size.try_into().unwrap()) as u64
+            })
+        } else {
+            0
+        };
+
+        match header_type {
+            // Main header, encrypted block headers
+            0x73 => {
+                if flags & 0x0080 != 0 {
+                    inspection.encrypted = true;
+                    break;
+                }
+            }
+            // File header
+            0x74 => {
+                if flags & 0x04 != 0 {
+                    inspection.encrypted = true;
+                }
+                let mut name_start = pos + 32;
+                if flags & 0x100 != 0 {
+                    if let Some(high) = bytes.get(pos + 32..pos + 36) {
+                        data_size |= (u32::from_le_bytes(high.try_into().unwrap()) as u64) << 32;
+                    }
+                    name_start += 8;
+                }
+                let name_size = bytes
+                    .get(pos + 26..pos + 28)
+                    .map_or(0, |size| u16::from_le_bytes([size[0], size[1]]) as usize);
+                // Directories have all dictionary bits set
+                if flags & 0x00E0 != 0x00E0 {
+                    if let Some(name) = bytes.get(name_start..name_start + name_size) {
+                        // Unicode names are stored after a NUL separator
+                        let name = name.split(|ch| *ch == 0).next().unwrap_or_default();
+                        inspection
+                            .add_member(String::from_utf8_lossy(name).into_owned(), max_members);
+                    }
+                }
+            }
+            // End of archive
+            0x7B => break,
+            _ => {}
+        }
+
+        match pos
+            .checked_add(header_size)
+            .and_then(|pos| pos.checked_add(usize::try_from(data_size).ok()?))
+        {
+            Some(next_pos) if next_pos < bytes.len() => pos = next_pos,
+            _ => break,
+        }
+    }
+
+    inspection
+}
+
+fn inspect_7z(bytes: &[u8], max_members: usize) -> Inspection {
+    let mut inspection = Inspection::new(ContentKind::SevenZip);
+
+    // The signature header points to the archive header at the end of the file
+    let mut reader = Reader::new(bytes, 12);
+    let (Some(offset), Some(size)) = (reader.read_u64(), reader.read_u64()) else {
+        return inspection;
+    };
+    let Some(header) = (32u64)
+        .checked_add(offset)
+        .and_then(|start| Some((start as usize, start.checked_add(size)? as usize)))
+        .and_then(|(start, end)| bytes.get(start..end))
+    else {
+        return inspection;
+    };
+
+    let decoded;
+    let mut reader = Reader::new(header, 0);
+    match reader.read_u8() {
+        Some(0x01) => {}
+        Some(0x17) => {
+            // Encoded header, usually compressed and possibly encrypted
+            let Some(info) = StreamsInfo::parse(&mut reader) else {
+                return inspection;
+            };
+            if info.is_encrypted() {
+                inspection.encrypted = true;
+                return inspection;
+            }
+            let Some(header) = info.decode_first(bytes) else {
+                return inspection;
+            };
+            decoded = header;
+            reader = Reader::new(&decoded, 0);
+            if reader.read_u8() != Some(0x01) {
+                return inspection;
+            }
+        }
+        _ => return inspection,
+    }
+
+    // Archive header
+    loop {
+        match reader.read_u8() {
+            // Archive properties
+            Some(0x02) => loop {
+                match reader.read_number() {
+                    Some(0) | None => break,
+                    Some(_) => {
+                        let size = reader.read_number().unwrap_or(u64::MAX);
+                        if reader.skip(size as usize).is_none() {
+                            return inspection;
+                        }
+                    }
+                }
+            },
+            // Additional and main streams
+            Some(0x03) | Some(0x04) => {
+                let Some(info) = StreamsInfo::parse(&mut reader) else {
+                    return inspection;
+                };
+                inspection.encrypted |= info.is_encrypted();
+            }
+            // Files
+            Some(0x05) => {
+                let num_files = reader.read_number().unwrap_or_default() as usize;
+                loop {
+                    let Some(prop) = reader.read_number().filter(|prop| *prop != 0) else {
+                        break;
+                    };
+                    let Some(size) = reader.read_number() else {
+                        break;
+                    };
+                    let Some(data) = reader.read_bytes(size as usize) else {
+                        break;
+                    };
+                    // File names, stored as NUL terminated UTF-16LE strings
+                    if prop == 0x11 && data.first() == Some(&0) {
+                        let mut name = Vec::new();
+                        for ch in data[1..].chunks_exact(2) {
+                            let ch = u16::from_le_bytes([ch[0], ch[1]]);
+                            if ch != 0 {
+                                name.push(ch);
+                            } else {
+                                inspection.add_member(String::from_utf16_lossy(&name), max_members);
+                                name.clear();
+                                if inspection.members.len() >= num_files {
+                                    break;
+                                }
+                            }
+                        }
+                    }
+                }
+                break;
+            }
+            _ => break,
+        }
+    }
+
+    inspection
+}
+
+struct StreamsInfo {
+    pack_pos: u64,
+    pack_sizes: Vec<u64>,
+    folders: Vec<Folder>,
+}
+
+struct Folder {
+    coders: Vec<Coder>,
+    unpack_sizes: Vec<u64>,
+    has_crc: bool,
+    num_unpack_streams: u64,
+}
+
+struct Coder {
+    id: Vec<u8>,
+    properties: Vec<u8>,
+}
+
+impl StreamsInfo {
+    fn parse(reader: &mut Reader<'_>) -> Option<Self> {
+        let mut info = StreamsInfo {
+            pack_pos: 0,
+            pack_sizes: Vec::new(),
+            folders: Vec::new(),
+        };
+
+        loop {
+            match reader.read_u8()? {
+                // End
+                0x00 => break,
+                // Pack info
+                0x06 => {
+                    info.pack_pos = reader.read_number()?;
+                    let num_pack_streams = reader.read_count()?;
+                    loop {
+                        match reader.read_u8()? {
+                            0x00 => break,
+                            0x09 => {
+                                for _ in 0..num_pack_streams {
+                                    info.pack_sizes.push(reader.read_number()?);
+                                }
+                            }
+                            0x0A => {
+                                reader.read_digests(num_pack_streams)?;
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                // Unpack info
+                0x07 => {
+                    if reader.read_u8()? != 0x0B {
+                        return None;
+                    }
+                    let num_folders = reader.read_count()?;
+                    if reader.read_u8()? != 0 {
+                        return None;
+                    }
+                    for _ in 0..num_folders.min(1024) {
+                        info.folders.push(Folder::parse(reader)?);
+                    }
+                    if reader.read_u8()? != 0x0C {
+                        return None;
+                    }
+                    for folder in &mut info.folders {
+                        for size in &mut folder.unpack_sizes {
+                            *size = reader.read_number()?;
+                        }
+                    }
+                    loop {
+                        match reader.read_u8()? {
+                            0x00 => break,
+                            0x0A => {
+                                for (folder, has_crc) in info
+                                    .folders
+                                    .iter_mut()
+                                    .zip(reader.read_digests(num_folders)?)
+                                {
+                                    folder.has_crc = has_crc;
+                                }
+                            }
+                            _ => return None,
+                        }
+                    }
+                }
+                // Substreams info
+                0x08 => loop {
+                    match reader.read_u8()? {
+                        0x00 => break,
+                        0x0D => {
+                            for folder in &mut info.folders {
+                                folder.num_unpack_streams = reader.read_count()? as u64;
+                            }
+                        }
+                        0x09 => {
+                            for folder in &info.folders {
+                                for _ in 1..folder.num_unpack_streams {
+                                    reader.read_number()?;
+                                }
+                            }
+                        }
+                        0x0A => {
+                            let num_digests =
+                                info.folders.iter().try_fold(0usize, |total, folder| {
+                                    if folder.num_unpack_streams == 1 && folder.has_crc {
+                                        Some(total)
+                                    } else {
+                                        total.checked_add(folder.num_unpack_streams as usize)
+                                    }
+                                })?;
+                            reader.read_digests(num_digests)?;
+                        }
+                        _ => return None,
+                    }
+                },
+                _ => return None,
+            }
+        }
+
+        Some(info)
+    }
+
+    fn is_encrypted(&self) -> bool {
+        self.folders.iter().any(|folder| {
+            folder
+                .coders
+                .iter()
+                .any(|coder| coder.id == [0x06, 0xF1, 0x07, 0x01])
+        })
+    }
+
+    fn decode_first(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let folder = self.folders.first()?;
+        let [coder] = folder.coders.as_slice() else {
+            return None;
+        };
+        let unpack_size = *folder.unpack_sizes.first()?;
+        let pack_size = *self.pack_sizes.first()?;
+        if unpack_size > MAX_HEADER_SIZE {
+            return None;
+        }
+        let start = 32usize.checked_add(self.pack_pos as usize)?;
+        let packed = bytes.get(start..start.checked_add(pack_size as usize)?)?;
+
+        let mut output = Vec::with_capacity(unpack_size as usize);
+        match coder.id.as_slice() {
+            // Copy
+            [0x00] => output.extend_from_slice(packed),
+            // LZMA
+            [0x03, 0x01, 0x01] => {
+                let mut input = coder.properties.clone();
+                input.extend_from_slice(packed);
+                lzma_rs::lzma_decompress_with_options(
+                    &mut Cursor::new(input),
+                    &mut output,
+                    &lzma_rs::decompress::Options {
+                        unpacked_size: lzma_rs::decompress::UnpackedSize::UseProvided(Some(
+                            unpack_size,
+                        )),
+                        ..Default::default()
+                    },
+                )
+                .ok()?;
+            }
+            // LZMA2
+            [0x21] => {
+                lzma_rs::lzma2_decompress(
+                    &mut Cursor::new(packed),
+                    &mut LimitedWriter {
+                        output: &mut output,
+                        limit: MAX_HEADER_SIZE as usize,
+                    },
+                )
+                .ok()?;
+            }
+            _ => return None,
+        }
+
+        Some(output)
+    }
+}
+
+impl Folder {
+    fn parse(reader: &mut Reader<'_>) -> Option<Self> {
+        let num_coders = reader.read_number()? as usize;
+        let mut coders = Vec::with_capacity(num_coders.min(8));
+        let mut total_in = 0u64;
+        let mut total_out = 0u64;
+
+        for _ in 0..num_coders.min(64) {
+            let flags = reader.read_u8()?;
+            let id = reader.read_bytes((flags & 0x0F) as usize)?.to_vec();
+            let (num_in, num_out) = if flags & 0x10 != 0 {
+                (reader.read_number()?, reader.read_number()?)
+            } else {
+                (1, 1)
+            };
+            total_in = total_in.checked_add(num_in)?;
+            total_out = total_out.checked_add(num_out)?;
+            let properties = if flags & 0x20 != 0 {
+                let size = reader.read_number()? as usize;
+                reader.read_bytes(size)?.to_vec()
+            } else {
+                Vec::new()
+            };
+            coders.push(Coder { id, properties });
+        }
+
+        // Bind pairs and packed streams
+        let num_bind_pairs = total_out.checked_sub(1)?;
+        for _ in 0..num_bind_pairs.min(64) {
+            reader.read_number()?;
+            reader.read_number()?;
+        }
+        let num_packed = total_in.checked_sub(num_bind_pairs)?;
+        if num_packed > 1 {
+            for _ in 0..num_packed.min(64) {
+                reader.read_number()?;
+            }
+        }
+
+        Some(Folder {
+            coders,
+            unpack_sizes: vec![0; total_out.min(64) as usize],
+            has_crc: false,
+            num_unpack_streams: 1,
+        })
+    }
+}
+
+struct Reader<'x> {
+    bytes: &'x [u8],
+    pos: usize,
+}
+
+impl<'x> Reader<'x> {
+    fn new(bytes: &'x [u8], pos: usize) -> Self {
+        Reader { bytes, pos }
+    }
+
+    fn read_u8(&mut self) -> Option<u8> {
+        let ch = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(ch)
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Option<&'x [u8]> {
+        let bytes = self.bytes.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.read_bytes(len).map(|_| ())
+    }
+
+    fn read_u64(&mut self) -> Option<u64> {
+        self.read_bytes(8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    // RAR5 variable length integer
+    fn read_vint(&mut self) -> Option<u64> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let ch = self.read_u8()?;
+            value |= ((ch & 0x7F) as u64) << shift;
+            if ch & 0x80 == 0 {
+                return Some(value);
+            }
+        }
+        None
+    }
+
+    // 7z variable length integer
+    fn read_number(&mut self) -> Option<u64> {
+        let first = self.read_u8()?;
+        let mut mask = 0x80u8;
+        let mut value = 0u64;
+        for idx in 0..8 {
+            if first & mask == 0 {
+                return Some(value | (((first & mask.wrapping_sub(1)) as u64) << (8 * idx)));
+            }
+            value |= (self.read_u8()? as u64) << (8 * idx);
+            mask >>= 1;
+        }
+        Some(value)
+    }
+
+    // 7z item count, rejected if it can't fit in the remaining bytes
+    fn read_count(&mut self) -> Option<usize> {
+        usize::try_from(self.read_number()?)
+            .ok()
+            .filter(|count| *count <= self.remaining().saturating_mul(8))
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len().saturating_sub(self.pos)
+    }
+
+    // 7z digests, returns which entries have a CRC defined
+    fn read_digests(&mut self, num_items: usize) -> Option<Vec<bool>> {
+        if num_items > self.remaining().saturating_mul(8) {
+            return None;
+        }
+        let defined = if self.read_u8()? != 0 {
+            vec![true; num_items]
+        } else {
+            let bits = self.read_bytes(num_items.div_ceil(8))?;
+            (0..num_items)
+                .map(|idx| bits[idx / 8] & (0x80 >> (idx % 8)) != 0)
+                .collect()
+        };
+        self.skip(defined.iter().filter(|defined| **defined).count() * 4)?;
+        Some(defined)
+    }
+}
+
+// Stops decompression once the output exceeds the limit
+struct LimitedWriter<'x> {
+    output: &'x mut Vec<u8>,
+    limit: usize,
+}
+
+impl Write for LimitedWriter<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        if self.output.len() + buf.len() > self.limit {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "Decompressed size limit exceeded",
+            ));
+        }
+        self.output.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+fn rfind(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .rposition(|window| window == needle)
+}
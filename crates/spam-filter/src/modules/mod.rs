@@ -10,10 +10,14 @@ use store::{
     Deserialize, Value,
 };
 
+pub mod antivirus;
 pub mod bayes;
 pub mod dnsbl;
 pub mod expression;
+pub mod ftrl;
+pub mod fuzzy;
 pub mod html;
+pub mod inspect;
 pub mod pyzor;
 pub mod sanitize;
 
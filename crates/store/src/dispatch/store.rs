@@ -340,6 +340,24 @@ impl Store {
         )
         .await
         .caused_by(trc::location!())?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine { id: 0, expires: 0 })),
+            ValueKey::from(ValueClass::Report(ReportClass::Quarantine {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await
+        .caused_by(trc::location!())?;
+        self.delete_range(
+            ValueKey::from(ValueClass::Report(ReportClass::Tracking { id: 0, expires: 0 })),
+            ValueKey::from(ValueClass::Report(ReportClass::Tracking {
+                id: u64::MAX,
+                expires: now,
+            })),
+        )
+        .await
+        .caused_by(trc::location!())?;
 
         match self {
             #[cfg(feature = "sqlite")]
@@ -377,6 +377,12 @@ impl<T: ResolveId> ValueClass<T> {
                 ReportClass::Arf { id, expires } => {
                     serializer.write(2u8).write(*expires).write(*id)
                 }
+                ReportClass::Quarantine { id, expires } => {
+                    serializer.write(3u8).write(*expires).write(*id)
+                }
+                ReportClass::Tracking { id, expires } => {
+                    serializer.write(4u8).write(*expires).write(*id)
+                }
             },
             ValueClass::Telemetry(telemetry) => match telemetry {
                 TelemetryClass::Span { span_id } => serializer.write(*span_id),
@@ -580,7 +586,7 @@ impl<T> ValueClass<T> {
     pub fn subspace(&self, collection: u8) -> u8 {
         match self {
             ValueClass::Property(field) => {
-                if *field == 84 && collection == 1 {
+                if (*field == 84 && collection == 1) || (*field == 105 && collection == 7) {
                     SUBSPACE_COUNTER
                 } else {
                     SUBSPACE_PROPERTY
@@ -629,6 +635,7 @@ impl<T> ValueClass<T> {
             | ValueClass::InMemory(InMemoryClass::Counter(_))
             | ValueClass::Queue(QueueClass::QuotaCount(_) | QueueClass::QuotaSize(_)) => true,
             ValueClass::Property(84) if collection == 1 => true, // TODO: Find a more elegant way to do this
+            ValueClass::Property(105) if collection == 7 => true,
             _ => false,
         }
     }
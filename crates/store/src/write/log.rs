@@ -17,7 +17,7 @@ pub struct ChangeLogBuilder {
     pub changes: VecMap<u8, Changes>,
 }
 
-#[derive(Default, Debug)]
+#[derive(Default, Debug, Clone)]
 pub struct Changes {
     pub inserts: AHashSet<u64>,
     pub updates: AHashSet<u64>,
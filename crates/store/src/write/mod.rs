@@ -208,6 +208,8 @@ pub enum ReportClass {
     Tls { id: u64, expires: u64 },
     Dmarc { id: u64, expires: u64 },
     Arf { id: u64, expires: u64 },
+    Quarantine { id: u64, expires: u64 },
+    Tracking { id: u64, expires: u64 },
 }
 
 #[derive(Debug, PartialEq, Clone, Eq, Hash)]
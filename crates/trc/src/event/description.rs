@@ -52,6 +52,11 @@ impl EventType {
             EventType::MessageIngest(event) => event.description(),
             EventType::Security(event) => event.description(),
             EventType::Ai(event) => event.description(),
+            EventType::Dav(event) => event.description(),
+            EventType::Itip(event) => event.description(),
+            EventType::Quarantine(event) => event.description(),
+            EventType::MailingList(event) => event.description(),
+            EventType::DnsSync(event) => event.description(),
         }
     }
 
@@ -100,6 +105,11 @@ impl EventType {
             EventType::MessageIngest(event) => event.explain(),
             EventType::Security(event) => event.explain(),
             EventType::Ai(event) => event.explain(),
+            EventType::Dav(event) => event.explain(),
+            EventType::Itip(event) => event.explain(),
+            EventType::Quarantine(event) => event.explain(),
+            EventType::MailingList(event) => event.explain(),
+            EventType::DnsSync(event) => event.explain(),
         }
     }
 }
@@ -250,6 +260,14 @@ impl ImapEvent {
             ImapEvent::ConnectionStart => "IMAP connection started",
             ImapEvent::ConnectionEnd => "IMAP connection ended",
             ImapEvent::GetQuota => "IMAP GETQUOTA command",
+            ImapEvent::Notify => "IMAP NOTIFY command",
+            ImapEvent::GetMetadata => "IMAP GETMETADATA command",
+            ImapEvent::SetMetadata => "IMAP SETMETADATA command",
+            ImapEvent::Compress => "IMAP COMPRESS command",
+            ImapEvent::Replace => "IMAP REPLACE command",
+            ImapEvent::ResetKey => "IMAP RESETKEY command",
+            ImapEvent::GenUrlAuth => "IMAP GENURLAUTH command",
+            ImapEvent::UrlFetch => "IMAP URLFETCH command",
         }
     }
 
@@ -292,6 +310,14 @@ impl ImapEvent {
             ImapEvent::ConnectionStart => "IMAP connection started",
             ImapEvent::ConnectionEnd => "IMAP connection ended",
             ImapEvent::GetQuota => "Client requested mailbox quota",
+            ImapEvent::Notify => "Client changed notification settings",
+            ImapEvent::GetMetadata => "Client requested mailbox or server annotations",
+            ImapEvent::SetMetadata => "Client modified mailbox or server annotations",
+            ImapEvent::Compress => "Client enabled compression on the connection",
+            ImapEvent::Replace => "Client replaced a message",
+            ImapEvent::ResetKey => "Client reset mailbox access keys",
+            ImapEvent::GenUrlAuth => "Client generated authorized URLs",
+            ImapEvent::UrlFetch => "Client fetched message parts by URL",
         }
     }
 }
@@ -477,6 +503,11 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "Unsupported parameter",
             SmtpEvent::SyntaxError => "Syntax error",
             SmtpEvent::RequestTooLarge => "Request too large",
+            SmtpEvent::Burl => "BURL command",
+            SmtpEvent::BurlFailed => "BURL URL resolution failed",
+            SmtpEvent::SrsRewritten => "Sender rewritten using SRS",
+            SmtpEvent::SrsReversed => "SRS address reversed",
+            SmtpEvent::SrsInvalid => "Invalid SRS address",
             SmtpEvent::ConnectionStart => "SMTP connection started",
             SmtpEvent::ConnectionEnd => "SMTP connection ended",
         }
@@ -599,6 +630,15 @@ impl SmtpEvent {
             SmtpEvent::UnsupportedParameter => "The command contained an unsupported parameter",
             SmtpEvent::SyntaxError => "The command contained a syntax error",
             SmtpEvent::RequestTooLarge => "The request was too large",
+            SmtpEvent::Burl => "The remote client submitted message data by URL",
+            SmtpEvent::BurlFailed => "The URL submitted with BURL could not be resolved",
+            SmtpEvent::SrsRewritten => {
+                "The envelope sender of a forwarded message was rewritten using SRS"
+            }
+            SmtpEvent::SrsReversed => {
+                "A bounce to an SRS address was rewritten to the original sender"
+            }
+            SmtpEvent::SrsInvalid => "An SRS address failed hash or timestamp validation",
             SmtpEvent::ConnectionStart => "A new SMTP connection was started",
             SmtpEvent::ConnectionEnd => "The SMTP connection was ended",
             SmtpEvent::StartTlsAlready => "TLS is already active",
@@ -620,6 +660,7 @@ impl DeliveryEvent {
             DeliveryEvent::IpLookupFailed => "IP address lookup failed",
             DeliveryEvent::NullMx => "Null MX record found",
             DeliveryEvent::Connect => "Connecting to remote server",
+            DeliveryEvent::ConnectionReused => "Reusing connection to remote server",
             DeliveryEvent::ConnectError => "Connection error",
             DeliveryEvent::MissingOutboundHostname => "Missing outbound hostname in configuration",
             DeliveryEvent::GreetingFailed => "SMTP greeting failed",
@@ -641,6 +682,8 @@ impl DeliveryEvent {
             DeliveryEvent::ImplicitTlsError => "Implicit TLS error",
             DeliveryEvent::ConcurrencyLimitExceeded => "Concurrency limit exceeded",
             DeliveryEvent::RateLimitExceeded => "Rate limit exceeded",
+            DeliveryEvent::ThrottleDetected => "Remote host is throttling deliveries",
+            DeliveryEvent::ThrottleRecovered => "Delivery throttling relaxed",
             DeliveryEvent::DoubleBounce => "Discarding message after double bounce",
             DeliveryEvent::DsnSuccess => "DSN success notification",
             DeliveryEvent::DsnTempFail => "DSN temporary failure notification",
@@ -663,6 +706,7 @@ impl DeliveryEvent {
             DeliveryEvent::IpLookupFailed => "Failed to look up IP address for the domain",
             DeliveryEvent::NullMx => "The domain has a null MX record, delivery is impossible",
             DeliveryEvent::Connect => "Connecting to the remote server",
+            DeliveryEvent::ConnectionReused => "An idle connection to the remote server was reused",
             DeliveryEvent::ConnectError => "Error connecting to the remote server",
             DeliveryEvent::MissingOutboundHostname => {
                 "The outbound hostname is missing in the configuration"
@@ -694,6 +738,12 @@ impl DeliveryEvent {
                 "The concurrency limit was exceeded for the remote host"
             }
             DeliveryEvent::RateLimitExceeded => "The rate limit was exceeded for the remote host",
+            DeliveryEvent::ThrottleDetected => {
+                "The remote host responded with a rate limit error, delivery limits were reduced"
+            }
+            DeliveryEvent::ThrottleRecovered => {
+                "No rate limit errors were received recently, delivery limits were increased"
+            }
             DeliveryEvent::DoubleBounce => "The message was discarded after a double bounce",
             DeliveryEvent::DsnSuccess => "A success delivery status notification was created",
             DeliveryEvent::DsnTempFail => {
@@ -1020,6 +1070,10 @@ impl SpamEvent {
             SpamEvent::ClassifyError => "Not enough training data for spam filter",
             SpamEvent::Dnsbl => "DNSBL query",
             SpamEvent::DnsblError => "Error querying DNSBL",
+            SpamEvent::Antivirus => "Antivirus scan",
+            SpamEvent::AntivirusError => "Antivirus scan error",
+            SpamEvent::Fuzzy => "Fuzzy hash lookup",
+            SpamEvent::FuzzyTrain => "Training fuzzy hash database",
         }
     }
 
@@ -1034,6 +1088,10 @@ impl SpamEvent {
             SpamEvent::Pyzor => "Pyzor query successful",
             SpamEvent::Dnsbl => "The DNSBL query was successful",
             SpamEvent::DnsblError => "An error occurred while querying the DNSBL",
+            SpamEvent::Antivirus => "The message was scanned for malware",
+            SpamEvent::AntivirusError => "An error occurred while scanning the message for malware",
+            SpamEvent::Fuzzy => "The message fuzzy hashes were looked up in the local database",
+            SpamEvent::FuzzyTrain => "The message fuzzy hashes are being trained as spam or ham",
         }
     }
 }
@@ -1392,6 +1450,11 @@ impl DkimEvent {
             DkimEvent::SignatureExpired => "DKIM signature expired",
             DkimEvent::SignatureLength => "DKIM signature length issue",
             DkimEvent::SignerNotFound => "DKIM signer not found",
+            DkimEvent::KeyStaged => "DKIM key staged for rotation",
+            DkimEvent::KeyActivated => "DKIM key activated",
+            DkimEvent::KeyRetired => "DKIM key retired",
+            DkimEvent::KeyNotPublished => "DKIM key not published",
+            DkimEvent::RotationError => "DKIM key rotation error",
         }
     }
 
@@ -1415,6 +1478,17 @@ impl DkimEvent {
             DkimEvent::SignatureExpired => "The DKIM signature has expired",
             DkimEvent::SignatureLength => "The DKIM signature length is incorrect",
             DkimEvent::SignerNotFound => "The DKIM signer was not found",
+            DkimEvent::KeyStaged => {
+                "A new DKIM key was generated and is waiting for its DNS record to be published"
+            }
+            DkimEvent::KeyActivated => "Messages are now signed using the new DKIM key",
+            DkimEvent::KeyRetired => {
+                "The previous DKIM key reached the end of its grace period and was removed"
+            }
+            DkimEvent::KeyNotPublished => {
+                "The DNS record for the staged DKIM key could not be verified"
+            }
+            DkimEvent::RotationError => "An error occurred while rotating a DKIM key",
         }
     }
 }
@@ -1826,3 +1900,147 @@ impl AiEvent {
         }
     }
 }
+
+impl DavEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DavEvent::Propfind => "WebDAV PROPFIND",
+            DavEvent::Proppatch => "WebDAV PROPPATCH",
+            DavEvent::Report => "WebDAV REPORT",
+            DavEvent::Get => "WebDAV GET",
+            DavEvent::Put => "WebDAV PUT",
+            DavEvent::Delete => "WebDAV DELETE",
+            DavEvent::Mkcol => "WebDAV MKCOL",
+            DavEvent::Error => "WebDAV error",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            DavEvent::Propfind => "A WebDAV PROPFIND request was processed",
+            DavEvent::Proppatch => "A WebDAV PROPPATCH request was processed",
+            DavEvent::Report => "A WebDAV REPORT request was processed",
+            DavEvent::Get => "A WebDAV resource was retrieved",
+            DavEvent::Put => "A WebDAV resource was stored",
+            DavEvent::Delete => "A WebDAV resource was deleted",
+            DavEvent::Mkcol => "A WebDAV collection was created",
+            DavEvent::Error => "An error occurred while processing a WebDAV request",
+        }
+    }
+}
+
+impl ItipEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            ItipEvent::Accepted => "Scheduling request accepted",
+            ItipEvent::Declined => "Scheduling request declined",
+            ItipEvent::Cancelled => "Scheduled event cancelled",
+            ItipEvent::Error => "Invalid scheduling message",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            ItipEvent::Accepted => "A resource accepted an iTIP scheduling request",
+            ItipEvent::Declined => "A resource declined an iTIP scheduling request",
+            ItipEvent::Cancelled => "An event was removed from a resource calendar",
+            ItipEvent::Error => "An iTIP scheduling message could not be processed",
+        }
+    }
+}
+
+impl QuarantineEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            QuarantineEvent::Message => "Message quarantined",
+            QuarantineEvent::Release => "Quarantined message released",
+            QuarantineEvent::Delete => "Quarantined message deleted",
+            QuarantineEvent::Digest => "Quarantine digest sent",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            QuarantineEvent::Message => {
+                "The message was held in quarantine instead of being queued"
+            }
+            QuarantineEvent::Release => "A quarantined message was released for delivery",
+            QuarantineEvent::Delete => "A quarantined message was deleted",
+            QuarantineEvent::Digest => "A digest of quarantined messages was sent to a recipient",
+        }
+    }
+}
+
+impl MailingListEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            MailingListEvent::Posted => "Message distributed to list",
+            MailingListEvent::Held => "Message held for moderation",
+            MailingListEvent::Approved => "Held message approved",
+            MailingListEvent::Rejected => "Message rejected by list",
+            MailingListEvent::ConfirmationSent => "List confirmation request sent",
+            MailingListEvent::Subscribed => "List subscription added",
+            MailingListEvent::Unsubscribed => "List subscription removed",
+            MailingListEvent::Bounce => "List bounce received",
+            MailingListEvent::SubscriberDisabled => "List subscriber disabled",
+            MailingListEvent::LoopDetected => "List loop detected",
+            MailingListEvent::Error => "Invalid list request",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            MailingListEvent::Posted => {
+                "A message was distributed to the subscribers of a mailing list"
+            }
+            MailingListEvent::Held => {
+                "A message from a non-member was held for approval by the list moderators"
+            }
+            MailingListEvent::Approved => "A moderator approved a held message",
+            MailingListEvent::Rejected => {
+                "A message was rejected by the list or by one of its moderators"
+            }
+            MailingListEvent::ConfirmationSent => {
+                "A subscription or unsubscription confirmation request was sent"
+            }
+            MailingListEvent::Subscribed => "An address was subscribed to a mailing list",
+            MailingListEvent::Unsubscribed => "An address was unsubscribed from a mailing list",
+            MailingListEvent::Bounce => "A delivery failure was recorded for a list subscriber",
+            MailingListEvent::SubscriberDisabled => {
+                "A list subscriber was disabled after too many delivery failures"
+            }
+            MailingListEvent::LoopDetected => {
+                "A message already distributed by the list was received again"
+            }
+            MailingListEvent::Error => "A list command or token could not be processed",
+        }
+    }
+}
+
+impl DnsSyncEvent {
+    pub fn description(&self) -> &'static str {
+        match self {
+            DnsSyncEvent::RecordCreated => "DNS record created",
+            DnsSyncEvent::RecordDeleted => "DNS record deleted",
+            DnsSyncEvent::RecordConflict => "DNS record conflict",
+            DnsSyncEvent::ZoneSynced => "DNS zone synchronized",
+            DnsSyncEvent::Error => "DNS update error",
+        }
+    }
+
+    pub fn explain(&self) -> &'static str {
+        match self {
+            DnsSyncEvent::RecordCreated => {
+                "A DNS record was added to the zone through a dynamic update"
+            }
+            DnsSyncEvent::RecordDeleted => {
+                "A stale DNS record was removed from the zone through a dynamic update"
+            }
+            DnsSyncEvent::RecordConflict => {
+                "A DNS record not published by this server conflicts with an expected record"
+            }
+            DnsSyncEvent::ZoneSynced => "The DNS records of a domain were reconciled",
+            DnsSyncEvent::Error => "A DNS lookup or dynamic update failed",
+        }
+    }
+}
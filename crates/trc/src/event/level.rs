@@ -78,7 +78,15 @@ impl EventType {
                 | ImapEvent::Error
                 | ImapEvent::IdleStart
                 | ImapEvent::IdleStop
-                | ImapEvent::GetQuota => Level::Debug,
+                | ImapEvent::GetQuota
+                | ImapEvent::Notify
+                | ImapEvent::GetMetadata
+                | ImapEvent::SetMetadata
+                | ImapEvent::Compress
+                | ImapEvent::Replace
+                | ImapEvent::ResetKey
+                | ImapEvent::GenUrlAuth
+                | ImapEvent::UrlFetch => Level::Debug,
                 ImapEvent::RawInput | ImapEvent::RawOutput => Level::Trace,
             },
             EventType::ManageSieve(event) => match event {
@@ -156,6 +164,11 @@ impl EventType {
                 | SmtpEvent::InvalidParameter
                 | SmtpEvent::UnsupportedParameter
                 | SmtpEvent::SyntaxError
+                | SmtpEvent::Burl
+                | SmtpEvent::BurlFailed
+                | SmtpEvent::SrsRewritten
+                | SmtpEvent::SrsReversed
+                | SmtpEvent::SrsInvalid
                 | SmtpEvent::Error => Level::Debug,
                 SmtpEvent::MissingLocalHostname | SmtpEvent::RemoteIdNotFound => Level::Warn,
                 SmtpEvent::ConcurrencyLimitExceeded
@@ -259,7 +272,11 @@ impl EventType {
                 ArcEvent::SealerNotFound => Level::Warn,
             },
             EventType::Dkim(event) => match event {
-                DkimEvent::SignerNotFound => Level::Warn,
+                DkimEvent::SignerNotFound | DkimEvent::KeyNotPublished => Level::Warn,
+                DkimEvent::KeyStaged | DkimEvent::KeyActivated | DkimEvent::KeyRetired => {
+                    Level::Info
+                }
+                DkimEvent::RotationError => Level::Error,
                 _ => Level::Debug,
             },
             EventType::MailAuth(_) => Level::Debug,
@@ -343,7 +360,11 @@ impl EventType {
                 | SpamEvent::Classify
                 | SpamEvent::ClassifyError
                 | SpamEvent::TrainBalance
-                | SpamEvent::Dnsbl => Level::Debug,
+                | SpamEvent::Dnsbl
+                | SpamEvent::Antivirus
+                | SpamEvent::AntivirusError
+                | SpamEvent::Fuzzy
+                | SpamEvent::FuzzyTrain => Level::Debug,
             },
             EventType::Http(event) => match event {
                 HttpEvent::ConnectionStart | HttpEvent::ConnectionEnd => Level::Debug,
@@ -430,6 +451,7 @@ impl EventType {
                 | DeliveryEvent::IpLookupFailed
                 | DeliveryEvent::NullMx
                 | DeliveryEvent::Connect
+                | DeliveryEvent::ConnectionReused
                 | DeliveryEvent::ConnectError
                 | DeliveryEvent::GreetingFailed
                 | DeliveryEvent::EhloRejected
@@ -447,7 +469,9 @@ impl EventType {
                 | DeliveryEvent::DoubleBounce => Level::Info,
                 DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
+                | DeliveryEvent::ThrottleDetected
                 | DeliveryEvent::MissingOutboundHostname => Level::Warn,
+                DeliveryEvent::ThrottleRecovered => Level::Info,
                 DeliveryEvent::DsnSuccess
                 | DeliveryEvent::DsnTempFail
                 | DeliveryEvent::DsnPermFail => Level::Info,
@@ -532,6 +556,44 @@ impl EventType {
                 AiEvent::LlmResponse => Level::Trace,
                 AiEvent::ApiError => Level::Warn,
             },
+            EventType::Dav(event) => match event {
+                DavEvent::Propfind
+                | DavEvent::Proppatch
+                | DavEvent::Report
+                | DavEvent::Get
+                | DavEvent::Put
+                | DavEvent::Delete
+                | DavEvent::Mkcol => Level::Info,
+                DavEvent::Error => Level::Debug,
+            },
+            EventType::Itip(event) => match event {
+                ItipEvent::Accepted | ItipEvent::Declined | ItipEvent::Cancelled => Level::Info,
+                ItipEvent::Error => Level::Debug,
+            },
+            EventType::Quarantine(event) => match event {
+                QuarantineEvent::Message | QuarantineEvent::Release | QuarantineEvent::Delete => {
+                    Level::Info
+                }
+                QuarantineEvent::Digest => Level::Debug,
+            },
+            EventType::MailingList(event) => match event {
+                MailingListEvent::Posted
+                | MailingListEvent::Held
+                | MailingListEvent::Approved
+                | MailingListEvent::Rejected
+                | MailingListEvent::Subscribed
+                | MailingListEvent::Unsubscribed
+                | MailingListEvent::SubscriberDisabled => Level::Info,
+                MailingListEvent::ConfirmationSent
+                | MailingListEvent::Bounce
+                | MailingListEvent::LoopDetected
+                | MailingListEvent::Error => Level::Debug,
+            },
+            EventType::DnsSync(event) => match event {
+                DnsSyncEvent::RecordCreated | DnsSyncEvent::RecordDeleted => Level::Info,
+                DnsSyncEvent::ZoneSynced => Level::Debug,
+                DnsSyncEvent::Error | DnsSyncEvent::RecordConflict => Level::Warn,
+            },
         }
     }
 }
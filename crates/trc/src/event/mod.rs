@@ -650,6 +650,34 @@ impl NetworkEvent {
     }
 }
 
+impl DavEvent {
+    #[inline(always)]
+    pub fn into_err(self) -> Error {
+        Error::new(EventType::Dav(self))
+    }
+}
+
+impl ItipEvent {
+    #[inline(always)]
+    pub fn into_err(self) -> Error {
+        Error::new(EventType::Itip(self))
+    }
+}
+
+impl MailingListEvent {
+    #[inline(always)]
+    pub fn into_err(self) -> Error {
+        Error::new(EventType::MailingList(self))
+    }
+}
+
+impl DnsSyncEvent {
+    #[inline(always)]
+    pub fn into_err(self) -> Error {
+        Error::new(EventType::DnsSync(self))
+    }
+}
+
 impl Value {
     pub fn from_maybe_string(value: &[u8]) -> Self {
         if let Ok(value) = std::str::from_utf8(value) {
@@ -582,7 +582,10 @@ impl EventType {
                 | SpamEvent::TrainError
                 | SpamEvent::Classify
                 | SpamEvent::ClassifyError
-                | SpamEvent::DnsblError,
+                | SpamEvent::DnsblError
+                | SpamEvent::Antivirus
+                | SpamEvent::AntivirusError
+                | SpamEvent::FuzzyTrain,
             ) => true,
             EventType::PushSubscription(_) => true,
             EventType::Cluster(
@@ -631,6 +634,8 @@ impl EventType {
                 | DeliveryEvent::ImplicitTlsError
                 | DeliveryEvent::ConcurrencyLimitExceeded
                 | DeliveryEvent::RateLimitExceeded
+                | DeliveryEvent::ThrottleDetected
+                | DeliveryEvent::ThrottleRecovered
                 | DeliveryEvent::DoubleBounce
                 | DeliveryEvent::DsnSuccess
                 | DeliveryEvent::DsnTempFail
@@ -187,6 +187,11 @@ pub enum EventType {
     Telemetry(TelemetryEvent),
     Security(SecurityEvent),
     Ai(AiEvent),
+    Dav(DavEvent),
+    Itip(ItipEvent),
+    Quarantine(QuarantineEvent),
+    MailingList(MailingListEvent),
+    DnsSync(DnsSyncEvent),
 }
 
 #[event_type]
@@ -282,6 +287,14 @@ pub enum ImapEvent {
     Unsubscribe,
     Thread,
     GetQuota,
+    Notify,
+    GetMetadata,
+    SetMetadata,
+    Compress,
+    Replace,
+    ResetKey,
+    GenUrlAuth,
+    UrlFetch,
 
     // Errors
     Error,
@@ -432,6 +445,11 @@ pub enum SmtpEvent {
     UnsupportedParameter,
     SyntaxError,
     RequestTooLarge,
+    Burl,
+    BurlFailed,
+    SrsRewritten,
+    SrsReversed,
+    SrsInvalid,
 }
 
 #[event_type]
@@ -448,6 +466,7 @@ pub enum DeliveryEvent {
     NullMx,
     Connect,
     ConnectError,
+    ConnectionReused,
     MissingOutboundHostname,
     GreetingFailed,
     Ehlo,
@@ -468,6 +487,8 @@ pub enum DeliveryEvent {
     ImplicitTlsError,
     ConcurrencyLimitExceeded,
     RateLimitExceeded,
+    ThrottleDetected,
+    ThrottleRecovered,
     DoubleBounce,
     DsnSuccess,
     DsnTempFail,
@@ -609,6 +630,10 @@ pub enum SpamEvent {
     TrainError,
     Classify,
     ClassifyError,
+    Antivirus,
+    AntivirusError,
+    Fuzzy,
+    FuzzyTrain,
 }
 
 #[event_type]
@@ -770,6 +795,11 @@ pub enum DkimEvent {
     SignatureExpired,
     SignatureLength,
     SignerNotFound,
+    KeyStaged,
+    KeyActivated,
+    KeyRetired,
+    KeyNotPublished,
+    RotationError,
 }
 
 #[event_type]
@@ -950,6 +980,58 @@ pub enum AiEvent {
     ApiError,
 }
 
+#[event_type]
+pub enum DavEvent {
+    Propfind,
+    Proppatch,
+    Report,
+    Get,
+    Put,
+    Delete,
+    Mkcol,
+    Error,
+}
+
+#[event_type]
+pub enum ItipEvent {
+    Accepted,
+    Declined,
+    Cancelled,
+    Error,
+}
+
+#[event_type]
+pub enum QuarantineEvent {
+    Message,
+    Release,
+    Delete,
+    Digest,
+}
+
+#[event_type]
+pub enum MailingListEvent {
+    Posted,
+    Held,
+    Approved,
+    Rejected,
+    ConfirmationSent,
+    Subscribed,
+    Unsubscribed,
+    Bounce,
+    SubscriberDisabled,
+    LoopDetected,
+    Error,
+}
+
+#[event_type]
+pub enum DnsSyncEvent {
+    RecordCreated,
+    RecordDeleted,
+    RecordConflict,
+    ZoneSynced,
+    Error,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub enum MetricType {
     ServerMemory,
@@ -864,6 +864,63 @@ impl EventType {
             EventType::Spam(SpamEvent::Dnsbl) => 562,
             EventType::Spam(SpamEvent::DnsblError) => 563,
             EventType::Spam(SpamEvent::Pyzor) => 564,
+            EventType::Imap(ImapEvent::Notify) => 565,
+            EventType::Imap(ImapEvent::GetMetadata) => 566,
+            EventType::Imap(ImapEvent::SetMetadata) => 567,
+            EventType::Imap(ImapEvent::Compress) => 568,
+            EventType::Imap(ImapEvent::Replace) => 569,
+            EventType::Imap(ImapEvent::ResetKey) => 570,
+            EventType::Imap(ImapEvent::GenUrlAuth) => 571,
+            EventType::Imap(ImapEvent::UrlFetch) => 572,
+            EventType::Smtp(SmtpEvent::Burl) => 573,
+            EventType::Smtp(SmtpEvent::BurlFailed) => 574,
+            EventType::Dav(DavEvent::Propfind) => 575,
+            EventType::Dav(DavEvent::Proppatch) => 576,
+            EventType::Dav(DavEvent::Report) => 577,
+            EventType::Dav(DavEvent::Get) => 578,
+            EventType::Dav(DavEvent::Put) => 579,
+            EventType::Dav(DavEvent::Delete) => 580,
+            EventType::Dav(DavEvent::Mkcol) => 581,
+            EventType::Dav(DavEvent::Error) => 582,
+            EventType::Itip(ItipEvent::Accepted) => 583,
+            EventType::Itip(ItipEvent::Declined) => 584,
+            EventType::Itip(ItipEvent::Cancelled) => 585,
+            EventType::Itip(ItipEvent::Error) => 586,
+            EventType::Quarantine(QuarantineEvent::Message) => 587,
+            EventType::Quarantine(QuarantineEvent::Release) => 588,
+            EventType::Quarantine(QuarantineEvent::Delete) => 589,
+            EventType::Quarantine(QuarantineEvent::Digest) => 590,
+            EventType::Delivery(DeliveryEvent::ConnectionReused) => 591,
+            EventType::Smtp(SmtpEvent::SrsRewritten) => 592,
+            EventType::Smtp(SmtpEvent::SrsReversed) => 593,
+            EventType::Smtp(SmtpEvent::SrsInvalid) => 594,
+            EventType::MailingList(MailingListEvent::Posted) => 595,
+            EventType::MailingList(MailingListEvent::Held) => 596,
+            EventType::MailingList(MailingListEvent::Approved) => 597,
+            EventType::MailingList(MailingListEvent::Rejected) => 598,
+            EventType::MailingList(MailingListEvent::ConfirmationSent) => 599,
+            EventType::MailingList(MailingListEvent::Subscribed) => 600,
+            EventType::MailingList(MailingListEvent::Unsubscribed) => 601,
+            EventType::MailingList(MailingListEvent::Bounce) => 602,
+            EventType::MailingList(MailingListEvent::SubscriberDisabled) => 603,
+            EventType::MailingList(MailingListEvent::LoopDetected) => 604,
+            EventType::MailingList(MailingListEvent::Error) => 605,
+            EventType::Dkim(DkimEvent::KeyStaged) => 606,
+            EventType::Dkim(DkimEvent::KeyActivated) => 607,
+            EventType::Dkim(DkimEvent::KeyRetired) => 608,
+            EventType::Dkim(DkimEvent::KeyNotPublished) => 609,
+            EventType::Dkim(DkimEvent::RotationError) => 610,
+            EventType::DnsSync(DnsSyncEvent::RecordCreated) => 611,
+            EventType::DnsSync(DnsSyncEvent::RecordDeleted) => 612,
+            EventType::DnsSync(DnsSyncEvent::ZoneSynced) => 613,
+            EventType::DnsSync(DnsSyncEvent::Error) => 614,
+            EventType::Delivery(DeliveryEvent::ThrottleDetected) => 615,
+            EventType::Delivery(DeliveryEvent::ThrottleRecovered) => 616,
+            EventType::Spam(SpamEvent::Antivirus) => 617,
+            EventType::Spam(SpamEvent::AntivirusError) => 618,
+            EventType::Spam(SpamEvent::Fuzzy) => 619,
+            EventType::Spam(SpamEvent::FuzzyTrain) => 620,
+            EventType::DnsSync(DnsSyncEvent::RecordConflict) => 621,
             EventType::Queue(QueueEvent::BackPressure) => 48,
             EventType::Imap(ImapEvent::GetQuota) => 57,
         }
@@ -1467,6 +1524,63 @@ impl EventType {
             562 => Some(EventType::Spam(SpamEvent::Dnsbl)),
             563 => Some(EventType::Spam(SpamEvent::DnsblError)),
             564 => Some(EventType::Spam(SpamEvent::Pyzor)),
+            565 => Some(EventType::Imap(ImapEvent::Notify)),
+            566 => Some(EventType::Imap(ImapEvent::GetMetadata)),
+            567 => Some(EventType::Imap(ImapEvent::SetMetadata)),
+            568 => Some(EventType::Imap(ImapEvent::Compress)),
+            569 => Some(EventType::Imap(ImapEvent::Replace)),
+            570 => Some(EventType::Imap(ImapEvent::ResetKey)),
+            571 => Some(EventType::Imap(ImapEvent::GenUrlAuth)),
+            572 => Some(EventType::Imap(ImapEvent::UrlFetch)),
+            573 => Some(EventType::Smtp(SmtpEvent::Burl)),
+            574 => Some(EventType::Smtp(SmtpEvent::BurlFailed)),
+            575 => Some(EventType::Dav(DavEvent::Propfind)),
+            576 => Some(EventType::Dav(DavEvent::Proppatch)),
+            577 => Some(EventType::Dav(DavEvent::Report)),
+            578 => Some(EventType::Dav(DavEvent::Get)),
+            579 => Some(EventType::Dav(DavEvent::Put)),
+            580 => Some(EventType::Dav(DavEvent::Delete)),
+            581 => Some(EventType::Dav(DavEvent::Mkcol)),
+            582 => Some(EventType::Dav(DavEvent::Error)),
+            583 => Some(EventType::Itip(ItipEvent::Accepted)),
+            584 => Some(EventType::Itip(ItipEvent::Declined)),
+            585 => Some(EventType::Itip(ItipEvent::Cancelled)),
+            586 => Some(EventType::Itip(ItipEvent::Error)),
+            587 => Some(EventType::Quarantine(QuarantineEvent::Message)),
+            588 => Some(EventType::Quarantine(QuarantineEvent::Release)),
+            589 => Some(EventType::Quarantine(QuarantineEvent::Delete)),
+            590 => Some(EventType::Quarantine(QuarantineEvent::Digest)),
+            591 => Some(EventType::Delivery(DeliveryEvent::ConnectionReused)),
+            592 => Some(EventType::Smtp(SmtpEvent::SrsRewritten)),
+            593 => Some(EventType::Smtp(SmtpEvent::SrsReversed)),
+            594 => Some(EventType::Smtp(SmtpEvent::SrsInvalid)),
+            595 => Some(EventType::MailingList(MailingListEvent::Posted)),
+            596 => Some(EventType::MailingList(MailingListEvent::Held)),
+            597 => Some(EventType::MailingList(MailingListEvent::Approved)),
+            598 => Some(EventType::MailingList(MailingListEvent::Rejected)),
+            599 => Some(EventType::MailingList(MailingListEvent::ConfirmationSent)),
+            600 => Some(EventType::MailingList(MailingListEvent::Subscribed)),
+            601 => Some(EventType::MailingList(MailingListEvent::Unsubscribed)),
+            602 => Some(EventType::MailingList(MailingListEvent::Bounce)),
+            603 => Some(EventType::MailingList(MailingListEvent::SubscriberDisabled)),
+            604 => Some(EventType::MailingList(MailingListEvent::LoopDetected)),
+            605 => Some(EventType::MailingList(MailingListEvent::Error)),
+            606 => Some(EventType::Dkim(DkimEvent::KeyStaged)),
+            607 => Some(EventType::Dkim(DkimEvent::KeyActivated)),
+            608 => Some(EventType::Dkim(DkimEvent::KeyRetired)),
+            609 => Some(EventType::Dkim(DkimEvent::KeyNotPublished)),
+            610 => Some(EventType::Dkim(DkimEvent::RotationError)),
+            611 => Some(EventType::DnsSync(DnsSyncEvent::RecordCreated)),
+            612 => Some(EventType::DnsSync(DnsSyncEvent::RecordDeleted)),
+            613 => Some(EventType::DnsSync(DnsSyncEvent::ZoneSynced)),
+            614 => Some(EventType::DnsSync(DnsSyncEvent::Error)),
+            615 => Some(EventType::Delivery(DeliveryEvent::ThrottleDetected)),
+            616 => Some(EventType::Delivery(DeliveryEvent::ThrottleRecovered)),
+            617 => Some(EventType::Spam(SpamEvent::Antivirus)),
+            618 => Some(EventType::Spam(SpamEvent::AntivirusError)),
+            619 => Some(EventType::Spam(SpamEvent::Fuzzy)),
+            620 => Some(EventType::Spam(SpamEvent::FuzzyTrain)),
+            621 => Some(EventType::DnsSync(DnsSyncEvent::RecordConflict)),
             48 => Some(EventType::Queue(QueueEvent::BackPressure)),
             57 => Some(EventType::Imap(ImapEvent::GetQuota)),
             _ => None,
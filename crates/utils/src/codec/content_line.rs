@@ -0,0 +1,201 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+// vCard (RFC 6350) and iCalendar (RFC 5545) share the same content line
+// syntax, only the subset required to map them to JSContact and JSCalendar
+// objects is implemented here.
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentLine {
+    pub name: String,
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl ContentLine {
+    pub fn parse_all(text: &str) -> Vec<ContentLine> {
+        let mut lines = Vec::new();
+        let mut current = String::new();
+
+        for line in text.split('\n') {
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            if let Some(folded) = line.strip_prefix([' ', '\t']) {
+                current.push_str(folded);
+            } else {
+                if let Some(line) = ContentLine::parse(&current) {
+                    lines.push(line);
+                }
+                current.clear();
+                current.push_str(line);
+            }
+        }
+        if let Some(line) = ContentLine::parse(&current) {
+            lines.push(line);
+        }
+
+        lines
+    }
+
+    pub fn parse(line: &str) -> Option<ContentLine> {
+        // Split the name and parameters from the value, skipping quoted colons
+        let mut in_quotes = false;
+        let mut params_start = None;
+        let mut value_start = None;
+        for (pos, ch) in line.char_indices() {
+            match ch {
+                '"' => in_quotes = !in_quotes,
+                ';' if !in_quotes && params_start.is_none() => params_start = Some(pos),
+                ':' if !in_quotes => {
+                    value_start = Some(pos);
+                    break;
+                }
+                _ => (),
+            }
+        }
+        let value_start = value_start?;
+        let name_end = params_start.unwrap_or(value_start);
+
+        // Group prefixes are discarded
+        let name = line[..name_end]
+            .rsplit('.')
+            .next()
+            .unwrap_or_default()
+            .trim()
+            .to_ascii_uppercase();
+        if name.is_empty() {
+            return None;
+        }
+
+        let mut params = Vec::new();
+        if name_end < value_start {
+            let mut param = String::new();
+            in_quotes = false;
+            for ch in line[name_end + 1..value_start].chars().chain([';']) {
+                match ch {
+                    '"' => in_quotes = !in_quotes,
+                    ';' if !in_quotes => {
+                        if let Some((key, value)) = param.split_once('=') {
+                            params.push((key.trim().to_ascii_uppercase(), value.to_string()));
+                        } else if !param.is_empty() {
+                            params.push((param.trim().to_ascii_uppercase(), String::new()));
+                        }
+                        param.clear();
+                    }
+                    _ => param.push(ch),
+                }
+            }
+        }
+
+        Some(ContentLine {
+            name,
+            params,
+            value: line[value_start + 1..].to_string(),
+        })
+    }
+
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    pub fn text(&self) -> String {
+        unescape_text(&self.value)
+    }
+
+    pub fn is(&self, name: &str, value: &str) -> bool {
+        self.name == name && self.value.eq_ignore_ascii_case(value)
+    }
+
+    // Structured values use unescaped semicolons as component separators
+    pub fn components(&self) -> Vec<String> {
+        let mut components = Vec::new();
+        let mut component = String::new();
+        let mut is_escaped = false;
+        for ch in self.value.chars() {
+            match ch {
+                ';' if !is_escaped => {
+                    components.push(unescape_text(&component));
+                    component.clear();
+                }
+                _ => {
+                    is_escaped = ch == '\\' && !is_escaped;
+                    component.push(ch);
+                }
+            }
+        }
+        components.push(unescape_text(&component));
+        components
+    }
+}
+
+pub fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch == '\\' {
+            match chars.next() {
+                Some('n' | 'N') => result.push('\n'),
+                Some(ch) => result.push(ch),
+                None => result.push('\\'),
+            }
+        } else {
+            result.push(ch);
+        }
+    }
+
+    result
+}
+
+pub fn escape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '\\' | ';' | ',' => {
+                result.push('\\');
+                result.push(ch);
+            }
+            '\n' => result.push_str("\\n"),
+            '\r' => (),
+            _ => result.push(ch),
+        }
+    }
+    result
+}
+
+// Lines longer than 75 octets are folded without splitting UTF-8 sequences
+pub fn write_line(buf: &mut String, name: &str, params: &[(&str, &str)], value: &str) {
+    let mut line = String::with_capacity(name.len() + value.len() + 2);
+    line.push_str(name);
+    for (param, param_value) in params {
+        line.push(';');
+        line.push_str(param);
+        line.push('=');
+        if param_value.contains([':', ';', ',']) {
+            line.push('"');
+            line.push_str(param_value);
+            line.push('"');
+        } else {
+            line.push_str(param_value);
+        }
+    }
+    line.push(':');
+    line.push_str(value);
+
+    let mut line_len = 0;
+    for ch in line.chars() {
+        let ch_len = ch.len_utf8();
+        if line_len + ch_len > 75 {
+            buf.push_str("\r\n ");
+            line_len = 1;
+        }
+        buf.push(ch);
+        line_len += ch_len;
+    }
+    buf.push_str("\r\n");
+}
@@ -5,4 +5,5 @@
  */
 
 pub mod base32_custom;
+pub mod content_line;
 pub mod leb128;
@@ -0,0 +1,139 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use imap_proto::ResponseType;
+
+use crate::jmap::delivery::SmtpConnection;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running BURL tests...");
+
+    // Store a message to be submitted by URL
+    imap.send("CREATE Outbox").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("LIST \"\" \"*\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let message = concat!(
+        "From: jdoe@example.com\r\n",
+        "To: jdoe@example.com\r\n",
+        "Subject: Sent by reference\r\n\r\n",
+        "This message was submitted using BURL.\r\n"
+    );
+    imap.send(&format!(
+        "APPEND Outbox {{{}+}}\r\n{message}",
+        message.len()
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // BURL is only available to authenticated clients
+    let mut smtp = SmtpConnection::connect_smtp(11587).await;
+    smtp.ehlo().await.assert_count("BURL", 0);
+    smtp.send("BURL \"/Outbox/;UID=1\" LAST").await;
+    smtp.read(1, 5).await.assert_contains("530 5.7.0");
+    smtp.send("AUTH PLAIN AGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    smtp.read(1, 2).await;
+    smtp.ehlo().await.assert_contains("BURL imap");
+
+    // Submit the stored message
+    let inbox_count = inbox_messages(imap).await;
+    smtp.mail_from("jdoe@example.com", 2).await;
+    smtp.rcpt_to("jdoe@example.com", 2).await;
+    smtp.send("BURL imap://jdoe%40example.com@example.com/Outbox/;UID=1 LAST")
+        .await;
+    smtp.read(1, 2).await;
+    wait_for_delivery(imap, inbox_count + 1).await;
+
+    // Invalid URLs are rejected
+    smtp.mail_from("jdoe@example.com", 2).await;
+    smtp.rcpt_to("jdoe@example.com", 2).await;
+    smtp.send("BURL imap://jdoe%40example.com@example.com/Outbox/;UID=99 LAST")
+        .await;
+    smtp.read(1, 5).await.assert_contains("554 5.6.6");
+    smtp.send("BURL imap://jane.smith%40example.com@example.com/Outbox/;UID=1 LAST")
+        .await;
+    smtp.read(1, 5).await.assert_contains("554 5.6.6");
+    smtp.send("RSET").await;
+    smtp.read(1, 2).await;
+
+    // Authorized URLs can be combined with BDAT chunks
+    imap.send(concat!(
+        "GENURLAUTH \"imap://jdoe%40example.com@example.com/Outbox/;UID=1/;SECTION=TEXT;",
+        "URLAUTH=submit+jdoe%40example.com\" INTERNAL"
+    ))
+    .await;
+    let url = imap
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find_map(|line| {
+            line.strip_prefix("* GENURLAUTH \"")
+                .and_then(|line| line.strip_suffix('"'))
+                .map(|url| url.to_string())
+        })
+        .expect("GENURLAUTH response");
+    smtp.mail_from("jdoe@example.com", 2).await;
+    smtp.rcpt_to("jdoe@example.com", 2).await;
+    smtp.bdat(
+        "From: jdoe@example.com\r\nSubject: Forwarded by reference\r\n\r\n",
+        2,
+    )
+    .await;
+    smtp.send(&format!("BURL {url} LAST")).await;
+    smtp.read(1, 2).await;
+    wait_for_delivery(imap, inbox_count + 2).await;
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH * BODY.PEEK[]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Subject: Forwarded by reference")
+        .assert_contains("This message was submitted using BURL.");
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Invalidated URLs can no longer be submitted
+    imap.send("RESETKEY Outbox").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    smtp.mail_from("jdoe@example.com", 2).await;
+    smtp.rcpt_to("jdoe@example.com", 2).await;
+    smtp.send(&format!("BURL {url} LAST")).await;
+    smtp.read(1, 5).await.assert_contains("554 5.6.6");
+    smtp.send("QUIT").await;
+    smtp.read(1, 2).await;
+
+    // Clean up
+    imap.send("DELETE Outbox").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
+
+async fn inbox_messages(imap: &mut ImapConnection) -> usize {
+    imap.send("STATUS INBOX (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find_map(|line| {
+            line.split_once("(MESSAGES ")
+                .and_then(|(_, count)| count.strip_suffix(')'))
+                .and_then(|count| count.parse().ok())
+        })
+        .expect("STATUS response")
+}
+
+async fn wait_for_delivery(imap: &mut ImapConnection, expected: usize) {
+    for _ in 0..50 {
+        if inbox_messages(imap).await == expected {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("Message was not delivered");
+}
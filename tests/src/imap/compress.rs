@@ -0,0 +1,91 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap::core::compress::DeflateStream;
+use imap_proto::ResponseType;
+use tokio::{
+    io::{AsyncBufReadExt, BufReader},
+    net::TcpStream,
+};
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test() {
+    println!("Running COMPRESS tests...");
+
+    // COMPRESS is not advertised before authentication
+    let mut imap = ImapConnection::connect(b"_z ").await;
+    imap.assert_read(Type::Untagged, ResponseType::Ok).await;
+    imap.send("CAPABILITY").await;
+    let lines = imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert!(!lines[0].contains("COMPRESS=DEFLATE"), "{lines:?}");
+    imap.send("COMPRESS DEFLATE").await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Authenticate and enable compression
+    imap.send("AUTHENTICATE PLAIN {32+}\r\nAGpkb2VAZXhhbXBsZS5jb20Ac2VjcmV0")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COMPRESS=DEFLATE");
+    imap.send("COMPRESS GZIP").await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap.send("COMPRESS DEFLATE").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // All further traffic is compressed
+    let mut imap = imap.into_compressed();
+    imap.send("CAPABILITY").await;
+    let lines = imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert!(!lines[0].contains("COMPRESS=DEFLATE"), "{lines:?}");
+    imap.send("COMPRESS DEFLATE").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_response_code("COMPRESSIONACTIVE");
+    imap.send("LIST \"\" \"*\"").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("INBOX");
+
+    // Large responses are split across multiple deflate blocks
+    let message = format!(
+        "Subject: compressed\r\n\r\n{}",
+        "All work and no play makes Jack a dull boy.\r\n".repeat(500)
+    );
+    imap.send("CREATE Compressed").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(&format!(
+        "APPEND Compressed {{{}+}}\r\n{message}",
+        message.len()
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SELECT Compressed").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("FETCH 1 BODY.PEEK[]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("All work and no play makes Jack a dull boy.", 500);
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Compressed").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("LOGOUT").await;
+    imap.assert_read(Type::Untagged, ResponseType::Bye).await;
+}
+
+impl ImapConnection {
+    fn into_compressed(self) -> ImapConnection<DeflateStream<TcpStream>> {
+        let (reader, writer) = tokio::io::split(DeflateStream::new(
+            self.reader.into_inner().into_inner().unsplit(self.writer),
+        ));
+        ImapConnection {
+            tag: self.tag,
+            reader: BufReader::new(reader).lines(),
+            writer,
+        }
+    }
+}
@@ -0,0 +1,141 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running SAVEDATE, MULTISEARCH and PARTIAL tests...");
+
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("SAVEDATE")
+        .assert_contains("MULTISEARCH")
+        .assert_contains("PARTIAL");
+
+    // Partial search results
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("10 EXISTS");
+    imap.send("UID SEARCH RETURN (COUNT PARTIAL 1:3) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("COUNT 10 PARTIAL (1:3 1:3)");
+    imap.send("UID SEARCH RETURN (PARTIAL -1:-2) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("PARTIAL (-1:-2 9:10)");
+    imap.send("UID SEARCH RETURN (PARTIAL 20:30) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("PARTIAL (20:30 NIL)");
+    imap.send("UID SEARCH RETURN (ALL PARTIAL 1:3) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap.send("UID SEARCH RETURN (PARTIAL 1:-3) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+
+    // Partial fetch results
+    imap.send("UID FETCH 1:* (UID) (PARTIAL -1:-2)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("FETCH (UID", 2)
+        .assert_contains("* 9 FETCH (UID 9)")
+        .assert_contains("* 10 FETCH (UID 10)");
+
+    // Save date
+    imap.send("FETCH 1 (SAVEDATE)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (SAVEDATE \"");
+    imap_check
+        .send("UID SEARCH SAVEDATESUPPORTED SAVEDSINCE 1-Jan-2000")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH 1 2 3 4 5 6 7 8 9 10");
+    imap_check.send("UID SEARCH SAVEDBEFORE 1-Jan-2000").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_equals("* SEARCH");
+
+    // Save dates are kept separately for each mailbox
+    imap.send("CREATE Saved").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let save_date = fetch_save_date(imap).await;
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    imap.send("COPY 1 Saved").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_eq!(fetch_save_date(imap).await, save_date);
+    imap.send("SELECT Saved").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    assert_ne!(fetch_save_date(imap).await, save_date);
+    imap.send("SELECT INBOX").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Saved").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Multi-mailbox search from the authenticated state
+    imap_check.send("UNSELECT").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .send("ESEARCH IN (mailboxes INBOX) RETURN (COUNT) ALL")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MAILBOX \"INBOX\" UIDVALIDITY")
+        .assert_contains("UID COUNT 10");
+    imap_check
+        .send("ESEARCH IN (personal) RETURN (MIN) SUBJECT argentina")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MAILBOX \"INBOX\" UIDVALIDITY")
+        .assert_contains("UID MIN");
+    imap_check
+        .send("ESEARCH IN (mailboxes INBOX) RETURN (COUNT) 1:5")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Bad)
+        .await;
+    imap_check
+        .send("ESEARCH IN (mailboxes INBOX) RETURN (SAVE) ALL")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Bad)
+        .await;
+    imap_check.send("ESEARCH IN () ALL").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Bad)
+        .await;
+
+    // Selected mailbox
+    imap.send("ESEARCH RETURN (COUNT) ALL").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("MAILBOX \"INBOX\" UIDVALIDITY")
+        .assert_contains("UID COUNT 10");
+
+    imap_check.send("SELECT INBOX").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
+
+async fn fetch_save_date(imap: &mut ImapConnection) -> String {
+    imap.send("FETCH 1 (SAVEDATE)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find(|line| line.starts_with("* 1 FETCH (SAVEDATE \""))
+        .unwrap()
+}
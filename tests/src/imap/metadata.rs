@@ -0,0 +1,118 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running METADATA tests...");
+
+    // Set mailbox annotations
+    imap.send("CREATE Provolone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send(concat!(
+        "SETMETADATA Provolone (/private/comment \"My comment\" ",
+        "/shared/vendor/cheese/type \"Hard\" /shared/vendor/cheese/origin/country \"Italy\")"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Annotations should be visible from other sessions
+    imap_check
+        .send("GETMETADATA Provolone (/private/comment /shared/comment)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(concat!(
+            "* METADATA \"Provolone\" ",
+            "(/private/comment \"My comment\" /shared/comment NIL)"
+        ));
+    imap_check
+        .send("GETMETADATA (DEPTH 1) Provolone /shared/vendor/cheese")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("/shared/vendor/cheese/type \"Hard\"")
+        .assert_count("/shared/vendor/cheese/origin/country", 0);
+    imap_check
+        .send("GETMETADATA (DEPTH infinity MAXSIZE 4) Provolone /shared/vendor")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("/shared/vendor/cheese/type \"Hard\"")
+        .assert_count("/shared/vendor/cheese/origin/country", 0)
+        .assert_contains("[METADATA LONGENTRIES 5]");
+
+    // Remove an annotation
+    imap.send("SETMETADATA Provolone (/private/comment NIL)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("GETMETADATA Provolone /private/comment").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("(/private/comment NIL)");
+
+    // Enforce limits
+    imap.send(&format!(
+        "SETMETADATA Provolone (/private/comment \"{}\")",
+        "a".repeat(101)
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[METADATA MAXSIZE 100]");
+    imap.send(concat!(
+        "SETMETADATA Provolone (/private/a \"1\" /private/b \"2\" ",
+        "/private/c \"3\" /private/d \"4\")"
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[METADATA TOOMANY]");
+    imap.send("SETMETADATA Provolone (/private/comment)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap.send("GETMETADATA Pecorino /private/comment").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[NONEXISTENT]");
+
+    // Server annotations
+    imap.send("SETMETADATA \"\" (/private/vendor/client/theme \"dark\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SETMETADATA \"\" (/shared/comment \"Hello\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[NOPERM]");
+    imap_check
+        .send("GETMETADATA \"\" (/shared/comment /private/vendor/client/theme)")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains(concat!(
+            "* METADATA \"\" (/shared/comment \"Stalwart test server\" ",
+            "/private/vendor/client/theme \"dark\")"
+        ));
+
+    // Annotations count towards the annotation storage quota
+    imap.send("GETQUOTAROOT Provolone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("ANNOTATION-STORAGE 0 1024");
+
+    // Clean up
+    imap.send("SETMETADATA \"\" (/private/vendor/client/theme NIL)")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Provolone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
@@ -9,16 +9,23 @@ pub mod append;
 pub mod basic;
 pub mod bayes;
 pub mod body_structure;
+pub mod burl;
+pub mod compress;
 pub mod condstore;
 pub mod copy_move;
+pub mod esearch;
 pub mod fetch;
 pub mod idle;
 pub mod mailbox;
 pub mod managesieve;
+pub mod metadata;
+pub mod notify;
 pub mod pop;
+pub mod replace;
 pub mod search;
 pub mod store;
 pub mod thread;
+pub mod urlauth;
 
 use std::{
     path::PathBuf,
@@ -45,7 +52,10 @@ use jmap::{api::JmapSessionManager, SpawnServices};
 use pop3::Pop3SessionManager;
 use smtp::{core::SmtpSessionManager, SpawnQueueManager};
 use tokio::{
-    io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines, ReadHalf, WriteHalf},
+    io::{
+        AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, Lines, ReadHalf,
+        WriteHalf,
+    },
     net::TcpStream,
     sync::watch,
 };
@@ -88,6 +98,11 @@ greeting = 'Test LMTP instance'
 protocol = 'lmtp'
 tls.implicit = false
 
+[server.listener.submission-debug]
+bind = ['127.0.0.1:11587']
+protocol = 'smtp'
+tls.implicit = false
+
 [server.socket]
 reuse-addr = true
 
@@ -99,6 +114,12 @@ certificate = "default"
 [session.ehlo]
 reject-non-fqdn = false
 
+[session.auth]
+mechanisms = [ { if = "local_port == 11587", then = "[plain]"},
+               { else = 0 } ]
+directory = [ { if = "local_port == 11587", then = "'{STORE}'"},
+              { else = false } ]
+
 [session.rcpt]
 relay = [ { if = "!is_empty(authenticated_as)", then = true }, 
           { else = false } ]
@@ -202,6 +223,13 @@ private-key = "%{file:{PK}}%"
 [imap.protocol]
 uidplus = true
 
+[imap.metadata]
+max-size = 100
+max-entries = 5
+
+[imap.metadata.server]
+comment = "Stalwart test server"
+
 [storage]
 data = "{STORE}"
 fts = "{STORE}"
@@ -493,13 +521,20 @@ pub async fn imap_tests() {
     mailbox::test(&mut imap, &mut imap_check).await;
     append::test(&mut imap, &mut imap_check, &handle).await;
     search::test(&mut imap, &mut imap_check).await;
+    esearch::test(&mut imap, &mut imap_check).await;
     fetch::test(&mut imap, &mut imap_check).await;
     store::test(&mut imap, &mut imap_check, &handle).await;
     copy_move::test(&mut imap, &mut imap_check).await;
     thread::test(&mut imap, &mut imap_check).await;
     idle::test(&mut imap, &mut imap_check).await;
+    notify::test(&mut imap, &mut imap_check).await;
+    metadata::test(&mut imap, &mut imap_check).await;
     condstore::test(&mut imap, &mut imap_check).await;
+    replace::test(&mut imap, &mut imap_check, &handle).await;
+    urlauth::test(&mut imap, &mut imap_check).await;
+    burl::test(&mut imap, &mut imap_check).await;
     acl::test(&mut imap, &mut imap_check).await;
+    compress::test().await;
 
     // Logout
     for imap in [&mut imap, &mut imap_check] {
@@ -533,10 +568,10 @@ pub async fn imap_tests() {
     }
 }
 
-pub struct ImapConnection {
+pub struct ImapConnection<T = TcpStream> {
     tag: &'static [u8],
-    reader: Lines<BufReader<ReadHalf<TcpStream>>>,
-    writer: WriteHalf<TcpStream>,
+    reader: Lines<BufReader<ReadHalf<T>>>,
+    writer: WriteHalf<T>,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -557,7 +592,9 @@ impl ImapConnection {
             writer,
         }
     }
+}
 
+impl<T: AsyncRead + AsyncWrite + Unpin> ImapConnection<T> {
     pub async fn assert_read(&mut self, t: Type, rt: ResponseType) -> Vec<String> {
         let lines = self.read(t).await;
         let mut buf = Vec::with_capacity(10);
@@ -625,6 +662,7 @@ impl ImapConnection {
         self.writer.write_all(self.tag).await.unwrap();
         self.writer.write_all(text.as_bytes()).await.unwrap();
         self.writer.write_all(b"\r\n").await.unwrap();
+        self.writer.flush().await.unwrap();
     }
 
     pub async fn send_untagged(&mut self, text: &str) {
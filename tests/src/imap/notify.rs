@@ -0,0 +1,148 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running NOTIFY tests...");
+
+    // Unsupported events should be rejected
+    imap_check
+        .send("NOTIFY SET (personal (MailboxMetadataChange))")
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("BADEVENT (");
+    imap_check
+        .send("NOTIFY SET (selected (MessageNew))")
+        .await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Bad).await;
+    imap_check.send("NOTIFY SET STATUS").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Bad).await;
+
+    // Select a mailbox and enable notifications
+    imap_check.send("CREATE Mozzarella").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("SELECT Mozzarella").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .send(concat!(
+            "NOTIFY SET (selected (MessageNew (UID RFC822.SIZE) MessageExpunge)) ",
+            "(personal (MessageNew MessageExpunge MailboxName SubscriptionChange))"
+        ))
+        .await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+
+    // Expect a new mailbox notification
+    imap.send("CREATE Burrata").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("LIST () \"/\" \"Burrata\"");
+
+    // Expect a subscription notification
+    imap.send("SUBSCRIBE Burrata").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("LIST (\\Subscribed) \"/\" \"Burrata\"");
+
+    // Expect a status update for a mailbox that is not selected
+    let message = "From: test@domain.com\nSubject: Test\n\nTest message\n";
+    imap.send(&format!("APPEND Burrata {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("STATUS \"Burrata\"")
+        .assert_contains("MESSAGES 1")
+        .assert_contains("UNSEEN 1")
+        .assert_contains("UIDNEXT 2");
+
+    // Expect new message notifications for the selected mailbox
+    imap.send(&format!("APPEND Mozzarella {{{}}}", message.len()))
+        .await;
+    imap.assert_read(Type::Continuation, ResponseType::Ok).await;
+    imap.send_untagged(message).await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (FLAGS () UID 1)");
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 FETCH (UID 1 RFC822.SIZE ");
+
+    // Expect a mailbox rename notification
+    imap.send("RENAME Burrata Provolone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("LIST () \"/\" \"Provolone\" (\"OLDNAME\" (\"Burrata\"))");
+
+    // Expect a mailbox deletion notification
+    imap.send("DELETE Provolone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check
+        .assert_read(Type::Status, ResponseType::Ok)
+        .await
+        .assert_contains("LIST (\\NonExistent) \"/\" \"Provolone\"");
+
+    // Expunges are delayed until a command that allows them
+    imap_check
+        .send("NOTIFY SET (selected-delayed (MessageNew MessageExpunge))")
+        .await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("CREATE Scamorza").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("SELECT Mozzarella").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("MOVE 1 Scamorza").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("SEARCH ALL").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("EXPUNGE", 0);
+    imap_check.send("NOOP").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXPUNGE");
+
+    // Disable notifications
+    imap_check.send("NOTIFY NONE").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("CREATE Stracciatella").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UNSELECT").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_count("Stracciatella", 0);
+
+    // Clean up
+    for mailbox in ["Mozzarella", "Scamorza", "Stracciatella"] {
+        imap.send(&format!("DELETE {}", mailbox)).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+}
@@ -0,0 +1,158 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use directory::backend::internal::manage::ManageDirectory;
+use imap_proto::ResponseType;
+use jmap_proto::types::collection::Collection;
+
+use super::{AssertResult, IMAPTest, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection, handle: &IMAPTest) {
+    println!("Running REPLACE tests...");
+    let account_id = handle
+        .server
+        .store()
+        .get_principal_id("jdoe@example.com")
+        .await
+        .unwrap()
+        .unwrap();
+
+    // Create test mailboxes and a draft
+    for mailbox in ["Gorgonzola", "Mascarpone"] {
+        imap.send(&format!("CREATE {mailbox}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+    imap_check.send("LIST \"\" \"*\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    append_draft(
+        imap,
+        "APPEND Gorgonzola (\\Draft)",
+        "Draft 1",
+        ResponseType::Ok,
+    )
+    .await;
+    imap.send("SELECT Gorgonzola").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+    imap_check.send("SELECT Gorgonzola").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXISTS");
+
+    // Replace the draft in the same mailbox
+    let num_threads = thread_count(handle, account_id).await;
+    append_draft(
+        imap,
+        "REPLACE 1 Gorgonzola (\\Draft)",
+        "Draft 2",
+        ResponseType::Ok,
+    )
+    .await
+    .assert_contains("* VANISHED 1")
+    .assert_contains("* 1 EXISTS")
+    .assert_contains("HIGHESTMODSEQ")
+    .assert_contains("[APPENDUID")
+    .assert_contains(" 2] REPLACE completed");
+    imap.send("FETCH 1 (UID FLAGS BODY.PEEK[HEADER.FIELDS (SUBJECT)])")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("UID 2")
+        .assert_contains("\\Draft")
+        .assert_contains("Subject: Draft 2")
+        .assert_count("* 1 FETCH", 1);
+
+    // The thread of the replaced message should have been deleted
+    assert_eq!(thread_count(handle, account_id).await, num_threads);
+
+    // Other sessions should see the old message expunged
+    imap_check.send("NOOP").await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 1 EXPUNGE")
+        .assert_contains("* 1 EXISTS");
+
+    // Replace the draft into another mailbox
+    append_draft(
+        imap,
+        "UID REPLACE 2 Mascarpone",
+        "Draft 3",
+        ResponseType::Ok,
+    )
+    .await
+    .assert_contains("* VANISHED 2")
+    .assert_contains("[APPENDUID")
+    .assert_contains("* 0 EXISTS");
+    imap.send("STATUS Mascarpone (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("(MESSAGES 1)");
+
+    // Messages in multiple mailboxes are only removed from the selected one
+    append_draft(imap, "APPEND Gorgonzola", "Draft 4", ResponseType::Ok).await;
+    imap.send("COPY 1 Mascarpone").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    append_draft(imap, "REPLACE 1 Gorgonzola", "Draft 5", ResponseType::Ok)
+        .await
+        .assert_contains("* VANISHED 3")
+        .assert_contains("* 1 EXISTS");
+    imap.send("STATUS Mascarpone (MESSAGES)").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("(MESSAGES 2)");
+    imap.send("FETCH 1 BODY.PEEK[HEADER.FIELDS (SUBJECT)]")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Subject: Draft 5");
+
+    // Invalid message numbers and mailboxes
+    append_draft(imap, "REPLACE 2 Gorgonzola", "Invalid", ResponseType::No).await;
+    append_draft(
+        imap,
+        "UID REPLACE 1 Gorgonzola",
+        "Invalid",
+        ResponseType::No,
+    )
+    .await;
+    append_draft(imap, "REPLACE 1 Brie", "Invalid", ResponseType::No)
+        .await
+        .assert_contains("[TRYCREATE]");
+
+    // Clean up
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("UNSELECT").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    for mailbox in ["Gorgonzola", "Mascarpone"] {
+        imap.send(&format!("DELETE {mailbox}")).await;
+        imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    }
+}
+
+async fn append_draft(
+    imap: &mut ImapConnection,
+    command: &str,
+    subject: &str,
+    response: ResponseType,
+) -> Vec<String> {
+    let message = format!("Subject: {subject}\r\n\r\nThis is {subject}.\r\n");
+    imap.send(&format!("{command} {{{}+}}\r\n{message}", message.len()))
+        .await;
+    imap.assert_read(Type::Tagged, response).await
+}
+
+async fn thread_count(handle: &IMAPTest, account_id: u32) -> u64 {
+    handle
+        .server
+        .get_document_ids(account_id, Collection::Thread)
+        .await
+        .unwrap()
+        .map_or(0, |ids| ids.len())
+}
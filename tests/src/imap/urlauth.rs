@@ -0,0 +1,139 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use imap_proto::ResponseType;
+
+use super::{AssertResult, ImapConnection, Type};
+
+pub async fn test(imap: &mut ImapConnection, imap_check: &mut ImapConnection) {
+    println!("Running CATENATE and URLAUTH tests...");
+
+    imap.send("CAPABILITY").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("CATENATE")
+        .assert_contains("URLAUTH");
+
+    // Create a mailbox with a multipart message
+    imap.send("CREATE Fondue").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send("LIST \"\" \"*\"").await;
+    imap_check.assert_read(Type::Tagged, ResponseType::Ok).await;
+    let message = concat!(
+        "Subject: Attachment\r\n",
+        "Content-Type: multipart/mixed; boundary=\"x\"\r\n\r\n",
+        "--x\r\n",
+        "Content-Type: text/plain\r\n\r\n",
+        "See attached.\r\n",
+        "--x\r\n",
+        "Content-Type: application/octet-stream\r\n\r\n",
+        "Large attachment\r\n",
+        "--x--\r\n"
+    );
+    imap.send(&format!(
+        "APPEND Fondue {{{}+}}\r\n{message}",
+        message.len()
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("[APPENDUID");
+
+    // Compose a new message reusing the attachment
+    let header = "Subject: Forwarded\r\n\r\n";
+    imap.send(&format!(
+        "APPEND Fondue CATENATE (TEXT {{{}+}}\r\n{header} URL \"/Fondue/;UID=1/;SECTION=2\" TEXT {{2+}}\r\n\r\n)",
+        header.len()
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("[APPENDUID");
+    imap.send("SELECT Fondue").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("* 2 EXISTS");
+    imap.send("FETCH 2 BODY.PEEK[]").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Subject: Forwarded")
+        .assert_contains("Large attachment");
+
+    // Invalid URLs
+    imap.send("APPEND Fondue CATENATE (URL \"/Fondue/;UID=99\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[BADURL /Fondue/;UID=99]");
+    imap.send("APPEND Fondue CATENATE (URL \"/Fondue/;UID=1/;SECTION=9\")")
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[BADURL");
+
+    // Generate an authorized URL
+    let rump = "imap://jdoe%40example.com@example.com/Fondue/;UID=1/;SECTION=2;URLAUTH=user+jdoe%40example.com";
+    imap.send(&format!("GENURLAUTH \"{rump}\" INTERNAL")).await;
+    let url = imap
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .into_iter()
+        .find_map(|line| {
+            line.strip_prefix("* GENURLAUTH \"")
+                .and_then(|line| line.strip_suffix('"'))
+                .map(|url| url.to_string())
+        })
+        .expect("GENURLAUTH response");
+    assert!(url.starts_with(&format!("{rump}:internal:")), "{url}");
+
+    // URLs can only be generated for the authenticated user
+    imap.send(&format!(
+        "GENURLAUTH \"{}\" INTERNAL",
+        rump.replace("imap://jdoe", "imap://jane")
+    ))
+    .await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[BADURL");
+    imap.send(&format!("GENURLAUTH \"{rump}\" HMAC")).await;
+    imap.assert_read(Type::Tagged, ResponseType::No).await;
+
+    // Fetch the authorized URL
+    imap_check
+        .send(&format!("URLFETCH \"{url}\" \"{url}0\""))
+        .await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("Large attachment")
+        .assert_contains("0\" NIL");
+
+    // Authorized URLs can be used in CATENATE
+    imap.send(&format!("APPEND Fondue CATENATE (URL \"{url}\")"))
+        .await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("[APPENDUID");
+
+    // Resetting the mailbox key invalidates existing URLs
+    imap.send("RESETKEY Fondue").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap_check.send(&format!("URLFETCH \"{url}\"")).await;
+    imap_check
+        .assert_read(Type::Tagged, ResponseType::Ok)
+        .await
+        .assert_contains("\" NIL");
+    imap.send("RESETKEY Tartiflette").await;
+    imap.assert_read(Type::Tagged, ResponseType::No)
+        .await
+        .assert_contains("[NONEXISTENT]");
+
+    // Clean up
+    imap.send("UNSELECT").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+    imap.send("DELETE Fondue").await;
+    imap.assert_read(Type::Tagged, ResponseType::Ok).await;
+}
@@ -0,0 +1,509 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use jmap_proto::types::id::Id;
+use serde_json::Value;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running Contacts and Calendars tests...");
+    let server = params.server.clone();
+    let mut account_id = Id::from(0u64);
+    let mut other_account_id = Id::from(0u64);
+
+    for (id, email, password, name) in [
+        (
+            &mut account_id,
+            "robert@example.com",
+            "aabbcc",
+            "Robert Foobar",
+        ),
+        (
+            &mut other_account_id,
+            "jdoe@example.com",
+            "12345",
+            "John Doe",
+        ),
+    ] {
+        *id = Id::from(
+            server
+                .core
+                .storage
+                .data
+                .create_test_user(email, password, name, &[email][..])
+                .await,
+        );
+    }
+    let account_id = account_id.to_string();
+    let other_account_id = other_account_id.to_string();
+
+    // Create an address book and a contact card
+    let response = request(
+        r##"[
+            [
+              "AddressBook/set",
+              {
+                "accountId": "$$",
+                "create": {
+                  "book": {
+                    "name": "Personal",
+                    "isDefault": true,
+                    "isSubscribed": true
+                  }
+                }
+              },
+              "R1"
+            ],
+            [
+              "ContactCard/set",
+              {
+                "accountId": "$$",
+                "create": {
+                  "card": {
+                    "@type": "Card",
+                    "addressBookIds": { "#book": true },
+                    "kind": "individual",
+                    "name": { "full": "Jane Doe" },
+                    "emails": { "e1": { "address": "jane@example.org" } }
+                  }
+                }
+              },
+              "R2"
+            ]
+        ]"##,
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    let book_id = pointer_str(&response, "/methodResponses/0/1/created/book/id");
+    let card_id = pointer_str(&response, "/methodResponses/1/1/created/card/id");
+    let card_uid = pointer_str(&response, "/methodResponses/1/1/created/card/uid");
+    assert_eq!(card_uid.len(), 32, "Response: {response:?}");
+
+    // Only one address book can be the default
+    let response = request(
+        r#"[[
+            "AddressBook/set",
+            {
+              "accountId": "$$",
+              "create": { "book2": { "name": "Work", "isDefault": true } }
+            },
+            "R1"
+        ]]"#,
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/0/1/notCreated/book2/type"),
+        "invalidProperties"
+    );
+
+    // Fetch the address book and contact card
+    let response = request(
+        &r#"[
+            [ "AddressBook/get", { "accountId": "$$", "ids": [ "%%" ] }, "R1" ],
+            [ "ContactCard/get", { "accountId": "$$", "ids": [ "&&" ] }, "R2" ]
+        ]"#
+        .replace("%%", &book_id)
+        .replace("&&", &card_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/0/1/list/0/name"),
+        "Personal"
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/list/0/isSubscribed"),
+        Some(&Value::Bool(true))
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/list/0/myRights/mayWrite"),
+        Some(&Value::Bool(true))
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/name/full"),
+        "Jane Doe"
+    );
+    assert_eq!(
+        response.pointer(&format!(
+            "/methodResponses/1/1/list/0/addressBookIds/{book_id}"
+        )),
+        Some(&Value::Bool(true))
+    );
+
+    // Patch the contact card and query it
+    let response = request(
+        &r#"[
+            [
+              "ContactCard/set",
+              {
+                "accountId": "$$",
+                "update": {
+                  "&&": {
+                    "name/full": "Jane Smith",
+                    "emails/e2": { "address": "jsmith@example.org" }
+                  }
+                }
+              },
+              "R1"
+            ],
+            [ "ContactCard/query", { "accountId": "$$", "filter": { "text": "smith" } }, "R2" ],
+            [ "ContactCard/query", { "accountId": "$$", "filter": { "text": "doe" } }, "R3" ],
+            [ "ContactCard/query", { "accountId": "$$", "filter": { "uid": "%%" } }, "R4" ],
+            [ "ContactCard/query", { "accountId": "$$", "filter": { "kind": "group" } }, "R5" ]
+        ]"#
+        .replace("%%", &card_uid)
+        .replace("&&", &card_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{card_id}"))
+            .is_some(),
+        "Response: {response:?}"
+    );
+    assert_eq!(query_ids(&response, 1), vec![card_id.clone()]);
+    assert_eq!(query_ids(&response, 2), Vec::<String>::new());
+    assert_eq!(query_ids(&response, 3), vec![card_id.clone()]);
+    assert_eq!(query_ids(&response, 4), Vec::<String>::new());
+
+    // Duplicate UIDs are not allowed
+    let response = request(
+        &r#"[[
+            "ContactCard/set",
+            {
+              "accountId": "$$",
+              "create": { "card2": { "uid": "%%", "addressBookIds": { "&&": true } } }
+            },
+            "R1"
+        ]]"#
+        .replace("%%", &card_uid)
+        .replace("&&", &book_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/0/1/notCreated/card2/type"),
+        "alreadyExists"
+    );
+
+    // Address books with contents cannot be destroyed
+    let response = request(
+        &r#"[[ "AddressBook/set", { "accountId": "$$", "destroy": [ "%%" ] }, "R1" ]]"#
+            .replace("%%", &book_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(
+            &response,
+            &format!("/methodResponses/0/1/notDestroyed/{book_id}/type")
+        ),
+        "addressBookHasContents"
+    );
+
+    // Create a calendar with events
+    let response = request(
+        r##"[
+            [
+              "Calendar/set",
+              {
+                "accountId": "$$",
+                "create": { "cal": { "name": "Work", "color": "#ff0000" } }
+              },
+              "R1"
+            ],
+            [
+              "CalendarEvent/set",
+              {
+                "accountId": "$$",
+                "create": {
+                  "ev1": {
+                    "@type": "Event",
+                    "calendarIds": { "#cal": true },
+                    "uid": "event-1@example.org",
+                    "title": "Budget review",
+                    "start": "2024-06-10T09:00:00",
+                    "duration": "PT1H"
+                  },
+                  "ev2": {
+                    "@type": "Event",
+                    "calendarIds": { "#cal": true },
+                    "uid": "event-2@example.org",
+                    "title": "Team offsite",
+                    "start": "2024-06-03T08:00:00",
+                    "duration": "P2D"
+                  }
+                }
+              },
+              "R2"
+            ]
+        ]"##,
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    let calendar_id = pointer_str(&response, "/methodResponses/0/1/created/cal/id");
+    let event_id_1 = pointer_str(&response, "/methodResponses/1/1/created/ev1/id");
+    let event_id_2 = pointer_str(&response, "/methodResponses/1/1/created/ev2/id");
+
+    // Query events by time range and title
+    let response = request(
+        r#"[
+            [ "CalendarEvent/query", { "accountId": "$$" }, "R1" ],
+            [
+              "CalendarEvent/query",
+              {
+                "accountId": "$$",
+                "filter": { "after": "2024-06-04T00:00:00Z", "before": "2024-06-11T00:00:00Z" }
+              },
+              "R2"
+            ],
+            [ "CalendarEvent/query", { "accountId": "$$", "filter": { "title": "offsite" } }, "R3" ]
+        ]"#,
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        query_ids(&response, 0),
+        vec![event_id_2.clone(), event_id_1.clone()]
+    );
+    assert_eq!(
+        query_ids(&response, 1),
+        vec![event_id_2.clone(), event_id_1.clone()]
+    );
+    assert_eq!(query_ids(&response, 2), vec![event_id_2.clone()]);
+
+    // Obtain changes
+    let response = request(
+        r#"[[ "CalendarEvent/changes", { "accountId": "$$", "sinceState": "n" }, "R1" ]]"#,
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert_eq!(
+        response
+            .pointer("/methodResponses/0/1/created")
+            .and_then(|v| v.as_array())
+            .map(|v| v.len()),
+        Some(2),
+        "Response: {response:?}"
+    );
+
+    // Calendars are not visible to other accounts until shared
+    let response = request(
+        r#"[[ "CalendarEvent/get", { "accountId": "$$" }, "R1" ]]"#,
+        &account_id,
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/0/1/type"),
+        "forbidden",
+        "Response: {response:?}"
+    );
+
+    // Share the calendar with read-only access
+    let response = request(
+        &r#"[[
+            "Calendar/set",
+            {
+              "accountId": "$$",
+              "update": { "%%": { "acl": { "jdoe@example.com": [ "read", "readItems" ] } } }
+            },
+            "R1"
+        ]]"#
+        .replace("%%", &calendar_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{calendar_id}"))
+            .is_some(),
+        "Response: {response:?}"
+    );
+    let response = request(
+        &r#"[
+            [ "Calendar/get", { "accountId": "$$" }, "R1" ],
+            [ "CalendarEvent/get", { "accountId": "$$", "properties": [ "title" ] }, "R2" ],
+            [ "AddressBook/get", { "accountId": "$$" }, "R3" ],
+            [ "CalendarEvent/set", { "accountId": "$$", "destroy": [ "%%" ] }, "R4" ]
+        ]"#
+        .replace("%%", &event_id_1),
+        &account_id,
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/0/1/list/0/id"),
+        calendar_id
+    );
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/list/0/myRights/mayWriteAll"),
+        Some(&Value::Bool(false))
+    );
+    assert_eq!(
+        response
+            .pointer("/methodResponses/1/1/list")
+            .and_then(|v| v.as_array())
+            .map(|v| v.len()),
+        Some(2),
+        "Response: {response:?}"
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/2/1/type"),
+        "forbidden"
+    );
+    assert_eq!(
+        pointer_str(
+            &response,
+            &format!("/methodResponses/3/1/notDestroyed/{event_id_1}/type")
+        ),
+        "forbidden"
+    );
+
+    // Grant write access and destroy an event from the shared account
+    request(
+        &r#"[[
+            "Calendar/set",
+            {
+              "accountId": "$$",
+              "update": { "%%": { "acl/jdoe@example.com": [ "read", "readItems", "removeItems" ] } }
+            },
+            "R1"
+        ]]"#
+        .replace("%%", &calendar_id),
+        &account_id,
+        "robert@example.com",
+        "aabbcc",
+    )
+    .await;
+    let response = request(
+        &r#"[[ "CalendarEvent/set", { "accountId": "$$", "destroy": [ "%%" ] }, "R1" ]]"#
+            .replace("%%", &event_id_1),
+        &account_id,
+        "jdoe@example.com",
+        "12345",
+    )
+    .await;
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/destroyed/0"),
+        Some(&Value::String(event_id_1.clone())),
+        "Response: {response:?}"
+    );
+
+    // Remove test data
+    for (account_id, login, secret) in [
+        (&account_id, "robert@example.com", "aabbcc"),
+        (&other_account_id, "jdoe@example.com", "12345"),
+    ] {
+        let response = request(
+            r##"[
+                [ "ContactCard/query", { "accountId": "$$" }, "R1" ],
+                [
+                  "ContactCard/set",
+                  {
+                    "accountId": "$$",
+                    "#destroy": { "resultOf": "R1", "name": "ContactCard/query", "path": "/ids" }
+                  },
+                  "R2"
+                ],
+                [ "CalendarEvent/query", { "accountId": "$$" }, "R3" ],
+                [
+                  "CalendarEvent/set",
+                  {
+                    "accountId": "$$",
+                    "#destroy": { "resultOf": "R3", "name": "CalendarEvent/query", "path": "/ids" }
+                  },
+                  "R4"
+                ],
+                [ "AddressBook/query", { "accountId": "$$" }, "R5" ],
+                [
+                  "AddressBook/set",
+                  {
+                    "accountId": "$$",
+                    "#destroy": { "resultOf": "R5", "name": "AddressBook/query", "path": "/ids" }
+                  },
+                  "R6"
+                ],
+                [ "Calendar/query", { "accountId": "$$" }, "R7" ],
+                [
+                  "Calendar/set",
+                  {
+                    "accountId": "$$",
+                    "#destroy": { "resultOf": "R7", "name": "Calendar/query", "path": "/ids" }
+                  },
+                  "R8"
+                ]
+            ]"##,
+            account_id,
+            login,
+            secret,
+        )
+        .await;
+        for idx in [1, 3, 5, 7] {
+            assert!(
+                response
+                    .pointer(&format!("/methodResponses/{idx}/1/notDestroyed"))
+                    .is_none_or(|v| v.is_null()),
+                "Response: {response:?}"
+            );
+        }
+    }
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+async fn request(body: &str, account_id: &str, login: &str, secret: &str) -> Value {
+    jmap_json_request(body.replace("$$", account_id), login, secret).await
+}
+
+fn pointer_str(response: &Value, pointer: &str) -> String {
+    response
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing {pointer} in response: {response:?}"))
+        .to_string()
+}
+
+fn query_ids(response: &Value, idx: usize) -> Vec<String> {
+    response
+        .pointer(&format!("/methodResponses/{idx}/1/ids"))
+        .and_then(|v| v.as_array())
+        .unwrap_or_else(|| panic!("Missing query results in response: {response:?}"))
+        .iter()
+        .map(|v| v.as_str().unwrap().to_string())
+        .collect()
+}
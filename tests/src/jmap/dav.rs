@@ -0,0 +1,710 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use base64::{engine::general_purpose, Engine};
+use jmap_proto::types::id::Id;
+use reqwest::{header, redirect::Policy, Method, StatusCode};
+use serde_json::Value;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{assert_is_empty, jmap_json_request, mailbox::destroy_all_mailboxes},
+};
+
+use super::JMAPTest;
+
+const VCARD: &str = concat!(
+    "BEGIN:VCARD\r\n",
+    "VERSION:4.0\r\n",
+    "UID:urn:uuid:4fbe8971-0bc3-424c-9c26-36c3e1eff6b1\r\n",
+    "FN:Jane Doe\r\n",
+    "N:Doe;Jane;;;\r\n",
+    "EMAIL;TYPE=work:jane@example.org\r\n",
+    "TEL;VALUE=uri:tel:+1-555-555-5555\r\n",
+    "NOTE:Met at the conference\\, call back\r\n",
+    "END:VCARD\r\n"
+);
+
+const VEVENT: &str = concat!(
+    "BEGIN:VCALENDAR\r\n",
+    "VERSION:2.0\r\n",
+    "PRODID:-//Example Corp.//Calendar//EN\r\n",
+    "BEGIN:VEVENT\r\n",
+    "UID:20240315T090000Z-meeting@example.org\r\n",
+    "DTSTAMP:20240301T120000Z\r\n",
+    "DTSTART:20240315T090000Z\r\n",
+    "DTEND:20240315T103000Z\r\n",
+    "SUMMARY:Project kickoff\r\n",
+    "LOCATION:Room 101\r\n",
+    "END:VEVENT\r\n",
+    "END:VCALENDAR\r\n"
+);
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running CardDAV and CalDAV tests...");
+    let server = params.server.clone();
+    let mut account_id = Id::from(0u64);
+    let mut other_account_id = Id::from(0u64);
+
+    for (id, email, password, name) in [
+        (&mut account_id, "dav@example.com", "davpass", "Dave Dav"),
+        (
+            &mut other_account_id,
+            "davshare@example.com",
+            "sharepass",
+            "Sharon Dav",
+        ),
+    ] {
+        *id = Id::from(
+            server
+                .core
+                .storage
+                .data
+                .create_test_user(email, password, name, &[email][..])
+                .await,
+        );
+    }
+    let owner = ("dav@example.com", "davpass");
+    let other = ("davshare@example.com", "sharepass");
+
+    // Discovery
+    let response = DavRequest::new(Method::OPTIONS, "/dav/").send().await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert!(response.header("dav").contains("addressbook"));
+    assert!(response.header("dav").contains("calendar-access"));
+    for (path, location) in [
+        ("/.well-known/carddav", "/dav/card/"),
+        ("/.well-known/caldav", "/dav/cal/"),
+    ] {
+        let response = DavRequest::new(Method::GET, path).send().await;
+        assert_eq!(response.status, StatusCode::MOVED_PERMANENTLY);
+        assert_eq!(response.header("location"), location);
+    }
+
+    // Unauthenticated requests are challenged
+    let response = DavRequest::new(propfind(), "/dav/").send().await;
+    assert_eq!(response.status, StatusCode::UNAUTHORIZED);
+    assert!(response.header("www-authenticate").starts_with("Basic"));
+
+    // Obtain the principal and home sets
+    let response = DavRequest::new(propfind(), "/dav/principal/dav@example.com/")
+        .auth(owner)
+        .depth("0")
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<D:propfind xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\" ",
+            "xmlns:CR=\"urn:ietf:params:xml:ns:carddav\">",
+            "<D:prop><D:current-user-principal/><CR:addressbook-home-set/>",
+            "<C:calendar-home-set/><C:calendar-user-address-set/><D:unknown-prop/></D:prop>",
+            "</D:propfind>"
+        ))
+        .send()
+        .await;
+    response.assert_multistatus();
+    for expected in [
+        "<D:current-user-principal><D:href>/dav/principal/dav@example.com/</D:href>",
+        "<CR:addressbook-home-set><D:href>/dav/card/dav@example.com/</D:href>",
+        "<C:calendar-home-set><D:href>/dav/cal/dav@example.com/</D:href>",
+        "<D:href>mailto:dav@example.com</D:href>",
+        "<D:unknown-prop/></D:prop><D:status>HTTP/1.1 404 Not Found</D:status>",
+    ] {
+        response.assert_contains(expected);
+    }
+
+    // Listing the address book home creates a default address book
+    let response = DavRequest::new(propfind(), "/dav/card/dav@example.com/")
+        .auth(owner)
+        .depth("1")
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains("<D:href>/dav/card/dav@example.com/default/</D:href>");
+    response.assert_contains("<D:collection/><CR:addressbook/>");
+
+    // Create a calendar using MKCALENDAR
+    let response = DavRequest::new(mkcalendar(), "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<C:mkcalendar xmlns:D=\"DAV:\" xmlns:C=\"urn:ietf:params:xml:ns:caldav\">",
+            "<D:set><D:prop><D:displayname>Work</D:displayname>",
+            "<C:calendar-description>Work events</C:calendar-description>",
+            "</D:prop></D:set></C:mkcalendar>"
+        ))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+    let response = DavRequest::new(mkcalendar(), "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::METHOD_NOT_ALLOWED);
+
+    // Update the calendar properties, unsupported properties fail atomically
+    let response = DavRequest::new(proppatch(), "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<D:propertyupdate xmlns:D=\"DAV:\" xmlns:I=\"http://apple.com/ns/ical/\">",
+            "<D:set><D:prop><I:calendar-color>#FF0000</I:calendar-color>",
+            "<D:getetag>invalid</D:getetag></D:prop></D:set></D:propertyupdate>"
+        ))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response
+        .assert_contains("<I:calendar-color/></D:prop><D:status>HTTP/1.1 424 Failed Dependency");
+    response.assert_contains("<D:getetag/></D:prop><D:status>HTTP/1.1 403 Forbidden");
+    let response = DavRequest::new(proppatch(), "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<D:propertyupdate xmlns:D=\"DAV:\" xmlns:I=\"http://apple.com/ns/ical/\">",
+            "<D:set><D:prop><I:calendar-color>#FF0000</I:calendar-color></D:prop></D:set>",
+            "</D:propertyupdate>"
+        ))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains("<D:status>HTTP/1.1 200 OK</D:status>");
+    let response = DavRequest::new(propfind(), "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .depth("0")
+        .send()
+        .await;
+    response.assert_multistatus();
+    for expected in [
+        "<D:displayname>Work</D:displayname>",
+        "<C:calendar-description>Work events</C:calendar-description>",
+        "<I:calendar-color>#FF0000</I:calendar-color>",
+        "<D:collection/><C:calendar/>",
+        "<D:sync-token>urn:stalwart:sync:",
+    ] {
+        response.assert_contains(expected);
+    }
+
+    // Create a contact card
+    let card_path = "/dav/card/dav@example.com/default/jane.vcf";
+    let response = DavRequest::new(Method::PUT, card_path)
+        .auth(owner)
+        .header("if-none-match", "*")
+        .header("content-type", "text/vcard")
+        .body(VCARD)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+    let card_etag = response.header("etag");
+    assert!(!card_etag.is_empty());
+    let response = DavRequest::new(Method::PUT, card_path)
+        .auth(owner)
+        .header("if-none-match", "*")
+        .body(VCARD)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+
+    // Invalid data and duplicate UIDs are rejected
+    let response = DavRequest::new(Method::PUT, "/dav/card/dav@example.com/default/bad.vcf")
+        .auth(owner)
+        .body("BEGIN:VCARD\r\nFN:Broken\r\n")
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    response.assert_contains("<CR:valid-address-data/>");
+    let response = DavRequest::new(Method::PUT, "/dav/card/dav@example.com/default/copy.vcf")
+        .auth(owner)
+        .body(VCARD)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    response.assert_contains("<CR:no-uid-conflict/>");
+
+    // The original vCard is returned
+    let response = DavRequest::new(Method::GET, card_path)
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    assert_eq!(response.body, VCARD);
+    assert_eq!(response.header("etag"), card_etag);
+    assert!(response.header("content-type").starts_with("text/vcard"));
+
+    // The card is mapped to JSContact
+    let response = jmap_json_request(
+        r##"[
+            [ "ContactCard/query", { "accountId": "$$" }, "R1" ],
+            [
+              "ContactCard/get",
+              {
+                "accountId": "$$",
+                "#ids": { "resultOf": "R1", "name": "ContactCard/query", "path": "/ids" }
+              },
+              "R2"
+            ]
+        ]"##
+        .replace("$$", &account_id.to_string()),
+        owner.0,
+        owner.1,
+    )
+    .await;
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/name/full"),
+        "Jane Doe"
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/emails/e1/address"),
+        "jane@example.org"
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/phones/p1/number"),
+        "+1-555-555-5555"
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/notes/n1/note"),
+        "Met at the conference, call back"
+    );
+    assert_eq!(
+        pointer_str(&response, "/methodResponses/1/1/list/0/uid"),
+        "urn:uuid:4fbe8971-0bc3-424c-9c26-36c3e1eff6b1"
+    );
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/list/0/davData")
+            .is_none(),
+        "Response: {response:?}"
+    );
+
+    // Search address book
+    for (text, expect_match) in [("jane", true), ("nobody", false)] {
+        let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+            .auth(owner)
+            .depth("1")
+            .body(
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+                    "<CR:addressbook-query xmlns:D=\"DAV:\" ",
+                    "xmlns:CR=\"urn:ietf:params:xml:ns:carddav\">",
+                    "<D:prop><D:getetag/></D:prop><CR:filter><CR:prop-filter name=\"FN\">",
+                    "<CR:text-match collation=\"i;unicode-casemap\" match-type=\"contains\">",
+                    "$$</CR:text-match></CR:prop-filter></CR:filter></CR:addressbook-query>"
+                )
+                .replace("$$", text),
+            )
+            .send()
+            .await;
+        response.assert_multistatus();
+        assert_eq!(
+            response.body.contains(card_path),
+            expect_match,
+            "{}",
+            response.body
+        );
+    }
+
+    // Create an event and search it by time range
+    let event_path = "/dav/cal/dav@example.com/work/kickoff.ics";
+    let response = DavRequest::new(Method::PUT, event_path)
+        .auth(owner)
+        .body(VEVENT)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::CREATED, "{}", response.body);
+    for (start, end, expect_match) in [
+        ("20240315T000000Z", "20240316T000000Z", true),
+        ("20240315T100000Z", "20240315T110000Z", true),
+        ("20240316T000000Z", "20240317T000000Z", false),
+    ] {
+        let response = DavRequest::new(report(), "/dav/cal/dav@example.com/work/")
+            .auth(owner)
+            .depth("1")
+            .body(
+                concat!(
+                    "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+                    "<C:calendar-query xmlns:D=\"DAV:\" ",
+                    "xmlns:C=\"urn:ietf:params:xml:ns:caldav\">",
+                    "<D:prop><D:getetag/><C:calendar-data/></D:prop>",
+                    "<C:filter><C:comp-filter name=\"VCALENDAR\"><C:comp-filter name=\"VEVENT\">",
+                    "<C:time-range start=\"$1\" end=\"$2\"/>",
+                    "</C:comp-filter></C:comp-filter></C:filter></C:calendar-query>"
+                )
+                .replace("$1", start)
+                .replace("$2", end),
+            )
+            .send()
+            .await;
+        response.assert_multistatus();
+        assert_eq!(
+            response.body.contains(event_path),
+            expect_match,
+            "{start} - {end}: {}",
+            response.body
+        );
+        if expect_match {
+            response.assert_contains("SUMMARY:Project kickoff");
+        }
+    }
+
+    // Synchronize the address book
+    let sync_body = |token: &str| {
+        concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<D:sync-collection xmlns:D=\"DAV:\">",
+            "<D:sync-token>$$</D:sync-token><D:sync-level>1</D:sync-level>",
+            "<D:prop><D:getetag/></D:prop></D:sync-collection>"
+        )
+        .replace("$$", token)
+    };
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body(""))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains(card_path);
+    let sync_token = response.sync_token();
+
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body(&sync_token))
+        .send()
+        .await;
+    response.assert_multistatus();
+    assert!(!response.body.contains(card_path), "{}", response.body);
+    assert_eq!(response.sync_token(), sync_token);
+
+    // Update the card and synchronize again
+    let updated_vcard = VCARD.replace("FN:Jane Doe", "FN:Jane Smith");
+    let response = DavRequest::new(Method::PUT, card_path)
+        .auth(owner)
+        .header("if-match", "\"invalid\"")
+        .body(&updated_vcard)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::PRECONDITION_FAILED);
+    let response = DavRequest::new(Method::PUT, card_path)
+        .auth(owner)
+        .header("if-match", &card_etag)
+        .body(&updated_vcard)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT, "{}", response.body);
+    let card_etag = response.header("etag");
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body(&sync_token))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains(card_path);
+    response.assert_contains(&format!(
+        "<D:getetag>{}</D:getetag>",
+        card_etag.replace('"', "&quot;")
+    ));
+    let sync_token = response.sync_token();
+
+    // Multiget
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<CR:addressbook-multiget xmlns:D=\"DAV:\" ",
+            "xmlns:CR=\"urn:ietf:params:xml:ns:carddav\">",
+            "<D:prop><D:getetag/><CR:address-data/></D:prop>",
+            "<D:href>/dav/card/dav@example.com/default/jane.vcf</D:href>",
+            "<D:href>/dav/card/dav@example.com/default/missing.vcf</D:href>",
+            "</CR:addressbook-multiget>"
+        ))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains("FN:Jane Smith");
+    response.assert_contains(concat!(
+        "<D:href>/dav/card/dav@example.com/default/missing.vcf</D:href>",
+        "<D:status>HTTP/1.1 404 Not Found</D:status>"
+    ));
+
+    // Other users require access to the address book
+    let response = DavRequest::new(propfind(), "/dav/card/dav@example.com/default/")
+        .auth(other)
+        .depth("1")
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    // Share the address book with read-only access
+    let response = jmap_json_request(
+        r#"[[ "AddressBook/get", { "accountId": "$$" }, "R1" ]]"#
+            .replace("$$", &account_id.to_string()),
+        owner.0,
+        owner.1,
+    )
+    .await;
+    let book_id = pointer_str(&response, "/methodResponses/0/1/list/0/id");
+    let response = jmap_json_request(
+        r#"[[
+            "AddressBook/set",
+            {
+              "accountId": "$$",
+              "update": { "%%": { "acl": { "davshare@example.com": [ "read", "readItems" ] } } }
+            },
+            "R1"
+        ]]"#
+        .replace("$$", &account_id.to_string())
+        .replace("%%", &book_id),
+        owner.0,
+        owner.1,
+    )
+    .await;
+    assert!(
+        response
+            .pointer(&format!("/methodResponses/0/1/updated/{book_id}"))
+            .is_some(),
+        "Response: {response:?}"
+    );
+    let response = DavRequest::new(propfind(), "/dav/card/dav@example.com/")
+        .auth(other)
+        .depth("1")
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains("<D:href>/dav/card/dav@example.com/default/</D:href>");
+    let response = DavRequest::new(propfind(), "/dav/card/dav@example.com/default/")
+        .auth(other)
+        .depth("1")
+        .body(concat!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>",
+            "<D:propfind xmlns:D=\"DAV:\"><D:prop><D:current-user-privilege-set/>",
+            "</D:prop></D:propfind>"
+        ))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains(card_path);
+    response.assert_contains("<D:privilege><D:read/></D:privilege>");
+    assert!(!response.body.contains("<D:bind/>"), "{}", response.body);
+    let response = DavRequest::new(Method::GET, card_path)
+        .auth(other)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::OK);
+    for (method, path) in [
+        (Method::PUT, "/dav/card/dav@example.com/default/new.vcf"),
+        (Method::DELETE, card_path),
+    ] {
+        let response = DavRequest::new(method, path)
+            .auth(other)
+            .body(VCARD.replace("4fbe8971", "00000000"))
+            .send()
+            .await;
+        assert_eq!(response.status, StatusCode::FORBIDDEN);
+    }
+    let response = DavRequest::new(Method::GET, "/dav/cal/dav@example.com/work/kickoff.ics")
+        .auth(other)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+
+    // Delete the card, deleted members are reported as not found
+    let response = DavRequest::new(Method::DELETE, card_path)
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    let response = DavRequest::new(Method::GET, card_path)
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body(&sync_token))
+        .send()
+        .await;
+    response.assert_multistatus();
+    response.assert_contains(&format!(
+        "<D:href>{card_path}</D:href><D:status>HTTP/1.1 404 Not Found</D:status>"
+    ));
+    let deleted_sync_token = response.sync_token();
+    assert_ne!(deleted_sync_token, sync_token);
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body(&deleted_sync_token))
+        .send()
+        .await;
+    response.assert_multistatus();
+    assert!(!response.body.contains(card_path), "{}", response.body);
+
+    // Unknown sync tokens are rejected
+    let response = DavRequest::new(report(), "/dav/card/dav@example.com/default/")
+        .auth(owner)
+        .body(sync_body("urn:stalwart:sync:invalid"))
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::FORBIDDEN);
+    response.assert_contains("<D:valid-sync-token/>");
+
+    // Delete the calendar along with its events
+    let response = DavRequest::new(Method::DELETE, "/dav/cal/dav@example.com/work/")
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NO_CONTENT);
+    let response = DavRequest::new(Method::GET, event_path)
+        .auth(owner)
+        .send()
+        .await;
+    assert_eq!(response.status, StatusCode::NOT_FOUND);
+
+    // Remove the remaining address books
+    let response = jmap_json_request(
+        r#"[[
+            "AddressBook/set",
+            { "accountId": "$$", "destroy": [ "%%" ] },
+            "R1"
+        ]]"#
+        .replace("$$", &account_id.to_string())
+        .replace("%%", &book_id),
+        owner.0,
+        owner.1,
+    )
+    .await;
+    assert_eq!(
+        response.pointer("/methodResponses/0/1/destroyed/0"),
+        Some(&Value::String(book_id)),
+        "Response: {response:?}"
+    );
+
+    destroy_all_mailboxes(params).await;
+    assert_is_empty(server).await;
+}
+
+struct DavRequest {
+    method: Method,
+    path: String,
+    headers: header::HeaderMap,
+    body: String,
+}
+
+struct DavResponse {
+    status: StatusCode,
+    headers: header::HeaderMap,
+    body: String,
+}
+
+impl DavRequest {
+    fn new(method: Method, path: &str) -> Self {
+        DavRequest {
+            method,
+            path: path.to_string(),
+            headers: header::HeaderMap::new(),
+            body: String::new(),
+        }
+    }
+
+    fn auth(self, (login, secret): (&str, &str)) -> Self {
+        self.header(
+            "authorization",
+            &format!(
+                "Basic {}",
+                general_purpose::STANDARD.encode(format!("{login}:{secret}"))
+            ),
+        )
+    }
+
+    fn depth(self, depth: &str) -> Self {
+        self.header("depth", depth)
+    }
+
+    fn header(mut self, name: &'static str, value: &str) -> Self {
+        self.headers.insert(
+            header::HeaderName::from_static(name),
+            header::HeaderValue::from_str(value).unwrap(),
+        );
+        self
+    }
+
+    fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    async fn send(self) -> DavResponse {
+        let response = reqwest::Client::builder()
+            .danger_accept_invalid_certs(true)
+            .redirect(Policy::none())
+            .timeout(Duration::from_secs(5))
+            .build()
+            .unwrap()
+            .request(self.method, format!("https://127.0.0.1:8899{}", self.path))
+            .headers(self.headers)
+            .body(self.body)
+            .send()
+            .await
+            .unwrap();
+
+        DavResponse {
+            status: response.status(),
+            headers: response.headers().clone(),
+            body: response.text().await.unwrap(),
+        }
+    }
+}
+
+impl DavResponse {
+    fn header(&self, name: &str) -> String {
+        self.headers
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default()
+            .to_string()
+    }
+
+    fn assert_multistatus(&self) {
+        assert_eq!(self.status, StatusCode::MULTI_STATUS, "{}", self.body);
+    }
+
+    fn assert_contains(&self, expected: &str) {
+        assert!(
+            self.body.contains(expected),
+            "Expected {expected:?} in {}",
+            self.body
+        );
+    }
+
+    fn sync_token(&self) -> String {
+        self.body
+            .split_once("<D:sync-token>")
+            .and_then(|(_, token)| token.split_once("</D:sync-token>"))
+            .map(|(token, _)| token.to_string())
+            .unwrap_or_else(|| panic!("Missing sync token in {}", self.body))
+    }
+}
+
+fn propfind() -> Method {
+    Method::from_bytes(b"PROPFIND").unwrap()
+}
+
+fn proppatch() -> Method {
+    Method::from_bytes(b"PROPPATCH").unwrap()
+}
+
+fn report() -> Method {
+    Method::from_bytes(b"REPORT").unwrap()
+}
+
+fn mkcalendar() -> Method {
+    Method::from_bytes(b"MKCALENDAR").unwrap()
+}
+
+fn pointer_str(response: &Value, pointer: &str) -> String {
+    response
+        .pointer(pointer)
+        .and_then(|v| v.as_str())
+        .unwrap_or_else(|| panic!("Missing {pointer} in response: {response:?}"))
+        .to_string()
+}
@@ -0,0 +1,165 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use jmap::{
+    api::management::dkim::{Algorithm, DkimManagement},
+    services::dkim::DkimRotation,
+};
+use mail_auth::common::{parse::TxtRecordParser, verify::DomainKey};
+use serde::Deserialize;
+use store::write::now;
+
+use crate::smtp::DnsCache;
+
+use super::{JMAPTest, ManagementApi};
+
+#[derive(Debug, Deserialize)]
+struct DnsRecord {
+    #[serde(rename = "type")]
+    typ: String,
+    name: String,
+    content: String,
+}
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running DKIM key rotation tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    // Create a signature that is rotated daily
+    server
+        .create_dkim_key(Algorithm::Ed25519, "rotate", "rotate.org", "current")
+        .await
+        .unwrap();
+    set_config(&server, "rotation.frequency", "1d").await;
+
+    // The first run only starts the rotation clock
+    server.dkim_rotate().await;
+    assert!(config(&server, "rotation.last").await.is_some());
+    assert!(config(&server, "rotation.pending.selector").await.is_none());
+
+    // The next key is staged ahead of time and its record is published
+    server.dkim_rotate().await;
+    let pending = config(&server, "rotation.pending.selector")
+        .await
+        .expect("next key was not staged");
+    assert_ne!(pending, "current");
+    assert!(
+        config(&server, "rotation.pending.private-key")
+            .await
+            .is_some()
+    );
+    let records = dkim_records(&api).await;
+    let pending_record = records
+        .iter()
+        .find(|record| record.name == format!("{pending}._domainkey.rotate.org."))
+        .unwrap_or_else(|| panic!("pending key not published: {records:?}"));
+    assert_eq!(pending_record.typ, "TXT");
+    assert!(
+        records
+            .iter()
+            .any(|record| record.name == "current._domainkey.rotate.org."),
+        "{records:?}"
+    );
+
+    // Activation is refused until the record resolves
+    set_config(&server, "rotation.last", &(now() - 2 * 86400).to_string()).await;
+    server.dkim_rotate().await;
+    assert_eq!(config(&server, "selector").await.unwrap(), "current");
+    assert_eq!(
+        config(&server, "rotation.pending.selector").await.unwrap(),
+        pending
+    );
+
+    // Once the record resolves the key is activated and the previous one retired
+    server.txt_add(
+        format!("{pending}._domainkey.rotate.org."),
+        DomainKey::parse(pending_record.content.as_bytes()).unwrap(),
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    server.dkim_rotate().await;
+    assert_eq!(config(&server, "selector").await.unwrap(), pending);
+    assert!(config(&server, "rotation.pending.selector").await.is_none());
+    assert_eq!(
+        config(&server, "rotation.retired.selector").await.unwrap(),
+        "current"
+    );
+    let records = dkim_records(&api).await;
+    for selector in [pending.as_str(), "current"] {
+        assert!(
+            records
+                .iter()
+                .any(|record| record.name == format!("{selector}._domainkey.rotate.org.")),
+            "{selector}: {records:?}"
+        );
+    }
+
+    // The retired key is kept until the grace period is over
+    server.dkim_rotate().await;
+    assert_eq!(
+        config(&server, "rotation.retired.selector").await.unwrap(),
+        "current"
+    );
+    set_config(&server, "rotation.retired.until", &(now() - 1).to_string()).await;
+    server.dkim_rotate().await;
+    assert!(config(&server, "rotation.retired.selector").await.is_none());
+    assert!(
+        config(&server, "rotation.retired.private-key")
+            .await
+            .is_none()
+    );
+    let records = dkim_records(&api).await;
+    assert!(
+        !records
+            .iter()
+            .any(|record| record.name == "current._domainkey.rotate.org."),
+        "{records:?}"
+    );
+
+    // Remove test data
+    server
+        .core
+        .storage
+        .config
+        .clear_prefix("signature.rotate.")
+        .await
+        .unwrap();
+}
+
+async fn config(server: &common::Server, key: &str) -> Option<String> {
+    server
+        .core
+        .storage
+        .config
+        .get(&format!("signature.rotate.{key}"))
+        .await
+        .unwrap()
+}
+
+async fn set_config(server: &common::Server, key: &str, value: &str) {
+    server
+        .core
+        .storage
+        .config
+        .set(
+            [(format!("signature.rotate.{key}"), value.to_string())],
+            true,
+        )
+        .await
+        .unwrap();
+}
+
+async fn dkim_records(api: &ManagementApi) -> Vec<DnsRecord> {
+    api.get::<Vec<DnsRecord>>("/api/dns/records/rotate.org")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .into_iter()
+        .filter(|record| record.name.contains("._domainkey."))
+        .collect()
+}
@@ -0,0 +1,296 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::sync::Arc;
+
+use hickory_client::{
+    op::{Message, MessageType, OpCode, ResponseCode},
+    proto::rr::dnssec::{
+        rdata::tsig::{TSIG, TsigAlgorithm, make_tsig_record, message_tbs},
+        tsig::TSigner,
+    },
+    rr::{DNSClass, Name, RData, Record, RecordType, rdata::TXT},
+};
+use jmap::{
+    api::management::{
+        dkim::{Algorithm, DkimManagement},
+        dns::DnsRecord,
+    },
+    services::dns_sync::{DnsChangeAction, DnsRecordChange},
+};
+use store::{parking_lot::Mutex, write::now};
+use tokio::net::UdpSocket;
+
+use super::{JMAPTest, ManagementApi};
+
+const TSIG_KEY: &str = "stalwart-key";
+const TSIG_SECRET: &[u8] = b"stalwart-dns-sync-test-secret";
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running DNS sync tests...");
+    let server = params.server.clone();
+    let api = ManagementApi::new(8899, "admin", "secret");
+
+    // Start an RFC 2136 server with records that were not created by this server
+    let zone = spawn_mock_dns_server(vec![
+        txt_record("sync.org.", "v=spf1 include:other.net -all"),
+        txt_record("sync.org.", "google-site-verification=1234"),
+    ]);
+    server
+        .create_dkim_key(Algorithm::Ed25519, "sync", "sync.org", "sync")
+        .await
+        .unwrap();
+    let expected = api
+        .get::<Vec<DnsRecord>>("/api/dns/records/sync.org")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .into_iter()
+        .filter(|record| record.name.ends_with("sync.org."))
+        .collect::<Vec<_>>();
+    assert!(
+        expected
+            .iter()
+            .any(|record| record.name == "sync._domainkey.sync.org."),
+        "{expected:?}"
+    );
+
+    // Dry runs report the changes without applying them
+    let changes = sync(&api, true).await;
+    for record in &expected {
+        assert!(
+            has_change(
+                &changes,
+                DnsChangeAction::Create,
+                &record.name,
+                &record.content
+            ),
+            "{record:?} {changes:?}"
+        );
+    }
+    assert!(
+        has_change(
+            &changes,
+            DnsChangeAction::Conflict,
+            "sync.org.",
+            "v=spf1 include:other.net -all"
+        ),
+        "{changes:?}"
+    );
+    assert!(
+        !changes
+            .iter()
+            .any(|change| change.action == DnsChangeAction::Delete
+                || change
+                    .record
+                    .content
+                    .starts_with("google-site-verification")),
+        "{changes:?}"
+    );
+    assert_eq!(zone.lock().len(), 2);
+
+    // Records are created and conflicting records are left alone
+    let changes = sync(&api, false).await;
+    assert!(
+        !changes
+            .iter()
+            .any(|change| change.action == DnsChangeAction::Delete),
+        "{changes:?}"
+    );
+    assert!(zone_has(&zone, "sync._domainkey.sync.org."));
+    assert!(zone_has_txt(&zone, "v=spf1 include:other.net -all"));
+    assert!(zone_has_txt(&zone, "google-site-verification=1234"));
+    let changes = sync(&api, true).await;
+    assert!(
+        changes
+            .iter()
+            .all(|change| change.action == DnsChangeAction::Conflict),
+        "{changes:?}"
+    );
+
+    // Records that are no longer generated are deleted
+    server
+        .core
+        .storage
+        .config
+        .clear_prefix("signature.sync.")
+        .await
+        .unwrap();
+    let changes = sync(&api, false).await;
+    assert!(
+        changes
+            .iter()
+            .any(|change| change.action == DnsChangeAction::Delete
+                && change.record.name == "sync._domainkey.sync.org."),
+        "{changes:?}"
+    );
+    assert!(!zone_has(&zone, "sync._domainkey.sync.org."));
+    assert!(zone_has_txt(&zone, "v=spf1 include:other.net -all"));
+    assert!(zone_has_txt(&zone, "google-site-verification=1234"));
+    let changes = sync(&api, true).await;
+    assert!(
+        changes
+            .iter()
+            .all(|change| change.action == DnsChangeAction::Conflict),
+        "{changes:?}"
+    );
+
+    // Remove test data
+    server
+        .core
+        .storage
+        .config
+        .clear_prefix("dns-sync.bind.published.")
+        .await
+        .unwrap();
+}
+
+async fn sync(api: &ManagementApi, dry_run: bool) -> Vec<DnsRecordChange> {
+    if dry_run {
+        api.get::<Vec<DnsRecordChange>>("/api/dns/sync/sync.org")
+            .await
+    } else {
+        api.post::<Vec<DnsRecordChange>>("/api/dns/sync/sync.org", &())
+            .await
+    }
+    .unwrap()
+    .unwrap_data()
+}
+
+fn has_change(
+    changes: &[DnsRecordChange],
+    action: DnsChangeAction,
+    name: &str,
+    content: &str,
+) -> bool {
+    changes.iter().any(|change| {
+        change.action == action && change.record.name == name && change.record.content == content
+    })
+}
+
+fn zone_has(zone: &Mutex<Vec<Record>>, name: &str) -> bool {
+    let name = Name::from_ascii(name).unwrap();
+    zone.lock().iter().any(|record| record.name() == &name)
+}
+
+fn zone_has_txt(zone: &Mutex<Vec<Record>>, content: &str) -> bool {
+    zone.lock().iter().any(|record| {
+        matches!(record.data(), Some(RData::TXT(txt)) if txt
+            .txt_data()
+            .iter()
+            .flat_map(|data| data.iter().copied())
+            .eq(content.bytes()))
+    })
+}
+
+fn txt_record(name: &str, content: &str) -> Record {
+    Record::from_rdata(
+        Name::from_ascii(name).unwrap(),
+        3600,
+        RData::TXT(TXT::new(vec![content.to_string()])),
+    )
+}
+
+// Minimal stand-in for BIND or Knot, it answers queries from an in-memory zone
+// and applies dynamic updates, all messages are authenticated with TSIG.
+fn spawn_mock_dns_server(zone: Vec<Record>) -> Arc<Mutex<Vec<Record>>> {
+    let zone = Arc::new(Mutex::new(zone));
+    let signer = TSigner::new(
+        TSIG_SECRET.to_vec(),
+        TsigAlgorithm::HmacSha256,
+        Name::from_ascii(TSIG_KEY).unwrap(),
+        300,
+    )
+    .unwrap();
+    let socket = std::net::UdpSocket::bind("127.0.0.1:5354").unwrap();
+    socket.set_nonblocking(true).unwrap();
+    let socket = UdpSocket::from_std(socket).unwrap();
+
+    let zone_ = zone.clone();
+    tokio::spawn(async move {
+        let mut buf = vec![0u8; 65535];
+        loop {
+            let (len, addr) = socket.recv_from(&mut buf).await.unwrap();
+            let response = handle_dns_request(&signer, &zone_, &buf[..len]);
+            socket.send_to(&response, addr).await.unwrap();
+        }
+    });
+
+    zone
+}
+
+fn handle_dns_request(signer: &TSigner, zone: &Mutex<Vec<Record>>, request: &[u8]) -> Vec<u8> {
+    let (request_mac, _, _) = signer
+        .verify_message_byte(None, request, true)
+        .expect("Request is not signed with the shared key");
+    let request = Message::from_vec(request).unwrap();
+    let mut response = Message::new();
+    response
+        .set_id(request.id())
+        .set_message_type(MessageType::Response)
+        .set_op_code(request.op_code())
+        .add_queries(request.queries().to_vec());
+
+    let mut zone = zone.lock();
+    match request.op_code() {
+        OpCode::Query => {
+            let query = &request.queries()[0];
+            response.add_answers(
+                zone.iter()
+                    .filter(|record| {
+                        record.name() == query.name() && record.record_type() == query.query_type()
+                    })
+                    .cloned()
+                    .collect::<Vec<_>>(),
+            );
+        }
+        OpCode::Update => {
+            for update in request.name_servers() {
+                if update.dns_class() == DNSClass::NONE {
+                    zone.retain(|record| {
+                        record.name() != update.name()
+                            || record.record_type() != update.record_type()
+                            || record.data() != update.data()
+                    });
+                } else if update.record_type() != RecordType::ANY {
+                    let mut record = update.clone();
+                    record.set_dns_class(DNSClass::IN);
+                    zone.push(record);
+                }
+            }
+        }
+        _ => {
+            response.set_response_code(ResponseCode::NotImp);
+        }
+    }
+
+    // Responses are signed using the request MAC
+    let pre_tsig = TSIG::new(
+        TsigAlgorithm::HmacSha256,
+        now(),
+        300,
+        Vec::new(),
+        response.id(),
+        0,
+        Vec::new(),
+    );
+    let mac = signer
+        .sign(
+            &message_tbs(
+                Some(&request_mac),
+                &response,
+                &pre_tsig,
+                signer.signer_name(),
+            )
+            .unwrap(),
+        )
+        .unwrap();
+    response.add_tsig(make_tsig_record(
+        signer.signer_name().clone(),
+        pre_tsig.set_mac(mac),
+    ));
+    response.to_vec().unwrap()
+}
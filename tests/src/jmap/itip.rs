@@ -0,0 +1,393 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField, PrincipalValue},
+    Principal, Type,
+};
+use jmap_client::mailbox;
+use jmap_proto::types::id::Id;
+use mail_auth::{common::parse::TxtRecordParser, dmarc::Dmarc, spf::Spf};
+use serde_json::Value;
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty,
+        delivery::SmtpConnection,
+        email_submission::{
+            assert_message_delivery, expect_nothing, spawn_mock_smtp_server, MockMessage,
+        },
+        jmap_json_request,
+        mailbox::destroy_all_mailboxes,
+    },
+    smtp::DnsCache,
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running iMIP scheduling tests...");
+    let server = params.server.clone();
+
+    // Create a meeting room and a regular user
+    server
+        .core
+        .storage
+        .data
+        .create_test_domains(&["room101@example.com"])
+        .await;
+    let room_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .create_principal(
+                Principal::new(0, Type::Location)
+                    .with_field(PrincipalField::Name, "room101".to_string())
+                    .with_field(PrincipalField::Description, "Room 101".to_string())
+                    .with_field(
+                        PrincipalField::Secrets,
+                        PrincipalValue::StringList(vec!["room101pass".to_string()]),
+                    )
+                    .with_field(
+                        PrincipalField::Emails,
+                        PrincipalValue::StringList(vec!["room101@example.com".to_string()]),
+                    )
+                    .with_field(
+                        PrincipalField::Roles,
+                        PrincipalValue::StringList(vec!["user".to_string()]),
+                    ),
+                None,
+                None,
+            )
+            .await
+            .unwrap()
+            .id,
+    )
+    .to_string();
+    let user_id = Id::from(
+        server
+            .core
+            .storage
+            .data
+            .create_test_user(
+                "jdoe@example.com",
+                "12345",
+                "John Doe",
+                &["jdoe@example.com"],
+            )
+            .await,
+    )
+    .to_string();
+
+    // Start mock SMTP server
+    let (mut smtp_rx, smtp_settings) = spawn_mock_smtp_server();
+    server.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    server.txt_add(
+        "remote.org",
+        Spf::parse(b"v=spf1 ip4:127.0.0.1 -all").unwrap(),
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    server.txt_add(
+        "_dmarc.remote.org",
+        Dmarc::parse(b"v=DMARC1; p=none").unwrap(),
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+
+    // Invitations from unverified senders are ignored
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "bill@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "REQUEST",
+            "kickoff@remote.org",
+            0,
+            "20300315T090000Z",
+            "20300315T103000Z",
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert!(room_events(&room_id).await.is_empty());
+
+    // Organizers must match the SPF-aligned sender or the DMARC-aligned From header
+    let mut lmtp = SmtpConnection::connect_port(11202).await;
+    lmtp.ingest(
+        "jane@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "REQUEST",
+            "kickoff@remote.org",
+            0,
+            "20300315T090000Z",
+            "20300315T103000Z",
+        )
+        .replace("From: bill@remote.org", "From: jane@remote.org"),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert!(room_events(&room_id).await.is_empty());
+
+    // Invitations to a free resource are accepted
+    lmtp.ingest(
+        "bill@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "REQUEST",
+            "kickoff@remote.org",
+            0,
+            "20300315T090000Z",
+            "20300315T103000Z",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<room101@example.com>",
+            ["<bill@remote.org>"],
+            "@Subject: Accepted: Project kickoff",
+        ),
+    )
+    .await;
+    let events = room_events(&room_id).await;
+    assert_eq!(events.len(), 1, "{events:?}");
+    assert_eq!(
+        events[0].pointer("/uid").and_then(|v| v.as_str()),
+        Some("kickoff@remote.org")
+    );
+
+    // Overlapping invitations are declined and not stored
+    lmtp.ingest(
+        "jane@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "REQUEST",
+            "review@remote.org",
+            0,
+            "20300315T100000Z",
+            "20300315T110000Z",
+        )
+        .replace("bill@remote.org", "jane@remote.org"),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<room101@example.com>",
+            ["<jane@remote.org>"],
+            "@PARTSTAT=DECLINED",
+        ),
+    )
+    .await;
+    assert_eq!(room_events(&room_id).await.len(), 1);
+
+    // Rescheduling an accepted event does not conflict with itself
+    lmtp.ingest(
+        "bill@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "REQUEST",
+            "kickoff@remote.org",
+            1,
+            "20300315T093000Z",
+            "20300315T110000Z",
+        ),
+    )
+    .await;
+    assert_message_delivery(
+        &mut smtp_rx,
+        MockMessage::new(
+            "<room101@example.com>",
+            ["<bill@remote.org>"],
+            "@PARTSTAT=ACCEPTED",
+        ),
+    )
+    .await;
+    let events = room_events(&room_id).await;
+    assert_eq!(events.len(), 1, "{events:?}");
+    assert_eq!(
+        events[0].pointer("/sequence").and_then(|v| v.as_u64()),
+        Some(1)
+    );
+
+    // Only the organizer can cancel an event
+    lmtp.ingest(
+        "jane@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "CANCEL",
+            "kickoff@remote.org",
+            2,
+            "20300315T093000Z",
+            "20300315T110000Z",
+        )
+        .replace("bill@remote.org", "jane@remote.org"),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert_eq!(room_events(&room_id).await.len(), 1);
+    lmtp.ingest(
+        "bill@remote.org",
+        &["room101@example.com"],
+        &invitation(
+            "CANCEL",
+            "kickoff@remote.org",
+            2,
+            "20300315T093000Z",
+            "20300315T110000Z",
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert!(room_events(&room_id).await.is_empty());
+
+    // Individuals do not reply automatically but can filter invitations
+    let client = &mut params.client;
+    client
+        .set_default_account_id(&user_id)
+        .sieve_script_create(
+            "itip",
+            concat!(
+                "require [\"fileinto\", \"mailbox\", \"vnd.stalwart.expressions\"];\n",
+                "if eval \"itip('method') == 'REQUEST' && ",
+                "itip('organizer') == 'bill@remote.org'\" {\n",
+                "    fileinto :create \"Invitations\";\n",
+                "}\n"
+            ),
+            true,
+        )
+        .await
+        .unwrap();
+    smtp_settings.lock().do_stop = true;
+    lmtp.ingest(
+        "bill@remote.org",
+        &["jdoe@example.com"],
+        &invitation(
+            "REQUEST",
+            "lunch@remote.org",
+            0,
+            "20300315T120000Z",
+            "20300315T130000Z",
+        )
+        .replace("room101@example.com", "jdoe@example.com"),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+    assert_eq!(
+        client
+            .mailbox_query(
+                mailbox::query::Filter::name("Invitations").into(),
+                None::<Vec<_>>,
+            )
+            .await
+            .unwrap()
+            .ids()
+            .len(),
+        1
+    );
+
+    // Remove test data
+    client.sieve_script_deactivate().await.unwrap();
+    let mut request = client.build();
+    request.query_sieve_script();
+    for id in request.send_query_sieve_script().await.unwrap().take_ids() {
+        client.sieve_script_destroy(&id).await.unwrap();
+    }
+    let response = jmap_json_request(
+        r##"[
+            [ "Calendar/query", { "accountId": "$$" }, "R1" ],
+            [
+              "Calendar/set",
+              {
+                "accountId": "$$",
+                "#destroy": { "resultOf": "R1", "name": "Calendar/query", "path": "/ids" }
+              },
+              "R2"
+            ]
+        ]"##
+        .replace("$$", &room_id),
+        "room101",
+        "room101pass",
+    )
+    .await;
+    assert!(
+        response
+            .pointer("/methodResponses/1/1/notDestroyed")
+            .is_none_or(|v| v.is_null()),
+        "Response: {response:?}"
+    );
+    for account_id in [&room_id, &user_id] {
+        params.client.set_default_account_id(account_id);
+        destroy_all_mailboxes(params).await;
+    }
+    assert_is_empty(server).await;
+}
+
+async fn room_events(account_id: &str) -> Vec<Value> {
+    let response = jmap_json_request(
+        r##"[
+            [ "CalendarEvent/query", { "accountId": "$$" }, "R1" ],
+            [
+              "CalendarEvent/get",
+              {
+                "accountId": "$$",
+                "#ids": { "resultOf": "R1", "name": "CalendarEvent/query", "path": "/ids" }
+              },
+              "R2"
+            ]
+        ]"##
+        .replace("$$", account_id),
+        "room101",
+        "room101pass",
+    )
+    .await;
+    response
+        .pointer("/methodResponses/1/1/list")
+        .and_then(|v| v.as_array())
+        .unwrap_or_else(|| panic!("Missing events in response: {response:?}"))
+        .clone()
+}
+
+fn invitation(method: &str, uid: &str, sequence: u32, start: &str, end: &str) -> String {
+    format!(
+        concat!(
+            "From: bill@remote.org\r\n",
+            "To: room101@example.com\r\n",
+            "Subject: Project kickoff\r\n",
+            "Content-Type: text/calendar; method={method}; charset=utf-8\r\n",
+            "\r\n",
+            "BEGIN:VCALENDAR\r\n",
+            "VERSION:2.0\r\n",
+            "PRODID:-//Remote Corp.//Calendar//EN\r\n",
+            "METHOD:{method}\r\n",
+            "BEGIN:VEVENT\r\n",
+            "UID:{uid}\r\n",
+            "SEQUENCE:{sequence}\r\n",
+            "DTSTAMP:20300301T120000Z\r\n",
+            "DTSTART:{start}\r\n",
+            "DTEND:{end}\r\n",
+            "SUMMARY:Project kickoff\r\n",
+            "ORGANIZER;CN=Bill:mailto:bill@remote.org\r\n",
+            "ATTENDEE;CN=Room 101;PARTSTAT=NEEDS-ACTION:mailto:room101@example.com\r\n",
+            "END:VEVENT\r\n",
+            "END:VCALENDAR\r\n"
+        ),
+        method = method,
+        uid = uid,
+        sequence = sequence,
+        start = start,
+        end = end
+    )
+}
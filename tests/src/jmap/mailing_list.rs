@@ -0,0 +1,411 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Instant;
+
+use directory::{
+    backend::internal::{manage::ManageDirectory, PrincipalField, PrincipalValue},
+    Principal, Type,
+};
+use jmap_proto::types::id::Id;
+use mail_auth::{common::parse::TxtRecordParser, dmarc::Dmarc, spf::Spf};
+
+use crate::{
+    directory::internal::TestInternalDirectory,
+    jmap::{
+        assert_is_empty,
+        delivery::SmtpConnection,
+        email_submission::{expect_message_delivery, expect_nothing, spawn_mock_smtp_server},
+        mailbox::destroy_all_mailboxes,
+    },
+    smtp::DnsCache,
+};
+
+use super::JMAPTest;
+
+pub async fn test(params: &mut JMAPTest) {
+    println!("Running mailing list tests...");
+    let server = params.server.clone();
+
+    // Create a managed list with one external subscriber
+    server
+        .core
+        .storage
+        .data
+        .create_test_domains(&["announce@example.com"])
+        .await;
+    let list_id = server
+        .core
+        .storage
+        .data
+        .create_principal(
+            Principal::new(0, Type::List)
+                .with_field(PrincipalField::Name, "announce".to_string())
+                .with_field(PrincipalField::Description, "Announcements".to_string())
+                .with_field(
+                    PrincipalField::Emails,
+                    PrincipalValue::StringList(vec!["announce@example.com".to_string()]),
+                )
+                .with_field(
+                    PrincipalField::ExternalMembers,
+                    PrincipalValue::StringList(vec!["alice@remote.org".to_string()]),
+                )
+                .with_field(
+                    PrincipalField::Moderators,
+                    PrincipalValue::StringList(vec!["mod@remote.org".to_string()]),
+                )
+                .with_field(
+                    PrincipalField::ListOptions,
+                    PrincipalValue::StringList(vec![
+                        "managed".to_string(),
+                        "subscribe".to_string(),
+                        "archive".to_string(),
+                    ]),
+                ),
+            None,
+            None,
+        )
+        .await
+        .unwrap()
+        .id;
+
+    // Start mock SMTP server
+    let (mut smtp_rx, _smtp_settings) = spawn_mock_smtp_server();
+    server.ipv4_add(
+        "localhost",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    server.txt_add(
+        "remote.org",
+        Spf::parse(b"v=spf1 ip4:127.0.0.1 -all").unwrap(),
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+    server.txt_add(
+        "_dmarc.remote.org",
+        Dmarc::parse(b"v=DMARC1; p=none").unwrap(),
+        Instant::now() + std::time::Duration::from_secs(10),
+    );
+
+    // Posts from unverified senders are held for moderation
+    let mut lmtp = SmtpConnection::connect().await;
+    lmtp.ingest(
+        "alice@remote.org",
+        &["announce@example.com"],
+        &post("alice@remote.org", "Unverified"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.rcpt_to, ["<mod@remote.org>"]);
+    assert!(message.message.contains("approval required: Unverified"));
+    expect_nothing(&mut smtp_rx).await;
+
+    // Posting rights are granted to the envelope sender, not the From header
+    let mut lmtp = SmtpConnection::connect_port(11202).await;
+    lmtp.ingest(
+        "bob@remote.org",
+        &["announce@example.com"],
+        &post("alice@remote.org", "Spoofed"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.rcpt_to, ["<mod@remote.org>"]);
+    assert!(message.message.contains("approval required: Spoofed"));
+    expect_nothing(&mut smtp_rx).await;
+
+    // Posts from members are distributed with list headers
+    lmtp.ingest(
+        "alice@remote.org",
+        &["announce@example.com"],
+        &post("alice@remote.org", "Hello list"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_bounce_address(&message.mail_from);
+    assert_eq!(message.rcpt_to, ["<alice@remote.org>"]);
+    for needle in [
+        "List-Id: \"Announcements\" <announce.example.com>",
+        "List-Post: <mailto:announce@example.com>",
+        "List-Subscribe: <mailto:announce+subscribe@example.com>",
+        "List-Unsubscribe-Post: List-Unsubscribe=One-Click",
+        "<mailto:announce+unsubscribe@example.com>",
+        "Subject: Hello list",
+    ] {
+        assert!(
+            message.message.contains(needle),
+            "{needle}: {}",
+            message.message
+        );
+    }
+    let unsubscribe_token = extract_token(&message.message, "/list/unsubscribe/");
+
+    // Posts from non-members are held for moderation
+    lmtp.ingest(
+        "bob@remote.org",
+        &["announce@example.com"],
+        &post("bob@remote.org", "Please approve"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.rcpt_to, ["<mod@remote.org>"]);
+    assert!(message
+        .message
+        .contains("approval required: Please approve"));
+    let token = extract_token(&message.message, "announce+approve-");
+    expect_nothing(&mut smtp_rx).await;
+
+    // Only moderators can approve messages
+    lmtp.ingest_with_code(
+        "bob@remote.org",
+        &[&format!("announce+approve-{token}@example.com")],
+        &post("bob@remote.org", "Approve"),
+        5,
+    )
+    .await;
+    lmtp.ingest(
+        "mod@remote.org",
+        &[&format!("announce+approve-{token}@example.com")],
+        &post("mod@remote.org", "Approve"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_bounce_address(&message.mail_from);
+    assert_eq!(message.rcpt_to, ["<alice@remote.org>"]);
+    assert!(message.message.contains("Subject: Please approve"));
+
+    // Messages that already went through the list are discarded
+    lmtp.ingest(
+        "alice@remote.org",
+        &["announce@example.com"],
+        &format!(
+            "X-Loop: announce@example.com\r\n{}",
+            post("alice@remote.org", "Loop")
+        ),
+    )
+    .await;
+    expect_nothing(&mut smtp_rx).await;
+
+    // Subscription requests must be confirmed
+    lmtp.ingest(
+        "bob@remote.org",
+        &["announce+subscribe@example.com"],
+        &post("bob@remote.org", "Subscribe"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_eq!(message.rcpt_to, ["<bob@remote.org>"]);
+    let token = extract_token(&message.message, "announce+confirm-");
+    assert!(
+        !list_has(
+            &server,
+            list_id,
+            PrincipalField::ExternalMembers,
+            "bob@remote.org"
+        )
+        .await
+    );
+    lmtp.ingest(
+        "bob@remote.org",
+        &[&format!("announce+confirm-{token}@example.com")],
+        &post("bob@remote.org", "Confirm"),
+    )
+    .await;
+    assert!(
+        list_has(
+            &server,
+            list_id,
+            PrincipalField::ExternalMembers,
+            "bob@remote.org"
+        )
+        .await
+    );
+
+    // Tokens can only be used once
+    lmtp.ingest_with_code(
+        "bob@remote.org",
+        &[&format!("announce+confirm-{token}@example.com")],
+        &post("bob@remote.org", "Confirm"),
+        5,
+    )
+    .await;
+
+    // Each subscriber gets its own bounce address
+    lmtp.ingest(
+        "alice@remote.org",
+        &["announce@example.com"],
+        &post("alice@remote.org", "Welcome Bob"),
+    )
+    .await;
+    let mut bob_bounces = String::new();
+    for _ in 0..2 {
+        let message = expect_message_delivery(&mut smtp_rx).await;
+        assert_bounce_address(&message.mail_from);
+        if message.rcpt_to == ["<bob@remote.org>"] {
+            bob_bounces = message.mail_from.trim_matches(['<', '>']).to_string();
+        }
+    }
+    assert!(!bob_bounces.is_empty());
+    expect_nothing(&mut smtp_rx).await;
+
+    // Bounces that do not match a subscriber's bounce address are not counted
+    for _ in 0..5 {
+        lmtp.ingest("", &["announce+bounces@example.com"], DSN)
+            .await;
+    }
+    assert!(
+        !list_has(
+            &server,
+            list_id,
+            PrincipalField::DisabledMembers,
+            "bob@remote.org"
+        )
+        .await
+    );
+
+    // Subscribers are disabled after repeated bounces
+    for _ in 0..5 {
+        lmtp.ingest("", &[&bob_bounces], DSN).await;
+    }
+    assert!(
+        list_has(
+            &server,
+            list_id,
+            PrincipalField::DisabledMembers,
+            "bob@remote.org"
+        )
+        .await
+    );
+    lmtp.ingest(
+        "alice@remote.org",
+        &["announce@example.com"],
+        &post("alice@remote.org", "Bob is gone"),
+    )
+    .await;
+    let message = expect_message_delivery(&mut smtp_rx).await;
+    assert_bounce_address(&message.mail_from);
+    assert_eq!(message.rcpt_to, ["<alice@remote.org>"]);
+    assert!(message.message.contains("Subject: Bob is gone"));
+    expect_nothing(&mut smtp_rx).await;
+
+    // One-click unsubscribe
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .post(format!(
+            "https://127.0.0.1:8899/list/unsubscribe/{unsubscribe_token}"
+        ))
+        .body("List-Unsubscribe=One-Click")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 200);
+    assert!(
+        !list_has(
+            &server,
+            list_id,
+            PrincipalField::ExternalMembers,
+            "alice@remote.org"
+        )
+        .await
+    );
+    let response = reqwest::Client::builder()
+        .danger_accept_invalid_certs(true)
+        .build()
+        .unwrap()
+        .post("https://127.0.0.1:8899/list/unsubscribe/00000001abcdef")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status().as_u16(), 404);
+
+    // Remove test data
+    params
+        .client
+        .set_default_account_id(Id::from(list_id).to_string());
+    destroy_all_mailboxes(params).await;
+    server
+        .core
+        .storage
+        .data
+        .delete_principal(directory::QueryBy::Id(list_id))
+        .await
+        .unwrap();
+    assert_is_empty(server).await;
+}
+
+async fn list_has(
+    server: &common::Server,
+    list_id: u32,
+    field: PrincipalField,
+    address: &str,
+) -> bool {
+    server
+        .core
+        .storage
+        .data
+        .get_principal(list_id)
+        .await
+        .unwrap()
+        .unwrap()
+        .iter_str(field)
+        .any(|item| item == address)
+}
+
+fn assert_bounce_address(mail_from: &str) {
+    assert!(
+        mail_from.starts_with("<announce+bounces-") && mail_from.ends_with("@example.com>"),
+        "{mail_from}"
+    );
+}
+
+fn extract_token(message: &str, marker: &str) -> String {
+    let start = message
+        .find(marker)
+        .unwrap_or_else(|| panic!("{marker} not found in {message}"))
+        + marker.len();
+    message[start..]
+        .chars()
+        .take_while(|ch| ch.is_ascii_hexdigit())
+        .collect()
+}
+
+fn post(from: &str, subject: &str) -> String {
+    format!(
+        concat!(
+            "From: <{}>\r\n",
+            "To: <announce@example.com>\r\n",
+            "Subject: {}\r\n",
+            "\r\n",
+            "Test message.\r\n"
+        ),
+        from, subject
+    )
+}
+
+const DSN: &str = concat!(
+    "From: <MAILER-DAEMON@remote.org>\r\n",
+    "To: <announce+bounces@example.com>\r\n",
+    "Subject: Delivery Status Notification\r\n",
+    "MIME-Version: 1.0\r\n",
+    "Content-Type: multipart/report; report-type=\"delivery-status\"; ",
+    "boundary=\"dsn\"\r\n",
+    "\r\n",
+    "--dsn\r\n",
+    "Content-Type: text/plain\r\n",
+    "\r\n",
+    "Delivery failed.\r\n",
+    "--dsn\r\n",
+    "Content-Type: message/delivery-status\r\n",
+    "\r\n",
+    "Reporting-MTA: dns; mx.remote.org\r\n",
+    "\r\n",
+    "Final-Recipient: rfc822; bob@remote.org\r\n",
+    "Action: failed\r\n",
+    "Status: 5.1.1\r\n",
+    "\r\n",
+    "--dsn--\r\n",
+);
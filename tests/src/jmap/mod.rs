@@ -52,8 +52,12 @@ pub mod auth_acl;
 pub mod auth_limits;
 pub mod auth_oauth;
 pub mod blob;
+pub mod contacts_calendars;
 pub mod crypto;
+pub mod dav;
 pub mod delivery;
+pub mod dkim_rotation;
+pub mod dns_sync;
 pub mod email_changes;
 pub mod email_copy;
 pub mod email_get;
@@ -65,7 +69,9 @@ pub mod email_set;
 pub mod email_submission;
 pub mod enterprise;
 pub mod event_source;
+pub mod itip;
 pub mod mailbox;
+pub mod mailing_list;
 pub mod permissions;
 pub mod purge;
 pub mod push_subscription;
@@ -100,6 +106,12 @@ greeting = 'Test LMTP instance'
 protocol = 'lmtp'
 tls.implicit = false
 
+[server.listener.lmtp-auth]
+bind = ['127.0.0.1:11202']
+greeting = 'Test LMTP instance'
+protocol = 'lmtp'
+tls.implicit = false
+
 [server.listener.pop3]
 bind = ["127.0.0.1:4110"]
 protocol = "pop3"
@@ -123,6 +135,14 @@ rate-limit = "100/2s"
 [session.ehlo]
 reject-non-fqdn = false
 
+[auth.spf.verify]
+mail-from = [ { if = "local_port == 11202", then = "relaxed" }, 
+              { else = "disable" } ]
+
+[auth.dmarc]
+verify = [ { if = "local_port == 11202", then = "relaxed" }, 
+           { else = "disable" } ]
+
 [session.rcpt]
 relay = [ { if = "!is_empty(authenticated_as)", then = true }, 
           { else = false } ]
@@ -167,6 +187,16 @@ protocol = "smtp"
 implicit = false
 allow-invalid-certs = true
 
+[dns-sync.bind]
+provider = "rfc2136-tsig"
+host = "127.0.0.1"
+port = 5354
+tsig-algorithm = "hmac-sha256"
+key = "stalwart-key"
+secret = "c3RhbHdhcnQtZG5zLXN5bmMtdGVzdC1zZWNyZXQ="
+domains = ["sync.org"]
+timeout = "2s"
+
 [session.extensions]
 future-release = [ { if = "!is_empty(authenticated_as)", then = "99999999d"},
                    { else = false } ]
@@ -396,6 +426,12 @@ pub async fn jmap_tests() {
     quota::test(&mut params).await;
     crypto::test(&mut params).await;
     blob::test(&mut params).await;
+    contacts_calendars::test(&mut params).await;
+    dav::test(&mut params).await;
+    itip::test(&mut params).await;
+    mailing_list::test(&mut params).await;
+    dkim_rotation::test(&mut params).await;
+    dns_sync::test(&mut params).await;
     permissions::test(&params).await;
     purge::test(&mut params).await;
     enterprise::test(&mut params).await;
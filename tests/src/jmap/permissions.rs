@@ -607,6 +607,9 @@ pub async fn test(params: &JMAPTest) {
         server
             .deliver_message(IngestMessage {
                 sender_address: "bill@foobar.org".to_string(),
+                sender_authenticated: false,
+                spf_pass: false,
+                dmarc_pass: false,
                 recipients: vec!["john@foobar.org".to_string()],
                 message_blob: message_blob.clone(),
                 message_size: TEST_MESSAGE.len(),
@@ -645,6 +648,9 @@ pub async fn test(params: &JMAPTest) {
         server
             .deliver_message(IngestMessage {
                 sender_address: "bill@foobar.org".to_string(),
+                sender_authenticated: false,
+                spf_pass: false,
+                dmarc_pass: false,
                 recipients: vec!["john@foobar.org".to_string()],
                 message_blob: message_blob.clone(),
                 message_size: TEST_MESSAGE.len(),
@@ -670,6 +676,9 @@ pub async fn test(params: &JMAPTest) {
         server
             .deliver_message(IngestMessage {
                 sender_address: "bill@foobar.org".to_string(),
+                sender_authenticated: false,
+                spf_pass: false,
+                dmarc_pass: false,
                 recipients: vec!["john@foobar.org".to_string()],
                 message_blob,
                 message_size: TEST_MESSAGE.len(),
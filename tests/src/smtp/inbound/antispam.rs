@@ -29,7 +29,8 @@ use smtp::core::{Session, SessionAddress};
 use smtp_proto::{MAIL_BODY_8BITMIME, MAIL_SMTPUTF8};
 use spam_filter::{
     analysis::{
-        bayes::SpamFilterAnalyzeBayes, date::SpamFilterAnalyzeDate, dmarc::SpamFilterAnalyzeDmarc,
+        attachment::SpamFilterAnalyzeAttachment, bayes::SpamFilterAnalyzeBayes,
+        date::SpamFilterAnalyzeDate, dmarc::SpamFilterAnalyzeDmarc,
         domain::SpamFilterAnalyzeDomain, ehlo::SpamFilterAnalyzeEhlo, from::SpamFilterAnalyzeFrom,
         headers::SpamFilterAnalyzeHeaders, html::SpamFilterAnalyzeHtml, init::SpamFilterInit,
         ip::SpamFilterAnalyzeIp, llm::SpamFilterAnalyzeLlm, messageid::SpamFilterAnalyzeMid,
@@ -326,6 +327,7 @@ async fn antispam() {
         "url",
         "html",
         "mime",
+        "attachment",
         "bounce",
         "dmarc",
         "rbl",
@@ -532,6 +534,7 @@ async fn antispam() {
                         dmarc_policy.as_ref(),
                     )
                     .await
+                    .0
                 {
                     SpamFilterAction::Allow(header) => {
                         let mut last_ch = 'x';
@@ -610,6 +613,9 @@ async fn antispam() {
                 "mime" => {
                     server.spam_filter_analyze_mime(&mut spam_ctx).await;
                 }
+                "attachment" => {
+                    server.spam_filter_analyze_attachment(&mut spam_ctx).await;
+                }
                 "headers" => {
                     server.spam_filter_analyze_headers(&mut spam_ctx).await;
                     server.spam_filter_analyze_rules(&mut spam_ctx).await;
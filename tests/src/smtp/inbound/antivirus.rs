@@ -0,0 +1,301 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use common::{config::server::ServerProtocol, listener::ServerInstance};
+use reqwest::Method;
+use serde_json::Value;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{
+    jmap::ManagementApi,
+    smtp::{
+        inbound::TestMessage,
+        management::queue::List,
+        session::{test_server_instance, TestSession, VerifyResponse},
+        TestSMTP,
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[session.rcpt]
+relay = true
+
+[spam-filter.antivirus]
+enable = true
+address = "127.0.0.1:9334"
+timeout = "5s"
+cache = "1h"
+
+[spam-filter.header.result]
+name = "X-Spam-Result"
+
+[session.data]
+spam-filter = "listener != 'nospam'"
+
+[session.data.antivirus]
+action = [ { if = "listener == 'quarantine'", then = "quarantine" },
+           { if = "listener == 'tag'", then = "tag" },
+           { if = "listener == 'trusted'", then = "disable" },
+           { else = "reject" } ]
+"#;
+
+const EICAR: &str = r"X5O!P%@AP[4\PZX54(P^)7CC)7}$EICAR-STANDARD-ANTIVIRUS-TEST-FILE!$H+H*";
+
+#[tokio::test]
+#[serial_test::serial]
+async fn antivirus_scan() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start clamd stand-in and test server
+    let scans = spawn_mock_clamd_server();
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let mut local = TestSMTP::new("smtp_antivirus_test", CONFIG).await;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+    let infected = message("Invoice", EICAR);
+    let clean = message("Lunch", "Are you hungry yet?");
+
+    // Infected messages are rejected by default
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &infected,
+            "550 5.7.1",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+    assert_eq!(scans.load(Ordering::Relaxed), 1);
+
+    // Verdicts are cached by content hash
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &infected,
+            "550 5.7.1",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+    assert_eq!(scans.load(Ordering::Relaxed), 1);
+
+    // Clean messages are accepted
+    session
+        .send_message("bill@remote.org", &["john@foobar.org"], &clean, "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_not_contains("VIRUS_FOUND")
+        .assert_contains("X-Virus-Status: Clean")
+        .assert_contains("Subject: Lunch");
+    assert_eq!(scans.load(Ordering::Relaxed), 2);
+
+    // Tag infected messages on the 'tag' listener
+    session.instance = listener("tag");
+    session
+        .send_message("bill@remote.org", &["john@foobar.org"], &infected, "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("VIRUS_FOUND")
+        .assert_contains("X-Virus-Status: Infected (Eicar-Test-Signature)")
+        .assert_contains("Subject: Invoice");
+
+    // Scan failures are tagged and the message is accepted
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &message("Error", "CLAMD_ERROR"),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("VIRUS_SCAN_FAIL")
+        .assert_contains("X-Virus-Status: Scan failed")
+        .assert_not_contains("VIRUS_FOUND");
+    assert_eq!(scans.load(Ordering::Relaxed), 3);
+
+    // Scanning can be disabled per listener
+    session.instance = listener("trusted");
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &message("Trusted", EICAR),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_not_contains("VIRUS_FOUND")
+        .assert_not_contains("X-Virus-Status")
+        .assert_contains("Subject: Trusted");
+    assert_eq!(scans.load(Ordering::Relaxed), 3);
+
+    // Quarantine infected messages on the 'quarantine' listener
+    session.instance = listener("quarantine");
+    session
+        .send_message("bill@remote.org", &["john@foobar.org"], &infected, "250")
+        .await;
+    local.queue_receiver.assert_no_events();
+    let items = ManagementApi::default()
+        .request::<List<Value>>(Method::GET, "/api/quarantine")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items;
+    assert_eq!(items.len(), 1, "{items:?}");
+    assert_eq!(items[0]["source"], "antivirus");
+    assert_eq!(items[0]["reason"], "Malware detected: Eicar-Test-Signature");
+    assert_eq!(scans.load(Ordering::Relaxed), 3);
+
+    // Scanning does not depend on the spam filter
+    session.instance = listener("nospam");
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &infected,
+            "550 5.7.1",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+    session
+        .send_message("bill@remote.org", &["john@foobar.org"], &clean, "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_not_contains("X-Spam-Result")
+        .assert_contains("X-Virus-Status: Clean");
+    assert_eq!(scans.load(Ordering::Relaxed), 3);
+}
+
+fn message(subject: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "From: Bill <bill@remote.org>\r\n",
+            "To: john@foobar.org\r\n",
+            "Subject: {}\r\n",
+            "\r\n",
+            "{}\r\n"
+        ),
+        subject, body
+    )
+}
+
+fn listener(id: &str) -> Arc<ServerInstance> {
+    Arc::new(ServerInstance {
+        id: id.to_string(),
+        ..test_server_instance()
+    })
+}
+
+fn spawn_mock_clamd_server() -> Arc<AtomicUsize> {
+    let scans = Arc::new(AtomicUsize::new(0));
+    let scans_ = scans.clone();
+
+    tokio::spawn(async move {
+        let listener = TcpListener::bind("127.0.0.1:9334")
+            .await
+            .unwrap_or_else(|e| {
+                panic!("Failed to bind mock clamd server to 127.0.0.1:9334: {e}");
+            });
+
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let scans = scans_.clone();
+            tokio::spawn(async move {
+                if let Err(err) = handle_clamd_session(stream, scans).await {
+                    panic!("Mock clamd session failed: {err}");
+                }
+            });
+        }
+    });
+
+    scans
+}
+
+async fn handle_clamd_session(
+    mut stream: TcpStream,
+    scans: Arc<AtomicUsize>,
+) -> std::io::Result<()> {
+    let mut command = [0u8; 10];
+    stream.read_exact(&mut command).await?;
+    assert_eq!(&command, b"zINSTREAM\0");
+
+    let mut contents = Vec::new();
+    loop {
+        let size = stream.read_u32().await? as usize;
+        if size == 0 {
+            break;
+        }
+        let offset = contents.len();
+        contents.resize(offset + size, 0);
+        stream.read_exact(&mut contents[offset..]).await?;
+    }
+    scans.fetch_add(1, Ordering::Relaxed);
+
+    let contents = String::from_utf8_lossy(&contents);
+    let response: &[u8] = if contents.contains(EICAR) {
+        b"stream: Eicar-Test-Signature FOUND\0"
+    } else if contents.contains("CLAMD_ERROR") {
+        b"INSTREAM size limit exceeded. ERROR\0"
+    } else {
+        b"stream: OK\0"
+    };
+    stream.write_all(response).await?;
+    stream.flush().await
+}
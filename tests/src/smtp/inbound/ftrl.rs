@@ -0,0 +1,155 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::MessageParser;
+use spam_filter::{
+    analysis::init::SpamFilterInit,
+    modules::{bayes::BayesClassifier, ftrl::FtrlClassifier},
+    SpamFilterInput,
+};
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[session.rcpt]
+relay = true
+
+[spam-filter.bayes]
+model = "ftrl"
+classify.learns = 10
+auto-learn.enable = false
+
+[spam-filter.bayes.ftrl]
+alpha = 0.5
+l1 = 0.1
+
+[spam-filter.header.result]
+name = "X-Spam-Result"
+"#;
+
+const SPAM: &[(&str, &str)] = &[
+    (
+        "Cheap pills online",
+        "buy cheap pills online now without prescription limited offer act fast",
+    ),
+    (
+        "You won a prize",
+        "congratulations you won a free prize claim your reward now limited offer",
+    ),
+    (
+        "Replica watches",
+        "cheap replica watches buy online now best prices limited offer act fast",
+    ),
+];
+
+const HAM: &[(&str, &str)] = &[
+    (
+        "Meeting notes",
+        "attached are the meeting notes from the quarterly review please add your comments",
+    ),
+    (
+        "Lunch on Thursday",
+        "could we move our lunch to thursday afternoon i have a conflict with the review",
+    ),
+    (
+        "Roadmap draft",
+        "please review the attached roadmap draft before the planning meeting next week",
+    ),
+];
+
+#[tokio::test]
+#[serial_test::serial]
+async fn ftrl_classifier() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_ftrl_test", CONFIG).await;
+
+    // Train the model
+    for _ in 0..5 {
+        for (messages, is_spam) in [(SPAM, true), (HAM, false)] {
+            for (subject, body) in messages {
+                let message = message(subject, body);
+                let parsed = MessageParser::new().parse(message.as_bytes()).unwrap();
+                local
+                    .server
+                    .bayes_train(
+                        &local
+                            .server
+                            .spam_filter_init(SpamFilterInput::from_message(&parsed, 0)),
+                        is_spam,
+                        true,
+                    )
+                    .await
+                    .unwrap();
+            }
+        }
+    }
+    let learns = local.server.ftrl_learns(None).await.unwrap();
+    assert_eq!((learns.spam, learns.ham), (15, 15));
+
+    // Classify messages
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+
+    for (subject, body, tag) in [
+        (
+            "Cheap watches",
+            "buy cheap watches online now limited offer act fast",
+            "BAYES_SPAM",
+        ),
+        (
+            "Review meeting",
+            "please add your comments to the roadmap before the review meeting",
+            "BAYES_HAM",
+        ),
+    ] {
+        session
+            .send_message(
+                "sender@remote.org",
+                &["john@foobar.org"],
+                &message(subject, body),
+                "250",
+            )
+            .await;
+        local
+            .queue_receiver
+            .expect_message()
+            .await
+            .read_lines(&local.queue_receiver)
+            .await
+            .assert_contains(tag);
+    }
+
+    // The Bayes model is not trained when FTRL is selected
+    assert_eq!(
+        local
+            .server
+            .bayes_weights_for_token(None, Default::default())
+            .await
+            .unwrap(),
+        Default::default()
+    );
+}
+
+fn message(subject: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "From: Sender <sender@remote.org>\r\n",
+            "To: john@foobar.org\r\n",
+            "Subject: {}\r\n",
+            "\r\n",
+            "{}\r\n"
+        ),
+        subject, body
+    )
+}
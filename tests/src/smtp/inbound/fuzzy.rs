@@ -0,0 +1,175 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use mail_parser::MessageParser;
+use spam_filter::{analysis::init::SpamFilterInit, modules::fuzzy::FuzzyDatabase, SpamFilterInput};
+
+use crate::smtp::{
+    inbound::TestMessage,
+    session::{TestSession, VerifyResponse},
+    TestSMTP,
+};
+
+const CONFIG: &str = r#"
+[session.rcpt]
+relay = true
+
+[spam-filter.fuzzy]
+enable = true
+count.bulk = 3
+count.spam = 1
+ratio = 0.5
+
+[spam-filter.header.result]
+name = "X-Spam-Result"
+"#;
+
+const NEWSLETTER: &str = concat!(
+    "dear {name} we are pleased to announce our exclusive spring collection ",
+    "with discounts on every item in the store visit our website today to ",
+    "discover the latest arrivals and claim your personal voucher before the ",
+    "offer expires this weekend our customer service team is available around ",
+    "the clock to answer any questions about shipping returns or product ",
+    "availability thank you for being a valued member of our community and we ",
+    "look forward to seeing you soon best regards the marketing team"
+);
+
+const PERSONAL: &str = concat!(
+    "hi john could we move tomorrow's meeting to the afternoon i have a conflict ",
+    "in the morning with the budget review and would prefer to discuss the ",
+    "quarterly roadmap after lunch let me know if three pm works for you and ",
+    "whether we should invite the design team as well since they had questions ",
+    "about the timeline for the new onboarding flow thanks a lot and see you ",
+    "tomorrow cheers mark"
+);
+
+#[tokio::test]
+#[serial_test::serial]
+async fn fuzzy_hash() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_fuzzy_test", CONFIG).await;
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+
+    // Near-duplicate messages are counted until they reach the bulk threshold
+    for (name, is_bulk) in [("john", false), ("alice", false), ("robert", true)] {
+        session
+            .send_message(
+                "news@remote.org",
+                &["john@foobar.org"],
+                &message("Spring sale", &NEWSLETTER.replace("{name}", name)),
+                "250",
+            )
+            .await;
+        let lines = local
+            .queue_receiver
+            .expect_message()
+            .await
+            .read_lines(&local.queue_receiver)
+            .await
+            .assert_not_contains("FUZZY_SPAM");
+        if is_bulk {
+            lines.assert_contains("FUZZY_BULK");
+        } else {
+            lines.assert_not_contains("FUZZY_BULK");
+        }
+    }
+
+    // Spam reports apply to similar messages
+    let report = message("Spring sale", &NEWSLETTER.replace("{name}", "jane"));
+    let parsed = MessageParser::new().parse(report.as_bytes()).unwrap();
+    local
+        .server
+        .fuzzy_train(
+            &local
+                .server
+                .spam_filter_init(SpamFilterInput::from_message(&parsed, 0)),
+            true,
+        )
+        .await
+        .unwrap();
+    session
+        .send_message(
+            "news@remote.org",
+            &["john@foobar.org"],
+            &message("Spring sale", &NEWSLETTER.replace("{name}", "peter")),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("FUZZY_BULK")
+        .assert_contains("FUZZY_SPAM");
+
+    // Unrelated messages are not affected
+    session
+        .send_message(
+            "mark@remote.org",
+            &["john@foobar.org"],
+            &message("Meeting", PERSONAL),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_not_contains("FUZZY_BULK")
+        .assert_not_contains("FUZZY_SPAM");
+
+    // Ham reports outweigh spam reports
+    for _ in 0..2 {
+        local
+            .server
+            .fuzzy_train(
+                &local
+                    .server
+                    .spam_filter_init(SpamFilterInput::from_message(&parsed, 0)),
+                false,
+            )
+            .await
+            .unwrap();
+    }
+    session
+        .send_message(
+            "news@remote.org",
+            &["john@foobar.org"],
+            &message("Spring sale", &NEWSLETTER.replace("{name}", "susan")),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("FUZZY_BULK")
+        .assert_not_contains("FUZZY_SPAM");
+}
+
+fn message(subject: &str, body: &str) -> String {
+    format!(
+        concat!(
+            "From: Sender <sender@remote.org>\r\n",
+            "To: john@foobar.org\r\n",
+            "Subject: {}\r\n",
+            "\r\n",
+            "{}\r\n"
+        ),
+        subject, body
+    )
+}
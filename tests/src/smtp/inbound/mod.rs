@@ -21,12 +21,15 @@ use smtp::queue::{Message, QueueId, QueuedMessage};
 use super::{QueueReceiver, ReportReceiver};
 
 pub mod antispam;
+pub mod antivirus;
 pub mod asn;
 pub mod auth;
 pub mod basic;
 pub mod data;
 pub mod dmarc;
 pub mod ehlo;
+pub mod ftrl;
+pub mod fuzzy;
 pub mod limits;
 pub mod mail;
 pub mod milter;
@@ -34,6 +37,7 @@ pub mod rcpt;
 pub mod rewrite;
 pub mod scripts;
 pub mod sign;
+pub mod srs;
 pub mod throttle;
 pub mod vrfy;
 
@@ -141,6 +145,7 @@ impl QueueReceiver {
         QueuedMessage {
             due: self.message_due(queue_id).await,
             queue_id,
+            queue: None,
         }
     }
 
@@ -0,0 +1,210 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::smtp::auth::SrsConfig;
+use smtp::queue::srs::{srs_forward, srs_reverse, SmtpSrs, SrsError};
+use store::write::now;
+
+use crate::smtp::{session::TestSession, TestSMTP};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = "john@example.org"
+
+[session.rcpt]
+directory = "'local'"
+relay = [{if = "remote_ip = '10.0.0.1'", then = true},
+         {else = false}]
+
+[session.rcpt.errors]
+total = 100
+wait = "1ms"
+
+[auth.srs]
+enable = true
+domain = "example.org"
+secret = ["new-secret", "old-secret"]
+max-age = "10d"
+"#;
+
+#[tokio::test]
+async fn srs() {
+    // Enable logging
+    crate::enable_logging();
+
+    let mut test = TestSMTP::new("smtp_srs_test", CONFIG).await;
+    let config = test.server.core.smtp.mail_auth.srs.clone();
+    let now = now();
+
+    // SRS0 rewriting
+    let srs0 = srs_forward(&config, "example.org", "Bill@Remote.net", now).unwrap();
+    assert!(srs0.starts_with("SRS0="), "{srs0}");
+    assert!(srs0.ends_with("=Remote.net=Bill@example.org"), "{srs0}");
+    assert_eq!(
+        srs_reverse(&config, "example.org", &srs0, now),
+        Ok(Some("Bill@Remote.net".to_string()))
+    );
+    assert_eq!(
+        srs_reverse(&config, "example.org", &srs0.to_lowercase(), now),
+        Ok(Some("bill@remote.net".to_string()))
+    );
+    assert_eq!(
+        srs_reverse(&config, "example.org", "john@example.org", now),
+        Ok(None)
+    );
+    assert_eq!(
+        srs_reverse(
+            &config,
+            "example.org",
+            &srs0.replace("@example.org", "@other.org"),
+            now
+        ),
+        Ok(None)
+    );
+
+    // Expired and tampered addresses are rejected
+    assert_eq!(
+        srs_reverse(&config, "example.org", &srs0, now + 11 * 86400),
+        Err(SrsError::Expired)
+    );
+    let tampered = srs0.replace("=Bill@", "=Jane@");
+    assert_eq!(
+        srs_reverse(&config, "example.org", &tampered, now),
+        Err(SrsError::InvalidHash)
+    );
+    assert_eq!(
+        srs_reverse(&config, "example.org", "SRS0=abcd@example.org", now),
+        Err(SrsError::InvalidSyntax)
+    );
+
+    // Addresses signed with a rotated key are still accepted
+    let old_config = SrsConfig {
+        secrets: vec!["old-secret".to_string()],
+        ..config.clone()
+    };
+    let unknown_config = SrsConfig {
+        secrets: vec!["unknown-secret".to_string()],
+        ..config.clone()
+    };
+    let old_srs0 = srs_forward(&old_config, "example.org", "bill@remote.net", now).unwrap();
+    assert_ne!(old_srs0, srs0.to_lowercase());
+    assert_eq!(
+        srs_reverse(&config, "example.org", &old_srs0, now),
+        Ok(Some("bill@remote.net".to_string()))
+    );
+    let unknown_srs0 = srs_forward(&unknown_config, "example.org", "bill@remote.net", now).unwrap();
+    assert_eq!(
+        srs_reverse(&config, "example.org", &unknown_srs0, now),
+        Err(SrsError::InvalidHash)
+    );
+
+    // SRS1 rewriting of addresses from other forwarders
+    let fwd_srs0 = srs_forward(&unknown_config, "fwd.org", "bill@remote.net", now).unwrap();
+    let srs1 = srs_forward(&config, "example.org", &fwd_srs0, now).unwrap();
+    assert!(srs1.starts_with("SRS1="), "{srs1}");
+    assert!(
+        srs1.ends_with(&format!(
+            "=fwd.org=={}",
+            &fwd_srs0[5..].replace("@fwd.org", "@example.org")
+        )),
+        "{srs1}"
+    );
+    assert_eq!(
+        srs_reverse(&config, "example.org", &srs1, now),
+        Ok(Some(fwd_srs0.clone()))
+    );
+    let other_srs1 = srs_forward(&unknown_config, "other.org", &fwd_srs0, now).unwrap();
+    let srs1_again = srs_forward(&config, "example.org", &other_srs1, now).unwrap();
+    assert_eq!(srs1_again, srs1);
+
+    // External senders relayed to remote domains are rewritten
+    let mut session = test.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.net").await;
+    session
+        .send_message(
+            "bill@remote.net",
+            &["jane@foobar.net", "john@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    let message = test.queue_receiver.expect_message().await;
+    assert!(message.return_path.starts_with("SRS0="), "{message:?}");
+    assert_eq!(message.return_path_domain, "example.org");
+    assert_eq!(
+        test.server.srs_reverse(&message.return_path),
+        Ok(Some("bill@remote.net".to_string()))
+    );
+    let srs0 = message.return_path;
+
+    // Local deliveries and local senders are not rewritten
+    session
+        .send_message(
+            "bill@remote.net",
+            &["john@example.org"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    assert_eq!(
+        test.queue_receiver.expect_message().await.return_path,
+        "bill@remote.net"
+    );
+    session
+        .send_message(
+            "john@example.org",
+            &["jane@foobar.net"],
+            "test:no_dkim",
+            "250",
+        )
+        .await;
+    assert_eq!(
+        test.queue_receiver.expect_message().await.return_path,
+        "john@example.org"
+    );
+
+    // Bounces to SRS addresses are relayed to the original sender
+    session.data.remote_ip_str = "10.0.0.2".to_string();
+    session.eval_session_params().await;
+    session.rset().await;
+    session.mail_from("<>", "250").await;
+    session.rcpt_to("jane@foobar.net", "550 5.1.2").await;
+    session.rcpt_to(&tampered, "550 5.1.1").await;
+    session.rcpt_to(&old_srs0, "250").await;
+    session.rset().await;
+
+    // Only null senders can relay through SRS addresses
+    session.mail_from("jane@foobar.net", "250").await;
+    session.rcpt_to(&srs0, "550 5.1.2").await;
+    session.rset().await;
+    session
+        .send_message("<>", &[&srs0], "test:no_dkim", "250")
+        .await;
+    let message = test.queue_receiver.expect_message().await;
+    assert_eq!(message.return_path, "");
+    assert_eq!(message.recipients.len(), 1);
+    assert_eq!(message.recipients[0].address_lcase, "bill@remote.net");
+    session.rset().await;
+    session
+        .send_message("<>", &[&srs1], "test:no_dkim", "250")
+        .await;
+    assert_eq!(
+        test.queue_receiver.expect_message().await.recipients[0].address_lcase,
+        fwd_srs0.to_lowercase()
+    );
+    test.queue_receiver.assert_no_events();
+}
@@ -89,6 +89,7 @@ async fn lookup_ip() {
         .resolve_host(
             &NextHop::MX("mx.foobar.org"),
             &RecipientDomain::new("envelope"),
+            None,
             2,
             0,
         )
@@ -124,6 +125,7 @@ async fn lookup_ip() {
         .resolve_host(
             &NextHop::MX("mx.foobar.org"),
             &RecipientDomain::new("envelope"),
+            None,
             2,
             0,
         )
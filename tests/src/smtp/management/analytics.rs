@@ -0,0 +1,343 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::server::ServerProtocol;
+use mail_auth::report::{
+    tlsrpt::TlsReport, ActionDisposition, DKIMAuthResult, DkimResult, DmarcResult, Record, Report,
+    SPFAuthResult, SpfResult,
+};
+use reqwest::Method;
+use serde_json::Value;
+use smtp::reporting::analysis::IncomingReport;
+use store::{
+    write::{now, BatchBuilder, Bincode, ReportClass, ValueClass},
+    Serialize,
+};
+
+use crate::{jmap::ManagementApi, smtp::TestSMTP};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+"#;
+
+const TLS_REPORT: &str = r#"{
+  "organization-name": "Company-X",
+  "date-range": {
+    "start-datetime": "2024-01-01T00:00:00Z",
+    "end-datetime": "2024-01-01T23:59:59Z"
+  },
+  "contact-info": "sts-reporting@company-x.example",
+  "report-id": "5065427c-23d3-47ca-b6e0-946ea0e8c4be",
+  "policies": [{
+    "policy": {
+      "policy-type": "sts",
+      "policy-string": ["version: STSv1", "mode: testing", "mx: *.example.org", "max_age: 86400"],
+      "policy-domain": "example.org",
+      "mx-host": ["*.example.org"]
+    },
+    "summary": {
+      "total-successful-session-count": 90,
+      "total-failure-session-count": 10
+    },
+    "failure-details": [{
+      "result-type": "certificate-expired",
+      "sending-mta-ip": "2001:db8:abcd:0012::1",
+      "receiving-mx-hostname": "mx1.example.org",
+      "failed-session-count": 4
+    }, {
+      "result-type": "starttls-not-supported",
+      "sending-mta-ip": "2001:db8:abcd:0013::1",
+      "receiving-mx-hostname": "mx2.example.org",
+      "failed-session-count": 6
+    }]
+  }, {
+    "policy": {
+      "policy-type": "tlsa",
+      "policy-domain": "other.org"
+    },
+    "summary": {
+      "total-successful-session-count": 50,
+      "total-failure-session-count": 0
+    }
+  }]
+}"#;
+
+const JAN_1: u64 = 1704067200;
+const JAN_2: u64 = JAN_1 + 86400;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_report_analytics() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut local = TestSMTP::new("smtp_manage_analytics", CONFIG).await;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // Store incoming reports
+    let legit = "192.0.2.1".parse().unwrap();
+    let spoofer = "203.0.113.5".parse().unwrap();
+    let reports = [
+        Report::new()
+            .with_org_name("google.com")
+            .with_domain("example.org")
+            .with_date_range_begin(JAN_1)
+            .with_date_range_end(JAN_1 + 86399)
+            .with_record(
+                Record::new()
+                    .with_source_ip(legit)
+                    .with_count(10)
+                    .with_header_from("example.org")
+                    .with_action_disposition(ActionDisposition::Pass)
+                    .with_dmarc_dkim_result(DmarcResult::Pass)
+                    .with_dmarc_spf_result(DmarcResult::Pass)
+                    .with_dkim_auth_result(
+                        DKIMAuthResult::new()
+                            .with_domain("example.org")
+                            .with_selector("sel1")
+                            .with_result(DkimResult::Pass),
+                    )
+                    .with_spf_auth_result(
+                        SPFAuthResult::new()
+                            .with_domain("example.org")
+                            .with_result(SpfResult::Pass),
+                    ),
+            )
+            .with_record(
+                Record::new()
+                    .with_source_ip(spoofer)
+                    .with_count(4)
+                    .with_header_from("example.org")
+                    .with_action_disposition(ActionDisposition::Reject)
+                    .with_dmarc_dkim_result(DmarcResult::Fail)
+                    .with_dmarc_spf_result(DmarcResult::Fail)
+                    .with_dkim_auth_result(
+                        DKIMAuthResult::new()
+                            .with_domain("spoofer.net")
+                            .with_selector("x")
+                            .with_result(DkimResult::Fail),
+                    )
+                    .with_spf_auth_result(
+                        SPFAuthResult::new()
+                            .with_domain("spoofer.net")
+                            .with_result(SpfResult::Fail),
+                    ),
+            ),
+        Report::new()
+            .with_org_name("yahoo.com")
+            .with_domain("example.org")
+            .with_date_range_begin(JAN_2)
+            .with_date_range_end(JAN_2 + 86399)
+            .with_record(
+                Record::new()
+                    .with_source_ip(legit)
+                    .with_count(6)
+                    .with_header_from("example.org")
+                    .with_action_disposition(ActionDisposition::Pass)
+                    .with_dmarc_dkim_result(DmarcResult::Pass)
+                    .with_dmarc_spf_result(DmarcResult::Fail)
+                    .with_dkim_auth_result(
+                        DKIMAuthResult::new()
+                            .with_domain("example.org")
+                            .with_selector("sel1")
+                            .with_result(DkimResult::Pass),
+                    )
+                    .with_spf_auth_result(
+                        SPFAuthResult::new()
+                            .with_domain("bounces.example.net")
+                            .with_result(SpfResult::Pass),
+                    ),
+            ),
+        Report::new()
+            .with_org_name("google.com")
+            .with_domain("other.org")
+            .with_date_range_begin(JAN_2)
+            .with_date_range_end(JAN_2 + 86399)
+            .with_record(
+                Record::new()
+                    .with_source_ip("198.51.100.1".parse().unwrap())
+                    .with_count(100)
+                    .with_header_from("other.org")
+                    .with_dmarc_dkim_result(DmarcResult::Fail)
+                    .with_dmarc_spf_result(DmarcResult::Fail),
+            ),
+    ];
+    let expires = now() + 86400;
+    let mut batch = BatchBuilder::new();
+    for (id, report) in reports.into_iter().enumerate() {
+        batch.set(
+            ValueClass::Report(ReportClass::Dmarc {
+                id: id as u64 + 1,
+                expires,
+            }),
+            Bincode::new(IncomingReport {
+                from: "noreply-dmarc-support@google.com".to_string(),
+                to: vec!["dmarc@example.org".to_string()],
+                subject: "Report".to_string(),
+                report,
+            })
+            .serialize(),
+        );
+    }
+    batch.set(
+        ValueClass::Report(ReportClass::Tls { id: 1, expires }),
+        Bincode::new(IncomingReport {
+            from: "tlsrpt@company-x.example".to_string(),
+            to: vec!["tlsrpt@example.org".to_string()],
+            subject: "Report".to_string(),
+            report: TlsReport::parse_json(TLS_REPORT.as_bytes()).unwrap(),
+        })
+        .serialize(),
+    );
+    local
+        .server
+        .core
+        .storage
+        .data
+        .write(batch.build())
+        .await
+        .unwrap();
+
+    // Aggregate DMARC reports by day
+    let api = ManagementApi::default();
+    let dmarc = analytics(&api, "dmarc?domain=example.org").await;
+    assert_eq!(dmarc["reports"], 2);
+    let series = dmarc["series"].as_array().unwrap();
+    assert_eq!(series.len(), 2, "{series:?}");
+    assert!(series[0]["timestamp"]
+        .as_str()
+        .unwrap()
+        .starts_with("2024-01-01T00:00:00"));
+    assert_counters(&series[0], 14, 10, 4);
+    assert_eq!(series[0]["rejected"], 4);
+    assert_eq!(series[0]["passRate"], 10.0 / 14.0);
+    assert!(series[1]["timestamp"]
+        .as_str()
+        .unwrap()
+        .starts_with("2024-01-02T00:00:00"));
+    assert_counters(&series[1], 6, 6, 0);
+    assert_eq!(series[1]["dkimAligned"], 6);
+    assert_eq!(series[1]["spfAligned"], 0);
+
+    // Breakdown by source, organization, selector and SPF alignment
+    let sources = dmarc["sourceIps"].as_array().unwrap();
+    assert_eq!(sources.len(), 2, "{sources:?}");
+    assert_eq!(sources[0]["ip"], "192.0.2.1");
+    assert_counters(&sources[0], 16, 16, 0);
+    assert_eq!(sources[1]["ip"], "203.0.113.5");
+    assert_counters(&sources[1], 4, 0, 4);
+    assert_eq!(
+        breakdown(&dmarc["organizations"], "key"),
+        vec![("google.com".to_string(), 14), ("yahoo.com".to_string(), 6)]
+    );
+    assert_eq!(
+        breakdown(&dmarc["dkimSelectors"], "selector"),
+        vec![("sel1".to_string(), 16), ("x".to_string(), 4)]
+    );
+    assert_eq!(dmarc["dkimSelectors"][1]["domain"], "spoofer.net");
+    assert_eq!(dmarc["dkimSelectors"][1]["fail"], 4);
+    assert_eq!(
+        breakdown(&dmarc["spfAlignment"], "key"),
+        vec![
+            ("aligned".to_string(), 10),
+            ("unaligned".to_string(), 6),
+            ("fail".to_string(), 4)
+        ]
+    );
+
+    // Sources that never pass DMARC are flagged
+    let unknown = dmarc["unknownSenders"].as_array().unwrap();
+    assert_eq!(unknown.len(), 1, "{unknown:?}");
+    assert_eq!(unknown[0]["ip"], "203.0.113.5");
+    assert_eq!(unknown[0]["domains"], serde_json::json!(["example.org"]));
+    assert_eq!(
+        unknown[0]["organizations"],
+        serde_json::json!(["google.com"])
+    );
+
+    // Filter by date range and domain
+    let dmarc = analytics(&api, "dmarc?domain=example.org&after=2024-01-02T00:00:00Z").await;
+    assert_eq!(dmarc["reports"], 1);
+    assert_eq!(dmarc["unknownSenders"].as_array().unwrap().len(), 0);
+    let dmarc = analytics(&api, "dmarc?before=2024-01-02T00:00:00Z").await;
+    assert_eq!(dmarc["reports"], 1);
+    let dmarc = analytics(&api, "dmarc?interval=week").await;
+    assert_eq!(dmarc["reports"], 3);
+    assert_eq!(dmarc["series"].as_array().unwrap().len(), 1);
+    assert_eq!(dmarc["unknownSenders"][0]["ip"], "198.51.100.1");
+
+    // Summarize TLS failures by result type
+    let tls = analytics(&api, "tls?domain=example.org").await;
+    assert_eq!(tls["reports"], 1);
+    assert_eq!(tls["series"][0]["success"], 90);
+    assert_eq!(tls["series"][0]["failure"], 10);
+    assert_eq!(tls["series"][0]["successRate"], 0.9);
+    assert_eq!(tls["organizations"][0]["key"], "Company-X");
+    let failures = tls["failures"].as_array().unwrap();
+    assert_eq!(failures.len(), 2, "{failures:?}");
+    assert_eq!(failures[0]["resultType"], "starttls-not-supported");
+    assert_eq!(failures[0]["sessions"], 6);
+    assert_eq!(
+        failures[0]["receivingMx"],
+        serde_json::json!(["mx2.example.org"])
+    );
+    assert_eq!(failures[1]["resultType"], "certificate-expired");
+    assert_eq!(failures[1]["sessions"], 4);
+    let tls = analytics(&api, "tls").await;
+    assert_eq!(tls["domains"].as_array().unwrap().len(), 2);
+    assert_eq!(tls["series"][0]["success"], 140);
+
+    // Invalid intervals are rejected
+    api.request::<Value>(Method::GET, "/api/reports/analytics/dmarc?interval=year")
+        .await
+        .unwrap()
+        .expect_request_error("Invalid interval");
+}
+
+async fn analytics(api: &ManagementApi, query: &str) -> Value {
+    api.request::<Value>(Method::GET, &format!("/api/reports/analytics/{query}"))
+        .await
+        .unwrap()
+        .unwrap_data()
+}
+
+fn assert_counters(value: &Value, total: u64, pass: u64, fail: u64) {
+    assert_eq!(
+        (
+            value["total"].as_u64().unwrap(),
+            value["pass"].as_u64().unwrap(),
+            value["fail"].as_u64().unwrap()
+        ),
+        (total, pass, fail),
+        "{value:?}"
+    );
+}
+
+fn breakdown(value: &Value, key: &str) -> Vec<(String, u64)> {
+    value
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|item| {
+            (
+                item[key].as_str().unwrap().to_string(),
+                item["total"].as_u64().unwrap(),
+            )
+        })
+        .collect()
+}
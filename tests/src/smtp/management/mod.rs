@@ -4,5 +4,9 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod analytics;
+pub mod quarantine;
 pub mod queue;
+pub mod queues;
 pub mod report;
+pub mod tracking;
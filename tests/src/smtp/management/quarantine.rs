@@ -0,0 +1,226 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use common::config::server::ServerProtocol;
+use reqwest::Method;
+use serde_json::Value;
+use smtp::queue::quarantine::SmtpQuarantine;
+
+use crate::{
+    jmap::{ManagementApi, Response},
+    smtp::{
+        inbound::TestMessage,
+        management::queue::List,
+        session::{TestSession, VerifyResponse},
+        TestSMTP,
+    },
+};
+
+const CONFIG: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[[directory."local".principals]]
+name = "john"
+description = "John Doe"
+secret = "secret"
+email = ["john@foobar.org"]
+
+[spam-filter]
+enable = false
+
+[session.rcpt]
+relay = true
+
+[session.data]
+script = "'quarantine'"
+
+[session.data.quarantine]
+expire = "7d"
+
+[sieve.trusted.scripts.quarantine]
+contents = '''
+require ["vnd.stalwart.expressions"];
+
+if header :contains "Subject" "invoice" {
+    eval "quarantine('Suspicious subject')";
+}
+'''
+"#;
+
+const MESSAGE: &str = concat!(
+    "From: Bill <bill@remote.org>\r\n",
+    "To: john@foobar.org, jane@foobar.org\r\n",
+    "Subject: Your invoice is overdue\r\n",
+    "\r\n",
+    "Please pay the attached invoice.\r\n"
+);
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_quarantine() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start local management interface
+    let mut local = TestSMTP::new("smtp_manage_quarantine", CONFIG).await;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // Messages matching the Sieve rule are quarantined
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.remote.org").await;
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org", "jane@foobar.org"],
+            MESSAGE,
+            "250",
+        )
+        .await;
+    local.queue_receiver.assert_no_events();
+    session
+        .send_message(
+            "bill@remote.org",
+            &["john@foobar.org"],
+            &MESSAGE.replace("invoice", "report"),
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("Subject: Your report is overdue");
+    local.queue_receiver.clear_queue(&local.server).await;
+
+    // Administrators can list all quarantined messages
+    let admin = ManagementApi::default();
+    let items = list_quarantine(&admin, "/api/quarantine").await;
+    assert_eq!(items.len(), 1, "{items:?}");
+    let id = items[0]["id"].as_str().unwrap().to_string();
+    assert_eq!(items[0]["source"], "sieve");
+    assert_eq!(items[0]["reason"], "Suspicious subject");
+    assert_eq!(items[0]["from"], "bill@remote.org");
+    assert_eq!(
+        items[0]["recipients"],
+        serde_json::json!(["jane@foobar.org", "john@foobar.org"])
+    );
+    assert!(list_quarantine(&admin, "/api/quarantine?text=nomatch")
+        .await
+        .is_empty());
+    let message = admin
+        .request::<Value>(Method::GET, &format!("/api/quarantine/{id}"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert!(
+        message["preview"]
+            .as_str()
+            .unwrap()
+            .contains("Please pay the attached invoice."),
+        "{message:?}"
+    );
+    assert!(message["headers"]
+        .as_str()
+        .unwrap()
+        .contains("Subject: Your invoice is overdue"));
+
+    // Local recipients receive a digest once
+    local.server.send_quarantine_digest().await;
+    let digest = local.queue_receiver.expect_message().await;
+    assert_eq!(
+        digest
+            .recipients
+            .iter()
+            .map(|r| r.address.as_str())
+            .collect::<Vec<_>>(),
+        vec!["john@foobar.org"]
+    );
+    digest
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("Subject: Your invoice is overdue")
+        .assert_contains("Reason: Suspicious subject");
+    local.queue_receiver.clear_queue(&local.server).await;
+    local.server.send_quarantine_digest().await;
+    local.queue_receiver.assert_no_events();
+
+    // Users only see and release their own copy
+    let john = ManagementApi::new(9980, "john", "secret");
+    let items = list_quarantine(&john, "/api/account/quarantine").await;
+    assert_eq!(items.len(), 1, "{items:?}");
+    assert_eq!(
+        items[0]["recipients"],
+        serde_json::json!(["john@foobar.org"])
+    );
+    assert!(john
+        .request::<bool>(Method::PATCH, &format!("/api/account/quarantine/{id}"))
+        .await
+        .unwrap()
+        .unwrap_data());
+    let released = local.queue_receiver.expect_message().await;
+    assert_eq!(
+        released
+            .recipients
+            .iter()
+            .map(|r| r.address.as_str())
+            .collect::<Vec<_>>(),
+        vec!["john@foobar.org"]
+    );
+    released
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("Please pay the attached invoice.");
+    local.queue_receiver.clear_queue(&local.server).await;
+    assert!(list_quarantine(&john, "/api/account/quarantine")
+        .await
+        .is_empty());
+    let items = list_quarantine(&admin, "/api/quarantine").await;
+    assert_eq!(items.len(), 1, "{items:?}");
+    assert_eq!(
+        items[0]["recipients"],
+        serde_json::json!(["jane@foobar.org"])
+    );
+
+    // Users cannot use the administrative endpoints
+    assert!(!matches!(
+        john.request::<Value>(Method::GET, "/api/quarantine")
+            .await
+            .unwrap(),
+        Response::Data { .. }
+    ));
+
+    // Administrators can delete quarantined messages
+    assert!(admin
+        .request::<bool>(Method::DELETE, &format!("/api/quarantine/{id}"))
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(list_quarantine(&admin, "/api/quarantine").await.is_empty());
+    local.queue_receiver.assert_no_events();
+}
+
+async fn list_quarantine(api: &ManagementApi, query: &str) -> Vec<Value> {
+    api.request::<List<Value>>(Method::GET, query)
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items
+}
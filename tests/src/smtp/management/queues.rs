@@ -0,0 +1,203 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use jmap::api::management::queue::Message;
+use mail_auth::MX;
+use reqwest::Method;
+use serde_json::Value;
+use smtp::queue::manager::SpawnQueue;
+use store::write::now;
+
+use crate::{
+    jmap::{ManagementApi, Response},
+    smtp::{management::queue::List, session::TestSession, DnsCache, TestSMTP},
+};
+
+const LOCAL: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[session.rcpt]
+relay = true
+
+[queue]
+select = [{if = "sender_domain = 'news.org'", then = "'bulk'"},
+          {else = "'default'"}]
+
+[queue.schedule]
+expire = "5d"
+
+[queue.named.bulk]
+threads = 1
+schedule.expire = "1d"
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_named_queues() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start remote test server
+    let mut remote = TestSMTP::new("smtp_named_queues_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let remote_core = remote.build_smtp();
+
+    // Start local management interface and queue manager
+    let local = TestSMTP::new("smtp_named_queues_local", LOCAL).await;
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+    let _rx_manage = local.start(&[ServerProtocol::Http]).await;
+    let mut session = local.new_session();
+    local
+        .queue_receiver
+        .queue_rx
+        .spawn(local.server.inner.clone());
+
+    // List configured queues
+    let api = ManagementApi::default();
+    assert_eq!(
+        list_queues(&api).await,
+        vec![
+            ("default".to_string(), 25, true),
+            ("bulk".to_string(), 1, true)
+        ]
+    );
+
+    // Pause the bulk queue
+    assert!(api
+        .request::<bool>(Method::PATCH, "/api/queue/queues/bulk/stop")
+        .await
+        .unwrap()
+        .unwrap_data());
+    assert!(!matches!(
+        api.request::<bool>(Method::PATCH, "/api/queue/queues/unknown/stop")
+            .await
+            .unwrap(),
+        Response::Data { .. }
+    ));
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert_eq!(
+        list_queues(&api).await,
+        vec![
+            ("default".to_string(), 25, true),
+            ("bulk".to_string(), 1, false)
+        ]
+    );
+
+    // Only messages in the default queue are delivered
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("bill@news.org", &["jane@foobar.org"], "test:no_dkim", "250")
+        .await;
+    session
+        .send_message("john@test.org", &["jane@foobar.org"], "test:no_dkim", "250")
+        .await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        remote
+            .queue_receiver
+            .consume_message(&remote_core)
+            .await
+            .return_path,
+        "john@test.org"
+    );
+    remote.queue_receiver.assert_no_events();
+
+    // The held message uses the bulk queue schedule
+    let messages = api
+        .request::<List<Message>>(Method::GET, "/api/queue/messages?queue=bulk&values=1")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items;
+    assert_eq!(messages.len(), 1, "{messages:?}");
+    assert_eq!(messages[0].queue.as_deref(), Some("bulk"));
+    assert_eq!(messages[0].return_path, "bill@news.org");
+    let expires_in = messages[0].domains[0].expires.to_timestamp() as u64 - now();
+    assert!((86300..=86400).contains(&expires_in), "{expires_in}");
+    assert!(api
+        .request::<List<Message>>(Method::GET, "/api/queue/messages?queue=default&values=1")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items
+        .is_empty());
+
+    // Resuming the queue delivers the held message
+    assert!(!api
+        .request::<bool>(Method::PATCH, "/api/queue/queues/bulk/start")
+        .await
+        .unwrap()
+        .unwrap_data());
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(
+        remote
+            .queue_receiver
+            .consume_message(&remote_core)
+            .await
+            .return_path,
+        "bill@news.org"
+    );
+    assert_eq!(
+        list_queues(&api).await,
+        vec![
+            ("default".to_string(), 25, true),
+            ("bulk".to_string(), 1, true)
+        ]
+    );
+}
+
+async fn list_queues(api: &ManagementApi) -> Vec<(String, u64, bool)> {
+    api.request::<Vec<Value>>(Method::GET, "/api/queue/queues")
+        .await
+        .unwrap()
+        .unwrap_data()
+        .into_iter()
+        .map(|queue| {
+            (
+                queue["name"].as_str().unwrap().to_string(),
+                queue["threads"].as_u64().unwrap(),
+                queue["active"].as_bool().unwrap(),
+            )
+        })
+        .collect()
+}
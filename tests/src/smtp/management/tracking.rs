@@ -0,0 +1,238 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use reqwest::Method;
+use serde_json::Value;
+
+use crate::{
+    jmap::ManagementApi,
+    smtp::{
+        inbound::{TestMessage, TestQueueEvent},
+        management::queue::List,
+        session::{TestSession, VerifyResponse},
+        DnsCache, TestSMTP,
+    },
+};
+
+const LOCAL: &str = r#"
+[storage]
+directory = "local"
+
+[directory."local"]
+type = "memory"
+
+[[directory."local".principals]]
+name = "admin"
+type = "admin"
+description = "Superuser"
+secret = "secret"
+class = "admin"
+
+[session.rcpt]
+relay = true
+
+[session.extensions]
+dsn = true
+
+[queue.tracking]
+enable = true
+retention = "7d"
+"#;
+
+const REMOTE: &str = r#"
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.rcpt]
+relay = true
+
+[session.extensions]
+dsn = true
+chunking = false
+"#;
+
+const MESSAGE: &str = concat!(
+    "From: John <john@test.org>\r\n",
+    "To: ok@foobar.org, fail@foobar.org\r\n",
+    "Message-ID: <tracking-test@test.org>\r\n",
+    "Subject: Tracking test\r\n",
+    "\r\n",
+    "Where did this message go?\r\n"
+);
+
+#[tokio::test]
+#[serial_test::serial]
+async fn manage_tracking() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test servers
+    let mut remote = TestSMTP::new("smtp_manage_tracking_remote", REMOTE).await;
+    let _rx_remote = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_manage_tracking_local", LOCAL).await;
+    let _rx = local.start(&[ServerProtocol::Http]).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    // Deliver a message to an accepted and a rejected recipient
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message(
+            "john@test.org",
+            &[
+                "<ok@foobar.org> NOTIFY=SUCCESS,FAILURE",
+                "<fail@foobar.org> NOTIFY=SUCCESS,FAILURE",
+            ],
+            MESSAGE,
+            "250",
+        )
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&local.queue_receiver)
+        .await
+        .assert_contains("<ok@foobar.org> (delivered to")
+        .assert_contains("<fail@foobar.org> (host ");
+    local.queue_receiver.read_event().await.assert_done();
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("Subject: Tracking test");
+
+    // The history is kept after the message left the queue
+    local.queue_receiver.clear_queue(&local.server).await;
+    let admin = ManagementApi::default();
+    let items = list_tracking(
+        &admin,
+        "/api/queue/tracking?message-id=tracking-test@test.org",
+    )
+    .await;
+    assert_eq!(items.len(), 1, "{items:?}");
+    let message = &items[0];
+    assert_eq!(message["messageId"], "tracking-test@test.org");
+    assert_eq!(message["returnPath"], "john@test.org");
+
+    let ok = recipient_events(message, "ok@foobar.org");
+    assert_eq!(
+        event_types(&ok),
+        vec!["queued", "delivered", "dsnSuccess"],
+        "{ok:?}"
+    );
+    assert_eq!(ok[1]["mx"], "mx.foobar.org");
+    assert_eq!(ok[1]["remoteIp"], "127.0.0.1");
+    assert!(
+        ok[1]["tls"].as_str().unwrap().starts_with("TLSv1_3"),
+        "{ok:?}"
+    );
+    assert!(
+        ok[1]["response"].as_str().unwrap().starts_with("250"),
+        "{ok:?}"
+    );
+
+    let fail = recipient_events(message, "fail@foobar.org");
+    assert_eq!(
+        event_types(&fail),
+        vec!["queued", "failed", "dsnFailure"],
+        "{fail:?}"
+    );
+    assert!(
+        fail[1]["response"].as_str().unwrap().starts_with("503"),
+        "{fail:?}"
+    );
+
+    // Look up by queue id, sender, recipient and date
+    let id = message["id"].as_u64().unwrap();
+    let by_id = admin
+        .request::<Value>(Method::GET, &format!("/api/queue/tracking/{id}"))
+        .await
+        .unwrap()
+        .unwrap_data();
+    assert_eq!(&by_id, message);
+    for (query, expected) in [
+        ("from=john@test.org", 1),
+        ("from=jane@test.org", 0),
+        ("to=fail@foobar.org", 1),
+        ("to=nobody@foobar.org", 0),
+        ("message-id=%3Ctracking-test@test.org%3E", 1),
+        ("message-id=unknown@test.org", 0),
+        ("before=2000-01-01T00:00:00Z", 0),
+        ("from=john@test.org&after=2000-01-01T00:00:00Z", 1),
+    ] {
+        assert_eq!(
+            list_tracking(&admin, &format!("/api/queue/tracking?{query}"))
+                .await
+                .len(),
+            expected,
+            "failed for {query}"
+        );
+    }
+
+    // The DSN is tracked as well
+    assert_eq!(
+        list_tracking(&admin, "/api/queue/tracking?to=john@test.org")
+            .await
+            .len(),
+        1
+    );
+}
+
+async fn list_tracking(api: &ManagementApi, query: &str) -> Vec<Value> {
+    api.request::<List<Value>>(Method::GET, query)
+        .await
+        .unwrap()
+        .unwrap_data()
+        .items
+}
+
+fn recipient_events(message: &Value, address: &str) -> Vec<Value> {
+    message["recipients"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|rcpt| rcpt["address"] == address)
+        .unwrap_or_else(|| panic!("Recipient {address} not found in {message:?}"))["events"]
+        .as_array()
+        .unwrap()
+        .clone()
+}
+
+fn event_types(events: &[Value]) -> Vec<&str> {
+    events
+        .iter()
+        .map(|event| event["type"].as_str().unwrap())
+        .collect()
+}
@@ -0,0 +1,144 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::Duration;
+
+use common::Server;
+use smtp::queue::{throttle::AdaptiveThrottling, Error, ErrorDetails, HostResponse, Status};
+use smtp_proto::Response;
+use store::write::now;
+
+use crate::smtp::TestSMTP;
+
+const CONFIG: &str = r#"
+[queue.adaptive]
+enable = true
+scope = "domain"
+status-codes = ["4.7.28"]
+recovery = "1s"
+
+[queue.adaptive.match]
+slow-down = "(?i)please slow down"
+
+[queue.adaptive.concurrency]
+min = 1
+max = 4
+
+[queue.adaptive.rate]
+min = 1
+max = 8
+
+[queue.adaptive.backoff]
+min = "1s"
+max = "4s"
+"#;
+
+#[tokio::test]
+async fn adaptive_throttle() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_adaptive_throttle", CONFIG).await;
+    let core = local.build_smtp();
+
+    // Destinations start without limits
+    assert_eq!(
+        core.throttle_key("example.org", "mx.example.org"),
+        Some("example.org".to_string())
+    );
+    assert_eq!(core.throttle_check("example.org"), Ok(0));
+
+    // Responses that are not rate limit errors are ignored
+    for status in [
+        temp_fail(452, [4, 2, 2], "Mailbox full"),
+        temp_fail(550, [5, 7, 28], "Rate limit exceeded"),
+        Status::Completed(()),
+    ] {
+        core.throttle_feedback("example.org", &status, std::iter::empty(), 0);
+    }
+    assert_eq!(throttle_state(&core, "example.org"), None);
+
+    // Rate limit errors cut the limits and start the backoff
+    core.throttle_feedback(
+        "example.org",
+        &temp_fail(451, [4, 7, 28], "Our system has detected an unusual rate"),
+        std::iter::empty(),
+        0,
+    );
+    assert_eq!(throttle_state(&core, "example.org"), Some((2, 4, 1, 1)));
+    assert!(matches!(core.throttle_check("example.org"), Err(retry_at) if retry_at > now()));
+
+    // In-flight deliveries do not reduce the limits twice
+    core.throttle_feedback(
+        "example.org",
+        &temp_fail(421, [4, 7, 0], "Please slow down"),
+        std::iter::empty(),
+        0,
+    );
+    assert_eq!(throttle_state(&core, "example.org"), Some((2, 4, 1, 2)));
+
+    // Custom signatures are matched against the response text
+    core.throttle_feedback(
+        "example.net",
+        &temp_fail(421, [4, 7, 0], "Please slow down and try again later"),
+        std::iter::empty(),
+        0,
+    );
+    assert_eq!(throttle_state(&core, "example.net"), Some((2, 4, 1, 1)));
+
+    // Backoff grows exponentially after the previous one expires
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    assert_eq!(core.throttle_check("example.org"), Ok(2));
+    core.throttle_feedback(
+        "example.org",
+        &temp_fail(451, [4, 7, 28], "Rate limited"),
+        std::iter::empty(),
+        0,
+    );
+    assert_eq!(throttle_state(&core, "example.org"), Some((1, 2, 2, 3)));
+
+    // Messages per minute are limited
+    tokio::time::sleep(Duration::from_millis(2100)).await;
+    assert_eq!(core.throttle_check("example.org"), Ok(1));
+    assert!(core.throttle_check("example.org").is_err());
+
+    // Limits are relaxed gradually after successful deliveries
+    core.throttle_feedback("example.org", &Status::Completed(()), std::iter::empty(), 0);
+    assert_eq!(throttle_state(&core, "example.org"), Some((2, 4, 1, 3)));
+    core.throttle_feedback("example.org", &Status::Completed(()), std::iter::empty(), 0);
+    assert_eq!(throttle_state(&core, "example.org"), Some((2, 4, 1, 3)));
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    core.throttle_feedback("example.org", &Status::Completed(()), std::iter::empty(), 0);
+    assert_eq!(throttle_state(&core, "example.org"), Some((3, 8, 0, 3)));
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    core.throttle_feedback("example.org", &Status::Completed(()), std::iter::empty(), 0);
+    assert_eq!(throttle_state(&core, "example.org"), None);
+    assert_eq!(core.throttle_check("example.org"), Ok(0));
+}
+
+fn temp_fail(code: u16, esc: [u8; 3], message: &str) -> Status<(), Error> {
+    Status::TemporaryFailure(Error::UnexpectedResponse(HostResponse {
+        hostname: ErrorDetails {
+            entity: "mx.example.org".to_string(),
+            details: "RCPT TO:<bill@example.org>".to_string(),
+        },
+        response: Response {
+            code,
+            esc,
+            message: message.to_string(),
+        },
+    }))
+}
+
+fn throttle_state(core: &Server, key: &str) -> Option<(usize, u64, u64, u64)> {
+    core.inner
+        .data
+        .smtp_connections
+        .throttled
+        .lock()
+        .get(key)
+        .map(|state| (state.concurrency, state.rate, state.backoff, state.hits))
+}
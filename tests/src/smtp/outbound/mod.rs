@@ -4,12 +4,15 @@
  * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
  */
 
+pub mod adaptive;
 pub mod dane;
 pub mod extensions;
 pub mod fallback_relay;
 pub mod ip_lookup;
 pub mod lmtp;
 pub mod mta_sts;
+pub mod proxy;
+pub mod reuse;
 pub mod smtp;
 pub mod throttle;
 pub mod tls;
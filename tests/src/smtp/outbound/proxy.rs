@@ -0,0 +1,229 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::{
+    net::SocketAddr,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use common::config::server::ServerProtocol;
+use mail_auth::MX;
+use store::parking_lot::Mutex;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    DnsCache, TestSMTP,
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.outbound]
+proxy = [{if = "rcpt_domain == 'foobar.org'", then = "'socks'"},
+         {if = "rcpt_domain == 'foobar.net'", then = "'http'"},
+         {else = false}]
+
+[proxy.socks]
+protocol = "socks5"
+address = "127.0.0.1"
+port = 9935
+auth.username = "proxy-user"
+auth.secret = "proxy-pass"
+
+[proxy.http]
+protocol = "http"
+address = "127.0.0.1"
+port = 9936
+auth.username = "proxy-user"
+auth.secret = "proxy-pass"
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+
+[session.extensions]
+dsn = true
+chunking = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn proxy_delivery() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start mock proxies
+    let socks_log = Arc::new(Mutex::new(Vec::new()));
+    let http_log = Arc::new(Mutex::new(Vec::new()));
+    spawn_proxy("127.0.0.1:9935", socks_log.clone(), false).await;
+    spawn_proxy("127.0.0.1:9936", http_log.clone(), true).await;
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_proxy_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+    let mut local = TestSMTP::new("smtp_proxy_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    for domain in ["foobar.org", "foobar.net"] {
+        core.mx_add(
+            domain,
+            vec![MX {
+                exchanges: vec![format!("mx.{domain}")],
+                preference: 10,
+            }],
+            Instant::now() + Duration::from_secs(10),
+        );
+        core.ipv4_add(
+            &format!("mx.{domain}"),
+            vec!["127.0.0.1".parse().unwrap()],
+            Instant::now() + Duration::from_secs(10),
+        );
+    }
+
+    // Deliver through a SOCKS5 proxy, STARTTLS happens end-to-end
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("using TLSv1.3 with cipher");
+    assert_eq!(
+        socks_log.lock().as_slice(),
+        ["proxy-user:proxy-pass 127.0.0.1:9925"]
+    );
+
+    // Deliver through an HTTP CONNECT proxy
+    session
+        .send_message("john@test.org", &["jane@foobar.net"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    local.queue_receiver.read_event().await.assert_done();
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("using TLSv1.3 with cipher");
+    assert_eq!(
+        http_log.lock().as_slice(),
+        ["Basic cHJveHktdXNlcjpwcm94eS1wYXNz 127.0.0.1:9925"]
+    );
+}
+
+async fn spawn_proxy(addr: &str, log: Arc<Mutex<Vec<String>>>, is_http: bool) {
+    let listener = TcpListener::bind(addr).await.unwrap();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let log = log.clone();
+            tokio::spawn(async move {
+                let (auth, dest) = if is_http {
+                    http_handshake(&mut stream).await
+                } else {
+                    socks5_handshake(&mut stream).await
+                };
+                log.lock().push(format!("{auth} {dest}"));
+                let mut remote = TcpStream::connect(dest).await.unwrap();
+                let _ = tokio::io::copy_bidirectional(&mut stream, &mut remote).await;
+            });
+        }
+    });
+}
+
+async fn socks5_handshake(stream: &mut TcpStream) -> (String, SocketAddr) {
+    let mut buf = [0u8; 2];
+    stream.read_exact(&mut buf).await.unwrap();
+    let mut methods = vec![0u8; buf[1] as usize];
+    stream.read_exact(&mut methods).await.unwrap();
+    assert!(methods.contains(&0x02));
+    stream.write_all(&[0x05, 0x02]).await.unwrap();
+
+    // Username and password
+    stream.read_exact(&mut buf).await.unwrap();
+    let mut username = vec![0u8; buf[1] as usize];
+    stream.read_exact(&mut username).await.unwrap();
+    let mut secret = vec![0u8; stream.read_u8().await.unwrap() as usize];
+    stream.read_exact(&mut secret).await.unwrap();
+    stream.write_all(&[0x01, 0x00]).await.unwrap();
+
+    // Connect request
+    let mut request = [0u8; 10];
+    stream.read_exact(&mut request).await.unwrap();
+    assert_eq!(&request[..4], &[0x05, 0x01, 0x00, 0x01]);
+    let dest = SocketAddr::from((
+        [request[4], request[5], request[6], request[7]],
+        u16::from_be_bytes([request[8], request[9]]),
+    ));
+    stream
+        .write_all(&[0x05, 0x00, 0x00, 0x01, 0, 0, 0, 0, 0, 0])
+        .await
+        .unwrap();
+
+    (
+        format!(
+            "{}:{}",
+            String::from_utf8(username).unwrap(),
+            String::from_utf8(secret).unwrap()
+        ),
+        dest,
+    )
+}
+
+async fn http_handshake(stream: &mut TcpStream) -> (String, SocketAddr) {
+    let mut request = Vec::new();
+    while !request.ends_with(b"\r\n\r\n") {
+        request.push(stream.read_u8().await.unwrap());
+    }
+    let request = String::from_utf8(request).unwrap();
+    let dest = request
+        .strip_prefix("CONNECT ")
+        .and_then(|r| r.split_once(' '))
+        .unwrap()
+        .0
+        .parse()
+        .unwrap();
+    let auth = request
+        .lines()
+        .find_map(|line| line.strip_prefix("Proxy-Authorization: "))
+        .unwrap()
+        .to_string();
+    stream
+        .write_all(b"HTTP/1.1 200 Connection established\r\n\r\n")
+        .await
+        .unwrap();
+
+    (auth, dest)
+}
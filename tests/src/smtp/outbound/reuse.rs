@@ -0,0 +1,171 @@
+/*
+ * SPDX-FileCopyrightText: 2020 Stalwart Labs Ltd <hello@stalw.art>
+ *
+ * SPDX-License-Identifier: AGPL-3.0-only OR LicenseRef-SEL
+ */
+
+use std::time::{Duration, Instant};
+
+use common::{config::server::ServerProtocol, Server};
+use mail_auth::MX;
+use smtp::{
+    outbound::pool::SmtpConnectionPool,
+    queue::{Error, Status},
+};
+use store::write::now;
+
+use crate::smtp::{
+    inbound::{TestMessage, TestQueueEvent},
+    session::{TestSession, VerifyResponse},
+    DnsCache, TestSMTP,
+};
+
+const LOCAL: &str = r#"
+[session.rcpt]
+relay = true
+
+[queue.outbound.connection]
+reuse = true
+idle-timeout = "1m"
+max-messages = 2
+max-per-host = 1
+"#;
+
+const REMOTE: &str = r#"
+[session.rcpt]
+relay = true
+
+[session.ehlo]
+reject-non-fqdn = false
+"#;
+
+#[tokio::test]
+#[serial_test::serial]
+async fn connection_reuse() {
+    // Enable logging
+    crate::enable_logging();
+
+    // Start test server
+    let mut remote = TestSMTP::new("smtp_reuse_remote", REMOTE).await;
+    let _rx = remote.start(&[ServerProtocol::Smtp]).await;
+
+    let mut local = TestSMTP::new("smtp_reuse_local", LOCAL).await;
+
+    // Add mock DNS entries
+    let core = local.build_smtp();
+    core.mx_add(
+        "foobar.org",
+        vec![MX {
+            exchanges: vec!["mx.foobar.org".to_string()],
+            preference: 10,
+        }],
+        Instant::now() + Duration::from_secs(10),
+    );
+    core.ipv4_add(
+        "mx.foobar.org",
+        vec!["127.0.0.1".parse().unwrap()],
+        Instant::now() + Duration::from_secs(10),
+    );
+
+    let mut session = local.new_session();
+    session.data.remote_ip_str = "10.0.0.1".to_string();
+    session.eval_session_params().await;
+    session.ehlo("mx.test.org").await;
+
+    // The first delivery leaves the connection open
+    for (num, expected_idle) in [(1, vec![1]), (2, vec![])] {
+        session
+            .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+            .await;
+        local
+            .queue_receiver
+            .expect_message_then_deliver()
+            .await
+            .try_deliver(core.clone());
+        remote
+            .queue_receiver
+            .expect_message()
+            .await
+            .read_lines(&remote.queue_receiver)
+            .await
+            .assert_contains("From: ");
+        local.queue_receiver.read_event().await.assert_done();
+        assert_eq!(idle_connections(&core), expected_idle, "message {num}");
+    }
+
+    // New connections are not opened once the per-host limit is reached
+    let slot = core.acquire_smtp_slot("mx.foobar.org", 1).unwrap();
+    assert!(core.acquire_smtp_slot("mx.foobar.org", 1).is_none());
+    session
+        .send_message("john@test.org", &["bill@foobar.org"], "test:no_dkim", "250")
+        .await;
+    local
+        .queue_receiver
+        .expect_message_then_deliver()
+        .await
+        .try_deliver(core.clone());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    local.queue_receiver.read_event().await.assert_refresh();
+    remote.queue_receiver.assert_no_events();
+    let mut retry = local.queue_receiver.last_queued_message().await;
+    assert!(retry.domains[0].retry.due > now());
+    assert_eq!(
+        retry.domains[0].status,
+        Status::TemporaryFailure(Error::ConcurrencyLimited)
+    );
+
+    // Delivery resumes once the slot is released
+    drop(slot);
+    let prev_due = retry.domains[0].retry.due;
+    let next_due = now();
+    let queue_id = retry.queue_id;
+    retry.domains[0].retry.due = next_due;
+    retry
+        .save_changes(&core, prev_due.into(), next_due.into())
+        .await;
+    local
+        .queue_receiver
+        .delivery_attempt(queue_id)
+        .await
+        .try_deliver(core.clone());
+    remote
+        .queue_receiver
+        .expect_message()
+        .await
+        .read_lines(&remote.queue_receiver)
+        .await
+        .assert_contains("From: ");
+    local.queue_receiver.read_event().await.assert_done();
+    assert_eq!(idle_connections(&core), vec![1]);
+
+    // Expired connections are closed and their host counters removed
+    for connection in core
+        .inner
+        .data
+        .smtp_connections
+        .idle
+        .lock()
+        .values_mut()
+        .flatten()
+    {
+        connection.expires = Instant::now();
+    }
+    core.evict_smtp_connections();
+    assert_eq!(idle_connections(&core), Vec::<usize>::new());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    core.evict_smtp_connections();
+    assert!(core.inner.data.smtp_connections.hosts.lock().is_empty());
+}
+
+fn idle_connections(server: &Server) -> Vec<usize> {
+    server
+        .inner
+        .data
+        .smtp_connections
+        .idle
+        .lock()
+        .values()
+        .flatten()
+        .map(|c| c.messages)
+        .collect()
+}
@@ -137,7 +137,7 @@ async fn smtp_delivery() {
     loop {
         match local.queue_receiver.try_read_event().await {
             Some(QueueEvent::Refresh | QueueEvent::WorkerDone { .. }) => {}
-            Some(QueueEvent::Paused(_)) => unreachable!(),
+            Some(QueueEvent::Paused(_) | QueueEvent::PausedQueue { .. }) => unreachable!(),
             None | Some(QueueEvent::Stop) => break,
         }
 
@@ -89,6 +89,7 @@ async fn generate_dsn() {
         priority: 0,
         blob_hash: BlobHash::from(dsn_original.as_bytes()),
         quota_keys: vec![],
+        queue: None,
     };
 
     // Load config
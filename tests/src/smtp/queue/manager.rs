@@ -8,8 +8,12 @@ use std::time::Duration;
 
 use mail_auth::hickory_resolver::proto::op::ResponseCode;
 
-use smtp::queue::{spool::SmtpSpool, Domain, Message, Schedule, Status};
-use store::write::now;
+use smtp::queue::{spool::SmtpSpool, Domain, Message, QuotaKey, Recipient, Schedule, Status};
+use store::{
+    write::{now, BatchBuilder, Bincode, QueueClass, QueueEvent, ValueClass},
+    Serialize, ValueKey,
+};
+use utils::BlobHash;
 
 use crate::smtp::TestSMTP;
 
@@ -65,6 +69,91 @@ async fn queue_due() {
     qr.assert_queue_is_empty().await;
 }
 
+#[tokio::test]
+async fn queue_legacy_record() {
+    // Enable logging
+    crate::enable_logging();
+
+    let local = TestSMTP::new("smtp_queue_legacy_test", CONFIG).await;
+    let core = local.build_smtp();
+    let qr = &local.queue_receiver;
+
+    // Write a message using the record layout that predates named queues
+    let mut message = new_message(0);
+    message.domains.push(domain("a", 1, 2, 3));
+    let due = message.next_event().unwrap();
+    let mut batch = BatchBuilder::new();
+    batch
+        .set(
+            ValueClass::Queue(QueueClass::MessageEvent(QueueEvent { due, queue_id: 0 })),
+            0u64.serialize(),
+        )
+        .set(
+            ValueClass::Queue(QueueClass::Message(0)),
+            Bincode::new(LegacyMessage::from(&message)).serialize(),
+        );
+    core.store().write(batch.build()).await.unwrap();
+    let mut stored = core.read_message(0).await.unwrap();
+    assert_eq!(stored, message);
+
+    // Named queues are kept in the event, the record layout is unchanged
+    stored.queue = Some("bulk".to_string());
+    stored.save_changes(&core, due.into(), due.into()).await;
+    let stored = core.read_message(0).await.unwrap();
+    assert_eq!(stored.queue.as_deref(), Some("bulk"));
+    assert_eq!(
+        core.store()
+            .get_value::<Bincode<LegacyMessage>>(ValueKey::from(ValueClass::Queue(
+                QueueClass::Message(0)
+            )))
+            .await
+            .unwrap()
+            .unwrap()
+            .inner,
+        LegacyMessage::from(&message)
+    );
+
+    stored.remove(&core, due).await;
+    qr.assert_queue_is_empty().await;
+}
+
+#[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+struct LegacyMessage {
+    queue_id: u64,
+    created: u64,
+    blob_hash: BlobHash,
+    return_path: String,
+    return_path_lcase: String,
+    return_path_domain: String,
+    recipients: Vec<Recipient>,
+    domains: Vec<Domain>,
+    flags: u64,
+    env_id: Option<String>,
+    priority: i16,
+    size: usize,
+    quota_keys: Vec<QuotaKey>,
+}
+
+impl From<&Message> for LegacyMessage {
+    fn from(message: &Message) -> Self {
+        LegacyMessage {
+            queue_id: message.queue_id,
+            created: message.created,
+            blob_hash: message.blob_hash.clone(),
+            return_path: message.return_path.clone(),
+            return_path_lcase: message.return_path_lcase.clone(),
+            return_path_domain: message.return_path_domain.clone(),
+            recipients: message.recipients.clone(),
+            domains: message.domains.clone(),
+            flags: message.flags,
+            env_id: message.env_id.clone(),
+            priority: message.priority,
+            size: message.size,
+            quota_keys: message.quota_keys.clone(),
+        }
+    }
+}
+
 #[test]
 fn delivery_events() {
     let mut message = new_message(0);
@@ -141,6 +230,7 @@ pub fn new_message(queue_id: u64) -> Message {
         env_id: None,
         priority: 0,
         quota_keys: vec![],
+        queue: None,
         blob_hash: Default::default(),
     }
 }
@@ -98,7 +98,10 @@ async fn queue_retry() {
                 }
             }
             Some(QueueEvent::Refresh) => (),
-            None | Some(QueueEvent::Stop) | Some(QueueEvent::Paused(_)) => break,
+            None
+            | Some(QueueEvent::Stop)
+            | Some(QueueEvent::Paused(_))
+            | Some(QueueEvent::PausedQueue { .. }) => break,
         }
 
         let now = now();